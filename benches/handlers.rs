@@ -0,0 +1,99 @@
+//! Cost of the XML handlers on attribute-heavy entries: every entry carries features
+//! with evidence and locations, dbReferences with properties and comments, the
+//! attribute/text traffic `metadata::consume_entry` has to decode per entry.
+//!
+//! Only the handlers are timed (no row building or writing), over an in-memory document.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use uniprot_etl::pipeline::handlers::metadata;
+use uniprot_etl::pipeline::scratch::EntryScratch;
+
+const ENTRIES: usize = 2_000;
+
+fn entry(i: usize) -> String {
+    let mut xml = format!(
+        r#"<entry dataset="Swiss-Prot" created="2001-01-01" modified="2024-01-01" version="7">
+<accession>P{i:05}</accession>
+<name>PROT{i}_HUMAN</name>
+<protein><recommendedName><fullName evidence="1">Kinase &amp; phosphatase {i}</fullName><ecNumber>2.7.11.1</ecNumber></recommendedName></protein>
+<gene><name type="primary">GENE{i}</name></gene>
+<organism><name type="scientific">Homo sapiens</name><dbReference type="NCBI Taxonomy" id="9606"/></organism>
+"#
+    );
+    for k in 0..8 {
+        xml.push_str(&format!(
+            r#"<dbReference type="PDB" id="{k}ABC"><property type="method" value="X-ray"/><property type="resolution" value="2.00 A"/><property type="chains" value="A=1-300"/></dbReference>
+"#
+        ));
+    }
+    xml.push_str(
+        r#"<comment type="function"><text evidence="1 2">Catalyzes the phosphorylation of serine &lt;and&gt; threonine residues.</text></comment>
+<comment type="subcellular location"><subcellularLocation><location evidence="3">Cytoplasm</location></subcellularLocation><subcellularLocation><location>Nucleus</location></subcellularLocation></comment>
+<keyword id="KW-0418">Kinase</keyword><keyword id="KW-0597">Phosphoprotein</keyword>
+"#,
+    );
+    for k in 0..24 {
+        let pos = 10 + k * 10;
+        xml.push_str(&format!(
+            r#"<feature type="modified residue" description="Phosphoserine" evidence="1 2 3"><location><position position="{pos}"/></location></feature>
+<feature type="domain" description="Protein kinase {k}" evidence="4"><location><begin position="{pos}"/><end position="{}"/></location></feature>
+"#,
+            pos + 5
+        ));
+    }
+    for k in 1..=4 {
+        xml.push_str(&format!(
+            r#"<evidence type="ECO:0000269" key="{k}"><source><dbReference type="PubMed" id="1234{k}"/></source></evidence>
+"#
+        ));
+    }
+    xml.push_str(&format!(
+        "<sequence length=\"300\" mass=\"33000\" checksum=\"ABCDEF\" modified=\"2001-01-01\" version=\"1\">{}</sequence>\n</entry>\n",
+        "MTAKSPLE".repeat(300 / 8 + 1)
+    ));
+    xml
+}
+
+fn document() -> String {
+    let mut xml = String::from("<uniprot>\n");
+    for i in 0..ENTRIES {
+        xml.push_str(&entry(i));
+    }
+    xml.push_str("</uniprot>\n");
+    xml
+}
+
+/// Runs every entry of `xml` through the handlers; returns the features seen.
+fn consume_all(xml: &[u8]) -> usize {
+    let mut reader = Reader::from_reader(xml);
+    reader.config_mut().trim_text(true);
+    let mut scratch = EntryScratch::new();
+    let mut buf = Vec::with_capacity(4096);
+    let mut features = 0;
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf).unwrap() {
+            Event::Start(e) if e.local_name().as_ref() == b"entry" => {
+                scratch.reset();
+                metadata::consume_entry(&mut reader, &mut scratch, &mut buf).unwrap();
+                features += scratch.take_entry().features.generic.len();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    features
+}
+
+fn benchmark_handlers(c: &mut Criterion) {
+    let xml = document();
+    let mut group = c.benchmark_group("handlers");
+    group.throughput(Throughput::Bytes(xml.len() as u64));
+    group.bench_function("consume_entry", |b| b.iter(|| consume_all(black_box(xml.as_bytes()))));
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_handlers);
+criterion_main!(benches);
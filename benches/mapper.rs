@@ -0,0 +1,101 @@
+//! Cost of mapping every PTM site of an edit-heavy isoform (titin-like: tens of VSPs,
+//! thousands of sites) from canonical to isoform coordinates.
+//!
+//! `linear_scan` reproduces the old `map_point_1based`, which walked every edit before
+//! the position; `coordinate_mapper` is the binary search over prefix shifts, and
+//! `map_points` the single sorted sweep the row builders use.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use uniprot_etl::pipeline::mapper::CoordinateMapper;
+use uniprot_etl::pipeline::scratch::{FeatureScratch, ParsedEntry};
+
+const SEQUENCE_LEN: i32 = 35_000;
+const SITES: i32 = 5_000;
+
+/// (begin, end, delta, is_deletion) of one edit, as the old mapper stored it.
+type Edit = (i32, i32, i32, bool);
+
+/// `count` evenly spread edits alternating between a 5-residue deletion and a
+/// 3-residue insertion at a single residue.
+fn edits(count: i32) -> Vec<Edit> {
+    let spacing = SEQUENCE_LEN / (count + 1);
+    (1..=count)
+        .map(|k| {
+            let begin = k * spacing;
+            if k % 2 == 0 {
+                (begin, begin + 4, -5, true)
+            } else {
+                (begin, begin, 2, false)
+            }
+        })
+        .collect()
+}
+
+fn mapper(edits: &[Edit]) -> CoordinateMapper {
+    let mut entry = ParsedEntry {
+        sequence: "A".repeat(SEQUENCE_LEN as usize),
+        ..Default::default()
+    };
+    let mut ids = Vec::new();
+    for (i, &(begin, end, _, is_deletion)) in edits.iter().enumerate() {
+        let id = format!("VSP_{i}");
+        entry.features.generic.push(FeatureScratch {
+            id: Some(id.clone()),
+            feature_type: "splice variant".to_string(),
+            start: Some(begin),
+            end: Some(end),
+            variation: Some(if is_deletion { "Missing" } else { "AAA" }.to_string()),
+            ..Default::default()
+        });
+        ids.push(id);
+    }
+    CoordinateMapper::from_entry_for_vsp_ids(&entry, &ids)
+}
+
+/// The old per-point walk over every edit starting before the position.
+fn linear_scan(edits: &[Edit], pos: i32) -> Option<i32> {
+    let mut shift = 0;
+    for &(begin, end, delta, is_deletion) in edits {
+        if pos < begin {
+            break;
+        }
+        if pos > end {
+            shift += delta;
+            continue;
+        }
+        if is_deletion || (delta != 0 && pos != begin) {
+            return None;
+        }
+        break;
+    }
+    Some(pos + shift).filter(|&p| p > 0)
+}
+
+fn benchmark_mapping(c: &mut Criterion) {
+    let sites: Vec<i32> = (0..SITES).map(|i| 1 + i * (SEQUENCE_LEN / SITES)).collect();
+    let mut group = c.benchmark_group("mapper");
+    group.throughput(Throughput::Elements(SITES as u64));
+
+    for count in [8, 64, 512] {
+        let edits = edits(count);
+        let mapper = mapper(&edits);
+        assert!(sites
+            .iter()
+            .all(|&pos| linear_scan(&edits, pos) == mapper.map_point_1based(pos).ok()));
+
+        group.bench_with_input(BenchmarkId::new("linear_scan", count), &edits, |b, edits| {
+            b.iter(|| sites.iter().filter_map(|&pos| linear_scan(edits, black_box(pos))).count())
+        });
+        group.bench_with_input(BenchmarkId::new("coordinate_mapper", count), &mapper, |b, mapper| {
+            b.iter(|| sites.iter().filter_map(|&pos| mapper.map_point_1based(black_box(pos)).ok()).count())
+        });
+        group.bench_with_input(BenchmarkId::new("map_points", count), &mapper, |b, mapper| {
+            b.iter(|| mapper.map_points(black_box(&sites)).into_iter().filter_map(Result::ok).count())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_mapping);
+criterion_main!(benches);
@@ -0,0 +1,123 @@
+//! Per-entry counter cost of `MetricsCollector` implementations.
+//!
+//! `mutex_adapter` reproduces the old swarm adapter (every increment locks a Mutex around
+//! plain counters); `atomic_metrics` is the per-file `Metrics` swarm workers count into now.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::sync::{Arc, Mutex};
+use uniprot_etl::metrics::{FeatureCategory, Metrics, MetricsCollector};
+
+const ENTRIES: u64 = 10_000;
+
+/// Counter updates of one parsed entry with a few features and PTM sites.
+fn count_entries<M: MetricsCollector>(metrics: &M) {
+    for i in 0..ENTRIES {
+        metrics.inc_entries();
+        metrics.add_bytes_read(black_box(2_000));
+        metrics.add_features(black_box(12));
+        metrics.add_isoforms(black_box(i % 2));
+        metrics.add_feature_category(FeatureCategory::Generic, black_box(8));
+        metrics.add_feature_category(FeatureCategory::Domain, black_box(2));
+        metrics.add_feature_category(FeatureCategory::SubcellularLocation, black_box(2));
+        for _ in 0..4 {
+            metrics.add_ptm_attempted(1);
+            metrics.add_ptm_mapped(1);
+        }
+    }
+}
+
+const COUNTERS: usize = 28 + FeatureCategory::COUNT;
+
+/// Baseline: plain counters behind a Mutex.
+#[derive(Clone)]
+struct MutexMetrics {
+    counters: Arc<Mutex<[u64; COUNTERS]>>,
+}
+
+impl Default for MutexMetrics {
+    fn default() -> Self {
+        Self {
+            counters: Arc::new(Mutex::new([0; COUNTERS])),
+        }
+    }
+}
+
+impl MutexMetrics {
+    fn add(&self, index: usize, count: u64) {
+        self.counters.lock().unwrap()[index] += count;
+    }
+}
+
+macro_rules! mutex_counters {
+    ($($method:ident => $index:expr),* $(,)?) => {
+        $(fn $method(&self, count: u64) {
+            self.add($index, count);
+        })*
+    };
+}
+
+impl MetricsCollector for MutexMetrics {
+    fn inc_entries(&self) {
+        self.add(0, 1);
+    }
+
+    fn inc_batches(&self) {
+        self.add(1, 1);
+    }
+
+    mutex_counters! {
+        add_bytes_read => 2,
+        add_bytes_written => 3,
+        add_features => 4,
+        add_isoforms => 5,
+        add_ptm_attempted => 6,
+        add_ptm_mapped => 7,
+        add_ptm_failed => 8,
+        add_ptm_failed_canonical_oob => 9,
+        add_ptm_failed_vsp_deletion => 10,
+        add_ptm_failed_mapper_oob => 11,
+        add_ptm_failed_vsp_unresolvable => 12,
+        add_ptm_failed_isoform_oob => 13,
+        add_ptm_failed_residue_mismatch => 14,
+        add_isoform_seq_missing => 15,
+        add_rows_rejected => 16,
+        add_checksum_mismatches => 17,
+        add_duplicate_entries => 20,
+        add_unknown_feature_types => 21,
+        add_invalid_entries => 22,
+        add_vsp_conflicts => 23,
+        add_isoform_length_mismatch => 24,
+        add_fasta_records_loaded => 25,
+        add_fasta_records_skipped => 26,
+        add_rows_filtered => 27,
+    }
+
+    fn add_feature_category(&self, category: FeatureCategory, count: u64) {
+        self.add(28 + category as usize, count);
+    }
+
+    fn record_builder_memory(&self, bytes: u64, rows: u64) {
+        self.add(18, bytes);
+        self.add(19, rows);
+    }
+}
+
+fn benchmark_counters(c: &mut Criterion) {
+    let mut group = c.benchmark_group("metrics");
+    group.throughput(Throughput::Elements(ENTRIES));
+
+    group.bench_function("mutex_adapter", |b| {
+        let metrics = MutexMetrics::default();
+        b.iter(|| count_entries(&metrics))
+    });
+
+    group.bench_function("atomic_metrics", |b| {
+        let metrics = Metrics::new();
+        b.iter(|| count_entries(&metrics))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_counters);
+criterion_main!(benches);
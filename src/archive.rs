@@ -0,0 +1,109 @@
+//! Run directory archives (`runs.archive`).
+//!
+//! Packs a finished run directory (log, report, config snapshot, rejected rows, traces)
+//! into `<run_id>.tar.zst` and optionally copies it to long-term storage, so run
+//! provenance outlives `runs_dir` cleanup.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::config::ArchiveConfig;
+
+/// Writes `run_dir` as `<dest_dir>/<run_id>.tar.zst` and returns the archive path.
+///
+/// Entries are stored under `<run_id>/`, so unpacking recreates the run directory.
+pub fn archive_run(run_dir: &Path, run_id: &str, dest_dir: &Path, zstd_level: i32) -> Result<PathBuf> {
+    fs::create_dir_all(dest_dir).with_context(|| format!("Failed to create {}", dest_dir.display()))?;
+    let path = dest_dir.join(format!("{}.tar.zst", run_id));
+    let part = path.with_extension("zst.part");
+
+    let file = File::create(&part).with_context(|| format!("Failed to create {}", part.display()))?;
+    let encoder = zstd::Encoder::new(BufWriter::new(file), zstd_level)?;
+    let mut tar = tar::Builder::new(encoder);
+    tar.append_dir_all(run_id, run_dir)
+        .with_context(|| format!("Failed to archive {}", run_dir.display()))?;
+    tar.into_inner()?.finish()?.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+
+    fs::rename(&part, &path).with_context(|| format!("Failed to move {} into place", part.display()))?;
+    Ok(path)
+}
+
+/// Copies `archive` to `destination`: an `http(s)://` URL prefix (HTTP PUT, e.g. a
+/// WebDAV or object store upload endpoint) or a directory (e.g. a mounted
+/// bucket). Returns where the archive was stored.
+pub fn upload_archive(archive: &Path, destination: &str) -> Result<String> {
+    let name = archive
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("Invalid archive path {}", archive.display()))?;
+
+    if destination.starts_with("http://") || destination.starts_with("https://") {
+        let url = format!("{}/{}", destination.trim_end_matches('/'), name);
+        let file = File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+        let length = file.metadata()?.len();
+        ureq::put(&url)
+            .set("Content-Type", "application/zstd")
+            .set("Content-Length", &length.to_string())
+            .send(BufReader::new(file))
+            .with_context(|| format!("Failed to upload {} to {}", archive.display(), url))?;
+        return Ok(url);
+    }
+
+    let dir = PathBuf::from(destination.strip_prefix("file://").unwrap_or(destination));
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let dest = dir.join(name);
+    fs::copy(archive, &dest)
+        .with_context(|| format!("Failed to copy {} to {}", archive.display(), dest.display()))?;
+    Ok(dest.display().to_string())
+}
+
+/// Archives the run per `config` and uploads it if configured; returns the local
+/// archive and the upload location.
+pub fn archive_and_upload(
+    run_dir: &Path,
+    run_id: &str,
+    runs_dir: &Path,
+    config: &ArchiveConfig,
+) -> Result<(PathBuf, Option<String>)> {
+    let dest_dir = config.dir.as_deref().unwrap_or(runs_dir);
+    let archive = archive_run(run_dir, run_id, dest_dir, config.zstd_level)?;
+    let uploaded = config
+        .upload_to
+        .as_deref()
+        .map(|destination| upload_archive(&archive, destination))
+        .transpose()?;
+    Ok((archive, uploaded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archives_and_copies_run_directory() {
+        let temp_dir = std::env::temp_dir().join("uniprot_etl_test_archive");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let run_dir = temp_dir.join("run_20250101_120000");
+        fs::create_dir_all(&run_dir).unwrap();
+        fs::write(run_dir.join("report.yaml"), "status: Success\n").unwrap();
+
+        let archive = archive_run(&run_dir, "run_20250101_120000", &temp_dir, 3).unwrap();
+        assert!(archive.ends_with("run_20250101_120000.tar.zst"));
+
+        let decoder = zstd::Decoder::new(File::open(&archive).unwrap()).unwrap();
+        let names: Vec<String> = tar::Archive::new(decoder)
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().display().to_string())
+            .collect();
+        assert!(names.iter().any(|n| n == "run_20250101_120000/report.yaml"));
+
+        let store = temp_dir.join("store");
+        let dest = upload_archive(&archive, &format!("file://{}", store.display())).unwrap();
+        assert!(Path::new(&dest).exists());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,382 @@
+//! Adds columns to existing Parquet output without a full re-run (`uniprot_etl backfill`).
+//!
+//! The original XML is parsed again, but only `id`, `parent_id` and the requested
+//! built-in columns are built: the other columns' builders (PTM mapping, structures,
+//! ...) and everything the writer does afterwards are skipped. The new values are
+//! joined on `id` into every Parquet file of the output, and each file is rewritten in
+//! place through a temporary file with its other columns, row groups and row order
+//! untouched, so sorted output stays parent_id-aligned and its index stays valid.
+//! Requested columns the files already have are replaced; the others are appended
+//! in schema order.
+//!
+//! The rebuilt columns of every input are held in memory for the join, so backfilling
+//! a wide nested column over a full TrEMBL dump is best done one input chunk at a time.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{Array, ArrayRef, StringArray, UInt32Array};
+use arrow::compute::{concat_batches, take};
+use arrow::datatypes::{Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use crossbeam_channel::unbounded;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use serde::Serialize;
+
+use crate::config::Settings;
+use crate::log_info;
+use crate::metrics::Metrics;
+use crate::pipeline::feature_routing::FeatureRouting;
+use crate::pipeline::parser::{parse_entries_with_hooks, ParseHooks};
+use crate::pipeline::plddt::PlddtSource;
+use crate::pipeline::ptm_vocab::PtmVocabulary;
+use crate::pipeline::reader::create_xml_reader;
+use crate::schema::create_uniprot_schema;
+use crate::stats::parquet_files;
+use crate::writer::dataset::refresh_manifest_sizes;
+use crate::writer::parquet::writer_properties_builder;
+
+/// What a backfill changed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BackfillSummary {
+    /// Columns added or replaced, in schema order
+    pub columns: Vec<String>,
+    pub files: usize,
+    pub rows: u64,
+    /// Rows whose id was found in the inputs (the others got nulls)
+    pub matched: u64,
+}
+
+/// Recomputes `columns` from `inputs` and merges them into every Parquet file of `output`
+/// (a file, swarm directory or dataset directory).
+///
+/// `release` is stamped into the `release` column, as `--release` does for a full run.
+pub fn backfill(
+    output: &Path,
+    inputs: &[PathBuf],
+    columns: &[String],
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    release: Option<String>,
+    settings: &Settings,
+) -> Result<BackfillSummary> {
+    check_columns(columns)?;
+    let files = parquet_files(output)?;
+    if files.is_empty() {
+        return Err(anyhow!("No Parquet files in {}", output.display()));
+    }
+
+    let rebuilt = rebuild_columns(inputs, columns, sidecar_fasta, release, settings)?;
+    let mut summary = BackfillSummary {
+        columns: rebuilt.added_fields().map(|f| f.name().clone()).collect(),
+        files: files.len(),
+        ..BackfillSummary::default()
+    };
+    for file in &files {
+        let (rows, matched) = rewrite_file(file, &rebuilt, settings)?;
+        log_info!("run", "Backfilled {}: {} of {} rows matched", file.display(), matched, rows);
+        summary.rows += rows;
+        summary.matched += matched;
+    }
+    if output.is_dir() {
+        refresh_manifest_sizes(output)?;
+    }
+    Ok(summary)
+}
+
+/// Rejects columns that are not built-in or are the join keys themselves.
+fn check_columns(columns: &[String]) -> Result<()> {
+    if columns.is_empty() {
+        return Err(anyhow!("No columns to backfill"));
+    }
+    let schema = create_uniprot_schema();
+    for column in columns {
+        if column == "id" || column == "parent_id" {
+            return Err(anyhow!("`{}` is the join key and cannot be backfilled", column));
+        }
+        if schema.field_with_name(column).is_err() {
+            return Err(anyhow!("`{}` is not a built-in column", column));
+        }
+    }
+    Ok(())
+}
+
+/// The rebuilt columns of every input row, addressable by id.
+struct Rebuilt {
+    batch: RecordBatch,
+    rows: HashMap<String, u32>,
+}
+
+impl Rebuilt {
+    /// Fields of the rebuilt columns (without the `id`/`parent_id` keys).
+    fn added_fields(&self) -> impl Iterator<Item = &Arc<Field>> {
+        self.batch
+            .schema_ref()
+            .fields()
+            .iter()
+            .filter(|f| f.name() != "id" && f.name() != "parent_id")
+    }
+}
+
+fn rebuild_columns(
+    inputs: &[PathBuf],
+    columns: &[String],
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    release: Option<String>,
+    settings: &Settings,
+) -> Result<Rebuilt> {
+    let transform = &settings.transform;
+    let hooks = ParseHooks {
+        columns: Some(columns.into()),
+        release,
+        ptm_vocabulary: match &transform.ptm_vocabulary_path {
+            Some(path) => PtmVocabulary::load(path)
+                .with_context(|| format!("Failed to load PTM vocabulary {}", path.display()))?,
+            None => PtmVocabulary::embedded(),
+        },
+        site_context: transform.site_context(),
+        plddt: match &transform.alphafold_dir {
+            Some(dir) => PlddtSource::open(dir)
+                .with_context(|| format!("Failed to index AlphaFold models in {}", dir.display()))?,
+            None => PlddtSource::default(),
+        },
+        reconcile_domain_xrefs: transform.reconcile_domain_xrefs,
+        debug_mapping: transform.debug_mapping,
+        feature_routing: FeatureRouting::with_overrides(&transform.feature_routes),
+        invalid_entries: transform.invalid_entries,
+        low_confidence_on_length_mismatch: transform.low_confidence_on_length_mismatch,
+        keep_unsequenced_isoforms: transform.keep_unsequenced_isoforms,
+        ..ParseHooks::default()
+    };
+
+    let mut batches = Vec::new();
+    for input in inputs {
+        let metrics = Metrics::new();
+        let reader = create_xml_reader(input, settings, &metrics)?;
+        let (tx, rx) = unbounded();
+        parse_entries_with_hooks(
+            reader,
+            tx,
+            &metrics,
+            settings.performance.batch_size,
+            sidecar_fasta.clone(),
+            &hooks,
+        )
+        .with_context(|| format!("Failed to parse {}", input.display()))?;
+        batches.extend(rx.iter());
+        log_info!("run", "Rebuilt {} entries of {}", metrics.entries(), input.display());
+    }
+
+    let schema = batches
+        .first()
+        .map(|b| b.schema())
+        .ok_or_else(|| anyhow!("No entries in the inputs"))?;
+    let batch = concat_batches(&schema, &batches)?;
+    let ids = string_column(&batch, "id")?;
+    let mut rows = HashMap::with_capacity(ids.len());
+    for (row, id) in ids.iter().enumerate() {
+        if let Some(id) = id {
+            rows.entry(id.to_string()).or_insert(row as u32);
+        }
+    }
+    Ok(Rebuilt { batch, rows })
+}
+
+/// Rewrites `path` with the rebuilt columns joined in; returns (rows, matched rows).
+///
+/// Input row groups are copied one at a time and each is closed with a flush, so row
+/// group boundaries (parent_id-aligned in sorted output) and row positions are kept
+/// and a `storage.write_index` index stays valid.
+fn rewrite_file(path: &Path, rebuilt: &Rebuilt, settings: &Settings) -> Result<(u64, u64)> {
+    let open = || -> Result<ParquetRecordBatchReaderBuilder<File>> {
+        let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        Ok(ParquetRecordBatchReaderBuilder::try_new(file)?)
+    };
+    let builder = open()?;
+    let schema = merged_schema(builder.schema(), rebuilt);
+    let row_groups = builder.metadata().num_row_groups();
+
+    let tmp = path.with_extension("parquet.backfill");
+    let props = writer_properties_builder(settings)?
+        .set_max_row_group_size(usize::MAX)
+        .build();
+    let out = File::create(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+    let mut writer = ArrowWriter::try_new(out, Arc::clone(&schema), Some(props))?;
+
+    let (mut rows, mut matched) = (0u64, 0u64);
+    for row_group in 0..row_groups {
+        for batch in open()?.with_row_groups(vec![row_group]).build()? {
+            let batch = batch?;
+            let ids = string_column(&batch, "id")?;
+            let indices: UInt32Array =
+                ids.iter().map(|id| id.and_then(|id| rebuilt.rows.get(id).copied())).collect();
+            rows += batch.num_rows() as u64;
+            matched += (indices.len() - indices.null_count()) as u64;
+
+            let arrays = schema
+                .fields()
+                .iter()
+                .map(|field| match rebuilt.batch.column_by_name(field.name()) {
+                    Some(values) if field.name() != "id" && field.name() != "parent_id" => {
+                        if !field.is_nullable() && indices.null_count() > 0 {
+                            return Err(anyhow!(
+                                "{} rows of {} are not in the inputs and `{}` cannot be null",
+                                indices.null_count(),
+                                path.display(),
+                                field.name()
+                            ));
+                        }
+                        Ok(take(values, &indices, None)?)
+                    }
+                    _ => batch
+                        .column_by_name(field.name())
+                        .cloned()
+                        .ok_or_else(|| anyhow!("{} has no `{}` column", path.display(), field.name())),
+                })
+                .collect::<Result<Vec<ArrayRef>>>()?;
+            writer.write(&RecordBatch::try_new(Arc::clone(&schema), arrays)?)?;
+        }
+        writer.flush()?;
+    }
+    writer.close()?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to move {} into place", tmp.display()))?;
+    Ok((rows, matched))
+}
+
+/// `existing` with the rebuilt columns replacing same-named fields or appended.
+fn merged_schema(existing: &Schema, rebuilt: &Rebuilt) -> SchemaRef {
+    let added: Vec<&Arc<Field>> = rebuilt.added_fields().collect();
+    let mut fields: Vec<Arc<Field>> = existing
+        .fields()
+        .iter()
+        .map(|field| match added.iter().find(|f| f.name() == field.name()) {
+            Some(&replacement) => Arc::clone(replacement),
+            None => Arc::clone(field),
+        })
+        .collect();
+    for &field in &added {
+        if existing.field_with_name(field.name()).is_err() {
+            fields.push(Arc::clone(field));
+        }
+    }
+    Arc::new(Schema::new(fields))
+}
+
+fn string_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a StringArray> {
+    batch
+        .column_by_name(name)
+        .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+        .ok_or_else(|| anyhow!("expected a Utf8 `{}` column", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::SchemaProfile;
+    use crate::writer::index::{index_path_for, read_indexed, write_index};
+    use crate::writer::parquet::write_batches;
+    use crate::writer::sort::sort_parquet_by_parent_id;
+    use arrow::array::ListArray;
+    use quick_xml::Reader;
+
+    const XML: &str = r#"<uniprot>
+        <entry>
+            <accession>P1BKF</accession>
+            <name>ONE_HUMAN</name>
+            <protein><recommendedName><fullName>Kinase</fullName><ecNumber>2.7.11.1</ecNumber></recommendedName></protein>
+            <sequence length="3">MKT</sequence>
+        </entry>
+        <entry>
+            <accession>P2BKF</accession>
+            <name>TWO_HUMAN</name>
+            <sequence length="3">MAA</sequence>
+        </entry>
+    </uniprot>"#;
+
+    #[test]
+    fn adds_missing_columns_and_keeps_the_others() -> Result<()> {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_backfill");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        let xml_path = dir.join("input.xml");
+        fs::write(&xml_path, XML)?;
+        let output = dir.join("out.parquet");
+
+        // Existing output built with the minimal profile
+        let mut settings = Settings::default();
+        settings.schema.profile = SchemaProfile::Minimal;
+        let (tx, rx) = unbounded();
+        let hooks = ParseHooks {
+            schema_profile: SchemaProfile::Minimal,
+            ..ParseHooks::default()
+        };
+        parse_entries_with_hooks(Reader::from_reader(XML.as_bytes()), tx, &Metrics::new(), 16, None, &hooks)?;
+        write_batches(rx, &output, &Metrics::new(), &settings)?;
+
+        let columns = ["entry_name".to_string(), "ec_numbers".to_string(), "gene_name".to_string()];
+        let summary = backfill(&output, &[xml_path], &columns, None, None, &Settings::default())?;
+        assert_eq!(summary.columns, ["entry_name", "gene_name", "ec_numbers"]);
+        assert_eq!((summary.files, summary.rows, summary.matched), (1, 2, 2));
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&output)?)?.build()?;
+        let batch = reader.collect::<std::result::Result<Vec<_>, _>>()?.remove(0);
+        let names: Vec<&str> = batch.schema_ref().fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(
+            names,
+            ["id", "sequence", "organism_id", "gene_name", "parent_id", "entry_name", "ec_numbers"]
+        );
+        let entry_names = string_column(&batch, "entry_name")?;
+        assert_eq!(entry_names.value(0), "ONE_HUMAN");
+        assert_eq!(entry_names.value(1), "TWO_HUMAN");
+        let ec = batch.column_by_name("ec_numbers").unwrap().as_any().downcast_ref::<ListArray>().unwrap();
+        assert_eq!(ec.value_length(0), 1);
+        assert_eq!(ec.value_length(1), 0);
+        assert_eq!(string_column(&batch, "sequence")?.value(1), "MAA");
+
+        assert!(backfill(&output, &[], &["id".to_string()], None, None, &Settings::default()).is_err());
+        assert!(backfill(&output, &[], &["keywords".to_string()], None, None, &Settings::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_the_row_groups_of_sorted_indexed_output() -> Result<()> {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_backfill_sorted");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        let xml_path = dir.join("input.xml");
+        fs::write(&xml_path, XML)?;
+        let output = dir.join("out.parquet");
+
+        // One parent_id per row group, indexed
+        let mut settings = Settings::default();
+        settings.schema.profile = SchemaProfile::Minimal;
+        settings.performance.max_row_group_size = 1;
+        settings.storage.temp_dir = dir.clone();
+        let (tx, rx) = unbounded();
+        let hooks = ParseHooks {
+            schema_profile: SchemaProfile::Minimal,
+            ..ParseHooks::default()
+        };
+        parse_entries_with_hooks(Reader::from_reader(XML.as_bytes()), tx, &Metrics::new(), 16, None, &hooks)?;
+        write_batches(rx, &output, &Metrics::new(), &settings)?;
+        sort_parquet_by_parent_id(&output, &settings)?;
+        let index_path = index_path_for(&output);
+        write_index(std::slice::from_ref(&output), &index_path)?;
+
+        backfill(&output, &[xml_path], &["entry_name".to_string()], None, None, &Settings::default())?;
+
+        let row_groups = ParquetRecordBatchReaderBuilder::try_new(File::open(&output)?)?
+            .metadata()
+            .num_row_groups();
+        assert_eq!(row_groups, 2);
+        for (accession, name) in [("P1BKF", "ONE_HUMAN"), ("P2BKF", "TWO_HUMAN")] {
+            let batches = read_indexed(&index_path, accession)?;
+            assert_eq!(batches.len(), 1);
+            assert_eq!(string_column(&batches[0], "entry_name")?.value(0), name);
+        }
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@ use arrow::record_batch::RecordBatch;
 use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use std::fs::File;
 use std::path::PathBuf;
+use uniprot_etl::schema::utf8_column;
 
 fn main() -> Result<()> {
     let path = PathBuf::from("data/parquet/uniprot.parquet");
@@ -37,11 +38,6 @@ fn main() -> Result<()> {
             .iter()
             .position(|f| f.name() == "protein_name")
             .ok_or_else(|| anyhow!("protein_name column not found"))?;
-        let organism_name_idx = schema
-            .fields()
-            .iter()
-            .position(|f| f.name() == "organism_name")
-            .ok_or_else(|| anyhow!("organism_name column not found"))?;
         let existence_idx = schema
             .fields()
             .iter()
@@ -68,11 +64,7 @@ fn main() -> Result<()> {
             .as_any()
             .downcast_ref::<StringArray>()
             .unwrap();
-        let org_names = batch
-            .column(organism_name_idx)
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .unwrap();
+        let org_names = utf8_column(&batch, "organism_name")?;
         let existence = batch
             .column(existence_idx)
             .as_any()
@@ -122,11 +114,7 @@ fn main() -> Result<()> {
                 );
                 let struct_vals = structures.value(i);
                 let struct_arr = struct_vals.as_any().downcast_ref::<StructArray>().unwrap();
-                let dbs = struct_arr
-                    .column(0)
-                    .as_any()
-                    .downcast_ref::<StringArray>()
-                    .unwrap();
+                let dbs = utf8_column(struct_arr, "db")?;
                 let ids_col = struct_arr
                     .column(1)
                     .as_any()
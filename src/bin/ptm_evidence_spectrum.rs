@@ -4,6 +4,7 @@ use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use std::collections::HashMap;
 use std::fs::File;
 use std::path::PathBuf;
+use uniprot_etl::schema::utf8_column;
 
 #[derive(Debug)]
 struct PtmStats {
@@ -73,12 +74,7 @@ fn main() -> Result<()> {
                 .downcast_ref::<StructArray>()
                 .ok_or_else(|| anyhow!("feature array is not a StructArray"))?;
 
-            let feature_types = feature_struct
-                .column_by_name("feature_type")
-                .ok_or_else(|| anyhow!("feature_type column not found"))?
-                .as_any()
-                .downcast_ref::<StringArray>()
-                .ok_or_else(|| anyhow!("feature_type is not a StringArray"))?;
+            let feature_types = utf8_column(feature_struct, "feature_type")?;
 
             let descriptions = feature_struct
                 .column_by_name("description")
@@ -87,12 +83,7 @@ fn main() -> Result<()> {
                 .downcast_ref::<StringArray>()
                 .ok_or_else(|| anyhow!("description is not a StringArray"))?;
 
-            let evidence_codes = feature_struct
-                .column_by_name("evidence_code")
-                .ok_or_else(|| anyhow!("evidence_code column not found"))?
-                .as_any()
-                .downcast_ref::<StringArray>()
-                .ok_or_else(|| anyhow!("evidence_code is not a StringArray"))?;
+            let evidence_codes = utf8_column(feature_struct, "evidence_code")?;
 
             for feature_idx in 0..feature_types.len() {
                 if feature_types.is_null(feature_idx) {
@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use uniprot_etl::config::Settings;
+use uniprot_etl::fasta::load_fasta_map;
+use uniprot_etl::metrics::Metrics;
+use uniprot_etl::pipeline::parser::for_each_entry;
+use uniprot_etl::pipeline::reader::create_xml_reader;
+use uniprot_etl::ptm_fixtures::{build_fixture, read_fail_log, read_rejected, write_fixture, PtmFailure, FIXTURE_DIR};
+
+/// Turn captured PTM mapping failures into regression fixtures.
+///
+/// Failures come from stderr logs (`[PTM_FAIL]` lines) and/or `rejected.parquet`
+/// files; the entries they refer to are read from the original XML (and sidecar
+/// FASTA for isoform rows). Fixtures that already exist are left untouched, so a
+/// curated `expected` outcome is never overwritten.
+#[derive(Parser, Debug)]
+#[command(name = "ptm_fixtures")]
+#[command(about = "Minimize [PTM_FAIL] records into tests/fixtures/ptm_regressions")]
+pub struct Args {
+    /// UniProt XML (.xml or .xml.gz) the failing run read
+    #[arg(short, long)]
+    pub input: PathBuf,
+
+    /// Sidecar FASTA with the isoform sequences of the run
+    #[arg(short, long)]
+    pub fasta: Option<PathBuf>,
+
+    /// Captured stderr log with [PTM_FAIL] lines (repeatable)
+    #[arg(long)]
+    pub log: Vec<PathBuf>,
+
+    /// rejected.parquet of a run (repeatable)
+    #[arg(long)]
+    pub rejected: Vec<PathBuf>,
+
+    /// Directory to write fixtures into
+    #[arg(short, long, default_value = FIXTURE_DIR)]
+    pub out: PathBuf,
+
+    /// At most this many fixtures per failure code
+    #[arg(long, default_value_t = 20)]
+    pub max_per_code: usize,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.log.is_empty() && args.rejected.is_empty() {
+        return Err(anyhow!("Pass at least one --log or --rejected file"));
+    }
+
+    let mut failures = BTreeSet::new();
+    for path in &args.log {
+        failures.extend(read_fail_log(path)?);
+    }
+    for path in &args.rejected {
+        failures.extend(read_rejected(path)?);
+    }
+    let mut by_parent: HashMap<String, Vec<PtmFailure>> = HashMap::new();
+    for failure in failures {
+        by_parent.entry(failure.parent_id.clone()).or_default().push(failure);
+    }
+    let fasta = args.fasta.as_deref().map(load_fasta_map).transpose()?.unwrap_or_default();
+
+    let metrics = Metrics::new();
+    let reader = create_xml_reader(&args.input, &Settings::default(), &metrics)?;
+    let mut fixtures = Vec::new();
+    let mut unreproduced = 0usize;
+    for_each_entry(reader, |entry| {
+        let Some(failures) = by_parent
+            .remove(&entry.parent_id)
+            .or_else(|| by_parent.remove(&entry.accession))
+        else {
+            return Ok(());
+        };
+        for failure in failures {
+            let row_sequence = if failure.id == entry.accession && entry.isoforms.is_empty() {
+                Some(entry.sequence.as_str())
+            } else {
+                fasta.get(&failure.id).map(String::as_str)
+            };
+            let Some(fixture) = row_sequence.and_then(|seq| build_fixture(&entry, seq, &failure)) else {
+                eprintln!(
+                    "[WARN] {} {} original_index={} no longer reproduces; skipped",
+                    failure.code, failure.id, failure.original_index
+                );
+                unreproduced += 1;
+                continue;
+            };
+            fixtures.push(fixture);
+        }
+        Ok(())
+    })?;
+
+    let mut written: BTreeMap<String, usize> = BTreeMap::new();
+    let mut skipped = 0usize;
+    for fixture in fixtures {
+        let count = written.entry(fixture.observed.clone()).or_default();
+        if *count >= args.max_per_code {
+            continue;
+        }
+        if write_fixture(&args.out, &fixture)? {
+            *count += 1;
+        } else {
+            skipped += 1;
+        }
+    }
+
+    for (code, count) in &written {
+        println!("{:<20} {} fixture(s)", code, count);
+    }
+    println!(
+        "Wrote {} fixture(s) to {} ({} already present, {} not reproduced, {} parents not found)",
+        written.values().sum::<usize>(),
+        args.out.display(),
+        skipped,
+        unreproduced,
+        by_parent.len()
+    );
+    Ok(())
+}
@@ -4,6 +4,7 @@ use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use std::collections::HashMap;
 use std::fs::File;
 use std::path::PathBuf;
+use uniprot_etl::schema::utf8_column;
 
 fn main() -> Result<()> {
     let path = PathBuf::from("data/parquet/uniprot_human_super_substrate.parquet");
@@ -50,19 +51,9 @@ fn main() -> Result<()> {
                 .ok_or_else(|| anyhow!("feature array is not a StructArray"))?;
 
             // Get the feature_type and evidence_code columns from the struct
-            let feature_types = feature_struct
-                .column_by_name("feature_type")
-                .ok_or_else(|| anyhow!("feature_type column not found"))?
-                .as_any()
-                .downcast_ref::<StringArray>()
-                .ok_or_else(|| anyhow!("feature_type is not a StringArray"))?;
+            let feature_types = utf8_column(feature_struct, "feature_type")?;
 
-            let evidence_codes = feature_struct
-                .column_by_name("evidence_code")
-                .ok_or_else(|| anyhow!("evidence_code column not found"))?
-                .as_any()
-                .downcast_ref::<StringArray>()
-                .ok_or_else(|| anyhow!("evidence_code is not a StringArray"))?;
+            let evidence_codes = utf8_column(feature_struct, "evidence_code")?;
 
             // Check each feature in this protein entry
             for feature_idx in 0..feature_types.len() {
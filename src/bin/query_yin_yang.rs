@@ -1,15 +1,14 @@
 use anyhow::{anyhow, Result};
 use arrow::array::{Array, Int32Array, ListArray, RecordBatch, StringArray, StructArray};
 use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fs::File;
 use std::path::PathBuf;
+use uniprot_etl::schema::utf8_column;
 
 #[derive(Debug, Clone)]
 struct SiteInfo {
-    position: i32,
     amino_acid: String,
-    ptm_type: String,
     evidence: String,
 }
 
@@ -81,12 +80,7 @@ fn main() -> Result<()> {
                 .downcast_ref::<StructArray>()
                 .ok_or_else(|| anyhow!("feature array is not a StructArray"))?;
 
-            let feature_types = feature_struct
-                .column_by_name("feature_type")
-                .ok_or_else(|| anyhow!("feature_type column not found"))?
-                .as_any()
-                .downcast_ref::<StringArray>()
-                .ok_or_else(|| anyhow!("feature_type is not a StringArray"))?;
+            let feature_types = utf8_column(feature_struct, "feature_type")?;
 
             let descriptions = feature_struct
                 .column_by_name("description")
@@ -102,12 +96,7 @@ fn main() -> Result<()> {
                 .downcast_ref::<Int32Array>()
                 .ok_or_else(|| anyhow!("start is not an Int32Array"))?;
 
-            let evidence_codes = feature_struct
-                .column_by_name("evidence_code")
-                .ok_or_else(|| anyhow!("evidence_code column not found"))?
-                .as_any()
-                .downcast_ref::<StringArray>()
-                .ok_or_else(|| anyhow!("evidence_code is not a StringArray"))?;
+            let evidence_codes = utf8_column(feature_struct, "evidence_code")?;
 
             // Collect all phosphorylation and O-GlcNAc sites for this protein
             for feature_idx in 0..feature_types.len() {
@@ -137,9 +126,7 @@ fn main() -> Result<()> {
                    (desc_lower.contains("phospho") || 
                     desc_lower.contains("phosphorylated")) {
                     phospho_sites.insert(position, SiteInfo {
-                        position,
                         amino_acid: extract_amino_acid(&desc_lower),
-                        ptm_type: "Phosphorylation".to_string(),
                         evidence: evidence.clone(),
                     });
                 }
@@ -150,9 +137,7 @@ fn main() -> Result<()> {
                     desc_lower.contains("n-acetylglucosamine") ||
                     desc_lower.contains("glcnac")) {
                     oglcnac_sites.insert(position, SiteInfo {
-                        position,
                         amino_acid: extract_amino_acid(&desc_lower),
-                        ptm_type: "O-GlcNAc".to_string(),
                         evidence: evidence.clone(),
                     });
                 }
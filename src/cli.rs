@@ -1,6 +1,9 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
+use crate::logging::LogFormat;
+use crate::network::NetworkFormat;
+
 #[derive(Parser, Debug)]
 #[command(name = "uniprot_etl")]
 #[command(about = "High-throughput ETL for UniProtKB/Swiss-Prot XML to Apache Parquet")]
@@ -36,4 +39,232 @@ pub struct Args {
     /// If the value does not start with "run_", it will be prefixed.
     #[arg(long)]
     pub run_id: Option<String>,
+
+    /// UniProt release of the input (e.g. 2024_06), stamped into the `release` column.
+    /// Without it the release is taken from the input itself or a reldate.txt next to it.
+    #[arg(long)]
+    pub release: Option<String>,
+
+    /// Fail on the first canonical sequence whose CRC64 does not match its
+    /// `<sequence checksum>` attribute (mismatches are otherwise only counted)
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Write a verbose trace of this entry (elements read, feature routing, isoform VSP
+    /// edits, PTM mapping decisions) to runs/<run_id>/trace_<ACCESSION>.log
+    #[arg(long, value_name = "ACCESSION")]
+    pub trace_accession: Option<String>,
+
+    /// Sample the run with an in-process CPU profiler and write a flamegraph SVG and a
+    /// pprof protobuf to runs/<run_id>/profiles/ (requires `--features profiling`)
+    #[arg(long)]
+    pub profile_cpu: bool,
+
+    /// Smoke-test mode: process only every Nth entry of each input file
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub sample_every: Option<u64>,
+
+    /// Smoke-test mode: stop each input file after N processed entries
+    #[arg(long, value_name = "N")]
+    pub max_entries: Option<usize>,
+
+    /// Watch a landing directory and process XML drops as they arrive (runs until Ctrl-C/SIGTERM).
+    ///
+    /// Processed files are moved to `<dir>/processed/` (failures to `<dir>/failed/`) and
+    /// recorded in the run's manifest.jsonl. Output goes to the output directory, as in
+    /// swarm mode.
+    #[arg(long, value_name = "DIR", conflicts_with = "input")]
+    pub watch: Option<PathBuf>,
+
+    /// Fetch these accessions from the UniProtKB REST API instead of reading an input file
+    /// (comma-separated). Batching and rate limiting follow `storage.rest_fetch`.
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["input", "watch"])]
+    pub accessions: Vec<String>,
+
+    /// File with accessions to fetch (whitespace- or comma-separated, `#` comments);
+    /// combined with --accessions
+    #[arg(long, conflicts_with_all = ["input", "watch"])]
+    pub accessions_file: Option<PathBuf>,
+
+    /// Write nothing to stderr but JSON events (with --log-format json); human-readable logs
+    /// still go to runs/<run_id>/etl.log. Also hides the progress spinner.
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// stderr format: text lines, or one JSON event per line for workflow managers
+    /// (no progress spinner)
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Optional subcommand; without one the ETL pipeline runs
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Report dataset-level statistics for produced Parquet output
+    Stats(StatsArgs),
+    /// Serve on-demand extraction over HTTP (raw XML or accession in, rows out)
+    Serve(ServeArgs),
+    /// Download a reference proteome (XML + isoform FASTA) and run the ETL on it
+    FetchProteome(FetchProteomeArgs),
+    /// Print the rows of one protein using an output index (`storage.write_index`)
+    Lookup(LookupArgs),
+    /// Compare two releases' outputs: added/removed rows, sequence, PTM and feature changes
+    DiffReleases(DiffReleasesArgs),
+    /// Write the interaction network as an edge list (TSV) or GraphML for Cytoscape
+    ExportNetwork(ExportNetworkArgs),
+    /// Walk through how one entry is transformed: fields, isoforms, VSP edits, feature
+    /// mapping per isoform, PTM decisions and the rows it yields
+    Explain(ExplainArgs),
+    /// Recompute built-in columns from the original XML and merge them into existing
+    /// output, without a full re-run
+    Backfill(BackfillArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct StatsArgs {
+    /// Parquet file, or directory of .parquet files (swarm output)
+    pub path: PathBuf,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = StatsFormat::Yaml)]
+    pub format: StatsFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub addr: String,
+
+    /// Request-handling threads
+    #[arg(long, default_value_t = 4)]
+    pub workers: usize,
+
+    /// UniProtKB REST base URL used by `GET /entry/<accession>`
+    #[arg(long, default_value = crate::rest::DEFAULT_REST_URL)]
+    pub rest_url: String,
+
+    /// Path to isoform sidecar FASTA (varsplic.fasta, unzipped)
+    #[arg(long)]
+    pub fasta_sidecar: Option<PathBuf>,
+
+    /// UniProt release written to the `release` column (default: `sidecar_download.release`)
+    #[arg(long)]
+    pub release: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct LookupArgs {
+    /// Index file (`_index.parquet` or `_<stem>.index.parquet`)
+    pub index: PathBuf,
+
+    /// Accession (all rows of the protein) or isoform id (that row only)
+    pub accession: String,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExplainArgs {
+    /// Accession of the entry (primary or secondary)
+    #[arg(long)]
+    pub accession: String,
+
+    /// UniProt XML (`.xml` or `.xml.gz`) containing the entry
+    #[arg(long)]
+    pub input: PathBuf,
+
+    /// Path to isoform sidecar FASTA (varsplic.fasta, unzipped); needed for isoform rows
+    #[arg(long)]
+    pub fasta_sidecar: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct BackfillArgs {
+    /// Existing output: Parquet file, or directory of .parquet files (swarm or dataset output)
+    pub output: PathBuf,
+
+    /// UniProt XML the output was built from (`.xml` or `.xml.gz`), or a directory of them
+    #[arg(long)]
+    pub input: PathBuf,
+
+    /// Built-in columns to compute (comma-separated); columns the output already has are replaced
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub columns: Vec<String>,
+
+    /// Path to isoform sidecar FASTA (varsplic.fasta, unzipped); needed for isoform rows
+    #[arg(long)]
+    pub fasta_sidecar: Option<PathBuf>,
+
+    /// UniProt release written to the `release` column, if backfilled
+    #[arg(long)]
+    pub release: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct DiffReleasesArgs {
+    /// Older release: Parquet file, or directory of .parquet files (swarm output)
+    pub old: PathBuf,
+
+    /// Newer release, same forms as `old`
+    pub new: PathBuf,
+
+    /// Detailed change Parquet (one row per added, removed or modified id)
+    #[arg(long, default_value = "release_changes.parquet")]
+    pub changes: PathBuf,
+
+    /// Summary format
+    #[arg(long, value_enum, default_value_t = StatsFormat::Yaml)]
+    pub format: StatsFormat,
+
+    /// Write the summary to this file instead of stdout
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExportNetworkArgs {
+    /// Parquet file, or directory of .parquet files (swarm output)
+    pub path: PathBuf,
+
+    /// Network file to write
+    #[arg(long, default_value = "interactions.tsv")]
+    pub out: PathBuf,
+
+    /// Network format
+    #[arg(long, value_enum, default_value_t = NetworkFormat::Tsv)]
+    pub format: NetworkFormat,
+
+    /// Merge reciprocal edges (A-B listed under A and B-A under B) into one undirected edge
+    #[arg(long)]
+    pub undirected: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct FetchProteomeArgs {
+    /// Proteome identifier, e.g. UP000005640 (human)
+    pub proteome_id: String,
+
+    /// Directory for the output Parquet (`<proteome_id>.parquet`)
+    #[arg(long, default_value = "data/parquet")]
+    pub output_dir: PathBuf,
+
+    /// Skip the isoform FASTA (rows for isoforms then fail without a configured sidecar)
+    #[arg(long)]
+    pub no_isoforms: bool,
+
+    /// Download again even if verified files are cached
+    #[arg(long)]
+    pub refresh: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsFormat {
+    Yaml,
+    Json,
 }
@@ -0,0 +1,339 @@
+//! Dataset card written next to the output of a successful run (`storage.dataset_card`).
+//!
+//! Data governance asks for a summary of every published dataset: row counts per
+//! organism, feature and PTM statistics, the schema, the UniProt release and the
+//! parameters the dataset was generated with. The card is written twice, as Markdown
+//! for people and JSON for catalogs: `_<stem>.card.md` / `_<stem>.card.json` next to a
+//! single output file, or `_dataset_card.md` / `_dataset_card.json` inside an output
+//! directory. The leading `_` keeps dataset readers from treating them as data.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::{Settings, TransformConfig};
+use crate::lineage;
+use crate::pipeline::parser::EntrySampling;
+use crate::release::ReleaseInfo;
+use crate::report::RunReport;
+use crate::stats::{DatasetStats, FileStats};
+
+/// Columns the card reads back from the output; everything else comes from the run.
+const CARD_COLUMNS: &[&str] = &["parent_id", "organism_id", "organism_name", "features", "ptm_sites"];
+
+/// Organisms listed in the Markdown card; the JSON card lists all of them.
+const MARKDOWN_ORGANISMS: usize = 25;
+
+/// Summary document of one output dataset.
+#[derive(Debug, Clone, Serialize)]
+pub struct DatasetCard {
+    pub generator: String,
+    pub generated_at: DateTime<Utc>,
+    pub run_id: String,
+    pub dataset: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release: Option<ReleaseInfo>,
+    pub rows: u64,
+    pub distinct_parents: u64,
+    pub isoform_ratio: f64,
+    pub total_bytes: u64,
+    pub files: Vec<FileStats>,
+    /// Rows per organism, most rows first
+    pub organisms: Vec<OrganismRows>,
+    pub feature_type_counts: BTreeMap<String, u64>,
+    pub ptm_type_histogram: BTreeMap<String, u64>,
+    pub ptm_mapping: PtmMapping,
+    pub quality_score: f64,
+    pub schema: Vec<ColumnCard>,
+    pub parameters: GenerationParameters,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrganismRows {
+    /// NCBI TaxID, or "unknown"
+    pub taxon_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub rows: u64,
+}
+
+/// PTM site mapping outcome of the run (see `performance.ptm_*` in report.yaml).
+#[derive(Debug, Clone, Serialize)]
+pub struct PtmMapping {
+    pub attempted: u64,
+    pub mapped: u64,
+    pub failed: u64,
+}
+
+/// One top-level output column, described from the lineage table.
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnCard {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    pub description: String,
+}
+
+/// Settings that decide what ends up in the dataset.
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationParameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fasta_sidecar: Option<PathBuf>,
+    pub schema_profile: &'static str,
+    pub batch_size: usize,
+    pub zstd_level: u32,
+    pub sort_by_parent_id: bool,
+    pub transform: TransformConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<EntrySampling>,
+}
+
+impl DatasetCard {
+    /// Builds the card of `output` (a Parquet file or directory) from the run's report and settings.
+    pub fn build(output: &Path, report: &RunReport, settings: &Settings) -> Result<Self> {
+        let stats = DatasetStats::collect_columns(output, Some(CARD_COLUMNS))?;
+
+        let mut organisms: Vec<OrganismRows> = stats
+            .organism_counts
+            .iter()
+            .map(|(taxon_id, &rows)| OrganismRows {
+                taxon_id: taxon_id.clone(),
+                name: stats.organism_names.get(taxon_id).cloned(),
+                rows,
+            })
+            .collect();
+        organisms.sort_by(|a, b| b.rows.cmp(&a.rows).then_with(|| a.taxon_id.cmp(&b.taxon_id)));
+
+        let schema = lineage::manifest(settings.schema.profile)
+            .fields
+            .into_iter()
+            .filter(|field| !field.field.contains('.'))
+            .map(|field| ColumnCard {
+                name: field.field,
+                data_type: field.data_type,
+                nullable: field.nullable,
+                description: field.derivation,
+            })
+            .collect();
+
+        let performance = &report.performance;
+        Ok(Self {
+            generator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            generated_at: Utc::now(),
+            run_id: report.run_id.clone(),
+            dataset: output.to_path_buf(),
+            release: report.release.clone(),
+            rows: stats.rows,
+            distinct_parents: stats.distinct_parents,
+            isoform_ratio: stats.isoform_ratio,
+            total_bytes: stats.total_bytes,
+            files: stats.files,
+            organisms,
+            feature_type_counts: stats.feature_type_counts,
+            ptm_type_histogram: stats.ptm_type_histogram,
+            ptm_mapping: PtmMapping {
+                attempted: performance.ptm_attempted,
+                mapped: performance.ptm_mapped,
+                failed: performance.ptm_failed,
+            },
+            quality_score: report.quality.score,
+            schema,
+            parameters: GenerationParameters {
+                input: settings.storage.input_path.clone(),
+                fasta_sidecar: settings.storage.fasta_sidecar_path.clone(),
+                schema_profile: settings.schema.profile.name(),
+                batch_size: settings.performance.batch_size,
+                zstd_level: settings.performance.zstd_level,
+                sort_by_parent_id: settings.storage.sort_by_parent_id,
+                transform: settings.transform.clone(),
+                sampling: report.sampling,
+            },
+        })
+    }
+
+    /// Writes the Markdown and JSON card next to `output`; returns both paths.
+    pub fn write(&self, output: &Path) -> Result<(PathBuf, PathBuf)> {
+        let (markdown_path, json_path) = card_paths(output);
+        fs::write(&markdown_path, self.to_markdown())
+            .with_context(|| format!("Failed to write dataset card {}", markdown_path.display()))?;
+        let json = serde_json::to_string_pretty(self)? + "\n";
+        fs::write(&json_path, json).with_context(|| format!("Failed to write dataset card {}", json_path.display()))?;
+        Ok((markdown_path, json_path))
+    }
+
+    /// The card as a Markdown document.
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let name = self
+            .dataset
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.dataset.display().to_string());
+        let _ = writeln!(md, "# Dataset card: {name}\n");
+        let _ = writeln!(md, "| | |\n|---|---|");
+        let _ = writeln!(md, "| Generated | {} by {} |", self.generated_at.format("%Y-%m-%d %H:%M:%S UTC"), self.generator);
+        let _ = writeln!(md, "| Run | `{}` |", self.run_id);
+        let release = self
+            .release
+            .as_ref()
+            .map(|r| format!("{} (from {})", r.version, r.source))
+            .unwrap_or_else(|| "unknown".to_string());
+        let _ = writeln!(md, "| UniProt release | {release} |");
+        let _ = writeln!(md, "| Rows | {} |", self.rows);
+        let _ = writeln!(md, "| Distinct entries (parent_id) | {} |", self.distinct_parents);
+        let _ = writeln!(md, "| Rows per entry | {:.3} |", self.isoform_ratio);
+        let _ = writeln!(md, "| Files | {} ({:.1} MB) |", self.files.len(), self.total_bytes as f64 / (1024.0 * 1024.0));
+        let _ = writeln!(md, "| Quality score | {:.3} |", self.quality_score);
+
+        let _ = writeln!(md, "\n## Rows per organism\n");
+        if self.organisms.is_empty() {
+            md.push_str("No `organism_id` column in this dataset.\n");
+        } else {
+            let _ = writeln!(md, "| TaxID | Organism | Rows |\n|---|---|---:|");
+            for organism in self.organisms.iter().take(MARKDOWN_ORGANISMS) {
+                let _ = writeln!(
+                    md,
+                    "| {} | {} | {} |",
+                    organism.taxon_id,
+                    organism.name.as_deref().unwrap_or(""),
+                    organism.rows
+                );
+            }
+            if self.organisms.len() > MARKDOWN_ORGANISMS {
+                let _ = writeln!(
+                    md,
+                    "\n{} more organisms are listed in the JSON card.",
+                    self.organisms.len() - MARKDOWN_ORGANISMS
+                );
+            }
+        }
+
+        let _ = writeln!(md, "\n## Features\n");
+        write_counts(&mut md, "Feature type", &self.feature_type_counts);
+
+        let _ = writeln!(md, "\n## PTMs\n");
+        let mapping = &self.ptm_mapping;
+        let _ = writeln!(
+            md,
+            "{} of {} PTM sites mapped to their row's sequence, {} failed.\n",
+            mapping.mapped, mapping.attempted, mapping.failed
+        );
+        write_counts(&mut md, "Modification type", &self.ptm_type_histogram);
+
+        let _ = writeln!(md, "\n## Schema\n");
+        let _ = writeln!(md, "| Column | Type | Nullable | Description |\n|---|---|---|---|");
+        for column in &self.schema {
+            let _ = writeln!(
+                md,
+                "| `{}` | {} | {} | {} |",
+                column.name,
+                column.data_type,
+                if column.nullable { "yes" } else { "no" },
+                column.description.replace('|', "\\|")
+            );
+        }
+
+        let _ = writeln!(md, "\n## Generation parameters\n");
+        let parameters = serde_yaml::to_string(&self.parameters).unwrap_or_default();
+        let _ = writeln!(md, "```yaml\n{}```", parameters);
+        md
+    }
+}
+
+fn write_counts(md: &mut String, label: &str, counts: &BTreeMap<String, u64>) {
+    if counts.is_empty() {
+        md.push_str("None in this dataset.\n");
+        return;
+    }
+    let mut sorted: Vec<(&String, &u64)> = counts.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let _ = writeln!(md, "| {label} | Count |\n|---|---:|");
+    for (name, count) in sorted {
+        let _ = writeln!(md, "| {name} | {count} |");
+    }
+}
+
+/// Markdown and JSON card paths for `output`.
+pub fn card_paths(output: &Path) -> (PathBuf, PathBuf) {
+    if output.is_dir() {
+        return (output.join("_dataset_card.md"), output.join("_dataset_card.json"));
+    }
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    (
+        output.with_file_name(format!("_{stem}.card.md")),
+        output.with_file_name(format!("_{stem}.card.json")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::pipeline::batcher::Batcher;
+    use crate::pipeline::scratch::ParsedEntry;
+    use crate::pipeline::transformer::TransformedRow;
+    use crate::report::RunStatus;
+    use crate::runs::RunContext;
+    use crate::sampler::{ChannelStats, ResourceSampler};
+    use crate::testing::canonical_row;
+    use crate::writer::parquet::write_batches;
+    use crossbeam_channel::unbounded;
+    use std::sync::Arc;
+
+    fn row(accession: &str, organism_id: i32) -> TransformedRow {
+        canonical_row(ParsedEntry {
+            accession: accession.to_string(),
+            parent_id: accession.to_string(),
+            sequence: "MSAK".to_string(),
+            organism_id: Some(organism_id),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn writes_markdown_and_json_cards_next_to_the_output() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_dataset_card");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("human.parquet");
+
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        let mut batcher = Batcher::with_batch_size(tx, metrics.clone(), 100);
+        for (accession, organism_id) in [("P1", 9606), ("P2", 9606), ("Q1", 10090)] {
+            batcher.add_row(row(accession, organism_id)).unwrap();
+        }
+        batcher.finish().unwrap();
+        let settings = Settings::default();
+        write_batches(rx, &output, &metrics, &settings).unwrap();
+
+        let run = RunContext::new(&dir.join("runs")).unwrap();
+        let mut sampler = ResourceSampler::start(Arc::new(ChannelStats::new(4)), metrics.clone());
+        sampler.stop();
+        let report = RunReport::generate(&run, &metrics, &sampler, RunStatus::Success);
+
+        let card = DatasetCard::build(&output, &report, &settings).unwrap();
+        assert_eq!(card.rows, 3);
+        assert_eq!(card.organisms[0].taxon_id, "9606");
+        assert_eq!(card.organisms[0].rows, 2);
+        assert!(card.schema.iter().any(|column| column.name == "organism_id" && !column.description.is_empty()));
+
+        let (markdown, json) = card.write(&output).unwrap();
+        assert_eq!(markdown, dir.join("_human.card.md"));
+        assert!(fs::read_to_string(&markdown).unwrap().contains("| 10090 |  | 1 |"));
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(json).unwrap()).unwrap();
+        assert_eq!(json["parameters"]["schema_profile"], "full");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
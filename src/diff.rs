@@ -0,0 +1,374 @@
+//! Release-over-release comparison of two Parquet outputs (`uniprot_etl diff-releases`).
+//!
+//! Rows are matched on `id`. The summary counts added/removed rows, sequence changes,
+//! PTM site gains and losses per mod_type (a site is a `(site_index, mod_type)` pair)
+//! and per-feature-type count deltas; every row that differs also gets a row in the
+//! detailed change Parquet.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{Array, ArrayRef, BooleanBuilder, Int32Array, Int32Builder, ListArray, StringBuilder, StructArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+
+use crate::pipeline::builders::DictStringBuilder;
+use crate::schema::dict_utf8;
+use crate::stats::{mod_type_label, parquet_files, utf8};
+
+/// Rows per batch of the change Parquet.
+const CHANGE_BATCH_ROWS: usize = 8192;
+
+/// Columns read from each side.
+const DIFF_COLUMNS: [&str; 5] = ["id", "parent_id", "sequence", "features", "ptm_sites"];
+
+/// What changed between two releases.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReleaseDiff {
+    pub old_rows: u64,
+    pub new_rows: u64,
+    pub added: u64,
+    pub removed: u64,
+    /// Rows present in both releases with any difference
+    pub modified: u64,
+    pub sequence_changed: u64,
+    pub ptm_sites_gained: BTreeMap<String, u64>,
+    pub ptm_sites_lost: BTreeMap<String, u64>,
+    /// New minus old `features` count, per feature_type
+    pub feature_count_deltas: BTreeMap<String, i64>,
+}
+
+/// The compared state of one row.
+struct RowSnapshot {
+    parent_id: String,
+    sequence_hash: u64,
+    features: i32,
+    ptm_sites: BTreeSet<(i32, i32)>,
+}
+
+/// All rows of one release, keyed by `id`.
+#[derive(Default)]
+struct Snapshot {
+    rows: HashMap<String, RowSnapshot>,
+    feature_types: BTreeMap<String, i64>,
+}
+
+/// Schema of the change Parquet: one row per added, removed or modified `id`.
+pub fn change_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("parent_id", DataType::Utf8, false),
+        // added | removed | modified
+        Field::new("change", dict_utf8(), false),
+        Field::new("sequence_changed", DataType::Boolean, false),
+        Field::new("features_old", DataType::Int32, true),
+        Field::new("features_new", DataType::Int32, true),
+        Field::new("ptm_sites_gained", DataType::Int32, false),
+        Field::new("ptm_sites_lost", DataType::Int32, false),
+    ]))
+}
+
+/// Compares `old` and `new` (Parquet files or swarm output directories), writing
+/// per-row changes to `changes` and returning the summary.
+pub fn diff_releases(old: &Path, new: &Path, changes: &Path) -> Result<ReleaseDiff> {
+    let old = Snapshot::load(old)?;
+    let new = Snapshot::load(new)?;
+
+    let mut diff = ReleaseDiff {
+        old_rows: old.rows.len() as u64,
+        new_rows: new.rows.len() as u64,
+        ..Default::default()
+    };
+    for (feature_type, count) in &new.feature_types {
+        *diff.feature_count_deltas.entry(feature_type.clone()).or_default() += count;
+    }
+    for (feature_type, count) in &old.feature_types {
+        *diff.feature_count_deltas.entry(feature_type.clone()).or_default() -= count;
+    }
+    diff.feature_count_deltas.retain(|_, delta| *delta != 0);
+
+    let mut ids: Vec<&String> = old.rows.keys().chain(new.rows.keys()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let props = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let file = File::create(changes).with_context(|| format!("Failed to create {}", changes.display()))?;
+    let mut writer = ArrowWriter::try_new(file, change_schema(), Some(props))?;
+    let mut batch = ChangeBatch::default();
+
+    for id in ids {
+        let (before, after) = (old.rows.get(id), new.rows.get(id));
+        let change = match (before, after) {
+            (None, Some(_)) => {
+                diff.added += 1;
+                "added"
+            }
+            (Some(_), None) => {
+                diff.removed += 1;
+                "removed"
+            }
+            (Some(b), Some(a)) => {
+                if b.sequence_hash == a.sequence_hash && b.features == a.features && b.ptm_sites == a.ptm_sites {
+                    continue;
+                }
+                diff.modified += 1;
+                "modified"
+            }
+            (None, None) => unreachable!("id comes from one of the snapshots"),
+        };
+
+        let empty = BTreeSet::new();
+        let old_sites = before.map_or(&empty, |r| &r.ptm_sites);
+        let new_sites = after.map_or(&empty, |r| &r.ptm_sites);
+        let gained = count_by_mod_type(new_sites.difference(old_sites), &mut diff.ptm_sites_gained);
+        let lost = count_by_mod_type(old_sites.difference(new_sites), &mut diff.ptm_sites_lost);
+        let sequence_changed = matches!((before, after), (Some(b), Some(a)) if b.sequence_hash != a.sequence_hash);
+        if sequence_changed {
+            diff.sequence_changed += 1;
+        }
+
+        let parent_id = after.or(before).map(|r| r.parent_id.as_str()).unwrap_or_default();
+        batch.id.append_value(id);
+        batch.parent_id.append_value(parent_id);
+        batch.change.append_value(change);
+        batch.sequence_changed.append_value(sequence_changed);
+        batch.features_old.append_option(before.map(|r| r.features));
+        batch.features_new.append_option(after.map(|r| r.features));
+        batch.ptm_sites_gained.append_value(gained);
+        batch.ptm_sites_lost.append_value(lost);
+        batch.rows += 1;
+        if batch.rows == CHANGE_BATCH_ROWS {
+            writer.write(&batch.finish()?)?;
+        }
+    }
+    if batch.rows > 0 {
+        writer.write(&batch.finish()?)?;
+    }
+    writer.close()?;
+
+    Ok(diff)
+}
+
+/// Tallies `sites` into `counts` by mod_type label; returns how many there were.
+fn count_by_mod_type<'a>(
+    sites: impl Iterator<Item = &'a (i32, i32)>,
+    counts: &mut BTreeMap<String, u64>,
+) -> i32 {
+    let mut total = 0;
+    for &(_, mod_type) in sites {
+        *counts.entry(mod_type_label(mod_type).to_string()).or_default() += 1;
+        total += 1;
+    }
+    total
+}
+
+impl Snapshot {
+    fn load(path: &Path) -> Result<Self> {
+        let files = parquet_files(path)?;
+        if files.is_empty() {
+            return Err(anyhow!("No Parquet files found at {}", path.display()));
+        }
+        let mut snapshot = Snapshot::default();
+        for file in files {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(
+                File::open(&file).with_context(|| format!("Failed to open {}", file.display()))?,
+            )?;
+            let roots = DIFF_COLUMNS
+                .iter()
+                .map(|name| builder.schema().index_of(name))
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("{} is not a uniprot_etl output", file.display()))?;
+            let projection = ProjectionMask::roots(builder.parquet_schema(), roots);
+            for batch in builder.with_projection(projection).build()? {
+                snapshot.add_batch(&batch?)?;
+            }
+        }
+        Ok(snapshot)
+    }
+
+    fn add_batch(&mut self, batch: &RecordBatch) -> Result<()> {
+        let column = |name: &str| batch.column_by_name(name).ok_or_else(|| anyhow!("missing column {}", name));
+        let ids = utf8(column("id")?.as_ref())?;
+        let parent_ids = utf8(column("parent_id")?.as_ref())?;
+        let sequences = utf8(column("sequence")?.as_ref())?;
+        let features = list(column("features")?)?;
+        let ptm_sites = list(column("ptm_sites")?)?;
+
+        if let Some(types) = struct_values(features)?.column_by_name("feature_type") {
+            let types = utf8(types.as_ref())?;
+            for i in 0..types.len() {
+                if types.is_valid(i) {
+                    *self.feature_types.entry(types.value(i).to_string()).or_default() += 1;
+                }
+            }
+        }
+        let sites = struct_values(ptm_sites)?;
+        let site_index = sites
+            .column_by_name("site_index")
+            .and_then(|c| c.as_any().downcast_ref::<Int32Array>())
+            .ok_or_else(|| anyhow!("ptm_sites is missing site_index"))?;
+        let modifications = sites
+            .column_by_name("modifications")
+            .ok_or_else(|| anyhow!("ptm_sites is missing modifications"))
+            .and_then(list)?;
+        let mod_types = struct_values(modifications)?
+            .column_by_name("mod_type")
+            .and_then(|c| c.as_any().downcast_ref::<Int32Array>())
+            .ok_or_else(|| anyhow!("modifications is missing mod_type"))?
+            .clone();
+
+        for row in 0..batch.num_rows() {
+            let mut hasher = DefaultHasher::new();
+            sequences.value(row).hash(&mut hasher);
+            let mut row_sites = BTreeSet::new();
+            for site in list_range(ptm_sites, row) {
+                for m in list_range(modifications, site) {
+                    row_sites.insert((site_index.value(site), mod_types.value(m)));
+                }
+            }
+            self.rows.insert(
+                ids.value(row).to_string(),
+                RowSnapshot {
+                    parent_id: parent_ids.value(row).to_string(),
+                    sequence_hash: hasher.finish(),
+                    features: list_range(features, row).len() as i32,
+                    ptm_sites: row_sites,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+fn list(column: &ArrayRef) -> Result<&ListArray> {
+    column
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .ok_or_else(|| anyhow!("expected a list column"))
+}
+
+/// The struct values of a `List<Struct>` column.
+fn struct_values(list: &ListArray) -> Result<&StructArray> {
+    list.values()
+        .as_any()
+        .downcast_ref::<StructArray>()
+        .ok_or_else(|| anyhow!("expected a list of structs"))
+}
+
+/// Value indices of list slot `row` (empty for null lists).
+fn list_range(list: &ListArray, row: usize) -> std::ops::Range<usize> {
+    let offsets = list.value_offsets();
+    offsets[row] as usize..offsets[row + 1] as usize
+}
+
+/// Builders of the change Parquet's columns.
+#[derive(Default)]
+struct ChangeBatch {
+    rows: usize,
+    id: StringBuilder,
+    parent_id: StringBuilder,
+    change: DictStringBuilder,
+    sequence_changed: BooleanBuilder,
+    features_old: Int32Builder,
+    features_new: Int32Builder,
+    ptm_sites_gained: Int32Builder,
+    ptm_sites_lost: Int32Builder,
+}
+
+impl ChangeBatch {
+    fn finish(&mut self) -> Result<RecordBatch> {
+        self.rows = 0;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.id.finish()),
+            Arc::new(self.parent_id.finish()),
+            Arc::new(self.change.finish()),
+            Arc::new(self.sequence_changed.finish()),
+            Arc::new(self.features_old.finish()),
+            Arc::new(self.features_new.finish()),
+            Arc::new(self.ptm_sites_gained.finish()),
+            Arc::new(self.ptm_sites_lost.finish()),
+        ];
+        Ok(RecordBatch::try_new(change_schema(), columns)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use crate::writer::parquet::write_batches;
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+    use std::fs;
+
+    const PHOSPHO: &str = r#"<feature type="modified residue" description="Phosphoserine"><location><position position="2"/></location></feature>"#;
+
+    fn write_release(path: &Path, entries: &[(&str, &str, &str)]) {
+        let entries: String = entries
+            .iter()
+            .map(|(accession, sequence, features)| {
+                format!(
+                    "<entry><accession>{}</accession>{}<sequence length=\"{}\">{}</sequence></entry>",
+                    accession,
+                    features,
+                    sequence.len(),
+                    sequence
+                )
+            })
+            .collect();
+        let xml = format!("<uniprot>{}</uniprot>", entries);
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        parse_entries(Reader::from_reader(xml.as_bytes()), tx, &metrics, 10, None).unwrap();
+        write_batches(rx, path, &metrics, &Settings::default()).unwrap();
+    }
+
+    #[test]
+    fn test_diff_releases() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_diff");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let (old, new, changes) = (dir.join("old.parquet"), dir.join("new.parquet"), dir.join("changes.parquet"));
+
+        write_release(&old, &[("P1", "MSAK", PHOSPHO), ("P2", "MSAK", ""), ("P3", "MK", "")]);
+        write_release(&new, &[("P1", "MSAK", ""), ("P2", "MSAKK", PHOSPHO), ("P4", "MK", "")]);
+
+        let diff = diff_releases(&old, &new, &changes).unwrap();
+        assert_eq!((diff.old_rows, diff.new_rows), (3, 3));
+        assert_eq!((diff.added, diff.removed, diff.modified), (1, 1, 2));
+        assert_eq!(diff.sequence_changed, 1);
+        assert_eq!(diff.ptm_sites_gained.get("phosphorylation"), Some(&1));
+        assert_eq!(diff.ptm_sites_lost.get("phosphorylation"), Some(&1));
+        // One modified residue moved from P1 to P2: no net change per type
+        assert!(diff.feature_count_deltas.is_empty());
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&changes).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(|b| b.unwrap()).collect();
+        let ids = utf8(batches[0].column(0).as_ref()).unwrap();
+        let kinds = utf8(batches[0].column(2).as_ref()).unwrap();
+        let rows: Vec<(&str, &str)> = (0..ids.len()).map(|i| (ids.value(i), kinds.value(i))).collect();
+        assert_eq!(
+            rows,
+            vec![("P1", "modified"), ("P2", "modified"), ("P3", "removed"), ("P4", "added")]
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -26,6 +26,50 @@ pub enum EtlError {
 
     #[error("Invalid XML attribute: {0}")]
     InvalidAttribute(String),
+
+    #[error("Invalid extra column: {0}")]
+    InvalidExtraColumn(String),
+
+    #[error("Invalid PTM vocabulary: {0}")]
+    InvalidPtmVocabulary(String),
+
+    #[error("Invalid AlphaFold model: {0}")]
+    InvalidPlddt(String),
+
+    #[error("Invalid row filter: {0}")]
+    InvalidRowFilter(String),
+
+    #[error("Unexpected end of XML inside {path} at byte {position}")]
+    UnexpectedEof { path: String, position: u64 },
+
+    #[error("Invalid parse transition from {from} to <{to}> at byte {position}")]
+    InvalidTransition {
+        from: String,
+        to: &'static str,
+        position: u64,
+    },
+
+    #[error("{source} (entry {accession}, in {path}, at byte {position})")]
+    Located {
+        accession: String,
+        path: String,
+        position: u64,
+        #[source]
+        source: Box<EtlError>,
+    },
+
+    #[error("Invalid entry '{accession}': {reason}")]
+    InvalidEntry { accession: String, reason: String },
+
+    #[error("Sequence checksum mismatch for {accession}: expected {expected}, computed {actual}")]
+    ChecksumMismatch {
+        accession: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Invariant violated for row '{id}': {detail}")]
+    InvariantViolation { id: String, detail: String },
 }
 
 pub type Result<T> = std::result::Result<T, EtlError>;
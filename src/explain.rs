@@ -0,0 +1,366 @@
+//! Literate walkthrough of one entry's transformation (`uniprot_etl explain`).
+//!
+//! The entry is located in the input and run through the same parser, transformer and
+//! batch builders as a batch run, with an `EntryTracer` attached. The report then reads
+//! top to bottom like the pipeline: parsed fields, the isoforms and the VSP edits behind
+//! them, where each feature lands on every isoform row, the PTM mapping decisions, and
+//! the rows that would be written. It is meant for curators asking "why does this
+//! protein look like that in the output", so it favours prose over raw dumps.
+
+use std::io::{self, Cursor, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use arrow::array::{Array, ListArray, StringArray};
+use arrow::record_batch::RecordBatch;
+use crossbeam_channel::unbounded;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::config::Settings;
+use crate::fasta::SidecarFasta;
+use crate::metrics::Metrics;
+use crate::pipeline::mapper::{CoordinateMapper, MapFailure};
+use crate::pipeline::parser::{for_each_entry, parse_entries_with_hooks, ParseHooks};
+use crate::pipeline::reader::create_xml_reader;
+use crate::pipeline::scratch::{FeatureScratch, ParsedEntry};
+use crate::pipeline::trace::{capture_element, EntryTracer};
+use crate::pipeline::transformer::canonical_isoform_id;
+
+/// The `<entry>` element of `input` listing `accession` (primary or secondary), as XML.
+pub fn find_entry(input: &Path, accession: &str, settings: &Settings) -> Result<Option<Vec<u8>>> {
+    let mut reader = create_xml_reader(input, settings, &Metrics::new())?;
+    let needle = format!("<accession>{}</accession>", accession);
+    let finder = memchr::memmem::Finder::new(needle.as_bytes());
+    let mut buf = Vec::new();
+    let mut inner = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"entry" => {
+                let chunk = capture_element(&mut reader, &e, &mut inner)?;
+                if finder.find(&chunk).is_some() {
+                    return Ok(Some(chunk));
+                }
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// Writes the walkthrough of the entry in `chunk` (one `<entry>` element) to `out`.
+/// Isoform rows need `sidecar`; without it everything up to the rows is still explained.
+pub fn explain_entry(chunk: &[u8], sidecar: Option<SidecarFasta>, out: &mut impl Write) -> Result<()> {
+    let mut parsed = None;
+    for_each_entry(chunk_reader(chunk), |entry| {
+        parsed = Some(entry);
+        Ok(())
+    })?;
+    let entry = parsed.ok_or_else(|| anyhow!("No <entry> in the captured XML"))?;
+
+    write_fields(&entry, out)?;
+    write_isoforms(&entry, out)?;
+    write_feature_mapping(&entry, out)?;
+
+    // Replay the entry through the real pipeline, tracing its PTM and row decisions
+    let trace = SharedBuffer::default();
+    let hooks = ParseHooks {
+        trace: EntryTracer::to_writer(&entry.accession, trace.clone()),
+        ..ParseHooks::default()
+    };
+    let (tx, rx) = unbounded();
+    let result = parse_entries_with_hooks(chunk_reader(chunk), tx, &Metrics::new(), 1024, sidecar, &hooks);
+    let batches: Vec<RecordBatch> = rx.iter().collect();
+    let trace = trace.text();
+
+    writeln!(out, "\nPTM mapping")?;
+    write_trace_lines(&trace, "ptm", "no PTM features", out)?;
+    writeln!(out, "\nRow decisions")?;
+    write_trace_lines(&trace, "row", "none", out)?;
+
+    match result {
+        Ok(()) => write_rows(&batches, out)?,
+        Err(e) => writeln!(out, "\nRows: not built: {}", e)?,
+    }
+    Ok(())
+}
+
+fn chunk_reader(chunk: &[u8]) -> Reader<Cursor<Vec<u8>>> {
+    let mut reader = Reader::from_reader(Cursor::new(chunk.to_vec()));
+    reader.config_mut().trim_text(true);
+    reader
+}
+
+fn write_fields(entry: &ParsedEntry, out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "Entry {} ({}), {}, version {}",
+        entry.accession,
+        or_dash(&entry.entry_name),
+        or_dash(&entry.dataset),
+        entry.version.map_or_else(|| "-".to_string(), |v| v.to_string())
+    )?;
+    writeln!(out, "\nParsed fields")?;
+    writeln!(out, "  protein_name: {}", or_dash(&entry.protein_name))?;
+    writeln!(out, "  gene_name:    {}", or_dash(&entry.gene_name))?;
+    writeln!(
+        out,
+        "  organism:     {} (taxon {})",
+        or_dash(&entry.organism_scientific_name),
+        entry.organism_id.map_or_else(|| "-".to_string(), |id| id.to_string())
+    )?;
+    writeln!(out, "  existence:    {}", entry.existence)?;
+    writeln!(
+        out,
+        "  sequence:     {} aa{}{}",
+        entry.sequence.len(),
+        entry.fragment.as_deref().map_or_else(String::new, |f| format!(", fragment ({})", f)),
+        entry.checksum.as_deref().map_or_else(String::new, |c| format!(", checksum {}", c))
+    )?;
+    if !entry.ec_numbers.is_empty() {
+        writeln!(out, "  ec_numbers:   {}", entry.ec_numbers.join(", "))?;
+    }
+
+    let features = &entry.features;
+    writeln!(
+        out,
+        "  features:     {} (active_sites={} binding_sites={} metal_coordinations={} mutagenesis_sites={} domains={} natural_variants={})",
+        features.generic.len(),
+        features.active_sites.len(),
+        features.binding_sites.len(),
+        features.metal_coordinations.len(),
+        features.mutagenesis_sites.len(),
+        features.domains.len(),
+        features.natural_variants.len()
+    )?;
+    let comments = &entry.comments;
+    writeln!(
+        out,
+        "  comments:     subcellular_locations={} subunits={} interactions={} other={}",
+        comments.locations.len(),
+        comments.subunits.len(),
+        comments.interactions.len(),
+        comments.others.len()
+    )
+}
+
+fn write_isoforms(entry: &ParsedEntry, out: &mut impl Write) -> io::Result<()> {
+    if entry.isoforms.is_empty() {
+        writeln!(out, "\nIsoforms: none; the entry becomes one canonical row {}", entry.accession)?;
+        return Ok(());
+    }
+    writeln!(out, "\nIsoforms ({})", entry.isoforms.len())?;
+    for iso in &entry.isoforms {
+        let mapper = CoordinateMapper::from_entry_for_vsp_ids(entry, &iso.vsp_ids);
+        writeln!(
+            out,
+            "  {} [{}] names={:?} event={}",
+            canonical_isoform_id(iso),
+            iso.sequence_type.as_deref().unwrap_or("-"),
+            iso.names,
+            iso.event_type.as_deref().unwrap_or("-")
+        )?;
+        if iso.vsp_ids.is_empty() {
+            writeln!(out, "    no VSP edits: canonical coordinates apply unchanged")?;
+            continue;
+        }
+        for vsp_id in &iso.vsp_ids {
+            match entry.features.generic.iter().find(|f| f.id.as_deref() == Some(vsp_id.as_str())) {
+                Some(vsp) => writeln!(
+                    out,
+                    "    {} {}: {} -> {}",
+                    vsp_id,
+                    span(vsp),
+                    vsp.original.as_deref().unwrap_or("(original)"),
+                    vsp.variation.as_deref().or(vsp.description.as_deref()).unwrap_or("Missing")
+                )?,
+                None => writeln!(out, "    {}: no splice variant feature with this id", vsp_id)?,
+            }
+        }
+        writeln!(
+            out,
+            "    edits: [{}], total delta {:+}",
+            mapper.describe_edits(),
+            mapper.total_delta()
+        )?;
+    }
+    Ok(())
+}
+
+fn write_feature_mapping(entry: &ParsedEntry, out: &mut impl Write) -> io::Result<()> {
+    let edited: Vec<_> = entry
+        .isoforms
+        .iter()
+        .filter(|iso| !iso.vsp_ids.is_empty())
+        .map(|iso| (canonical_isoform_id(iso), CoordinateMapper::from_entry_for_vsp_ids(entry, &iso.vsp_ids)))
+        .collect();
+    if edited.is_empty() {
+        writeln!(out, "\nFeature mapping: every row uses canonical coordinates")?;
+        return Ok(());
+    }
+
+    let features: Vec<&FeatureScratch> = entry
+        .features
+        .generic
+        .iter()
+        .filter(|f| f.feature_type != "splice variant" && f.feature_type != "variant sequence")
+        .collect();
+    writeln!(out, "\nFeature mapping (canonical -> isoform coordinates)")?;
+    for (isoform_id, mapper) in &edited {
+        writeln!(out, "  {}", isoform_id)?;
+        for feature in &features {
+            let mapped = match (&feature.sequence, feature.start, feature.end) {
+                (Some(sequence), _, _) => format!("located on {}, not mapped", sequence),
+                (None, Some(start), Some(end)) => map_span(mapper, start, end),
+                _ => "no coordinates".to_string(),
+            };
+            writeln!(out, "    {} {}: {}", feature.feature_type, span(feature), mapped)?;
+        }
+    }
+    Ok(())
+}
+
+fn map_span(mapper: &CoordinateMapper, start: i32, end: i32) -> String {
+    match (mapper.map_point_1based(start), mapper.map_point_1based(end)) {
+        (Ok(s), Ok(e)) if s == e => s.to_string(),
+        (Ok(s), Ok(e)) => format!("{}-{}", s, e),
+        (Err(failure), _) => format!("start {}", describe_failure(&failure)),
+        (_, Err(failure)) => format!("end {}", describe_failure(&failure)),
+    }
+}
+
+fn describe_failure(failure: &MapFailure) -> &'static str {
+    match failure {
+        MapFailure::VspDeletionEvent => "is deleted in this isoform",
+        MapFailure::PtmOutOfBounds => "falls outside the isoform",
+        MapFailure::VspUnresolvable => "lies inside a replaced segment (no deterministic position)",
+    }
+}
+
+fn write_trace_lines(trace: &str, section: &str, none: &str, out: &mut impl Write) -> io::Result<()> {
+    let prefix = format!("[{}] ", section);
+    let lines: Vec<&str> = trace.lines().filter_map(|line| line.strip_prefix(&prefix)).collect();
+    if lines.is_empty() {
+        writeln!(out, "  {}", none)?;
+    }
+    for line in lines {
+        writeln!(out, "  {}", line)?;
+    }
+    Ok(())
+}
+
+fn write_rows(batches: &[RecordBatch], out: &mut impl Write) -> Result<()> {
+    let rows: usize = batches.iter().map(RecordBatch::num_rows).sum();
+    writeln!(out, "\nRows emitted ({})", rows)?;
+    for batch in batches {
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+                .ok_or_else(|| anyhow!("Batch has no Utf8 column {}", name))
+        };
+        let (ids, parents, sequences) = (column("id")?, column("parent_id")?, column("sequence")?);
+        let schema = batch.schema();
+        for row in 0..batch.num_rows() {
+            // Non-empty nested lists, e.g. "ptm_sites=3 domains=1"
+            let lists: Vec<String> = schema
+                .fields()
+                .iter()
+                .zip(batch.columns())
+                .filter_map(|(field, column)| {
+                    let list = column.as_any().downcast_ref::<ListArray>()?;
+                    let len = if list.is_null(row) { 0 } else { list.value_length(row) };
+                    (len > 0).then(|| format!("{}={}", field.name(), len))
+                })
+                .collect();
+            writeln!(
+                out,
+                "  {} parent={} sequence={} aa {}",
+                ids.value(row),
+                parents.value(row),
+                sequences.value(row).len(),
+                lists.join(" ")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn span(feature: &FeatureScratch) -> String {
+    match (feature.start, feature.end) {
+        (Some(start), Some(end)) if start == end => start.to_string(),
+        (Some(start), Some(end)) => format!("{}-{}", start, end),
+        _ => "?".to_string(),
+    }
+}
+
+fn or_dash(value: &Option<String>) -> &str {
+    value.as_deref().unwrap_or("-")
+}
+
+/// Trace sink the report reads back once the pipeline is done.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{sidecar, uniprot_xml, EntryXml, FeatureXml};
+
+    #[test]
+    fn explains_isoform_edits_feature_mapping_and_rows() {
+        let xml = uniprot_xml(&[
+            EntryXml::new("P2", "MAAAA"),
+            EntryXml::new("P1", "MSKTAYLLPE")
+                .name("TEST_HUMAN")
+                .displayed_isoform("P1-1")
+                .described_isoform("P1-2", "VSP_1")
+                .feature(FeatureXml::span("splice variant", 2, 4).id("VSP_1").change("SKT", "Missing"))
+                .feature(FeatureXml::at("modified residue", 3).description("Phosphoserine"))
+                .feature(FeatureXml::at("modified residue", 8).description("Phosphoserine")),
+        ]);
+        let path = std::env::temp_dir().join(format!("uniprot_etl_explain_{}.xml", std::process::id()));
+        std::fs::write(&path, &xml).unwrap();
+        let chunk = find_entry(&path, "P1", &Settings::default()).unwrap().unwrap();
+        assert!(find_entry(&path, "Q9", &Settings::default()).unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+
+        let fasta = sidecar([("P1-1", "MSKTAYLLPE"), ("P1-2", "MAYLLPE")]);
+        let mut out = Vec::new();
+        explain_entry(&chunk, Some(fasta), &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+
+        assert!(report.starts_with("Entry P1 (TEST_HUMAN)"), "{}", report);
+        assert!(report.contains("edits: [2-4:-3(deletion)], total delta -3"), "{}", report);
+        assert!(report.contains("modified residue 3: start is deleted in this isoform"), "{}", report);
+        assert!(report.contains("modified residue 8: 5"), "{}", report);
+        assert!(report.contains("Rows emitted (2)"), "{}", report);
+        assert!(report.contains("  P1-2 parent=P1 sequence=7 aa"), "{}", report);
+
+        // Without the sidecar the isoform rows cannot be built, but the rest is explained
+        let mut out = Vec::new();
+        explain_entry(&chunk, None, &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+        assert!(report.contains("Feature mapping"), "{}", report);
+        assert!(report.contains("Rows: not built:"), "{}", report);
+    }
+}
@@ -1,68 +1,236 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use memchr::memmem;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::Read;
 use std::path::Path;
+use std::sync::Arc;
 
-/// Loads a FASTA file into a map of accession -> sequence.
+/// Shared isoform accession -> sequence map loaded from the sidecar FASTA.
+pub type SidecarFasta = Arc<HashMap<String, String>>;
+
+/// What to do when two FASTA records normalize to the same accession.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FastaDuplicates {
+    /// Fail the load, naming the accession
+    Error,
+    /// Keep the first record, skip later ones
+    First,
+    /// Keep the last record (later records replace earlier ones)
+    #[default]
+    Last,
+}
+
+/// Record counts of one FASTA load.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FastaLoadStats {
+    /// Records in the map
+    pub loaded: u64,
+    /// Records dropped as repeats of an accession under `first`/`last`
+    pub duplicates: u64,
+    /// Records without an accession or a sequence
+    pub invalid: u64,
+}
+
+impl FastaLoadStats {
+    /// Records read but not in the map.
+    pub fn skipped(&self) -> u64 {
+        self.duplicates + self.invalid
+    }
+}
+
+/// Loads a FASTA file into a map of accession -> sequence, later duplicates winning.
 ///
-/// Header parsing:
-/// - If header is like `>sp|P04637-2|...`, uses `P04637-2`.
-/// - Otherwise uses the first token after `>` up to whitespace.
+/// See [`load_fasta`] for header parsing.
 pub fn load_fasta_map(path: &Path) -> Result<HashMap<String, String>> {
-    let file =
-        File::open(path).with_context(|| format!("Failed to open FASTA: {}", path.display()))?;
-    let reader = BufReader::new(file);
-
-    let mut map: HashMap<String, String> = HashMap::new();
-
-    let mut current_key: Option<String> = None;
-    let mut current_seq = String::new();
-
-    for line in reader.lines() {
-        let line = line?;
-        if line.starts_with('>') {
-            if let Some(key) = current_key.take() {
-                if !current_seq.is_empty() {
-                    map.insert(key, std::mem::take(&mut current_seq));
-                } else {
-                    map.insert(key, String::new());
-                }
-            }
+    load_fasta(path, FastaDuplicates::Last).map(|(map, _)| map)
+}
 
-            let header = line.trim_start_matches('>').trim();
-            let key = parse_fasta_key(header);
-            current_key = Some(key);
-        } else {
-            let part = line.trim();
-            if !part.is_empty() {
-                current_seq.push_str(part);
-            }
+/// Below this size a FASTA file is parsed as a single chunk; also the read window per
+/// rayon thread.
+const MIN_CHUNK_BYTES: usize = 4 * 1024 * 1024;
+
+/// Loads a FASTA file into a map of accession -> sequence.
+///
+/// Each header is reduced to its accession by [`parse_fasta_key`]; records whose
+/// accession repeats are handled by `duplicates`. Records without an accession or
+/// without sequence lines are skipped and counted as invalid.
+///
+/// The file is read in windows of [`MIN_CHUNK_BYTES`] per rayon thread, cut at the
+/// last record boundary, so only one window is held besides the map being built. Each
+/// window is split on record boundaries into one chunk per thread, which are parsed in
+/// parallel. Records are then inserted in file order, so `first`/`last` pick the same
+/// record as a sequential read.
+pub fn load_fasta(path: &Path, duplicates: FastaDuplicates) -> Result<(HashMap<String, String>, FastaLoadStats)> {
+    let file = File::open(path).with_context(|| format!("Failed to open FASTA: {}", path.display()))?;
+    let threads = rayon::current_num_threads();
+    load_windows(file, MIN_CHUNK_BYTES * threads, threads, duplicates)
+        .with_context(|| format!("Invalid FASTA: {}", path.display()))
+}
+
+/// Reads `reader` in windows of about `window` bytes that end on a record boundary
+/// and parses each in up to `chunks` parallel chunks.
+fn load_windows(
+    mut reader: impl Read,
+    window: usize,
+    chunks: usize,
+    duplicates: FastaDuplicates,
+) -> Result<(HashMap<String, String>, FastaLoadStats)> {
+    let mut loader = Loader {
+        map: HashMap::new(),
+        stats: FastaLoadStats::default(),
+        duplicates,
+    };
+    let mut buf = Vec::with_capacity(window);
+    loop {
+        let carried = buf.len();
+        let read = reader.by_ref().take(window as u64).read_to_end(&mut buf)?;
+        let eof = read < window;
+        // Keep the last (possibly incomplete) record for the next window; a record
+        // longer than the window is read on until it ends
+        let end = match memmem::rfind(&buf[carried.saturating_sub(1)..], b"\n>") {
+            _ if eof => buf.len(),
+            Some(offset) => carried.saturating_sub(1) + offset + 1,
+            None => continue,
+        };
+        let chunks = (end / MIN_CHUNK_BYTES).clamp(1, chunks);
+        loader.load(&buf[..end], chunks)?;
+        if eof {
+            break;
         }
+        buf.drain(..end);
     }
+    loader.stats.loaded = loader.map.len() as u64;
+    Ok((loader.map, loader.stats))
+}
 
-    if let Some(key) = current_key.take() {
-        map.insert(key, current_seq);
+#[cfg(test)]
+fn parse_fasta(
+    data: &[u8],
+    chunks: usize,
+    duplicates: FastaDuplicates,
+) -> Result<(HashMap<String, String>, FastaLoadStats)> {
+    let mut loader = Loader {
+        map: HashMap::new(),
+        stats: FastaLoadStats::default(),
+        duplicates,
+    };
+    loader.load(data, chunks)?;
+    loader.stats.loaded = loader.map.len() as u64;
+    Ok((loader.map, loader.stats))
+}
+
+/// Splits `data` into at most `chunks` slices of roughly equal size, each after the
+/// first starting at a `>` header line.
+fn record_chunks(data: &[u8], chunks: usize) -> Vec<&[u8]> {
+    let mut bounds = vec![0];
+    for i in 1..chunks {
+        let from = (data.len() * i / chunks).max(bounds[bounds.len() - 1]);
+        match memmem::find(&data[from..], b"\n>") {
+            Some(offset) => bounds.push(from + offset + 1),
+            None => break,
+        }
     }
+    bounds.push(data.len());
+    bounds.windows(2).map(|w| &data[w[0]..w[1]]).collect()
+}
 
-    Ok(map)
+/// (accession, sequence) of every record in `chunk`, in order. Lines before the first
+/// header belong to no record and are ignored.
+fn parse_records(chunk: &[u8]) -> Result<Vec<(String, String)>> {
+    let text = std::str::from_utf8(chunk).context("FASTA is not valid UTF-8")?;
+    let mut records = Vec::new();
+    let mut current: Option<(String, String)> = None;
+    for line in text.lines() {
+        if let Some(header) = line.strip_prefix('>') {
+            records.extend(current.replace((parse_fasta_key(header), String::new())));
+        } else if let Some((_, sequence)) = current.as_mut() {
+            sequence.push_str(line.trim());
+        }
+    }
+    records.extend(current);
+    Ok(records)
 }
 
-fn parse_fasta_key(header: &str) -> String {
-    // Prefer UniProt pipe format.
-    // Examples: `sp|P04637-2|...`, `tr|Q9TEST-1|...`
-    let first_token = header.split_whitespace().next().unwrap_or(header);
-    let mut parts = first_token.split('|');
-    let p0 = parts.next();
-    let p1 = parts.next();
-    let p2 = parts.next();
-
-    match (p0, p1, p2) {
-        (Some(_db), Some(acc), Some(_rest)) if !acc.is_empty() => acc.to_string(),
-        _ => first_token.to_string(),
+struct Loader {
+    map: HashMap<String, String>,
+    stats: FastaLoadStats,
+    duplicates: FastaDuplicates,
+}
+
+impl Loader {
+    /// Parses `data` in up to `chunks` parallel chunks and inserts its records in order.
+    fn load(&mut self, data: &[u8], chunks: usize) -> Result<()> {
+        let records = record_chunks(data, chunks)
+            .into_par_iter()
+            .map(parse_records)
+            .collect::<Result<Vec<_>>>()?;
+        self.map.reserve(records.iter().map(Vec::len).sum());
+        for (key, sequence) in records.into_iter().flatten() {
+            self.insert(key, sequence)?;
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, key: String, sequence: String) -> Result<()> {
+        if key.is_empty() || sequence.is_empty() {
+            self.stats.invalid += 1;
+            return Ok(());
+        }
+        match self.map.entry(key) {
+            Entry::Vacant(slot) => {
+                slot.insert(sequence);
+            }
+            Entry::Occupied(mut slot) => match self.duplicates {
+                FastaDuplicates::Error => bail!("duplicate FASTA record for {}", slot.key()),
+                FastaDuplicates::First => self.stats.duplicates += 1,
+                FastaDuplicates::Last => {
+                    slot.insert(sequence);
+                    self.stats.duplicates += 1;
+                }
+            },
+        }
+        Ok(())
     }
 }
 
+/// Accession of a FASTA header (without the leading `>`).
+///
+/// - UniProt pipe headers (`sp|P04637-2|TP53_HUMAN ...`, `tr|Q9TEST-1`) use the
+///   second field.
+/// - Otherwise the first whitespace-delimited token is used (`P04637-2 desc`).
+///
+/// The token is then normalized: a `UniProtKB:` prefix and a trailing `.N` sequence
+/// version are dropped and the accession is upper-cased, so `sp|p04637-2.1|` and
+/// `P04637-2` name the same isoform. Returns an empty string for headers without one.
+pub fn parse_fasta_key(header: &str) -> String {
+    let first_token = header.split_whitespace().next().unwrap_or("");
+    let parts: Vec<&str> = first_token.split('|').collect();
+    let token = match parts.as_slice() {
+        [db, acc, ..] if matches!(db.to_ascii_lowercase().as_str(), "sp" | "tr") => *acc,
+        [_, acc, _, ..] if !acc.is_empty() => *acc,
+        [first, ..] => *first,
+        [] => "",
+    };
+    normalize_accession(token)
+}
+
+fn normalize_accession(token: &str) -> String {
+    let token = token
+        .split_once(':')
+        .filter(|(prefix, _)| prefix.eq_ignore_ascii_case("uniprotkb"))
+        .map_or(token, |(_, rest)| rest);
+    let token = match token.rsplit_once('.') {
+        Some((acc, version)) if !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit()) => acc,
+        _ => token,
+    };
+    token.to_ascii_uppercase()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,4 +245,75 @@ mod tests {
     fn parses_simple_header() {
         assert_eq!(parse_fasta_key("Q9TEST-1 some desc"), "Q9TEST-1");
     }
+
+    #[test]
+    fn normalizes_header_accessions() {
+        assert_eq!(parse_fasta_key("sp|P04637-2"), "P04637-2");
+        assert_eq!(parse_fasta_key(" sp|P04637-2|TP53_HUMAN Isoform 2 of Cellular tumor antigen p53"), "P04637-2");
+        assert_eq!(parse_fasta_key("SP|p04637-2.3|TP53_HUMAN"), "P04637-2");
+        assert_eq!(parse_fasta_key("UniProtKB:P04637-2 desc"), "P04637-2");
+        assert_eq!(parse_fasta_key("   "), "");
+    }
+
+    fn load(contents: &str, duplicates: FastaDuplicates) -> Result<(HashMap<String, String>, FastaLoadStats)> {
+        let path = std::env::temp_dir().join(format!(
+            "uniprot_etl_fasta_{}_{:?}_{}.fasta",
+            std::process::id(),
+            duplicates,
+            contents.len()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        let result = load_fasta(&path, duplicates);
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn duplicate_policy_picks_a_record_or_fails() {
+        let fasta = ">sp|P1-2|A\nMK\nLV\n>sp|P1-2|A\nMKK\n>sp|P2-2|B\n>\nMA\n>P3-2\nMS\n";
+
+        let (map, stats) = load(fasta, FastaDuplicates::First).unwrap();
+        assert_eq!(map["P1-2"], "MKLV");
+        assert_eq!(map["P3-2"], "MS");
+        assert_eq!(stats, FastaLoadStats { loaded: 2, duplicates: 1, invalid: 2 });
+        assert_eq!(stats.skipped(), 3);
+
+        let (map, _) = load(fasta, FastaDuplicates::Last).unwrap();
+        assert_eq!(map["P1-2"], "MKK");
+
+        let err = format!("{:#}", load(fasta, FastaDuplicates::Error).unwrap_err());
+        assert!(err.contains("duplicate FASTA record for P1-2"), "{err}");
+    }
+
+    #[test]
+    fn chunked_parsing_matches_a_single_chunk() {
+        let fasta: String = (0..40)
+            .map(|i| format!(">sp|P{}-2|X desc\r\nMK{}\nLV\n", i % 25, "A".repeat(i)))
+            .collect();
+        let data = format!("stray line\n{fasta}");
+        let expected = parse_fasta(data.as_bytes(), 1, FastaDuplicates::First).unwrap();
+        assert_eq!(expected.1, FastaLoadStats { loaded: 25, duplicates: 15, invalid: 0 });
+        assert_eq!(expected.0["P3-2"], "MKAAALV");
+
+        for chunks in [2, 3, 7, 64] {
+            let split = record_chunks(data.as_bytes(), chunks);
+            assert!(split.len() <= chunks && split[1..].iter().all(|c| c.starts_with(b">")));
+            assert_eq!(split.concat(), data.as_bytes());
+            assert_eq!(parse_fasta(data.as_bytes(), chunks, FastaDuplicates::First).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn windowed_reading_matches_a_single_read() {
+        let fasta: String = (0..40)
+            .map(|i| format!(">sp|P{}-2|X desc\nMK{}\nLV\n", i % 25, "A".repeat(i * 3)))
+            .collect();
+        let data = format!("stray line\n{fasta}");
+        let expected = parse_fasta(data.as_bytes(), 1, FastaDuplicates::Last).unwrap();
+        // Windows shorter than a record, around a record, and larger than the file
+        for window in [1, 7, 30, 64, 1 << 20] {
+            let loaded = load_windows(data.as_bytes(), window, 4, FastaDuplicates::Last).unwrap();
+            assert_eq!(loaded, expected, "window {window}");
+        }
+    }
 }
@@ -1,10 +1,35 @@
+pub mod archive;
+pub mod backfill;
 pub mod cli;
 pub mod config;
+pub mod dataset_card;
+pub mod diff;
 pub mod error;
+pub mod explain;
+pub mod fasta;
+pub mod lineage;
+pub mod logging;
 pub mod metrics;
+pub mod network;
+pub mod paths;
 pub mod pipeline;
+pub mod preflight;
+pub mod profiling;
+pub mod proteome;
+pub mod ptm_fixtures;
+pub mod quality;
+pub mod release;
 pub mod report;
+pub mod report_html;
+pub mod rest;
 pub mod runs;
 pub mod sampler;
 pub mod schema;
+pub mod serve;
+pub mod shutdown;
+pub mod sidecar;
+pub mod stats;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod watch;
 pub mod writer;
@@ -0,0 +1,599 @@
+//! Column-level lineage: which XML paths and which handler produce every output field.
+//!
+//! Each run writes `runs/<run_id>/lineage.json` for data catalogs. The field list, types
+//! and nullability are walked from `schema::create_uniprot_schema`, and each field is
+//! joined with its entry in `sources()`. A unit test fails when a schema field has no
+//! source or a source names a field that no longer exists, so a new column cannot ship
+//! without its lineage.
+//!
+//! Paths are relative to `/uniprot/entry`; nested fields are dotted with list items
+//! elided (`ptm_sites.modifications.mod_type`).
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use arrow::datatypes::{DataType, Field};
+use serde::Serialize;
+
+use crate::schema::{create_uniprot_schema, SchemaProfile};
+
+/// Lineage of one output field.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldLineage {
+    pub field: String,
+    pub data_type: String,
+    pub nullable: bool,
+    /// XML paths read (empty for fields derived from run inputs other than the XML)
+    pub xml_paths: Vec<String>,
+    /// Function that reads the XML or computes the value
+    pub handler: String,
+    pub derivation: String,
+}
+
+/// Lineage of every built-in column of a schema profile, in schema order.
+#[derive(Debug, Clone, Serialize)]
+pub struct LineageManifest {
+    pub generator: String,
+    /// `schema.profile` the fields were selected by
+    pub profile: &'static str,
+    pub fields: Vec<FieldLineage>,
+}
+
+struct Source {
+    field: String,
+    xml_paths: Vec<String>,
+    handler: &'static str,
+    derivation: &'static str,
+}
+
+fn source(field: &str, xml_paths: &[&str], handler: &'static str, derivation: &'static str) -> Source {
+    Source {
+        field: field.to_string(),
+        xml_paths: xml_paths.iter().map(|p| p.to_string()).collect(),
+        handler,
+        derivation,
+    }
+}
+
+/// Builds the manifest of `profile`'s columns; fields without a source are listed with
+/// an empty handler.
+pub fn manifest(profile: SchemaProfile) -> LineageManifest {
+    let mut sources: HashMap<String, Source> = sources().into_iter().map(|s| (s.field.clone(), s)).collect();
+    let mut fields = Vec::new();
+    for (path, field) in schema_fields() {
+        let column = path.split('.').next().unwrap_or_default();
+        if !profile.includes(column) {
+            continue;
+        }
+        let source = sources.remove(&path);
+        fields.push(FieldLineage {
+            data_type: type_name(field.data_type()),
+            nullable: field.is_nullable(),
+            xml_paths: source.as_ref().map(|s| s.xml_paths.clone()).unwrap_or_default(),
+            handler: source.as_ref().map(|s| s.handler.to_string()).unwrap_or_default(),
+            derivation: source.map(|s| s.derivation.to_string()).unwrap_or_default(),
+            field: path,
+        });
+    }
+    LineageManifest {
+        generator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        profile: profile.name(),
+        fields,
+    }
+}
+
+/// Writes `manifest(profile)` as pretty JSON to `path`.
+pub fn write_manifest(path: &Path, profile: SchemaProfile) -> Result<()> {
+    let json = serde_json::to_string_pretty(&manifest(profile))?;
+    fs::write(path, json).with_context(|| format!("Failed to write lineage manifest {}", path.display()))
+}
+
+/// Every field of the schema with its dotted path, parents before children.
+fn schema_fields() -> Vec<(String, Field)> {
+    fn walk(prefix: &str, field: &Field, out: &mut Vec<(String, Field)>) {
+        let path = if prefix.is_empty() {
+            field.name().clone()
+        } else {
+            format!("{}.{}", prefix, field.name())
+        };
+        out.push((path.clone(), field.clone()));
+        let children = match field.data_type() {
+            DataType::List(item) => match item.data_type() {
+                DataType::Struct(children) => children.clone(),
+                _ => return,
+            },
+            DataType::Struct(children) => children.clone(),
+            _ => return,
+        };
+        for child in children.iter() {
+            walk(&path, child, out);
+        }
+    }
+    let schema = create_uniprot_schema();
+    let mut out = Vec::new();
+    for field in schema.fields().iter() {
+        walk("", field, &mut out);
+    }
+    out
+}
+
+/// Compact type name: "utf8", "int32", "dictionary<utf8>", "list<struct>", "list<utf8>".
+fn type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::List(item) => format!("list<{}>", type_name(item.data_type())),
+        DataType::Struct(_) => "struct".to_string(),
+        DataType::Dictionary(_, value) => format!("dictionary<{}>", type_name(value)),
+        other => other.to_string().to_ascii_lowercase(),
+    }
+}
+
+/// Lineage of the `id`/`description`/coordinate/evidence fields shared by the
+/// coordinate-based feature columns.
+fn feature_column(column: &str, feature_type: &str, handler: &'static str) -> Vec<Source> {
+    let feature = format!("feature[@type='{}']", feature_type);
+    let path = |suffix: &str| format!("{}{}", feature, suffix);
+    let field = |name: &str| format!("{}.{}", column, name);
+    let location = [path("/location/position/@position"), path("/location/begin/@position")];
+    let end = [path("/location/position/@position"), path("/location/end/@position")];
+    let status = [path("/location//@status")];
+    let evidence = [path("/@evidence"), "evidence/@type".to_string()];
+    let owned = |field: String, xml_paths: Vec<String>, handler: &'static str, derivation: &'static str| Source {
+        field,
+        xml_paths,
+        handler,
+        derivation,
+    };
+    vec![
+        owned(column.to_string(), vec![feature.clone()], handler, "One item per feature routed here (transform.feature_routes) that maps onto the row"),
+        owned(field("id"), vec![path("/@id")], "pipeline::handlers::features::prepare_feature", "Feature id"),
+        owned(
+            field("description"),
+            vec![path("/@description")],
+            "pipeline::handlers::features::prepare_feature",
+            "Feature description",
+        ),
+        owned(
+            field("start"),
+            location.to_vec(),
+            "pipeline::mapper::CoordinateMapper::map_points",
+            "Canonical begin mapped through the row's VSP edits (isoform-located features: as given)",
+        ),
+        owned(
+            field("end"),
+            end.to_vec(),
+            "pipeline::mapper::CoordinateMapper::map_points",
+            "Canonical end mapped through the row's VSP edits (isoform-located features: as given)",
+        ),
+        owned(
+            field("evidence_code"),
+            evidence.to_vec(),
+            "pipeline::scratch::ParsedEntry::resolve_evidence",
+            "ECO codes of the feature's evidence keys, ';'-joined",
+        ),
+        owned(
+            field("confidence_score"),
+            evidence.to_vec(),
+            "pipeline::scratch::ParsedEntry::max_confidence_for_evidence",
+            "Highest confidence of the feature's ECO codes",
+        ),
+        owned(
+            field("position_status"),
+            status.to_vec(),
+            "pipeline::scratch::FeatureScratch::position_status",
+            "Null when certain; begin..end when the two statuses differ",
+        ),
+        owned(
+            field("mapping_shift"),
+            location.to_vec(),
+            "pipeline::builders::common::FeatureListBuilder::append_features",
+            "Mapped minus canonical start (transform.debug_mapping; null for isoform-located features)",
+        ),
+        owned(
+            field("mapping_failure_code"),
+            location.to_vec(),
+            "pipeline::builders::common::FeatureListBuilder::append_features",
+            "Why the range did not map; start/end are then canonical (transform.debug_mapping)",
+        ),
+    ]
+}
+
+/// Where every built-in field comes from. Keep in step with the handlers; the tests
+/// check it against the schema.
+fn sources() -> Vec<Source> {
+    let mut sources = vec![
+        source(
+            "id",
+            &["accession[1]", "comment[@type='alternative products']/isoform/id"],
+            "pipeline::transformer::EntryTransformer::transform_shared",
+            "Primary accession for entries without isoforms, otherwise the isoform id (canonical_isoform_id)",
+        ),
+        source(
+            "sequence",
+            &["sequence"],
+            "pipeline::handlers::metadata::handle_sequence",
+            "Canonical sequence without whitespace; isoform rows take the sidecar FASTA sequence instead (null when missing from it and kept by transform.keep_unsequenced_isoforms)",
+        ),
+        source(
+            "organism_id",
+            &["organism/dbReference[@type='NCBI Taxonomy']/@id"],
+            "pipeline::handlers::metadata::handle_organism_db_reference",
+            "NCBI taxonomy id",
+        ),
+        source(
+            "isoforms",
+            &["comment[@type='alternative products']/isoform"],
+            "pipeline::handlers::comments::consume_isoform",
+            "Every isoform of the entry, repeated on each row",
+        ),
+        source("isoforms.isoform_id", &["comment[@type='alternative products']/isoform/id"], "pipeline::handlers::comments::consume_isoform", "Isoform id as written"),
+        source(
+            "isoforms.isoform_sequence",
+            &["comment[@type='alternative products']/isoform/sequence/@ref"],
+            "pipeline::handlers::comments::capture_isoform_sequence",
+            "Non-VSP sequence reference (e.g. an external isoform accession)",
+        ),
+        source("isoforms.isoform_note", &["comment[@type='alternative products']/isoform/note"], "pipeline::handlers::comments::consume_isoform", "Isoform note text"),
+        source(
+            "isoforms.sequence_type",
+            &["comment[@type='alternative products']/isoform/sequence/@type"],
+            "pipeline::handlers::comments::capture_isoform_sequence",
+            "displayed, described, external or not described",
+        ),
+        source(
+            "isoforms.event_type",
+            &["comment[@type='alternative products']/event/@type"],
+            "pipeline::handlers::comments::consume_isoform_comment",
+            "Event producing the isoform (e.g. alternative splicing)",
+        ),
+        source("features", &["feature"], "pipeline::handlers::features::consume_feature", "Every feature located on the row (canonical coordinates)"),
+        source("features.feature_type", &["feature/@type"], "pipeline::handlers::features::prepare_feature", "Feature key"),
+        source("features.description", &["feature/@description"], "pipeline::handlers::features::prepare_feature", "Feature description"),
+        source(
+            "features.start",
+            &["feature/location/position/@position", "feature/location/begin/@position"],
+            "pipeline::handlers::features::handle_position_tag",
+            "Begin as written (not mapped to isoforms)",
+        ),
+        source(
+            "features.end",
+            &["feature/location/position/@position", "feature/location/end/@position"],
+            "pipeline::handlers::features::handle_position_tag",
+            "End as written (not mapped to isoforms)",
+        ),
+        source(
+            "features.evidence_code",
+            &["feature/@evidence", "evidence/@type"],
+            "pipeline::scratch::ParsedEntry::resolve_evidence",
+            "ECO codes of the feature's evidence keys, ';'-joined",
+        ),
+        source(
+            "features.position_status",
+            &["feature/location//@status"],
+            "pipeline::scratch::FeatureScratch::position_status",
+            "Null when certain; begin..end when the two statuses differ",
+        ),
+        source(
+            "location",
+            &["comment[@type='subcellular location']/subcellularLocation"],
+            "pipeline::handlers::comments::consume_subcellular_location_comment",
+            "One item per <location>; molecule-scoped comments only on the matching row",
+        ),
+        source("location.location", &["comment[@type='subcellular location']/subcellularLocation/location"], "pipeline::handlers::comments::consume_subcellular_location_comment", "Location text"),
+        source(
+            "location.evidence_code",
+            &["comment[@type='subcellular location']/subcellularLocation/location/@evidence", "evidence/@type"],
+            "pipeline::scratch::ParsedEntry::resolve_evidence",
+            "ECO codes of the location's evidence keys, ';'-joined",
+        ),
+        source("location.topology", &["comment[@type='subcellular location']/subcellularLocation/topology"], "pipeline::handlers::comments::consume_subcellular_location_comment", "Applies to every location of the <subcellularLocation>"),
+        source("location.orientation", &["comment[@type='subcellular location']/subcellularLocation/orientation"], "pipeline::handlers::comments::consume_subcellular_location_comment", "Applies to every location of the <subcellularLocation>"),
+        source("location.molecule", &["comment[@type='subcellular location']/molecule"], "pipeline::handlers::comments::consume_subcellular_location_comment", "Isoform/chain the comment is scoped to"),
+        source("entry_name", &["name"], "pipeline::handlers::metadata::handle_entry_name", "UniProt entry name"),
+        source("gene_name", &["gene/name[@type='primary']"], "pipeline::handlers::metadata::consume_gene", "Primary gene name"),
+        source("protein_name", &["protein/recommendedName/fullName"], "pipeline::handlers::metadata::consume_recommended_name", "Recommended full name"),
+        source("organism_name", &["organism/name[@type='scientific']"], "pipeline::handlers::metadata::consume_organism", "Scientific name"),
+        source(
+            "existence",
+            &["protein/proteinExistence/@type", "proteinExistence/@type"],
+            "pipeline::handlers::metadata::handle_protein_existence",
+            "1 (protein level) to 5 (uncertain); null when absent",
+        ),
+        source(
+            "structures",
+            &["dbReference[@type='PDB']", "dbReference[@type='AlphaFoldDB']"],
+            "pipeline::handlers::metadata::handle_entry_db_reference",
+            "PDB and AlphaFoldDB cross-references",
+        ),
+        source("structures.db", &["dbReference/@type"], "pipeline::handlers::metadata::handle_entry_db_reference", "PDB or AlphaFoldDB"),
+        source("structures.id", &["dbReference/@id"], "pipeline::handlers::metadata::handle_entry_db_reference", "Structure id"),
+        source(
+            "structure_coverage",
+            &["dbReference[@type='PDB']/property[@type='chains']"],
+            "pipeline::builders::append_structure_coverage",
+            "One item per PDB chain range that maps onto the row",
+        ),
+        source("structure_coverage.pdb_id", &["dbReference[@type='PDB']/@id"], "pipeline::handlers::metadata::handle_entry_db_reference", "PDB id"),
+        source("structure_coverage.method", &["dbReference[@type='PDB']/property[@type='method']/@value"], "pipeline::handlers::metadata::handle_structure_property", "Experimental method"),
+        source(
+            "structure_coverage.resolution",
+            &["dbReference[@type='PDB']/property[@type='resolution']/@value"],
+            "pipeline::handlers::metadata::handle_structure_property",
+            "Resolution in Å; null for '-'",
+        ),
+        source("structure_coverage.chains", &["dbReference[@type='PDB']/property[@type='chains']/@value"], "pipeline::handlers::metadata::handle_structure_property", "Chain ids of the range"),
+        source(
+            "structure_coverage.start",
+            &["dbReference[@type='PDB']/property[@type='chains']/@value"],
+            "pipeline::builders::common::map_range_1based",
+            "Range begin mapped through the row's VSP edits",
+        ),
+        source(
+            "structure_coverage.end",
+            &["dbReference[@type='PDB']/property[@type='chains']/@value"],
+            "pipeline::builders::common::map_range_1based",
+            "Range end mapped through the row's VSP edits",
+        ),
+        source(
+            "alphafold_mean_plddt",
+            &[],
+            "pipeline::plddt::mean_plddt",
+            "Mean B-factor of the AlphaFold model in transform.alphafold_dir (canonical rows only)",
+        ),
+        source("parent_id", &["accession[1]"], "pipeline::handlers::metadata::handle_accession", "Primary accession of the entry"),
+        source(
+            "ptm_sites",
+            &[
+                "feature[@type='modified residue']",
+                "feature[@type='glycosylation site']",
+                "feature[@type='lipid moiety-binding region']",
+                "feature[@type='cross-link']",
+            ],
+            "pipeline::builders::ptm::append_ptm_sites",
+            "Single-residue PTM features mapped onto the row, grouped by residue",
+        ),
+        source(
+            "ptm_sites.site_index",
+            &["feature/location/position/@position"],
+            "pipeline::builders::ptm::resolve_mapped_site",
+            "1-based position on the row sequence after VSP mapping",
+        ),
+        source("ptm_sites.site_aa", &["sequence"], "pipeline::builders::ptm::resolve_mapped_site", "Residue of the row sequence at site_index"),
+        source("ptm_sites.modifications", &["feature"], "pipeline::builders::ptm::append_ptm_sites", "One item per PTM feature at the site"),
+        source(
+            "ptm_sites.modifications.mod_type",
+            &["feature/@type", "feature/@description"],
+            "pipeline::ptm_vocab::ModType::classify",
+            "class * 10000 + subclass * 100 + residue code (see ptm_vocab)",
+        ),
+        source(
+            "ptm_sites.modifications.confidence_score",
+            &["feature/@evidence", "evidence/@type"],
+            "pipeline::scratch::ParsedEntry::max_confidence_for_evidence",
+            "Highest confidence of the feature's ECO codes",
+        ),
+        source("ptm_sites.modifications.partner_protein", &["feature[@type='cross-link']/@description"], "pipeline::builders::ptm::parse_cross_link", "'interchain with ... in <protein>'"),
+        source("ptm_sites.modifications.partner_residue", &["feature[@type='cross-link']/@description"], "pipeline::builders::ptm::parse_cross_link", "'interchain with <residue>'"),
+        source("ptm_sites.modifications.linkage_chemistry", &["feature[@type='cross-link']/@description"], "pipeline::builders::ptm::parse_cross_link", "First parenthesised part, e.g. Lys-Gly"),
+        source("ptm_sites.modifications.mod_class", &["feature/@type"], "pipeline::ptm_vocab::ModType::class_name", "Feature key class"),
+        source(
+            "ptm_sites.modifications.mod_subclass",
+            &["feature/@description"],
+            "pipeline::ptm_vocab::ModType::subclass_name",
+            "Keyword of the ptmlist term the description names",
+        ),
+        source("ptm_sites.modifications.ptm_accession", &["feature/@description"], "pipeline::ptm_vocab::PtmVocabulary::term", "PTM-xxxx accession of the ptmlist term"),
+        source("ptm_sites.modifications.target_aa", &["feature/@description"], "pipeline::ptm_vocab::PtmVocabulary::term", "Target residue of the ptmlist term"),
+        source("ptm_sites.modifications.monoisotopic_mass_delta", &["feature/@description"], "pipeline::ptm_vocab::PtmVocabulary::term", "Mass difference of the ptmlist term"),
+        source("ptm_sites.modifications.average_mass_delta", &["feature/@description"], "pipeline::ptm_vocab::PtmVocabulary::term", "Mass difference of the ptmlist term"),
+        source(
+            "ptm_sites.modifications.evidence_code",
+            &["feature/@evidence", "evidence/@type"],
+            "pipeline::scratch::ParsedEntry::resolve_evidence",
+            "ECO codes of the feature's evidence keys, ';'-joined",
+        ),
+        source(
+            "ptm_sites.site_context",
+            &["sequence"],
+            "pipeline::builders::ptm::append_ptm_sites",
+            "Row sequence window around the site (transform.emit_site_context)",
+        ),
+        source("ptm_sites.plddt", &[], "pipeline::plddt::PlddtSource::load", "AlphaFold pLDDT of the site residue (transform.alphafold_dir)"),
+        source(
+            "ptm_sites.mapping_shift",
+            &["feature/location/position/@position"],
+            "pipeline::builders::ptm::append_ptm_sites",
+            "site_index minus the canonical position (transform.debug_mapping; null for isoform-located sites)",
+        ),
+        source(
+            "ptm_sites.mapping_failure_code",
+            &["feature/location/position/@position"],
+            "pipeline::builders::ptm::append_ptm_sites",
+            "[PTM_FAIL] code of a site that did not map; site_index is then the position as given (transform.debug_mapping)",
+        ),
+    ];
+
+    let coordinate_handler = "pipeline::builders::common::FeatureListBuilder::append_features";
+    sources.extend(feature_column("active_sites", "active site", coordinate_handler));
+    sources.extend(feature_column("binding_sites", "binding site", coordinate_handler));
+    sources.extend(feature_column("metal_coordinations", "metal ion-binding site", coordinate_handler));
+    sources.push(source(
+        "metal_coordinations.metal",
+        &[],
+        "pipeline::builders::EntryBuilders::append_row",
+        "Reserved; not populated by the parser (always null)",
+    ));
+    sources.extend(feature_column("mutagenesis_sites", "mutagenesis site", coordinate_handler));
+    sources.extend(feature_column("domains", "domain", coordinate_handler));
+    sources.push(source(
+        "domains.domain_name",
+        &["feature[@type='domain']/@description"],
+        "pipeline::builders::EntryBuilders::append_row",
+        "Domain description",
+    ));
+    sources.push(source(
+        "domains.family_accessions",
+        &["dbReference[@type='InterPro' or @type='Pfam' or @type='SMART']/property[@type='entry name']/@value"],
+        "pipeline::builders::domains::family_accessions",
+        "domain_xrefs ids whose name matches domain_name (transform.reconcile_domain_xrefs; else null)",
+    ));
+    sources.extend(feature_column("natural_variants", "sequence variant", coordinate_handler));
+    sources.extend([
+        source("natural_variants.original", &["feature[@type='sequence variant']/original"], "pipeline::handlers::features::assign_original", "Reference residues"),
+        source("natural_variants.variation", &["feature[@type='sequence variant']/variation"], "pipeline::handlers::features::assign_variation", "Variant residues"),
+        source(
+            "natural_variants.dbsnp_id",
+            &["feature[@type='sequence variant']/dbReference[@type='dbSNP']/@id", "feature[@type='sequence variant']/@description"],
+            "pipeline::handlers::features::capture_variant_db_reference",
+            "dbSNP reference, else the 'dbSNP:rs...' part of the description",
+        ),
+        source(
+            "natural_variants.disease_association",
+            &["feature[@type='sequence variant']/@description"],
+            "pipeline::handlers::features::parse_variant_description",
+            "'in <disease>' parts of the description",
+        ),
+        source(
+            "subunits",
+            &["comment[@type='subunit']"],
+            "pipeline::handlers::comments::consume_subunit_comment",
+            "Molecule-scoped comments only on the matching row",
+        ),
+        source("subunits.text", &["comment[@type='subunit']/text"], "pipeline::handlers::comments::consume_subunit_comment", "Comment text"),
+        source(
+            "subunits.evidence_code",
+            &["comment[@type='subunit']/text/@evidence", "comment[@type='subunit']/@evidence", "evidence/@type"],
+            "pipeline::scratch::ParsedEntry::resolve_evidence",
+            "ECO codes of the comment's evidence keys, ';'-joined",
+        ),
+        source(
+            "subunits.confidence_score",
+            &["comment[@type='subunit']/text/@evidence", "comment[@type='subunit']/@evidence", "evidence/@type"],
+            "pipeline::scratch::ParsedEntry::max_confidence_for_evidence",
+            "Highest confidence of the comment's ECO codes",
+        ),
+        source("subunits.molecule", &["comment[@type='subunit']/molecule"], "pipeline::handlers::comments::consume_subunit_comment", "Isoform/chain the comment is scoped to"),
+        source("interactions", &["comment[@type='interaction']"], "pipeline::handlers::comments::consume_interaction_comment", "One item per binary interaction"),
+        source(
+            "interactions.interactant_id_1",
+            &["comment[@type='interaction']/interactant[1]/id", "comment[@type='interaction']/dbReference[starts-with(@type,'UniProtKB')]/@id", "accession[1]"],
+            "pipeline::handlers::comments::set_intact_interactant",
+            "First interactant; the entry's accession when it carries no <id>",
+        ),
+        source(
+            "interactions.interactant_id_2",
+            &["comment[@type='interaction']/interactant[2]/id", "comment[@type='interaction']/dbReference[starts-with(@type,'UniProtKB')]/@id"],
+            "pipeline::handlers::comments::set_intact_interactant",
+            "Partner interactant",
+        ),
+        source(
+            "interactions.evidence_code",
+            &["comment[@type='interaction']/@evidence", "evidence/@type"],
+            "pipeline::scratch::ParsedEntry::resolve_evidence",
+            "ECO codes of the comment's evidence keys, ';'-joined",
+        ),
+        source(
+            "interactions.confidence_score",
+            &["comment[@type='interaction']/@evidence", "evidence/@type"],
+            "pipeline::scratch::ParsedEntry::max_confidence_for_evidence",
+            "Highest confidence of the comment's ECO codes",
+        ),
+        source("interactions.intact_id_1", &["comment[@type='interaction']/interactant[1]/@intactId"], "pipeline::handlers::comments::set_intact_interactant", "IntAct id of the first interactant"),
+        source("interactions.intact_id_2", &["comment[@type='interaction']/interactant[2]/@intactId"], "pipeline::handlers::comments::set_intact_interactant", "IntAct id of the partner"),
+        source("interactions.organisms_differ", &["comment[@type='interaction']/organismsDiffer"], "pipeline::handlers::comments::consume_interaction_comment", "true/false"),
+        source("interactions.experiments", &["comment[@type='interaction']/experiments"], "pipeline::handlers::comments::consume_interaction_comment", "Number of IntAct experiments"),
+        source("is_fragment", &["sequence/@fragment"], "pipeline::handlers::metadata::handle_sequence", "true when the attribute is present"),
+        source("is_precursor", &["sequence/@precursor"], "pipeline::handlers::metadata::handle_sequence", "true when precursor=\"true\""),
+        source("dataset", &["@dataset"], "pipeline::handlers::metadata::handle_entry_start", "Swiss-Prot or TrEMBL"),
+        source("gene_location", &["geneLocation/@type"], "pipeline::handlers::metadata::handle_gene_location", "Distinct types joined with '; '"),
+        source(
+            "ec_numbers",
+            &["protein/recommendedName/ecNumber", "dbReference[@type='EC']/@id"],
+            "pipeline::scratch::ParsedEntry::add_ec_number",
+            "Distinct EC numbers in document order",
+        ),
+        source(
+            "pathways",
+            &["dbReference[@type='Reactome']", "dbReference[@type='KEGG']"],
+            "pipeline::handlers::metadata::handle_entry_db_reference",
+            "Pathway cross-references",
+        ),
+        source("pathways.db", &["dbReference/@type"], "pipeline::handlers::metadata::handle_entry_db_reference", "Reactome or KEGG"),
+        source("pathways.id", &["dbReference/@id"], "pipeline::handlers::metadata::handle_entry_db_reference", "Pathway id"),
+        source("pathways.name", &["dbReference/property[@type='pathway name']/@value"], "pipeline::handlers::metadata::handle_pathway_property", "Pathway name (null for KEGG)"),
+        source(
+            "domain_xrefs",
+            &["dbReference[@type='InterPro']", "dbReference[@type='Pfam']", "dbReference[@type='SMART']"],
+            "pipeline::handlers::metadata::handle_entry_db_reference",
+            "Domain family cross-references",
+        ),
+        source("domain_xrefs.db", &["dbReference/@type"], "pipeline::handlers::metadata::handle_entry_db_reference", "InterPro, Pfam or SMART"),
+        source("domain_xrefs.id", &["dbReference/@id"], "pipeline::handlers::metadata::handle_entry_db_reference", "Family accession"),
+        source("domain_xrefs.name", &["dbReference/property[@type='entry name']/@value"], "pipeline::handlers::metadata::handle_domain_family_property", "Family entry name"),
+        source(
+            "isoform_count",
+            &["comment[@type='alternative products']/isoform"],
+            "pipeline::builders::EntryBuilders::append_row",
+            "Number of isoforms of the entry (0 without alternative products), same on each row",
+        ),
+        source("sequence_length", &["sequence"], "pipeline::builders::EntryBuilders::append_row", "Length of the row's sequence; null for unsequenced isoforms"),
+        source("ptm_site_count", &["feature"], "pipeline::builders::EntryBuilders::append_row", "Number of ptm_sites items of the row"),
+        source(
+            "other_comments",
+            &["comment"],
+            "pipeline::handlers::comments::consume_other_comment",
+            "One item per <text> of comment types without a dedicated column",
+        ),
+        source("other_comments.comment_type", &["comment/@type"], "pipeline::handlers::comments::consume_other_comment", "Comment type"),
+        source("other_comments.text", &["comment/text"], "pipeline::handlers::comments::consume_other_comment", "Trimmed text"),
+        source(
+            "other_comments.evidence_code",
+            &["comment/text/@evidence", "comment/@evidence", "evidence/@type"],
+            "pipeline::scratch::ParsedEntry::resolve_evidence",
+            "ECO codes of the text's (else the comment's) evidence keys",
+        ),
+        source("other_comments.molecule", &["comment/molecule"], "pipeline::handlers::comments::consume_other_comment", "Isoform/chain the comment is scoped to"),
+        source("release", &[], "release::resolve_release", "UniProt release of the run (--release, the input's release notes or its XML header)"),
+        source(
+            "isoform_status",
+            &["comment[@type='alternative products']/isoform/id"],
+            "pipeline::transformer::EntryTransformer::transform_shared",
+            "canonical for entries without isoforms; sequenced/unsequenced for isoforms found in/missing from the sidecar FASTA",
+        ),
+    ]);
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn every_schema_field_has_lineage_and_no_lineage_is_stale() {
+        let schema_paths: HashSet<String> = schema_fields().into_iter().map(|(path, _)| path).collect();
+        let mut seen = HashSet::new();
+        for source in sources() {
+            assert!(schema_paths.contains(&source.field), "lineage for unknown field {}", source.field);
+            assert!(seen.insert(source.field.clone()), "duplicate lineage for {}", source.field);
+            assert!(!source.handler.is_empty() && !source.derivation.is_empty(), "{}", source.field);
+        }
+        let missing: Vec<_> = schema_paths.difference(&seen).collect();
+        assert!(missing.is_empty(), "fields without lineage: {:?}", missing);
+
+        let full = manifest(SchemaProfile::Full);
+        assert_eq!(full.fields.len(), schema_paths.len());
+        let minimal = manifest(SchemaProfile::Minimal);
+        assert_eq!(minimal.fields.len(), 5);
+        let mod_type = full
+            .fields
+            .iter()
+            .find(|f| f.field == "ptm_sites.modifications.mod_type")
+            .unwrap();
+        assert_eq!(mod_type.data_type, "int32");
+        assert!(!mod_type.nullable);
+        let sites = full.fields.iter().find(|f| f.field == "ptm_sites").unwrap();
+        assert_eq!(sites.data_type, "list<struct>");
+    }
+}
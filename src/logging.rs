@@ -0,0 +1,319 @@
+//! Leveled, target-scoped logging to stderr and the run log.
+//!
+//! Every diagnostic line goes through `write` with a level and a short target naming the
+//! part of the pipeline it comes from (`run`, `config`, `ptm`, `transform`, `writer`, ...).
+//! `logging.log_level` sets the minimum level and `logging.module_levels` overrides it per
+//! target, e.g. `ptm: error` silences the `[PTM_FAIL]` lines while other warnings stay.
+//! Until `init` is called (or in library use) everything at info and above is printed.
+//!
+//! Lines are written to stderr and, once `set_log_file` has attached one, to the run's
+//! `etl.log`. While a progress spinner is registered with `set_progress` it is cleared
+//! for each line and redrawn after, so log output and the spinner don't garble each other.
+//!
+//! Codes that can repeat thousands of times for one row (`[PTM_FAIL]`) go through
+//! `Repeats`: the first `repeat_limit` lines per code are written, the rest are counted
+//! and summarized in one `[PTM_FAIL_REPEAT]` line (`logging.repeat_limit`, 0 = no limit).
+//!
+//! For workflow managers, `set_stderr` switches stderr to one JSON object per line
+//! (`--log-format json`) or silences it (`--quiet`); `etl.log` keeps the text lines.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex, RwLock};
+
+use chrono::{SecondsFormat, Utc};
+use clap::ValueEnum;
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+
+/// Severity of a log line, least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// Bracketed prefix of the level's lines (`[WARN] ...`).
+    pub fn tag(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "debug" => Ok(Level::Debug),
+            "info" => Ok(Level::Info),
+            "warn" | "warning" => Ok(Level::Warn),
+            "error" => Ok(Level::Error),
+            other => Err(format!("unknown log level {:?} (debug, info, warn, error)", other)),
+        }
+    }
+}
+
+/// Minimum level overall and per target.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogFilter {
+    level: Level,
+    targets: BTreeMap<String, Level>,
+}
+
+impl LogFilter {
+    pub fn new(level: Level, targets: BTreeMap<String, Level>) -> Self {
+        Self { level, targets }
+    }
+
+    /// Whether a `level` line of `target` is written.
+    pub fn enabled(&self, level: Level, target: &str) -> bool {
+        level >= *self.targets.get(target).unwrap_or(&self.level)
+    }
+}
+
+/// How log lines are written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogFormat {
+    /// `[LEVEL] message` lines
+    #[default]
+    Text,
+    /// One JSON object per line: `ts`, `level`, `target`, `message` (and `tag` for
+    /// tagged lines such as `PTM_FAIL`)
+    Json,
+}
+
+#[derive(Clone, Copy)]
+struct Stderr {
+    format: LogFormat,
+    quiet: bool,
+}
+
+struct Logger {
+    filter: RwLock<LogFilter>,
+    stderr: RwLock<Stderr>,
+    file: Mutex<Option<BufWriter<File>>>,
+    progress: Mutex<Option<ProgressBar>>,
+}
+
+static LOGGER: LazyLock<Logger> = LazyLock::new(|| Logger {
+    filter: RwLock::new(LogFilter::default()),
+    stderr: RwLock::new(Stderr {
+        format: LogFormat::Text,
+        quiet: false,
+    }),
+    file: Mutex::new(None),
+    progress: Mutex::new(None),
+});
+
+/// Lines written per code and scope before `Repeats` starts counting instead.
+pub const DEFAULT_REPEAT_LIMIT: usize = 5;
+
+static REPEAT_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_REPEAT_LIMIT);
+
+/// Replaces the process-wide filter.
+pub fn init(filter: LogFilter) {
+    *LOGGER.filter.write().unwrap() = filter;
+}
+
+/// Sets how many lines per code and scope `Repeats` admits (0 = all of them).
+pub fn set_repeat_limit(limit: usize) {
+    REPEAT_LIMIT.store(limit, Ordering::Relaxed);
+}
+
+/// Sets the stderr format. `quiet` drops text lines from stderr (they still reach the log
+/// file); JSON events are written either way.
+pub fn set_stderr(format: LogFormat, quiet: bool) {
+    *LOGGER.stderr.write().unwrap() = Stderr { format, quiet };
+}
+
+/// Whether stderr carries human-readable output (no `--quiet`, text format), i.e. whether
+/// a progress spinner may be drawn.
+pub fn interactive() -> bool {
+    let stderr = *LOGGER.stderr.read().unwrap();
+    stderr.format == LogFormat::Text && !stderr.quiet
+}
+
+/// Also writes every following line to `file`.
+pub fn set_log_file(file: File) {
+    *LOGGER.file.lock().unwrap() = Some(BufWriter::new(file));
+}
+
+/// Keeps `progress` drawn below the log lines (None once it is finished).
+pub fn set_progress(progress: Option<ProgressBar>) {
+    *LOGGER.progress.lock().unwrap() = progress;
+}
+
+/// Whether a `level` line of `target` passes the filter.
+pub fn enabled(level: Level, target: &str) -> bool {
+    LOGGER.filter.read().unwrap().enabled(level, target)
+}
+
+/// Writes one line, prefixed with `[tag]` when given. Callers check `enabled` first
+/// (the `log_*!` macros do).
+pub fn write(level: Level, target: &str, tag: Option<&str>, message: fmt::Arguments) {
+    let message = message.to_string();
+    let line = match tag {
+        Some(tag) => format!("[{}] {}", tag, message),
+        None => message.clone(),
+    };
+    let stderr = *LOGGER.stderr.read().unwrap();
+    let stderr_line = match stderr.format {
+        LogFormat::Json => Some(json_event(level, target, tag, &message)),
+        LogFormat::Text if stderr.quiet => None,
+        LogFormat::Text => Some(line.clone()),
+    };
+    if let Some(stderr_line) = stderr_line {
+        let progress = LOGGER.progress.lock().unwrap().clone();
+        match progress {
+            Some(progress) => progress.suspend(|| eprintln!("{}", stderr_line)),
+            None => eprintln!("{}", stderr_line),
+        }
+    }
+    if let Some(file) = LOGGER.file.lock().unwrap().as_mut() {
+        let _ = writeln!(file, "{}", line);
+        let _ = file.flush();
+    }
+}
+
+/// Per-code occurrence counts within one scope, e.g. the PTM failures of one row.
+///
+/// `admit` says whether an occurrence still gets its own line; `over_limit` lists the
+/// codes that went past the limit so the caller can log one summary each.
+#[derive(Debug, Default)]
+pub struct Repeats {
+    counts: Vec<(&'static str, u64)>,
+}
+
+impl Repeats {
+    /// Counts one occurrence of `code`; true while it is within the repeat limit.
+    pub fn admit(&mut self, code: &'static str) -> bool {
+        let count = match self.counts.iter_mut().find(|(c, _)| *c == code) {
+            Some((_, count)) => count,
+            None => {
+                self.counts.push((code, 0));
+                &mut self.counts.last_mut().unwrap().1
+            }
+        };
+        *count += 1;
+        let limit = REPEAT_LIMIT.load(Ordering::Relaxed);
+        limit == 0 || *count <= limit as u64
+    }
+
+    /// `(code, total, not logged)` of each code that exceeded the limit.
+    pub fn over_limit(&self) -> impl Iterator<Item = (&'static str, u64, u64)> + '_ {
+        let limit = REPEAT_LIMIT.load(Ordering::Relaxed) as u64;
+        self.counts
+            .iter()
+            .filter(move |&&(_, count)| limit > 0 && count > limit)
+            .map(move |&(code, count)| (code, count, count - limit))
+    }
+}
+
+fn json_event(level: Level, target: &str, tag: Option<&str>, message: &str) -> String {
+    let mut event = serde_json::json!({
+        "ts": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "level": level,
+        "target": target,
+        "message": message,
+    });
+    if let Some(tag) = tag.filter(|&t| t != level.tag()) {
+        event["tag"] = tag.into();
+    }
+    event.to_string()
+}
+
+/// Logs at `level` for `target` with an explicit tag (`Some("PTM_FAIL")`) or none at all.
+#[macro_export]
+macro_rules! log_event {
+    ($level:expr, $target:expr, $tag:expr, $($arg:tt)*) => {
+        if $crate::logging::enabled($level, $target) {
+            $crate::logging::write($level, $target, $tag, format_args!($($arg)*));
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($target:expr, $($arg:tt)*) => {
+        $crate::log_event!($crate::logging::Level::Debug, $target, Some($crate::logging::Level::Debug.tag()), $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($target:expr, $($arg:tt)*) => {
+        $crate::log_event!($crate::logging::Level::Info, $target, Some($crate::logging::Level::Info.tag()), $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($target:expr, $($arg:tt)*) => {
+        $crate::log_event!($crate::logging::Level::Warn, $target, Some($crate::logging::Level::Warn.tag()), $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($target:expr, $($arg:tt)*) => {
+        $crate::log_event!($crate::logging::Level::Error, $target, Some($crate::logging::Level::Error.tag()), $($arg)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_levels_override_the_default() {
+        let filter = LogFilter::new(
+            Level::Info,
+            BTreeMap::from([("ptm".to_string(), Level::Error), ("writer".to_string(), Level::Debug)]),
+        );
+        assert!(filter.enabled(Level::Warn, "transform"));
+        assert!(!filter.enabled(Level::Debug, "transform"));
+        assert!(!filter.enabled(Level::Warn, "ptm"));
+        assert!(filter.enabled(Level::Error, "ptm"));
+        assert!(filter.enabled(Level::Debug, "writer"));
+
+        assert_eq!("WARNING".parse::<Level>(), Ok(Level::Warn));
+        assert!("verbose".parse::<Level>().is_err());
+    }
+
+    #[test]
+    fn json_events_carry_level_target_and_custom_tags() {
+        let event: serde_json::Value =
+            serde_json::from_str(&json_event(Level::Warn, "ptm", Some("PTM_FAIL"), "code=MAPPER_OOB")).unwrap();
+        assert_eq!(event["level"], "warn");
+        assert_eq!(event["target"], "ptm");
+        assert_eq!(event["tag"], "PTM_FAIL");
+        assert_eq!(event["message"], "code=MAPPER_OOB");
+        assert!(event["ts"].as_str().unwrap().ends_with('Z'));
+
+        let event: serde_json::Value = serde_json::from_str(&json_event(Level::Info, "run", Some("INFO"), "ok")).unwrap();
+        assert!(event.get("tag").is_none());
+    }
+
+    #[test]
+    fn repeats_admit_up_to_the_limit_per_code() {
+        let mut repeats = Repeats::default();
+        let admitted = (0..8).filter(|_| repeats.admit("RESIDUE_MISMATCH")).count();
+        assert_eq!(admitted, DEFAULT_REPEAT_LIMIT);
+        assert!(repeats.admit("ISOFORM_OOB"));
+        assert_eq!(repeats.over_limit().collect::<Vec<_>>(), vec![("RESIDUE_MISMATCH", 8, 3)]);
+    }
+}
@@ -1,150 +1,318 @@
-mod cli;
-mod config;
-mod error;
-mod fasta;
-mod metrics;
-mod pipeline;
-mod report;
-mod runs;
-mod sampler;
-mod schema;
-mod writer;
-
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
 use clap::Parser;
 use crossbeam_channel::bounded;
-use glob::glob;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::env;
-use std::fs::{self, File, OpenOptions};
-use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
 use std::sync::{
-    atomic::{AtomicBool, AtomicUsize, Ordering},
+    atomic::{AtomicBool, Ordering},
     Arc,
 };
 use std::thread;
 
-use crate::cli::Args;
-use crate::config::Settings;
-use crate::fasta::load_fasta_map;
-use crate::metrics::{LocalMetricsAdapter, Metrics, MetricsCollector};
-use crate::pipeline::parser::parse_entries;
-use crate::pipeline::reader::create_xml_reader;
-use crate::report::{RunReport, RunStatus};
-use crate::runs::{cleanup_old_runs, RunContext};
-use crate::sampler::{ChannelStats, ResourceSampler};
-use crate::writer::parquet::write_batches;
-
-/// A writer that tees output to both a file and stderr.
-struct TeeWriter {
-    file: BufWriter<File>,
-}
+use uniprot_etl::cli::{
+    Args, BackfillArgs, Command, DiffReleasesArgs, ExplainArgs, ExportNetworkArgs, LookupArgs, ServeArgs, StatsArgs,
+    StatsFormat,
+};
+use uniprot_etl::config::{ConfigOrigin, OutputFormat, Settings};
+use uniprot_etl::dataset_card::DatasetCard;
+use uniprot_etl::backfill::backfill;
+use uniprot_etl::diff::diff_releases;
+use uniprot_etl::explain::{explain_entry, find_entry};
+use uniprot_etl::fasta::{load_fasta, load_fasta_map, SidecarFasta};
+use uniprot_etl::lineage::write_manifest as write_lineage_manifest;
+use uniprot_etl::logging::{self, Level, LogFormat};
+use uniprot_etl::{log_error, log_event, log_info, log_warn};
+use uniprot_etl::metrics::{Metrics, MetricsCollector};
+use uniprot_etl::network::Network;
+use uniprot_etl::paths::{canonicalize, list_xml_inputs, strip_xml_extensions};
+use uniprot_etl::pipeline::dedup::{scan_versions, AccessionDedup, DedupPolicy};
+use uniprot_etl::pipeline::feature_routing::FeatureRouting;
+use uniprot_etl::pipeline::pacing::ChannelPacer;
+use uniprot_etl::pipeline::parser::{parse_entries_sharded, parse_entries_with_hooks, EntrySampling, ParseHooks};
+use uniprot_etl::pipeline::plddt::PlddtSource;
+use uniprot_etl::pipeline::ptm_vocab::PtmVocabulary;
+use uniprot_etl::pipeline::reader::create_xml_reader;
+use uniprot_etl::pipeline::rejects::RejectLog;
+use uniprot_etl::pipeline::row_filter::RowFilter;
+use uniprot_etl::pipeline::trace::EntryTracer;
+use uniprot_etl::preflight::{self, Preflight};
+use uniprot_etl::proteome::fetch_proteome;
+use uniprot_etl::quality::{QualityInputs, QualityReport};
+use uniprot_etl::release::resolve_release;
+use uniprot_etl::report::{RunReport, RunStatus};
+use uniprot_etl::rest::{fetch_accessions_to, is_valid_accession, read_accession_file};
+use uniprot_etl::archive::archive_and_upload;
+use uniprot_etl::profiling::{CpuProfiler, SAMPLE_FREQUENCY};
+use uniprot_etl::runs::{append_run_index, cleanup_old_runs, RunContext};
+use uniprot_etl::sampler::{ChannelStats, MemoryGovernor, ResourceSampler};
+use uniprot_etl::schema::SchemaProfile;
+use uniprot_etl::serve::{encode_json, serve, ServeOptions};
+use uniprot_etl::shutdown::install_signal_handler;
+use uniprot_etl::sidecar::{
+    check_sidecar_consistency, input_has_isoforms, resolve_sidecar, SidecarCheck, SidecarInfo,
+};
+use uniprot_etl::stats::DatasetStats;
+use uniprot_etl::watch::{self, append_manifest, LandingDir, ManifestRecord};
+use uniprot_etl::writer::dataset::{output_files, shard_path};
+use uniprot_etl::writer::index::{index_path_for, read_indexed, write_index, SWARM_INDEX_FILE};
+use uniprot_etl::writer::embeddings::write_embedding_shards;
+use uniprot_etl::writer::id_mapping::write_id_mapping;
+use uniprot_etl::writer::ptm_flat::write_ptm_flat;
+use uniprot_etl::writer::variant_peptides::write_variant_peptides;
+use uniprot_etl::writer::rejected::write_rejections;
+use uniprot_etl::writer::sort::sort_parquet_by_parent_id;
+use uniprot_etl::writer::{validate_output, write_output};
 
-impl TeeWriter {
-    fn new(file: File) -> Self {
-        Self {
-            file: BufWriter::new(file),
-        }
-    }
+fn main() -> Result<()> {
+    let args = Args::parse();
+    logging::set_stderr(args.log_format, args.quiet);
+    let json_events = args.log_format == LogFormat::Json;
 
-    fn writeln(&mut self, msg: &str) {
-        // Write to stderr
-        eprintln!("{}", msg);
-        // Write to file
-        let _ = writeln!(self.file, "{}", msg);
-        let _ = self.file.flush();
+    let result = run(args);
+    if let (Err(e), true) = (&result, json_events) {
+        // Report the failure as an event rather than anyhow's free-text report
+        log_error!("run", "{:#}", e);
+        std::process::exit(1);
     }
+    result
 }
 
-/// Log macro that writes to both file and stderr via TeeWriter.
-macro_rules! log {
-    ($writer:expr, $($arg:tt)*) => {
-        $writer.writeln(&format!($($arg)*))
-    };
-}
-
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn run(args: Args) -> Result<()> {
+    if let Some(Command::Stats(stats_args)) = &args.command {
+        return run_stats(stats_args);
+    }
+    if let Some(Command::Serve(serve_args)) = &args.command {
+        return run_serve(serve_args, args.config.as_deref());
+    }
+    if let Some(Command::Lookup(lookup_args)) = &args.command {
+        return run_lookup(lookup_args);
+    }
+    if let Some(Command::DiffReleases(diff_args)) = &args.command {
+        return run_diff_releases(diff_args);
+    }
+    if let Some(Command::ExportNetwork(network_args)) = &args.command {
+        return run_export_network(network_args);
+    }
+    if let Some(Command::Explain(explain_args)) = &args.command {
+        return run_explain(explain_args);
+    }
+    if let Some(Command::Backfill(backfill_args)) = &args.command {
+        return run_backfill(backfill_args, args.config.as_deref());
+    }
 
     // Load settings from YAML, with CLI overrides
-    let mut settings = Settings::load_from_yaml(args.config.as_deref())?;
-    settings =
-        settings.merge_with_cli(args.input, args.output, args.batch_size, args.fasta_sidecar);
+    let (mut settings, config_file) = Settings::load_with_origin(args.config.as_deref())?;
+    logging::init(settings.logging.filter());
+    logging::set_repeat_limit(settings.logging.repeat_limit);
+    let watch_mode = args.watch.is_some();
+    if let Some(landing_dir) = &args.watch {
+        // The landing directory may legitimately start out missing
+        fs::create_dir_all(landing_dir)?;
+    }
+    let input = args.watch.or(args.input);
+    let proteome_args = match &args.command {
+        Some(Command::FetchProteome(proteome_args)) => Some(proteome_args),
+        _ => None,
+    };
+    let output = match proteome_args {
+        Some(p) => Some(p.output_dir.join(format!("{}.parquet", p.proteome_id))),
+        None => args.output,
+    };
+    settings = settings.merge_with_cli(input, output, args.batch_size, args.fasta_sidecar);
+    if let Some(release) = &args.release {
+        // Same release drives the sidecar download URL unless configured explicitly
+        settings
+            .storage
+            .sidecar_download
+            .release
+            .get_or_insert_with(|| release.clone());
+    }
 
     // Resolve paths relative to current working directory (project root)
-    let root = env::current_dir()?;
+    let root = canonicalize(&env::current_dir()?)?;
     settings.resolve_paths(&root)?;
+    validate_output(&settings)?;
 
     // Create run context (timestamped directory, optionally overridden)
     let run_context = RunContext::new_with_run_id(&settings.runs.runs_dir, args.run_id)?;
 
-    // Set up tee logging to both file and stderr
+    // Log lines go to the run directory as well as stderr
     let log_file = OpenOptions::new()
         .create(true)
         .write(true)
         .truncate(true)
         .open(run_context.log_path())?;
-    let mut logger = TeeWriter::new(log_file);
+    logging::set_log_file(log_file);
 
-    log!(logger, "[INFO] Run ID: {}", run_context.run_id);
-    log!(
-        logger,
-        "[INFO] Run directory: {}",
+    log_info!("run", "Run ID: {}", run_context.run_id);
+    log_info!(
+        "run",
+        "Run directory: {}",
         run_context.run_dir.display()
     );
 
+    // --accessions: fetch the entries into the run directory and process that file
+    let mut accessions = args.accessions;
+    if let Some(path) = &args.accessions_file {
+        accessions.extend(read_accession_file(path)?);
+    }
+    if !accessions.is_empty() {
+        let fetched = run_context.fetched_xml_path();
+        let summary = fetch_accessions_to(&settings.storage.rest_fetch, &accessions, &fetched)?;
+        log_info!(
+            "run",
+            "Fetched {} accession(s) from {} ({} request(s), {:.2} MB)",
+            summary.accessions,
+            settings.storage.rest_fetch.base_url,
+            summary.requests,
+            summary.bytes as f64 / (1024.0 * 1024.0)
+        );
+        settings.storage.input_path = Some(fetched);
+    }
+
+    // fetch-proteome: download (or reuse verified cached) XML + isoform FASTA as the run's inputs
+    let proteome_info = match proteome_args {
+        Some(p) => {
+            let info = fetch_proteome(
+                &settings.storage.rest_fetch.base_url,
+                &p.proteome_id,
+                &settings.storage.sidecar_download.cache_dir,
+                !p.no_isoforms,
+                p.refresh,
+            )?;
+            log_info!(
+                "run",
+                "Proteome {} (release {}, {}): {} sha256={}",
+                info.proteome_id,
+                info.release.as_deref().unwrap_or("unknown"),
+                if info.cache_hit { "cached" } else { "downloaded" },
+                info.xml.path.display(),
+                info.xml.sha256
+            );
+            settings.storage.input_path = Some(info.xml.path.clone());
+            if let Some(fasta) = &info.isoform_fasta {
+                settings.storage.fasta_sidecar_path = Some(fasta.path.clone());
+            }
+            Some(info)
+        }
+        None => None,
+    };
+
+    // Auto-download the varsplic sidecar if none was configured but the input has isoforms
+    let sidecar_info = resolve_missing_sidecar(
+        &mut settings,
+        args.release.as_deref(),
+        proteome_info.as_ref().and_then(|p| p.release.as_deref()),
+    )?;
+
     // Save config snapshot
-    settings.save_snapshot(&run_context.config_snapshot_path())?;
-    log!(
-        logger,
-        "[INFO] Config snapshot saved to {}",
-        run_context.config_snapshot_path().display()
+    settings.save_snapshot(&run_context, &ConfigOrigin::capture(config_file, &root))?;
+    log_info!(
+        "run",
+        "Config snapshot saved to {} (with its origin in {})",
+        run_context.config_snapshot_path().display(),
+        run_context.run_snapshot_path().display()
     );
 
-    log!(logger, "[INFO] Configuration ready");
-    log!(
-        logger,
-        "[INFO]   Input: {}",
+    // Column lineage for data catalogs, generated from the schema and handler table
+    write_lineage_manifest(&run_context.lineage_path(), settings.schema.profile)?;
+    log_info!(
+        "run",
+        "Lineage manifest saved to {}",
+        run_context.lineage_path().display()
+    );
+
+    log_info!("run", "Configuration ready");
+    log_info!(
+        "run",
+        "  Input: {}",
         settings.input_path()?.display()
     );
-    log!(
-        logger,
-        "[INFO]   Output: {}",
+    log_info!(
+        "run",
+        "  Output: {}",
         settings.storage.output_path.display()
     );
+    if settings.schema.profile != SchemaProfile::Full {
+        log_info!(
+            "run",
+            "  Schema profile: {} ({} columns)",
+            settings.schema.profile.name(),
+            settings.schema.profile.schema().fields().len()
+        );
+    }
     if let Some(ref fasta) = settings.storage.fasta_sidecar_path {
-        log!(logger, "[INFO]   FASTA sidecar: {}", fasta.display());
+        log_info!("run", "  FASTA sidecar: {}", fasta.display());
     } else {
-        log!(logger, "[WARN]   FASTA sidecar: (not set)");
+        log_warn!("run", "  FASTA sidecar: (not set)");
     }
-    log!(
-        logger,
-        "[INFO]   Batch size: {}",
+    log_info!(
+        "run",
+        "  Batch size: {}",
         settings.performance.batch_size
     );
-    log!(
-        logger,
-        "[INFO]   Channel capacity: {}",
+    log_info!(
+        "run",
+        "  Channel capacity: {}",
         settings.performance.channel_capacity
     );
-    log!(
-        logger,
-        "[INFO]   Zstd level: {}",
+    log_info!(
+        "run",
+        "  Zstd level: {}",
         settings.performance.zstd_level
     );
 
+    // Estimate the run's entries and bytes up front for progress percentages and
+    // largest-first swarm scheduling
+    let preflight = if !settings.performance.preflight {
+        None
+    } else if watch_mode {
+        log_warn!("run", "performance.preflight does not apply to --watch; skipped");
+        None
+    } else {
+        let input_path = settings.input_path()?;
+        let files = if input_path.is_dir() {
+            list_xml_inputs(input_path)?
+        } else {
+            vec![input_path.to_path_buf()]
+        };
+        let sample_bytes = settings.performance.preflight_sample_mb.filter(|&mb| mb > 0).map(|mb| mb * 1024 * 1024);
+        let started = std::time::Instant::now();
+        let estimate = preflight::scan(&files, sample_bytes).context("Preflight scan failed")?;
+        log_info!(
+            "run",
+            "Preflight: {}{} entries, {:.1} MB of XML in {} file(s) ({:.1}s)",
+            if estimate.is_exact() { "" } else { "~" },
+            estimate.total_entries,
+            estimate.total_bytes as f64 / (1024.0 * 1024.0),
+            files.len(),
+            started.elapsed().as_secs_f64()
+        );
+        Some(estimate)
+    };
+
     let metrics = Metrics::new();
 
     // Start a lightweight terminal progress bar that updates from Metrics
     let progress_running = Arc::new(AtomicBool::new(true));
     let progress_flag = Arc::clone(&progress_running);
     let progress_metrics = metrics.clone();
-    let pb = ProgressBar::new_spinner();
+    let progress_total = preflight.as_ref().map(|p| (p.total_bytes, p.total_entries));
+    // Spinner only on an interactive stderr (not with --quiet / --log-format json)
+    let pb = if logging::interactive() {
+        ProgressBar::new_spinner()
+    } else {
+        ProgressBar::hidden()
+    };
     pb.set_style(ProgressStyle::with_template("[{spinner}] {msg}").unwrap());
     pb.enable_steady_tick(std::time::Duration::from_millis(200));
+    logging::set_progress(Some(pb.clone()));
     let progress_handle = thread::spawn(move || {
         while progress_flag.load(Ordering::Relaxed) {
             let elapsed = progress_metrics.elapsed_secs();
@@ -163,86 +331,586 @@ fn main() -> Result<()> {
             };
             let mb_read = bytes_read as f64 / (1024.0 * 1024.0);
             let mb_written = bytes_written as f64 / (1024.0 * 1024.0);
+            let estimate = progress_total
+                .map(|(total_bytes, total_entries)| progress_estimate(bytes_read, total_bytes, total_entries, elapsed))
+                .unwrap_or_default();
             pb.set_message(format!(
-                "rows: {} ({:.0}/s) | batches: {} | features: {} | isoforms: {} | ptm: {} mapped / {} failed | read: {:.2} MB | written: {:.2} MB",
-                entries, eps, batches, features, isoforms, ptm_mapped, ptm_failed, mb_read, mb_written
+                "{}rows: {} ({:.0}/s) | batches: {} | features: {} | isoforms: {} | ptm: {} mapped / {} failed | read: {:.2} MB | written: {:.2} MB",
+                estimate, entries, eps, batches, features, isoforms, ptm_mapped, ptm_failed, mb_read, mb_written
             ));
             std::thread::sleep(std::time::Duration::from_millis(200));
         }
+        logging::set_progress(None);
         pb.finish_and_clear();
     });
 
-    // Create channel stats for backpressure tracking (used in single-file mode only)
+    // Channel fullness for the bottleneck diagnosis, sampled by the pacer on every send
+    // (`performance.adaptive_channel`; shared by every per-file channel in swarm mode)
     let channel_stats = Arc::new(ChannelStats::new(settings.performance.channel_capacity));
 
     // Start resource sampler (background thread sampling at 1Hz)
-    // Note: In swarm mode, this tracks a dummy channel; per-file channels are not monitored
-    let mut sampler = ResourceSampler::start(Arc::clone(&channel_stats));
+    let mut sampler = ResourceSampler::start(Arc::clone(&channel_stats), metrics.clone());
+
+    // Optional side output of dropped rows/features, shared by every worker
+    let (reject_log, rejected_writer) = if settings.runs.write_rejected {
+        let (log, rx) = RejectLog::channel();
+        let rejected_path = run_context.rejected_path();
+        let handle = thread::spawn(move || write_rejections(rx, &rejected_path));
+        (log, Some(handle))
+    } else {
+        (RejectLog::disabled(), None)
+    };
 
     // Detect if input is a directory (swarm mode) or a single file
     let input_path = settings.input_path()?;
-    let is_directory = input_path.is_dir();
-
-    // Run the appropriate pipeline mode
-    let etl_result = if is_directory {
-        log!(logger, "[INFO] Swarm mode activated: processing directory");
 
-        // Load sidecar FASTA once, shared across all workers
-        let sidecar_fasta = if let Some(ref path) = settings.storage.fasta_sidecar_path {
-            let map = load_fasta_map(path)?;
-            Some(Arc::new(map))
+    let release = resolve_release(
+        args.release.as_deref(),
+        proteome_info.as_ref().and_then(|p| p.release.as_deref()),
+        input_path,
+        &settings,
+    )?;
+    match &release {
+        Some(r) => log_info!("run", "UniProt release: {} (from {})", r.version, r.source),
+        None => log_warn!("run", "UniProt release unknown; `release` column will be null"),
+    }
+    let trace = match &args.trace_accession {
+        Some(accession) => {
+            if !is_valid_accession(accession) {
+                return Err(anyhow!("Invalid --trace-accession: {:?}", accession));
+            }
+            let trace_path = run_context.trace_path(accession);
+            log_info!("run", "Tracing {} to {}", accession, trace_path.display());
+            EntryTracer::to_file(accession, &trace_path)?
+        }
+        None => EntryTracer::disabled(),
+    };
+    let dedup = match settings.transform.deduplicate {
+        DedupPolicy::Off => AccessionDedup::disabled(),
+        DedupPolicy::FirstWins => AccessionDedup::first_wins(),
+        DedupPolicy::NewestVersion => {
+            if watch_mode {
+                return Err(anyhow!(
+                    "transform.deduplicate: newest_version scans every input up front; use first_wins with --watch"
+                ));
+            }
+            let files = if input_path.is_dir() {
+                list_xml_inputs(input_path)?
+            } else {
+                vec![input_path.to_path_buf()]
+            };
+            let versions = scan_versions(&files, &settings).context("Failed to scan entry versions")?;
+            log_info!("run", "Deduplication: newest versions of {} accessions in {} file(s)", versions.len(), files.len());
+            AccessionDedup::newest_version(versions)
+        }
+    };
+    let hooks = ParseHooks {
+        rejects: reject_log,
+        trace: trace.clone(),
+        release: release.as_ref().map(|r| r.version.clone()),
+        strict_checksums: args.strict,
+        sampling: EntrySampling {
+            every: args.sample_every.map(|n| n as usize),
+            max_entries: args.max_entries,
+        },
+        flush_interval: settings.performance.flush_interval_secs.map(std::time::Duration::from_secs),
+        dedup,
+        cancel: install_signal_handler()?,
+        build_threads: settings.performance.build_threads,
+        pacer: if settings.performance.adaptive_channel {
+            ChannelPacer::new(
+                settings.performance.channel_capacity,
+                settings.performance.max_inflight_mb,
+                Arc::clone(&channel_stats),
+            )
         } else {
-            None
-        };
-
-        // In swarm mode, output_path is treated as a directory
-        let output_dir = &settings.storage.output_path;
-        run_swarm_pipeline(input_path, output_dir, &settings, &metrics, sidecar_fasta)
+            ChannelPacer::disabled()
+        },
+        ..transform_hooks(&settings)?
+    };
+    if hooks.sampling.is_active() {
+        log_warn!(
+            "run",
+            "Sampling mode (every {}, max {} per file): output is a partial extract",
+            hooks.sampling.every.unwrap_or(1),
+            hooks
+                .sampling
+                .max_entries
+                .map_or_else(|| "unlimited".to_string(), |n| n.to_string())
+        );
+    }
+    let is_directory = watch_mode || input_path.is_dir();
+    let profiler = if args.profile_cpu {
+        log_info!("run", "CPU profiling at {} Hz", SAMPLE_FREQUENCY);
+        Some(CpuProfiler::start()?)
     } else {
-        // Single file mode (legacy behavior)
-        run_etl_pipeline(&settings, &metrics, &channel_stats)
+        None
+    };
+
+    // Load (and spot-check) the sidecar FASTA once; it is shared by both pipeline modes
+    let mut sidecar_check = None;
+    let etl_result = match load_sidecar_fasta(&settings, &metrics) {
+        Err(e) => Err(e),
+        Ok((sidecar_fasta, check)) => {
+            sidecar_check = check;
+
+            // Run the appropriate pipeline mode
+            if is_directory && settings.storage.accession_shards > 0 {
+                Err(anyhow!("accession_shards splits a single input file; it does not apply to directory input"))
+            } else if is_directory {
+                // In swarm/watch mode, output_path is treated as a directory
+                let governor = settings.performance.max_rss_gb.map(|gb| {
+                    MemoryGovernor::new(sampler.rss_gauge(), (gb * 1024.0 * 1024.0 * 1024.0) as u64)
+                });
+                let swarm = SwarmContext {
+                    output_dir: &settings.storage.output_path,
+                    settings: &settings,
+                    metrics: &metrics,
+                    sidecar_fasta,
+                    governor: governor.as_ref(),
+                    hooks: &hooks,
+                    preflight: preflight.as_ref(),
+                };
+                if watch_mode {
+                    log_info!("run", "Watch mode activated: {}", input_path.display());
+                    run_watch(input_path, &swarm, &run_context.manifest_path())
+                } else {
+                    log_info!("run", "Swarm mode activated: processing directory");
+                    run_swarm_pipeline(input_path, &swarm)
+                }
+            } else {
+                // Single file mode (legacy behavior)
+                run_etl_pipeline(&settings, &metrics, &channel_stats, sidecar_fasta, &hooks)
+            }
+        }
     };
 
+    // Dropping the last handle lets the rejected.parquet writer finish
+    let sampling = hooks.sampling;
+    let channel_tuning = hooks.pacer.summary();
+    let cancelled = hooks.cancel.is_cancelled();
+    drop(hooks);
+    if let Some(accession) = &args.trace_accession {
+        if trace.hits() == 0 {
+            log_warn!("run", "--trace-accession {} was not found in the input", accession);
+        }
+    }
+    if let Some(handle) = rejected_writer {
+        match handle.join().expect("Rejected writer thread panicked") {
+            Ok(count) => log_info!(
+                "run",
+                "Rejected rows/features: {} (written to {})",
+                count,
+                run_context.rejected_path().display()
+            ),
+            Err(e) => log_error!("run", "Failed to write rejected.parquet: {:#}", e),
+        }
+    }
+
     // Stop the sampler
     sampler.stop();
+    if let Some(profiler) = profiler {
+        match profiler.finish(&run_context.profiles_dir()) {
+            Ok(paths) => {
+                for path in paths {
+                    log_info!("run", "CPU profile written to {}", path.display());
+                }
+            }
+            Err(e) => log_warn!("run", "Failed to write CPU profile: {:#}", e),
+        }
+    }
+
+    // Evaluate data quality gates; a breach fails an otherwise successful run
+    let quality = QualityReport::evaluate(&QualityInputs::from_metrics(&metrics), &settings.quality);
+    log_info!("run", "Data quality score: {:.4}", quality.score);
+    let etl_result = match (etl_result, quality.failure_message()) {
+        (Ok(()), Some(message)) => {
+            log_error!("run", "{}", message);
+            Err(anyhow!(message))
+        }
+        (result, _) => result,
+    };
 
     // Generate report (even on error)
     let status = match &etl_result {
+        _ if cancelled => RunStatus::Cancelled,
         Ok(()) => RunStatus::Success,
         Err(e) => RunStatus::Error {
             message: format!("{:#}", e),
         },
     };
+    let etl_result = match etl_result {
+        Ok(()) if cancelled => Err(anyhow!("Run cancelled by signal; outputs are partial")),
+        result => result,
+    };
 
-    let report = RunReport::generate(&run_context, &metrics, &sampler, status);
+    let mut report = RunReport::generate(&run_context, &metrics, &sampler, status);
+    report.sidecar = sidecar_info;
+    report.sidecar_check = sidecar_check;
+    report.proteome = proteome_info;
+    report.release = release;
+    report.sampling = Some(sampling).filter(EntrySampling::is_active);
+    report.channel = channel_tuning;
+    report.preflight = preflight;
+    report.quality = quality;
 
     // Attempt to save report
     if let Err(e) = report.save_yaml(&run_context.report_path()) {
-        log!(logger, "[ERROR] Failed to save report: {}", e);
+        log_error!("run", "Failed to save report: {}", e);
     } else {
-        log!(
-            logger,
-            "[INFO] Report saved to {}",
+        log_info!(
+            "run",
+            "Report saved to {}",
             run_context.report_path().display()
         );
     }
+    if let Err(e) = report.save_html(&run_context.report_html_path()) {
+        log_warn!("run", "Failed to save HTML report: {}", e);
+    }
+
+    // Governance summary of the published dataset; only for complete Parquet output
+    if settings.storage.dataset_card
+        && settings.storage.output_format == OutputFormat::Parquet
+        && matches!(report.status, RunStatus::Success)
+    {
+        let output = &settings.storage.output_path;
+        match DatasetCard::build(output, &report, &settings).and_then(|card| card.write(output)) {
+            Ok((markdown, _)) => log_info!("run", "Dataset card written to {}", markdown.display()),
+            Err(e) => log_error!("run", "Failed to write dataset card: {}", e),
+        }
+    }
+
+    // Register the run in runs/index.yaml
+    let storage = &settings.storage;
+    let outputs: Vec<PathBuf> = std::iter::once(storage.output_path.clone())
+        .chain(storage.ptm_flat.path.clone())
+        .chain(storage.variant_peptides.path.clone())
+        .chain(storage.embedding_export.dir.clone())
+        .chain(storage.id_mapping_path.clone())
+        .collect();
+    let record = report.index_record(storage.input_path.clone(), outputs);
+    if let Err(e) = append_run_index(&settings.runs.runs_dir, &record) {
+        log_warn!("run", "Failed to update run index: {:#}", e);
+    }
 
     // Print metrics summary
-    print_summary_to_tee(&metrics, &mut logger);
+    log_summary(&metrics);
 
     // Stop and join progress bar thread
     progress_running.store(false, Ordering::Relaxed);
     let _ = progress_handle.join();
 
+    // Archive this run's artifacts
+    if settings.runs.archive.enabled {
+        match archive_and_upload(
+            &run_context.run_dir,
+            &run_context.run_id,
+            &settings.runs.runs_dir,
+            &settings.runs.archive,
+        ) {
+            Ok((archive, uploaded)) => {
+                log_info!("run", "Run archived to {}", archive.display());
+                if let Some(uploaded) = uploaded {
+                    log_info!("run", "Run archive uploaded to {}", uploaded);
+                }
+            }
+            Err(e) => log_warn!("run", "Failed to archive run: {:#}", e),
+        }
+    }
+
     // Cleanup old runs
-    if let Err(e) = cleanup_old_runs(&settings.runs.runs_dir, settings.runs.keep_runs) {
-        log!(logger, "[WARN] Failed to cleanup old runs: {}", e);
+    if let Err(e) = cleanup_old_runs(&settings.runs.runs_dir, &settings.runs.retention()) {
+        log_warn!("run", "Failed to cleanup old runs: {}", e);
     }
 
     // Return the ETL result
     etl_result
 }
 
+/// `uniprot_etl lookup`: prints the rows of one accession as JSON, read through the index.
+fn run_lookup(args: &LookupArgs) -> Result<()> {
+    let batches = read_indexed(&args.index, &args.accession)?;
+    if batches.is_empty() {
+        return Err(anyhow!("{} is not in {}", args.accession, args.index.display()));
+    }
+    println!("{}", serde_json::to_string_pretty(&encode_json(&batches)?)?);
+    Ok(())
+}
+
+/// `uniprot_etl stats`: prints dataset statistics for produced Parquet output.
+fn run_stats(args: &StatsArgs) -> Result<()> {
+    let stats = DatasetStats::collect(&args.path)?;
+    let rendered = match args.format {
+        StatsFormat::Yaml => serde_yaml::to_string(&stats)?,
+        StatsFormat::Json => serde_json::to_string_pretty(&stats)? + "\n",
+    };
+
+    match &args.out {
+        Some(path) => fs::write(path, rendered)?,
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+fn run_diff_releases(args: &DiffReleasesArgs) -> Result<()> {
+    let diff = diff_releases(&args.old, &args.new, &args.changes)?;
+    let rendered = match args.format {
+        StatsFormat::Yaml => serde_yaml::to_string(&diff)?,
+        StatsFormat::Json => serde_json::to_string_pretty(&diff)? + "\n",
+    };
+
+    match &args.out {
+        Some(path) => fs::write(path, rendered)?,
+        None => print!("{}", rendered),
+    }
+    log_info!("run", "Wrote per-row changes to {}", args.changes.display());
+    Ok(())
+}
+
+fn run_export_network(args: &ExportNetworkArgs) -> Result<()> {
+    let network = Network::load(&args.path, args.undirected)?;
+    let summary = network.write(&args.out, args.format)?;
+    log_info!(
+        "run",
+        "Wrote {} edges between {} proteins ({} interactions) to {}",
+        summary.edges,
+        summary.nodes,
+        summary.interactions,
+        args.out.display()
+    );
+    Ok(())
+}
+
+fn run_explain(args: &ExplainArgs) -> Result<()> {
+    let chunk = find_entry(&args.input, &args.accession, &Settings::default())?
+        .ok_or_else(|| anyhow!("{} is not in {}", args.accession, args.input.display()))?;
+    let sidecar_fasta = match &args.fasta_sidecar {
+        Some(path) => Some(Arc::new(load_fasta_map(path)?)),
+        None => None,
+    };
+    explain_entry(&chunk, sidecar_fasta, &mut std::io::stdout().lock())
+}
+
+fn run_backfill(args: &BackfillArgs, config: Option<&Path>) -> Result<()> {
+    // Same transform settings as the run that built the output
+    let settings = Settings::load_from_yaml(config)?;
+    logging::init(settings.logging.filter());
+    logging::set_repeat_limit(settings.logging.repeat_limit);
+    let inputs = if args.input.is_dir() {
+        list_xml_inputs(&args.input)?
+    } else {
+        vec![args.input.clone()]
+    };
+    let sidecar_path = args.fasta_sidecar.as_ref().or(settings.storage.fasta_sidecar_path.as_ref());
+    let sidecar_fasta = match sidecar_path {
+        Some(path) => Some(Arc::new(load_fasta(path, settings.storage.fasta_duplicates)?.0)),
+        None => None,
+    };
+    let summary = backfill(&args.output, &inputs, &args.columns, sidecar_fasta, args.release.clone(), &settings)?;
+    log_info!(
+        "run",
+        "Backfilled {} into {} file(s): {} of {} rows matched the inputs",
+        summary.columns.join(", "),
+        summary.files,
+        summary.matched,
+        summary.rows
+    );
+    Ok(())
+}
+
+fn run_serve(args: &ServeArgs, config: Option<&Path>) -> Result<()> {
+    // Same transform settings as a batch run
+    let settings = Settings::load_from_yaml(config)?;
+    logging::init(settings.logging.filter());
+    logging::set_repeat_limit(settings.logging.repeat_limit);
+    let sidecar_path = args.fasta_sidecar.as_ref().or(settings.storage.fasta_sidecar_path.as_ref());
+    let sidecar_fasta = match sidecar_path {
+        Some(path) => Some(Arc::new(load_fasta(path, settings.storage.fasta_duplicates)?.0)),
+        None => None,
+    };
+    let release = args.release.clone().or_else(|| settings.storage.sidecar_download.release.clone());
+    match &release {
+        Some(release) => log_info!("serve", "UniProt release: {}", release),
+        None => log_warn!("serve", "UniProt release unknown; `release` column will be null"),
+    }
+    serve(
+        &args.addr,
+        ServeOptions {
+            rest_url: args.rest_url.clone(),
+            workers: args.workers,
+            sidecar_fasta,
+            hooks: ParseHooks {
+                release,
+                ..transform_hooks(&settings)?
+            },
+        },
+    )
+}
+
+/// The `ParseHooks` derived from the `transform`, `schema`, `filters` and `quality`
+/// settings, shared by batch runs and `serve`.
+fn transform_hooks(settings: &Settings) -> Result<ParseHooks> {
+    let ptm_vocabulary = match &settings.transform.ptm_vocabulary_path {
+        Some(path) => {
+            let vocabulary = PtmVocabulary::load(path)
+                .with_context(|| format!("Failed to load PTM vocabulary {}", path.display()))?;
+            log_info!("run", "PTM vocabulary: {} terms from {}", vocabulary.len(), path.display());
+            vocabulary
+        }
+        None => PtmVocabulary::embedded(),
+    };
+    let plddt = match &settings.transform.alphafold_dir {
+        Some(dir) => {
+            let source = PlddtSource::open(dir)
+                .with_context(|| format!("Failed to index AlphaFold models in {}", dir.display()))?;
+            log_info!("run", "AlphaFold models: {} accessions from {}", source.len(), dir.display());
+            source
+        }
+        None => PlddtSource::default(),
+    };
+    Ok(ParseHooks {
+        ptm_vocabulary,
+        site_context: settings.transform.site_context(),
+        plddt,
+        reconcile_domain_xrefs: settings.transform.reconcile_domain_xrefs,
+        debug_mapping: settings.transform.debug_mapping,
+        schema_profile: settings.schema.profile,
+        feature_routing: FeatureRouting::with_overrides(&settings.transform.feature_routes),
+        invalid_entries: settings.transform.invalid_entries,
+        low_confidence_on_length_mismatch: settings.transform.low_confidence_on_length_mismatch,
+        keep_unsequenced_isoforms: settings.transform.keep_unsequenced_isoforms,
+        row_filter: settings
+            .filters
+            .row_expr
+            .as_deref()
+            .map(RowFilter::parse)
+            .transpose()
+            .context("Invalid filters.row_expr")?,
+        check_invariants: settings.quality.check_invariants,
+        ..ParseHooks::default()
+    })
+}
+
+/// Resolves a sidecar FASTA via the download cache when none is configured.
+///
+/// Only applies when `storage.sidecar_download.enabled` is set and the input
+/// actually contains isoforms; on success the resolved path is written back into
+/// `settings` so both pipeline modes pick it up. The cache is keyed on the
+/// input's release (CLI, proteome download, XML root or reldate.txt) when
+/// `sidecar_download.release` is unset.
+fn resolve_missing_sidecar(
+    settings: &mut Settings,
+    cli_release: Option<&str>,
+    downloaded_release: Option<&str>,
+) -> Result<Option<SidecarInfo>> {
+    let download = &settings.storage.sidecar_download;
+    if settings.storage.fasta_sidecar_path.is_some() || !download.enabled {
+        return Ok(None);
+    }
+
+    if !input_has_isoforms(settings.input_path()?, settings, download.probe_entries)? {
+        log_info!(
+            "run",
+            "No isoforms found in the first {} entries; skipping sidecar download",
+            download.probe_entries
+        );
+        return Ok(None);
+    }
+
+    log_info!(
+        "run",
+        "Isoforms detected without a sidecar; resolving varsplic FASTA into {}",
+        download.cache_dir.display()
+    );
+    let mut download = download.clone();
+    if download.release.is_none() {
+        let input = settings.input_path()?;
+        download.release = resolve_release(cli_release, downloaded_release, input, settings)?
+            .map(|release| release.version);
+    }
+    if download.release.is_none() {
+        log_warn!("run", "UniProt release unknown; downloading the sidecar instead of reusing the cache");
+    }
+    let info = resolve_sidecar(&download)?;
+    log_info!(
+        "run",
+        "Sidecar FASTA {} ({}): {} sha256={}",
+        if info.cache_hit { "cached" } else { "downloaded" },
+        info.source_url,
+        info.path.display(),
+        info.sha256
+    );
+    settings.storage.fasta_sidecar_path = Some(info.path.clone());
+    Ok(Some(info))
+}
+
+/// Loads the configured sidecar FASTA and spot-checks it against the input release.
+///
+/// A suspect sidecar is logged as a warning, or fails the run when
+/// `storage.sidecar_check.fail_on_mismatch` is set.
+fn load_sidecar_fasta(
+    settings: &Settings,
+    metrics: &Metrics,
+) -> Result<(Option<SidecarFasta>, Option<SidecarCheck>)> {
+    let Some(ref path) = settings.storage.fasta_sidecar_path else {
+        return Ok((None, None));
+    };
+    let started = std::time::Instant::now();
+    let (map, stats) = load_fasta(path, settings.storage.fasta_duplicates)?;
+    log_info!(
+        "run",
+        "Loaded {} sidecar FASTA records from {} ({:.1}s)",
+        stats.loaded,
+        path.display(),
+        started.elapsed().as_secs_f64()
+    );
+    metrics.add_fasta_records_loaded(stats.loaded);
+    metrics.add_fasta_records_skipped(stats.skipped());
+    if stats.skipped() > 0 {
+        log_warn!(
+            "run",
+            "Sidecar FASTA {}: loaded {} records, skipped {} duplicate(s) and {} without an accession or sequence",
+            path.display(),
+            stats.loaded,
+            stats.duplicates,
+            stats.invalid
+        );
+    }
+
+    let cfg = &settings.storage.sidecar_check;
+    if cfg.sample_entries == 0 {
+        return Ok((Some(Arc::new(map)), None));
+    }
+
+    let check = check_sidecar_consistency(settings.input_path()?, settings, &map, cfg)?;
+    let ratio = check.mismatch_ratio();
+    if check.is_suspect(cfg) {
+        let msg = format!(
+            "Sidecar FASTA {} looks like a different release than the XML: {}/{} checked isoforms disagree ({:.1}% > {:.1}%), e.g. {}",
+            path.display(),
+            check.length_mismatches + check.missing_records,
+            check.isoforms_checked + check.missing_records,
+            ratio * 100.0,
+            cfg.max_mismatch_ratio * 100.0,
+            check.examples.join(", ")
+        );
+        if cfg.fail_on_mismatch {
+            return Err(anyhow!(msg));
+        }
+        log_warn!("run", "{}", msg);
+    } else {
+        log_info!(
+            "run",
+            "Sidecar consistency check passed: {} isoforms across {} entries ({:.1}% mismatched)",
+            check.isoforms_checked,
+            check.entries_checked,
+            ratio * 100.0
+        );
+    }
+
+    Ok((Some(Arc::new(map)), Some(check)))
+}
+
 /// Process a single XML file through the ETL pipeline.
 /// Creates its own channel and writer thread for complete isolation.
 fn process_single_file<M: MetricsCollector>(
@@ -251,28 +919,30 @@ fn process_single_file<M: MetricsCollector>(
     settings: &Settings,
     metrics: &M,
     sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    hooks: &ParseHooks,
 ) -> Result<()> {
     // Create bounded channel for this file (isolated from other files)
     let (tx, rx) = bounded(settings.performance.channel_capacity);
 
-    // Writer thread: consumes RecordBatches, writes Parquet
+    // Writer thread: consumes RecordBatches, writes Parquet (or DuckDB)
     let output_path_owned = output_path.to_path_buf();
     let writer_metrics = metrics.clone();
     let writer_settings = settings.clone();
     let writer_handle = thread::spawn(move || {
-        write_batches(rx, &output_path_owned, &writer_metrics, &writer_settings)
+        write_output(rx, &output_path_owned, &writer_metrics, &writer_settings)
     });
 
     // Create XML reader for this file
     let reader = create_xml_reader(input_path, settings, metrics)?;
 
     // Run the parser
-    let parse_result = parse_entries(
+    let parse_result = parse_entries_with_hooks(
         reader,
         tx,
         metrics,
         settings.performance.batch_size,
         sidecar_fasta,
+        hooks,
     );
 
     // Wait for writer to finish
@@ -281,55 +951,109 @@ fn process_single_file<M: MetricsCollector>(
     // Propagate any errors
     parse_result?;
     writer_result?;
+    if hooks.cancel.is_cancelled() {
+        return Err(anyhow!("Cancelled before the end of {}", input_path.display()));
+    }
+
+    if settings.storage.sort_by_parent_id {
+        sort_parquet_by_parent_id(output_path, settings)?;
+    }
 
     Ok(())
 }
 
 
-/// Derive output parquet path from input XML path.
-/// Handles both .xml and .xml.gz extensions.
-fn derive_output_path(input_path: &Path, output_dir: &Path) -> Result<std::path::PathBuf> {
-    let file_name = input_path
-        .file_name()
-        .ok_or_else(|| anyhow!("Input path has no filename: {}", input_path.display()))?
-        .to_string_lossy();
-
-    // Strip .gz if present, then .xml
-    let stem = file_name
-        .strip_suffix(".gz")
-        .unwrap_or(&file_name);
-    let stem = stem
-        .strip_suffix(".xml")
-        .unwrap_or(stem);
-
-    Ok(output_dir.join(format!("{}.parquet", stem)))
-}
-
-/// Run the ETL pipeline in swarm mode: process all XML files in a directory in parallel.
-fn run_swarm_pipeline(
-    input_dir: &Path,
+/// Process one XML file into `storage.accession_shards` Parquet shards in `output_dir`,
+/// one writer thread per shard. Returns the shard files in shard order.
+fn process_sharded_file<M: MetricsCollector>(
+    input_path: &Path,
     output_dir: &Path,
     settings: &Settings,
-    metrics: &Metrics,
+    metrics: &M,
     sidecar_fasta: Option<Arc<HashMap<String, String>>>,
-) -> Result<()> {
-    // Create output directory if it doesn't exist
-    fs::create_dir_all(output_dir)?;
+    hooks: &ParseHooks,
+) -> Result<Vec<PathBuf>> {
+    let shards = settings.storage.accession_shards;
+    fs::create_dir_all(output_dir).with_context(|| format!("Failed to create {}", output_dir.display()))?;
+    let outputs: Vec<PathBuf> = (0..shards).map(|i| shard_path(output_dir, i, shards)).collect();
 
-    // Find all XML files (both .xml and .xml.gz)
-    let pattern_xml = input_dir.join("*.xml").to_string_lossy().to_string();
-    let pattern_gz = input_dir.join("*.xml.gz").to_string_lossy().to_string();
+    let mut senders = Vec::with_capacity(shards);
+    let mut writers = Vec::with_capacity(shards);
+    for output in &outputs {
+        let (tx, rx) = bounded(settings.performance.channel_capacity);
+        senders.push(tx);
+        let (output, writer_metrics, writer_settings) = (output.clone(), metrics.clone(), settings.clone());
+        writers.push(thread::spawn(move || write_output(rx, &output, &writer_metrics, &writer_settings)));
+    }
 
-    let mut files: Vec<std::path::PathBuf> = Vec::new();
+    let reader = create_xml_reader(input_path, settings, metrics)?;
+    let parse_result = parse_entries_sharded(
+        reader,
+        senders,
+        metrics,
+        settings.performance.batch_size,
+        sidecar_fasta,
+        hooks,
+    );
+    let writer_result = writers
+        .into_iter()
+        .map(|handle| handle.join().expect("Writer thread panicked"))
+        .fold(Ok(()), Result::and);
 
-    for pattern in [&pattern_xml, &pattern_gz] {
-        for entry in glob(pattern)? {
-            match entry {
-                Ok(path) => files.push(path),
-                Err(e) => eprintln!("[WARN] Failed to read glob entry: {}", e),
-            }
+    parse_result?;
+    writer_result?;
+    if hooks.cancel.is_cancelled() {
+        return Err(anyhow!("Cancelled before the end of {}", input_path.display()));
+    }
+    log_info!("run", "Wrote {} accession shards to {}", shards, output_dir.display());
+
+    if settings.storage.sort_by_parent_id {
+        for output in &outputs {
+            sort_parquet_by_parent_id(output, settings)?;
         }
     }
+    Ok(outputs)
+}
+
+/// Derive output path from input XML path, with the extension of `format`.
+/// Handles both .xml and .xml.gz extensions (case-insensitive).
+fn derive_output_path(
+    input_path: &Path,
+    output_dir: &Path,
+    format: OutputFormat,
+) -> Result<std::path::PathBuf> {
+    let mut file_name = strip_xml_extensions(input_path)
+        .ok_or_else(|| anyhow!("Input path has no filename: {}", input_path.display()))?;
+    file_name.push(".");
+    file_name.push(format.extension());
+
+    Ok(output_dir.join(file_name))
+}
+
+/// Shared inputs of the parallel (swarm and watch) pipeline modes.
+struct SwarmContext<'a> {
+    output_dir: &'a Path,
+    settings: &'a Settings,
+    metrics: &'a Metrics,
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    governor: Option<&'a MemoryGovernor>,
+    hooks: &'a ParseHooks,
+    /// Input size estimates; files are started largest first when present
+    preflight: Option<&'a Preflight>,
+}
+
+/// Result of processing one input file in the swarm.
+struct FileOutcome {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    error: Option<String>,
+}
+
+/// Run the ETL pipeline in swarm mode: process all XML files in a directory in parallel.
+fn run_swarm_pipeline(input_dir: &Path, swarm: &SwarmContext) -> Result<()> {
+    // Find all XML files (both .xml and .xml.gz)
+    let files = list_xml_inputs(input_dir)
+        .map_err(|e| anyhow!("Failed to list {}: {}", input_dir.display(), e))?;
 
     if files.is_empty() {
         return Err(anyhow!(
@@ -338,44 +1062,35 @@ fn run_swarm_pipeline(
         ));
     }
 
-    eprintln!("[INFO] Swarm mode: found {} XML files to process", files.len());
-
-    // Track failures across parallel execution
-    let failure_count = Arc::new(AtomicUsize::new(0));
-
-    // Process files in parallel using rayon with per-file local metrics
-    files.par_iter().for_each(|input_path| {
-        let output_path = match derive_output_path(input_path, output_dir) {
-            Ok(p) => p,
-            Err(e) => {
-                eprintln!("[ERROR] Failed to derive output path for {}: {}", input_path.display(), e);
-                failure_count.fetch_add(1, Ordering::Relaxed);
-                return;
-            }
-        };
-
-        eprintln!("[INFO] Processing: {} -> {}", input_path.display(), output_path.display());
-
-        // Create thread-local metrics for this file (zero cross-thread contention)
-        // The Mutex is uncontended since each worker operates on its own LocalMetricsAdapter
-        let local_metrics_adapter = LocalMetricsAdapter::new();
-
-        if let Err(e) = process_single_file(
-            input_path,
-            &output_path,
-            settings,
-            &local_metrics_adapter,
-            sidecar_fasta.clone(),
-        ) {
-            eprintln!("[ERROR] Failed to process {}: {:#}", input_path.display(), e);
-            failure_count.fetch_add(1, Ordering::Relaxed);
-        }
+    log_info!("run", "Swarm mode: found {} XML files to process", files.len());
 
-        // Merge local metrics into global (1 atomic operation per metric field)
-        local_metrics_adapter.merge_into(metrics);
-    });
+    let outcomes = run_swarm_files(&files, swarm)?;
+    let failures = outcomes.iter().filter(|outcome| outcome.error.is_some()).count();
 
-    let failures = failure_count.load(Ordering::Relaxed);
+    let mut outputs = Vec::new();
+    for output in outcomes.into_iter().filter_map(|o| o.output) {
+        outputs.extend(output_files(&output)?);
+    }
+    if swarm.settings.storage.write_index {
+        let index_path = swarm.output_dir.join(SWARM_INDEX_FILE);
+        let rows = write_index(&outputs, &index_path)?;
+        log_info!("run", "Indexed {} rows of {} files in {}", rows, outputs.len(), index_path.display());
+    }
+    if let Some(ptm_flat_path) = &swarm.settings.storage.ptm_flat.path {
+        let rows = write_ptm_flat(&outputs, ptm_flat_path, &swarm.settings.storage.ptm_flat)?;
+        log_info!("run", "Wrote {} PTM site rows to {}", rows, ptm_flat_path.display());
+    }
+    if let Some(variant_peptides_path) = &swarm.settings.storage.variant_peptides.path {
+        let rows = write_variant_peptides(&outputs, variant_peptides_path, &swarm.settings.storage.variant_peptides)?;
+        log_info!("run", "Wrote {} variant peptide rows to {}", rows, variant_peptides_path.display());
+    }
+    if let Some(embedding_dir) = &swarm.settings.storage.embedding_export.dir {
+        export_embedding_shards(&outputs, embedding_dir, swarm.settings)?;
+    }
+    if let Some(id_mapping_path) = &swarm.settings.storage.id_mapping_path {
+        let rows = write_id_mapping(&outputs, id_mapping_path)?;
+        log_info!("run", "Wrote {} id mapping rows to {}", rows, id_mapping_path.display());
+    }
     if failures > 0 {
         Err(anyhow!(
             "Swarm completed with {} file(s) failed out of {}",
@@ -383,32 +1098,224 @@ fn run_swarm_pipeline(
             files.len()
         ))
     } else {
-        eprintln!("[INFO] Swarm completed successfully: {} files processed", files.len());
+        log_info!("run", "Swarm completed successfully: {} files processed", files.len());
         Ok(())
     }
 }
 
+/// Processes `files` in parallel, returning one outcome per file (in input order).
+fn run_swarm_files(files: &[PathBuf], swarm: &SwarmContext) -> Result<Vec<FileOutcome>> {
+    // Create output directory if it doesn't exist
+    fs::create_dir_all(swarm.output_dir)?;
+
+    // Process files in parallel using rayon with per-file local metrics
+    let outcomes = match swarm.preflight {
+        Some(preflight) => {
+            // Workers pull files off the bridged iterator in order, so the largest start first
+            let mut order: Vec<usize> = (0..files.len()).collect();
+            order.sort_by_cached_key(|&i| Reverse(preflight.bytes_of(&files[i]).unwrap_or(0)));
+            let mut outcomes: Vec<(usize, FileOutcome)> = order
+                .into_iter()
+                .par_bridge()
+                .map(|i| (i, process_swarm_file(&files[i], swarm)))
+                .collect();
+            outcomes.sort_by_key(|(i, _)| *i);
+            outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+        }
+        None => files
+            .par_iter()
+            .map(|input_path| process_swarm_file(input_path, swarm))
+            .collect(),
+    };
+
+    if let Some(pauses) = swarm.governor.map(MemoryGovernor::pauses).filter(|&p| p > 0) {
+        log_info!("run", "Swarm: {} worker start(s) delayed by max_rss_gb", pauses);
+    }
+
+    Ok(outcomes)
+}
+
+/// Processes one swarm input into its own output file.
+fn process_swarm_file(input_path: &Path, swarm: &SwarmContext) -> FileOutcome {
+    // Hold off starting a new file while process RSS is over performance.max_rss_gb
+    let _permit = swarm.governor.map(MemoryGovernor::acquire);
+    if swarm.hooks.cancel.is_cancelled() {
+        return FileOutcome {
+            input: input_path.to_path_buf(),
+            output: None,
+            error: Some("Cancelled before start".to_string()),
+        };
+    }
+
+    let format = swarm.settings.storage.output_format;
+    let output_path = match derive_output_path(input_path, swarm.output_dir, format) {
+        Ok(p) => p,
+        Err(e) => {
+            log_error!("run", "Failed to derive output path for {}: {}", input_path.display(), e);
+            return FileOutcome {
+                input: input_path.to_path_buf(),
+                output: None,
+                error: Some(format!("{:#}", e)),
+            };
+        }
+    };
+
+    log_info!("run", "Processing: {} -> {}", input_path.display(), output_path.display());
+
+    // Per-file counters: only this file's threads touch them, merged once at the end
+    let file_metrics = Metrics::new();
+
+    let result = process_single_file(
+        input_path,
+        &output_path,
+        swarm.settings,
+        &file_metrics,
+        swarm.sidecar_fasta.clone(),
+        swarm.hooks,
+    );
+
+    // Merge local metrics into global (1 atomic operation per metric field)
+    file_metrics.merge_into(swarm.metrics);
+
+    match result {
+        Ok(()) => FileOutcome {
+            input: input_path.to_path_buf(),
+            output: Some(output_path),
+            error: None,
+        },
+        Err(e) => {
+            log_error!("run", "Failed to process {}: {:#}", input_path.display(), e);
+            FileOutcome {
+                input: input_path.to_path_buf(),
+                output: None,
+                error: Some(format!("{:#}", e)),
+            }
+        }
+    }
+}
+
+/// Watch mode: process XML drops in `landing_dir` through the swarm path until
+/// SIGINT/SIGTERM.
+fn run_watch(landing_dir: &Path, swarm: &SwarmContext, manifest_path: &Path) -> Result<()> {
+    let landing = LandingDir::open(landing_dir)?;
+    log_info!(
+        "run",
+        "Watching {} (manifest: {})",
+        landing.dir().display(),
+        manifest_path.display()
+    );
+
+    let cancel = &swarm.hooks.cancel;
+    while !cancel.is_cancelled() {
+        let files = landing.pending(watch::SETTLE_INTERVAL, cancel)?;
+        if !files.is_empty() {
+            log_info!("run", "Watch: {} new XML file(s)", files.len());
+            for outcome in run_swarm_files(&files, swarm)? {
+                let succeeded = outcome.error.is_none();
+                if !succeeded && cancel.is_cancelled() {
+                    // Interrupted: leave the drop in place to be processed on restart
+                    continue;
+                }
+                let archived_to = landing.archive(&outcome.input, succeeded)?;
+                append_manifest(
+                    manifest_path,
+                    &ManifestRecord {
+                        processed_at: Utc::now(),
+                        input: outcome.input,
+                        archived_to,
+                        output: outcome.output,
+                        status: if succeeded { "ok" } else { "failed" },
+                        error: outcome.error,
+                    },
+                )?;
+            }
+            // Something may have landed while we were busy
+            continue;
+        }
+        landing.wait(watch::RESCAN_INTERVAL, cancel);
+    }
+    Ok(())
+}
+
 /// Legacy wrapper for single-file mode that maintains backwards compatibility.
 fn run_etl_pipeline(
     settings: &Settings,
     metrics: &Metrics,
     _channel_stats: &Arc<ChannelStats>,
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    hooks: &ParseHooks,
 ) -> Result<()> {
     let input_path = settings.input_path()?;
     let output_path = &settings.storage.output_path;
 
-    // Load sidecar FASTA (shared for single file mode)
-    let sidecar_fasta = if let Some(ref path) = settings.storage.fasta_sidecar_path {
-        let map = load_fasta_map(path)?;
-        Some(Arc::new(map))
+    let outputs = if settings.storage.accession_shards > 0 {
+        process_sharded_file(input_path, output_path, settings, metrics, sidecar_fasta, hooks)?
     } else {
-        None
+        process_single_file(input_path, output_path, settings, metrics, sidecar_fasta, hooks)?;
+        // One file, or the parts of a dataset directory
+        output_files(output_path)?
     };
 
-    process_single_file(input_path, output_path, settings, metrics, sidecar_fasta)
+    if settings.storage.write_index {
+        let index_path = index_path_for(output_path);
+        let rows = write_index(&outputs, &index_path)?;
+        log_info!("run", "Indexed {} rows in {}", rows, index_path.display());
+    }
+    if let Some(ptm_flat_path) = &settings.storage.ptm_flat.path {
+        let rows = write_ptm_flat(&outputs, ptm_flat_path, &settings.storage.ptm_flat)?;
+        log_info!("run", "Wrote {} PTM site rows to {}", rows, ptm_flat_path.display());
+    }
+    if let Some(variant_peptides_path) = &settings.storage.variant_peptides.path {
+        let rows = write_variant_peptides(&outputs, variant_peptides_path, &settings.storage.variant_peptides)?;
+        log_info!("run", "Wrote {} variant peptide rows to {}", rows, variant_peptides_path.display());
+    }
+    if let Some(embedding_dir) = &settings.storage.embedding_export.dir {
+        export_embedding_shards(&outputs, embedding_dir, settings)?;
+    }
+    if let Some(id_mapping_path) = &settings.storage.id_mapping_path {
+        let rows = write_id_mapping(&outputs, id_mapping_path)?;
+        log_info!("run", "Wrote {} id mapping rows to {}", rows, id_mapping_path.display());
+    }
+    Ok(())
 }
 
-fn print_summary_to_tee(metrics: &Metrics, logger: &mut TeeWriter) {
+fn export_embedding_shards(outputs: &[PathBuf], dir: &Path, settings: &Settings) -> Result<()> {
+    let manifest = write_embedding_shards(outputs, dir, &settings.storage.embedding_export)?;
+    log_info!(
+        "run",
+        "Exported {} sequences in {} shards to {}",
+        manifest.total_rows,
+        manifest.shards.len(),
+        dir.display()
+    );
+    Ok(())
+}
+
+/// "42.0% of ~1200000 entries | ETA 0:03:10 | " from the bytes read against the
+/// preflight estimate (swarm files count once they finish).
+fn progress_estimate(bytes_read: u64, total_bytes: u64, total_entries: u64, elapsed: f64) -> String {
+    let fraction = if total_bytes > 0 {
+        (bytes_read as f64 / total_bytes as f64).min(1.0)
+    } else {
+        0.0
+    };
+    let eta = if fraction > 0.0 {
+        let secs = (elapsed * (1.0 - fraction) / fraction) as u64;
+        format!(" | ETA {}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        String::new()
+    };
+    format!("{:.1}% of ~{} entries{} | ", fraction * 100.0, total_entries, eta)
+}
+
+/// Untagged line of the end-of-run summary.
+macro_rules! summary {
+    ($($arg:tt)*) => {
+        log_event!(Level::Info, "run", None, $($arg)*)
+    };
+}
+
+fn log_summary(metrics: &Metrics) {
     let elapsed = metrics.elapsed_secs();
     let entries = metrics.entries();
     let batches = metrics.batches();
@@ -424,21 +1331,43 @@ fn print_summary_to_tee(metrics: &Metrics, logger: &mut TeeWriter) {
     let mb_read = bytes_read as f64 / (1024.0 * 1024.0);
     let mb_written = bytes_written as f64 / (1024.0 * 1024.0);
 
-    log!(logger, "");
-    log!(logger, "=== ETL Summary ===");
-    log!(logger, "Entries parsed:  {}", entries);
-    log!(logger, "Batches written: {}", batches);
-    log!(logger, "PTMs attempted:  {}", ptm_attempted);
-    log!(logger, "PTMs mapped:     {}", ptm_mapped);
-    log!(logger, "PTMs failed:     {}", ptm_failed);
-    log!(logger, "Features:        {}", features);
-    log!(logger, "Isoforms:        {}", isoforms);
-    log!(logger, "Time elapsed:    {:.2}s", elapsed);
-    log!(
-        logger,
+    summary!("");
+    summary!("=== ETL Summary ===");
+    summary!("Entries parsed:  {}", entries);
+    summary!("Batches written: {}", batches);
+    summary!("PTMs attempted:  {}", ptm_attempted);
+    summary!("PTMs mapped:     {}", ptm_mapped);
+    summary!("PTMs failed:     {}", ptm_failed);
+    summary!("Features:        {}", features);
+    for (category, count) in metrics.feature_category_counts() {
+        summary!("  - {:<19} {}", format!("{}:", category), count);
+    }
+    summary!("Isoforms:        {}", isoforms);
+    summary!("CRC64 mismatches: {}", metrics.checksum_mismatches());
+    summary!("Duplicates:      {}", metrics.duplicate_entries());
+    summary!("Invalid entries: {}", metrics.invalid_entries());
+    summary!("VSP conflicts:   {}", metrics.vsp_conflicts());
+    summary!("Length mismatch: {}", metrics.isoform_length_mismatch());
+    if metrics.fasta_records_loaded() + metrics.fasta_records_skipped() > 0 {
+        summary!(
+            "Sidecar FASTA:   {} loaded, {} skipped",
+            metrics.fasta_records_loaded(),
+            metrics.fasta_records_skipped()
+        );
+    }
+    if metrics.rows_filtered() > 0 {
+        summary!("Rows filtered:   {}", metrics.rows_filtered());
+    }
+    summary!("Time elapsed:    {:.2}s", elapsed);
+    summary!(
         "Throughput:      {:.0} entries/sec",
         entries_per_sec
     );
-    log!(logger, "Bytes read:      {:.2} MB", mb_read);
-    log!(logger, "Bytes written:   {:.2} MB", mb_written);
+    summary!("Bytes read:      {:.2} MB", mb_read);
+    summary!("Bytes written:   {:.2} MB", mb_written);
+    summary!(
+        "Builder memory:  {:.2} MB peak per batch ({:.0} bytes/row)",
+        metrics.peak_builder_bytes() as f64 / (1024.0 * 1024.0),
+        metrics.builder_bytes_per_row()
+    );
 }
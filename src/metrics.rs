@@ -1,9 +1,62 @@
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::Instant;
 
-/// Trait for metrics collection, implemented by both global (atomic) and local (plain) metrics.
-/// This allows pipeline components to be generic over metrics type while maintaining zero overhead.
+/// Extracted feature and comment categories, counted per run so extraction drift in
+/// any one of them shows up release over release.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeatureCategory {
+    Generic,
+    ActiveSite,
+    BindingSite,
+    MetalCoordination,
+    MutagenesisSite,
+    Domain,
+    NaturalVariant,
+    SubcellularLocation,
+    Subunit,
+    Interaction,
+    OtherComment,
+}
+
+impl FeatureCategory {
+    pub const COUNT: usize = 11;
+
+    pub const ALL: [FeatureCategory; Self::COUNT] = [
+        FeatureCategory::Generic,
+        FeatureCategory::ActiveSite,
+        FeatureCategory::BindingSite,
+        FeatureCategory::MetalCoordination,
+        FeatureCategory::MutagenesisSite,
+        FeatureCategory::Domain,
+        FeatureCategory::NaturalVariant,
+        FeatureCategory::SubcellularLocation,
+        FeatureCategory::Subunit,
+        FeatureCategory::Interaction,
+        FeatureCategory::OtherComment,
+    ];
+
+    /// Name of the output column the category is written to.
+    pub fn name(self) -> &'static str {
+        match self {
+            FeatureCategory::Generic => "features",
+            FeatureCategory::ActiveSite => "active_sites",
+            FeatureCategory::BindingSite => "binding_sites",
+            FeatureCategory::MetalCoordination => "metal_coordinations",
+            FeatureCategory::MutagenesisSite => "mutagenesis_sites",
+            FeatureCategory::Domain => "domains",
+            FeatureCategory::NaturalVariant => "natural_variants",
+            FeatureCategory::SubcellularLocation => "location",
+            FeatureCategory::Subunit => "subunits",
+            FeatureCategory::Interaction => "interactions",
+            FeatureCategory::OtherComment => "other_comments",
+        }
+    }
+}
+
+/// Counters the pipeline reports into. `Metrics` is the implementation; pipeline components
+/// stay generic over it so tests and benchmarks can count into their own collector.
 pub trait MetricsCollector: Clone + Send + Sync + 'static {
     fn inc_entries(&self);
     fn inc_batches(&self);
@@ -20,226 +73,20 @@ pub trait MetricsCollector: Clone + Send + Sync + 'static {
     fn add_ptm_failed_vsp_unresolvable(&self, count: u64);
     fn add_ptm_failed_isoform_oob(&self, count: u64);
     fn add_ptm_failed_residue_mismatch(&self, count: u64);
-}
-
-/// Thread-local metrics for zero-contention counting in parallel workloads.
-/// Use this in worker threads, then merge into global Metrics at the end.
-#[derive(Default)]
-pub struct LocalMetrics {
-    entries_parsed: u64,
-    batches_written: u64,
-    bytes_read: u64,
-    bytes_written: u64,
-    features_count: u64,
-    isoforms_count: u64,
-    ptm_attempted: u64,
-    ptm_mapped: u64,
-    ptm_failed: u64,
-    ptm_failed_canonical_oob: u64,
-    ptm_failed_vsp_deletion: u64,
-    ptm_failed_mapper_oob: u64,
-    ptm_failed_vsp_unresolvable: u64,
-    ptm_failed_isoform_oob: u64,
-    ptm_failed_residue_mismatch: u64,
-}
-
-impl LocalMetrics {
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    pub fn inc_entries(&mut self) {
-        self.entries_parsed += 1;
-    }
-
-    pub fn inc_batches(&mut self) {
-        self.batches_written += 1;
-    }
-
-    pub fn add_bytes_read(&mut self, bytes: u64) {
-        self.bytes_read += bytes;
-    }
-
-    pub fn add_bytes_written(&mut self, bytes: u64) {
-        self.bytes_written += bytes;
-    }
-
-    pub fn add_features(&mut self, count: u64) {
-        self.features_count += count;
-    }
-
-    pub fn add_isoforms(&mut self, count: u64) {
-        self.isoforms_count += count;
-    }
-
-    pub fn add_ptm_attempted(&mut self, count: u64) {
-        self.ptm_attempted += count;
-    }
-
-    pub fn add_ptm_mapped(&mut self, count: u64) {
-        self.ptm_mapped += count;
-    }
-
-    pub fn add_ptm_failed(&mut self, count: u64) {
-        self.ptm_failed += count;
-    }
-
-    pub fn add_ptm_failed_canonical_oob(&mut self, count: u64) {
-        self.ptm_failed_canonical_oob += count;
-    }
-
-    pub fn add_ptm_failed_vsp_deletion(&mut self, count: u64) {
-        self.ptm_failed_vsp_deletion += count;
-    }
-
-    pub fn add_ptm_failed_mapper_oob(&mut self, count: u64) {
-        self.ptm_failed_mapper_oob += count;
-    }
-
-    pub fn add_ptm_failed_vsp_unresolvable(&mut self, count: u64) {
-        self.ptm_failed_vsp_unresolvable += count;
-    }
-
-    pub fn add_ptm_failed_isoform_oob(&mut self, count: u64) {
-        self.ptm_failed_isoform_oob += count;
-    }
-
-    pub fn add_ptm_failed_residue_mismatch(&mut self, count: u64) {
-        self.ptm_failed_residue_mismatch += count;
-    }
-
-    /// Merge this local metrics into a global Metrics instance (one atomic op per field)
-    pub fn merge_into(&self, global: &Metrics) {
-        if self.entries_parsed > 0 {
-            global.inner.entries_parsed.fetch_add(self.entries_parsed, Ordering::Relaxed);
-        }
-        if self.batches_written > 0 {
-            global.inner.batches_written.fetch_add(self.batches_written, Ordering::Relaxed);
-        }
-        if self.bytes_read > 0 {
-            global.inner.bytes_read.fetch_add(self.bytes_read, Ordering::Relaxed);
-        }
-        if self.bytes_written > 0 {
-            global.inner.bytes_written.fetch_add(self.bytes_written, Ordering::Relaxed);
-        }
-        if self.features_count > 0 {
-            global.inner.features_count.fetch_add(self.features_count, Ordering::Relaxed);
-        }
-        if self.isoforms_count > 0 {
-            global.inner.isoforms_count.fetch_add(self.isoforms_count, Ordering::Relaxed);
-        }
-        if self.ptm_attempted > 0 {
-            global.inner.ptm_attempted.fetch_add(self.ptm_attempted, Ordering::Relaxed);
-        }
-        if self.ptm_mapped > 0 {
-            global.inner.ptm_mapped.fetch_add(self.ptm_mapped, Ordering::Relaxed);
-        }
-        if self.ptm_failed > 0 {
-            global.inner.ptm_failed.fetch_add(self.ptm_failed, Ordering::Relaxed);
-        }
-        if self.ptm_failed_canonical_oob > 0 {
-            global.inner.ptm_failures.add_canonical_oob(self.ptm_failed_canonical_oob);
-        }
-        if self.ptm_failed_vsp_deletion > 0 {
-            global.inner.ptm_failures.add_vsp_deletion(self.ptm_failed_vsp_deletion);
-        }
-        if self.ptm_failed_mapper_oob > 0 {
-            global.inner.ptm_failures.add_mapper_oob(self.ptm_failed_mapper_oob);
-        }
-        if self.ptm_failed_vsp_unresolvable > 0 {
-            global.inner.ptm_failures.add_vsp_unresolvable(self.ptm_failed_vsp_unresolvable);
-        }
-        if self.ptm_failed_isoform_oob > 0 {
-            global.inner.ptm_failures.add_isoform_oob(self.ptm_failed_isoform_oob);
-        }
-        if self.ptm_failed_residue_mismatch > 0 {
-            global.inner.ptm_failures.add_residue_mismatch(self.ptm_failed_residue_mismatch);
-        }
-    }
-}
-
-/// Adapter that wraps LocalMetrics in a Metrics-compatible API using a Mutex.
-/// This allows LocalMetrics to be used with existing pipeline code that expects Clone.
-/// The Mutex ensures thread-safety, but in practice each file processing is single-threaded,
-/// so there's no actual contention (the Mutex is just to satisfy the borrow checker).
-#[derive(Clone)]
-pub struct LocalMetricsAdapter {
-    inner: Arc<Mutex<LocalMetrics>>,
-}
-
-impl LocalMetricsAdapter {
-    pub fn new() -> Self {
-        Self {
-            inner: Arc::new(Mutex::new(LocalMetrics::new())),
-        }
-    }
-
-    /// Merge the accumulated local metrics into a global Metrics instance
-    pub fn merge_into(&self, global: &Metrics) {
-        self.inner.lock().unwrap().merge_into(global);
-    }
-}
-
-impl MetricsCollector for LocalMetricsAdapter {
-    fn inc_entries(&self) {
-        self.inner.lock().unwrap().inc_entries();
-    }
-
-    fn inc_batches(&self) {
-        self.inner.lock().unwrap().inc_batches();
-    }
-
-    fn add_bytes_read(&self, bytes: u64) {
-        self.inner.lock().unwrap().add_bytes_read(bytes);
-    }
-
-    fn add_bytes_written(&self, bytes: u64) {
-        self.inner.lock().unwrap().add_bytes_written(bytes);
-    }
-
-    fn add_features(&self, count: u64) {
-        self.inner.lock().unwrap().add_features(count);
-    }
-
-    fn add_isoforms(&self, count: u64) {
-        self.inner.lock().unwrap().add_isoforms(count);
-    }
-
-    fn add_ptm_attempted(&self, count: u64) {
-        self.inner.lock().unwrap().add_ptm_attempted(count);
-    }
-
-    fn add_ptm_mapped(&self, count: u64) {
-        self.inner.lock().unwrap().add_ptm_mapped(count);
-    }
-
-    fn add_ptm_failed(&self, count: u64) {
-        self.inner.lock().unwrap().add_ptm_failed(count);
-    }
-
-    fn add_ptm_failed_canonical_oob(&self, count: u64) {
-        self.inner.lock().unwrap().add_ptm_failed_canonical_oob(count);
-    }
-
-    fn add_ptm_failed_vsp_deletion(&self, count: u64) {
-        self.inner.lock().unwrap().add_ptm_failed_vsp_deletion(count);
-    }
-
-    fn add_ptm_failed_mapper_oob(&self, count: u64) {
-        self.inner.lock().unwrap().add_ptm_failed_mapper_oob(count);
-    }
-
-    fn add_ptm_failed_vsp_unresolvable(&self, count: u64) {
-        self.inner.lock().unwrap().add_ptm_failed_vsp_unresolvable(count);
-    }
-
-    fn add_ptm_failed_isoform_oob(&self, count: u64) {
-        self.inner.lock().unwrap().add_ptm_failed_isoform_oob(count);
-    }
-
-    fn add_ptm_failed_residue_mismatch(&self, count: u64) {
-        self.inner.lock().unwrap().add_ptm_failed_residue_mismatch(count);
-    }
+    fn add_isoform_seq_missing(&self, count: u64);
+    fn add_rows_rejected(&self, count: u64);
+    fn add_checksum_mismatches(&self, count: u64);
+    fn add_duplicate_entries(&self, count: u64);
+    fn add_invalid_entries(&self, count: u64);
+    fn add_vsp_conflicts(&self, count: u64);
+    fn add_isoform_length_mismatch(&self, count: u64);
+    fn add_fasta_records_loaded(&self, count: u64);
+    fn add_fasta_records_skipped(&self, count: u64);
+    fn add_rows_filtered(&self, count: u64);
+    fn add_unknown_feature_types(&self, count: u64);
+    fn add_feature_category(&self, category: FeatureCategory, count: u64);
+    /// Builder memory of one finished batch of `rows` rows (see `EntryBuilders::last_batch_bytes`).
+    fn record_builder_memory(&self, bytes: u64, rows: u64);
 }
 
 #[derive(Clone)]
@@ -259,6 +106,21 @@ struct MetricsInner {
     ptm_mapped: AtomicU64,
     ptm_failed: AtomicU64,
     ptm_failures: PtmFailures,
+    isoform_seq_missing: AtomicU64,
+    rows_rejected: AtomicU64,
+    checksum_mismatches: AtomicU64,
+    duplicate_entries: AtomicU64,
+    invalid_entries: AtomicU64,
+    vsp_conflicts: AtomicU64,
+    isoform_length_mismatch: AtomicU64,
+    fasta_records_loaded: AtomicU64,
+    fasta_records_skipped: AtomicU64,
+    rows_filtered: AtomicU64,
+    unknown_feature_types: AtomicU64,
+    feature_categories: [AtomicU64; FeatureCategory::COUNT],
+    peak_builder_bytes: AtomicU64,
+    builder_bytes: AtomicU64,
+    builder_rows: AtomicU64,
 }
 
 struct PtmFailures {
@@ -346,72 +208,72 @@ impl Metrics {
                 ptm_mapped: AtomicU64::new(0),
                 ptm_failed: AtomicU64::new(0),
                 ptm_failures: PtmFailures::new(),
+                isoform_seq_missing: AtomicU64::new(0),
+                rows_rejected: AtomicU64::new(0),
+                checksum_mismatches: AtomicU64::new(0),
+                duplicate_entries: AtomicU64::new(0),
+                invalid_entries: AtomicU64::new(0),
+                vsp_conflicts: AtomicU64::new(0),
+                isoform_length_mismatch: AtomicU64::new(0),
+                fasta_records_loaded: AtomicU64::new(0),
+                fasta_records_skipped: AtomicU64::new(0),
+                rows_filtered: AtomicU64::new(0),
+                unknown_feature_types: AtomicU64::new(0),
+                feature_categories: std::array::from_fn(|_| AtomicU64::new(0)),
+                peak_builder_bytes: AtomicU64::new(0),
+                builder_bytes: AtomicU64::new(0),
+                builder_rows: AtomicU64::new(0),
             }),
         }
     }
 
-    pub fn inc_entries(&self) {
-        self.inner.entries_parsed.fetch_add(1, Ordering::Relaxed);
-    }
-
-    pub fn inc_batches(&self) {
-        self.inner.batches_written.fetch_add(1, Ordering::Relaxed);
-    }
-
-    pub fn add_bytes_read(&self, bytes: u64) {
-        self.inner.bytes_read.fetch_add(bytes, Ordering::Relaxed);
-    }
-
-    pub fn add_bytes_written(&self, bytes: u64) {
-        self.inner.bytes_written.fetch_add(bytes, Ordering::Relaxed);
-    }
-
-    pub fn add_features(&self, count: u64) {
-        self.inner
-            .features_count
-            .fetch_add(count, Ordering::Relaxed);
-    }
-
-    pub fn add_isoforms(&self, count: u64) {
-        self.inner
-            .isoforms_count
-            .fetch_add(count, Ordering::Relaxed);
-    }
-
-    pub fn add_ptm_attempted(&self, count: u64) {
-        self.inner.ptm_attempted.fetch_add(count, Ordering::Relaxed);
-    }
-
-    pub fn add_ptm_mapped(&self, count: u64) {
-        self.inner.ptm_mapped.fetch_add(count, Ordering::Relaxed);
-    }
-
-    pub fn add_ptm_failed(&self, count: u64) {
-        self.inner.ptm_failed.fetch_add(count, Ordering::Relaxed);
-    }
-
-    pub fn add_ptm_failed_canonical_oob(&self, count: u64) {
-        self.inner.ptm_failures.add_canonical_oob(count);
-    }
-
-    pub fn add_ptm_failed_vsp_deletion(&self, count: u64) {
-        self.inner.ptm_failures.add_vsp_deletion(count);
-    }
-
-    pub fn add_ptm_failed_mapper_oob(&self, count: u64) {
-        self.inner.ptm_failures.add_mapper_oob(count);
-    }
-
-    pub fn add_ptm_failed_vsp_unresolvable(&self, count: u64) {
-        self.inner.ptm_failures.add_vsp_unresolvable(count);
-    }
-
-    pub fn add_ptm_failed_isoform_oob(&self, count: u64) {
-        self.inner.ptm_failures.add_isoform_oob(count);
-    }
-
-    pub fn add_ptm_failed_residue_mismatch(&self, count: u64) {
-        self.inner.ptm_failures.add_residue_mismatch(count);
+    /// Adds this collector's counts into `global` (one atomic op per counter).
+    ///
+    /// Swarm workers count each file into a fresh `Metrics`, touched only by that file's
+    /// parse, build and write threads, and merge it once the file is done, so workers
+    /// never contend on the global counters.
+    pub fn merge_into(&self, global: &Metrics) {
+        let (local, total) = (&self.inner, &global.inner);
+        let pairs = [
+            (&local.entries_parsed, &total.entries_parsed),
+            (&local.batches_written, &total.batches_written),
+            (&local.bytes_read, &total.bytes_read),
+            (&local.bytes_written, &total.bytes_written),
+            (&local.features_count, &total.features_count),
+            (&local.isoforms_count, &total.isoforms_count),
+            (&local.ptm_attempted, &total.ptm_attempted),
+            (&local.ptm_mapped, &total.ptm_mapped),
+            (&local.ptm_failed, &total.ptm_failed),
+            (&local.ptm_failures.canonical_oob, &total.ptm_failures.canonical_oob),
+            (&local.ptm_failures.vsp_deletion, &total.ptm_failures.vsp_deletion),
+            (&local.ptm_failures.mapper_oob, &total.ptm_failures.mapper_oob),
+            (&local.ptm_failures.vsp_unresolvable, &total.ptm_failures.vsp_unresolvable),
+            (&local.ptm_failures.isoform_oob, &total.ptm_failures.isoform_oob),
+            (&local.ptm_failures.residue_mismatch, &total.ptm_failures.residue_mismatch),
+            (&local.isoform_seq_missing, &total.isoform_seq_missing),
+            (&local.rows_rejected, &total.rows_rejected),
+            (&local.checksum_mismatches, &total.checksum_mismatches),
+            (&local.duplicate_entries, &total.duplicate_entries),
+            (&local.invalid_entries, &total.invalid_entries),
+            (&local.vsp_conflicts, &total.vsp_conflicts),
+            (&local.isoform_length_mismatch, &total.isoform_length_mismatch),
+            (&local.fasta_records_loaded, &total.fasta_records_loaded),
+            (&local.fasta_records_skipped, &total.fasta_records_skipped),
+            (&local.rows_filtered, &total.rows_filtered),
+            (&local.unknown_feature_types, &total.unknown_feature_types),
+            (&local.builder_bytes, &total.builder_bytes),
+            (&local.builder_rows, &total.builder_rows),
+        ];
+        let categories = local.feature_categories.iter().zip(&total.feature_categories);
+        for (from, to) in pairs.into_iter().chain(categories) {
+            let count = from.load(Ordering::Relaxed);
+            if count > 0 {
+                to.fetch_add(count, Ordering::Relaxed);
+            }
+        }
+        total
+            .peak_builder_bytes
+            .fetch_max(local.peak_builder_bytes.load(Ordering::Relaxed), Ordering::Relaxed);
     }
 
     pub fn entries(&self) -> u64 {
@@ -474,6 +336,86 @@ impl Metrics {
         self.inner.ptm_failures.residue_mismatch()
     }
 
+    /// Isoform rows not emitted because the sidecar FASTA lacked their sequence.
+    pub fn isoform_seq_missing(&self) -> u64 {
+        self.inner.isoform_seq_missing.load(Ordering::Relaxed)
+    }
+
+    /// Rows dropped after parsing (missing isoform sequence, enricher drops).
+    pub fn rows_rejected(&self) -> u64 {
+        self.inner.rows_rejected.load(Ordering::Relaxed)
+    }
+
+    /// Canonical sequences whose CRC64 disagrees with `<sequence checksum=...>`.
+    pub fn checksum_mismatches(&self) -> u64 {
+        self.inner.checksum_mismatches.load(Ordering::Relaxed)
+    }
+
+    /// Entries dropped as repeats of an accession (`transform.deduplicate`).
+    pub fn duplicate_entries(&self) -> u64 {
+        self.inner.duplicate_entries.load(Ordering::Relaxed)
+    }
+
+    /// Entries dropped for a missing accession or empty sequence (`transform.invalid_entries`).
+    pub fn invalid_entries(&self) -> u64 {
+        self.inner.invalid_entries.load(Ordering::Relaxed)
+    }
+
+    /// Isoforms whose VSP edits overlap irreconcilably; none of their PTM sites are mapped.
+    pub fn vsp_conflicts(&self) -> u64 {
+        self.inner.vsp_conflicts.load(Ordering::Relaxed)
+    }
+
+    /// Isoforms whose sidecar length differs from canonical length + VSP delta.
+    pub fn isoform_length_mismatch(&self) -> u64 {
+        self.inner.isoform_length_mismatch.load(Ordering::Relaxed)
+    }
+
+    /// Sidecar FASTA records loaded into the isoform sequence map.
+    pub fn fasta_records_loaded(&self) -> u64 {
+        self.inner.fasta_records_loaded.load(Ordering::Relaxed)
+    }
+
+    /// Sidecar FASTA records skipped as duplicates or for a missing accession/sequence.
+    pub fn fasta_records_skipped(&self) -> u64 {
+        self.inner.fasta_records_skipped.load(Ordering::Relaxed)
+    }
+
+    /// Rows dropped because they did not match `filters.row_expr`.
+    pub fn rows_filtered(&self) -> u64 {
+        self.inner.rows_filtered.load(Ordering::Relaxed)
+    }
+
+    /// Features whose type is missing from the routing table (`transform.feature_routes`).
+    pub fn unknown_feature_types(&self) -> u64 {
+        self.inner.unknown_feature_types.load(Ordering::Relaxed)
+    }
+
+    /// Extracted items per category, keyed by output column name.
+    pub fn feature_category_counts(&self) -> BTreeMap<&'static str, u64> {
+        FeatureCategory::ALL
+            .iter()
+            .map(|&category| {
+                let count = self.inner.feature_categories[category as usize].load(Ordering::Relaxed);
+                (category.name(), count)
+            })
+            .collect()
+    }
+
+    /// Largest builder memory of any single batch, in bytes.
+    pub fn peak_builder_bytes(&self) -> u64 {
+        self.inner.peak_builder_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Average builder memory per row over all batches (0 before the first batch).
+    pub fn builder_bytes_per_row(&self) -> f64 {
+        let rows = self.inner.builder_rows.load(Ordering::Relaxed);
+        if rows == 0 {
+            return 0.0;
+        }
+        self.inner.builder_bytes.load(Ordering::Relaxed) as f64 / rows as f64
+    }
+
     pub fn elapsed_secs(&self) -> f64 {
         self.inner.start_time.elapsed().as_secs_f64()
     }
@@ -588,4 +530,83 @@ impl MetricsCollector for Metrics {
     fn add_ptm_failed_residue_mismatch(&self, count: u64) {
         self.inner.ptm_failures.add_residue_mismatch(count);
     }
+
+    fn add_isoform_seq_missing(&self, count: u64) {
+        self.inner.isoform_seq_missing.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_rows_rejected(&self, count: u64) {
+        self.inner.rows_rejected.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_checksum_mismatches(&self, count: u64) {
+        self.inner.checksum_mismatches.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_duplicate_entries(&self, count: u64) {
+        self.inner.duplicate_entries.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_invalid_entries(&self, count: u64) {
+        self.inner.invalid_entries.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_vsp_conflicts(&self, count: u64) {
+        self.inner.vsp_conflicts.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_isoform_length_mismatch(&self, count: u64) {
+        self.inner.isoform_length_mismatch.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_fasta_records_loaded(&self, count: u64) {
+        self.inner.fasta_records_loaded.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_fasta_records_skipped(&self, count: u64) {
+        self.inner.fasta_records_skipped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_rows_filtered(&self, count: u64) {
+        self.inner.rows_filtered.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_unknown_feature_types(&self, count: u64) {
+        self.inner.unknown_feature_types.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn add_feature_category(&self, category: FeatureCategory, count: u64) {
+        self.inner.feature_categories[category as usize].fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn record_builder_memory(&self, bytes: u64, rows: u64) {
+        self.inner.peak_builder_bytes.fetch_max(bytes, Ordering::Relaxed);
+        self.inner.builder_bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.inner.builder_rows.fetch_add(rows, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_adds_counts_and_keeps_the_peak() {
+        let global = Metrics::new();
+        global.inc_entries();
+        global.record_builder_memory(500, 10);
+
+        let file = Metrics::new();
+        file.inc_entries();
+        file.add_ptm_failed_vsp_deletion(3);
+        file.add_feature_category(FeatureCategory::Domain, 2);
+        file.record_builder_memory(300, 10);
+        file.merge_into(&global);
+
+        assert_eq!(global.entries(), 2);
+        assert_eq!(global.ptm_failed_vsp_deletion(), 3);
+        assert_eq!(global.feature_category_counts()["domains"], 2);
+        assert_eq!(global.peak_builder_bytes(), 500);
+        assert_eq!(global.builder_bytes_per_row(), 40.0);
+    }
 }
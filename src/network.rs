@@ -0,0 +1,315 @@
+//! Protein interaction network export (`uniprot_etl export-network`).
+//!
+//! Reads the `interactions` column of every output file and collapses it into one
+//! edge per interactant pair, keeping the highest confidence score, the union of the
+//! evidence codes and the largest experiment count seen for the pair. Rows of the same
+//! entry (isoforms) repeat its interactions, so duplicates are expected and merged.
+//! With `undirected`, A–B and B–A (the same interaction listed under both entries)
+//! become one edge. Written as a TSV edge list or GraphML for Cytoscape.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{Array, Float32Array, Int32Array, ListArray, StructArray};
+use arrow::record_batch::RecordBatch;
+use clap::ValueEnum;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ProjectionMask;
+use quick_xml::escape::escape;
+use serde::Serialize;
+
+use crate::stats::{parquet_files, utf8};
+
+/// File format of the exported network.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkFormat {
+    /// protein_a, protein_b, max_confidence, evidence, experiments with a header row
+    Tsv,
+    /// GraphML with one node per protein and the edge columns as edge data
+    Graphml,
+}
+
+/// One merged interaction.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Edge {
+    pub max_confidence: Option<f32>,
+    /// ECO codes of every occurrence of the pair
+    pub evidence: BTreeSet<String>,
+    /// Largest IntAct experiment count of the pair
+    pub experiments: Option<i32>,
+}
+
+/// What was written.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NetworkSummary {
+    pub nodes: usize,
+    pub edges: usize,
+    /// Interactions read, before merging duplicates
+    pub interactions: u64,
+}
+
+/// Interactions merged per (protein_a, protein_b).
+#[derive(Debug, Default)]
+pub struct Network {
+    pub edges: BTreeMap<(String, String), Edge>,
+    undirected: bool,
+    interactions: u64,
+}
+
+impl Network {
+    pub fn new(undirected: bool) -> Self {
+        Self {
+            undirected,
+            ..Self::default()
+        }
+    }
+
+    /// Reads the interactions of `path` (Parquet file or output directory).
+    pub fn load(path: &Path, undirected: bool) -> Result<Self> {
+        let files = parquet_files(path)?;
+        if files.is_empty() {
+            return Err(anyhow!("No Parquet files found at {}", path.display()));
+        }
+        let mut network = Self::new(undirected);
+        for file in files {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(
+                File::open(&file).with_context(|| format!("Failed to open {}", file.display()))?,
+            )?;
+            let root = builder
+                .schema()
+                .index_of("interactions")
+                .with_context(|| format!("{} has no interactions column", file.display()))?;
+            let projection = ProjectionMask::roots(builder.parquet_schema(), [root]);
+            for batch in builder.with_projection(projection).build()? {
+                network.add_batch(&batch?)?;
+            }
+        }
+        Ok(network)
+    }
+
+    /// Merges one interaction into its edge.
+    pub fn add(
+        &mut self,
+        a: &str,
+        b: &str,
+        confidence: Option<f32>,
+        evidence: Option<&str>,
+        experiments: Option<i32>,
+    ) {
+        self.interactions += 1;
+        let key = if self.undirected && b < a {
+            (b.to_string(), a.to_string())
+        } else {
+            (a.to_string(), b.to_string())
+        };
+        let edge = self.edges.entry(key).or_default();
+        edge.max_confidence = match (edge.max_confidence, confidence) {
+            (Some(seen), Some(c)) => Some(seen.max(c)),
+            (seen, c) => seen.or(c),
+        };
+        edge.evidence.extend(
+            evidence
+                .into_iter()
+                .flat_map(|codes| codes.split(';'))
+                .map(str::trim)
+                .filter(|code| !code.is_empty())
+                .map(str::to_string),
+        );
+        edge.experiments = edge.experiments.max(experiments);
+    }
+
+    fn add_batch(&mut self, batch: &RecordBatch) -> Result<()> {
+        let column = batch
+            .column_by_name("interactions")
+            .ok_or_else(|| anyhow!("output is missing column interactions"))?;
+        let lists = column
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .ok_or_else(|| anyhow!("interactions is not a List"))?;
+        for row in 0..lists.len() {
+            if lists.is_null(row) {
+                continue;
+            }
+            let values = lists.value(row);
+            let interactions = values
+                .as_any()
+                .downcast_ref::<StructArray>()
+                .ok_or_else(|| anyhow!("interactions is not a List<Struct>"))?;
+            let text = |name: &str| -> Result<_> {
+                interactions.column_by_name(name).map(|c| utf8(c.as_ref())).transpose()
+            };
+            let evidence = text("evidence_code")?;
+            let (Some(first), Some(second)) = (text("interactant_id_1")?, text("interactant_id_2")?) else {
+                return Err(anyhow!("interactions is missing interactant ids"));
+            };
+            let scores = interactions
+                .column_by_name("confidence_score")
+                .and_then(|c| c.as_any().downcast_ref::<Float32Array>().cloned());
+            let experiments = interactions
+                .column_by_name("experiments")
+                .and_then(|c| c.as_any().downcast_ref::<Int32Array>().cloned());
+
+            for i in 0..interactions.len() {
+                // Interactions without both partners cannot be edges
+                if first.is_null(i) || second.is_null(i) {
+                    continue;
+                }
+                let confidence = scores.as_ref().filter(|s| s.is_valid(i)).map(|s| s.value(i));
+                let evidence = evidence.as_ref().filter(|e| e.is_valid(i)).map(|e| e.value(i));
+                let experiments = experiments.as_ref().filter(|e| e.is_valid(i)).map(|e| e.value(i));
+                self.add(first.value(i), second.value(i), confidence, evidence, experiments);
+            }
+        }
+        Ok(())
+    }
+
+    /// Every protein that is an end of some edge, sorted.
+    pub fn nodes(&self) -> BTreeSet<&str> {
+        self.edges
+            .keys()
+            .flat_map(|(a, b)| [a.as_str(), b.as_str()])
+            .collect()
+    }
+
+    /// Writes the network to `path` in `format`.
+    pub fn write(&self, path: &Path, format: NetworkFormat) -> Result<NetworkSummary> {
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        match format {
+            NetworkFormat::Tsv => self.write_tsv(&mut out)?,
+            NetworkFormat::Graphml => self.write_graphml(&mut out)?,
+        }
+        out.flush()?;
+        Ok(NetworkSummary {
+            nodes: self.nodes().len(),
+            edges: self.edges.len(),
+            interactions: self.interactions,
+        })
+    }
+
+    fn write_tsv(&self, out: &mut impl Write) -> Result<()> {
+        writeln!(out, "protein_a\tprotein_b\tmax_confidence\tevidence\texperiments")?;
+        for ((a, b), edge) in &self.edges {
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}",
+                a,
+                b,
+                edge.max_confidence.map(|c| c.to_string()).unwrap_or_default(),
+                evidence_text(edge),
+                edge.experiments.map(|e| e.to_string()).unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_graphml(&self, out: &mut impl Write) -> Result<()> {
+        let edge_default = if self.undirected { "undirected" } else { "directed" };
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+        writeln!(out, r#"  <key id="max_confidence" for="edge" attr.name="max_confidence" attr.type="double"/>"#)?;
+        writeln!(out, r#"  <key id="evidence" for="edge" attr.name="evidence" attr.type="string"/>"#)?;
+        writeln!(out, r#"  <key id="experiments" for="edge" attr.name="experiments" attr.type="int"/>"#)?;
+        writeln!(out, r#"  <graph id="interactions" edgedefault="{}">"#, edge_default)?;
+        for node in self.nodes() {
+            writeln!(out, r#"    <node id="{}"/>"#, escape(node))?;
+        }
+        for ((a, b), edge) in &self.edges {
+            writeln!(out, r#"    <edge source="{}" target="{}">"#, escape(a.as_str()), escape(b.as_str()))?;
+            if let Some(confidence) = edge.max_confidence {
+                writeln!(out, r#"      <data key="max_confidence">{}</data>"#, confidence)?;
+            }
+            if !edge.evidence.is_empty() {
+                writeln!(out, r#"      <data key="evidence">{}</data>"#, escape(evidence_text(edge).as_str()))?;
+            }
+            if let Some(experiments) = edge.experiments {
+                writeln!(out, r#"      <data key="experiments">{}</data>"#, experiments)?;
+            }
+            writeln!(out, "    </edge>")?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")?;
+        Ok(())
+    }
+}
+
+fn evidence_text(edge: &Edge) -> String {
+    edge.evidence.iter().map(String::as_str).collect::<Vec<_>>().join(";")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use crate::writer::parquet::write_batches;
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+    use std::fs;
+
+    fn interaction(partner: &str, experiments: i32) -> String {
+        format!(
+            "<comment type=\"interaction\"><interactant intactId=\"EBI-1\"/><interactant intactId=\"EBI-2\">\
+             <id>{}</id></interactant><experiments>{}</experiments></comment>",
+            partner, experiments
+        )
+    }
+
+    #[test]
+    fn merges_duplicate_and_reciprocal_interactions() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_network");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let xml = format!(
+            "<uniprot><entry><accession>P1</accession>{}{}<sequence length=\"2\">MA</sequence></entry>\
+             <entry><accession>P2</accession>{}<sequence length=\"2\">MA</sequence></entry></uniprot>",
+            interaction("P2", 3),
+            interaction("P3", 1),
+            interaction("P1", 5),
+        );
+        let output = dir.join("out.parquet");
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        parse_entries(Reader::from_reader(xml.as_bytes()), tx, &metrics, 10, None).unwrap();
+        write_batches(rx, &output, &metrics, &Settings::default()).unwrap();
+
+        let directed = Network::load(&output, false).unwrap();
+        assert_eq!(directed.edges.len(), 3);
+        let undirected = Network::load(&output, true).unwrap();
+        assert_eq!(undirected.edges.len(), 2);
+        let edge = &undirected.edges[&("P1".to_string(), "P2".to_string())];
+        assert_eq!(edge.experiments, Some(5));
+
+        let tsv = dir.join("network.tsv");
+        let summary = undirected.write(&tsv, NetworkFormat::Tsv).unwrap();
+        assert_eq!((summary.nodes, summary.edges, summary.interactions), (3, 2, 3));
+        let text = fs::read_to_string(&tsv).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "protein_a\tprotein_b\tmax_confidence\tevidence\texperiments");
+        assert!(lines[1].starts_with("P1\tP2\t") && lines[1].ends_with("\t5"));
+
+        let graphml = dir.join("network.graphml");
+        undirected.write(&graphml, NetworkFormat::Graphml).unwrap();
+        let text = fs::read_to_string(&graphml).unwrap();
+        assert!(text.contains(r#"edgedefault="undirected""#));
+        assert_eq!(text.matches("<node ").count(), 3);
+        assert!(text.contains(r#"<edge source="P1" target="P3">"#));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keeps_the_highest_confidence_and_all_evidence() {
+        let mut network = Network::new(false);
+        network.add("P1", "P2", Some(0.4), Some("ECO:0000269"), None);
+        network.add("P1", "P2", Some(0.9), Some("ECO:0000353;ECO:0000269"), Some(2));
+        network.add("P1", "P2", None, None, None);
+        let edge = &network.edges[&("P1".to_string(), "P2".to_string())];
+        assert_eq!(edge.max_confidence, Some(0.9));
+        assert_eq!(evidence_text(edge), "ECO:0000269;ECO:0000353");
+        assert_eq!(edge.experiments, Some(2));
+    }
+}
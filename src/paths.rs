@@ -0,0 +1,97 @@
+//! Cross-platform path helpers.
+//!
+//! Input discovery and output naming work on `Path` components and extensions rather
+//! than on lossy strings, so backslash separators, drive prefixes, glob metacharacters
+//! in directory names and upper-case extensions (`.XML.GZ`) all behave the same on
+//! Windows and Unix.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+fn has_extension(path: &Path, ext: &str) -> bool {
+    path.extension()
+        .is_some_and(|e| e.to_string_lossy().eq_ignore_ascii_case(ext))
+}
+
+/// File name without a trailing `.gz` and then `.xml` extension (either may be absent).
+///
+/// `uniprot_sprot.xml.gz` -> `uniprot_sprot`, `part_01.XML` -> `part_01`.
+pub fn strip_xml_extensions(path: &Path) -> Option<OsString> {
+    let mut name = PathBuf::from(path.file_name()?);
+    if has_extension(&name, "gz") {
+        name = PathBuf::from(name.file_stem()?);
+    }
+    if has_extension(&name, "xml") {
+        name = PathBuf::from(name.file_stem()?);
+    }
+    Some(name.into_os_string())
+}
+
+/// True for `*.xml` and `*.xml.gz` (case-insensitive).
+pub fn is_xml_input(path: &Path) -> bool {
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+    let name = Path::new(name);
+    if has_extension(name, "gz") {
+        name.file_stem().is_some_and(|stem| has_extension(Path::new(stem), "xml"))
+    } else {
+        has_extension(name, "xml")
+    }
+}
+
+/// Regular `*.xml` / `*.xml.gz` files directly inside `dir`, sorted by path.
+pub fn list_xml_inputs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_xml_input(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Canonicalizes `path` without the `\\?\` verbatim prefix Windows would otherwise add
+/// (which breaks joins with relative paths and most external tools).
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    dunce::canonicalize(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_xml_extensions() {
+        let stem = |p: &str| strip_xml_extensions(Path::new(p)).unwrap();
+        assert_eq!(stem("data/raw/uniprot_sprot.xml.gz"), "uniprot_sprot");
+        assert_eq!(stem("part_01.XML"), "part_01");
+        assert_eq!(stem("chunk.Xml.GZ"), "chunk");
+        assert_eq!(stem("notes.txt"), "notes.txt");
+        assert_eq!(stem("release.2024_01.xml"), "release.2024_01");
+    }
+
+    #[test]
+    fn test_list_xml_inputs_handles_glob_metacharacters() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_paths [batch]");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["b.xml.gz", "a.XML", "c.txt", "d.gz"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        fs::create_dir_all(dir.join("nested.xml")).unwrap();
+
+        let names: Vec<_> = list_xml_inputs(&dir)
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["a.XML", "b.xml.gz"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,18 +1,42 @@
 use arrow::record_batch::RecordBatch;
 use crossbeam_channel::Sender;
+use std::time::{Duration, Instant};
 
 use crate::error::{EtlError, Result};
 use crate::metrics::MetricsCollector;
 use crate::pipeline::builders::EntryBuilders;
+use crate::pipeline::enrich::Enrichers;
+use crate::pipeline::invariants;
+use crate::pipeline::pacing::ChannelPacer;
+use crate::pipeline::plddt::PlddtSource;
+use crate::pipeline::ptm_vocab::PtmVocabulary;
+use crate::pipeline::window::SiteContext;
+use crate::pipeline::rejects::RejectLog;
+use crate::pipeline::trace::EntryTracer;
 use crate::pipeline::transformer::TransformedRow;
+use crate::schema::SchemaProfile;
 
 #[allow(dead_code)]
 pub const DEFAULT_BATCH_SIZE: usize = 10_000;
 
+/// Upper bound on `sequence` bytes, and on `isoforms` string bytes, per batch.
+///
+/// `StringBuilder` uses i32 offsets, so a batch of very long sequences (titin is ~35k aa)
+/// can overflow at 2 GiB long before `batch_size` rows are reached. The `isoforms` struct
+/// repeats every isoform of an entry on each of its rows, so entries with many isoforms
+/// can get there first. Flushing early at 1 GiB keeps well clear of that limit while
+/// leaving normal batches untouched.
+pub const MAX_SEQUENCE_BYTES_PER_BATCH: usize = 1 << 30;
+
 /// Manages batching of entries into RecordBatches and sending to the writer.
 pub struct Batcher<M: MetricsCollector> {
     builders: EntryBuilders,
     batch_size: usize,
+    max_sequence_bytes: usize,
+    flush_interval: Option<Duration>,
+    last_flush: Instant,
+    pacer: ChannelPacer,
+    check_invariants: bool,
     sender: Sender<RecordBatch>,
     metrics: M,
 }
@@ -26,17 +50,127 @@ impl<M: MetricsCollector> Batcher<M> {
         Self {
             builders: EntryBuilders::new(batch_size),
             batch_size,
+            max_sequence_bytes: MAX_SEQUENCE_BYTES_PER_BATCH,
+            flush_interval: None,
+            last_flush: Instant::now(),
+            pacer: ChannelPacer::disabled(),
+            check_invariants: false,
             sender,
             metrics,
         }
     }
 
+    /// Adds builders for the extra columns registered in `enrichers`.
+    pub fn with_enrichers(mut self, enrichers: &Enrichers) -> Result<Self> {
+        self.builders = EntryBuilders::with_extra_columns(self.batch_size, enrichers.extra_fields())?;
+        Ok(self)
+    }
+
+    /// Builds only the built-in columns of `profile`; call after `with_enrichers`.
+    pub fn with_schema_profile(mut self, profile: SchemaProfile) -> Self {
+        self.builders.set_schema_profile(profile);
+        self
+    }
+
+    /// Builds only `id`, `parent_id` and the built-in `columns`; call after `with_enrichers`.
+    pub fn with_columns(mut self, columns: &[String]) -> Self {
+        self.builders.set_columns(columns);
+        self
+    }
+
+    /// Records features dropped while building rows into `rejects`.
+    pub fn with_reject_log(mut self, rejects: RejectLog) -> Self {
+        self.builders.set_reject_log(rejects);
+        self
+    }
+
+    /// Stamps `release` into the `release` column of every row.
+    pub fn with_release(mut self, release: Option<String>) -> Self {
+        self.builders.set_release(release);
+        self
+    }
+
+    /// Classifies PTM modifications with `vocabulary`.
+    pub fn with_ptm_vocabulary(mut self, vocabulary: PtmVocabulary) -> Self {
+        self.builders.set_ptm_vocabulary(vocabulary);
+        self
+    }
+
+    /// Writes the `context` window around each PTM site to `site_context`.
+    pub fn with_site_context(mut self, context: Option<SiteContext>) -> Self {
+        self.builders.set_site_context(context);
+        self
+    }
+
+    /// Attaches AlphaFold pLDDT from `source` to canonical rows.
+    pub fn with_plddt(mut self, source: PlddtSource) -> Self {
+        self.builders.set_plddt(source);
+        self
+    }
+
+    /// Matches domain features to InterPro/Pfam/SMART accessions by name.
+    pub fn with_domain_reconciliation(mut self, reconcile: bool) -> Self {
+        self.builders.set_reconcile_domain_xrefs(reconcile);
+        self
+    }
+
+    /// Writes mapping shifts and failure codes into the PTM/feature structs.
+    pub fn with_debug_mapping(mut self, debug_mapping: bool) -> Self {
+        self.builders.set_debug_mapping(debug_mapping);
+        self
+    }
+
+    /// Logs PTM mapping decisions of the traced accession to `trace`.
+    pub fn with_tracer(mut self, trace: EntryTracer) -> Self {
+        self.builders.set_tracer(trace);
+        self
+    }
+
+    /// Also flushes a partially filled batch once `interval` has passed since the last
+    /// flush. Checked when a row is added, so an idle input sends nothing until its
+    /// next row arrives.
+    pub fn with_flush_interval(mut self, interval: Option<Duration>) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Holds batches back while the channel is at the pacer's effective capacity.
+    pub fn with_pacer(mut self, pacer: ChannelPacer) -> Self {
+        self.pacer = pacer;
+        self
+    }
+
+    /// Checks every row and batch against `pipeline::invariants`, failing on the first violation.
+    pub fn with_invariant_checks(mut self, check: bool) -> Self {
+        self.check_invariants = check;
+        self
+    }
+
+    /// Overrides the per-batch sequence/isoform byte budget (mainly for tests).
+    pub fn set_max_sequence_bytes(&mut self, bytes: usize) {
+        self.max_sequence_bytes = bytes;
+    }
+
     /// Adds a pre-transformed row to the current batch. Flushes if batch is full.
     pub fn add_row(&mut self, row: TransformedRow) -> Result<()> {
+        // Flush first if this row would push the sequence or isoforms column past its byte budget
+        let sequence_full = self.builders.sequence_bytes() + row.sequence.len() > self.max_sequence_bytes;
+        let isoforms_full =
+            self.builders.isoform_bytes() + self.builders.row_isoform_bytes(&row) > self.max_sequence_bytes;
+        if sequence_full || isoforms_full {
+            self.flush()?;
+        }
+
+        if self.check_invariants {
+            invariants::check_row(&row)?;
+        }
         self.builders.append_row(&row, &self.metrics);
         self.metrics.inc_entries();
 
-        if self.builders.len() >= self.batch_size {
+        let interval_elapsed = self
+            .flush_interval
+            .is_some_and(|interval| self.last_flush.elapsed() >= interval);
+        if self.builders.len() >= self.batch_size || interval_elapsed {
             self.flush()?;
         }
 
@@ -45,11 +179,18 @@ impl<M: MetricsCollector> Batcher<M> {
 
     /// Flushes the current batch to the channel
     pub fn flush(&mut self) -> Result<()> {
+        self.last_flush = Instant::now();
         if self.builders.is_empty() {
             return Ok(());
         }
 
         let batch = self.builders.finish_batch()?;
+        if self.check_invariants {
+            invariants::check_batch(&batch)?;
+        }
+        self.metrics
+            .record_builder_memory(self.builders.last_batch_bytes() as u64, batch.num_rows() as u64);
+        self.pacer.before_send(&self.sender, &batch);
         self.sender.send(batch).map_err(|_| EtlError::ChannelSend)?;
         self.metrics.inc_batches();
 
@@ -61,3 +202,97 @@ impl<M: MetricsCollector> Batcher<M> {
         self.flush()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::pipeline::scratch::{IsoformScratch, ParsedEntry};
+    use crate::testing::canonical_row;
+    use crossbeam_channel::unbounded;
+
+    fn row(id: &str, sequence: &str) -> TransformedRow {
+        canonical_row(ParsedEntry {
+            accession: id.to_string(),
+            parent_id: id.to_string(),
+            sequence: sequence.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_flushes_before_sequence_budget_overflows() {
+        let (tx, rx) = unbounded();
+        let mut batcher = Batcher::with_batch_size(tx, Metrics::new(), 100);
+        batcher.set_max_sequence_bytes(10);
+
+        batcher.add_row(row("A", "MTAKMTAK")).unwrap();
+        batcher.add_row(row("B", "MTAKMTAK")).unwrap();
+        batcher.add_row(row("C", "MT")).unwrap();
+        batcher.finish().unwrap();
+
+        let sizes: Vec<usize> = rx.iter().map(|b| b.num_rows()).collect();
+        assert_eq!(sizes, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_flushes_before_isoform_budget_overflows() {
+        let (tx, rx) = unbounded();
+        let mut batcher = Batcher::with_batch_size(tx, Metrics::new(), 100);
+        batcher.set_max_sequence_bytes(10);
+
+        // Short sequences, but each row carries 8 bytes of isoform strings
+        let isoforms = ["P1-1", "P1-2"].map(|id| IsoformScratch {
+            isoform_id: id.to_string(),
+            ..Default::default()
+        });
+        let isoform_row = |id: &str| {
+            canonical_row(ParsedEntry {
+                accession: id.to_string(),
+                parent_id: id.to_string(),
+                sequence: "M".to_string(),
+                isoforms: isoforms.to_vec(),
+                ..Default::default()
+            })
+        };
+        batcher.add_row(isoform_row("A")).unwrap();
+        batcher.add_row(isoform_row("B")).unwrap();
+        batcher.add_row(row("C", "MT")).unwrap();
+        batcher.finish().unwrap();
+
+        let sizes: Vec<usize> = rx.iter().map(|b| b.num_rows()).collect();
+        assert_eq!(sizes, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_flush_interval_emits_partial_batches() {
+        let (tx, rx) = unbounded();
+        let mut batcher = Batcher::with_batch_size(tx, Metrics::new(), 100)
+            .with_flush_interval(Some(Duration::ZERO));
+
+        batcher.add_row(row("A", "MT")).unwrap();
+        batcher.add_row(row("B", "MT")).unwrap();
+        assert_eq!(rx.len(), 2);
+
+        let (tx, rx) = unbounded();
+        let mut batcher = Batcher::with_batch_size(tx, Metrics::new(), 100)
+            .with_flush_interval(Some(Duration::from_secs(3600)));
+        batcher.add_row(row("A", "MT")).unwrap();
+        batcher.add_row(row("B", "MT")).unwrap();
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn test_records_builder_memory_per_batch() {
+        let (tx, _rx) = unbounded();
+        let metrics = Metrics::new();
+        let mut batcher = Batcher::with_batch_size(tx, metrics.clone(), 2);
+        for id in ["A", "B", "C"] {
+            batcher.add_row(row(id, "MTAKMTAK")).unwrap();
+        }
+        batcher.finish().unwrap();
+
+        assert!(metrics.peak_builder_bytes() > 0);
+        assert!(metrics.builder_bytes_per_row() > 0.0);
+    }
+}
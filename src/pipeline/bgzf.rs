@@ -0,0 +1,210 @@
+//! Multi-threaded decompression of BGZF input (`performance.decompress_threads`).
+//!
+//! BGZF (the blocked gzip of samtools/htslib, written by `bgzip`) is a series of
+//! independent gzip members of at most 64 KiB each, with the compressed member size
+//! stored in a `BC` extra subfield. That makes blocks decompressible in parallel:
+//! a reader thread splits the file into groups of blocks, a worker pool inflates
+//! them, and `BgzfReader` hands the output back in file order.
+//!
+//! Plain (multi-member) gzip has no block sizes and stays on `MultiGzDecoder`.
+
+use crossbeam_channel::{bounded, Receiver, Sender};
+use flate2::read::GzDecoder;
+use std::io::{self, Read};
+use std::thread;
+
+/// Blocks inflated per job (~1 MiB of output), to amortise the channel hand-offs.
+const BLOCKS_PER_JOB: usize = 16;
+
+/// Fixed part of a BGZF member header: magic, CM, FLG, MTIME, XFL, OS, XLEN.
+const HEADER_LEN: usize = 12;
+
+type JobResult = io::Result<Vec<u8>>;
+
+/// True if `header` (the first bytes of a file) starts a BGZF block.
+pub fn is_bgzf(header: &[u8]) -> bool {
+    header.len() >= HEADER_LEN + 6
+        && header[..4] == [0x1f, 0x8b, 0x08, 0x04]
+        && block_size(header).is_some()
+}
+
+/// Total size of the BGZF block whose header starts `header`, from its `BC` subfield.
+fn block_size(header: &[u8]) -> Option<usize> {
+    let xlen = u16::from_le_bytes([header[10], header[11]]) as usize;
+    let extra = header.get(HEADER_LEN..HEADER_LEN + xlen)?;
+    let mut i = 0;
+    while i + 4 <= extra.len() {
+        let len = u16::from_le_bytes([extra[i + 2], extra[i + 3]]) as usize;
+        if extra[i] == b'B' && extra[i + 1] == b'C' && len == 2 {
+            let bsize = u16::from_le_bytes([*extra.get(i + 4)?, *extra.get(i + 5)?]);
+            return Some(bsize as usize + 1);
+        }
+        i += 4 + len;
+    }
+    None
+}
+
+/// Reads the next whole block; None at a clean end of input.
+fn read_block<R: Read>(input: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut block = vec![0u8; HEADER_LEN];
+    let mut filled = 0;
+    while filled < HEADER_LEN {
+        match input.read(&mut block[filled..])? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated BGZF block header")),
+            n => filled += n,
+        }
+    }
+    let xlen = u16::from_le_bytes([block[10], block[11]]) as usize;
+    block.resize(HEADER_LEN + xlen, 0);
+    input.read_exact(&mut block[HEADER_LEN..])?;
+    let size = block_size(&block)
+        .filter(|&size| size >= block.len())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "gzip member without a BGZF block size"))?;
+    let header_len = block.len();
+    block.resize(size, 0);
+    input.read_exact(&mut block[header_len..])?;
+    Ok(Some(block))
+}
+
+/// Inflates consecutive BGZF blocks into one buffer.
+fn inflate(blocks: &[Vec<u8>]) -> JobResult {
+    let mut out = Vec::with_capacity(blocks.len() * 64 * 1024);
+    for block in blocks {
+        GzDecoder::new(block.as_slice()).read_to_end(&mut out)?;
+    }
+    Ok(out)
+}
+
+/// `Read` over the decompressed content of a BGZF stream, inflated by `threads` workers.
+pub struct BgzfReader {
+    /// One receiver per job, in file order
+    pending: Receiver<Receiver<JobResult>>,
+    current: Vec<u8>,
+    offset: usize,
+}
+
+impl BgzfReader {
+    pub fn new<R: Read + Send + 'static>(mut input: R, threads: usize) -> Self {
+        let threads = threads.max(1);
+        let (job_tx, job_rx) = bounded::<(Vec<Vec<u8>>, Sender<JobResult>)>(threads * 2);
+        let (pending_tx, pending_rx) = bounded(threads * 2);
+
+        for _ in 0..threads {
+            let job_rx = job_rx.clone();
+            thread::spawn(move || {
+                for (blocks, result_tx) in job_rx {
+                    let _ = result_tx.send(inflate(&blocks));
+                }
+            });
+        }
+
+        // Splitter: cuts the input into jobs. Stops once the reader is dropped.
+        thread::spawn(move || loop {
+            let mut blocks = Vec::with_capacity(BLOCKS_PER_JOB);
+            let mut error = None;
+            while blocks.len() < BLOCKS_PER_JOB {
+                match read_block(&mut input) {
+                    Ok(Some(block)) => blocks.push(block),
+                    Ok(None) => break,
+                    Err(err) => {
+                        error = Some(err);
+                        break;
+                    }
+                }
+            }
+            let done = blocks.len() < BLOCKS_PER_JOB || error.is_some();
+            if !blocks.is_empty() {
+                let (result_tx, result_rx) = bounded(1);
+                if pending_tx.send(result_rx).is_err() || job_tx.send((blocks, result_tx)).is_err() {
+                    return;
+                }
+            }
+            if let Some(err) = error {
+                let (result_tx, result_rx) = bounded(1);
+                let _ = result_tx.send(Err(err));
+                let _ = pending_tx.send(result_rx);
+            }
+            if done {
+                return;
+            }
+        });
+
+        Self {
+            pending: pending_rx,
+            current: Vec::new(),
+            offset: 0,
+        }
+    }
+}
+
+impl Read for BgzfReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset == self.current.len() {
+            // Every job is queued before the splitter exits, so a closed queue is the end
+            let Ok(result_rx) = self.pending.recv() else {
+                return Ok(0);
+            };
+            let result = result_rx
+                .recv()
+                .map_err(|_| io::Error::other("BGZF worker exited without a result"))?;
+            self.current = result?;
+            self.offset = 0;
+        }
+        let n = buf.len().min(self.current.len() - self.offset);
+        buf[..n].copy_from_slice(&self.current[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// One BGZF block holding `data`.
+    fn bgzf_block(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        let member = encoder.finish().unwrap();
+        // Re-wrap the deflate payload and trailer with a BGZF header
+        let payload = &member[10..];
+        let size = HEADER_LEN + 6 + payload.len();
+        let mut block = vec![0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0];
+        block.extend_from_slice(&((size - 1) as u16).to_le_bytes());
+        block.extend_from_slice(payload);
+        block
+    }
+
+    #[test]
+    fn inflates_blocks_in_order_across_workers() {
+        let mut file = Vec::new();
+        let mut expected = Vec::new();
+        for i in 0..100 {
+            let chunk = format!("<entry><accession>P{:05}</accession></entry>\n", i).repeat(20);
+            file.extend(bgzf_block(chunk.as_bytes()));
+            expected.extend_from_slice(chunk.as_bytes());
+        }
+        file.extend(bgzf_block(b""));
+        assert!(is_bgzf(&file));
+
+        let mut out = Vec::new();
+        BgzfReader::new(io::Cursor::new(file), 4).read_to_end(&mut out).unwrap();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn rejects_plain_gzip_and_truncated_blocks() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"<uniprot/>").unwrap();
+        assert!(!is_bgzf(&encoder.finish().unwrap()));
+
+        let mut file = bgzf_block(b"<uniprot/>");
+        file.truncate(file.len() - 3);
+        let mut out = Vec::new();
+        assert!(BgzfReader::new(io::Cursor::new(file), 2).read_to_end(&mut out).is_err());
+    }
+}
@@ -2,11 +2,14 @@ use arrow::array::{
     ArrayBuilder, Float32Builder, Int32Builder, ListArray, ListBuilder, StringBuilder, StructBuilder,
 };
 
-use crate::pipeline::mapper::CoordinateMapper;
+use crate::pipeline::builders::DictStringBuilder;
+use crate::pipeline::mapper::{CoordinateMapper, MapFailure};
+use crate::pipeline::rejects::{RejectKind, RejectLog};
 use crate::pipeline::scratch::{
     ActiveSiteScratch, BindingSiteScratch, DomainScratch, MetalCoordinationScratch, MutagenesisSiteScratch,
     NaturalVariantScratch, ParsedEntry,
 };
+use crate::pipeline::transformer::{sequence_applies_to_row, TransformedRow};
 
 pub trait MappableFeature {
     fn id(&self) -> Option<&str>;
@@ -14,6 +17,8 @@ pub trait MappableFeature {
     fn start(&self) -> Option<i32>;
     fn end(&self) -> Option<i32>;
     fn evidence_keys(&self) -> &[String];
+    fn position_status(&self) -> Option<&str>;
+    fn sequence(&self) -> Option<&str>;
 }
 
 macro_rules! impl_mappable {
@@ -38,6 +43,14 @@ macro_rules! impl_mappable {
             fn evidence_keys(&self) -> &[String] {
                 &self.evidence_keys
             }
+
+            fn position_status(&self) -> Option<&str> {
+                self.position_status.as_deref()
+            }
+
+            fn sequence(&self) -> Option<&str> {
+                self.sequence.as_deref()
+            }
         }
     };
 }
@@ -53,42 +66,95 @@ impl_mappable!(NaturalVariantScratch);
 pub struct FeatureListBuilder {
     inner: ListBuilder<StructBuilder>,
     extra_fields: usize,
+    /// Column name, used as context when a feature is rejected
+    column: &'static str,
+    /// Fill `mapping_shift`/`mapping_failure_code` and keep unmapped features
+    debug_mapping: bool,
 }
 
 impl FeatureListBuilder {
-    pub fn new(inner: ListBuilder<StructBuilder>, extra_fields: usize) -> Self {
+    pub fn new(column: &'static str, inner: ListBuilder<StructBuilder>, extra_fields: usize) -> Self {
         Self {
             inner,
             extra_fields,
+            column,
+            debug_mapping: false,
         }
     }
 
-    /// Appends a row of coordinate-based features, mapping coordinates with the provided mapper.
+    /// Fills `mapping_shift` and `mapping_failure_code`, keeping features that fail to
+    /// map in canonical coordinates instead of leaving them out (`transform.debug_mapping`).
+    pub fn set_debug_mapping(&mut self, debug_mapping: bool) {
+        self.debug_mapping = debug_mapping;
+    }
+
+    /// Appends a row of coordinate-based features, mapping coordinates with the row's mapper.
     ///
     /// `write_extra` is responsible for populating any extra fields between description and start/end.
+    /// Features whose range cannot be mapped onto the row's sequence are recorded and left
+    /// out, unless `set_debug_mapping` keeps them.
+    /// Features located on a specific isoform (`<location sequence>`) only go on that isoform's
+    /// row, with their coordinates taken as-is.
     pub fn append_features<'a, F, I>(
         &mut self,
-        entry: &ParsedEntry,
-        isoform_sequence: &str,
-        mapper: &CoordinateMapper,
+        row: &TransformedRow,
+        rejects: &RejectLog,
         features: I,
         mut write_extra: impl FnMut(&mut StructBuilder, usize, usize, &F),
     ) where
         F: MappableFeature + 'a,
         I: IntoIterator<Item = &'a F>,
     {
+        let entry: &ParsedEntry = &row.entry;
         let start_index = 2 + self.extra_fields;
-        let mut struct_builder = self.inner.values();
+        let struct_builder = self.inner.values();
+
+        let features: Vec<(&F, i32, i32)> = features
+            .into_iter()
+            .filter(|feature| sequence_applies_to_row(feature.sequence(), &row.row_id))
+            .filter_map(|feature| Some((feature, feature.start()?, feature.end()?)))
+            .collect();
+        // Both ends of every canonical-coordinate range, mapped in one pass
+        let points: Vec<i32> = features
+            .iter()
+            .filter(|(feature, _, _)| feature.sequence().is_none())
+            .flat_map(|&(_, start, end)| [start, end])
+            .collect();
+        let mut mapped_points = row.mapper.map_points(&points).into_iter();
 
-        for feature in features {
-            let (Some(start), Some(end)) = (feature.start(), feature.end()) else {
-                continue;
+        for (feature, start, end) in features {
+            let mapped = match feature.sequence() {
+                Some(_) => range_in_sequence_1based(&row.sequence, start, end).ok_or("ISOFORM_OOB"),
+                None => {
+                    let (Some(mapped_start), Some(mapped_end)) = (mapped_points.next(), mapped_points.next()) else {
+                        unreachable!("two mapped points per canonical-coordinate feature");
+                    };
+                    checked_range(entry, &row.sequence, start, end, mapped_start, mapped_end)
+                }
             };
-            let Some((mapped_start, mapped_end)) =
-                map_range_1based(entry, isoform_sequence, mapper, start, end)
-            else {
-                continue;
+            // (start, end, mapping_shift, mapping_failure_code)
+            let (mapped_start, mapped_end, shift, failure) = match mapped {
+                Ok((mapped_start, mapped_end)) => {
+                    let shift = feature.sequence().is_none().then_some(mapped_start - start);
+                    (mapped_start, mapped_end, shift, None)
+                }
+                Err(code) => {
+                    rejects.record(RejectKind::Feature, "FEATURE_UNMAPPED", &row.parent_id, &row.row_id, || {
+                        Some(format!(
+                            "column={} feature_id={} start={} end={}",
+                            self.column,
+                            feature.id().unwrap_or("-"),
+                            start,
+                            end
+                        ))
+                    });
+                    if !self.debug_mapping {
+                        continue;
+                    }
+                    (start, end, None, Some(code))
+                }
             };
+            let shift = shift.filter(|_| self.debug_mapping);
 
             let evidence = entry.resolve_evidence(feature.evidence_keys());
             let confidence = entry.max_confidence_for_evidence(feature.evidence_keys());
@@ -102,7 +168,7 @@ impl FeatureListBuilder {
                 .unwrap()
                 .append_option(feature.description());
 
-            write_extra(&mut struct_builder, 2, self.extra_fields, feature);
+            write_extra(struct_builder, 2, self.extra_fields, feature);
 
             struct_builder
                 .field_builder::<Int32Builder>(start_index)
@@ -113,19 +179,36 @@ impl FeatureListBuilder {
                 .unwrap()
                 .append_value(mapped_end);
             struct_builder
-                .field_builder::<StringBuilder>(start_index + 2)
+                .field_builder::<DictStringBuilder>(start_index + 2)
                 .unwrap()
                 .append_option(evidence.as_deref());
             struct_builder
                 .field_builder::<Float32Builder>(start_index + 3)
                 .unwrap()
                 .append_value(confidence);
+            struct_builder
+                .field_builder::<DictStringBuilder>(start_index + 4)
+                .unwrap()
+                .append_option(feature.position_status());
+            struct_builder
+                .field_builder::<Int32Builder>(start_index + 5)
+                .unwrap()
+                .append_option(shift);
+            struct_builder
+                .field_builder::<DictStringBuilder>(start_index + 6)
+                .unwrap()
+                .append_option(failure);
             struct_builder.append(true);
         }
 
         self.inner.append(true);
     }
 
+    /// Appends a null list (a row whose coordinates are unknown).
+    pub fn append_null(&mut self) {
+        self.inner.append(false);
+    }
+
     pub fn finish(&mut self) -> ListArray {
         self.inner.finish()
     }
@@ -151,36 +234,59 @@ pub fn map_range_1based(
     start: i32,
     end: i32,
 ) -> Option<(i32, i32)> {
+    let mapped_start = mapper.map_point_1based(start);
+    let mapped_end = if end == start {
+        mapped_start.clone()
+    } else {
+        mapper.map_point_1based(end)
+    };
+    checked_range(entry, isoform_sequence, start, end, mapped_start, mapped_end).ok()
+}
+
+/// Bounds checks of `map_range_1based` on ends already mapped by the row's mapper;
+/// fails with the `mapping_failure_code` of the first check that does not pass.
+fn checked_range(
+    entry: &ParsedEntry,
+    isoform_sequence: &str,
+    start: i32,
+    end: i32,
+    mapped_start: Result<i32, MapFailure>,
+    mapped_end: Result<i32, MapFailure>,
+) -> Result<(i32, i32), &'static str> {
     if start <= 0 || end <= 0 || end < start {
-        return None;
+        return Err("INVALID_RANGE");
     }
 
     let canonical_len = entry.sequence.len() as i32;
     if canonical_len <= 0 || end > canonical_len {
-        return None;
+        return Err("CANONICAL_OOB");
     }
 
     let iso_len = isoform_sequence.len() as i32;
     if iso_len <= 0 {
-        return None;
+        return Err("ISOFORM_OOB");
     }
 
-    let mapped_start = mapper.map_point_1based(start).ok()?;
-    let mapped_end = if end == start {
-        mapped_start
-    } else {
-        mapper.map_point_1based(end).ok()?
-    };
-
+    let mapped_start = mapped_start.map_err(|f| f.code())?;
+    let mapped_end = mapped_end.map_err(|f| f.code())?;
     if mapped_start <= 0 || mapped_end <= 0 {
-        return None;
+        return Err("MAPPER_OOB");
     }
     if mapped_start > iso_len || mapped_end > iso_len {
-        return None;
+        return Err("ISOFORM_OOB");
     }
     if mapped_end < mapped_start {
-        return None;
+        return Err("INVALID_RANGE");
     }
 
-    Some((mapped_start, mapped_end))
+    Ok((mapped_start, mapped_end))
+}
+
+/// Bounds-checks a range that is already in the row sequence's coordinates.
+pub fn range_in_sequence_1based(sequence: &str, start: i32, end: i32) -> Option<(i32, i32)> {
+    let len = sequence.len() as i32;
+    if start <= 0 || end < start || end > len {
+        return None;
+    }
+    Some((start, end))
 }
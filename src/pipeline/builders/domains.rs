@@ -0,0 +1,66 @@
+//! Reconciliation of `domains` features with InterPro/Pfam/SMART cross-references.
+//!
+//! UniProt's domain features carry a name ("SH2", "PDZ 2") but no family accession, while
+//! the entry's `<dbReference>`s carry accessions with their own entry names ("SH2",
+//! "PDZ_dom", "SH3_1"). A cross-reference belongs to a domain when both names reduce to
+//! the same key: lowercase alphanumeric words, without a trailing repeat number and
+//! without "dom"/"domain" words. Names that differ in wording ("Protein kinase" vs
+//! "Pkinase") stay unmatched rather than guessed.
+
+use crate::pipeline::scratch::NamedXref;
+
+/// Ids of the `xrefs` whose name matches `domain_name`, in cross-reference order.
+pub fn family_accessions<'a>(domain_name: &str, xrefs: &'a [NamedXref]) -> Vec<&'a str> {
+    let key = name_key(domain_name);
+    if key.is_empty() {
+        return Vec::new();
+    }
+    xrefs
+        .iter()
+        .filter(|xref| xref.name.as_deref().is_some_and(|name| name_key(name) == key))
+        .map(|xref| xref.id.as_str())
+        .collect()
+}
+
+/// "PDZ 2" -> "pdz", "SH3_domain" -> "sh3", "Kinase-like_dom_sf" -> "kinaselikesf".
+fn name_key(name: &str) -> String {
+    let mut words: Vec<String> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_ascii_lowercase())
+        .filter(|w| w != "dom" && w != "domain")
+        .collect();
+    if words.len() > 1 && words.last().is_some_and(|w| w.bytes().all(|b| b.is_ascii_digit())) {
+        words.pop();
+    }
+    words.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xref(database: &str, id: &str, name: &str) -> NamedXref {
+        NamedXref {
+            database: database.to_string(),
+            id: id.to_string(),
+            name: Some(name.to_string()),
+        }
+    }
+
+    #[test]
+    fn matches_family_names_ignoring_repeat_numbers_and_domain_words() {
+        let xrefs = [
+            xref("InterPro", "IPR001478", "PDZ"),
+            xref("InterPro", "IPR036034", "PDZ_sf"),
+            xref("Pfam", "PF00595", "PDZ"),
+            xref("Pfam", "PF00018", "SH3_1"),
+            xref("SMART", "SM00326", "SH3"),
+            xref("Pfam", "PF00069", "Pkinase"),
+        ];
+        assert_eq!(family_accessions("PDZ 2", &xrefs), ["IPR001478", "PF00595"]);
+        assert_eq!(family_accessions("SH3 domain", &xrefs), ["PF00018", "SM00326"]);
+        assert!(family_accessions("Protein kinase", &xrefs).is_empty());
+        assert!(family_accessions("", &xrefs).is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use arrow::array::{ArrayBuilder, ArrayRef, BooleanBuilder, Float64Builder, Int64Builder, StringBuilder};
+use arrow::datatypes::{DataType, Field};
+
+use crate::error::{EtlError, Result};
+use crate::pipeline::enrich::ExtraValue;
+
+/// Builder for one enricher-registered extra column.
+pub enum ExtraColumnBuilder {
+    Boolean(BooleanBuilder),
+    Int64(Int64Builder),
+    Float64(Float64Builder),
+    Utf8(StringBuilder),
+}
+
+impl ExtraColumnBuilder {
+    pub fn for_field(field: &Field, capacity: usize) -> Result<Self> {
+        Ok(match field.data_type() {
+            DataType::Boolean => Self::Boolean(BooleanBuilder::with_capacity(capacity)),
+            DataType::Int64 => Self::Int64(Int64Builder::with_capacity(capacity)),
+            DataType::Float64 => Self::Float64(Float64Builder::with_capacity(capacity)),
+            DataType::Utf8 => Self::Utf8(StringBuilder::with_capacity(capacity, capacity * 16)),
+            other => {
+                return Err(EtlError::InvalidExtraColumn(format!(
+                    "{}: unsupported type {}",
+                    field.name(),
+                    other
+                )))
+            }
+        })
+    }
+
+    /// Appends `value`, or null when it is missing or of the wrong type.
+    pub fn append(&mut self, value: Option<&ExtraValue>) {
+        match (self, value) {
+            (Self::Boolean(b), Some(ExtraValue::Boolean(v))) => b.append_value(*v),
+            (Self::Int64(b), Some(ExtraValue::Int64(v))) => b.append_value(*v),
+            (Self::Float64(b), Some(ExtraValue::Float64(v))) => b.append_value(*v),
+            (Self::Utf8(b), Some(ExtraValue::Utf8(v))) => b.append_value(v),
+            (Self::Boolean(b), _) => b.append_null(),
+            (Self::Int64(b), _) => b.append_null(),
+            (Self::Float64(b), _) => b.append_null(),
+            (Self::Utf8(b), _) => b.append_null(),
+        }
+    }
+
+    pub fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::Boolean(b) => Arc::new(b.finish()),
+            Self::Int64(b) => Arc::new(b.finish()),
+            Self::Float64(b) => Arc::new(b.finish()),
+            Self::Utf8(b) => Arc::new(b.finish()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Boolean(b) => b.len(),
+            Self::Int64(b) => b.len(),
+            Self::Float64(b) => b.len(),
+            Self::Utf8(b) => b.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
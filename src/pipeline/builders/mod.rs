@@ -1,21 +1,106 @@
 pub mod common;
+pub mod domains;
+pub mod extra;
 pub mod ptm;
 
 use std::sync::Arc;
 
 use arrow::array::{
-    ArrayBuilder, ArrayRef, Float32Builder, Int32Builder, Int8Builder, ListBuilder, StringBuilder, StructBuilder,
+    ArrayBuilder, ArrayRef, BooleanBuilder, Float32Builder, Int32Builder, Int8Builder, ListBuilder, StringBuilder,
+    StringDictionaryBuilder, StructBuilder,
 };
-use arrow::datatypes::{DataType, Field, Fields};
+use arrow::datatypes::{DataType, Field, Fields, Int32Type, SchemaRef};
 use arrow::record_batch::RecordBatch;
 
 use crate::error::Result;
 use crate::metrics::MetricsCollector;
-use crate::pipeline::builders::common::FeatureListBuilder;
-use crate::pipeline::builders::ptm::append_ptm_sites;
-use crate::pipeline::scratch::ParsedEntry;
-use crate::pipeline::transformer::TransformedRow;
-use crate::schema::schema_ref;
+use crate::pipeline::builders::common::{map_range_1based, FeatureListBuilder};
+use crate::pipeline::builders::domains::family_accessions;
+use crate::pipeline::builders::extra::ExtraColumnBuilder;
+use crate::pipeline::builders::ptm::{append_ptm_sites, PtmAnnotation};
+use crate::pipeline::plddt::{mean_plddt, Plddt, PlddtSource};
+use crate::pipeline::ptm_vocab::PtmVocabulary;
+use crate::pipeline::window::SiteContext;
+use crate::pipeline::rejects::{RejectKind, RejectLog};
+use crate::pipeline::scratch::{NamedXref, ParsedEntry};
+use crate::pipeline::trace::EntryTracer;
+use crate::pipeline::transformer::{molecule_applies_to_row, sequence_applies_to_row, IsoformStatus, TransformedRow};
+use crate::schema::{
+    create_uniprot_schema, dict_utf8, project_builtin, schema_ref, schema_with_extra_columns, SchemaProfile,
+};
+use crate::log_warn;
+
+/// Builder for dictionary-encoded Utf8 columns (see `schema::dict_utf8`).
+pub type DictStringBuilder = StringDictionaryBuilder<Int32Type>;
+
+/// Positions of the built-in columns in `schema::create_uniprot_schema`.
+mod col {
+    pub const ID: usize = 0;
+    pub const SEQUENCE: usize = 1;
+    pub const ORGANISM_ID: usize = 2;
+    pub const ISOFORMS: usize = 3;
+    pub const FEATURES: usize = 4;
+    pub const LOCATION: usize = 5;
+    pub const ENTRY_NAME: usize = 6;
+    pub const GENE_NAME: usize = 7;
+    pub const PROTEIN_NAME: usize = 8;
+    pub const ORGANISM_NAME: usize = 9;
+    pub const EXISTENCE: usize = 10;
+    pub const STRUCTURES: usize = 11;
+    pub const STRUCTURE_COVERAGE: usize = 12;
+    pub const ALPHAFOLD_MEAN_PLDDT: usize = 13;
+    pub const PARENT_ID: usize = 14;
+    pub const PTM_SITES: usize = 15;
+    pub const ACTIVE_SITES: usize = 16;
+    pub const BINDING_SITES: usize = 17;
+    pub const METAL_COORDINATIONS: usize = 18;
+    pub const MUTAGENESIS_SITES: usize = 19;
+    pub const DOMAINS: usize = 20;
+    pub const NATURAL_VARIANTS: usize = 21;
+    pub const SUBUNITS: usize = 22;
+    pub const INTERACTIONS: usize = 23;
+    pub const IS_FRAGMENT: usize = 24;
+    pub const IS_PRECURSOR: usize = 25;
+    pub const DATASET: usize = 26;
+    pub const GENE_LOCATION: usize = 27;
+    pub const EC_NUMBERS: usize = 28;
+    pub const PATHWAYS: usize = 29;
+    pub const DOMAIN_XREFS: usize = 30;
+    pub const ISOFORM_COUNT: usize = 31;
+    pub const SEQUENCE_LENGTH: usize = 32;
+    pub const PTM_SITE_COUNT: usize = 33;
+    pub const OTHER_COMMENTS: usize = 34;
+    pub const RELEASE: usize = 35;
+    pub const ISOFORM_STATUS: usize = 36;
+    pub const COUNT: usize = 37;
+}
+
+/// Built-in columns a batch is built with (bit `i` = column `i` of the built-in schema).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ColumnMask(u64);
+
+impl ColumnMask {
+    const ALL: ColumnMask = ColumnMask((1 << col::COUNT) - 1);
+
+    /// The built-in columns whose name `keep` accepts.
+    fn matching(keep: impl Fn(&str) -> bool) -> Self {
+        let schema = create_uniprot_schema();
+        let bits = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| keep(field.name()))
+            .fold(0, |bits, (i, _)| bits | 1 << i);
+        let mask = ColumnMask(bits);
+        // Row counting and sharding rely on these two
+        debug_assert!(mask.has(col::ID) && mask.has(col::PARENT_ID));
+        mask
+    }
+
+    fn has(self, column: usize) -> bool {
+        self.0 & (1 << column) != 0
+    }
+}
 
 pub struct EntryBuilders {
     pub id: StringBuilder,
@@ -27,9 +112,11 @@ pub struct EntryBuilders {
     pub entry_name: StringBuilder,
     pub gene_name: StringBuilder,
     pub protein_name: StringBuilder,
-    pub organism_name: StringBuilder,
+    pub organism_name: DictStringBuilder,
     pub existence: Int8Builder,
     pub structures: ListBuilder<StructBuilder>,
+    pub structure_coverage: ListBuilder<StructBuilder>,
+    pub alphafold_mean_plddt: Float32Builder,
     pub parent_id: StringBuilder,
     pub ptm_sites: ListBuilder<StructBuilder>,
     pub active_sites: FeatureListBuilder,
@@ -40,7 +127,36 @@ pub struct EntryBuilders {
     pub natural_variants: FeatureListBuilder,
     pub subunits: ListBuilder<StructBuilder>,
     pub interactions: ListBuilder<StructBuilder>,
-    capacity: usize,
+    pub is_fragment: BooleanBuilder,
+    pub is_precursor: BooleanBuilder,
+    pub dataset: DictStringBuilder,
+    pub gene_location: DictStringBuilder,
+    pub ec_numbers: ListBuilder<StringBuilder>,
+    pub pathways: ListBuilder<StructBuilder>,
+    pub domain_xrefs: ListBuilder<StructBuilder>,
+    pub isoform_count: Int32Builder,
+    pub sequence_length: Int32Builder,
+    pub ptm_site_count: Int32Builder,
+    pub other_comments: ListBuilder<StructBuilder>,
+    pub release: DictStringBuilder,
+    pub isoform_status: DictStringBuilder,
+    /// Enricher-registered columns, appended after the built-in ones
+    pub extra: Vec<ExtraColumnBuilder>,
+    schema: SchemaRef,
+    /// Built-in columns of `schema.profile`; the others are neither appended nor emitted
+    columns: ColumnMask,
+    rejects: RejectLog,
+    trace: EntryTracer,
+    // Value of the `release` column for every row
+    release_value: Option<String>,
+    ptm_vocabulary: PtmVocabulary,
+    site_context: Option<SiteContext>,
+    plddt: PlddtSource,
+    reconcile_domain_xrefs: bool,
+    debug_mapping: bool,
+    last_batch_bytes: usize,
+    // String bytes appended to the `isoforms` struct in the current batch
+    isoform_bytes: usize,
 }
 
 impl EntryBuilders {
@@ -55,20 +171,123 @@ impl EntryBuilders {
             entry_name: StringBuilder::with_capacity(capacity, capacity * 20),
             gene_name: StringBuilder::with_capacity(capacity, capacity * 20),
             protein_name: StringBuilder::with_capacity(capacity, capacity * 50),
-            organism_name: StringBuilder::with_capacity(capacity, capacity * 30),
+            organism_name: DictStringBuilder::with_capacity(capacity, 1024, 32 * 1024),
             existence: Int8Builder::with_capacity(capacity),
             structures: create_structures_builder(capacity),
+            structure_coverage: create_structure_coverage_builder(capacity),
+            alphafold_mean_plddt: Float32Builder::with_capacity(capacity),
             parent_id: StringBuilder::with_capacity(capacity, capacity * 10),
             ptm_sites: create_ptm_sites_builder(capacity),
-            active_sites: FeatureListBuilder::new(create_coordinate_feature_builder(capacity), 0),
-            binding_sites: FeatureListBuilder::new(create_coordinate_feature_builder(capacity), 0),
-            metal_coordinations: FeatureListBuilder::new(create_metal_coordination_builder(capacity), 1),
-            mutagenesis_sites: FeatureListBuilder::new(create_coordinate_feature_builder(capacity), 0),
-            domains: FeatureListBuilder::new(create_domain_builder(capacity), 1),
-            natural_variants: FeatureListBuilder::new(create_natural_variant_builder(capacity), 2),
+            active_sites: FeatureListBuilder::new("active_sites", create_coordinate_feature_builder(capacity), 0),
+            binding_sites: FeatureListBuilder::new("binding_sites", create_coordinate_feature_builder(capacity), 0),
+            metal_coordinations: FeatureListBuilder::new("metal_coordinations", create_metal_coordination_builder(capacity), 1),
+            mutagenesis_sites: FeatureListBuilder::new("mutagenesis_sites", create_coordinate_feature_builder(capacity), 0),
+            domains: FeatureListBuilder::new("domains", create_domain_builder(capacity), 2),
+            natural_variants: FeatureListBuilder::new("natural_variants", create_natural_variant_builder(capacity), 4),
             subunits: create_subunit_builder(capacity),
             interactions: create_interaction_builder(capacity),
-            capacity,
+            is_fragment: BooleanBuilder::with_capacity(capacity),
+            is_precursor: BooleanBuilder::with_capacity(capacity),
+            dataset: DictStringBuilder::with_capacity(capacity, 4, 32),
+            gene_location: DictStringBuilder::with_capacity(capacity, 16, 256),
+            ec_numbers: ListBuilder::with_capacity(StringBuilder::with_capacity(capacity, capacity * 8), capacity),
+            pathways: create_named_xrefs_builder(capacity),
+            domain_xrefs: create_named_xrefs_builder(capacity),
+            isoform_count: Int32Builder::with_capacity(capacity),
+            sequence_length: Int32Builder::with_capacity(capacity),
+            ptm_site_count: Int32Builder::with_capacity(capacity),
+            other_comments: create_other_comments_builder(capacity),
+            release: DictStringBuilder::with_capacity(capacity, 1, 16),
+            isoform_status: DictStringBuilder::with_capacity(capacity, 3, 32),
+            extra: Vec::new(),
+            schema: schema_ref(),
+            columns: ColumnMask::ALL,
+            rejects: RejectLog::disabled(),
+            trace: EntryTracer::disabled(),
+            release_value: None,
+            ptm_vocabulary: PtmVocabulary::default(),
+            site_context: None,
+            plddt: PlddtSource::default(),
+            reconcile_domain_xrefs: false,
+            debug_mapping: false,
+            last_batch_bytes: 0,
+            isoform_bytes: 0,
+        }
+    }
+
+    /// Builders for the built-in schema plus the given enricher columns.
+    pub fn with_extra_columns(capacity: usize, extra_fields: &[Field]) -> Result<Self> {
+        let mut builders = Self::new(capacity);
+        builders.extra = extra_fields
+            .iter()
+            .map(|field| ExtraColumnBuilder::for_field(field, capacity))
+            .collect::<Result<_>>()?;
+        builders.schema = schema_with_extra_columns(extra_fields);
+        Ok(builders)
+    }
+
+    /// Builds only the built-in columns of `profile` (enricher columns are always built).
+    pub fn set_schema_profile(&mut self, profile: SchemaProfile) {
+        self.columns = ColumnMask::matching(|name| profile.includes(name));
+        self.schema = profile.project(&self.schema);
+    }
+
+    /// Builds only `id`, `parent_id` and the built-in columns in `names` (enricher
+    /// columns are always built).
+    pub fn set_columns(&mut self, names: &[String]) {
+        let keep = |name: &str| name == "id" || name == "parent_id" || names.iter().any(|n| n == name);
+        self.columns = ColumnMask::matching(keep);
+        self.schema = project_builtin(&self.schema, keep);
+    }
+
+    /// Records features that are dropped while building rows.
+    pub fn set_reject_log(&mut self, rejects: RejectLog) {
+        self.rejects = rejects;
+    }
+
+    /// Traces PTM mapping decisions for the accession `trace` targets.
+    pub fn set_tracer(&mut self, trace: EntryTracer) {
+        self.trace = trace;
+    }
+
+    /// Sets the `release` value stamped on every following row.
+    pub fn set_release(&mut self, release: Option<String>) {
+        self.release_value = release;
+    }
+
+    /// Classifies `ptm_sites` modifications with `vocabulary` instead of the embedded one.
+    pub fn set_ptm_vocabulary(&mut self, vocabulary: PtmVocabulary) {
+        self.ptm_vocabulary = vocabulary;
+    }
+
+    /// Fills `ptm_sites.site_context` with the `context` window (null when None).
+    pub fn set_site_context(&mut self, context: Option<SiteContext>) {
+        self.site_context = context;
+    }
+
+    /// Fills `alphafold_mean_plddt` and `ptm_sites.plddt` of canonical rows from `source`.
+    pub fn set_plddt(&mut self, source: PlddtSource) {
+        self.plddt = source;
+    }
+
+    /// Fills `domains.family_accessions` from the entry's `domain_xrefs` (null when off).
+    pub fn set_reconcile_domain_xrefs(&mut self, reconcile: bool) {
+        self.reconcile_domain_xrefs = reconcile;
+    }
+
+    /// Fills the `mapping_shift`/`mapping_failure_code` fields of `ptm_sites` and the
+    /// coordinate-feature columns, keeping sites and features that fail to map.
+    pub fn set_debug_mapping(&mut self, debug_mapping: bool) {
+        self.debug_mapping = debug_mapping;
+        for column in [
+            &mut self.active_sites,
+            &mut self.binding_sites,
+            &mut self.metal_coordinations,
+            &mut self.mutagenesis_sites,
+            &mut self.domains,
+            &mut self.natural_variants,
+        ] {
+            column.set_debug_mapping(debug_mapping);
         }
     }
 
@@ -77,105 +296,280 @@ impl EntryBuilders {
     /// while row_id, row_sequence, and parent_id vary per row.
     pub fn append_row<M: MetricsCollector>(&mut self, row: &TransformedRow, metrics: &M) {
         let entry: &ParsedEntry = &row.entry;
+        let columns = self.columns;
+        // Nothing can be placed on an isoform without a sequence: its coordinate columns are null
+        let located = row.isoform_status != IsoformStatus::Unsequenced;
 
         self.id.append_value(&row.row_id);
-        self.sequence.append_value(&row.sequence);
-        self.organism_id.append_option(entry.organism_id);
-
-        self.entry_name.append_option(entry.entry_name.as_deref());
-        self.gene_name.append_option(entry.gene_name.as_deref());
-        self.protein_name
-            .append_option(entry.protein_name.as_deref());
-        self.organism_name
-            .append_option(entry.organism_scientific_name.as_deref());
-        if entry.existence == 0 {
-            self.existence.append_null();
-        } else {
-            self.existence.append_value(entry.existence);
+        if columns.has(col::SEQUENCE) {
+            self.sequence.append_option(located.then_some(row.sequence.as_str()));
+        }
+        if columns.has(col::ORGANISM_ID) {
+            self.organism_id.append_option(entry.organism_id);
         }
 
-        append_isoforms(&mut self.isoforms, entry);
-        append_features(&mut self.features, entry);
-        append_locations(&mut self.locations, entry);
-        append_structures(&mut self.structures, entry);
+        if columns.has(col::ENTRY_NAME) {
+            self.entry_name.append_option(entry.entry_name.as_deref());
+        }
+        if columns.has(col::GENE_NAME) {
+            self.gene_name.append_option(entry.gene_name.as_deref());
+        }
+        if columns.has(col::PROTEIN_NAME) {
+            self.protein_name
+                .append_option(entry.protein_name.as_deref());
+        }
+        if columns.has(col::ORGANISM_NAME) {
+            self.organism_name
+                .append_option(entry.organism_scientific_name.as_deref());
+        }
+        if columns.has(col::EXISTENCE) {
+            self.existence
+                .append_option((entry.existence != 0).then_some(entry.existence));
+        }
+
+        if columns.has(col::ISOFORMS) {
+            append_isoforms(&mut self.isoforms, entry);
+            self.isoform_bytes += isoform_string_bytes(entry);
+        }
+        if columns.has(col::FEATURES) && located {
+            append_features(&mut self.features, entry, row);
+        }
+        if columns.has(col::LOCATION) {
+            append_locations(&mut self.locations, entry, row);
+        }
+        if columns.has(col::STRUCTURES) {
+            append_structures(&mut self.structures, entry);
+        }
+        if columns.has(col::STRUCTURE_COVERAGE) && located {
+            append_structure_coverage(&mut self.structure_coverage, row, &self.rejects);
+        }
+        let plddt = if columns.has(col::ALPHAFOLD_MEAN_PLDDT) || columns.has(col::PTM_SITES) {
+            self.row_plddt(row)
+        } else {
+            None
+        };
+        if columns.has(col::ALPHAFOLD_MEAN_PLDDT) {
+            self.alphafold_mean_plddt
+                .append_option(plddt.as_deref().and_then(mean_plddt));
+        }
 
         self.parent_id.append_value(&row.parent_id);
 
         // Coordinate-based features
-        self.active_sites.append_features(
-            entry,
-            &row.sequence,
-            &row.mapper,
-            entry.features.active_sites.iter(),
-            |_, _, _, _| {},
-        );
-        self.binding_sites.append_features(
-            entry,
-            &row.sequence,
-            &row.mapper,
-            entry.features.binding_sites.iter(),
-            |_, _, _, _| {},
-        );
-        self.mutagenesis_sites.append_features(
-            entry,
-            &row.sequence,
-            &row.mapper,
-            entry.features.mutagenesis_sites.iter(),
-            |_, _, _, _| {},
-        );
-        self.metal_coordinations.append_features(
-            entry,
-            &row.sequence,
-            &row.mapper,
-            entry.features.metal_coordinations.iter(),
-            |builder, base, _, feat| {
-                builder
-                    .field_builder::<StringBuilder>(base)
-                    .unwrap()
-                    .append_option(feat.metal.as_deref());
-            },
-        );
-        self.domains.append_features(
-            entry,
-            &row.sequence,
-            &row.mapper,
-            entry.features.domains.iter(),
-            |builder, base, _, feat| {
-                let domain_name = feat.domain_name.as_deref().or(feat.description.as_deref());
-                builder
-                    .field_builder::<StringBuilder>(base)
-                    .unwrap()
-                    .append_option(domain_name);
-            },
-        );
-        self.natural_variants.append_features(
-            entry,
-            &row.sequence,
-            &row.mapper,
-            entry.features.natural_variants.iter(),
-            |builder, base, _, feat| {
-                builder
-                    .field_builder::<StringBuilder>(base)
-                    .unwrap()
-                    .append_option(feat.original.as_deref());
-                builder
-                    .field_builder::<StringBuilder>(base + 1)
-                    .unwrap()
-                    .append_option(feat.variation.as_deref());
-            },
-        );
+        if !located {
+            self.append_unlocated();
+        }
+        if columns.has(col::ACTIVE_SITES) && located {
+            self.active_sites.append_features(
+                row,
+                &self.rejects,
+                entry.features.active_sites.iter(),
+                |_, _, _, _| {},
+            );
+        }
+        if columns.has(col::BINDING_SITES) && located {
+            self.binding_sites.append_features(
+                row,
+                &self.rejects,
+                entry.features.binding_sites.iter(),
+                |_, _, _, _| {},
+            );
+        }
+        if columns.has(col::MUTAGENESIS_SITES) && located {
+            self.mutagenesis_sites.append_features(
+                row,
+                &self.rejects,
+                entry.features.mutagenesis_sites.iter(),
+                |_, _, _, _| {},
+            );
+        }
+        if columns.has(col::METAL_COORDINATIONS) && located {
+            self.metal_coordinations.append_features(
+                row,
+                &self.rejects,
+                entry.features.metal_coordinations.iter(),
+                |builder, base, _, feat| {
+                    builder
+                        .field_builder::<StringBuilder>(base)
+                        .unwrap()
+                        .append_option(feat.metal.as_deref());
+                },
+            );
+        }
+        let reconcile_domain_xrefs = self.reconcile_domain_xrefs;
+        if columns.has(col::DOMAINS) && located {
+            self.domains.append_features(
+                row,
+                &self.rejects,
+                entry.features.domains.iter(),
+                |builder, base, _, feat| {
+                    let domain_name = feat.domain_name.as_deref().or(feat.description.as_deref());
+                    builder
+                        .field_builder::<StringBuilder>(base)
+                        .unwrap()
+                        .append_option(domain_name);
+                    let families = builder
+                        .field_builder::<ListBuilder<StringBuilder>>(base + 1)
+                        .unwrap();
+                    if reconcile_domain_xrefs {
+                        for id in family_accessions(domain_name.unwrap_or(""), &entry.domain_xrefs) {
+                            families.values().append_value(id);
+                        }
+                        families.append(true);
+                    } else {
+                        families.append(false);
+                    }
+                },
+            );
+        }
+        if columns.has(col::NATURAL_VARIANTS) && located {
+            self.natural_variants.append_features(
+                row,
+                &self.rejects,
+                entry.features.natural_variants.iter(),
+                |builder, base, _, feat| {
+                    builder
+                        .field_builder::<StringBuilder>(base)
+                        .unwrap()
+                        .append_option(feat.original.as_deref());
+                    builder
+                        .field_builder::<StringBuilder>(base + 1)
+                        .unwrap()
+                        .append_option(feat.variation.as_deref());
+                    builder
+                        .field_builder::<StringBuilder>(base + 2)
+                        .unwrap()
+                        .append_option(feat.dbsnp_id.as_deref());
+                    builder
+                        .field_builder::<StringBuilder>(base + 3)
+                        .unwrap()
+                        .append_option(feat.disease_association.as_deref());
+                },
+            );
+        }
 
         // Text-based comment features
-        append_subunits(&mut self.subunits, entry);
-        append_interactions(&mut self.interactions, entry);
+        if columns.has(col::SUBUNITS) {
+            append_subunits(&mut self.subunits, entry, row);
+        }
+        if columns.has(col::INTERACTIONS) {
+            append_interactions(&mut self.interactions, entry);
+        }
+
+        // PTM sites (residue-centric); also built for `ptm_site_count` alone, in which
+        // case finish_batch drops the list
+        let mut site_count = 0;
+        if (columns.has(col::PTM_SITES) || columns.has(col::PTM_SITE_COUNT)) && located {
+            site_count = append_ptm_sites(
+                &mut self.ptm_sites,
+                metrics,
+                &self.rejects,
+                &self.trace,
+                &PtmAnnotation {
+                    vocabulary: &self.ptm_vocabulary,
+                    site_context: self.site_context,
+                    plddt: plddt.as_deref(),
+                    debug_mapping: self.debug_mapping,
+                },
+                entry,
+                row,
+            );
+        }
+
+        if columns.has(col::IS_FRAGMENT) {
+            self.is_fragment.append_value(entry.fragment.is_some());
+        }
+        if columns.has(col::IS_PRECURSOR) {
+            self.is_precursor.append_value(entry.precursor);
+        }
+        if columns.has(col::DATASET) {
+            self.dataset.append_option(entry.dataset.as_deref());
+        }
+        if columns.has(col::GENE_LOCATION) {
+            self.gene_location.append_option(entry.gene_location.as_deref());
+        }
+        if columns.has(col::EC_NUMBERS) {
+            for ec in &entry.ec_numbers {
+                self.ec_numbers.values().append_value(ec);
+            }
+            self.ec_numbers.append(true);
+        }
+        if columns.has(col::PATHWAYS) {
+            append_named_xrefs(&mut self.pathways, &entry.pathways);
+        }
+        if columns.has(col::DOMAIN_XREFS) {
+            append_named_xrefs(&mut self.domain_xrefs, &entry.domain_xrefs);
+        }
+        if columns.has(col::ISOFORM_COUNT) {
+            self.isoform_count.append_value(entry.isoforms.len() as i32);
+        }
+        if columns.has(col::SEQUENCE_LENGTH) {
+            // Unknown, not 0, for unsequenced isoforms
+            self.sequence_length.append_option(located.then_some(row.sequence.len() as i32));
+        }
+        if columns.has(col::PTM_SITE_COUNT) {
+            self.ptm_site_count.append_value(site_count as i32);
+        }
+        if columns.has(col::OTHER_COMMENTS) {
+            append_other_comments(&mut self.other_comments, entry, row);
+        }
+        if columns.has(col::RELEASE) {
+            self.release.append_option(self.release_value.as_deref());
+        }
+        if columns.has(col::ISOFORM_STATUS) {
+            self.isoform_status.append_value(row.isoform_status.as_str());
+        }
+
+        for (i, builder) in self.extra.iter_mut().enumerate() {
+            builder.append(row.extra.get(i));
+        }
+    }
 
-        // PTM sites (residue-centric)
-        append_ptm_sites(&mut self.ptm_sites, metrics, entry, row);
+    /// Null coordinate columns of an `IsoformStatus::Unsequenced` row.
+    fn append_unlocated(&mut self) {
+        let columns = self.columns;
+        if columns.has(col::FEATURES) {
+            self.features.append(false);
+        }
+        if columns.has(col::STRUCTURE_COVERAGE) {
+            self.structure_coverage.append(false);
+        }
+        if columns.has(col::PTM_SITES) || columns.has(col::PTM_SITE_COUNT) {
+            self.ptm_sites.append(false);
+        }
+        for (column, builder) in [
+            (col::ACTIVE_SITES, &mut self.active_sites),
+            (col::BINDING_SITES, &mut self.binding_sites),
+            (col::METAL_COORDINATIONS, &mut self.metal_coordinations),
+            (col::MUTAGENESIS_SITES, &mut self.mutagenesis_sites),
+            (col::DOMAINS, &mut self.domains),
+            (col::NATURAL_VARIANTS, &mut self.natural_variants),
+        ] {
+            if columns.has(column) {
+                builder.append_null();
+            }
+        }
+    }
+
+    /// AlphaFold pLDDT of a canonical row whose sequence matches the model's length.
+    ///
+    /// A model that cannot be read is logged and treated as missing rather than failing the run.
+    fn row_plddt(&self, row: &TransformedRow) -> Option<Plddt> {
+        if self.plddt.is_empty() || row.row_id != row.parent_id {
+            return None;
+        }
+        match self.plddt.load(&row.row_id) {
+            Ok(plddt) => plddt.filter(|p| p.len() == row.sequence.len()),
+            Err(e) => {
+                log_warn!("transform", "{}", e);
+                None
+            }
+        }
     }
 
     /// Finishes the current batch and returns a RecordBatch
     pub fn finish_batch(&mut self) -> Result<RecordBatch> {
-        let arrays: Vec<ArrayRef> = vec![
+        let mut arrays: Vec<ArrayRef> = vec![
             Arc::new(self.id.finish()),
             Arc::new(self.sequence.finish()),
             Arc::new(self.organism_id.finish()),
@@ -188,6 +582,8 @@ impl EntryBuilders {
             Arc::new(self.organism_name.finish()),
             Arc::new(self.existence.finish()),
             Arc::new(self.structures.finish()),
+            Arc::new(self.structure_coverage.finish()),
+            Arc::new(self.alphafold_mean_plddt.finish()),
             Arc::new(self.parent_id.finish()),
             Arc::new(self.ptm_sites.finish()),
             Arc::new(self.active_sites.finish()),
@@ -198,15 +594,70 @@ impl EntryBuilders {
             Arc::new(self.natural_variants.finish()),
             Arc::new(self.subunits.finish()),
             Arc::new(self.interactions.finish()),
+            Arc::new(self.is_fragment.finish()),
+            Arc::new(self.is_precursor.finish()),
+            Arc::new(self.dataset.finish()),
+            Arc::new(self.gene_location.finish()),
+            Arc::new(self.ec_numbers.finish()),
+            Arc::new(self.pathways.finish()),
+            Arc::new(self.domain_xrefs.finish()),
+            Arc::new(self.isoform_count.finish()),
+            Arc::new(self.sequence_length.finish()),
+            Arc::new(self.ptm_site_count.finish()),
+            Arc::new(self.other_comments.finish()),
+            Arc::new(self.release.finish()),
+            Arc::new(self.isoform_status.finish()),
         ];
+        if self.columns != ColumnMask::ALL {
+            // Columns outside the selection were never appended to; their arrays are empty
+            let columns = self.columns;
+            arrays = arrays
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| columns.has(*i))
+                .map(|(_, array)| array)
+                .collect();
+        }
+        arrays.extend(self.extra.iter_mut().map(ExtraColumnBuilder::finish));
 
-        let batch = RecordBatch::try_new(schema_ref(), arrays)?;
-
-        *self = Self::new(self.capacity);
+        // `finish()` resets every builder in place, so the nested builder tree is
+        // reused for the next batch rather than rebuilt from scratch.
+        let batch = RecordBatch::try_new(Arc::clone(&self.schema), arrays)?;
+        self.last_batch_bytes = batch.get_array_memory_size();
+        self.isoform_bytes = 0;
 
         Ok(batch)
     }
 
+    /// Approximate memory the builders held for the last finished batch: the allocated
+    /// capacity of every column buffer, which `finish()` hands over to the batch.
+    ///
+    /// Scales with `batch_size` × schema width; 0 before the first batch.
+    pub fn last_batch_bytes(&self) -> usize {
+        self.last_batch_bytes
+    }
+
+    /// Bytes accumulated in the `sequence` column for the current batch.
+    pub fn sequence_bytes(&self) -> usize {
+        self.sequence.values_slice().len()
+    }
+
+    /// String bytes (`isoform_id`, `isoform_sequence`, `isoform_note`) accumulated in the
+    /// `isoforms` column for the current batch. Every row of an entry repeats all its
+    /// isoforms, so this grows with isoform count squared and can outpace `sequence`.
+    pub fn isoform_bytes(&self) -> usize {
+        self.isoform_bytes
+    }
+
+    /// What appending `row` would add to `isoform_bytes` (0 if the column is not built).
+    pub fn row_isoform_bytes(&self, row: &TransformedRow) -> usize {
+        if self.columns.has(col::ISOFORMS) {
+            isoform_string_bytes(&row.entry)
+        } else {
+            0
+        }
+    }
+
     /// Returns the current number of entries in the builders
     pub fn len(&self) -> usize {
         self.id.len()
@@ -222,6 +673,8 @@ fn create_isoforms_builder(capacity: usize) -> ListBuilder<StructBuilder> {
         Field::new("isoform_id", DataType::Utf8, false),
         Field::new("isoform_sequence", DataType::Utf8, true),
         Field::new("isoform_note", DataType::Utf8, true),
+        Field::new("sequence_type", dict_utf8(), true),
+        Field::new("event_type", dict_utf8(), true),
     ]);
 
     let struct_builder = StructBuilder::from_fields(fields, capacity);
@@ -230,11 +683,12 @@ fn create_isoforms_builder(capacity: usize) -> ListBuilder<StructBuilder> {
 
 fn create_features_builder(capacity: usize) -> ListBuilder<StructBuilder> {
     let fields = Fields::from(vec![
-        Field::new("feature_type", DataType::Utf8, false),
+        Field::new("feature_type", dict_utf8(), false),
         Field::new("description", DataType::Utf8, true),
         Field::new("start", DataType::Int32, true),
         Field::new("end", DataType::Int32, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
+        Field::new("position_status", dict_utf8(), true),
     ]);
 
     let struct_builder = StructBuilder::from_fields(fields, capacity);
@@ -244,7 +698,10 @@ fn create_features_builder(capacity: usize) -> ListBuilder<StructBuilder> {
 fn create_locations_builder(capacity: usize) -> ListBuilder<StructBuilder> {
     let fields = Fields::from(vec![
         Field::new("location", DataType::Utf8, false),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
+        Field::new("topology", dict_utf8(), true),
+        Field::new("orientation", dict_utf8(), true),
+        Field::new("molecule", DataType::Utf8, true),
     ]);
 
     let struct_builder = StructBuilder::from_fields(fields, capacity);
@@ -253,8 +710,33 @@ fn create_locations_builder(capacity: usize) -> ListBuilder<StructBuilder> {
 
 fn create_structures_builder(capacity: usize) -> ListBuilder<StructBuilder> {
     let fields = Fields::from(vec![
-        Field::new("db", DataType::Utf8, false),
+        Field::new("db", dict_utf8(), false),
+        Field::new("id", DataType::Utf8, false),
+    ]);
+
+    let struct_builder = StructBuilder::from_fields(fields, capacity);
+    ListBuilder::new(struct_builder)
+}
+
+fn create_named_xrefs_builder(capacity: usize) -> ListBuilder<StructBuilder> {
+    let fields = Fields::from(vec![
+        Field::new("db", dict_utf8(), false),
         Field::new("id", DataType::Utf8, false),
+        Field::new("name", DataType::Utf8, true),
+    ]);
+
+    let struct_builder = StructBuilder::from_fields(fields, capacity);
+    ListBuilder::new(struct_builder)
+}
+
+fn create_structure_coverage_builder(capacity: usize) -> ListBuilder<StructBuilder> {
+    let fields = Fields::from(vec![
+        Field::new("pdb_id", DataType::Utf8, false),
+        Field::new("method", dict_utf8(), true),
+        Field::new("resolution", DataType::Float32, true),
+        Field::new("chains", DataType::Utf8, false),
+        Field::new("start", DataType::Int32, false),
+        Field::new("end", DataType::Int32, false),
     ]);
 
     let struct_builder = StructBuilder::from_fields(fields, capacity);
@@ -265,6 +747,16 @@ fn create_ptm_sites_builder(capacity: usize) -> ListBuilder<StructBuilder> {
     let mod_fields = Fields::from(vec![
         Field::new("mod_type", DataType::Int32, false),
         Field::new("confidence_score", DataType::Float32, false),
+        Field::new("partner_protein", DataType::Utf8, true),
+        Field::new("partner_residue", DataType::Utf8, true),
+        Field::new("linkage_chemistry", dict_utf8(), true),
+        Field::new("mod_class", dict_utf8(), true),
+        Field::new("mod_subclass", dict_utf8(), true),
+        Field::new("ptm_accession", DataType::Utf8, true),
+        Field::new("target_aa", DataType::Utf8, true),
+        Field::new("monoisotopic_mass_delta", DataType::Float64, true),
+        Field::new("average_mass_delta", DataType::Float64, true),
+        Field::new("evidence_code", dict_utf8(), true),
     ]);
 
     let mods_struct_builder = StructBuilder::from_fields(mod_fields.clone(), capacity);
@@ -280,6 +772,10 @@ fn create_ptm_sites_builder(capacity: usize) -> ListBuilder<StructBuilder> {
         Field::new("site_index", DataType::Int32, false),
         Field::new("site_aa", DataType::Utf8, false),
         Field::new("modifications", mods_list_type, true),
+        Field::new("site_context", DataType::Utf8, true),
+        Field::new("plddt", DataType::Float32, true),
+        Field::new("mapping_shift", DataType::Int32, true),
+        Field::new("mapping_failure_code", dict_utf8(), true),
     ]);
 
     let site_struct_builder = StructBuilder::new(
@@ -288,6 +784,10 @@ fn create_ptm_sites_builder(capacity: usize) -> ListBuilder<StructBuilder> {
             Box::new(Int32Builder::with_capacity(capacity)),
             Box::new(StringBuilder::with_capacity(capacity, capacity)),
             Box::new(mods_list_builder),
+            Box::new(StringBuilder::with_capacity(capacity, capacity)),
+            Box::new(Float32Builder::with_capacity(capacity)),
+            Box::new(Int32Builder::with_capacity(capacity)),
+            Box::new(DictStringBuilder::new()),
         ],
     );
 
@@ -300,8 +800,11 @@ fn create_coordinate_feature_builder(capacity: usize) -> ListBuilder<StructBuild
         Field::new("description", DataType::Utf8, true),
         Field::new("start", DataType::Int32, true),
         Field::new("end", DataType::Int32, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("position_status", dict_utf8(), true),
+        Field::new("mapping_shift", DataType::Int32, true),
+        Field::new("mapping_failure_code", dict_utf8(), true),
     ]);
     let struct_builder = StructBuilder::from_fields(fields, capacity);
     ListBuilder::new(struct_builder)
@@ -314,8 +817,11 @@ fn create_metal_coordination_builder(capacity: usize) -> ListBuilder<StructBuild
         Field::new("metal", DataType::Utf8, true),
         Field::new("start", DataType::Int32, true),
         Field::new("end", DataType::Int32, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("position_status", dict_utf8(), true),
+        Field::new("mapping_shift", DataType::Int32, true),
+        Field::new("mapping_failure_code", dict_utf8(), true),
     ]);
     let struct_builder = StructBuilder::from_fields(fields, capacity);
     ListBuilder::new(struct_builder)
@@ -326,12 +832,35 @@ fn create_domain_builder(capacity: usize) -> ListBuilder<StructBuilder> {
         Field::new("id", DataType::Utf8, true),
         Field::new("description", DataType::Utf8, true),
         Field::new("domain_name", DataType::Utf8, true),
+        Field::new(
+            "family_accessions",
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            true,
+        ),
         Field::new("start", DataType::Int32, true),
         Field::new("end", DataType::Int32, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("position_status", dict_utf8(), true),
+        Field::new("mapping_shift", DataType::Int32, true),
+        Field::new("mapping_failure_code", dict_utf8(), true),
     ]);
-    let struct_builder = StructBuilder::from_fields(fields, capacity);
+    let struct_builder = StructBuilder::new(
+        fields,
+        vec![
+            Box::new(StringBuilder::with_capacity(capacity, capacity)),
+            Box::new(StringBuilder::with_capacity(capacity, capacity)),
+            Box::new(StringBuilder::with_capacity(capacity, capacity)),
+            Box::new(ListBuilder::new(StringBuilder::with_capacity(capacity, capacity))),
+            Box::new(Int32Builder::with_capacity(capacity)),
+            Box::new(Int32Builder::with_capacity(capacity)),
+            Box::new(DictStringBuilder::new()),
+            Box::new(Float32Builder::with_capacity(capacity)),
+            Box::new(DictStringBuilder::new()),
+            Box::new(Int32Builder::with_capacity(capacity)),
+            Box::new(DictStringBuilder::new()),
+        ],
+    );
     ListBuilder::new(struct_builder)
 }
 
@@ -341,10 +870,15 @@ fn create_natural_variant_builder(capacity: usize) -> ListBuilder<StructBuilder>
         Field::new("description", DataType::Utf8, true),
         Field::new("original", DataType::Utf8, true),
         Field::new("variation", DataType::Utf8, true),
+        Field::new("dbsnp_id", DataType::Utf8, true),
+        Field::new("disease_association", DataType::Utf8, true),
         Field::new("start", DataType::Int32, true),
         Field::new("end", DataType::Int32, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("position_status", dict_utf8(), true),
+        Field::new("mapping_shift", DataType::Int32, true),
+        Field::new("mapping_failure_code", dict_utf8(), true),
     ]);
     let struct_builder = StructBuilder::from_fields(fields, capacity);
     ListBuilder::new(struct_builder)
@@ -353,8 +887,9 @@ fn create_natural_variant_builder(capacity: usize) -> ListBuilder<StructBuilder>
 fn create_subunit_builder(capacity: usize) -> ListBuilder<StructBuilder> {
     let fields = Fields::from(vec![
         Field::new("text", DataType::Utf8, false),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("molecule", DataType::Utf8, true),
     ]);
     let struct_builder = StructBuilder::from_fields(fields, capacity);
     ListBuilder::new(struct_builder)
@@ -364,13 +899,42 @@ fn create_interaction_builder(capacity: usize) -> ListBuilder<StructBuilder> {
     let fields = Fields::from(vec![
         Field::new("interactant_id_1", DataType::Utf8, true),
         Field::new("interactant_id_2", DataType::Utf8, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("intact_id_1", DataType::Utf8, true),
+        Field::new("intact_id_2", DataType::Utf8, true),
+        Field::new("organisms_differ", DataType::Boolean, true),
+        Field::new("experiments", DataType::Int32, true),
     ]);
     let struct_builder = StructBuilder::from_fields(fields, capacity);
     ListBuilder::new(struct_builder)
 }
 
+fn create_other_comments_builder(capacity: usize) -> ListBuilder<StructBuilder> {
+    let fields = Fields::from(vec![
+        Field::new("comment_type", dict_utf8(), false),
+        Field::new("text", DataType::Utf8, false),
+        Field::new("evidence_code", dict_utf8(), true),
+        Field::new("molecule", DataType::Utf8, true),
+    ]);
+    let struct_builder = StructBuilder::from_fields(fields, capacity);
+    ListBuilder::new(struct_builder)
+}
+
+/// Bytes of the Utf8 fields `append_isoforms` writes for `entry`. Their sum bounds each
+/// field's own i32-offset values buffer.
+fn isoform_string_bytes(entry: &ParsedEntry) -> usize {
+    entry
+        .isoforms
+        .iter()
+        .map(|iso| {
+            iso.isoform_id.len()
+                + iso.isoform_sequence.as_ref().map_or(0, String::len)
+                + iso.isoform_note.as_ref().map_or(0, String::len)
+        })
+        .sum()
+}
+
 fn append_isoforms(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry) {
     let isoforms_struct = builder.values();
     for iso in &entry.isoforms {
@@ -386,17 +950,28 @@ fn append_isoforms(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry
             .field_builder::<StringBuilder>(2)
             .unwrap()
             .append_option(iso.isoform_note.as_deref());
+        isoforms_struct
+            .field_builder::<DictStringBuilder>(3)
+            .unwrap()
+            .append_option(iso.sequence_type.as_deref());
+        isoforms_struct
+            .field_builder::<DictStringBuilder>(4)
+            .unwrap()
+            .append_option(iso.event_type.as_deref());
         isoforms_struct.append(true);
     }
     builder.append(true);
 }
 
-fn append_features(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry) {
+fn append_features(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry, row: &TransformedRow) {
     let features_struct = builder.values();
     for feat in &entry.features.generic {
+        if !sequence_applies_to_row(feat.sequence.as_deref(), &row.row_id) {
+            continue;
+        }
         let evidence = entry.resolve_evidence(&feat.evidence_keys);
         features_struct
-            .field_builder::<StringBuilder>(0)
+            .field_builder::<DictStringBuilder>(0)
             .unwrap()
             .append_value(&feat.feature_type);
         features_struct
@@ -412,26 +987,45 @@ fn append_features(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry
             .unwrap()
             .append_option(feat.end);
         features_struct
-            .field_builder::<StringBuilder>(4)
+            .field_builder::<DictStringBuilder>(4)
             .unwrap()
             .append_option(evidence.as_deref());
+        features_struct
+            .field_builder::<DictStringBuilder>(5)
+            .unwrap()
+            .append_option(feat.position_status().as_deref());
         features_struct.append(true);
     }
     builder.append(true);
 }
 
-fn append_locations(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry) {
+fn append_locations(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry, row: &TransformedRow) {
     let locations_struct = builder.values();
     for loc in &entry.comments.locations {
+        if !molecule_applies_to_row(entry, loc.molecule.as_deref(), &row.row_id) {
+            continue;
+        }
         let evidence = entry.resolve_evidence(&loc.evidence_keys);
         locations_struct
             .field_builder::<StringBuilder>(0)
             .unwrap()
             .append_value(&loc.location);
         locations_struct
-            .field_builder::<StringBuilder>(1)
+            .field_builder::<DictStringBuilder>(1)
             .unwrap()
             .append_option(evidence.as_deref());
+        locations_struct
+            .field_builder::<DictStringBuilder>(2)
+            .unwrap()
+            .append_option(loc.topology.as_deref());
+        locations_struct
+            .field_builder::<DictStringBuilder>(3)
+            .unwrap()
+            .append_option(loc.orientation.as_deref());
+        locations_struct
+            .field_builder::<StringBuilder>(4)
+            .unwrap()
+            .append_option(loc.molecule.as_deref());
         locations_struct.append(true);
     }
     builder.append(true);
@@ -441,7 +1035,7 @@ fn append_structures(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEnt
     let structures_struct = builder.values();
     for s in &entry.structures {
         structures_struct
-            .field_builder::<StringBuilder>(0)
+            .field_builder::<DictStringBuilder>(0)
             .unwrap()
             .append_value(&s.database);
         structures_struct
@@ -453,9 +1047,74 @@ fn append_structures(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEnt
     builder.append(true);
 }
 
-fn append_subunits(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry) {
+fn append_named_xrefs(builder: &mut ListBuilder<StructBuilder>, xrefs: &[NamedXref]) {
+    let xrefs_struct = builder.values();
+    for xref in xrefs {
+        xrefs_struct
+            .field_builder::<DictStringBuilder>(0)
+            .unwrap()
+            .append_value(&xref.database);
+        xrefs_struct
+            .field_builder::<StringBuilder>(1)
+            .unwrap()
+            .append_value(&xref.id);
+        xrefs_struct
+            .field_builder::<StringBuilder>(2)
+            .unwrap()
+            .append_option(xref.name.as_deref());
+        xrefs_struct.append(true);
+    }
+    builder.append(true);
+}
+
+/// PDB chain ranges in the row's coordinates. Ranges are canonical positions, so isoform
+/// rows go through the row's mapper; a range whose ends fall in a spliced-out span is
+/// left out and recorded.
+fn append_structure_coverage(builder: &mut ListBuilder<StructBuilder>, row: &TransformedRow, rejects: &RejectLog) {
+    let entry: &ParsedEntry = &row.entry;
+    let coverage = builder.values();
+    for structure in entry.structures.iter().filter(|s| s.database == "PDB") {
+        for range in &structure.chains {
+            let Some((start, end)) = map_range_1based(entry, &row.sequence, &row.mapper, range.start, range.end)
+            else {
+                rejects.record(RejectKind::Feature, "FEATURE_UNMAPPED", &row.parent_id, &row.row_id, || {
+                    Some(format!(
+                        "column=structure_coverage feature_id={} start={} end={}",
+                        structure.id, range.start, range.end
+                    ))
+                });
+                continue;
+            };
+            coverage
+                .field_builder::<StringBuilder>(0)
+                .unwrap()
+                .append_value(&structure.id);
+            coverage
+                .field_builder::<DictStringBuilder>(1)
+                .unwrap()
+                .append_option(structure.method.as_deref());
+            coverage
+                .field_builder::<Float32Builder>(2)
+                .unwrap()
+                .append_option(structure.resolution);
+            coverage
+                .field_builder::<StringBuilder>(3)
+                .unwrap()
+                .append_value(&range.chains);
+            coverage.field_builder::<Int32Builder>(4).unwrap().append_value(start);
+            coverage.field_builder::<Int32Builder>(5).unwrap().append_value(end);
+            coverage.append(true);
+        }
+    }
+    builder.append(true);
+}
+
+fn append_subunits(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry, row: &TransformedRow) {
     let list_struct = builder.values();
     for sub in &entry.comments.subunits {
+        if !molecule_applies_to_row(entry, sub.molecule.as_deref(), &row.row_id) {
+            continue;
+        }
         let evidence_code = entry.resolve_evidence(&sub.evidence_keys);
         let confidence = entry.max_confidence_for_evidence(&sub.evidence_keys);
         list_struct
@@ -463,13 +1122,17 @@ fn append_subunits(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry
             .unwrap()
             .append_value(sub.text.trim());
         list_struct
-            .field_builder::<StringBuilder>(1)
+            .field_builder::<DictStringBuilder>(1)
             .unwrap()
             .append_option(evidence_code.as_deref());
         list_struct
             .field_builder::<Float32Builder>(2)
             .unwrap()
             .append_value(confidence);
+        list_struct
+            .field_builder::<StringBuilder>(3)
+            .unwrap()
+            .append_option(sub.molecule.as_deref());
         list_struct.append(true);
     }
     builder.append(true);
@@ -489,14 +1152,110 @@ fn append_interactions(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedE
             .unwrap()
             .append_option(inter.interactant_id_2.as_deref());
         list_struct
-            .field_builder::<StringBuilder>(2)
+            .field_builder::<DictStringBuilder>(2)
             .unwrap()
             .append_option(evidence_code.as_deref());
         list_struct
             .field_builder::<Float32Builder>(3)
             .unwrap()
             .append_value(confidence);
+        list_struct
+            .field_builder::<StringBuilder>(4)
+            .unwrap()
+            .append_option(inter.intact_id_1.as_deref());
+        list_struct
+            .field_builder::<StringBuilder>(5)
+            .unwrap()
+            .append_option(inter.intact_id_2.as_deref());
+        list_struct
+            .field_builder::<BooleanBuilder>(6)
+            .unwrap()
+            .append_option(inter.organisms_differ);
+        list_struct
+            .field_builder::<Int32Builder>(7)
+            .unwrap()
+            .append_option(inter.experiments);
         list_struct.append(true);
     }
     builder.append(true);
 }
+
+fn append_other_comments(builder: &mut ListBuilder<StructBuilder>, entry: &ParsedEntry, row: &TransformedRow) {
+    let list_struct = builder.values();
+    for comment in &entry.comments.others {
+        if !molecule_applies_to_row(entry, comment.molecule.as_deref(), &row.row_id) {
+            continue;
+        }
+        let evidence_code = entry.resolve_evidence(&comment.evidence_keys);
+        list_struct
+            .field_builder::<DictStringBuilder>(0)
+            .unwrap()
+            .append_value(&comment.comment_type);
+        list_struct
+            .field_builder::<StringBuilder>(1)
+            .unwrap()
+            .append_value(&comment.text);
+        list_struct
+            .field_builder::<DictStringBuilder>(2)
+            .unwrap()
+            .append_option(evidence_code.as_deref());
+        list_struct
+            .field_builder::<StringBuilder>(3)
+            .unwrap()
+            .append_option(comment.molecule.as_deref());
+        list_struct.append(true);
+    }
+    builder.append(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_positions_match_the_schema() {
+        let schema = create_uniprot_schema();
+        assert_eq!(schema.fields().len(), col::COUNT);
+        for (index, name) in [
+            (col::ID, "id"),
+            (col::SEQUENCE, "sequence"),
+            (col::ORGANISM_ID, "organism_id"),
+            (col::ISOFORMS, "isoforms"),
+            (col::FEATURES, "features"),
+            (col::LOCATION, "location"),
+            (col::ENTRY_NAME, "entry_name"),
+            (col::GENE_NAME, "gene_name"),
+            (col::PROTEIN_NAME, "protein_name"),
+            (col::ORGANISM_NAME, "organism_name"),
+            (col::EXISTENCE, "existence"),
+            (col::STRUCTURES, "structures"),
+            (col::STRUCTURE_COVERAGE, "structure_coverage"),
+            (col::ALPHAFOLD_MEAN_PLDDT, "alphafold_mean_plddt"),
+            (col::PARENT_ID, "parent_id"),
+            (col::PTM_SITES, "ptm_sites"),
+            (col::ACTIVE_SITES, "active_sites"),
+            (col::BINDING_SITES, "binding_sites"),
+            (col::METAL_COORDINATIONS, "metal_coordinations"),
+            (col::MUTAGENESIS_SITES, "mutagenesis_sites"),
+            (col::DOMAINS, "domains"),
+            (col::NATURAL_VARIANTS, "natural_variants"),
+            (col::SUBUNITS, "subunits"),
+            (col::INTERACTIONS, "interactions"),
+            (col::IS_FRAGMENT, "is_fragment"),
+            (col::IS_PRECURSOR, "is_precursor"),
+            (col::DATASET, "dataset"),
+            (col::GENE_LOCATION, "gene_location"),
+            (col::EC_NUMBERS, "ec_numbers"),
+            (col::PATHWAYS, "pathways"),
+            (col::DOMAIN_XREFS, "domain_xrefs"),
+            (col::ISOFORM_COUNT, "isoform_count"),
+            (col::SEQUENCE_LENGTH, "sequence_length"),
+            (col::PTM_SITE_COUNT, "ptm_site_count"),
+            (col::OTHER_COMMENTS, "other_comments"),
+            (col::RELEASE, "release"),
+            (col::ISOFORM_STATUS, "isoform_status"),
+        ] {
+            assert_eq!(schema.field(index).name(), name);
+        }
+    }
+}
@@ -1,109 +1,159 @@
-use arrow::array::{Float32Builder, Int32Builder, ListBuilder, StringBuilder, StructBuilder};
+use arrow::array::{Float32Builder, Float64Builder, Int32Builder, ListBuilder, StringBuilder, StructBuilder};
 use std::collections::BTreeMap;
 
 use crate::metrics::MetricsCollector;
 use crate::pipeline::mapper::{CoordinateMapper, MapFailure};
-use crate::pipeline::scratch::ParsedEntry;
-use crate::pipeline::transformer::TransformedRow;
+use crate::pipeline::rejects::{RejectKind, RejectLog};
+use crate::pipeline::builders::DictStringBuilder;
+use crate::pipeline::ptm_vocab::{class_for_feature_type, ModType, PtmTerm, PtmVocabulary};
+use crate::pipeline::scratch::{FeatureScratch, ParsedEntry};
+use crate::pipeline::trace::EntryTracer;
+use crate::pipeline::transformer::{sequence_applies_to_row, TransformedRow};
+use crate::pipeline::window::{sequence_window_with, SiteContext};
+use crate::log_event;
+use crate::logging::{Level, Repeats};
 
+/// How PTM sites are annotated beyond their position and residue.
+pub struct PtmAnnotation<'a> {
+    /// Classifies and identifies each modification
+    pub vocabulary: &'a PtmVocabulary,
+    /// Residue window written to `site_context` (null when None)
+    pub site_context: Option<SiteContext>,
+    /// Per-residue AlphaFold pLDDT of the row written to `plddt` (null when None)
+    pub plddt: Option<&'a [Option<f32>]>,
+    /// Fill `mapping_shift`/`mapping_failure_code` and keep the sites that fail to map
+    /// (`transform.debug_mapping`)
+    pub debug_mapping: bool,
+}
+
+/// Appends the row's PTM sites and returns how many mapped (failed sites kept by
+/// `debug_mapping` are not counted).
 pub fn append_ptm_sites<M: MetricsCollector>(
     builder: &mut ListBuilder<StructBuilder>,
     metrics: &M,
+    rejects: &RejectLog,
+    trace: &EntryTracer,
+    annotation: &PtmAnnotation,
     entry: &ParsedEntry,
     row: &TransformedRow,
-) {
+) -> usize {
     let isoform_bytes = row.sequence.as_bytes();
-    let mut sites: BTreeMap<i32, (u8, Vec<(i32, f32)>)> = BTreeMap::new();
+    // site_index -> (site_aa, mapping_shift, modifications)
+    let mut sites: BTreeMap<i32, (u8, Option<i32>, Vec<SiteModification>)> = BTreeMap::new();
+    // Sites that did not map, as given: (position, canonical residue, code, modification)
+    let mut unmapped = Vec::new();
+    let mut failures = Repeats::default();
 
+    let mut candidates = Vec::new();
     for feat in &entry.features.generic {
         let ft = feat.feature_type.to_ascii_lowercase();
-        let is_point_ptm =
-            ft == "glycosylation site" || ft == "modified residue" || ft == "cross-link";
-        if !is_point_ptm {
+        if class_for_feature_type(&ft) == 0 {
             continue;
         }
 
+        // Sites located on a specific isoform only belong to that isoform's row
+        if !sequence_applies_to_row(feat.sequence.as_deref(), &row.row_id) {
+            continue;
+        }
         let (Some(start), Some(end)) = (feat.start, feat.end) else {
             continue;
         };
         if start <= 0 || end <= 0 || start != end {
             continue;
         }
+        candidates.push((feat, ft, start));
+    }
 
-        metrics.add_ptm_attempted(1);
+    // Canonical coordinates of an isoform row go through its mapper in one pass
+    let isoform_row = row.row_id != row.parent_id;
+    let mut mapped_sites = if isoform_row {
+        let starts: Vec<i32> = candidates
+            .iter()
+            .filter(|(feat, _, _)| feat.sequence.is_none())
+            .map(|&(_, _, start)| start)
+            .collect();
+        row.mapper.map_points(&starts)
+    } else {
+        Vec::new()
+    }
+    .into_iter();
 
-        let Some(original_aa) = entry.canonical_aa_at_1based(start) else {
-            metrics.add_ptm_failed(1);
-            metrics.add_ptm_failed_canonical_oob(1);
-            eprintln!(
-                "[PTM_FAIL] code=CANONICAL_OOB parent_id={} id={} original_index={} mapped_index=?",
-                row.parent_id, row.row_id, start
-            );
-            continue;
-        };
+    for (feat, ft, start) in candidates {
+        metrics.add_ptm_attempted(1);
 
-        let mapped_1based = if row.row_id == row.parent_id {
-            start
+        let (frame, mapped) = if feat.sequence.is_some() {
+            (SiteFrame::RowSequence, None)
+        } else if isoform_row {
+            (SiteFrame::Isoform(&row.mapper), mapped_sites.next())
         } else {
-            match map_point(metrics, &row.mapper, start, &row.parent_id, &row.row_id) {
-                Ok(m) => m,
-                Err(_) => continue,
+            (SiteFrame::Canonical, None)
+        };
+        let outcome = resolve_mapped_site(entry.sequence.as_bytes(), isoform_bytes, start, frame, mapped);
+        let (mapped_1based, residue) = match outcome {
+            SiteOutcome::Mapped { index, residue } => (index, residue),
+            SiteOutcome::Failed { code, mapped } => {
+                report_failure(metrics, &mut failures, entry, row, frame, start, code, mapped);
+                reject_ptm(rejects, code, row, start);
+                trace_ptm(trace, row, &ft, start, || failure_trace(row, frame, code, start, mapped));
+                if annotation.debug_mapping {
+                    let residue = match frame {
+                        SiteFrame::RowSequence => None,
+                        _ => entry.canonical_aa_at_1based(start),
+                    }
+                    .unwrap_or(b'X');
+                    let modification = SiteModification::new(annotation.vocabulary, entry, &ft, feat, residue);
+                    unmapped.push((start, residue, code, modification));
+                }
+                continue;
             }
         };
+        let shift = match frame {
+            SiteFrame::RowSequence => None,
+            _ => Some(mapped_1based - start).filter(|_| annotation.debug_mapping),
+        };
 
-        let mapped_idx0 = (mapped_1based as usize).saturating_sub(1);
-        if mapped_idx0 >= isoform_bytes.len() {
-            metrics.add_ptm_failed(1);
-            metrics.add_ptm_failed_isoform_oob(1);
-            let shift = mapped_1based - start;
-            let expected_len = entry.sequence.len() as i32 + row.mapper.total_delta();
-            eprintln!(
-                "[PTM_FAIL] code=ISOFORM_OOB parent_id={} id={} original_index={} mapped_index={} isoform_len={} shift={} vsp_count={} expected_len={}",
-                row.parent_id,
-                row.row_id,
-                start,
-                mapped_1based,
-                isoform_bytes.len(),
-                shift,
-                row.mapper.edit_count(),
-                expected_len
-            );
-            continue;
+        let mut modification = SiteModification::new(annotation.vocabulary, entry, &ft, feat, residue);
+        if matches!(frame, SiteFrame::Isoform(mapper) if mapper.low_confidence()) {
+            modification.confidence = LOW_CONFIDENCE;
         }
-
-        let isoform_aa = isoform_bytes[mapped_idx0];
-
-        if isoform_aa != original_aa {
-            metrics.add_ptm_failed(1);
-            metrics.add_ptm_failed_residue_mismatch(1);
-            let shift = mapped_1based - start;
-            eprintln!(
-                "[PTM_FAIL] code=RESIDUE_MISMATCH parent_id={} id={} original_index={} mapped_index={} original_aa={} isoform_aa={} shift={} vsp_count={}",
-                row.parent_id,
-                row.row_id,
-                start,
-                mapped_1based,
-                original_aa as char,
-                isoform_aa as char,
-                shift,
-                row.mapper.edit_count()
-            );
-            continue;
-        }
-
-        let mod_type = classify_mod_type(&ft, feat.description.as_deref());
-        let confidence = entry.max_confidence_for_evidence(&feat.evidence_keys);
-
-        let entry_site = sites
+        let mod_type = modification.mod_type.code();
+        sites
             .entry(mapped_1based)
-            .or_insert_with(|| (original_aa, Vec::new()));
-        entry_site.1.push((mod_type, confidence));
+            .or_insert_with(|| (residue, shift, Vec::new()))
+            .2
+            .push(modification);
 
         metrics.add_ptm_mapped(1);
+        let located = if matches!(frame, SiteFrame::RowSequence) { "isoform-located" } else { "mapped" };
+        trace_ptm(trace, row, &ft, start, || {
+            format!("{} mapped_index={} residue={} mod_type={}", located, mapped_1based, residue as char, mod_type)
+        });
+    }
+
+    for (code, total, not_logged) in failures.over_limit() {
+        log_event!(
+            Level::Warn,
+            "ptm",
+            Some("PTM_FAIL_REPEAT"),
+            "{} ×{} for {} ({} not logged individually)",
+            code,
+            total,
+            row.row_id,
+            not_logged
+        );
     }
 
+    let mapped_sites = sites.len();
+    let sites = sites
+        .into_iter()
+        .map(|(index, (residue, shift, modifications))| (index, residue, shift, None, modifications))
+        .chain(
+            unmapped
+                .into_iter()
+                .map(|(start, residue, code, modification)| (start, residue, None, Some(code), vec![modification])),
+        );
     let sites_struct = builder.values();
-    for (site_index, (site_aa, modifications)) in sites {
+    for (site_index, site_aa, shift, failure, modifications) in sites {
         sites_struct
             .field_builder::<Int32Builder>(0)
             .unwrap()
@@ -117,71 +167,380 @@ pub fn append_ptm_sites<M: MetricsCollector>(
             .field_builder::<ListBuilder<StructBuilder>>(2)
             .unwrap();
         let mods_struct = mods_list.values();
-        for (mod_type, confidence_score) in modifications {
+        for modification in modifications {
             mods_struct
                 .field_builder::<Int32Builder>(0)
                 .unwrap()
-                .append_value(mod_type);
+                .append_value(modification.mod_type.code());
             mods_struct
                 .field_builder::<Float32Builder>(1)
                 .unwrap()
-                .append_value(confidence_score);
+                .append_value(modification.confidence);
+            let cross_link = modification.cross_link.unwrap_or_default();
+            mods_struct
+                .field_builder::<StringBuilder>(2)
+                .unwrap()
+                .append_option(cross_link.partner_protein);
+            mods_struct
+                .field_builder::<StringBuilder>(3)
+                .unwrap()
+                .append_option(cross_link.partner_residue);
+            mods_struct
+                .field_builder::<DictStringBuilder>(4)
+                .unwrap()
+                .append_option(cross_link.linkage_chemistry);
+            mods_struct
+                .field_builder::<DictStringBuilder>(5)
+                .unwrap()
+                .append_option(modification.mod_type.class_name());
+            mods_struct
+                .field_builder::<DictStringBuilder>(6)
+                .unwrap()
+                .append_option(modification.mod_type.subclass_name());
+            let term = modification.term;
+            mods_struct
+                .field_builder::<StringBuilder>(7)
+                .unwrap()
+                .append_option(term.and_then(|t| t.accession.as_deref()));
+            mods_struct
+                .field_builder::<StringBuilder>(8)
+                .unwrap()
+                .append_option(term.and_then(|t| t.target).map(String::from));
+            mods_struct
+                .field_builder::<Float64Builder>(9)
+                .unwrap()
+                .append_option(term.and_then(|t| t.monoisotopic_mass));
+            mods_struct
+                .field_builder::<Float64Builder>(10)
+                .unwrap()
+                .append_option(term.and_then(|t| t.average_mass));
+            mods_struct
+                .field_builder::<DictStringBuilder>(11)
+                .unwrap()
+                .append_option(modification.evidence_code);
             mods_struct.append(true);
         }
         mods_list.append(true);
 
+        let mapped = failure.is_none();
+        let context = annotation.site_context.filter(|_| mapped).and_then(|c| {
+            sequence_window_with(&row.sequence, site_index as usize, c.flank, c.padding)
+        });
+        sites_struct
+            .field_builder::<StringBuilder>(3)
+            .unwrap()
+            .append_option(context);
+        let plddt = annotation
+            .plddt
+            .filter(|_| mapped)
+            .and_then(|p| p.get(site_index as usize - 1).copied().flatten());
+        sites_struct
+            .field_builder::<Float32Builder>(4)
+            .unwrap()
+            .append_option(plddt);
+        sites_struct
+            .field_builder::<Int32Builder>(5)
+            .unwrap()
+            .append_option(shift);
+        sites_struct
+            .field_builder::<DictStringBuilder>(6)
+            .unwrap()
+            .append_option(failure);
+
         sites_struct.append(true);
     }
     builder.append(true);
+    mapped_sites
+}
+
+/// Confidence of sites mapped through a mapper marked low-confidence; the score
+/// `max_confidence_for_evidence` gives predicted or unsupported annotations.
+const LOW_CONFIDENCE: f32 = 0.1;
+
+/// One modification at a PTM site.
+struct SiteModification<'v> {
+    mod_type: ModType,
+    /// Vocabulary term the feature description names, if any
+    term: Option<&'v PtmTerm>,
+    confidence: f32,
+    /// ECO codes of the feature's evidence, `;`-joined
+    evidence_code: Option<String>,
+    cross_link: Option<CrossLink>,
+}
+
+impl<'v> SiteModification<'v> {
+    fn new(
+        vocabulary: &'v PtmVocabulary,
+        entry: &ParsedEntry,
+        feature_type_lower: &str,
+        feat: &FeatureScratch,
+        residue: u8,
+    ) -> Self {
+        let description = feat.description.as_deref();
+        let term = description.and_then(|d| vocabulary.term(d));
+        Self {
+            mod_type: ModType::classify(term, feature_type_lower, residue),
+            term,
+            confidence: entry.max_confidence_for_evidence(&feat.evidence_keys),
+            evidence_code: entry.resolve_evidence(&feat.evidence_keys),
+            cross_link: (feature_type_lower == "cross-link")
+                .then(|| parse_cross_link(description.unwrap_or_default())),
+        }
+    }
+}
+
+/// Structured form of a cross-link description such as
+/// "Glycyl lysine isopeptide (Lys-Gly) (interchain with G-Cter in ubiquitin)".
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CrossLink {
+    /// Protein on the other end of an interchain link (e.g. "ubiquitin", "SUMO2")
+    pub partner_protein: Option<String>,
+    /// Partner residue as UniProt writes it (e.g. "G-Cter", "K-63")
+    pub partner_residue: Option<String>,
+    /// Linked residue pair (e.g. "Lys-Gly")
+    pub linkage_chemistry: Option<String>,
+}
+
+/// Parses the parenthesised parts of a cross-link description: the first one is the
+/// linkage chemistry, an "interchain with <residue>[ in <protein>]" one names the partner.
+pub fn parse_cross_link(description: &str) -> CrossLink {
+    let mut cross_link = CrossLink::default();
+    // Drop qualifiers such as "; alternate"
+    let description = description.split(';').next().unwrap_or_default();
+    for part in description.split('(').skip(1) {
+        let Some(part) = part.split(')').next().map(str::trim) else {
+            continue;
+        };
+        if let Some(partner) = part.strip_prefix("interchain with ") {
+            let (residue, protein) = match partner.split_once(" in ") {
+                Some((residue, protein)) => (residue, Some(protein)),
+                None => (partner, None),
+            };
+            cross_link.partner_residue = Some(residue.trim().to_string());
+            cross_link.partner_protein = protein.map(|p| p.trim().to_string());
+        } else if part.starts_with("intrachain") {
+            continue;
+        } else if cross_link.linkage_chemistry.is_none() && !part.is_empty() {
+            cross_link.linkage_chemistry = Some(part.to_string());
+        }
+    }
+    cross_link
+}
+
+fn reject_ptm(rejects: &RejectLog, code: &'static str, row: &TransformedRow, original_index: i32) {
+    rejects.record(RejectKind::Feature, code, &row.parent_id, &row.row_id, || {
+        Some(format!("ptm original_index={}", original_index))
+    });
+}
+
+fn trace_ptm(
+    trace: &EntryTracer,
+    row: &TransformedRow,
+    feature_type: &str,
+    original_index: i32,
+    outcome: impl FnOnce() -> String,
+) {
+    trace.log(&row.parent_id, "ptm", || {
+        format!("row={} type={:?} original_index={} {}", row.row_id, feature_type, original_index, outcome())
+    });
+}
+
+/// Coordinate frame of a single-residue PTM feature on the row being built.
+#[derive(Debug, Clone, Copy)]
+pub enum SiteFrame<'a> {
+    /// `<location sequence>`: the coordinate already refers to the row sequence
+    RowSequence,
+    /// Canonical coordinate on the canonical row
+    Canonical,
+    /// Canonical coordinate mapped onto an isoform row through its VSP edits
+    Isoform(&'a CoordinateMapper),
+}
+
+/// Where a PTM site lands on a row, or why it does not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SiteOutcome {
+    /// 1-based position on the row sequence and the residue there
+    Mapped { index: i32, residue: u8 },
+    /// `[PTM_FAIL]` code, with the row position when one was computed
+    Failed { code: &'static str, mapped: Option<i32> },
+}
+
+/// Places the PTM at 1-based `start` on `row_sequence`: maps it through the frame and
+/// checks that the canonical residue survives on the row.
+pub fn resolve_site(canonical: &[u8], row_sequence: &[u8], start: i32, frame: SiteFrame) -> SiteOutcome {
+    resolve_mapped_site(canonical, row_sequence, start, frame, None)
+}
+
+/// `resolve_site` with the mapper's result for `start` already computed
+/// (`CoordinateMapper::map_points`); `mapped` only matters for `SiteFrame::Isoform`,
+/// where None maps `start` here.
+pub fn resolve_mapped_site(
+    canonical: &[u8],
+    row_sequence: &[u8],
+    start: i32,
+    frame: SiteFrame,
+    mapped: Option<Result<i32, MapFailure>>,
+) -> SiteOutcome {
+    let residue_at = |sequence: &[u8], pos: i32| sequence.get((pos as usize).wrapping_sub(1)).copied();
+
+    let mapped = match frame {
+        SiteFrame::RowSequence => {
+            // No mapping and no residue check against the canonical sequence
+            return match residue_at(row_sequence, start) {
+                Some(residue) => SiteOutcome::Mapped { index: start, residue },
+                None => SiteOutcome::Failed { code: "ISOFORM_OOB", mapped: Some(start) },
+            };
+        }
+        SiteFrame::Canonical => None,
+        SiteFrame::Isoform(mapper) => Some(mapped.unwrap_or_else(|| mapper.map_point_1based(start))),
+    };
+
+    let Some(original_aa) = residue_at(canonical, start) else {
+        return SiteOutcome::Failed { code: "CANONICAL_OOB", mapped: None };
+    };
+    let mapped = match mapped {
+        None => start,
+        Some(Ok(mapped)) => mapped,
+        Some(Err(failure)) => return SiteOutcome::Failed { code: failure.code(), mapped: None },
+    };
+    match residue_at(row_sequence, mapped) {
+        None => SiteOutcome::Failed { code: "ISOFORM_OOB", mapped: Some(mapped) },
+        Some(isoform_aa) if isoform_aa != original_aa => {
+            SiteOutcome::Failed { code: "RESIDUE_MISMATCH", mapped: Some(mapped) }
+        }
+        Some(residue) => SiteOutcome::Mapped { index: mapped, residue },
+    }
 }
 
-fn map_point<M: MetricsCollector>(
+/// Counts a failed site and logs its `[PTM_FAIL]` line while `failures` admits the code.
+#[allow(clippy::too_many_arguments)]
+fn report_failure<M: MetricsCollector>(
     metrics: &M,
-    mapper: &CoordinateMapper,
+    failures: &mut Repeats,
+    entry: &ParsedEntry,
+    row: &TransformedRow,
+    frame: SiteFrame,
     start: i32,
-    parent_id: &str,
-    row_id: &str,
-) -> Result<i32, ()> {
-    match mapper.map_point_1based(start) {
-        Ok(m) => Ok(m),
-        Err(MapFailure::VspDeletionEvent) => {
-            metrics.add_ptm_failed(1);
-            metrics.add_ptm_failed_vsp_deletion(1);
-            eprintln!(
-                "[PTM_FAIL] code=VSP_DELETION_EVENT parent_id={} id={} original_index={} mapped_index=?",
-                parent_id, row_id, start
+    code: &'static str,
+    mapped: Option<i32>,
+) {
+    metrics.add_ptm_failed(1);
+    match code {
+        "ISOFORM_OOB" => metrics.add_ptm_failed_isoform_oob(1),
+        "RESIDUE_MISMATCH" => metrics.add_ptm_failed_residue_mismatch(1),
+        "CANONICAL_OOB" => metrics.add_ptm_failed_canonical_oob(1),
+        "VSP_DELETION_EVENT" => metrics.add_ptm_failed_vsp_deletion(1),
+        "MAPPER_OOB" => metrics.add_ptm_failed_mapper_oob(1),
+        _ => metrics.add_ptm_failed_vsp_unresolvable(1),
+    }
+    if !failures.admit(code) {
+        return;
+    }
+
+    let isoform_bytes = row.sequence.as_bytes();
+    let mapped_index = mapped.unwrap_or(start);
+    match code {
+        "ISOFORM_OOB" => {
+            let (shift, vsp_count, expected_len) = match frame {
+                SiteFrame::RowSequence => (0, 0, isoform_bytes.len() as i32),
+                _ => (
+                    mapped_index - start,
+                    row.mapper.edit_count(),
+                    entry.sequence.len() as i32 + row.mapper.total_delta(),
+                ),
+            };
+            log_event!(
+                Level::Warn,
+                "ptm",
+                Some("PTM_FAIL"),
+                "code=ISOFORM_OOB parent_id={} id={} original_index={} mapped_index={} isoform_len={} shift={} vsp_count={} expected_len={}",
+                row.parent_id,
+                row.row_id,
+                start,
+                mapped_index,
+                isoform_bytes.len(),
+                shift,
+                vsp_count,
+                expected_len
             );
-            Err(())
         }
-        Err(MapFailure::PtmOutOfBounds) => {
-            metrics.add_ptm_failed(1);
-            metrics.add_ptm_failed_mapper_oob(1);
-            eprintln!(
-                "[PTM_FAIL] code=MAPPER_OOB parent_id={} id={} original_index={} mapped_index=?",
-                parent_id, row_id, start
+        "RESIDUE_MISMATCH" => {
+            let original_aa = entry.canonical_aa_at_1based(start).unwrap_or(b'?');
+            let isoform_aa = isoform_bytes[mapped_index as usize - 1];
+            log_event!(
+                Level::Warn,
+                "ptm",
+                Some("PTM_FAIL"),
+                "code=RESIDUE_MISMATCH parent_id={} id={} original_index={} mapped_index={} original_aa={} isoform_aa={} shift={} vsp_count={}",
+                row.parent_id,
+                row.row_id,
+                start,
+                mapped_index,
+                original_aa as char,
+                isoform_aa as char,
+                mapped_index - start,
+                row.mapper.edit_count()
             );
-            Err(())
         }
-        Err(MapFailure::VspUnresolvable) => {
-            metrics.add_ptm_failed(1);
-            metrics.add_ptm_failed_vsp_unresolvable(1);
-            eprintln!(
-                "[PTM_FAIL] code=VSP_UNRESOLVABLE parent_id={} id={} original_index={} mapped_index=?",
-                parent_id, row_id, start
+        _ => {
+            log_event!(
+                Level::Warn,
+                "ptm",
+                Some("PTM_FAIL"),
+                "code={} parent_id={} id={} original_index={} mapped_index=?",
+                code, row.parent_id, row.row_id, start
             );
-            Err(())
         }
     }
 }
 
-fn classify_mod_type(feature_type_lower: &str, description: Option<&str>) -> i32 {
-    let desc = description.unwrap_or("").to_ascii_lowercase();
+/// Trace outcome of a failed site.
+fn failure_trace(row: &TransformedRow, frame: SiteFrame, code: &str, start: i32, mapped: Option<i32>) -> String {
+    let Some(mapped) = mapped else {
+        return format!("code={}", code);
+    };
+    let isoform_bytes = row.sequence.as_bytes();
+    match (code, frame) {
+        ("ISOFORM_OOB", SiteFrame::RowSequence) => {
+            format!("code=ISOFORM_OOB isoform-located mapped_index={} isoform_len={}", mapped, isoform_bytes.len())
+        }
+        ("ISOFORM_OOB", _) => format!("code=ISOFORM_OOB mapped_index={} isoform_len={}", mapped, isoform_bytes.len()),
+        _ => format!(
+            "code={} mapped_index={} original_aa={} isoform_aa={}",
+            code,
+            mapped,
+            row.entry.canonical_aa_at_1based(start).unwrap_or(b'?') as char,
+            isoform_bytes[mapped as usize - 1] as char
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if feature_type_lower == "modified residue" && desc.contains("phospho") {
-        1
-    } else if feature_type_lower == "glycosylation site" && desc.contains("n-acetylglucosamine") {
-        2
-    } else {
-        0
+    #[test]
+    fn parses_interchain_cross_link_partner() {
+        let cross_link =
+            parse_cross_link("Glycyl lysine isopeptide (Lys-Gly) (interchain with G-Cter in ubiquitin); alternate");
+        assert_eq!(cross_link.linkage_chemistry.as_deref(), Some("Lys-Gly"));
+        assert_eq!(cross_link.partner_residue.as_deref(), Some("G-Cter"));
+        assert_eq!(cross_link.partner_protein.as_deref(), Some("ubiquitin"));
+    }
+
+    #[test]
+    fn parses_cross_link_without_partner_protein() {
+        let cross_link = parse_cross_link("Isoglutamyl lysine isopeptide (Lys-Gln) (interchain with Q-?)");
+        assert_eq!(cross_link.linkage_chemistry.as_deref(), Some("Lys-Gln"));
+        assert_eq!(cross_link.partner_residue.as_deref(), Some("Q-?"));
+        assert_eq!(cross_link.partner_protein, None);
+
+        assert_eq!(
+            parse_cross_link("Lysine tyrosylquinone (Lys-Tyr)"),
+            CrossLink {
+                linkage_chemistry: Some("Lys-Tyr".to_string()),
+                ..CrossLink::default()
+            }
+        );
     }
 }
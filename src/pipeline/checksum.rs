@@ -0,0 +1,82 @@
+//! UniProt sequence checksums (`<sequence checksum="...">`).
+//!
+//! UniProt uses the CRC-64-ISO polynomial (ISO 3309), bit-reflected, zero initial
+//! value and no final XOR, printed as 16 uppercase hex digits.
+
+/// Reflected CRC-64-ISO polynomial.
+const POLY: u64 = 0xD800_0000_0000_0000;
+
+const TABLE: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut part = i as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            part = if part & 1 == 1 { (part >> 1) ^ POLY } else { part >> 1 };
+            bit += 1;
+        }
+        table[i] = part;
+        i += 1;
+    }
+    table
+};
+
+/// CRC64 of `sequence` as computed by UniProt.
+pub fn crc64(sequence: &[u8]) -> u64 {
+    sequence.iter().fold(0u64, |crc, &byte| {
+        TABLE[((crc ^ byte as u64) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// CRC64 formatted like the XML `checksum` attribute.
+pub fn crc64_hex(sequence: &[u8]) -> String {
+    format!("{:016X}", crc64(sequence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc64_matches_uniprot() {
+        // Reference values from Biopython's `crc64` / SWISS-PROT
+        assert_eq!(crc64_hex(b"ACGTACGTACGT"), "C4FBB762C4A87EBD");
+        assert_eq!(crc64_hex(b""), "0000000000000000");
+    }
+
+    #[test]
+    fn test_mismatches_are_counted_or_fatal_when_strict() {
+        use crate::error::EtlError;
+        use crate::metrics::Metrics;
+        use crate::pipeline::parser::{parse_entries_with_hooks, ParseHooks};
+        use crossbeam_channel::unbounded;
+        use quick_xml::Reader;
+        use std::io::Cursor;
+
+        let xml = r#"<uniprot>
+            <entry><accession>P1</accession><sequence length="12" checksum="C4FBB762C4A87EBD">ACGT ACGT
+            ACGT</sequence></entry>
+            <entry><accession>P2</accession><sequence length="4" checksum="0123456789ABCDEF">MTAK</sequence></entry>
+        </uniprot>"#;
+        let run = |strict_checksums| {
+            let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+            reader.config_mut().trim_text(true);
+            let hooks = ParseHooks {
+                strict_checksums,
+                ..ParseHooks::default()
+            };
+            let metrics = Metrics::new();
+            let (tx, _rx) = unbounded();
+            let result = parse_entries_with_hooks(reader, tx, &metrics, 16, None, &hooks);
+            (result, metrics.checksum_mismatches())
+        };
+
+        let (result, mismatches) = run(false);
+        assert!(result.is_ok());
+        assert_eq!(mismatches, 1);
+
+        let (result, _) = run(true);
+        assert!(matches!(result, Err(EtlError::ChecksumMismatch { accession, .. }) if accession == "P2"));
+    }
+}
@@ -0,0 +1,197 @@
+//! Accession deduplication across the inputs of a run (`transform.deduplicate`).
+//!
+//! Overlapping chunk dumps repeat entries, and every copy would otherwise become rows of
+//! its own. `AccessionDedup` is shared by every worker and decides at transform time
+//! whether an entry is the copy to keep:
+//! - `first_wins`: the first copy transformed wins. In swarm mode that is whichever file
+//!   reaches the accession first, so when copies differ the survivor can vary between runs.
+//! - `newest_version`: the copy with the highest `<entry version>` wins. The inputs are
+//!   scanned for versions before the run (`scan_versions`); among copies of the newest
+//!   version the first transformed wins.
+//!
+//! Dropped copies are counted in `duplicate_entries` and recorded as `DUPLICATE_ACCESSION`
+//! rejects. Every kept accession is remembered for the whole run, so memory grows with
+//! the number of distinct accessions.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Settings;
+use crate::error::Result;
+use crate::metrics::Metrics;
+use crate::pipeline::handlers::{get_attribute, read_text, skip_element};
+use crate::pipeline::reader::create_xml_reader;
+
+/// Which copy of a repeated accession is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupPolicy {
+    /// Keep every copy
+    #[default]
+    Off,
+    FirstWins,
+    NewestVersion,
+}
+
+/// Claimed accessions are split over this many locks so workers rarely contend.
+const SHARDS: usize = 16;
+
+struct DedupState {
+    /// Highest `<entry version>` per accession (`newest_version` only)
+    newest: Option<HashMap<String, u32>>,
+    claimed: [Mutex<HashSet<String>>; SHARDS],
+}
+
+/// Run-wide record of the accessions already transformed (disabled by default).
+#[derive(Clone, Default)]
+pub struct AccessionDedup {
+    state: Option<Arc<DedupState>>,
+}
+
+impl AccessionDedup {
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn first_wins() -> Self {
+        Self::with_newest(None)
+    }
+
+    /// Keeps the copies at the `newest` version of their accession (see `scan_versions`).
+    pub fn newest_version(newest: HashMap<String, u32>) -> Self {
+        Self::with_newest(Some(newest))
+    }
+
+    fn with_newest(newest: Option<HashMap<String, u32>>) -> Self {
+        Self {
+            state: Some(Arc::new(DedupState {
+                newest,
+                claimed: std::array::from_fn(|_| Mutex::new(HashSet::new())),
+            })),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Why the copy of `accession` at `version` is dropped, or None when it is kept (and
+    /// claims the accession).
+    pub fn duplicate_reason(&self, accession: &str, version: Option<u32>) -> Option<&'static str> {
+        let state = self.state.as_ref()?;
+        if let Some(newest) = &state.newest {
+            if newest.get(accession).is_some_and(|&n| version.unwrap_or(0) < n) {
+                return Some("a newer version is in the inputs");
+            }
+        }
+        let mut hasher = DefaultHasher::new();
+        accession.hash(&mut hasher);
+        let shard = &state.claimed[hasher.finish() as usize % SHARDS];
+        if shard.lock().unwrap().insert(accession.to_string()) {
+            None
+        } else {
+            Some("already transformed from another copy")
+        }
+    }
+}
+
+/// Highest `<entry version>` of every primary accession in `files` (missing versions
+/// count as 0). Files are scanned in parallel.
+pub fn scan_versions(files: &[PathBuf], settings: &Settings) -> Result<HashMap<String, u32>> {
+    let per_file = files
+        .par_iter()
+        .map(|path| {
+            let mut reader = create_xml_reader(path, settings, &Metrics::new())?;
+            entry_versions(&mut reader)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut newest = HashMap::new();
+    for versions in per_file {
+        for (accession, version) in versions {
+            let entry = newest.entry(accession).or_insert(version);
+            *entry = (*entry).max(version);
+        }
+    }
+    Ok(newest)
+}
+
+fn entry_versions<R: BufRead>(reader: &mut Reader<R>) -> Result<HashMap<String, u32>> {
+    let mut versions = HashMap::new();
+    let mut buf = Vec::new();
+    let mut inner = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"entry" => {
+                let version = get_attribute(&e, b"version")?.and_then(|v| v.parse().ok()).unwrap_or(0);
+                let Some(accession) = primary_accession(reader, &mut inner)? else {
+                    continue;
+                };
+                skip_element(reader, b"entry", &mut inner)?;
+                let entry = versions.entry(accession).or_insert(version);
+                *entry = (*entry).max(version);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(versions)
+}
+
+/// The entry's first `<accession>`; None (with the entry consumed) when it has none.
+fn primary_accession<R: BufRead>(reader: &mut Reader<R>, buf: &mut Vec<u8>) -> Result<Option<String>> {
+    loop {
+        buf.clear();
+        match reader.read_event_into(buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"accession" => break,
+            Event::End(e) if e.local_name().as_ref() == b"entry" => return Ok(None),
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+    read_text(reader, b"accession", buf).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_first_copy_or_newest_version() {
+        let dedup = AccessionDedup::first_wins();
+        assert_eq!(dedup.duplicate_reason("P1", Some(3)), None);
+        assert!(dedup.duplicate_reason("P1", Some(5)).is_some());
+        assert_eq!(dedup.duplicate_reason("P2", None), None);
+
+        let dedup = AccessionDedup::newest_version(HashMap::from([("P1".to_string(), 5)]));
+        assert_eq!(dedup.duplicate_reason("P1", Some(3)), Some("a newer version is in the inputs"));
+        assert_eq!(dedup.duplicate_reason("P1", Some(5)), None);
+        assert!(dedup.duplicate_reason("P1", Some(5)).is_some());
+
+        assert_eq!(AccessionDedup::disabled().duplicate_reason("P1", Some(1)), None);
+    }
+
+    #[test]
+    fn scans_the_newest_version_of_each_accession() {
+        let xml = r#"<uniprot>
+            <entry version="3"><accession>P1</accession><accession>Q1</accession><name>A</name></entry>
+            <entry version="7"><accession>P1</accession></entry>
+            <entry><accession>P2</accession></entry>
+            <entry version="2"><name>no accession</name></entry>
+        </uniprot>"#;
+        let mut reader = Reader::from_reader(xml.as_bytes());
+        reader.config_mut().trim_text(true);
+        let versions = entry_versions(&mut reader).unwrap();
+        assert_eq!(versions, HashMap::from([("P1".to_string(), 7), ("P2".to_string(), 0)]));
+    }
+}
@@ -0,0 +1,293 @@
+//! User-defined, entry-level enrichment hooks.
+//!
+//! An [`EntryEnricher`] runs on every row produced by `EntryTransformer` and may
+//! contribute values for its own extra columns or drop the row entirely. Extra
+//! columns are appended after the built-in schema, in registration order.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use arrow::datatypes::{DataType, Field, SchemaRef};
+
+use crate::error::{EtlError, Result};
+use crate::pipeline::rejects::{RejectKind, RejectLog};
+use crate::pipeline::transformer::TransformedRow;
+use crate::schema::{create_uniprot_schema, schema_with_extra_columns};
+
+/// A single extra-column value emitted by an enricher.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtraValue {
+    Null,
+    Boolean(bool),
+    Int64(i64),
+    Float64(f64),
+    Utf8(String),
+}
+
+impl ExtraValue {
+    fn matches(&self, data_type: &DataType) -> bool {
+        matches!(
+            (self, data_type),
+            (ExtraValue::Null, _)
+                | (ExtraValue::Boolean(_), DataType::Boolean)
+                | (ExtraValue::Int64(_), DataType::Int64)
+                | (ExtraValue::Float64(_), DataType::Float64)
+                | (ExtraValue::Utf8(_), DataType::Utf8)
+        )
+    }
+}
+
+/// Whether a row survives enrichment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enrichment {
+    Keep,
+    Drop,
+}
+
+/// Hook invoked once per transformed row.
+pub trait EntryEnricher: Send + Sync {
+    /// Columns this enricher contributes (Boolean, Int64, Float64 or Utf8).
+    fn extra_columns(&self) -> Vec<Field> {
+        Vec::new()
+    }
+
+    /// Pushes exactly one value per extra column onto `values`, in declaration order.
+    fn enrich(&self, row: &TransformedRow, values: &mut Vec<ExtraValue>) -> Enrichment;
+}
+
+/// Ordered set of registered enrichers and the extra columns they own.
+#[derive(Clone, Default)]
+pub struct Enrichers {
+    enrichers: Vec<Arc<dyn EntryEnricher>>,
+    fields: Vec<Field>,
+    // Number of extra columns owned by each enricher, parallel to `enrichers`
+    widths: Vec<usize>,
+}
+
+impl Enrichers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an enricher, validating its extra columns against the schema so far.
+    pub fn register(&mut self, enricher: impl EntryEnricher + 'static) -> Result<&mut Self> {
+        let columns = enricher.extra_columns();
+
+        let builtin = create_uniprot_schema();
+        let mut taken: HashSet<&str> = builtin
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .chain(self.fields.iter().map(|f| f.name().as_str()))
+            .collect();
+        for field in &columns {
+            if !matches!(
+                field.data_type(),
+                DataType::Boolean | DataType::Int64 | DataType::Float64 | DataType::Utf8
+            ) {
+                return Err(EtlError::InvalidExtraColumn(format!(
+                    "{}: unsupported type {}",
+                    field.name(),
+                    field.data_type()
+                )));
+            }
+            if !taken.insert(field.name().as_str()) {
+                return Err(EtlError::InvalidExtraColumn(format!(
+                    "{}: column already exists",
+                    field.name()
+                )));
+            }
+        }
+
+        self.widths.push(columns.len());
+        self.fields.extend(columns);
+        self.enrichers.push(Arc::new(enricher));
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.enrichers.is_empty()
+    }
+
+    /// Extra columns in the order they are appended to the schema.
+    pub fn extra_fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Built-in schema followed by every registered extra column.
+    pub fn schema(&self) -> SchemaRef {
+        schema_with_extra_columns(&self.fields)
+    }
+
+    /// Runs every enricher over `rows`, filling `extra` and removing dropped rows.
+    pub fn apply(&self, rows: &mut Vec<TransformedRow>, rejects: &RejectLog) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let mut error = None;
+        rows.retain_mut(|row| {
+            if error.is_some() {
+                return false;
+            }
+            match self.enrich_row(row) {
+                Ok(Enrichment::Keep) => true,
+                Ok(Enrichment::Drop) => {
+                    rejects.record(RejectKind::Row, "ENRICHER_DROP", &row.parent_id, &row.row_id, || None);
+                    false
+                }
+                Err(e) => {
+                    error = Some(e);
+                    false
+                }
+            }
+        });
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn enrich_row(&self, row: &mut TransformedRow) -> Result<Enrichment> {
+        let mut values = std::mem::take(&mut row.extra);
+        values.clear();
+
+        let mut offset = 0;
+        for (enricher, &width) in self.enrichers.iter().zip(&self.widths) {
+            if enricher.enrich(row, &mut values) == Enrichment::Drop {
+                return Ok(Enrichment::Drop);
+            }
+            if values.len() != offset + width {
+                return Err(EtlError::InvalidExtraColumn(format!(
+                    "enricher emitted {} values for {} columns (row {})",
+                    values.len().saturating_sub(offset),
+                    width,
+                    row.row_id
+                )));
+            }
+            for (value, field) in values[offset..].iter().zip(&self.fields[offset..offset + width]) {
+                if !value.matches(field.data_type()) {
+                    return Err(EtlError::InvalidExtraColumn(format!(
+                        "{}: value {:?} does not match type {}",
+                        field.name(),
+                        value,
+                        field.data_type()
+                    )));
+                }
+            }
+            offset += width;
+        }
+
+        row.extra = values;
+        Ok(Enrichment::Keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::{parse_entries_with_hooks, ParseHooks};
+    use arrow::array::{Array, BooleanArray, Int64Array, StringArray};
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+    use std::io::Cursor;
+
+    struct SequenceBytes;
+
+    impl EntryEnricher for SequenceBytes {
+        fn extra_columns(&self) -> Vec<Field> {
+            vec![
+                Field::new("sequence_bytes", DataType::Int64, false),
+                Field::new("has_gene", DataType::Boolean, false),
+            ]
+        }
+
+        fn enrich(&self, row: &TransformedRow, values: &mut Vec<ExtraValue>) -> Enrichment {
+            if row.row_id == "DROPME" {
+                return Enrichment::Drop;
+            }
+            values.push(ExtraValue::Int64(row.sequence.len() as i64));
+            values.push(ExtraValue::Boolean(row.entry.gene_name.is_some()));
+            Enrichment::Keep
+        }
+    }
+
+    #[test]
+    fn test_enricher_adds_columns_and_drops_rows() {
+        let xml = r#"<uniprot>
+            <entry><accession>P1</accession><gene><name type="primary">ABC</name></gene><sequence length="4">MTAK</sequence></entry>
+            <entry><accession>DROPME</accession><sequence length="2">MT</sequence></entry>
+            <entry><accession>P2</accession><sequence length="6">MTAKLL</sequence></entry>
+        </uniprot>"#;
+        let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+        reader.config_mut().trim_text(true);
+
+        let mut enrichers = Enrichers::new();
+        enrichers.register(SequenceBytes).unwrap();
+        let (rejects, rejected) = RejectLog::channel();
+        let hooks = ParseHooks {
+            enrichers: enrichers.clone(),
+            rejects,
+            ..ParseHooks::default()
+        };
+
+        let (tx, rx) = unbounded();
+        parse_entries_with_hooks(reader, tx, &Metrics::new(), 16, None, &hooks).unwrap();
+        drop(hooks);
+        let batches: Vec<_> = rx.iter().collect();
+        let batch = &batches[0];
+
+        assert_eq!(batch.schema(), enrichers.schema());
+        assert_eq!(batch.num_rows(), 2);
+        let ids = batch
+            .column_by_name("id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let lengths = batch
+            .column_by_name("sequence_bytes")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        let has_gene = batch
+            .column_by_name("has_gene")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .unwrap();
+        assert_eq!(ids.value(1), "P2");
+        assert_eq!(lengths.values(), &[4, 6]);
+        assert!(has_gene.value(0));
+        assert!(!has_gene.value(1));
+
+        let rejected: Vec<_> = rejected.iter().collect();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].id, "DROPME");
+        assert_eq!(rejected[0].code, "ENRICHER_DROP");
+    }
+
+    #[test]
+    fn test_register_rejects_clashing_column() {
+        struct Clash;
+        impl EntryEnricher for Clash {
+            fn extra_columns(&self) -> Vec<Field> {
+                vec![Field::new("gene_name", DataType::Utf8, true)]
+            }
+            fn enrich(&self, _: &TransformedRow, values: &mut Vec<ExtraValue>) -> Enrichment {
+                values.push(ExtraValue::Null);
+                Enrichment::Keep
+            }
+        }
+
+        let mut enrichers = Enrichers::new();
+        assert!(matches!(
+            enrichers.register(Clash),
+            Err(EtlError::InvalidExtraColumn(_))
+        ));
+        assert!(enrichers.is_empty());
+    }
+}
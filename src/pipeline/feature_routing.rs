@@ -0,0 +1,118 @@
+//! Feature type -> output column routing (`transform.feature_routes`).
+//!
+//! Every `<feature>` lands in `features`; the routing table decides which typed column
+//! (`active_sites`, `binding_sites`, ...) it also fills. The embedded table lists every
+//! feature type of the UniProt XSD, and `transform.feature_routes` adds or overrides
+//! entries, so a renamed or newly introduced UniProt type can be routed without a code
+//! change. Types missing from the table only fill `features`; they are counted in
+//! `unknown_feature_types` and logged once per run per type.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::pipeline::scratch::FeatureContext;
+
+/// UniProt feature types and the column each fills besides `features`.
+const DEFAULT_ROUTES: &[(&str, FeatureContext)] = &[
+    ("active site", FeatureContext::ActiveSite),
+    ("binding site", FeatureContext::BindingSite),
+    ("calcium-binding region", FeatureContext::Generic),
+    ("chain", FeatureContext::Generic),
+    ("coiled-coil region", FeatureContext::Generic),
+    ("compositionally biased region", FeatureContext::Generic),
+    ("cross-link", FeatureContext::Generic),
+    ("disulfide bond", FeatureContext::Generic),
+    ("DNA-binding region", FeatureContext::Generic),
+    ("domain", FeatureContext::Domain),
+    ("glycosylation site", FeatureContext::Generic),
+    ("helix", FeatureContext::Generic),
+    ("initiator methionine", FeatureContext::Generic),
+    ("intramembrane region", FeatureContext::Generic),
+    ("lipid moiety-binding region", FeatureContext::Generic),
+    ("metal ion-binding site", FeatureContext::MetalCoordination),
+    ("modified residue", FeatureContext::Generic),
+    ("mutagenesis site", FeatureContext::Mutagenesis),
+    ("non-consecutive residues", FeatureContext::Generic),
+    ("non-standard amino acid", FeatureContext::Generic),
+    ("non-terminal residue", FeatureContext::Generic),
+    ("nucleotide phosphate-binding region", FeatureContext::Generic),
+    ("peptide", FeatureContext::Generic),
+    ("propeptide", FeatureContext::Generic),
+    ("region of interest", FeatureContext::Generic),
+    ("repeat", FeatureContext::Generic),
+    ("sequence conflict", FeatureContext::Generic),
+    ("sequence variant", FeatureContext::NaturalVariant),
+    ("short sequence motif", FeatureContext::Generic),
+    ("signal peptide", FeatureContext::Generic),
+    ("site", FeatureContext::Generic),
+    ("splice variant", FeatureContext::Generic),
+    ("strand", FeatureContext::Generic),
+    ("topological domain", FeatureContext::Generic),
+    ("transit peptide", FeatureContext::Generic),
+    ("transmembrane region", FeatureContext::Generic),
+    ("turn", FeatureContext::Generic),
+    ("unsure residue", FeatureContext::Generic),
+    ("zinc finger region", FeatureContext::Generic),
+];
+
+/// Run-wide routing table; clones share it and the set of unknown types already logged.
+#[derive(Debug, Clone)]
+pub struct FeatureRouting {
+    routes: Arc<HashMap<String, FeatureContext>>,
+    seen_unknown: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Default for FeatureRouting {
+    fn default() -> Self {
+        Self::with_overrides(&HashMap::new())
+    }
+}
+
+impl FeatureRouting {
+    /// The embedded table with `overrides` (feature type -> route) applied on top.
+    pub fn with_overrides<'a>(overrides: impl IntoIterator<Item = (&'a String, &'a FeatureContext)>) -> Self {
+        let mut routes: HashMap<String, FeatureContext> = DEFAULT_ROUTES
+            .iter()
+            .map(|&(feature_type, context)| (feature_type.to_string(), context))
+            .collect();
+        routes.extend(overrides.into_iter().map(|(feature_type, &context)| (feature_type.clone(), context)));
+        Self {
+            routes: Arc::new(routes),
+            seen_unknown: Arc::default(),
+        }
+    }
+
+    /// Route of `feature_type`, or None when the table doesn't know it.
+    pub fn route(&self, feature_type: &str) -> Option<FeatureContext> {
+        self.routes.get(feature_type).copied()
+    }
+
+    /// True the first time an unknown `feature_type` is reported in this run.
+    pub fn first_unknown(&self, feature_type: &str) -> bool {
+        self.seen_unknown.lock().unwrap().insert(feature_type.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_add_and_replace_routes() {
+        let routing = FeatureRouting::default();
+        assert_eq!(routing.route("binding site"), Some(FeatureContext::BindingSite));
+        assert_eq!(routing.route("chain"), Some(FeatureContext::Generic));
+        assert_eq!(routing.route("ligand site"), None);
+
+        let overrides = HashMap::from([
+            ("ligand site".to_string(), FeatureContext::BindingSite),
+            ("metal ion-binding site".to_string(), FeatureContext::BindingSite),
+        ]);
+        let routing = FeatureRouting::with_overrides(&overrides);
+        assert_eq!(routing.route("ligand site"), Some(FeatureContext::BindingSite));
+        assert_eq!(routing.route("metal ion-binding site"), Some(FeatureContext::BindingSite));
+
+        assert!(routing.first_unknown("new type"));
+        assert!(!routing.clone().first_unknown("new type"));
+    }
+}
@@ -4,7 +4,8 @@ use std::io::BufRead;
 
 use crate::error::Result;
 use crate::pipeline::handlers::{get_attribute, parse_evidence_refs, read_text, skip_element};
-use crate::pipeline::scratch::EntryScratch;
+use crate::pipeline::scratch::{EntryScratch, OtherCommentScratch};
+use crate::pipeline::state::ParseState;
 
 pub fn consume_comment<R: BufRead>(
     reader: &mut Reader<R>,
@@ -13,12 +14,54 @@ pub fn consume_comment<R: BufRead>(
     buf: &mut Vec<u8>,
 ) -> Result<()> {
     let comment_type = get_attribute(start, b"type")?.unwrap_or_default();
-    match comment_type.as_str() {
-        "subcellular location" => consume_subcellular_location_comment(reader, scratch, buf),
-        "alternative products" => consume_isoform_comment(reader, scratch, buf),
-        "subunit" => consume_subunit_comment(reader, start, scratch, buf),
-        "interaction" => consume_interaction_comment(reader, start, scratch, buf),
-        _ => skip_element(reader, b"comment", buf),
+    scratch.state.enter(ParseState::Comment, reader)?;
+    match comment_type.as_ref() {
+        "subcellular location" => consume_subcellular_location_comment(reader, scratch, buf)?,
+        "alternative products" => consume_isoform_comment(reader, scratch, buf)?,
+        "subunit" => consume_subunit_comment(reader, start, scratch, buf)?,
+        "interaction" => consume_interaction_comment(reader, start, scratch, buf)?,
+        other => consume_other_comment(reader, start, other, scratch, buf)?,
+    }
+    scratch.state.leave(ParseState::Comment, reader)
+}
+
+/// Captures every `<text>` of a comment type without a dedicated column, tagged with its type.
+fn consume_other_comment<R: BufRead>(
+    reader: &mut Reader<R>,
+    start: &BytesStart<'_>,
+    comment_type: &str,
+    scratch: &mut EntryScratch,
+    buf: &mut Vec<u8>,
+) -> Result<()> {
+    let mut inner = Vec::new();
+    let comment_evidence = get_attribute(start, b"evidence")?
+        .map(|ev| parse_evidence_refs(&ev))
+        .unwrap_or_default();
+    let mut molecule = None;
+
+    loop {
+        match scratch.state.next_event(reader, buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"molecule" => {
+                molecule = Some(read_text(reader, b"molecule", &mut inner)?);
+            }
+            Event::Start(e) if e.local_name().as_ref() == b"text" => {
+                let evidence_keys = match get_attribute(&e, b"evidence")? {
+                    Some(ev) => parse_evidence_refs(&ev),
+                    None => comment_evidence.clone(),
+                };
+                let text = read_text(reader, b"text", &mut inner)?;
+                if !text.trim().is_empty() {
+                    scratch.entry.comments.others.push(OtherCommentScratch {
+                        comment_type: comment_type.to_string(),
+                        text: text.trim().to_string(),
+                        evidence_keys,
+                        molecule: molecule.clone(),
+                    });
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == b"comment" => return Ok(()),
+            _ => {}
+        }
     }
 }
 
@@ -28,26 +71,45 @@ fn consume_subcellular_location_comment<R: BufRead>(
     buf: &mut Vec<u8>,
 ) -> Result<()> {
     let mut inner = Vec::new();
+    let mut molecule = None;
+    // Locations of the current <subcellularLocation>; its topology and orientation
+    // follow the locations and apply to all of them
+    let mut pending = Vec::new();
+    let (mut topology, mut orientation) = (None, None);
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"molecule" => {
+                molecule = Some(read_text(reader, b"molecule", &mut inner)?);
+            }
             Event::Start(e) if e.local_name().as_ref() == b"subcellularLocation" => {
-                scratch.current_location.clear();
+                pending.clear();
+                topology = None;
+                orientation = None;
             }
             Event::Start(e) if e.local_name().as_ref() == b"location" => {
+                scratch.current_location.clear();
                 if let Some(ev) = get_attribute(&e, b"evidence")? {
                     scratch.current_location.evidence_keys = parse_evidence_refs(&ev);
                 }
                 let text = read_text(reader, b"location", &mut inner)?;
                 scratch.current_location.location = text;
-                scratch
-                    .entry
-                    .comments
-                    .locations
-                    .push(std::mem::take(&mut scratch.current_location));
+                pending.push(std::mem::take(&mut scratch.current_location));
+            }
+            Event::Start(e) if e.local_name().as_ref() == b"topology" => {
+                topology = Some(read_text(reader, b"topology", &mut inner)?);
+            }
+            Event::Start(e) if e.local_name().as_ref() == b"orientation" => {
+                orientation = Some(read_text(reader, b"orientation", &mut inner)?);
+            }
+            Event::End(e) if e.local_name().as_ref() == b"subcellularLocation" => {
+                for mut location in pending.drain(..) {
+                    location.topology = topology.clone();
+                    location.orientation = orientation.clone();
+                    location.molecule = molecule.clone();
+                    scratch.entry.comments.locations.push(location);
+                }
             }
             Event::End(e) if e.local_name().as_ref() == b"comment" => return Ok(()),
-            Event::Eof => return Ok(()),
             _ => {}
         }
     }
@@ -59,18 +121,42 @@ fn consume_isoform_comment<R: BufRead>(
     buf: &mut Vec<u8>,
 ) -> Result<()> {
     let mut inner = Vec::new();
+    let first_isoform = scratch.entry.isoforms.len();
+    let mut events = Vec::new();
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"event" => {
+                if let Some(event) = get_attribute(&e, b"type")? {
+                    events.push(event.into_owned());
+                }
+            }
             Event::Start(e) if e.local_name().as_ref() == b"isoform" => {
                 scratch.current_isoform.clear();
                 consume_isoform(reader, scratch, &mut inner)?;
             }
-            Event::End(e) if e.local_name().as_ref() == b"comment" => return Ok(()),
-            Event::Eof => return Ok(()),
+            Event::End(e) if e.local_name().as_ref() == b"comment" => break,
             _ => {}
         }
     }
+    for iso in &mut scratch.entry.isoforms[first_isoform..] {
+        iso.event_type = isoform_event_type(&events, iso.isoform_note.as_deref());
+    }
+    Ok(())
+}
+
+/// The event producing an isoform. The comment lists its events, not which isoform each
+/// produces: with several, the one the isoform's note names ("Produced by alternative
+/// initiation at Met-40") wins, otherwise all of them are joined with "; ".
+fn isoform_event_type(events: &[String], note: Option<&str>) -> Option<String> {
+    match events {
+        [] => None,
+        [event] => Some(event.clone()),
+        _ => {
+            let note = note.unwrap_or_default().to_ascii_lowercase();
+            let named = events.iter().find(|event| note.contains(&event.to_ascii_lowercase()));
+            Some(named.cloned().unwrap_or_else(|| events.join("; ")))
+        }
+    }
 }
 
 fn consume_isoform<R: BufRead>(
@@ -79,9 +165,9 @@ fn consume_isoform<R: BufRead>(
     buf: &mut Vec<u8>,
 ) -> Result<()> {
     let mut inner = Vec::new();
+    scratch.state.enter(ParseState::Isoform, reader)?;
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
             Event::Start(e) => match e.local_name().as_ref() {
                 b"id" => {
                     let id = read_text(reader, b"id", &mut inner)?;
@@ -91,6 +177,10 @@ fn consume_isoform<R: BufRead>(
                     capture_isoform_sequence(&e, scratch)?;
                     skip_element(reader, b"sequence", &mut inner)?;
                 }
+                b"name" => {
+                    let name = read_text(reader, b"name", &mut inner)?;
+                    scratch.current_isoform.names.push(name);
+                }
                 b"note" => {
                     let note = read_text(reader, b"note", &mut inner)?;
                     scratch.current_isoform.isoform_note = Some(note);
@@ -105,9 +195,8 @@ fn consume_isoform<R: BufRead>(
                     .entry
                     .isoforms
                     .push(std::mem::take(&mut scratch.current_isoform));
-                return Ok(());
+                return scratch.state.leave(ParseState::Isoform, reader);
             }
-            Event::Eof => return Ok(()),
             _ => {}
         }
     }
@@ -115,9 +204,12 @@ fn consume_isoform<R: BufRead>(
 
 fn capture_isoform_sequence(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
     let seq_type = get_attribute(e, b"type")?.unwrap_or_default();
+    if scratch.current_isoform.sequence_type.is_none() && !seq_type.is_empty() {
+        scratch.current_isoform.sequence_type = Some(seq_type.to_string());
+    }
     if let Some(ref_attr) = get_attribute(e, b"ref")? {
         if seq_type == "described" || ref_attr.starts_with("VSP_") {
-            scratch.current_isoform.vsp_ids.push(ref_attr);
+            scratch.current_isoform.vsp_ids.push(ref_attr.into_owned());
         } else if scratch.current_isoform.isoform_sequence.is_none()
             || scratch
                 .current_isoform
@@ -125,7 +217,7 @@ fn capture_isoform_sequence(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> R
                 .as_deref()
                 .is_some_and(|s| s.starts_with("VSP_"))
         {
-            scratch.current_isoform.isoform_sequence = Some(ref_attr);
+            scratch.current_isoform.isoform_sequence = Some(ref_attr.into_owned());
         }
     }
     Ok(())
@@ -144,8 +236,11 @@ fn consume_subunit_comment<R: BufRead>(
     }
 
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"molecule" => {
+                let molecule = read_text(reader, b"molecule", &mut inner)?;
+                scratch.current_subunit.molecule = Some(molecule);
+            }
             Event::Start(e) if e.local_name().as_ref() == b"text" => {
                 if let Some(ev) = get_attribute(&e, b"evidence")? {
                     scratch.current_subunit.evidence_keys = parse_evidence_refs(&ev);
@@ -163,7 +258,6 @@ fn consume_subunit_comment<R: BufRead>(
                 }
                 return Ok(());
             }
-            Event::Eof => return Ok(()),
             _ => {}
         }
     }
@@ -181,18 +275,35 @@ fn consume_interaction_comment<R: BufRead>(
         scratch.current_interaction.evidence_keys = parse_evidence_refs(&ev);
     }
 
+    let mut interactants = 0;
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
-            Event::Start(e) => {
-                if e.local_name().as_ref() == b"dbReference" {
-                    handle_interactant(&e, scratch)?;
-                    skip_element(reader, b"dbReference", &mut inner)?;
-                }
+        match scratch.state.next_event(reader, buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"dbReference" => {
+                handle_interactant(&e, scratch)?;
+                skip_element(reader, b"dbReference", &mut inner)?;
             }
             Event::Empty(e) if e.local_name().as_ref() == b"dbReference" => {
                 handle_interactant(&e, scratch)?;
             }
+            Event::Start(e) if e.local_name().as_ref() == b"interactant" => {
+                let intact_id = get_attribute(&e, b"intactId")?.map(|v| v.into_owned());
+                let id = read_interactant_id(reader, scratch, &mut inner)?;
+                set_intact_interactant(scratch, interactants, intact_id, id);
+                interactants += 1;
+            }
+            Event::Empty(e) if e.local_name().as_ref() == b"interactant" => {
+                let intact_id = get_attribute(&e, b"intactId")?.map(|v| v.into_owned());
+                set_intact_interactant(scratch, interactants, intact_id, None);
+                interactants += 1;
+            }
+            Event::Start(e) if e.local_name().as_ref() == b"organismsDiffer" => {
+                let text = read_text(reader, b"organismsDiffer", &mut inner)?;
+                scratch.current_interaction.organisms_differ = Some(text.trim() == "true");
+            }
+            Event::Start(e) if e.local_name().as_ref() == b"experiments" => {
+                let text = read_text(reader, b"experiments", &mut inner)?;
+                scratch.current_interaction.experiments = text.trim().parse().ok();
+            }
             Event::End(e) if e.local_name().as_ref() == b"comment" => {
                 if scratch.current_interaction.interactant_id_1.is_some()
                     || scratch.current_interaction.interactant_id_2.is_some()
@@ -205,16 +316,66 @@ fn consume_interaction_comment<R: BufRead>(
                 }
                 return Ok(());
             }
-            Event::Eof => return Ok(()),
             _ => {}
         }
     }
 }
 
+/// Reads the `<id>` of an `<interactant>`, skipping `<label>` and other children.
+fn read_interactant_id<R: BufRead>(
+    reader: &mut Reader<R>,
+    scratch: &mut EntryScratch,
+    buf: &mut Vec<u8>,
+) -> Result<Option<String>> {
+    let mut id = None;
+    let mut inner = Vec::new();
+    scratch.state.enter(ParseState::Interactant, reader)?;
+    loop {
+        match scratch.state.next_event(reader, buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"id" => {
+                id = Some(read_text(reader, b"id", &mut inner)?.trim().to_string());
+            }
+            Event::Start(e) => {
+                let name = e.local_name().as_ref().to_vec();
+                skip_element(reader, &name, &mut inner)?;
+            }
+            Event::End(e) if e.local_name().as_ref() == b"interactant" => {
+                scratch.state.leave(ParseState::Interactant, reader)?;
+                return Ok(id);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Fills side `index` (0 or 1) of the current interaction from an `<interactant>`.
+/// The first interactant is the entry itself and carries no `<id>` unless it is an
+/// isoform, so it defaults to the entry's accession.
+fn set_intact_interactant(
+    scratch: &mut EntryScratch,
+    index: usize,
+    intact_id: Option<String>,
+    id: Option<String>,
+) {
+    let interaction = &mut scratch.current_interaction;
+    match index {
+        0 => {
+            interaction.intact_id_1 = intact_id;
+            interaction.interactant_id_1 = id.or_else(|| Some(scratch.entry.accession.clone()));
+        }
+        1 => {
+            interaction.intact_id_2 = intact_id;
+            interaction.interactant_id_2 = id;
+        }
+        _ => {}
+    }
+}
+
 fn handle_interactant(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
     if let Some(t) = get_attribute(e, b"type")? {
         if t.starts_with("UniProtKB") {
             if let Some(id) = get_attribute(e, b"id")? {
+                let id = id.into_owned();
                 if scratch.current_interaction.interactant_id_1.is_none() {
                     scratch.current_interaction.interactant_id_1 = Some(id);
                 } else if scratch.current_interaction.interactant_id_2.is_none() {
@@ -3,8 +3,10 @@ use quick_xml::Reader;
 use std::io::BufRead;
 
 use crate::error::Result;
+use crate::log_warn;
 use crate::pipeline::handlers::{get_attribute, parse_evidence_refs, read_text, skip_element};
 use crate::pipeline::scratch::{EntryScratch, FeatureContext};
+use crate::pipeline::state::ParseState;
 
 pub fn consume_feature<R: BufRead>(
     reader: &mut Reader<R>,
@@ -13,13 +15,16 @@ pub fn consume_feature<R: BufRead>(
     buf: &mut Vec<u8>,
 ) -> Result<()> {
     prepare_feature(start, scratch)?;
+    scratch.state.enter(ParseState::Feature, reader)?;
     let mut inner = Vec::new();
 
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
             Event::Start(e) => match e.local_name().as_ref() {
-                b"location" => consume_location(reader, scratch, &mut inner)?,
+                b"location" => {
+                    capture_location_sequence(&e, scratch)?;
+                    consume_location(reader, scratch, &mut inner)?;
+                }
                 b"original" => {
                     let text = read_text(reader, b"original", &mut inner)?;
                     assign_original(scratch, text);
@@ -28,25 +33,29 @@ pub fn consume_feature<R: BufRead>(
                     let text = read_text(reader, b"variation", &mut inner)?;
                     assign_variation(scratch, text);
                 }
+                b"dbReference" => {
+                    capture_variant_db_reference(&e, scratch)?;
+                    skip_element(reader, b"dbReference", &mut inner)?;
+                }
                 _ => skip_element(reader, e.local_name().as_ref(), &mut inner)?,
             },
             Event::Empty(e) => match e.local_name().as_ref() {
-                b"location" => {}
+                b"location" => capture_location_sequence(&e, scratch)?,
                 b"position" => handle_position_tag(&e, CoordinateType::Position, scratch)?,
                 b"begin" => handle_position_tag(&e, CoordinateType::Begin, scratch)?,
                 b"end" => handle_position_tag(&e, CoordinateType::End, scratch)?,
+                b"dbReference" => capture_variant_db_reference(&e, scratch)?,
                 _ => {}
             },
             Event::End(e) if e.local_name().as_ref() == b"feature" => {
                 finalize_feature(scratch);
                 break;
             }
-            Event::Eof => break,
             _ => {}
         }
     }
 
-    Ok(())
+    scratch.state.leave(ParseState::Feature, reader)
 }
 
 fn prepare_feature(start: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
@@ -54,14 +63,14 @@ fn prepare_feature(start: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result
     scratch.current_feature_context = FeatureContext::Generic;
 
     if let Some(id) = get_attribute(start, b"id")? {
-        scratch.current_feature.id = Some(id);
+        scratch.current_feature.id = Some(id.into_owned());
     }
     if let Some(ft) = get_attribute(start, b"type")? {
-        scratch.current_feature.feature_type = ft.clone();
+        scratch.current_feature.feature_type.push_str(&ft);
         set_context(&ft, scratch);
     }
     if let Some(desc) = get_attribute(start, b"description")? {
-        scratch.current_feature.description = Some(desc);
+        scratch.current_feature.description = Some(desc.into_owned());
     }
     if let Some(ev) = get_attribute(start, b"evidence")? {
         scratch.current_feature.evidence_keys = parse_evidence_refs(&ev);
@@ -72,14 +81,19 @@ fn prepare_feature(start: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result
 }
 
 fn set_context(feature_type: &str, scratch: &mut EntryScratch) {
-    scratch.current_feature_context = match feature_type {
-        "active site" => FeatureContext::ActiveSite,
-        "binding site" => FeatureContext::BindingSite,
-        "metal ion-binding site" => FeatureContext::MetalCoordination,
-        "mutagenesis site" => FeatureContext::Mutagenesis,
-        "domain" => FeatureContext::Domain,
-        "sequence variant" => FeatureContext::NaturalVariant,
-        _ => FeatureContext::Generic,
+    scratch.current_feature_context = match scratch.feature_routing.route(feature_type) {
+        Some(context) => context,
+        None => {
+            scratch.entry.unknown_feature_types += 1;
+            if scratch.feature_routing.first_unknown(feature_type) {
+                log_warn!(
+                    "transform",
+                    "code=UNKNOWN_FEATURE_TYPE type={:?} id={} (kept in features only; route it with transform.feature_routes)",
+                    feature_type, scratch.entry.accession
+                );
+            }
+            FeatureContext::Generic
+        }
     };
 
     match scratch.current_feature_context {
@@ -132,15 +146,23 @@ fn propagate_common_to_context(scratch: &mut EntryScratch) {
     }
 }
 
+/// Records the isoform a feature's coordinates refer to (`<location sequence="P04637-2">`).
+fn capture_location_sequence(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
+    if let Some(sequence) = get_attribute(e, b"sequence")? {
+        scratch.current_feature.sequence = Some(sequence.trim().to_string());
+    }
+    Ok(())
+}
+
 fn consume_location<R: BufRead>(
     reader: &mut Reader<R>,
     scratch: &mut EntryScratch,
     buf: &mut Vec<u8>,
 ) -> Result<()> {
     let mut inner = Vec::new();
+    scratch.state.enter(ParseState::FeatureLocation, reader)?;
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
             Event::Start(e) => match e.local_name().as_ref() {
                 b"position" => {
                     handle_position_tag(&e, CoordinateType::Position, scratch)?;
@@ -163,11 +185,10 @@ fn consume_location<R: BufRead>(
                 _ => {}
             },
             Event::End(e) if e.local_name().as_ref() == b"location" => break,
-            Event::Eof => break,
             _ => {}
         }
     }
-    Ok(())
+    scratch.state.leave(ParseState::FeatureLocation, reader)
 }
 
 fn handle_position_tag(
@@ -180,6 +201,20 @@ fn handle_position_tag(
             apply_coordinate_to_feature(p, coord_type, scratch);
         }
     }
+
+    // "less than", "greater than", "uncertain" or "unknown" (no position at all)
+    let status = get_attribute(e, b"status")?
+        .filter(|s| s != "certain")
+        .map(|s| s.into_owned());
+    let feature = &mut scratch.current_feature;
+    match coord_type {
+        CoordinateType::Position => {
+            feature.start_status = status.clone();
+            feature.end_status = status;
+        }
+        CoordinateType::Begin => feature.start_status = status,
+        CoordinateType::End => feature.end_status = status,
+    }
     Ok(())
 }
 
@@ -197,9 +232,46 @@ fn assign_variation(scratch: &mut EntryScratch, text: String) {
     }
 }
 
+/// Records the dbSNP id of a natural variant (`<dbReference type="dbSNP" id="rs28934578"/>`).
+fn capture_variant_db_reference(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
+    if !matches!(scratch.current_feature_context, FeatureContext::NaturalVariant) {
+        return Ok(());
+    }
+    if get_attribute(e, b"type")?.as_deref() != Some("dbSNP") {
+        return Ok(());
+    }
+    if let Some(id) = get_attribute(e, b"id")? {
+        scratch.current_natural_variant.dbsnp_id.get_or_insert_with(|| id.trim().to_string());
+    }
+    Ok(())
+}
+
+/// Splits a variant description such as "in LFS; germline mutation; dbSNP:rs28934578"
+/// into its disease association ("LFS") and dbSNP id.
+fn parse_variant_description(description: &str) -> (Option<String>, Option<String>) {
+    let mut diseases = Vec::new();
+    let mut dbsnp = None;
+    for fragment in description.split(';').map(str::trim) {
+        if let Some(disease) = fragment.strip_prefix("in ") {
+            let disease = disease.trim();
+            if !disease.is_empty() {
+                diseases.push(disease);
+            }
+        } else if let Some(id) = fragment.strip_prefix("dbSNP:") {
+            dbsnp.get_or_insert_with(|| id.trim().to_string());
+        }
+    }
+    let diseases = (!diseases.is_empty()).then(|| diseases.join("; "));
+    (diseases, dbsnp)
+}
+
 fn finalize_feature(scratch: &mut EntryScratch) {
+    let position_status = scratch.current_feature.position_status();
+    let sequence = scratch.current_feature.sequence.clone();
     match scratch.current_feature_context {
         FeatureContext::ActiveSite => {
+            scratch.current_active_site.position_status = position_status;
+            scratch.current_active_site.sequence = sequence;
             scratch
                 .entry
                 .features
@@ -207,6 +279,8 @@ fn finalize_feature(scratch: &mut EntryScratch) {
                 .push(std::mem::take(&mut scratch.current_active_site));
         }
         FeatureContext::BindingSite => {
+            scratch.current_binding_site.position_status = position_status;
+            scratch.current_binding_site.sequence = sequence;
             scratch
                 .entry
                 .features
@@ -214,6 +288,8 @@ fn finalize_feature(scratch: &mut EntryScratch) {
                 .push(std::mem::take(&mut scratch.current_binding_site));
         }
         FeatureContext::MetalCoordination => {
+            scratch.current_metal_coordination.position_status = position_status;
+            scratch.current_metal_coordination.sequence = sequence;
             scratch
                 .entry
                 .features
@@ -221,6 +297,8 @@ fn finalize_feature(scratch: &mut EntryScratch) {
                 .push(std::mem::take(&mut scratch.current_metal_coordination));
         }
         FeatureContext::Mutagenesis => {
+            scratch.current_mutagenesis_site.position_status = position_status;
+            scratch.current_mutagenesis_site.sequence = sequence;
             scratch
                 .entry
                 .features
@@ -228,6 +306,8 @@ fn finalize_feature(scratch: &mut EntryScratch) {
                 .push(std::mem::take(&mut scratch.current_mutagenesis_site));
         }
         FeatureContext::Domain => {
+            scratch.current_domain.position_status = position_status;
+            scratch.current_domain.sequence = sequence;
             scratch
                 .entry
                 .features
@@ -235,6 +315,16 @@ fn finalize_feature(scratch: &mut EntryScratch) {
                 .push(std::mem::take(&mut scratch.current_domain));
         }
         FeatureContext::NaturalVariant => {
+            let variant = &mut scratch.current_natural_variant;
+            variant.position_status = position_status;
+            variant.sequence = sequence;
+            if let Some(description) = variant.description.as_deref() {
+                let (diseases, dbsnp) = parse_variant_description(description);
+                variant.disease_association = diseases;
+                if variant.dbsnp_id.is_none() {
+                    variant.dbsnp_id = dbsnp;
+                }
+            }
             scratch
                 .entry
                 .features
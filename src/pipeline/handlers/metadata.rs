@@ -3,51 +3,79 @@ use quick_xml::Reader;
 use std::io::BufRead;
 
 use crate::error::Result;
-use crate::pipeline::handlers::{comments, features, get_attribute, read_text, skip_element};
-use crate::pipeline::scratch::EntryScratch;
+use crate::pipeline::handlers::{
+    comments, features, get_attribute, read_text, read_text_into, skip_element,
+};
+use crate::pipeline::scratch::{ChainRange, EntryScratch, NamedXref, StructureRef};
+use crate::pipeline::state::ParseState;
+
+/// Captures attributes of the `<entry>` start tag itself (e.g. `dataset`, `version`).
+pub fn handle_entry_start(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
+    scratch.entry.dataset = get_attribute(e, b"dataset")?.map(|d| d.into_owned());
+    scratch.entry.version = get_attribute(e, b"version")?.and_then(|v| v.parse().ok());
+    Ok(())
+}
 
 pub fn consume_entry<R: BufRead>(
     reader: &mut Reader<R>,
     scratch: &mut EntryScratch,
     buf: &mut Vec<u8>,
 ) -> Result<()> {
-    let mut inner_buf = Vec::new();
+    // Reuse the scratch-owned event buffer across entries instead of allocating per entry
+    let mut inner_buf = std::mem::take(&mut scratch.xml_buffer);
+    let result = consume_entry_events(reader, scratch, buf, &mut inner_buf);
+    inner_buf.clear();
+    scratch.xml_buffer = inner_buf;
+    result
+}
+
+fn consume_entry_events<R: BufRead>(
+    reader: &mut Reader<R>,
+    scratch: &mut EntryScratch,
+    buf: &mut Vec<u8>,
+    inner_buf: &mut Vec<u8>,
+) -> Result<()> {
+    scratch.state.enter(ParseState::Entry, reader)?;
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
             Event::Start(e) => match e.local_name().as_ref() {
-                b"name" => handle_entry_name(reader, scratch, &mut inner_buf)?,
-                b"accession" => handle_accession(reader, scratch, &mut inner_buf)?,
-                b"sequence" => handle_sequence(reader, scratch, &mut inner_buf)?,
-                b"organism" => consume_organism(reader, scratch, &mut inner_buf)?,
-                b"gene" => consume_gene(reader, scratch, &mut inner_buf)?,
-                b"protein" => consume_protein(reader, scratch, &mut inner_buf)?,
-                b"dbReference" => handle_entry_db_reference(&e, scratch)?,
-                b"feature" => features::consume_feature(reader, &e, scratch, &mut inner_buf)?,
-                b"comment" => comments::consume_comment(reader, &e, scratch, &mut inner_buf)?,
+                b"name" => handle_entry_name(reader, scratch, inner_buf)?,
+                b"accession" => handle_accession(reader, scratch, inner_buf)?,
+                b"sequence" => handle_sequence(reader, &e, scratch, inner_buf)?,
+                b"organism" => consume_organism(reader, scratch, inner_buf)?,
+                b"gene" => consume_gene(reader, scratch, inner_buf)?,
+                b"protein" => consume_protein(reader, scratch, inner_buf)?,
+                b"dbReference" => consume_entry_db_reference(reader, &e, scratch, inner_buf)?,
+                b"feature" => features::consume_feature(reader, &e, scratch, inner_buf)?,
+                b"comment" => comments::consume_comment(reader, &e, scratch, inner_buf)?,
                 b"evidence" => handle_evidence(&e, scratch)?,
-                _ => skip_element(reader, e.local_name().as_ref(), &mut inner_buf)?,
+                b"geneLocation" => {
+                    handle_gene_location(&e, scratch)?;
+                    skip_element(reader, b"geneLocation", inner_buf)?;
+                }
+                _ => skip_element(reader, e.local_name().as_ref(), inner_buf)?,
             },
             Event::Empty(e) => match e.local_name().as_ref() {
-                b"dbReference" => handle_entry_db_reference(&e, scratch)?,
+                b"dbReference" => {
+                    handle_entry_db_reference(&e, scratch)?;
+                }
                 b"evidence" => handle_evidence(&e, scratch)?,
+                b"geneLocation" => handle_gene_location(&e, scratch)?,
                 _ => {}
             },
             Event::End(e) if e.local_name().as_ref() == b"entry" => break,
-            Event::Eof => break,
             _ => {}
         }
     }
-    Ok(())
+    scratch.state.leave(ParseState::Entry, reader)
 }
 
 fn handle_entry_name<R: BufRead>(
     reader: &mut Reader<R>,
     scratch: &mut EntryScratch,
-    _buf: &mut Vec<u8>,
+    buf: &mut Vec<u8>,
 ) -> Result<()> {
-    let mut inner = Vec::new();
-    let name = read_text(reader, b"name", &mut inner)?;
+    let name = read_text(reader, b"name", buf)?;
     scratch.entry.entry_name = Some(name);
     Ok(())
 }
@@ -55,10 +83,9 @@ fn handle_entry_name<R: BufRead>(
 fn handle_accession<R: BufRead>(
     reader: &mut Reader<R>,
     scratch: &mut EntryScratch,
-    _buf: &mut Vec<u8>,
+    buf: &mut Vec<u8>,
 ) -> Result<()> {
-    let mut inner = Vec::new();
-    let accession = read_text(reader, b"accession", &mut inner)?;
+    let accession = read_text(reader, b"accession", buf)?;
     if !scratch.has_primary_accession {
         scratch.entry.accession = accession.clone();
         scratch.entry.parent_id = accession;
@@ -69,12 +96,20 @@ fn handle_accession<R: BufRead>(
 
 fn handle_sequence<R: BufRead>(
     reader: &mut Reader<R>,
+    start: &BytesStart<'_>,
     scratch: &mut EntryScratch,
-    _buf: &mut Vec<u8>,
+    buf: &mut Vec<u8>,
 ) -> Result<()> {
-    let mut inner = Vec::new();
-    let sequence_raw = read_text(reader, b"sequence", &mut inner)?;
-    scratch.entry.sequence = sequence_raw.chars().filter(|c| !c.is_whitespace()).collect();
+    scratch.entry.fragment = get_attribute(start, b"fragment")?.map(|f| f.into_owned());
+    scratch.entry.precursor = get_attribute(start, b"precursor")?.is_some_and(|p| p == "true");
+    scratch.entry.checksum = get_attribute(start, b"checksum")?.map(|c| c.into_owned());
+
+    scratch.text_buffer.clear();
+    read_text_into(reader, b"sequence", buf, &mut scratch.text_buffer)?;
+    let sequence = &mut scratch.entry.sequence;
+    sequence.clear();
+    sequence.reserve(scratch.text_buffer.len());
+    sequence.extend(scratch.text_buffer.chars().filter(|c| !c.is_whitespace()));
     Ok(())
 }
 
@@ -84,9 +119,9 @@ fn consume_organism<R: BufRead>(
     buf: &mut Vec<u8>,
 ) -> Result<()> {
     let mut inner = Vec::new();
+    scratch.state.enter(ParseState::Organism, reader)?;
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
             Event::Start(e) => match e.local_name().as_ref() {
                 b"name" => {
                     if let Some(t) = get_attribute(&e, b"type")? {
@@ -106,17 +141,14 @@ fn consume_organism<R: BufRead>(
                 }
                 _ => skip_element(reader, e.local_name().as_ref(), &mut inner)?,
             },
-            Event::Empty(e) => {
-                if e.local_name().as_ref() == b"dbReference" {
-                    handle_organism_db_reference(&e, scratch)?;
-                }
+            Event::Empty(e) if e.local_name().as_ref() == b"dbReference" => {
+                handle_organism_db_reference(&e, scratch)?;
             }
             Event::End(e) if e.local_name().as_ref() == b"organism" => break,
-            Event::Eof => break,
             _ => {}
         }
     }
-    Ok(())
+    scratch.state.leave(ParseState::Organism, reader)
 }
 
 fn handle_organism_db_reference(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
@@ -136,9 +168,9 @@ fn consume_gene<R: BufRead>(
     buf: &mut Vec<u8>,
 ) -> Result<()> {
     let mut inner = Vec::new();
+    scratch.state.enter(ParseState::Gene, reader)?;
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
             Event::Start(e) if e.local_name().as_ref() == b"name" => {
                 if let Some(t) = get_attribute(&e, b"type")? {
                     if t == "primary" {
@@ -152,11 +184,10 @@ fn consume_gene<R: BufRead>(
                 }
             }
             Event::End(e) if e.local_name().as_ref() == b"gene" => break,
-            Event::Eof => break,
             _ => {}
         }
     }
-    Ok(())
+    scratch.state.leave(ParseState::Gene, reader)
 }
 
 fn consume_protein<R: BufRead>(
@@ -165,9 +196,9 @@ fn consume_protein<R: BufRead>(
     buf: &mut Vec<u8>,
 ) -> Result<()> {
     let mut inner = Vec::new();
+    scratch.state.enter(ParseState::Protein, reader)?;
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
             Event::Start(e) => match e.local_name().as_ref() {
                 b"recommendedName" => consume_recommended_name(reader, scratch, &mut inner)?,
                 b"proteinExistence" => {
@@ -180,11 +211,10 @@ fn consume_protein<R: BufRead>(
                 handle_protein_existence(&e, scratch)?;
             }
             Event::End(e) if e.local_name().as_ref() == b"protein" => break,
-            Event::Eof => break,
             _ => {}
         }
     }
-    Ok(())
+    scratch.state.leave(ParseState::Protein, reader)
 }
 
 fn consume_recommended_name<R: BufRead>(
@@ -193,19 +223,22 @@ fn consume_recommended_name<R: BufRead>(
     buf: &mut Vec<u8>,
 ) -> Result<()> {
     let mut inner = Vec::new();
+    scratch.state.enter(ParseState::RecommendedName, reader)?;
     loop {
-        buf.clear();
-        match reader.read_event_into(buf)? {
+        match scratch.state.next_event(reader, buf)? {
             Event::Start(e) if e.local_name().as_ref() == b"fullName" => {
                 let text = read_text(reader, b"fullName", &mut inner)?;
                 scratch.entry.protein_name = Some(text);
             }
+            Event::Start(e) if e.local_name().as_ref() == b"ecNumber" => {
+                let text = read_text(reader, b"ecNumber", &mut inner)?;
+                scratch.entry.add_ec_number(&text);
+            }
             Event::End(e) if e.local_name().as_ref() == b"recommendedName" => break,
-            Event::Eof => break,
             _ => {}
         }
     }
-    Ok(())
+    scratch.state.leave(ParseState::RecommendedName, reader)
 }
 
 fn handle_protein_existence(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
@@ -215,16 +248,137 @@ fn handle_protein_existence(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> R
     Ok(())
 }
 
-fn handle_entry_db_reference(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
-    if let Some(db) = get_attribute(e, b"type")? {
-        if db == "PDB" || db == "AlphaFoldDB" {
-            if let Some(id) = get_attribute(e, b"id")? {
-                scratch.entry.structures.push(crate::pipeline::scratch::StructureRef {
-                    database: db,
-                    id,
-                });
+/// Entry-level `<dbReference>`s whose `<property>` children are kept.
+#[derive(Clone, Copy)]
+enum EntryDbReference {
+    Structure,
+    Pathway,
+    DomainFamily,
+}
+
+/// Records structure (PDB/AlphaFoldDB), pathway (Reactome/KEGG) and domain family
+/// (InterPro/Pfam/SMART) references and EC numbers; returns the kind of reference whose
+/// properties should be read.
+fn handle_entry_db_reference(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<Option<EntryDbReference>> {
+    let (Some(db), Some(id)) = (get_attribute(e, b"type")?, get_attribute(e, b"id")?) else {
+        return Ok(None);
+    };
+    match db.as_ref() {
+        "EC" => {
+            scratch.entry.add_ec_number(&id);
+            Ok(None)
+        }
+        "PDB" | "AlphaFoldDB" => {
+            scratch.entry.structures.push(StructureRef {
+                database: db.into_owned(),
+                id: id.into_owned(),
+                ..StructureRef::default()
+            });
+            Ok(Some(EntryDbReference::Structure))
+        }
+        "Reactome" | "KEGG" => {
+            scratch.entry.pathways.push(NamedXref {
+                database: db.into_owned(),
+                id: id.into_owned(),
+                name: None,
+            });
+            Ok(Some(EntryDbReference::Pathway))
+        }
+        "InterPro" | "Pfam" | "SMART" => {
+            scratch.entry.domain_xrefs.push(NamedXref {
+                database: db.into_owned(),
+                id: id.into_owned(),
+                name: None,
+            });
+            Ok(Some(EntryDbReference::DomainFamily))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// A structure, pathway or domain family reference with its `<property>` children
+/// (method, resolution, chains; pathway name; entry name).
+fn consume_entry_db_reference<R: BufRead>(
+    reader: &mut Reader<R>,
+    e: &BytesStart<'_>,
+    scratch: &mut EntryScratch,
+    buf: &mut Vec<u8>,
+) -> Result<()> {
+    let Some(kind) = handle_entry_db_reference(e, scratch)? else {
+        return skip_element(reader, b"dbReference", buf);
+    };
+    let handle_property = match kind {
+        EntryDbReference::Structure => handle_structure_property,
+        EntryDbReference::Pathway => handle_pathway_property,
+        EntryDbReference::DomainFamily => handle_domain_family_property,
+    };
+    let mut inner = Vec::new();
+    scratch.state.enter(ParseState::DbReference, reader)?;
+    loop {
+        match scratch.state.next_event(reader, buf)? {
+            Event::Start(e) => {
+                if e.local_name().as_ref() == b"property" {
+                    handle_property(&e, scratch)?;
+                }
+                skip_element(reader, e.local_name().as_ref(), &mut inner)?;
+            }
+            Event::Empty(e) if e.local_name().as_ref() == b"property" => handle_property(&e, scratch)?,
+            Event::End(e) if e.local_name().as_ref() == b"dbReference" => break,
+            _ => {}
+        }
+    }
+    scratch.state.leave(ParseState::DbReference, reader)
+}
+
+fn handle_pathway_property(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
+    set_xref_name(e, "pathway name", scratch.entry.pathways.last_mut())
+}
+
+fn handle_domain_family_property(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
+    set_xref_name(e, "entry name", scratch.entry.domain_xrefs.last_mut())
+}
+
+/// Names `xref` from a `<property type=name_type value="...">`.
+fn set_xref_name(e: &BytesStart<'_>, name_type: &str, xref: Option<&mut NamedXref>) -> Result<()> {
+    let (Some(kind), Some(value), Some(xref)) = (get_attribute(e, b"type")?, get_attribute(e, b"value")?, xref) else {
+        return Ok(());
+    };
+    if kind == name_type {
+        xref.name = Some(value.into_owned());
+    }
+    Ok(())
+}
+
+fn handle_structure_property(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
+    let (Some(kind), Some(value)) = (get_attribute(e, b"type")?, get_attribute(e, b"value")?) else {
+        return Ok(());
+    };
+    let Some(structure) = scratch.entry.structures.last_mut() else {
+        return Ok(());
+    };
+    match kind.as_ref() {
+        "method" => structure.method = Some(value.into_owned()),
+        // "2.50 A"; "-" for methods without one
+        "resolution" => structure.resolution = value.split_whitespace().next().and_then(|r| r.parse().ok()),
+        "chains" => structure.chains = ChainRange::parse_list(&value),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Adds a `<geneLocation>` type; the `<name>` of plasmids etc. is not kept.
+fn handle_gene_location(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
+    let Some(location) = get_attribute(e, b"type")? else {
+        return Ok(());
+    };
+    match &mut scratch.entry.gene_location {
+        Some(locations) => {
+            if !locations.split("; ").any(|l| l == location) {
+                locations.push_str("; ");
+                locations.push_str(&location);
             }
         }
+        None => scratch.entry.gene_location = Some(location.into_owned()),
     }
     Ok(())
 }
@@ -232,7 +386,7 @@ fn handle_entry_db_reference(e: &BytesStart<'_>, scratch: &mut EntryScratch) ->
 fn handle_evidence(e: &BytesStart<'_>, scratch: &mut EntryScratch) -> Result<()> {
     if let Some(key) = get_attribute(e, b"key")? {
         if let Some(eco) = get_attribute(e, b"type")? {
-            scratch.entry.evidence_map.insert(key, eco);
+            scratch.entry.evidence_map.insert(key.into_owned(), eco.into_owned());
         }
     }
     Ok(())
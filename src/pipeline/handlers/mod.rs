@@ -1,19 +1,23 @@
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::name::QName;
 use quick_xml::Reader;
+use std::borrow::Cow;
 use std::io::BufRead;
 
-use crate::error::Result;
+use crate::error::{EtlError, Result};
 
 pub mod comments;
 pub mod features;
 pub mod metadata;
 
-/// Extracts an attribute value as a String
-pub fn get_attribute(e: &BytesStart<'_>, name: &[u8]) -> Result<Option<String>> {
+/// Extracts an attribute value, borrowing from the event buffer unless it needs unescaping.
+///
+/// Callers that only compare or parse the value never allocate; call `into_owned()`
+/// when the value has to outlive the event.
+pub fn get_attribute<'a>(e: &'a BytesStart<'_>, name: &[u8]) -> Result<Option<Cow<'a, str>>> {
     for attr in e.attributes().flatten() {
         if attr.key.as_ref() == name {
-            return Ok(Some(attr.unescape_value()?.into_owned()));
+            return Ok(Some(attr.unescape_value()?));
         }
     }
     Ok(None)
@@ -31,17 +35,78 @@ pub fn read_text<R: BufRead>(
     buf: &mut Vec<u8>,
 ) -> Result<String> {
     let mut text = String::new();
+    read_text_into(reader, end_tag, buf, &mut text)?;
+    Ok(text)
+}
+
+/// Appends text content up to the matching end tag into a caller-owned buffer.
+///
+/// Lets hot paths (e.g. `<sequence>`) reuse `EntryScratch::text_buffer` instead of
+/// allocating a fresh String per element.
+///
+/// Text is accumulated across every Text/CDATA event inside the element, including
+/// those of nested formatting elements (`<sub>`, `<i>`, a nested element with the
+/// same name), so "Ca<sup>2+</sup> binding" reads as "Ca2+ binding". Trimming is
+/// suspended while reading so the spaces around such elements survive; only the
+/// ends of the appended text are trimmed. Entities quick-xml cannot resolve are
+/// kept verbatim instead of failing the entry.
+pub fn read_text_into<R: BufRead>(
+    reader: &mut Reader<R>,
+    end_tag: &[u8],
+    buf: &mut Vec<u8>,
+    out: &mut String,
+) -> Result<()> {
+    let config = reader.config_mut();
+    let trim = (config.trim_text_start, config.trim_text_end);
+    config.trim_text(false);
+
+    let start = out.len();
+    let result = accumulate_text(reader, end_tag, buf, out);
+
+    let config = reader.config_mut();
+    config.trim_text_start = trim.0;
+    config.trim_text_end = trim.1;
+    result?;
+
+    out.truncate(out.trim_end().len());
+    let leading = out[start..].len() - out[start..].trim_start().len();
+    out.drain(start..start + leading);
+    Ok(())
+}
+
+fn accumulate_text<R: BufRead>(
+    reader: &mut Reader<R>,
+    end_tag: &[u8],
+    buf: &mut Vec<u8>,
+    out: &mut String,
+) -> Result<()> {
+    let decoder = reader.decoder();
+    let mut depth = 0usize;
     loop {
         buf.clear();
         match reader.read_event_into(buf)? {
-            Event::Text(e) => text.push_str(&e.unescape()?),
-            Event::CData(e) => text.push_str(&String::from_utf8_lossy(e.as_ref())),
-            Event::End(e) if e.local_name().as_ref() == end_tag => break,
-            Event::Eof => break,
+            Event::Text(e) => match e.unescape() {
+                Ok(text) => out.push_str(&text),
+                Err(_) => out.push_str(&decoder.decode(&e).map_err(quick_xml::Error::from)?),
+            },
+            Event::CData(e) => out.push_str(&e.decode().map_err(quick_xml::Error::from)?),
+            Event::Start(e) if e.local_name().as_ref() == end_tag => depth += 1,
+            Event::End(e) if e.local_name().as_ref() == end_tag => {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            }
+            Event::Eof => {
+                return Err(EtlError::UnexpectedEof {
+                    path: String::from_utf8_lossy(end_tag).into_owned(),
+                    position: reader.buffer_position(),
+                })
+            }
             _ => {}
         }
     }
-    Ok(text)
+    Ok(())
 }
 
 /// Skips the current element, consuming events until its end tag.
@@ -53,3 +118,60 @@ pub fn skip_element<R: BufRead>(
     reader.read_to_end_into(QName(tag), buf)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn read<'a>(xml: &'a str, tag: &[u8]) -> (String, Reader<Cursor<&'a [u8]>>) {
+        let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+        reader.config_mut().trim_text(true);
+        let mut buf = Vec::new();
+        // Position the reader just past the opening tag
+        loop {
+            if let Event::Start(e) = reader.read_event_into(&mut buf).unwrap() {
+                if e.local_name().as_ref() == tag {
+                    break;
+                }
+            }
+            buf.clear();
+        }
+        let text = read_text(&mut reader, tag, &mut buf).unwrap();
+        (text, reader)
+    }
+
+    #[test]
+    fn test_read_text_unescapes_entities_and_keeps_unicode() {
+        let (text, _) = read("<fullName>Na(+)/K(+) ATPase &gt; subunit &amp; &#945;-chain</fullName>", b"fullName");
+        assert_eq!(text, "Na(+)/K(+) ATPase > subunit & α-chain");
+
+        let (text, _) = read("<fullName>α/β-hydrolase domain-containing protein 17B</fullName>", b"fullName");
+        assert_eq!(text, "α/β-hydrolase domain-containing protein 17B");
+
+        // Undeclared entities are kept rather than failing the entry
+        let (text, _) = read("<text>TNF-&alpha; receptor</text>", b"text");
+        assert_eq!(text, "TNF-&alpha; receptor");
+    }
+
+    #[test]
+    fn test_read_text_accumulates_mixed_content() {
+        let (text, _) = read(
+            "<text>\n  Binds Ca<sup>2+</sup> and <i>in vivo</i> targets <![CDATA[<5 nM]]> &lt;x&gt;\n</text>",
+            b"text",
+        );
+        assert_eq!(text, "Binds Ca2+ and in vivo targets <5 nM <x>");
+    }
+
+    #[test]
+    fn test_read_text_handles_nested_same_name() {
+        let xml = "<r><text>outer <text>inner</text> tail</text><next>  after  </next></r>";
+        let (text, mut reader) = read(xml, b"text");
+        assert_eq!(text, "outer inner tail");
+
+        // Stopped at the outer end tag, with the caller's trimming restored
+        let mut buf = Vec::new();
+        assert!(matches!(reader.read_event_into(&mut buf).unwrap(), Event::Start(e) if e.local_name().as_ref() == b"next"));
+        assert!(matches!(reader.read_event_into(&mut buf).unwrap(), Event::Text(e) if e.as_ref() == b"after"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Output invariants checked while batching (`quality.check_invariants`).
+//!
+//! The canonical control: a canonical row (`id == parent_id`) carries no VSP edits, so
+//! every PTM site on it maps with shift 0 and lands on the residue the canonical
+//! sequence has at that position. Any other outcome means the mapping code is broken,
+//! not the data. `check_row` enforces this on each canonical row before it is built,
+//! and `check_batch` verifies on every built batch that each `ptm_sites` entry's
+//! `site_aa` is the residue of its row's `sequence` at `site_index`.
+//!
+//! Both fail with `EtlError::InvariantViolation` naming the row and the site, so the run
+//! stops at the first violation instead of writing inconsistent output. On by default in
+//! debug builds; release builds opt in for QA runs.
+
+use arrow::array::{Array, Int32Array, ListArray, StringArray, StructArray};
+use arrow::record_batch::RecordBatch;
+
+use crate::error::{EtlError, Result};
+use crate::pipeline::builders::ptm::{resolve_site, SiteFrame, SiteOutcome};
+use crate::pipeline::ptm_vocab::class_for_feature_type;
+use crate::pipeline::transformer::TransformedRow;
+
+fn violation(row: &TransformedRow, detail: String) -> EtlError {
+    EtlError::InvariantViolation {
+        id: row.row_id.clone(),
+        detail,
+    }
+}
+
+/// Canonical control of one row: no VSP edits, the entry's own sequence, and every PTM
+/// site at its original position on its original residue. Isoform rows pass unchecked.
+pub fn check_row(row: &TransformedRow) -> Result<()> {
+    if row.row_id != row.parent_id {
+        return Ok(());
+    }
+    let entry = &row.entry;
+    if row.mapper.edit_count() > 0 {
+        return Err(violation(
+            row,
+            format!("canonical row carries VSP edits ({})", row.mapper.describe_edits()),
+        ));
+    }
+    if row.sequence != entry.sequence {
+        return Err(violation(
+            row,
+            format!(
+                "canonical row sequence ({} aa) differs from the entry sequence ({} aa)",
+                row.sequence.len(),
+                entry.sequence.len()
+            ),
+        ));
+    }
+
+    let canonical = entry.sequence.as_bytes();
+    for feat in &entry.features.generic {
+        // Isoform-located sites are not canonical coordinates
+        if feat.sequence.is_some() || class_for_feature_type(&feat.feature_type.to_ascii_lowercase()) == 0 {
+            continue;
+        }
+        let (Some(start), Some(end)) = (feat.start, feat.end) else {
+            continue;
+        };
+        if start <= 0 || start != end {
+            continue;
+        }
+        match resolve_site(canonical, row.sequence.as_bytes(), start, SiteFrame::Canonical) {
+            SiteOutcome::Mapped { index, residue } => {
+                if index != start {
+                    return Err(violation(
+                        row,
+                        format!("{} at {} mapped to {} (shift {})", feat.feature_type, start, index, index - start),
+                    ));
+                }
+                if residue != canonical[start as usize - 1] {
+                    return Err(violation(
+                        row,
+                        format!(
+                            "{} at {} landed on {} instead of the canonical {}",
+                            feat.feature_type,
+                            start,
+                            residue as char,
+                            canonical[start as usize - 1] as char
+                        ),
+                    ));
+                }
+            }
+            // A coordinate past the sequence end is a data problem, counted as a PTM failure
+            SiteOutcome::Failed { code: "CANONICAL_OOB", .. } => {}
+            SiteOutcome::Failed { code, .. } => {
+                return Err(violation(row, format!("{} at {} failed with {}", feat.feature_type, start, code)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Every `ptm_sites` entry of `batch` names the residue its row's `sequence` has at
+/// `site_index`. Skipped when the batch lacks `id`, `sequence` or `ptm_sites`; sites
+/// kept with a `mapping_failure_code` (`transform.debug_mapping`) are not on the row.
+pub fn check_batch(batch: &RecordBatch) -> Result<()> {
+    let (Some(ids), Some(parent_ids), Some(sequences), Some(sites)) = (
+        batch.column_by_name("id").and_then(|c| c.as_any().downcast_ref::<StringArray>()),
+        batch.column_by_name("parent_id").and_then(|c| c.as_any().downcast_ref::<StringArray>()),
+        batch.column_by_name("sequence").and_then(|c| c.as_any().downcast_ref::<StringArray>()),
+        batch.column_by_name("ptm_sites").and_then(|c| c.as_any().downcast_ref::<ListArray>()),
+    ) else {
+        return Ok(());
+    };
+    let Some(site_struct) = sites.values().as_any().downcast_ref::<StructArray>() else {
+        return Ok(());
+    };
+    let (Some(indices), Some(residues)) = (
+        site_struct.column_by_name("site_index").and_then(|c| c.as_any().downcast_ref::<Int32Array>()),
+        site_struct.column_by_name("site_aa").and_then(|c| c.as_any().downcast_ref::<StringArray>()),
+    ) else {
+        return Ok(());
+    };
+    let failures = site_struct.column_by_name("mapping_failure_code");
+
+    let offsets = sites.value_offsets();
+    for row in 0..batch.num_rows() {
+        if sites.is_null(row) {
+            continue;
+        }
+        let sequence = sequences.value(row).as_bytes();
+        for site in offsets[row] as usize..offsets[row + 1] as usize {
+            if failures.is_some_and(|f| f.is_valid(site)) {
+                continue;
+            }
+            let index = indices.value(site);
+            let expected = sequence.get((index as usize).wrapping_sub(1)).map(|&aa| aa as char);
+            let site_aa = residues.value(site);
+            if expected.is_none_or(|aa| site_aa.len() != 1 || !site_aa.starts_with(aa)) {
+                let kind = if ids.value(row) == parent_ids.value(row) { "canonical" } else { "isoform" };
+                return Err(EtlError::InvariantViolation {
+                    id: ids.value(row).to_string(),
+                    detail: format!(
+                        "{} row ptm_sites[{}] has site_aa {} but the sequence has {}",
+                        kind,
+                        index,
+                        site_aa,
+                        expected.map_or_else(|| format!("no residue ({} aa)", sequence.len()), String::from)
+                    ),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::pipeline::builders::EntryBuilders;
+    use crate::pipeline::scratch::{FeatureScratch, ParsedEntry};
+    use crate::testing;
+    use std::sync::Arc;
+
+    fn canonical_row(sequence: &str, site: i32) -> TransformedRow {
+        let mut entry = ParsedEntry {
+            accession: "P1".to_string(),
+            parent_id: "P1".to_string(),
+            sequence: sequence.to_string(),
+            ..Default::default()
+        };
+        entry.features.generic.push(FeatureScratch {
+            feature_type: "modified residue".to_string(),
+            description: Some("Phosphoserine".to_string()),
+            start: Some(site),
+            end: Some(site),
+            ..Default::default()
+        });
+        testing::canonical_row(entry)
+    }
+
+    #[test]
+    fn canonical_rows_and_built_batches_pass_and_tampering_fails() {
+        let row = canonical_row("MSAK", 2);
+        check_row(&row).unwrap();
+        // Past the end: a data problem, not a broken invariant
+        check_row(&canonical_row("MSAK", 9)).unwrap();
+
+        let mut builders = EntryBuilders::new(4);
+        builders.append_row(&row, &Metrics::new());
+        let batch = builders.finish_batch().unwrap();
+        check_batch(&batch).unwrap();
+
+        let mut tampered = canonical_row("MSAK", 2);
+        tampered.sequence = "MTAK".to_string();
+        let err = check_row(&tampered).unwrap_err().to_string();
+        assert!(err.contains("'P1'") && err.contains("differs from the entry sequence"), "{err}");
+
+        let mut columns = batch.columns().to_vec();
+        columns[batch.schema().index_of("sequence").unwrap()] = Arc::new(StringArray::from(vec!["MTAK"]));
+        let tampered_batch = RecordBatch::try_new(batch.schema(), columns).unwrap();
+        let err = check_batch(&tampered_batch).unwrap_err().to_string();
+        assert!(err.contains("canonical row ptm_sites[2] has site_aa S but the sequence has T"), "{err}");
+    }
+}
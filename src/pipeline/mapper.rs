@@ -11,7 +11,18 @@ pub enum MapFailure {
     VspUnresolvable,
 }
 
-#[derive(Debug, Clone)]
+impl MapFailure {
+    /// `[PTM_FAIL]` / `mapping_failure_code` name of the failure.
+    pub fn code(&self) -> &'static str {
+        match self {
+            MapFailure::VspDeletionEvent => "VSP_DELETION_EVENT",
+            MapFailure::PtmOutOfBounds => "MAPPER_OOB",
+            MapFailure::VspUnresolvable => "VSP_UNRESOLVABLE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct VspEdit {
     begin_1based: i32,
     end_1based: i32,
@@ -26,12 +37,37 @@ struct VspEdit {
 /// - Output position is 1-based isoform coordinate.
 /// - If a position is deleted by a VSP "Missing" event, returns `VspDeletionEvent`.
 /// - If a position falls within a non-deletion indel region, returns `VspUnresolvable`.
+/// - If the isoform's edits overlap in a way that has no single order (see
+///   `resolve_overlaps`), every position returns `VspUnresolvable`.
 #[derive(Debug, Clone)]
 pub struct CoordinateMapper {
+    /// Sorted by position and non-overlapping unless `conflict` is set
     edits: Vec<VspEdit>,
+    /// `shifts[i]`: summed delta of `edits[..i]`, the shift of positions between
+    /// `edits[i - 1]` and `edits[i]` (one more entry than `edits`)
+    shifts: Vec<i32>,
+    /// Why the edits can't be applied, e.g. "VSP edits 5-9 and 8-12 overlap"
+    conflict: Option<String>,
+    /// Sites mapped through this mapper get the lowest evidence confidence
+    /// (see `mark_low_confidence`)
+    low_confidence: bool,
 }
 
 impl CoordinateMapper {
+    fn new(edits: Vec<VspEdit>, conflict: Option<String>) -> Self {
+        let mut shifts = Vec::with_capacity(edits.len() + 1);
+        shifts.push(0);
+        for edit in &edits {
+            shifts.push(shifts[shifts.len() - 1] + edit.delta);
+        }
+        Self {
+            edits,
+            shifts,
+            conflict,
+            low_confidence: false,
+        }
+    }
+
     pub fn from_entry(entry: &ParsedEntry) -> Self {
         Self::from_entry_for_vsp_ids(entry, &[])
     }
@@ -41,10 +77,48 @@ impl CoordinateMapper {
         self.edits.len()
     }
 
+    /// Human-readable edit list, e.g. "10-20:-11(deletion), 40-41:+3" (for tracing).
+    pub fn describe_edits(&self) -> String {
+        self.edits
+            .iter()
+            .map(|e| {
+                format!(
+                    "{}-{}:{:+}{}",
+                    e.begin_1based,
+                    e.end_1based,
+                    e.delta,
+                    if e.is_deletion { "(deletion)" } else { "" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Why this isoform's VSP edits can't be applied, when they overlap irreconcilably.
+    pub fn conflict(&self) -> Option<&str> {
+        self.conflict.as_deref()
+    }
+
+    /// Marks coordinates mapped by this mapper as untrustworthy, e.g. when the edits
+    /// don't reproduce the isoform's sidecar length.
+    pub fn mark_low_confidence(&mut self) {
+        self.low_confidence = true;
+    }
+
+    /// Whether `mark_low_confidence` was called.
+    pub fn low_confidence(&self) -> bool {
+        self.low_confidence
+    }
+
+    /// Canonical length plus `total_delta`: the isoform length these edits produce.
+    pub fn expected_len(&self, canonical_len: usize) -> i64 {
+        canonical_len as i64 + self.total_delta() as i64
+    }
+
     /// Returns the total delta (sum of all edit deltas) for this mapper.
     /// Positive = net insertion, Negative = net deletion.
     pub fn total_delta(&self) -> i32 {
-        self.edits.iter().map(|e| e.delta).sum()
+        self.shifts[self.edits.len()]
     }
 
     /// Builds a mapper using only splice-variant edits referenced by the isoform.
@@ -52,7 +126,7 @@ impl CoordinateMapper {
     /// If `vsp_ids` is empty, returns an identity mapper.
     pub fn from_entry_for_vsp_ids(entry: &ParsedEntry, vsp_ids: &[String]) -> Self {
         if vsp_ids.is_empty() {
-            return Self { edits: Vec::new() };
+            return Self::new(Vec::new(), None);
         }
 
         let vsp_set: HashSet<&str> = vsp_ids.iter().map(|s| s.as_str()).collect();
@@ -119,13 +193,48 @@ impl CoordinateMapper {
             });
         }
 
-        edits.sort_by_key(|e| e.begin_1based);
-
-        Self { edits }
+        // Features are not guaranteed to be in coordinate order
+        edits.sort_by_key(|e| (e.begin_1based, e.end_1based));
+        match resolve_overlaps(edits) {
+            Ok(edits) => Self::new(edits, None),
+            Err((edits, conflict)) => Self::new(edits, Some(conflict)),
+        }
     }
 
     /// Maps a point coordinate (1-based) from canonical to isoform.
     pub fn map_point_1based(&self, original_pos_1based: i32) -> Result<i32, MapFailure> {
+        if self.conflict.is_some() {
+            return Err(MapFailure::VspUnresolvable);
+        }
+        // Edits don't overlap, so the ones ending before pos are a prefix: binary search
+        // for its length instead of scanning (titin has dozens of VSPs and thousands of sites).
+        let ended = self.edits.partition_point(|e| e.end_1based < original_pos_1based);
+        self.map_after(original_pos_1based, ended)
+    }
+
+    /// Maps many points (1-based) at once, in input order: the points are sorted and the
+    /// edits swept once, instead of searching the edits for every point.
+    pub fn map_points(&self, points: &[i32]) -> Vec<Result<i32, MapFailure>> {
+        if self.edits.is_empty() || self.conflict.is_some() {
+            return points.iter().map(|&p| self.map_point_1based(p)).collect();
+        }
+        let mut order: Vec<usize> = (0..points.len()).collect();
+        order.sort_unstable_by_key(|&i| points[i]);
+
+        let mut results = vec![Err(MapFailure::VspUnresolvable); points.len()];
+        let mut ended = 0;
+        for i in order {
+            let pos = points[i];
+            while self.edits.get(ended).is_some_and(|e| e.end_1based < pos) {
+                ended += 1;
+            }
+            results[i] = self.map_after(pos, ended);
+        }
+        results
+    }
+
+    /// Maps `original_pos_1based` given that exactly `edits[..ended]` end before it.
+    fn map_after(&self, original_pos_1based: i32, ended: usize) -> Result<i32, MapFailure> {
         if original_pos_1based <= 0 {
             return Err(MapFailure::VspUnresolvable);
         }
@@ -138,48 +247,21 @@ impl CoordinateMapper {
         //   - delta == 0 => map to the same coordinate (within-span substitution)
         //   - delta != 0 => map to start of the variation (begin), after applying prior shifts
         // - If pos > end: apply delta to downstream positions.
-        let mut shift: i32 = 0;
-        for edit in &self.edits {
-            if original_pos_1based < edit.begin_1based {
-                break;
-            }
-
-            if original_pos_1based > edit.end_1based {
-                shift += edit.delta;
-                continue;
-            }
-
+        let shift = self.shifts[ended];
+        if let Some(edit) = self.edits.get(ended).filter(|e| e.begin_1based <= original_pos_1based) {
             // Inside edited span.
             if edit.is_deletion {
                 return Err(MapFailure::VspDeletionEvent);
             }
 
-            // Requirement 1: Identity mapping for substitutions (delta == 0)
-            // Within-span substitution: position maps to itself with accumulated shift
-            if edit.delta == 0 {
-                let mapped = original_pos_1based + shift;
-                return if mapped <= 0 {
-                    Err(MapFailure::PtmOutOfBounds)
-                } else {
-                    Ok(mapped)
-                };
+            // Requirement 1: Identity mapping for substitutions (delta == 0): the position
+            // maps to itself with the accumulated shift.
+            // Requirement 2: For length-changing indels (delta != 0), only the FIRST residue
+            // of the segment can be mapped deterministically. Internal residues have no
+            // deterministic isoform coordinate (snapping them to begin caused RESIDUE_MISMATCH).
+            if edit.delta != 0 && original_pos_1based != edit.begin_1based {
+                return Err(MapFailure::VspUnresolvable);
             }
-
-            // Requirement 2: For length-changing indels (delta != 0),
-            // only the FIRST residue of the segment can be mapped deterministically.
-            if original_pos_1based == edit.begin_1based {
-                let mapped = edit.begin_1based + shift;
-                return if mapped <= 0 {
-                    Err(MapFailure::PtmOutOfBounds)
-                } else {
-                    Ok(mapped)
-                };
-            }
-
-            // Internal residues (not at exact start) have no deterministic isoform coordinate.
-            // Previously these were "snapped" to begin, causing RESIDUE_MISMATCH.
-            // Now they are cleanly rejected as VspUnresolvable.
-            return Err(MapFailure::VspUnresolvable);
         }
 
         let mapped = original_pos_1based + shift;
@@ -190,6 +272,41 @@ impl CoordinateMapper {
     }
 }
 
+/// Makes sorted edits non-overlapping where the result is unambiguous:
+/// - identical edits (the same VSP listed twice) are kept once;
+/// - an edit inside a deleted span is dropped, since those residues are gone anyway;
+/// - overlapping deletions are merged into one deletion of their union.
+///
+/// Any other overlap (e.g. two length-changing substitutions sharing residues) has no
+/// single order of application, so the edits are returned with the reason.
+fn resolve_overlaps(edits: Vec<VspEdit>) -> Result<Vec<VspEdit>, (Vec<VspEdit>, String)> {
+    let mut resolved: Vec<VspEdit> = Vec::with_capacity(edits.len());
+    for edit in &edits {
+        let Some(prev) = resolved.last_mut() else {
+            resolved.push(edit.clone());
+            continue;
+        };
+        if edit.begin_1based > prev.end_1based {
+            resolved.push(edit.clone());
+        } else if edit == prev || (prev.is_deletion && edit.end_1based <= prev.end_1based) {
+            continue;
+        } else if prev.is_deletion && edit.is_deletion {
+            prev.end_1based = edit.end_1based;
+            prev.delta = prev.begin_1based - prev.end_1based - 1;
+        } else if edit.is_deletion && edit.begin_1based == prev.begin_1based {
+            // Sorted by end, so this deletion covers the whole previous edit
+            *prev = edit.clone();
+        } else {
+            let conflict = format!(
+                "VSP edits {}-{} and {}-{} overlap",
+                prev.begin_1based, prev.end_1based, edit.begin_1based, edit.end_1based
+            );
+            return Err((edits, conflict));
+        }
+    }
+    Ok(resolved)
+}
+
 /// Returns the amino acid count for a valid sequence, or 0 for descriptive notes.
 ///
 /// A string is considered a descriptive note (returning 0) if it contains:
@@ -236,8 +353,10 @@ mod tests {
 
     #[test]
     fn deletion_shifts_downstream_positions() {
-        let mut entry = ParsedEntry::default();
-        entry.sequence = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string();
+        let mut entry = ParsedEntry {
+            sequence: "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string(),
+            ..Default::default()
+        };
 
         let vsp = FeatureScratch {
             id: Some("VSP_TEST".to_string()),
@@ -263,8 +382,10 @@ mod tests {
 
     #[test]
     fn non_missing_indel_rejects_interior() {
-        let mut entry = ParsedEntry::default();
-        entry.sequence = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string();
+        let mut entry = ParsedEntry {
+            sequence: "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string(),
+            ..Default::default()
+        };
 
         // Replace positions 5..7 (len=3) with len=1 -> delta=-2.
         let vsp = FeatureScratch {
@@ -295,6 +416,81 @@ mod tests {
         assert_eq!(mapper.map_point_1based(10).unwrap(), 8);
     }
 
+    fn vsp(id: &str, start: i32, end: i32, variation: &str) -> FeatureScratch {
+        FeatureScratch {
+            id: Some(id.to_string()),
+            feature_type: "splice variant".to_string(),
+            start: Some(start),
+            end: Some(end),
+            variation: Some(variation.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overlapping_edits_resolve_or_flag_conflict() {
+        let mut entry = ParsedEntry {
+            sequence: "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string(),
+            ..Default::default()
+        };
+        // Out of order, overlapping deletions (5-9, 8-12) and a substitution inside one
+        entry.features.generic.push(vsp("VSP_B", 8, 12, "Missing"));
+        entry.features.generic.push(vsp("VSP_A", 5, 9, "Missing"));
+        entry.features.generic.push(vsp("VSP_C", 6, 6, "Q"));
+        entry.features.generic.push(vsp("VSP_D", 20, 22, "QQ"));
+        entry.features.generic.push(vsp("VSP_E", 21, 24, "R"));
+
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let mapper = CoordinateMapper::from_entry_for_vsp_ids(&entry, &ids(&["VSP_A", "VSP_B", "VSP_C"]));
+        assert_eq!(mapper.conflict(), None);
+        assert_eq!(mapper.edit_count(), 1);
+        assert_eq!(mapper.total_delta(), -8);
+        assert_eq!(mapper.map_point_1based(10), Err(MapFailure::VspDeletionEvent));
+        assert_eq!(mapper.map_point_1based(15).unwrap(), 7);
+
+        // Two length-changing substitutions sharing residues 21-22: no single order
+        let mapper = CoordinateMapper::from_entry_for_vsp_ids(&entry, &ids(&["VSP_D", "VSP_E"]));
+        assert_eq!(mapper.conflict(), Some("VSP edits 20-22 and 21-24 overlap"));
+        assert_eq!(mapper.map_point_1based(2), Err(MapFailure::VspUnresolvable));
+    }
+
+    #[test]
+    fn many_edits_map_through_prefix_shifts() {
+        let mut entry = ParsedEntry {
+            sequence: "A".repeat(1000),
+            ..Default::default()
+        };
+        // 49 two-residue deletions at 10-11, 30-31, ... and a two-residue insertion at 20, 40, ...
+        let mut ids = Vec::new();
+        for k in 1..50 {
+            let id = format!("VSP_{k}");
+            let start = k * 20 - 10;
+            entry.features.generic.push(vsp(&id, start, start + 1, "Missing"));
+            ids.push(id);
+            let id = format!("VSP_{k}_INS");
+            entry.features.generic.push(vsp(&id, k * 20, k * 20, "AAA"));
+            ids.push(id);
+        }
+        let mapper = CoordinateMapper::from_entry_for_vsp_ids(&entry, &ids);
+        assert_eq!(mapper.edit_count(), 98);
+        assert_eq!(mapper.total_delta(), 0);
+
+        assert_eq!(mapper.map_point_1based(9).unwrap(), 9);
+        assert_eq!(mapper.map_point_1based(11), Err(MapFailure::VspDeletionEvent));
+        assert_eq!(mapper.map_point_1based(12).unwrap(), 10);
+        assert_eq!(mapper.map_point_1based(20).unwrap(), 18);
+        assert_eq!(mapper.map_point_1based(21).unwrap(), 21);
+        assert_eq!(mapper.map_point_1based(500).unwrap(), 498);
+        assert_eq!(mapper.map_point_1based(510), Err(MapFailure::VspDeletionEvent));
+        assert_eq!(mapper.map_point_1based(519).unwrap(), 517);
+        assert_eq!(mapper.map_point_1based(1000).unwrap(), 1000);
+
+        // Unsorted batches map like single points, in input order
+        let points: Vec<i32> = (-2..=1002).rev().step_by(7).chain([519, 11, 519]).collect();
+        let single: Vec<_> = points.iter().map(|&p| mapper.map_point_1based(p)).collect();
+        assert_eq!(mapper.map_points(&points), single);
+    }
+
     #[test]
     fn malformed_variation_treated_as_note() {
         // Strings with spaces/digits should return 0 length (bullshit detection)
@@ -315,8 +511,10 @@ mod tests {
 
     #[test]
     fn substitution_maps_identity() {
-        let mut entry = ParsedEntry::default();
-        entry.sequence = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string();
+        let mut entry = ParsedEntry {
+            sequence: "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string(),
+            ..Default::default()
+        };
 
         // Replace positions 5..7 (len=3) with len=3 -> delta=0 (substitution).
         let vsp = FeatureScratch {
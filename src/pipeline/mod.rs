@@ -1,8 +1,22 @@
 pub mod batcher;
+pub mod bgzf;
 pub mod builders;
+pub mod checksum;
+pub mod dedup;
+pub mod enrich;
+pub mod feature_routing;
 pub mod handlers;
+pub mod invariants;
 pub mod mapper;
+pub mod pacing;
 pub mod parser;
+pub mod plddt;
+pub mod ptm_vocab;
 pub mod reader;
+pub mod rejects;
+pub mod row_filter;
 pub mod scratch;
+pub mod state;
+pub mod trace;
 pub mod transformer;
+pub mod window;
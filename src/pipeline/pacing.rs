@@ -0,0 +1,246 @@
+//! Adaptive pacing of the parser → writer batch channel (`performance.adaptive_channel`).
+//!
+//! With large `batch_size`, `channel_capacity` full batches can hold several GB. The
+//! pacer tracks the average batch size and holds the producer back once the batches
+//! queued in the channel would exceed `max_inflight_mb`, so the effective capacity
+//! shrinks as batches grow. Every send also samples channel fullness into
+//! `ChannelStats`, from which `summary` suggests a capacity for the next run.
+
+use arrow::record_batch::RecordBatch;
+use crossbeam_channel::Sender;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::sampler::ChannelStats;
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// How often a held-back producer rechecks the channel.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Share of sends that must find the channel empty and full before it counts as bursty.
+const BURST_SHARE: f32 = 0.25;
+
+/// Cheap, cloneable pacing handle shared by every batcher; a no-op when disabled.
+#[derive(Clone, Default)]
+pub struct ChannelPacer {
+    state: Option<Arc<PacerState>>,
+}
+
+struct PacerState {
+    capacity: usize,
+    max_inflight_bytes: Option<u64>,
+    stats: Arc<ChannelStats>,
+    /// Running mean of batch memory, in bytes
+    avg_batch_bytes: AtomicU64,
+    batches: AtomicU64,
+    waits: AtomicU64,
+    wait_nanos: AtomicU64,
+    peak_inflight_bytes: AtomicU64,
+}
+
+/// What the pacer saw, for the run report.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ChannelTuning {
+    pub configured_capacity: usize,
+    /// Batches allowed in flight under `max_inflight_mb` at the final batch size
+    pub effective_capacity: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_inflight_mb: Option<u64>,
+    pub avg_batch_mb: f64,
+    pub peak_inflight_mb: f64,
+    pub producer_waits: u64,
+    pub producer_wait_secs: f64,
+    /// Share of sends that found the writer with nothing queued
+    pub starved_percent: f32,
+    /// Share of sends that found the channel at its effective capacity
+    pub saturated_percent: f32,
+    pub suggested_capacity: usize,
+}
+
+impl ChannelPacer {
+    /// Handle that never waits or records (the default).
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Paces a channel of `capacity` batches, holding at most `max_inflight_mb` of
+    /// queued batches when set; fullness samples go to `stats`.
+    pub fn new(capacity: usize, max_inflight_mb: Option<u64>, stats: Arc<ChannelStats>) -> Self {
+        Self {
+            state: Some(Arc::new(PacerState {
+                capacity,
+                max_inflight_bytes: max_inflight_mb.map(|mb| mb * 1024 * 1024),
+                stats,
+                avg_batch_bytes: AtomicU64::new(0),
+                batches: AtomicU64::new(0),
+                waits: AtomicU64::new(0),
+                wait_nanos: AtomicU64::new(0),
+                peak_inflight_bytes: AtomicU64::new(0),
+            })),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Waits until `batch` fits within the effective capacity of `sender`'s channel.
+    ///
+    /// Returns immediately when disabled. A dropped receiver empties the channel, so
+    /// the wait ends and the following send reports the error.
+    pub fn before_send(&self, sender: &Sender<RecordBatch>, batch: &RecordBatch) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        let avg = state.observe_batch(batch.get_array_memory_size() as u64);
+        let allowed = state.effective_capacity(avg);
+
+        let queued = sender.len();
+        state.stats.record_fullness(queued);
+        state
+            .peak_inflight_bytes
+            .fetch_max((queued as u64 + 1) * avg, Ordering::Relaxed);
+        if queued < allowed {
+            return;
+        }
+
+        state.waits.fetch_add(1, Ordering::Relaxed);
+        let started = Instant::now();
+        while sender.len() >= allowed {
+            thread::sleep(POLL_INTERVAL);
+        }
+        state
+            .wait_nanos
+            .fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Summary for the run report; None when disabled or nothing was sent.
+    pub fn summary(&self) -> Option<ChannelTuning> {
+        let state = self.state.as_ref()?;
+        if state.batches.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+        let avg = state.avg_batch_bytes.load(Ordering::Relaxed);
+        let effective = state.effective_capacity(avg);
+        let starved = state.stats.share_at_most(0.0);
+        let saturated = state.stats.share_at_least(effective as f32 / state.capacity.max(1) as f32);
+        let observed = (state.stats.fullness_quantile(0.95) * state.capacity as f32).ceil() as usize;
+
+        Some(ChannelTuning {
+            configured_capacity: state.capacity,
+            effective_capacity: effective,
+            max_inflight_mb: state.max_inflight_bytes.map(|b| b / (1024 * 1024)),
+            avg_batch_mb: avg as f64 / MIB,
+            peak_inflight_mb: state.peak_inflight_bytes.load(Ordering::Relaxed) as f64 / MIB,
+            producer_waits: state.waits.load(Ordering::Relaxed),
+            producer_wait_secs: Duration::from_nanos(state.wait_nanos.load(Ordering::Relaxed)).as_secs_f64(),
+            starved_percent: starved * 100.0,
+            saturated_percent: saturated * 100.0,
+            suggested_capacity: suggest_capacity(
+                observed,
+                effective,
+                state.budget_batches(avg),
+                starved,
+                saturated,
+            ),
+        })
+    }
+}
+
+impl PacerState {
+    /// Folds one batch into the running mean and returns the new mean.
+    fn observe_batch(&self, bytes: u64) -> u64 {
+        let n = self.batches.fetch_add(1, Ordering::Relaxed) + 1;
+        let previous = self.avg_batch_bytes.load(Ordering::Relaxed);
+        let avg = (previous as f64 + (bytes as f64 - previous as f64) / n as f64) as u64;
+        self.avg_batch_bytes.store(avg, Ordering::Relaxed);
+        avg
+    }
+
+    /// Batches of `avg_bytes` that fit in the memory budget (unbounded without one).
+    fn budget_batches(&self, avg_bytes: u64) -> usize {
+        match self.max_inflight_bytes {
+            Some(budget) if avg_bytes > 0 => ((budget / avg_bytes) as usize).max(1),
+            _ => usize::MAX,
+        }
+    }
+
+    fn effective_capacity(&self, avg_bytes: u64) -> usize {
+        self.budget_batches(avg_bytes).min(self.capacity).max(1)
+    }
+}
+
+/// Capacity to configure next time, from the 95th-percentile queue length `observed`.
+///
+/// - Bursty (often empty and often full): the writer starves between bursts, so
+///   double the effective capacity, within the memory budget.
+/// - Regularly full: the writer is the bottleneck and extra slots only hold memory,
+///   so keep the effective capacity.
+/// - Otherwise: the observed need plus one slot of headroom (at least 2).
+fn suggest_capacity(observed: usize, effective: usize, budget: usize, starved: f32, saturated: f32) -> usize {
+    if starved >= BURST_SHARE && saturated >= BURST_SHARE {
+        return effective.saturating_mul(2).min(budget).max(1);
+    }
+    if observed >= effective {
+        return effective;
+    }
+    (observed + 1).max(2).min(effective)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use crossbeam_channel::bounded;
+
+    fn batch(rows: i64) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("n", DataType::Int64, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from_iter_values(0..rows))]).unwrap()
+    }
+
+    #[test]
+    fn holds_the_producer_to_the_memory_budget() {
+        let stats = Arc::new(ChannelStats::new(8));
+        // ~0.8 MB per batch against a 1 MB budget: one batch in flight at a time
+        let pacer = ChannelPacer::new(8, Some(1), Arc::clone(&stats));
+        let (tx, rx) = bounded(8);
+
+        pacer.before_send(&tx, &batch(100_000));
+        tx.send(batch(100_000)).unwrap();
+
+        let reader = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            rx.recv().unwrap();
+            rx
+        });
+        pacer.before_send(&tx, &batch(100_000));
+        assert!(tx.is_empty());
+        reader.join().unwrap();
+
+        let summary = pacer.summary().unwrap();
+        assert_eq!(summary.effective_capacity, 1);
+        assert_eq!(summary.producer_waits, 1);
+        assert_eq!(summary.suggested_capacity, 1);
+    }
+
+    #[test]
+    fn suggests_capacity_from_observed_queue_length() {
+        assert_eq!(suggest_capacity(2, 8, usize::MAX, 0.0, 0.0), 3);
+        assert_eq!(suggest_capacity(0, 8, usize::MAX, 0.9, 0.0), 2);
+        assert_eq!(suggest_capacity(8, 8, usize::MAX, 0.0, 0.9), 8);
+        assert_eq!(suggest_capacity(8, 8, 12, 0.4, 0.4), 12);
+    }
+
+    #[test]
+    fn disabled_pacer_records_nothing() {
+        let pacer = ChannelPacer::disabled();
+        let (tx, _rx) = bounded(1);
+        pacer.before_send(&tx, &batch(10));
+        assert!(pacer.summary().is_none());
+    }
+}
@@ -1,47 +1,453 @@
 use arrow::record_batch::RecordBatch;
-use crossbeam_channel::Sender;
-use quick_xml::events::Event;
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::io::BufRead;
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-use crate::error::Result;
+use crate::error::{EtlError, Result};
 use crate::metrics::MetricsCollector;
 use crate::pipeline::batcher::Batcher;
-use crate::pipeline::handlers::metadata;
-use crate::pipeline::scratch::EntryScratch;
-use crate::pipeline::transformer::EntryTransformer;
+use crate::pipeline::dedup::AccessionDedup;
+use crate::pipeline::enrich::Enrichers;
+use crate::pipeline::feature_routing::FeatureRouting;
+use crate::pipeline::rejects::RejectLog;
+use crate::pipeline::row_filter::RowFilter;
+use crate::pipeline::handlers::{metadata, skip_element};
+use crate::pipeline::pacing::ChannelPacer;
+use crate::pipeline::plddt::PlddtSource;
+use crate::pipeline::ptm_vocab::PtmVocabulary;
+use crate::pipeline::window::SiteContext;
+use crate::schema::SchemaProfile;
+use crate::pipeline::scratch::{EntryScratch, ParsedEntry};
+use crate::pipeline::trace::{capture_element, EntryTracer};
+use crate::pipeline::transformer::{EntryTransformer, InvalidEntryPolicy};
+use crate::shutdown::CancelToken;
 
 /// Parses UniProt XML entries and sends RecordBatches to the channel.
 pub fn parse_entries<R: BufRead, M: MetricsCollector>(
-    mut reader: Reader<R>,
+    reader: Reader<R>,
+    sender: Sender<RecordBatch>,
+    metrics: &M,
+    batch_size: usize,
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+) -> Result<()> {
+    parse_entries_with_hooks(reader, sender, metrics, batch_size, sidecar_fasta, &ParseHooks::default())
+}
+
+/// Calls `on_entry` with every parsed entry, without transforming or batching it
+/// (for tools that inspect entries, e.g. `ptm_fixtures`).
+pub fn for_each_entry<R: BufRead>(reader: Reader<R>, mut on_entry: impl FnMut(ParsedEntry) -> Result<()>) -> Result<()> {
+    read_entries(reader, &ParseHooks::default(), |scratch| on_entry(scratch.take_entry()))
+}
+
+/// Optional extension points for `parse_entries_with_hooks`.
+#[derive(Clone, Default)]
+pub struct ParseHooks {
+    /// User-defined enrichers; batches then carry `enrichers.schema()`
+    pub enrichers: Enrichers,
+    /// Receives every row/feature dropped during transformation
+    pub rejects: RejectLog,
+    /// UniProt release written to the `release` column (see `release::resolve_release`)
+    pub release: Option<String>,
+    /// Abort on the first `<sequence checksum>` mismatch (otherwise only counted)
+    pub strict_checksums: bool,
+    /// Deterministic entry subset for smoke runs (default: every entry)
+    pub sampling: EntrySampling,
+    /// Verbose trace of one accession (`--trace-accession`)
+    pub trace: EntryTracer,
+    /// Flush partially filled batches this long after the previous one
+    /// (`performance.flush_interval_secs`)
+    pub flush_interval: Option<Duration>,
+    /// PTM vocabulary for `ptm_sites` mod_type codes (`transform.ptm_vocabulary_path`;
+    /// default: the embedded subset)
+    pub ptm_vocabulary: PtmVocabulary,
+    /// Residue window written to `ptm_sites.site_context` (`transform.emit_site_context`;
+    /// default: none)
+    pub site_context: Option<SiteContext>,
+    /// AlphaFold pLDDT for `alphafold_mean_plddt` / `ptm_sites.plddt` (`transform.alphafold_dir`;
+    /// default: none)
+    pub plddt: PlddtSource,
+    /// Fill `domains.family_accessions` from `domain_xrefs` (`transform.reconcile_domain_xrefs`;
+    /// default: off)
+    pub reconcile_domain_xrefs: bool,
+    /// `mapping_shift`/`mapping_failure_code` in `ptm_sites` and the coordinate-feature
+    /// columns, keeping what fails to map (`transform.debug_mapping`; default: off)
+    pub debug_mapping: bool,
+    /// Built-in columns to build (`schema.profile`; default: every column)
+    pub schema_profile: SchemaProfile,
+    /// Only `id`, `parent_id` and these built-in columns, in place of `schema_profile`
+    /// (`backfill`; default: none)
+    pub columns: Option<Arc<[String]>>,
+    /// Feature type -> typed column routing (`transform.feature_routes`; default: the
+    /// embedded table)
+    pub feature_routing: FeatureRouting,
+    /// Run-wide duplicate accession filter (`transform.deduplicate`; default: keep every copy)
+    pub dedup: AccessionDedup,
+    /// Entries without an accession or sequence: skip (default) or fail
+    /// (`transform.invalid_entries`)
+    pub invalid_entries: InvalidEntryPolicy,
+    /// Lowest confidence for PTM sites mapped onto isoforms whose sidecar length disagrees
+    /// with their VSP edits (`transform.low_confidence_on_length_mismatch`; default: off)
+    pub low_confidence_on_length_mismatch: bool,
+    /// Emit isoforms missing from the sidecar as rows with a null sequence
+    /// (`transform.keep_unsequenced_isoforms`; default: dropped)
+    pub keep_unsequenced_isoforms: bool,
+    /// Rows to keep (`filters.row_expr`; default: every row)
+    pub row_filter: Option<RowFilter>,
+    /// Stops parsing at the next entry once cancelled (SIGINT/SIGTERM); rows already
+    /// read are still flushed
+    pub cancel: CancelToken,
+    /// Threads that transform parsed entries and build RecordBatches, fed by the parse
+    /// thread over a second channel (`performance.build_threads`; 0 = build inline)
+    pub build_threads: usize,
+    /// Memory-bounded pacing of the batch channel (`performance.adaptive_channel`)
+    pub pacer: ChannelPacer,
+    /// Canonical control and `ptm_sites` residue checks on every batch
+    /// (`quality.check_invariants`; default: off)
+    pub check_invariants: bool,
+}
+
+/// Which entries of each input file are processed (`--sample-every` / `--max-entries`).
+///
+/// Applied per file: entries are numbered from 0 in document order, every `every`-th
+/// one is kept, and reading stops once `max_entries` have been kept. Skipped entries
+/// are stepped over without being parsed.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EntrySampling {
+    pub every: Option<usize>,
+    pub max_entries: Option<usize>,
+}
+
+impl EntrySampling {
+    /// True unless every entry is processed.
+    pub fn is_active(&self) -> bool {
+        self.every.is_some_and(|n| n > 1) || self.max_entries.is_some()
+    }
+
+    fn keeps(&self, index: usize) -> bool {
+        index.is_multiple_of(self.every.unwrap_or(1).max(1))
+    }
+
+    fn is_full(&self, kept: usize) -> bool {
+        self.max_entries.is_some_and(|max| kept >= max)
+    }
+}
+
+/// Like `parse_entries`, with enrichers and a reject log.
+///
+/// With enrichers registered, pass `hooks.enrichers.schema()` to `write_batches_with_schema`.
+pub fn parse_entries_with_hooks<R: BufRead, M: MetricsCollector>(
+    reader: Reader<R>,
+    sender: Sender<RecordBatch>,
+    metrics: &M,
+    batch_size: usize,
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    hooks: &ParseHooks,
+) -> Result<()> {
+    if hooks.build_threads > 0 {
+        return parse_with_build_stage(reader, sender, metrics, batch_size, sidecar_fasta, hooks);
+    }
+
+    let mut batcher = build_batcher(sender, metrics, batch_size, hooks)?;
+    let transformer = build_transformer(metrics, sidecar_fasta, hooks);
+    read_entries(reader, hooks, |scratch| {
+        let entry = Arc::new(scratch.take_entry());
+        for row in transformer.transform_shared(Arc::clone(&entry))? {
+            batcher.add_row(row)?;
+        }
+        // Rows are consumed by the batcher, so the entry is normally unique again
+        if let Ok(entry) = Arc::try_unwrap(entry) {
+            scratch.recycle_entry(entry);
+        }
+        Ok(())
+    })?;
+    batcher.finish()
+}
+
+/// Entries in flight between the parse thread and each build thread.
+const BUILD_CHANNEL_CAPACITY: usize = 64;
+
+/// Parses on the calling thread and hands entries over a second channel to
+/// `hooks.build_threads` threads that transform them and build the RecordBatches.
+///
+/// Each build thread owns its own batcher. With one build thread rows keep document
+/// order; with more, batches from different threads interleave in the output.
+fn parse_with_build_stage<R: BufRead, M: MetricsCollector>(
+    reader: Reader<R>,
+    sender: Sender<RecordBatch>,
+    metrics: &M,
+    batch_size: usize,
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    hooks: &ParseHooks,
+) -> Result<()> {
+    let (entry_tx, entry_rx) = bounded::<ParsedEntry>(BUILD_CHANNEL_CAPACITY);
+    // Finished entries go back to the parser so their allocations are reused
+    let (recycle_tx, recycle_rx) = unbounded::<ParsedEntry>();
+    let failed = &CancelToken::new();
+
+    thread::scope(|scope| {
+        let builders: Vec<_> = (0..hooks.build_threads)
+            .map(|_| {
+                let (entry_rx, recycle_tx, sender) = (entry_rx.clone(), recycle_tx.clone(), sender.clone());
+                let sidecar_fasta = sidecar_fasta.clone();
+                scope.spawn(move || {
+                    run_builder(entry_rx, recycle_tx, sender, metrics, batch_size, sidecar_fasta, hooks, failed)
+                })
+            })
+            .collect();
+        drop((entry_rx, recycle_tx, sender));
+
+        let parsed = read_entries(reader, hooks, |scratch| {
+            if failed.is_cancelled() {
+                return Err(EtlError::ChannelSend);
+            }
+            if let Ok(spare) = recycle_rx.try_recv() {
+                scratch.recycle_entry(spare);
+            }
+            entry_tx.send(scratch.take_entry()).map_err(|_| EtlError::ChannelSend)
+        });
+        drop(entry_tx);
+
+        // A failed builder stops the parser (other builders keep the entry channel open),
+        // so report its error over the parser's send error
+        let built = builders
+            .into_iter()
+            .map(|handle| handle.join().expect("Build thread panicked"))
+            .fold(Ok(()), Result::and);
+        built.and(parsed)
+    })
+}
+
+/// Build thread body: transforms the entries of `entry_rx` into rows, batches them into
+/// `sender` and hands each finished entry back over `recycle_tx` for reuse. On error it
+/// sets `failed`, which stops the parser at the next entry.
+#[allow(clippy::too_many_arguments)]
+fn run_builder<M: MetricsCollector>(
+    entry_rx: Receiver<ParsedEntry>,
+    recycle_tx: Sender<ParsedEntry>,
+    sender: Sender<RecordBatch>,
+    metrics: &M,
+    batch_size: usize,
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    hooks: &ParseHooks,
+    failed: &CancelToken,
+) -> Result<()> {
+    build_entries(entry_rx, recycle_tx, sender, metrics, batch_size, sidecar_fasta, hooks)
+        .inspect_err(|_| failed.cancel())
+}
+
+fn build_entries<M: MetricsCollector>(
+    entry_rx: Receiver<ParsedEntry>,
+    recycle_tx: Sender<ParsedEntry>,
+    sender: Sender<RecordBatch>,
+    metrics: &M,
+    batch_size: usize,
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    hooks: &ParseHooks,
+) -> Result<()> {
+    let mut batcher = build_batcher(sender, metrics, batch_size, hooks)?;
+    let transformer = build_transformer(metrics, sidecar_fasta, hooks);
+    for entry in entry_rx {
+        let entry = Arc::new(entry);
+        for row in transformer.transform_shared(Arc::clone(&entry))? {
+            batcher.add_row(row)?;
+        }
+        if let Ok(entry) = Arc::try_unwrap(entry) {
+            let _ = recycle_tx.send(entry);
+        }
+    }
+    batcher.finish()
+}
+
+/// Parses one input into `senders.len()` shards: every entry goes to the shard its
+/// parent accession hashes to (`shard_of`), and each shard has its own build thread
+/// and batcher feeding its sender.
+///
+/// An accession lands in the same shard on every run, so a single large dump yields a
+/// stable sharded layout. `hooks.build_threads` is ignored; each shard builds on one thread.
+pub fn parse_entries_sharded<R: BufRead, M: MetricsCollector>(
+    reader: Reader<R>,
+    senders: Vec<Sender<RecordBatch>>,
+    metrics: &M,
+    batch_size: usize,
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    hooks: &ParseHooks,
+) -> Result<()> {
+    let shards = senders.len();
+    let (recycle_tx, recycle_rx) = unbounded::<ParsedEntry>();
+    let failed = &CancelToken::new();
+
+    thread::scope(|scope| {
+        let mut entry_txs = Vec::with_capacity(shards);
+        let mut builders = Vec::with_capacity(shards);
+        for sender in senders {
+            let (entry_tx, entry_rx) = bounded::<ParsedEntry>(BUILD_CHANNEL_CAPACITY);
+            entry_txs.push(entry_tx);
+            let (recycle_tx, sidecar_fasta) = (recycle_tx.clone(), sidecar_fasta.clone());
+            builders.push(scope.spawn(move || {
+                run_builder(entry_rx, recycle_tx, sender, metrics, batch_size, sidecar_fasta, hooks, failed)
+            }));
+        }
+        drop(recycle_tx);
+
+        let parsed = read_entries(reader, hooks, |scratch| {
+            if failed.is_cancelled() {
+                return Err(EtlError::ChannelSend);
+            }
+            if let Ok(spare) = recycle_rx.try_recv() {
+                scratch.recycle_entry(spare);
+            }
+            let entry = scratch.take_entry();
+            let key = if entry.parent_id.is_empty() { &entry.accession } else { &entry.parent_id };
+            entry_txs[shard_of(key, shards)]
+                .send(entry)
+                .map_err(|_| EtlError::ChannelSend)
+        });
+        drop(entry_txs);
+
+        // A failed shard builder stops the parser, so report its error over the parser's
+        // send error
+        let built = builders
+            .into_iter()
+            .map(|handle| handle.join().expect("Build thread panicked"))
+            .fold(Ok(()), Result::and);
+        built.and(parsed)
+    })
+}
+
+/// Shard (out of `shards`) of the entry with parent accession `parent_id`.
+///
+/// FNV-1a rather than `std`'s hasher, whose output may change between Rust releases:
+/// the layout must stay the same across runs and builds.
+pub fn shard_of(parent_id: &str, shards: usize) -> usize {
+    let hash = parent_id.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    (hash % shards.max(1) as u64) as usize
+}
+
+fn build_batcher<M: MetricsCollector>(
     sender: Sender<RecordBatch>,
     metrics: &M,
     batch_size: usize,
+    hooks: &ParseHooks,
+) -> Result<Batcher<M>> {
+    let batcher = Batcher::with_batch_size(sender, metrics.clone(), batch_size).with_enrichers(&hooks.enrichers)?;
+    let batcher = match &hooks.columns {
+        Some(columns) => batcher.with_columns(columns),
+        None => batcher.with_schema_profile(hooks.schema_profile),
+    };
+    Ok(batcher
+        .with_reject_log(hooks.rejects.clone())
+        .with_release(hooks.release.clone())
+        .with_tracer(hooks.trace.clone())
+        .with_ptm_vocabulary(hooks.ptm_vocabulary.clone())
+        .with_site_context(hooks.site_context)
+        .with_plddt(hooks.plddt.clone())
+        .with_domain_reconciliation(hooks.reconcile_domain_xrefs)
+        .with_debug_mapping(hooks.debug_mapping)
+        .with_flush_interval(hooks.flush_interval)
+        .with_pacer(hooks.pacer.clone())
+        .with_invariant_checks(hooks.check_invariants))
+}
+
+fn build_transformer<M: MetricsCollector>(
+    metrics: &M,
     sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    hooks: &ParseHooks,
+) -> EntryTransformer<M> {
+    EntryTransformer::new(metrics.clone(), sidecar_fasta)
+        .with_enrichers(hooks.enrichers.clone())
+        .with_reject_log(hooks.rejects.clone())
+        .with_strict_checksums(hooks.strict_checksums)
+        .with_tracer(hooks.trace.clone())
+        .with_dedup(hooks.dedup.clone())
+        .with_invalid_entries(hooks.invalid_entries)
+        .with_length_mismatch_downgrade(hooks.low_confidence_on_length_mismatch)
+        .with_unsequenced_isoforms(hooks.keep_unsequenced_isoforms)
+        .with_row_filter(hooks.row_filter.clone())
+}
+
+/// Reads the XML and calls `on_entry` once each sampled entry is complete in the scratch.
+fn read_entries<R: BufRead>(
+    mut reader: Reader<R>,
+    hooks: &ParseHooks,
+    mut on_entry: impl FnMut(&mut EntryScratch) -> Result<()>,
 ) -> Result<()> {
-    let mut batcher = Batcher::with_batch_size(sender, metrics.clone(), batch_size);
-    let transformer = EntryTransformer::new(metrics.clone(), sidecar_fasta);
     let mut scratch = EntryScratch::new();
+    scratch.feature_routing = hooks.feature_routing.clone();
     let mut buf = Vec::with_capacity(4096);
+    let (mut seen, mut kept) = (0usize, 0usize);
 
     loop {
         buf.clear();
-        match reader.read_event_into(&mut buf)? {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|err| located(err.into(), &reader, None));
+        match event? {
             Event::Start(e) if e.local_name().as_ref() == b"entry" => {
-                scratch.reset();
-                metadata::consume_entry(&mut reader, &mut scratch, &mut buf)?;
-                let entry = scratch.take_entry();
-                for row in transformer.transform(entry)? {
-                    batcher.add_row(row)?;
+                if hooks.sampling.is_full(kept) || hooks.cancel.is_cancelled() {
+                    break;
+                }
+                seen += 1;
+                if !hooks.sampling.keeps(seen - 1) {
+                    skip_element(&mut reader, b"entry", &mut buf).map_err(|err| located(err, &reader, None))?;
+                    continue;
                 }
+                kept += 1;
+
+                scratch.reset();
+                let started = metadata::handle_entry_start(&e, &mut scratch);
+                let trace_start = hooks.trace.is_enabled().then(|| e.into_owned());
+                started
+                    .and_then(|()| read_entry(&mut reader, trace_start, hooks, &mut scratch, &mut buf))
+                    .map_err(|err| located(err, &reader, Some(&scratch)))?;
+                on_entry(&mut scratch)?;
             }
             Event::Eof => break,
             _ => {}
         }
     }
+    Ok(())
+}
 
-    batcher.finish()?;
+/// Consumes the rest of an entry into `scratch`; with tracing, `trace_start` is its
+/// start tag and the entry is parsed from a captured copy so it can be replayed.
+fn read_entry<R: BufRead>(
+    reader: &mut Reader<R>,
+    trace_start: Option<BytesStart<'static>>,
+    hooks: &ParseHooks,
+    scratch: &mut EntryScratch,
+    buf: &mut Vec<u8>,
+) -> Result<()> {
+    let Some(start) = trace_start else {
+        return metadata::consume_entry(reader, scratch, buf);
+    };
+    let chunk = capture_element(reader, &start, buf)?;
+    let mut chunk_reader = Reader::from_reader(chunk.as_slice());
+    chunk_reader.config_mut().trim_text(true);
+    chunk_reader.read_event_into(buf)?;
+    metadata::consume_entry(&mut chunk_reader, scratch, buf)?;
+    hooks.trace.trace_entry(&chunk, &scratch.entry);
     Ok(())
 }
+
+/// Attaches the entry being parsed (if any), its element path and the reader's byte
+/// offset to a parse error, so a failure deep into a large file can be located.
+fn located<R>(err: EtlError, reader: &Reader<R>, scratch: Option<&EntryScratch>) -> EtlError {
+    let accession = scratch
+        .map(|s| s.entry.accession.as_str())
+        .filter(|a| !a.is_empty())
+        .unwrap_or("unknown");
+    EtlError::Located {
+        accession: accession.to_string(),
+        path: scratch.map_or_else(|| "(top level)".to_string(), |s| s.state.to_string()),
+        position: reader.buffer_position(),
+        source: Box::new(err),
+    }
+}
@@ -0,0 +1,272 @@
+//! AlphaFold per-residue confidence (pLDDT) from a local model directory.
+//!
+//! `transform.alphafold_dir` points at AlphaFold DB downloads keyed by accession:
+//! `AF-<ACC>-F1-confidence_v4.json`, `AF-<ACC>-F1-model_v4.cif` or `.pdb` (optionally
+//! gzipped), or the same contents named `<ACC>.json` / `.cif` / `.pdb`. The directory is
+//! indexed once; files are read when their entry is built, so a proteome-sized directory
+//! costs one path per accession in memory. When several files exist for an accession the
+//! confidence JSON wins over mmCIF over PDB. Only the first fragment (`F1`) is used, so
+//! proteins AlphaFold splits into overlapping fragments get no values.
+//!
+//! Models cover the canonical sequence; `EntryBuilders` applies them to canonical rows
+//! whose length matches the model.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use flate2::read::MultiGzDecoder;
+
+use crate::error::{EtlError, Result};
+
+/// Model file formats, in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ModelFormat {
+    Json,
+    Cif,
+    Pdb,
+}
+
+/// Per-residue pLDDT of one model; index 0 is residue 1. Residues the model lacks are None.
+pub type Plddt = Vec<Option<f32>>;
+
+/// Accession -> model file index of an AlphaFold directory (empty when not configured).
+#[derive(Clone, Default)]
+pub struct PlddtSource {
+    files: Arc<HashMap<String, (ModelFormat, PathBuf)>>,
+}
+
+impl PlddtSource {
+    /// Indexes the model files of `dir`.
+    pub fn open(dir: &Path) -> Result<Self> {
+        let mut files: HashMap<String, (ModelFormat, PathBuf)> = HashMap::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some((accession, format)) = path.file_name().and_then(|n| n.to_str()).and_then(model_key) else {
+                continue;
+            };
+            match files.get(&accession) {
+                Some((existing, _)) if *existing <= format => {}
+                _ => {
+                    files.insert(accession, (format, path));
+                }
+            }
+        }
+        Ok(Self { files: Arc::new(files) })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Accessions with a model.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// pLDDT of `accession`'s model, or None without one.
+    pub fn load(&self, accession: &str) -> Result<Option<Plddt>> {
+        let Some((format, path)) = self.files.get(accession) else {
+            return Ok(None);
+        };
+        let text = read_maybe_gzipped(path)?;
+        let plddt = match format {
+            ModelFormat::Json => parse_confidence_json(&text),
+            ModelFormat::Cif => parse_cif(&text),
+            ModelFormat::Pdb => Ok(parse_pdb(&text)),
+        }
+        .map_err(|e| EtlError::InvalidPlddt(format!("{}: {}", path.display(), e)))?;
+        Ok(Some(plddt))
+    }
+}
+
+/// Mean of the residues the model has values for.
+pub fn mean_plddt(plddt: &[Option<f32>]) -> Option<f32> {
+    let values: Vec<f32> = plddt.iter().flatten().copied().collect();
+    (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+}
+
+/// (accession, format) of a model file name.
+fn model_key(name: &str) -> Option<(String, ModelFormat)> {
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    let (stem, format) = if let Some(stem) = name.strip_suffix(".json") {
+        (stem, ModelFormat::Json)
+    } else if let Some(stem) = name.strip_suffix(".cif") {
+        (stem, ModelFormat::Cif)
+    } else if let Some(stem) = name.strip_suffix(".pdb") {
+        (stem, ModelFormat::Pdb)
+    } else {
+        return None;
+    };
+    let accession = match stem.strip_prefix("AF-") {
+        // AF-P04637-F1-model_v4 / AF-P04637-F1-confidence_v4
+        Some(rest) => {
+            let (accession, rest) = rest.split_once('-')?;
+            if !rest.starts_with("F1-") {
+                return None;
+            }
+            let kind = rest["F1-".len()..].split('_').next()?;
+            let expected = if format == ModelFormat::Json { "confidence" } else { "model" };
+            if kind != expected {
+                return None;
+            }
+            accession
+        }
+        None => stem,
+    };
+    Some((accession.to_string(), format))
+}
+
+fn read_maybe_gzipped(path: &Path) -> Result<String> {
+    let mut text = String::new();
+    let file = File::open(path)?;
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        MultiGzDecoder::new(file).read_to_string(&mut text)?;
+    } else {
+        let mut file = file;
+        file.read_to_string(&mut text)?;
+    }
+    Ok(text)
+}
+
+/// Values indexed by 1-based residue number.
+fn by_residue(values: impl IntoIterator<Item = (usize, f32)>) -> Plddt {
+    let mut plddt: Plddt = Vec::new();
+    for (residue, value) in values {
+        if residue == 0 {
+            continue;
+        }
+        if plddt.len() < residue {
+            plddt.resize(residue, None);
+        }
+        plddt[residue - 1] = Some(value);
+    }
+    plddt
+}
+
+/// AlphaFold DB confidence JSON: `{"residueNumber": [...], "confidenceScore": [...]}`,
+/// or a one-element array of it (v4).
+fn parse_confidence_json(text: &str) -> std::result::Result<Plddt, String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let object = match &value {
+        serde_json::Value::Array(items) => items.first().ok_or("empty array")?,
+        other => other,
+    };
+    let numbers = |key: &str| -> std::result::Result<Vec<f64>, String> {
+        object
+            .get(key)
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| format!("missing {}", key))?
+            .iter()
+            .map(|v| v.as_f64().ok_or_else(|| format!("non-numeric {}", key)))
+            .collect()
+    };
+    let scores = numbers("confidenceScore")?;
+    let residues = match numbers("residueNumber") {
+        Ok(residues) if residues.len() == scores.len() => residues,
+        Ok(_) => return Err("residueNumber and confidenceScore differ in length".to_string()),
+        // Scores alone are residues 1..n
+        Err(_) => (1..=scores.len()).map(|r| r as f64).collect(),
+    };
+    Ok(by_residue(residues.iter().zip(&scores).map(|(&r, &s)| (r as usize, s as f32))))
+}
+
+/// PDB: the B-factor column of each residue's CA atom holds its pLDDT.
+fn parse_pdb(text: &str) -> Plddt {
+    by_residue(text.lines().filter(|line| line.starts_with("ATOM")).filter_map(|line| {
+        if line.get(12..16)?.trim() != "CA" {
+            return None;
+        }
+        let residue = line.get(22..26)?.trim().parse().ok()?;
+        let b_factor = line.get(60..66)?.trim().parse().ok()?;
+        Some((residue, b_factor))
+    }))
+}
+
+/// mmCIF: `B_iso_or_equiv` of the CA rows of the `_atom_site` loop.
+fn parse_cif(text: &str) -> std::result::Result<Plddt, String> {
+    let mut columns: Vec<&str> = Vec::new();
+    let mut values = Vec::new();
+    let mut in_atom_site = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(column) = line.strip_prefix("_atom_site.") {
+            in_atom_site = true;
+            columns.push(column.trim());
+            continue;
+        }
+        if !in_atom_site {
+            continue;
+        }
+        if line.starts_with('_') || line.starts_with("loop_") || line.starts_with('#') {
+            break;
+        }
+        values.push(line);
+    }
+    let index = |name: &str| {
+        columns
+            .iter()
+            .position(|c| *c == name)
+            .ok_or_else(|| format!("_atom_site has no {} column", name))
+    };
+    let (atom, residue, b_factor) = (index("label_atom_id")?, index("label_seq_id")?, index("B_iso_or_equiv")?);
+    Ok(by_residue(values.iter().filter_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != columns.len() || fields[atom] != "CA" {
+            return None;
+        }
+        Some((fields[residue].parse().ok()?, fields[b_factor].parse().ok()?))
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_plddt_from_every_model_format() {
+        let json = r#"[{"residueNumber":[1,2,3],"confidenceScore":[90.5,70.0,45.25],"confidenceCategory":["H","L","D"]}]"#;
+        assert_eq!(parse_confidence_json(json).unwrap(), [Some(90.5), Some(70.0), Some(45.25)]);
+
+        let pdb = "\
+ATOM      1  N   MET A   1      -1.000   2.000   3.000  1.00 90.50           N
+ATOM      2  CA  MET A   1      -1.000   2.000   3.000  1.00 90.50           C
+ATOM      3  CA  SER A   3      -1.000   2.000   3.000  1.00 45.25           C
+";
+        assert_eq!(parse_pdb(pdb), [Some(90.5), None, Some(45.25)]);
+
+        let cif = "\
+data_AF
+loop_
+_atom_site.group_PDB
+_atom_site.id
+_atom_site.label_atom_id
+_atom_site.label_seq_id
+_atom_site.B_iso_or_equiv
+ATOM 1 N 1 90.50
+ATOM 2 CA 1 90.50
+ATOM 3 CA 2 70.00
+#
+";
+        assert_eq!(parse_cif(cif).unwrap(), [Some(90.5), Some(70.0)]);
+        assert_eq!(mean_plddt(&[Some(90.0), None, Some(70.0)]), Some(80.0));
+    }
+
+    #[test]
+    fn indexes_first_fragments_by_accession() {
+        assert_eq!(
+            model_key("AF-P04637-F1-model_v4.cif.gz"),
+            Some(("P04637".to_string(), ModelFormat::Cif))
+        );
+        assert_eq!(
+            model_key("AF-P04637-F1-confidence_v4.json"),
+            Some(("P04637".to_string(), ModelFormat::Json))
+        );
+        assert_eq!(model_key("AF-Q8WZ42-F2-model_v4.pdb"), None);
+        assert_eq!(model_key("AF-P04637-F1-predicted_aligned_error_v4.json"), None);
+        assert_eq!(model_key("P12345.pdb"), Some(("P12345".to_string(), ModelFormat::Pdb)));
+        assert_eq!(model_key("notes.txt"), None);
+    }
+}
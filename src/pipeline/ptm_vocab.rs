@@ -0,0 +1,372 @@
+//! PTM controlled vocabulary and hierarchical `mod_type` codes.
+//!
+//! Terms are read from UniProt's ptmlist.txt, whose `ID` lines are exactly the descriptions
+//! used on modified residue, glycosylation, lipidation and cross-link features, so a feature
+//! is identified by looking its description up (see `PtmVocabulary::term`). A subset
+//! covering the common modifications is embedded (resources/ptmlist.txt);
+//! `transform.ptm_vocabulary_path` replaces it with a full copy.
+//!
+//! `mod_type` packs three levels into one integer, `class * 10_000 + subclass * 100 + residue`:
+//! - class: the feature key (see `CLASSES`), e.g. 1 = modified residue
+//! - subclass: the term's most specific keyword (see `SUBCLASSES`), e.g. 1 = phosphorylation
+//! - residue: 1-based position of the modified amino acid in `RESIDUES`
+//!
+//! Any level is 0 when unknown, so phosphoserine is 10116 and 0 means unclassified.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use crate::error::{EtlError, Result};
+
+const EMBEDDED_PTMLIST: &str = include_str!("../../resources/ptmlist.txt");
+
+/// Modification classes: (ptmlist `FT` key, feature type, label).
+pub const CLASSES: [(&str, &str, &str); 4] = [
+    ("MOD_RES", "modified residue", "modified_residue"),
+    ("CARBOHYD", "glycosylation site", "glycosylation"),
+    ("LIPID", "lipid moiety-binding region", "lipidation"),
+    ("CROSSLNK", "cross-link", "cross_link"),
+];
+
+/// Subclasses: (ptmlist `KW` keyword, label). Ordered most specific first; codes are
+/// positions in this list, so new entries are only ever appended.
+pub const SUBCLASSES: [(&str, &str); 24] = [
+    ("Phosphoprotein", "phosphorylation"),
+    ("Acetylation", "acetylation"),
+    ("Methylation", "methylation"),
+    ("Hydroxylation", "hydroxylation"),
+    ("Glycoprotein", "glycosylation"),
+    ("Palmitate", "palmitoylation"),
+    ("Myristate", "myristoylation"),
+    ("Prenylation", "prenylation"),
+    ("GPI-anchor", "gpi_anchor"),
+    ("Lipoprotein", "lipidation"),
+    ("Ubl conjugation", "ubl_conjugation"),
+    ("Isopeptide bond", "isopeptide"),
+    ("Citrullination", "citrullination"),
+    ("ADP-ribosylation", "adp_ribosylation"),
+    ("Amidation", "amidation"),
+    ("Sulfation", "sulfation"),
+    ("Pyrrolidone carboxylic acid", "pyroglutamate"),
+    ("Nitration", "nitration"),
+    ("S-nitrosylation", "s_nitrosylation"),
+    ("Oxidation", "oxidation"),
+    ("Gamma-carboxyglutamic acid", "gamma_carboxylation"),
+    ("Hypusine", "hypusine"),
+    ("Thioester bond", "thioester"),
+    ("Formylation", "formylation"),
+];
+
+/// One-letter codes of the residues a modification can target.
+pub const RESIDUES: &str = "ACDEFGHIKLMNPQRSTVWYUO";
+
+const RESIDUE_NAMES: [(&str, char); 22] = [
+    ("Alanine", 'A'),
+    ("Cysteine", 'C'),
+    ("Aspartate", 'D'),
+    ("Glutamate", 'E'),
+    ("Phenylalanine", 'F'),
+    ("Glycine", 'G'),
+    ("Histidine", 'H'),
+    ("Isoleucine", 'I'),
+    ("Lysine", 'K'),
+    ("Leucine", 'L'),
+    ("Methionine", 'M'),
+    ("Asparagine", 'N'),
+    ("Proline", 'P'),
+    ("Glutamine", 'Q'),
+    ("Arginine", 'R'),
+    ("Serine", 'S'),
+    ("Threonine", 'T'),
+    ("Valine", 'V'),
+    ("Tryptophan", 'W'),
+    ("Tyrosine", 'Y'),
+    ("Selenocysteine", 'U'),
+    ("Pyrrolysine", 'O'),
+];
+
+/// Decoded hierarchical `mod_type`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModType {
+    pub class: u8,
+    pub subclass: u8,
+    pub residue: u8,
+}
+
+impl ModType {
+    /// Classifies a PTM feature at residue `aa`: class and subclass from its vocabulary
+    /// `term`, or only the class (from the feature type) when the term is unknown.
+    pub fn classify(term: Option<&PtmTerm>, feature_type_lower: &str, aa: u8) -> Self {
+        let class = term
+            .map(PtmTerm::class)
+            .filter(|&class| class > 0)
+            .unwrap_or_else(|| class_for_feature_type(feature_type_lower));
+        Self {
+            class,
+            subclass: term.map_or(0, PtmTerm::subclass),
+            residue: residue_code(aa),
+        }
+    }
+
+    pub fn code(self) -> i32 {
+        self.class as i32 * 10_000 + self.subclass as i32 * 100 + self.residue as i32
+    }
+
+    pub fn from_code(code: i32) -> Self {
+        let code = code.max(0);
+        Self {
+            class: (code / 10_000).min(u8::MAX as i32) as u8,
+            subclass: (code / 100 % 100) as u8,
+            residue: (code % 100) as u8,
+        }
+    }
+
+    pub fn class_name(self) -> Option<&'static str> {
+        CLASSES.get((self.class as usize).checked_sub(1)?).map(|c| c.2)
+    }
+
+    pub fn subclass_name(self) -> Option<&'static str> {
+        SUBCLASSES.get((self.subclass as usize).checked_sub(1)?).map(|s| s.1)
+    }
+
+    pub fn residue(self) -> Option<char> {
+        RESIDUES.chars().nth((self.residue as usize).checked_sub(1)?)
+    }
+
+    /// Most specific name: the subclass, else the class, else "other".
+    pub fn label(self) -> &'static str {
+        self.subclass_name().or(self.class_name()).unwrap_or("other")
+    }
+}
+
+/// Class code of a UniProt feature type (lowercase), 0 if it is not a PTM feature.
+pub fn class_for_feature_type(feature_type_lower: &str) -> u8 {
+    position(CLASSES.iter().map(|c| c.1), feature_type_lower)
+}
+
+/// Residue code of a one-letter amino acid, 0 if not in `RESIDUES`.
+pub fn residue_code(aa: u8) -> u8 {
+    position(RESIDUES.bytes(), aa.to_ascii_uppercase())
+}
+
+fn position<T: PartialEq>(mut items: impl Iterator<Item = T>, item: T) -> u8 {
+    items.position(|i| i == item).map_or(0, |i| i as u8 + 1)
+}
+
+/// One ptmlist.txt entry.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PtmTerm {
+    /// `ID`: the feature description the term is used as
+    pub name: String,
+    /// `AC`: stable accession (PTM-xxxx)
+    pub accession: Option<String>,
+    /// `FT`: feature key (MOD_RES, CARBOHYD, LIPID, CROSSLNK, ...)
+    pub feature_key: String,
+    /// `TG`: modified amino acid (first one for cross-links); None if undefined
+    pub target: Option<char>,
+    /// `MM`: monoisotopic mass difference in Da
+    pub monoisotopic_mass: Option<f64>,
+    /// `MA`: average mass difference in Da
+    pub average_mass: Option<f64>,
+    /// `KW`: UniProt keywords, without the trailing period
+    pub keywords: Vec<String>,
+}
+
+impl PtmTerm {
+    fn class(&self) -> u8 {
+        position(CLASSES.iter().map(|c| c.0), self.feature_key.as_str())
+    }
+
+    fn subclass(&self) -> u8 {
+        SUBCLASSES
+            .iter()
+            .position(|(keyword, _)| self.keywords.iter().any(|k| k == keyword))
+            .map_or(0, |i| i as u8 + 1)
+    }
+}
+
+/// Parsed vocabulary; clones share the terms.
+#[derive(Debug, Clone)]
+pub struct PtmVocabulary(Arc<Terms>);
+
+#[derive(Debug, Default)]
+struct Terms {
+    terms: Vec<PtmTerm>,
+    by_name: HashMap<String, usize>,
+}
+
+impl Default for PtmVocabulary {
+    fn default() -> Self {
+        Self::embedded()
+    }
+}
+
+impl PtmVocabulary {
+    /// The vocabulary shipped with the crate.
+    pub fn embedded() -> Self {
+        static EMBEDDED: OnceLock<PtmVocabulary> = OnceLock::new();
+        EMBEDDED
+            .get_or_init(|| Self::parse(EMBEDDED_PTMLIST).expect("embedded ptmlist.txt is valid"))
+            .clone()
+    }
+
+    /// Reads a ptmlist.txt file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    /// Parses ptmlist.txt content. Anything before the first `ID` line is documentation
+    /// and ignored; each entry ends at a `//` line.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut terms = Terms::default();
+        let mut current: Option<PtmTerm> = None;
+        for (line_no, line) in text.lines().enumerate() {
+            if line.starts_with("//") {
+                if let Some(term) = current.take() {
+                    terms.insert(term);
+                }
+                continue;
+            }
+            let (Some(code), Some(value)) = (line.get(..2), line.get(5..)) else {
+                continue;
+            };
+            if !line[2..5].chars().all(|c| c == ' ') {
+                continue;
+            }
+            let value = value.trim();
+            if code == "ID" {
+                if current.is_some() {
+                    return Err(EtlError::InvalidPtmVocabulary(format!(
+                        "line {}: ID {:?} starts before the previous entry's //",
+                        line_no + 1,
+                        value
+                    )));
+                }
+                current = Some(PtmTerm {
+                    name: value.to_string(),
+                    ..PtmTerm::default()
+                });
+                continue;
+            }
+            let Some(term) = current.as_mut() else {
+                continue;
+            };
+            match code {
+                "AC" => term.accession = Some(value.to_string()),
+                "FT" => term.feature_key = value.to_string(),
+                "TG" => term.target = parse_target(value),
+                "MM" => term.monoisotopic_mass = parse_mass(value, line_no)?,
+                "MA" => term.average_mass = parse_mass(value, line_no)?,
+                "KW" => term.keywords.push(value.trim_end_matches('.').to_string()),
+                _ => {}
+            }
+        }
+        if let Some(term) = current {
+            return Err(EtlError::InvalidPtmVocabulary(format!("entry {:?} has no closing //", term.name)));
+        }
+        Ok(Self(Arc::new(terms)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.terms.is_empty()
+    }
+
+    /// The term a feature description names. Matching ignores case, qualifiers after
+    /// ";" ("Phosphoserine; by PKA", "...; alternate") and a "(Microbial infection)" prefix.
+    pub fn term(&self, description: &str) -> Option<&PtmTerm> {
+        self.0.by_name.get(&term_key(description)).map(|&i| &self.0.terms[i])
+    }
+}
+
+impl Terms {
+    fn insert(&mut self, term: PtmTerm) {
+        self.by_name.insert(term_key(&term.name), self.terms.len());
+        self.terms.push(term);
+    }
+}
+
+/// Lookup key of a term name or feature description.
+fn term_key(description: &str) -> String {
+    let name = description.split(';').next().unwrap_or_default().trim();
+    let name = name.strip_prefix("(Microbial infection)").unwrap_or(name);
+    name.trim().to_lowercase()
+}
+
+fn parse_mass(value: &str, line_no: usize) -> Result<Option<f64>> {
+    value.parse().map(Some).map_err(|_| {
+        EtlError::InvalidPtmVocabulary(format!("line {}: invalid mass {:?}", line_no + 1, value))
+    })
+}
+
+/// One-letter code of a `TG` value ("Serine.", "Lysine-Glycine.", "Undefined.").
+fn parse_target(value: &str) -> Option<char> {
+    let name = value.trim_end_matches('.').split('-').next()?.trim();
+    RESIDUE_NAMES
+        .iter()
+        .find(|(residue, _)| residue.eq_ignore_ascii_case(name))
+        .map(|&(_, aa)| aa)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_entries_and_skips_the_header() {
+        let text = "Header text\n  ID    Identifier\n______\nID   Phosphoserine\nAC   PTM-0001\nFT   MOD_RES\nTG   Serine.\nMM   79.97\nMA   79.98\nKW   Phosphoprotein.\n//\nID   Glycyl lysine isopeptide (Lys-Gly) (interchain with G-Cter in ubiquitin)\nFT   CROSSLNK\nTG   Lysine-Glycine.\nKW   Isopeptide bond.\nKW   Ubl conjugation.\n//\n";
+        let vocabulary = PtmVocabulary::parse(text).unwrap();
+        assert_eq!(vocabulary.len(), 2);
+
+        let term = vocabulary.term("Phosphoserine; by CK2").unwrap();
+        assert_eq!(term.accession.as_deref(), Some("PTM-0001"));
+        assert_eq!(term.monoisotopic_mass, Some(79.97));
+        assert_eq!(term.average_mass, Some(79.98));
+        assert_eq!(term.feature_key, "MOD_RES");
+        assert_eq!(term.target, Some('S'));
+        assert_eq!(term.keywords, ["Phosphoprotein"]);
+
+        let cross_link = vocabulary
+            .term("Glycyl lysine isopeptide (Lys-Gly) (interchain with G-Cter in ubiquitin)")
+            .unwrap();
+        assert_eq!(cross_link.target, Some('K'));
+        // The more specific keyword wins
+        assert_eq!(SUBCLASSES[cross_link.subclass() as usize - 1].1, "ubl_conjugation");
+    }
+
+    #[test]
+    fn rejects_unterminated_entries() {
+        assert!(PtmVocabulary::parse("ID   Phosphoserine\nFT   MOD_RES\n").is_err());
+        assert!(PtmVocabulary::parse("ID   A\nID   B\n//\n").is_err());
+        assert!(PtmVocabulary::parse("ID   A\nMM   heavy\n//\n").is_err());
+    }
+
+    #[test]
+    fn encodes_class_subclass_and_residue() {
+        let vocabulary = PtmVocabulary::embedded();
+        assert!(!vocabulary.is_empty());
+
+        let term = vocabulary.term("(Microbial infection) phosphoserine; alternate");
+        let phosphoserine = ModType::classify(term, "modified residue", b'S');
+        assert_eq!(phosphoserine.code(), 10116);
+        assert_eq!(ModType::from_code(10116), phosphoserine);
+        assert_eq!(phosphoserine.class_name(), Some("modified_residue"));
+        assert_eq!(phosphoserine.label(), "phosphorylation");
+        assert_eq!(phosphoserine.residue(), Some('S'));
+
+        let term = vocabulary.term("N-linked (GlcNAc...) asparagine");
+        let glycan = ModType::classify(term, "glycosylation site", b'N');
+        assert_eq!((glycan.class, glycan.label()), (2, "glycosylation"));
+
+        // Unknown description: class from the feature type, no subclass
+        let unknown = ModType::classify(vocabulary.term("Something new"), "modified residue", b'K');
+        assert_eq!(unknown.label(), "modified_residue");
+        assert_eq!(ModType::default().label(), "other");
+        assert_eq!(ModType::from_code(0).code(), 0);
+    }
+}
@@ -1,12 +1,13 @@
-use flate2::read::GzDecoder;
+use flate2::read::MultiGzDecoder;
 use quick_xml::Reader;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 
 use crate::config::Settings;
 use crate::error::Result;
 use crate::metrics::MetricsCollector;
+use crate::pipeline::bgzf::{is_bgzf, BgzfReader};
 
 pub type XmlReader<R> = Reader<R>;
 
@@ -42,7 +43,9 @@ impl<R: BufRead, M: MetricsCollector> BufRead for TrackedReader<R, M> {
 }
 
 /// Creates an XML reader from a file path.
-/// Automatically detects .gz files and applies gzip decompression.
+/// Automatically detects .gz files and applies gzip decompression: every member of a
+/// multi-member gzip is read, and BGZF files are inflated on
+/// `performance.decompress_threads` threads when that is set.
 /// Uses buffer size from Settings.
 /// Tracks bytes read via the provided Metrics.
 pub fn create_xml_reader<M: MetricsCollector>(
@@ -50,13 +53,19 @@ pub fn create_xml_reader<M: MetricsCollector>(
     settings: &Settings,
     metrics: &M,
 ) -> Result<XmlReader<TrackedReader<Box<dyn BufRead + Send>, M>>> {
-    let file = File::open(path)?;
+    let mut file = File::open(path)?;
     let buf_size = settings.performance.buffer_size;
+    let threads = settings.performance.decompress_threads;
 
     let reader: Box<dyn BufRead + Send> = if path.extension().is_some_and(|ext| ext == "gz") {
-        // Gzipped file: File -> GzDecoder -> BufReader
-        let decoder = GzDecoder::new(file);
-        Box::new(BufReader::with_capacity(buf_size, decoder))
+        if threads > 0 && starts_with_bgzf_block(&mut file)? {
+            // BGZF: File -> block splitter -> worker pool -> BufReader
+            Box::new(BufReader::with_capacity(buf_size, BgzfReader::new(file, threads)))
+        } else {
+            // Gzipped file: File -> MultiGzDecoder -> BufReader
+            let decoder = MultiGzDecoder::new(file);
+            Box::new(BufReader::with_capacity(buf_size, decoder))
+        }
     } else {
         // Plain XML: File -> BufReader
         Box::new(BufReader::with_capacity(buf_size, file))
@@ -69,3 +78,11 @@ pub fn create_xml_reader<M: MetricsCollector>(
 
     Ok(xml_reader)
 }
+
+/// Peeks at the first block header and rewinds.
+fn starts_with_bgzf_block(file: &mut File) -> Result<bool> {
+    let mut header = Vec::with_capacity(64);
+    file.by_ref().take(64).read_to_end(&mut header)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(is_bgzf(&header))
+}
@@ -0,0 +1,80 @@
+//! Side channel for rows and features dropped during transformation.
+//!
+//! Every drop site also logs to stderr; the `RejectLog` additionally hands the
+//! identifiers and reason to a writer so completeness audits can query them.
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+/// Granularity of a rejection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectKind {
+    /// A whole output row was not emitted
+    Row,
+    /// A single feature/PTM was left out of an emitted row
+    Feature,
+}
+
+impl RejectKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RejectKind::Row => "row",
+            RejectKind::Feature => "feature",
+        }
+    }
+}
+
+/// One dropped row or feature and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    pub parent_id: String,
+    pub id: String,
+    pub kind: RejectKind,
+    /// Stable reason code (matches the `code=` in the stderr log line)
+    pub code: &'static str,
+    pub detail: Option<String>,
+}
+
+/// Cheap, cloneable handle for recording rejections; a no-op when disabled.
+#[derive(Debug, Clone, Default)]
+pub struct RejectLog {
+    tx: Option<Sender<Rejection>>,
+}
+
+impl RejectLog {
+    /// Handle that discards everything (the default).
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Enabled handle plus the receiver a writer drains.
+    pub fn channel() -> (Self, Receiver<Rejection>) {
+        let (tx, rx) = unbounded();
+        (Self { tx: Some(tx) }, rx)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.tx.is_some()
+    }
+
+    /// Records a rejection; `detail` is only evaluated when the log is enabled.
+    pub fn record(
+        &self,
+        kind: RejectKind,
+        code: &'static str,
+        parent_id: &str,
+        id: &str,
+        detail: impl FnOnce() -> Option<String>,
+    ) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        // A closed receiver means the writer gave up; the stderr log still has the record
+        let _ = tx.send(Rejection {
+            parent_id: parent_id.to_string(),
+            id: id.to_string(),
+            kind,
+            code,
+            detail: detail(),
+        });
+    }
+}
@@ -0,0 +1,495 @@
+//! Row filters (`filters.row_expr`).
+//!
+//! A small expression language over the flat fields of a transformed row, evaluated
+//! before the row is built, so common subsets (one organism, reviewed entries, ...)
+//! are extracted during the run rather than by post-processing the output:
+//!
+//! ```text
+//! organism_id == 9606 && existence <= 2
+//! dataset == "Swiss-Prot" && !(is_fragment || gene_name == null)
+//! ```
+//!
+//! Comparisons are `field op literal` with `==`, `!=`, `<`, `<=`, `>` or `>=`, combined
+//! with `&&`, `||`, `!` and parentheses; a boolean field on its own is `field == true`.
+//! Literals are integers, quoted strings, `true`/`false` and `null`. Integer fields
+//! compare numerically, string fields lexicographically. A comparison with a missing
+//! value is false, except `== null` and `!= null`. Unknown fields and type mismatches
+//! are rejected when the expression is parsed, not per row.
+
+use std::cmp::Ordering;
+
+use crate::error::{EtlError, Result};
+use crate::pipeline::transformer::{IsoformStatus, TransformedRow};
+
+/// Flat row fields an expression can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Id,
+    ParentId,
+    EntryName,
+    GeneName,
+    ProteinName,
+    OrganismName,
+    Dataset,
+    GeneLocation,
+    IsoformStatus,
+    OrganismId,
+    Existence,
+    Version,
+    SequenceLength,
+    IsoformCount,
+    IsFragment,
+    IsPrecursor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Int,
+    Str,
+    Bool,
+}
+
+impl Field {
+    const ALL: [(&'static str, Field); 16] = [
+        ("id", Field::Id),
+        ("parent_id", Field::ParentId),
+        ("entry_name", Field::EntryName),
+        ("gene_name", Field::GeneName),
+        ("protein_name", Field::ProteinName),
+        ("organism_name", Field::OrganismName),
+        ("dataset", Field::Dataset),
+        ("gene_location", Field::GeneLocation),
+        ("isoform_status", Field::IsoformStatus),
+        ("organism_id", Field::OrganismId),
+        ("existence", Field::Existence),
+        ("version", Field::Version),
+        ("sequence_length", Field::SequenceLength),
+        ("isoform_count", Field::IsoformCount),
+        ("is_fragment", Field::IsFragment),
+        ("is_precursor", Field::IsPrecursor),
+    ];
+
+    fn from_name(name: &str) -> Option<Field> {
+        Self::ALL.iter().find(|(n, _)| *n == name).map(|&(_, field)| field)
+    }
+
+    fn kind(self) -> Kind {
+        match self {
+            Field::OrganismId | Field::Existence | Field::Version | Field::SequenceLength | Field::IsoformCount => {
+                Kind::Int
+            }
+            Field::IsFragment | Field::IsPrecursor => Kind::Bool,
+            _ => Kind::Str,
+        }
+    }
+
+    fn value(self, row: &TransformedRow) -> Value<'_> {
+        let entry = &row.entry;
+        match self {
+            Field::Id => Value::Str(&row.row_id),
+            Field::ParentId => Value::Str(&row.parent_id),
+            Field::EntryName => text(&entry.entry_name),
+            Field::GeneName => text(&entry.gene_name),
+            Field::ProteinName => text(&entry.protein_name),
+            Field::OrganismName => text(&entry.organism_scientific_name),
+            Field::Dataset => text(&entry.dataset),
+            Field::GeneLocation => text(&entry.gene_location),
+            Field::IsoformStatus => Value::Str(row.isoform_status.as_str()),
+            Field::OrganismId => entry.organism_id.map_or(Value::Null, |id| Value::Int(id as i64)),
+            // 0 is "unknown", written as null
+            Field::Existence => match entry.existence {
+                0 => Value::Null,
+                level => Value::Int(level as i64),
+            },
+            Field::Version => entry.version.map_or(Value::Null, |v| Value::Int(v as i64)),
+            Field::SequenceLength => match row.isoform_status {
+                IsoformStatus::Unsequenced => Value::Null,
+                _ => Value::Int(row.sequence.len() as i64),
+            },
+            Field::IsoformCount => Value::Int(entry.isoforms.len() as i64),
+            Field::IsFragment => Value::Bool(entry.fragment.is_some()),
+            Field::IsPrecursor => Value::Bool(entry.precursor),
+        }
+    }
+}
+
+fn text(value: &Option<String>) -> Value<'_> {
+    value.as_deref().map_or(Value::Null, Value::Str)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value<'a> {
+    Null,
+    Int(i64),
+    Str(&'a str),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Literal {
+    Null,
+    Int(i64),
+    Str(String),
+    Bool(bool),
+}
+
+impl Literal {
+    fn kind(&self) -> Option<Kind> {
+        match self {
+            Literal::Null => None,
+            Literal::Int(_) => Some(Kind::Int),
+            Literal::Str(_) => Some(Kind::Str),
+            Literal::Bool(_) => Some(Kind::Bool),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering.is_eq(),
+            Op::Ne => ordering.is_ne(),
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+            Op::Gt => ordering.is_gt(),
+            Op::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Compare { field: Field, op: Op, literal: Literal },
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, row: &TransformedRow) -> bool {
+        match self {
+            Expr::Compare { field, op, literal } => compare(field.value(row), *op, literal),
+            Expr::Not(inner) => !inner.eval(row),
+            Expr::And(a, b) => a.eval(row) && b.eval(row),
+            Expr::Or(a, b) => a.eval(row) || b.eval(row),
+        }
+    }
+}
+
+fn compare(value: Value<'_>, op: Op, literal: &Literal) -> bool {
+    let ordering = match (value, literal) {
+        (Value::Null, Literal::Null) => return op == Op::Eq,
+        (_, Literal::Null) => return op == Op::Ne,
+        (Value::Null, _) => return false,
+        (Value::Int(a), Literal::Int(b)) => a.cmp(b),
+        (Value::Str(a), Literal::Str(b)) => a.cmp(b.as_str()),
+        (Value::Bool(a), Literal::Bool(b)) => a.cmp(b),
+        // Kinds are checked at parse time
+        _ => return false,
+    };
+    op.holds(ordering)
+}
+
+/// A parsed `filters.row_expr`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowFilter {
+    expr: Expr,
+}
+
+impl RowFilter {
+    /// Parses `source`, failing with `EtlError::InvalidRowFilter` on a syntax error, an
+    /// unknown field or a literal of the wrong type.
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some((token, at)) = parser.tokens.get(parser.pos) {
+            return Err(invalid(*at, format!("unexpected {}", token.describe())));
+        }
+        Ok(Self { expr })
+    }
+
+    /// Whether `row` is kept.
+    pub fn matches(&self, row: &TransformedRow) -> bool {
+        self.expr.eval(row)
+    }
+}
+
+fn invalid(at: usize, detail: String) -> EtlError {
+    EtlError::InvalidRowFilter(format!("{} at offset {}", detail, at))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("'{}'", name),
+            Token::Int(value) => format!("'{}'", value),
+            Token::Str(value) => format!("\"{}\"", value),
+            Token::Op(_) => "comparison operator".to_string(),
+            Token::And => "'&&'".to_string(),
+            Token::Or => "'||'".to_string(),
+            Token::Not => "'!'".to_string(),
+            Token::Open => "'('".to_string(),
+            Token::Close => "')'".to_string(),
+        }
+    }
+}
+
+/// Tokens of `source` with their byte offsets.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let two = bytes.get(i..i + 2);
+        let token = match bytes[i] {
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'(' => Token::Open,
+            b')' => Token::Close,
+            _ if two == Some(b"&&") => Token::And,
+            _ if two == Some(b"||") => Token::Or,
+            _ if two == Some(b"==") => Token::Op(Op::Eq),
+            _ if two == Some(b"!=") => Token::Op(Op::Ne),
+            _ if two == Some(b"<=") => Token::Op(Op::Le),
+            _ if two == Some(b">=") => Token::Op(Op::Ge),
+            b'<' => Token::Op(Op::Lt),
+            b'>' => Token::Op(Op::Gt),
+            b'!' => Token::Not,
+            quote @ (b'"' | b'\'') => {
+                let end = source[i + 1..]
+                    .find(quote as char)
+                    .ok_or_else(|| invalid(start, "unterminated string".to_string()))?;
+                tokens.push((Token::Str(source[i + 1..i + 1 + end].to_string()), start));
+                i += end + 2;
+                continue;
+            }
+            b if b == b'-' || b.is_ascii_digit() => {
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let text = &source[start..i];
+                let value = text
+                    .parse()
+                    .map_err(|_| invalid(start, format!("invalid integer '{}'", text)))?;
+                tokens.push((Token::Int(value), start));
+                continue;
+            }
+            b if b == b'_' || b.is_ascii_alphabetic() => {
+                while i < bytes.len() && (bytes[i] == b'_' || bytes[i].is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+                tokens.push((Token::Ident(source[start..i].to_string()), start));
+                continue;
+            }
+            _ => {
+                let c = source[i..].chars().next().unwrap_or_default();
+                return Err(invalid(start, format!("unexpected character '{}'", c)));
+            }
+        };
+        i += match token {
+            Token::And | Token::Or | Token::Op(Op::Eq | Op::Ne | Op::Le | Op::Ge) => 2,
+            _ => 1,
+        };
+        tokens.push((token, start));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    /// Offset of the next token (the end of the input after the last one).
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(0, |(_, at)| *at)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let at = self.offset();
+        match self.next() {
+            Some((Token::Not, _)) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some((Token::Open, _)) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some((Token::Close, _)) => Ok(expr),
+                    _ => Err(invalid(at, "unclosed '('".to_string())),
+                }
+            }
+            Some((Token::Ident(name), at)) => self.comparison(&name, at),
+            Some((token, at)) => Err(invalid(at, format!("expected a field, found {}", token.describe()))),
+            None => Err(invalid(at, "expected a field, found the end of the expression".to_string())),
+        }
+    }
+
+    fn comparison(&mut self, name: &str, at: usize) -> Result<Expr> {
+        let field = Field::from_name(name).ok_or_else(|| {
+            let known: Vec<&str> = Field::ALL.iter().map(|(n, _)| *n).collect();
+            invalid(at, format!("unknown field '{}' (known: {})", name, known.join(", ")))
+        })?;
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            // A boolean field on its own
+            _ if field.kind() == Kind::Bool => {
+                return Ok(Expr::Compare { field, op: Op::Eq, literal: Literal::Bool(true) });
+            }
+            _ => return Err(invalid(self.offset(), format!("expected a comparison operator after '{}'", name))),
+        };
+        self.pos += 1;
+
+        let literal_at = self.offset();
+        let literal = match self.next() {
+            Some((Token::Int(value), _)) => Literal::Int(value),
+            Some((Token::Str(value), _)) => Literal::Str(value),
+            Some((Token::Ident(word), _)) if word == "true" => Literal::Bool(true),
+            Some((Token::Ident(word), _)) if word == "false" => Literal::Bool(false),
+            Some((Token::Ident(word), _)) if word == "null" => Literal::Null,
+            Some((token, _)) => {
+                return Err(invalid(literal_at, format!("expected a value after '{}', found {}", name, token.describe())))
+            }
+            None => return Err(invalid(literal_at, format!("expected a value after '{}'", name))),
+        };
+        match literal.kind() {
+            None if !matches!(op, Op::Eq | Op::Ne) => {
+                Err(invalid(literal_at, format!("'{}' can only be compared to null with == or !=", name)))
+            }
+            Some(kind) if kind != field.kind() => {
+                Err(invalid(literal_at, format!("'{}' is {:?}, not {:?}", name, field.kind(), kind)))
+            }
+            Some(Kind::Bool) if !matches!(op, Op::Eq | Op::Ne) => {
+                Err(invalid(literal_at, format!("'{}' can only be compared with == or !=", name)))
+            }
+            _ => Ok(Expr::Compare { field, op, literal }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::scratch::ParsedEntry;
+    use crate::testing::canonical_row;
+
+    fn row(organism_id: Option<i32>, existence: i8, gene_name: Option<&str>) -> TransformedRow {
+        canonical_row(ParsedEntry {
+            accession: "P1".to_string(),
+            parent_id: "P1".to_string(),
+            sequence: "MSAK".to_string(),
+            organism_id,
+            existence,
+            gene_name: gene_name.map(str::to_string),
+            dataset: Some("Swiss-Prot".to_string()),
+            ..Default::default()
+        })
+    }
+
+    fn matches(expr: &str, row: &TransformedRow) -> bool {
+        RowFilter::parse(expr).unwrap().matches(row)
+    }
+
+    #[test]
+    fn evaluates_comparisons_and_connectives() {
+        let human = row(Some(9606), 1, Some("TP53"));
+        let mouse = row(Some(10090), 3, None);
+        let expr = "organism_id == 9606 && existence <= 2";
+        assert!(matches(expr, &human));
+        assert!(!matches(expr, &mouse));
+
+        assert!(matches("organism_id == 10090 || gene_name == 'TP53'", &mouse));
+        assert!(matches("!(gene_name != null) && dataset == \"Swiss-Prot\"", &mouse));
+        assert!(matches("sequence_length >= 4 && !is_fragment && isoform_status == 'canonical'", &human));
+        assert!(matches("gene_name > 'A' && gene_name < 'U'", &human));
+        // && binds tighter than ||
+        assert!(matches("existence == 1 || existence == 3 && organism_id == 0", &human));
+
+        // A missing value only matches null comparisons
+        let unknown = row(None, 0, None);
+        assert!(!matches("organism_id != 9606", &unknown));
+        assert!(!matches("existence < 5", &unknown));
+        assert!(matches("organism_id == null && existence == null", &unknown));
+        let mut unsequenced = row(Some(9606), 1, None);
+        unsequenced.sequence.clear();
+        unsequenced.isoform_status = IsoformStatus::Unsequenced;
+        assert!(matches("sequence_length == null", &unsequenced));
+        assert!(!matches("sequence_length < 10", &unsequenced));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions_when_parsed() {
+        for (expr, message) in [
+            ("organism == 9606", "unknown field 'organism'"),
+            ("organism_id == '9606'", "'organism_id' is Int, not Str"),
+            ("organism_id 9606", "expected a comparison operator after 'organism_id'"),
+            ("organism_id == 9606 &&", "expected a field, found the end"),
+            ("(organism_id == 9606", "unclosed '('"),
+            ("gene_name < null", "only be compared to null"),
+            ("is_fragment > false", "only be compared with == or !="),
+            ("gene_name == 'TP53", "unterminated string"),
+            ("organism_id == 9606 existence", "unexpected 'existence'"),
+            ("organism_id = 9606", "unexpected character '='"),
+        ] {
+            let err = RowFilter::parse(expr).unwrap_err().to_string();
+            assert!(err.contains(message), "{expr}: {err}");
+        }
+    }
+}
@@ -1,5 +1,9 @@
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::pipeline::feature_routing::FeatureRouting;
+use crate::pipeline::state::ParseStates;
+
 /// Per-feature scratch data
 #[derive(Debug, Default, Clone)]
 pub struct FeatureScratch {
@@ -11,6 +15,12 @@ pub struct FeatureScratch {
     pub evidence_keys: Vec<String>,
     pub original: Option<String>,
     pub variation: Option<String>,
+    /// `status` of the begin (or single) position; None when certain
+    pub start_status: Option<String>,
+    /// `status` of the end (or single) position; None when certain
+    pub end_status: Option<String>,
+    /// Isoform the coordinates refer to (`<location sequence>`); None for canonical
+    pub sequence: Option<String>,
 }
 
 impl FeatureScratch {
@@ -23,6 +33,24 @@ impl FeatureScratch {
         self.evidence_keys.clear();
         self.original = None;
         self.variation = None;
+        self.start_status = None;
+        self.end_status = None;
+        self.sequence = None;
+    }
+
+    /// Combined position status: None if every coordinate is certain, the shared
+    /// status if both ends agree (e.g. "uncertain"), otherwise "<begin>..<end>"
+    /// such as "less than..certain".
+    pub fn position_status(&self) -> Option<String> {
+        match (&self.start_status, &self.end_status) {
+            (None, None) => None,
+            (Some(start), Some(end)) if start == end => Some(start.clone()),
+            (start, end) => Some(format!(
+                "{}..{}",
+                start.as_deref().unwrap_or("certain"),
+                end.as_deref().unwrap_or("certain")
+            )),
+        }
     }
 }
 
@@ -38,6 +66,9 @@ pub struct ActiveSiteScratch {
     pub start: Option<i32>,
     pub end: Option<i32>,
     pub evidence_keys: Vec<String>,
+    pub position_status: Option<String>,
+    /// Isoform the coordinates refer to (`<location sequence>`); None for canonical
+    pub sequence: Option<String>,
 }
 
 impl ActiveSiteScratch {
@@ -47,6 +78,8 @@ impl ActiveSiteScratch {
         self.start = None;
         self.end = None;
         self.evidence_keys.clear();
+        self.position_status = None;
+        self.sequence = None;
     }
 }
 
@@ -58,6 +91,9 @@ pub struct BindingSiteScratch {
     pub start: Option<i32>,
     pub end: Option<i32>,
     pub evidence_keys: Vec<String>,
+    pub position_status: Option<String>,
+    /// Isoform the coordinates refer to (`<location sequence>`); None for canonical
+    pub sequence: Option<String>,
 }
 
 impl BindingSiteScratch {
@@ -67,6 +103,8 @@ impl BindingSiteScratch {
         self.start = None;
         self.end = None;
         self.evidence_keys.clear();
+        self.position_status = None;
+        self.sequence = None;
     }
 }
 
@@ -79,6 +117,9 @@ pub struct MetalCoordinationScratch {
     pub start: Option<i32>,
     pub end: Option<i32>,
     pub evidence_keys: Vec<String>,
+    pub position_status: Option<String>,
+    /// Isoform the coordinates refer to (`<location sequence>`); None for canonical
+    pub sequence: Option<String>,
 }
 
 impl MetalCoordinationScratch {
@@ -89,6 +130,8 @@ impl MetalCoordinationScratch {
         self.start = None;
         self.end = None;
         self.evidence_keys.clear();
+        self.position_status = None;
+        self.sequence = None;
     }
 }
 
@@ -100,6 +143,9 @@ pub struct MutagenesisSiteScratch {
     pub start: Option<i32>,
     pub end: Option<i32>,
     pub evidence_keys: Vec<String>,
+    pub position_status: Option<String>,
+    /// Isoform the coordinates refer to (`<location sequence>`); None for canonical
+    pub sequence: Option<String>,
 }
 
 impl MutagenesisSiteScratch {
@@ -109,6 +155,8 @@ impl MutagenesisSiteScratch {
         self.start = None;
         self.end = None;
         self.evidence_keys.clear();
+        self.position_status = None;
+        self.sequence = None;
     }
 }
 
@@ -121,6 +169,9 @@ pub struct DomainScratch {
     pub start: Option<i32>,
     pub end: Option<i32>,
     pub evidence_keys: Vec<String>,
+    pub position_status: Option<String>,
+    /// Isoform the coordinates refer to (`<location sequence>`); None for canonical
+    pub sequence: Option<String>,
 }
 
 impl DomainScratch {
@@ -131,6 +182,8 @@ impl DomainScratch {
         self.start = None;
         self.end = None;
         self.evidence_keys.clear();
+        self.position_status = None;
+        self.sequence = None;
     }
 }
 
@@ -144,6 +197,13 @@ pub struct NaturalVariantScratch {
     pub start: Option<i32>,
     pub end: Option<i32>,
     pub evidence_keys: Vec<String>,
+    pub position_status: Option<String>,
+    /// Isoform the coordinates refer to (`<location sequence>`); None for canonical
+    pub sequence: Option<String>,
+    /// dbSNP reference SNP id (`<dbReference type="dbSNP">`, else `dbSNP:rs...` in the description)
+    pub dbsnp_id: Option<String>,
+    /// Diseases named by the "in ..." fragments of the description, joined with "; "
+    pub disease_association: Option<String>,
 }
 
 impl NaturalVariantScratch {
@@ -155,6 +215,10 @@ impl NaturalVariantScratch {
         self.start = None;
         self.end = None;
         self.evidence_keys.clear();
+        self.position_status = None;
+        self.sequence = None;
+        self.dbsnp_id = None;
+        self.disease_association = None;
     }
 }
 
@@ -167,12 +231,15 @@ impl NaturalVariantScratch {
 pub struct SubunitScratch {
     pub text: String,
     pub evidence_keys: Vec<String>,
+    /// `<molecule>` the comment is about (e.g. "Isoform 2"); None for the whole entry
+    pub molecule: Option<String>,
 }
 
 impl SubunitScratch {
     pub fn clear(&mut self) {
         self.text.clear();
         self.evidence_keys.clear();
+        self.molecule = None;
     }
 }
 
@@ -183,6 +250,13 @@ pub struct InteractionScratch {
     pub partner_name: Option<String>,
     pub interactant_id_1: Option<String>,
     pub interactant_id_2: Option<String>,
+    /// `<interactant intactId="EBI-...">` of each side
+    pub intact_id_1: Option<String>,
+    pub intact_id_2: Option<String>,
+    /// `<organismsDiffer>`: the partner is from another organism (xeno interaction)
+    pub organisms_differ: Option<bool>,
+    /// `<experiments>`: number of experiments supporting the interaction
+    pub experiments: Option<i32>,
     pub evidence_keys: Vec<String>,
 }
 
@@ -192,20 +266,43 @@ impl InteractionScratch {
         self.partner_name = None;
         self.interactant_id_1 = None;
         self.interactant_id_2 = None;
+        self.intact_id_1 = None;
+        self.intact_id_2 = None;
+        self.organisms_differ = None;
+        self.experiments = None;
         self.evidence_keys.clear();
     }
 }
 
+/// Any comment type without a dedicated column (e.g. "caution", "polymorphism")
+#[derive(Debug, Default, Clone)]
+pub struct OtherCommentScratch {
+    pub comment_type: String,
+    pub text: String,
+    pub evidence_keys: Vec<String>,
+    /// `<molecule>` the comment is about (e.g. "Isoform 2"); None for the whole entry
+    pub molecule: Option<String>,
+}
+
 /// Per-location scratch data
 #[derive(Debug, Default, Clone)]
 pub struct LocationScratch {
     pub location: String,
+    /// `<topology>` of the enclosing `<subcellularLocation>` (e.g. "Multi-pass membrane protein")
+    pub topology: Option<String>,
+    /// `<orientation>` of the enclosing `<subcellularLocation>` (e.g. "Cytoplasmic side")
+    pub orientation: Option<String>,
+    /// `<molecule>` the comment is about (e.g. "Isoform 2"); None for the whole entry
+    pub molecule: Option<String>,
     pub evidence_keys: Vec<String>,
 }
 
 impl LocationScratch {
     pub fn clear(&mut self) {
         self.location.clear();
+        self.topology = None;
+        self.orientation = None;
+        self.molecule = None;
         self.evidence_keys.clear();
     }
 }
@@ -218,6 +315,12 @@ pub struct IsoformScratch {
     /// UniProt "described" sequence refs (usually VSP_...) that define how this isoform differs.
     pub vsp_ids: Vec<String>,
     pub isoform_note: Option<String>,
+    /// `<name>`s of the isoform (e.g. "2", "Beta"), matched against comment `<molecule>`s.
+    pub names: Vec<String>,
+    /// `<sequence type>`: "displayed", "described", "external" or "not described"
+    pub sequence_type: Option<String>,
+    /// `<event type>` that produces the isoform (e.g. "alternative splicing", "alternative initiation")
+    pub event_type: Option<String>,
 }
 
 impl IsoformScratch {
@@ -226,6 +329,9 @@ impl IsoformScratch {
         self.isoform_sequence = None;
         self.vsp_ids.clear();
         self.isoform_note = None;
+        self.names.clear();
+        self.sequence_type = None;
+        self.event_type = None;
     }
 }
 
@@ -234,24 +340,85 @@ impl IsoformScratch {
 pub struct StructureRef {
     pub database: String,
     pub id: String,
+    /// `<property type="method">`, e.g. "X-ray"
+    pub method: Option<String>,
+    /// `<property type="resolution">` in Å ("2.50 A")
+    pub resolution: Option<f32>,
+    /// `<property type="chains">` ("A/B=1-393, C=5-120"), in canonical coordinates
+    pub chains: Vec<ChainRange>,
 }
 
-/// Tracks which feature type we're currently parsing to route coordinates correctly
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Cross-reference with an optional display name: pathways (Reactome/KEGG) and
+/// domain families (InterPro/Pfam/SMART)
+#[derive(Debug, Default, Clone)]
+pub struct NamedXref {
+    pub database: String,
+    pub id: String,
+    /// `<property type="pathway name">` or `"entry name"`; KEGG references carry none
+    pub name: Option<String>,
+}
+
+/// Residues of the canonical sequence resolved in some chains of a PDB structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainRange {
+    /// Chain identifiers as written, e.g. "A/B"
+    pub chains: String,
+    pub start: i32,
+    pub end: i32,
+}
+
+impl ChainRange {
+    /// Parses a PDB `chains` property; parts without a numeric range ("A=-") are skipped.
+    pub fn parse_list(value: &str) -> Vec<ChainRange> {
+        value
+            .split(',')
+            .filter_map(|part| {
+                let (chains, range) = part.trim().split_once('=')?;
+                let (start, end) = range.trim().split_once('-')?;
+                let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+                (start > 0 && end >= start).then(|| ChainRange {
+                    chains: chains.trim().to_string(),
+                    start,
+                    end,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Tracks which feature type we're currently parsing to route coordinates correctly.
+///
+/// Serialized as the output column a route fills (see `feature_routing`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum FeatureContext {
+    /// Only `features`
     #[default]
+    #[serde(rename = "features")]
     Generic,
+    #[serde(rename = "active_sites")]
     ActiveSite,
+    #[serde(rename = "binding_sites")]
     BindingSite,
+    #[serde(rename = "metal_coordinations")]
     MetalCoordination,
+    #[serde(rename = "mutagenesis_sites")]
     Mutagenesis,
+    #[serde(rename = "domains")]
     Domain,
+    #[serde(rename = "natural_variants")]
     NaturalVariant,
 }
 
 /// Finalized entry representation used by downstream transformer and batcher.
 #[derive(Debug, Default)]
 pub struct ParsedEntry {
+    /// `<entry dataset="...">`: "Swiss-Prot" (reviewed) or "TrEMBL" (unreviewed)
+    pub dataset: Option<String>,
+    /// `<entry version="...">`: entry revision, used by `transform.deduplicate: newest_version`
+    pub version: Option<u32>,
+    /// `<geneLocation type="...">` types ("mitochondrion", "plastid", "plasmid", ...),
+    /// distinct and joined with "; " when an entry has several; None for nuclear genes
+    pub gene_location: Option<String>,
     pub accession: String,
     pub parent_id: String,
     pub sequence: String,
@@ -262,8 +429,20 @@ pub struct ParsedEntry {
     pub protein_name: Option<String>,
     pub organism_scientific_name: Option<String>,
     pub existence: i8,
+    /// `<sequence fragment="single|multiple">`; None for full-length sequences
+    pub fragment: Option<String>,
+    /// `<sequence precursor="true">`
+    pub precursor: bool,
+    /// `<sequence checksum="...">`: UniProt CRC64 of `sequence` (hex)
+    pub checksum: Option<String>,
+    /// Features whose type is not in the routing table (`transform.feature_routes`)
+    pub unknown_feature_types: u32,
 
     pub structures: Vec<StructureRef>,
+    pub pathways: Vec<NamedXref>,
+    pub domain_xrefs: Vec<NamedXref>,
+    /// Distinct EC numbers in document order
+    pub ec_numbers: Vec<String>,
     pub evidence_map: HashMap<String, String>,
 
     pub features: FeatureCollections,
@@ -273,6 +452,9 @@ pub struct ParsedEntry {
 
 impl ParsedEntry {
     pub fn clear(&mut self) {
+        self.dataset = None;
+        self.version = None;
+        self.gene_location = None;
         self.accession.clear();
         self.parent_id.clear();
         self.sequence.clear();
@@ -282,13 +464,28 @@ impl ParsedEntry {
         self.protein_name = None;
         self.organism_scientific_name = None;
         self.existence = 0;
+        self.fragment = None;
+        self.precursor = false;
+        self.checksum = None;
+        self.unknown_feature_types = 0;
         self.structures.clear();
+        self.pathways.clear();
+        self.domain_xrefs.clear();
+        self.ec_numbers.clear();
         self.evidence_map.clear();
         self.features.clear();
         self.comments.clear();
         self.isoforms.clear();
     }
 
+    /// Adds an EC number unless the entry already has it.
+    pub fn add_ec_number(&mut self, ec: &str) {
+        let ec = ec.trim();
+        if !ec.is_empty() && !self.ec_numbers.iter().any(|e| e == ec) {
+            self.ec_numbers.push(ec.to_string());
+        }
+    }
+
     /// Returns the canonical amino acid at a 1-based XML coordinate.
     ///
     /// IMPORTANT: This must be called BEFORE any coordinate shifting.
@@ -385,6 +582,7 @@ pub struct CommentCollections {
     pub locations: Vec<LocationScratch>,
     pub subunits: Vec<SubunitScratch>,
     pub interactions: Vec<InteractionScratch>,
+    pub others: Vec<OtherCommentScratch>,
 }
 
 impl CommentCollections {
@@ -392,6 +590,7 @@ impl CommentCollections {
         self.locations.clear();
         self.subunits.clear();
         self.interactions.clear();
+        self.others.clear();
     }
 }
 
@@ -400,9 +599,18 @@ impl CommentCollections {
 #[derive(Debug, Default)]
 pub struct EntryScratch {
     pub entry: ParsedEntry,
+    /// Reusable text arena for large element bodies (e.g. `<sequence>`)
     pub text_buffer: String,
+    /// Reusable event buffer for nested element reads
+    pub xml_buffer: Vec<u8>,
+    /// Previously emitted entry handed back via `recycle_entry`; its buffers are reused
+    spare_entry: Option<ParsedEntry>,
     pub has_primary_accession: bool,
+    /// Element path of the handler currently consuming events
+    pub state: ParseStates,
     pub current_feature_context: FeatureContext,
+    /// Feature type -> column routing, shared by every scratch of a run
+    pub feature_routing: FeatureRouting,
 
     pub current_feature: FeatureScratch,
     pub current_active_site: ActiveSiteScratch,
@@ -428,6 +636,7 @@ impl EntryScratch {
         self.entry.clear();
         self.text_buffer.clear();
         self.has_primary_accession = false;
+        self.state.reset();
         self.current_feature_context = FeatureContext::Generic;
 
         self.current_feature.clear();
@@ -444,9 +653,42 @@ impl EntryScratch {
     }
 
     /// Moves the accumulated entry out, leaving the scratch ready for reuse.
+    ///
+    /// If an entry was recycled, it replaces the taken one so its allocations
+    /// (sequence, feature vectors, evidence map) carry over to the next entry.
     pub fn take_entry(&mut self) -> ParsedEntry {
-        let entry = std::mem::take(&mut self.entry);
+        let replacement = self.spare_entry.take().unwrap_or_default();
+        let entry = std::mem::replace(&mut self.entry, replacement);
         self.reset();
         entry
     }
+
+    /// Returns a finished entry to the scratch so the next `take_entry` can reuse it.
+    pub fn recycle_entry(&mut self, mut entry: ParsedEntry) {
+        entry.clear();
+        self.spare_entry = Some(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recycled_entry_keeps_capacity() {
+        let mut scratch = EntryScratch::new();
+        scratch.entry.sequence.push_str("MTAKMTAKMTAK");
+        scratch.entry.features.generic.push(FeatureScratch::default());
+
+        let entry = scratch.take_entry();
+        let seq_capacity = entry.sequence.capacity();
+        scratch.recycle_entry(entry);
+
+        // The recycled entry becomes the working entry after the next take.
+        let _second = scratch.take_entry();
+        assert!(scratch.entry.sequence.is_empty());
+        assert!(scratch.entry.features.generic.is_empty());
+        assert_eq!(scratch.entry.sequence.capacity(), seq_capacity);
+        assert!(scratch.entry.features.generic.capacity() >= 1);
+    }
 }
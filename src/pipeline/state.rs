@@ -0,0 +1,174 @@
+//! Explicit parse state of the entry handlers.
+//!
+//! Every handler that consumes a container element enters its `ParseState` on the
+//! start tag, pulls events through `ParseStates::next_event` and leaves the state on
+//! the matching end tag. The stack is the element path inside the current `<entry>`,
+//! so a transition the schema does not allow (a `<location>` outside a `<feature>`,
+//! a second `<entry>` before the first closed) and input that ends mid-entry are
+//! reported with that path and the byte offset instead of yielding a partial entry.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::fmt;
+use std::io::BufRead;
+
+use crate::error::{EtlError, Result};
+
+/// Container element a handler is consuming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseState {
+    Entry,
+    Organism,
+    Gene,
+    Protein,
+    RecommendedName,
+    Feature,
+    FeatureLocation,
+    Comment,
+    Isoform,
+    Interactant,
+    DbReference,
+}
+
+impl ParseState {
+    /// Element name of the state.
+    pub fn tag(self) -> &'static str {
+        match self {
+            ParseState::Entry => "entry",
+            ParseState::Organism => "organism",
+            ParseState::Gene => "gene",
+            ParseState::Protein => "protein",
+            ParseState::RecommendedName => "recommendedName",
+            ParseState::Feature => "feature",
+            ParseState::FeatureLocation => "location",
+            ParseState::Comment => "comment",
+            ParseState::Isoform => "isoform",
+            ParseState::Interactant => "interactant",
+            ParseState::DbReference => "dbReference",
+        }
+    }
+
+    /// State the element must be directly inside; None for the top level.
+    fn parent(self) -> Option<ParseState> {
+        match self {
+            ParseState::Entry => None,
+            ParseState::Organism
+            | ParseState::Gene
+            | ParseState::Protein
+            | ParseState::Feature
+            | ParseState::Comment
+            | ParseState::DbReference => Some(ParseState::Entry),
+            ParseState::RecommendedName => Some(ParseState::Protein),
+            ParseState::FeatureLocation => Some(ParseState::Feature),
+            ParseState::Isoform | ParseState::Interactant => Some(ParseState::Comment),
+        }
+    }
+}
+
+/// Stack of entered states, outermost first.
+#[derive(Debug, Default, Clone)]
+pub struct ParseStates {
+    stack: Vec<ParseState>,
+}
+
+impl ParseStates {
+    /// Drops any state left over from an aborted entry.
+    pub fn reset(&mut self) {
+        self.stack.clear();
+    }
+
+    pub fn current(&self) -> Option<ParseState> {
+        self.stack.last().copied()
+    }
+
+    /// Enters `state` at the reader's position, if the current state allows it.
+    pub fn enter<R>(&mut self, state: ParseState, reader: &Reader<R>) -> Result<()> {
+        if self.current() != state.parent() {
+            return Err(EtlError::InvalidTransition {
+                from: self.to_string(),
+                to: state.tag(),
+                position: reader.buffer_position(),
+            });
+        }
+        self.stack.push(state);
+        Ok(())
+    }
+
+    /// Leaves `state`, which must be the current one.
+    pub fn leave<R>(&mut self, state: ParseState, reader: &Reader<R>) -> Result<()> {
+        if self.current() != Some(state) {
+            return Err(EtlError::InvalidTransition {
+                from: self.to_string(),
+                to: "/",
+                position: reader.buffer_position(),
+            });
+        }
+        self.stack.pop();
+        Ok(())
+    }
+
+    /// Next event inside the current state; the input ending here is an error.
+    pub fn next_event<'b, R: BufRead>(&self, reader: &mut Reader<R>, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        buf.clear();
+        match reader.read_event_into(buf)? {
+            Event::Eof => Err(EtlError::UnexpectedEof {
+                path: self.to_string(),
+                position: reader.buffer_position(),
+            }),
+            event => Ok(event),
+        }
+    }
+}
+
+impl fmt::Display for ParseStates {
+    /// Element path, e.g. `entry/feature/location`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.stack.is_empty() {
+            return f.write_str("(top level)");
+        }
+        for (i, state) in self.stack.iter().enumerate() {
+            if i > 0 {
+                f.write_str("/")?;
+            }
+            f.write_str(state.tag())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_elements_outside_their_parent() {
+        let reader = Reader::from_reader("".as_bytes());
+        let mut states = ParseStates::default();
+        states.enter(ParseState::Entry, &reader).unwrap();
+        states.enter(ParseState::Feature, &reader).unwrap();
+        states.enter(ParseState::FeatureLocation, &reader).unwrap();
+        assert_eq!(states.to_string(), "entry/feature/location");
+
+        let err = states.enter(ParseState::Isoform, &reader).unwrap_err();
+        assert!(err.to_string().contains("entry/feature/location"));
+        assert!(states.leave(ParseState::Feature, &reader).is_err());
+
+        states.leave(ParseState::FeatureLocation, &reader).unwrap();
+        states.leave(ParseState::Feature, &reader).unwrap();
+        assert!(states.enter(ParseState::Entry, &reader).is_err());
+    }
+
+    #[test]
+    fn reports_input_ending_inside_a_state() {
+        let mut reader = Reader::from_reader("<feature><location>".as_bytes());
+        let mut buf = Vec::new();
+        let mut states = ParseStates::default();
+        states.enter(ParseState::Entry, &reader).unwrap();
+        states.enter(ParseState::Feature, &reader).unwrap();
+        while let Ok(event) = states.next_event(&mut reader, &mut buf) {
+            assert!(matches!(event, Event::Start(_)));
+        }
+        let err = states.next_event(&mut reader, &mut buf).unwrap_err();
+        assert_eq!(err.to_string(), "Unexpected end of XML inside entry/feature at byte 19");
+    }
+}
@@ -0,0 +1,290 @@
+//! Verbose tracing of a single entry (`--trace-accession`).
+//!
+//! With a tracer enabled the parser captures every `<entry>` as an XML chunk before
+//! parsing it, so once the traced accession turns up its elements can be replayed
+//! verbatim. Feature routing, the VSP edits behind each isoform row, and every PTM
+//! mapping decision are then appended by the stages that make them, so the trace
+//! follows the same code paths as the normal run.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::error::Result;
+use crate::pipeline::scratch::ParsedEntry;
+
+/// Longest text node echoed into the element trace.
+const MAX_TEXT_CHARS: usize = 120;
+
+/// Cheap, cloneable handle for tracing one accession; a no-op when disabled.
+#[derive(Clone, Default)]
+pub struct EntryTracer {
+    target: Option<Arc<TraceTarget>>,
+}
+
+struct TraceTarget {
+    accession: String,
+    out: Mutex<Box<dyn Write + Send>>,
+    /// Number of times the accession was encountered
+    hits: AtomicUsize,
+}
+
+impl EntryTracer {
+    /// Handle that traces nothing (the default).
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Traces `accession` into a new file at `path`.
+    pub fn to_file(accession: &str, path: &Path) -> io::Result<Self> {
+        Ok(Self::to_writer(accession, BufWriter::new(File::create(path)?)))
+    }
+
+    /// Traces `accession` into `out`.
+    pub fn to_writer(accession: &str, out: impl Write + Send + 'static) -> Self {
+        Self {
+            target: Some(Arc::new(TraceTarget {
+                accession: accession.to_string(),
+                out: Mutex::new(Box::new(out)),
+                hits: AtomicUsize::new(0),
+            })),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// True if `accession` (an entry accession or a row's parent id) is being traced.
+    pub fn is_target(&self, accession: &str) -> bool {
+        self.target.as_ref().is_some_and(|t| t.accession == accession)
+    }
+
+    /// How often the traced accession was encountered so far.
+    pub fn hits(&self) -> usize {
+        self.target.as_ref().map_or(0, |t| t.hits.load(Ordering::Relaxed))
+    }
+
+    /// Writes `line` under `section` if `accession` is traced; `line` is only
+    /// evaluated in that case.
+    pub fn log(&self, accession: &str, section: &str, line: impl FnOnce() -> String) {
+        let Some(target) = self.target.as_ref().filter(|t| t.accession == accession) else {
+            return;
+        };
+        let mut out = target.out.lock().unwrap_or_else(|e| e.into_inner());
+        // Tracing is best effort; a failed write must not fail the run
+        let _ = writeln!(out, "[{}] {}", section, line());
+        let _ = out.flush();
+    }
+
+    /// Writes the captured XML and the routed features of a traced entry.
+    pub fn trace_entry(&self, chunk: &[u8], entry: &ParsedEntry) {
+        let Some(target) = self.target.as_ref().filter(|t| t.accession == entry.accession) else {
+            return;
+        };
+        target.hits.fetch_add(1, Ordering::Relaxed);
+
+        self.log(&entry.accession, "entry", || {
+            format!(
+                "accession={} dataset={} sequence_len={} isoforms={}",
+                entry.accession,
+                entry.dataset.as_deref().unwrap_or("-"),
+                entry.sequence.len(),
+                entry.isoforms.len()
+            )
+        });
+        for line in element_lines(chunk) {
+            self.log(&entry.accession, "element", || line);
+        }
+
+        for feat in &entry.features.generic {
+            self.log(&entry.accession, "feature", || {
+                format!(
+                    "type={:?} id={} start={} end={} status={} evidence={:?}",
+                    feat.feature_type,
+                    feat.id.as_deref().unwrap_or("-"),
+                    opt(feat.start),
+                    opt(feat.end),
+                    feat.position_status().as_deref().unwrap_or("certain"),
+                    feat.evidence_keys
+                )
+            });
+        }
+        let features = &entry.features;
+        let routed = [
+            ("active_sites", features.active_sites.len()),
+            ("binding_sites", features.binding_sites.len()),
+            ("metal_coordinations", features.metal_coordinations.len()),
+            ("mutagenesis_sites", features.mutagenesis_sites.len()),
+            ("domains", features.domains.len()),
+            ("natural_variants", features.natural_variants.len()),
+        ];
+        self.log(&entry.accession, "route", || {
+            routed
+                .iter()
+                .map(|(column, n)| format!("{}={}", column, n))
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+    }
+}
+
+/// Reads the rest of the element opened by `start` and returns it re-serialized,
+/// so it can be parsed (and, when traced, replayed) from memory.
+pub fn capture_element<R: BufRead>(
+    reader: &mut Reader<R>,
+    start: &BytesStart<'_>,
+    buf: &mut Vec<u8>,
+) -> Result<Vec<u8>> {
+    let mut writer = Writer::new(Vec::new());
+    writer.write_event(Event::Start(start.borrow()))?;
+    let name = start.name().as_ref().to_vec();
+    let mut depth = 0usize;
+    loop {
+        buf.clear();
+        let event = reader.read_event_into(buf)?;
+        let closes = match &event {
+            Event::Start(e) if e.name().as_ref() == name => {
+                depth += 1;
+                false
+            }
+            Event::End(e) if e.name().as_ref() == name => {
+                if depth == 0 {
+                    true
+                } else {
+                    depth -= 1;
+                    false
+                }
+            }
+            Event::Eof => break,
+            _ => false,
+        };
+        writer.write_event(event)?;
+        if closes {
+            break;
+        }
+    }
+    Ok(writer.into_inner())
+}
+
+/// One indented line per element (with attributes) or text node of `chunk`.
+fn element_lines(chunk: &[u8]) -> Vec<String> {
+    let mut reader = Reader::from_reader(chunk);
+    reader.config_mut().trim_text(true);
+    let mut lines = Vec::new();
+    let mut depth = 0usize;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let (line, next_depth) = match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => (Some(tag_line(&e, false)), depth + 1),
+            Ok(Event::Empty(e)) => (Some(tag_line(&e, true)), depth),
+            Ok(Event::Text(e)) => {
+                let text = e.unescape().map(|t| t.into_owned()).unwrap_or_default();
+                let mut shown: String = text.chars().take(MAX_TEXT_CHARS).collect();
+                if shown.len() < text.len() {
+                    shown.push_str("...");
+                }
+                (Some(format!("{:?}", shown)), depth)
+            }
+            Ok(Event::End(_)) => {
+                depth = depth.saturating_sub(1);
+                (None, depth)
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => (None, depth),
+        };
+        if let Some(line) = line {
+            lines.push(format!("{}{}", "  ".repeat(depth), line));
+        }
+        depth = next_depth;
+    }
+    lines
+}
+
+fn tag_line(e: &BytesStart<'_>, empty: bool) -> String {
+    let mut line = format!("<{}", String::from_utf8_lossy(e.local_name().as_ref()));
+    for attr in e.attributes().flatten() {
+        let value = attr.unescape_value().map(|v| v.into_owned()).unwrap_or_default();
+        let _ = write!(line, " {}={:?}", String::from_utf8_lossy(attr.key.as_ref()), value);
+    }
+    line.push_str(if empty { "/>" } else { ">" });
+    line
+}
+
+fn opt(value: Option<i32>) -> String {
+    value.map_or_else(|| "?".to_string(), |v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::{parse_entries_with_hooks, ParseHooks};
+    use crossbeam_channel::unbounded;
+    use std::collections::HashMap;
+
+    /// Clonable in-memory sink so the test can read what the tracer wrote.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(data)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    const XML: &str = r#"<uniprot>
+        <entry dataset="Swiss-Prot">
+            <accession>P1</accession>
+            <comment type="alternative products">
+                <isoform><id>P1-2</id><sequence type="described" ref="VSP_1"/></isoform>
+            </comment>
+            <feature type="splice variant" id="VSP_1"><location><begin position="2"/><end position="3"/></location></feature>
+            <feature type="modified residue" description="Phosphoserine"><location><position position="5"/></location></feature>
+            <feature type="modified residue" description="Phosphothreonine"><location><position position="2"/></location></feature>
+            <sequence length="6">MTAKSW</sequence>
+        </entry>
+        <entry><accession>P2</accession><sequence length="2">MK</sequence></entry>
+    </uniprot>"#;
+
+    fn run(trace: EntryTracer) -> Vec<arrow::record_batch::RecordBatch> {
+        let mut reader = Reader::from_reader(XML.as_bytes());
+        reader.config_mut().trim_text(true);
+        let sidecar = HashMap::from([("P1-2".to_string(), "MKSW".to_string())]);
+        let hooks = ParseHooks {
+            trace,
+            ..ParseHooks::default()
+        };
+        let (tx, rx) = unbounded();
+        parse_entries_with_hooks(reader, tx, &Metrics::new(), 16, Some(Arc::new(sidecar)), &hooks).unwrap();
+        rx.iter().collect()
+    }
+
+    #[test]
+    fn test_traces_one_accession_without_changing_output() {
+        let out = SharedBuf::default();
+        let tracer = EntryTracer::to_writer("P1", out.clone());
+        let traced = run(tracer.clone());
+        assert_eq!(traced, run(EntryTracer::disabled()));
+        assert_eq!(tracer.hits(), 1);
+
+        let log = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert!(log.contains("[entry] accession=P1 dataset=Swiss-Prot sequence_len=6 isoforms=1"));
+        assert!(log.contains("[element]       <position position=\"5\"/>"));
+        assert!(log.contains("[feature] type=\"splice variant\" id=VSP_1 start=2 end=3"));
+        assert!(log.contains("[row] isoform_id=P1-2 sequence_len=4 vsp_ids=[\"VSP_1\"] edits=[2-3:-2(deletion)]"));
+        assert!(log.contains("[ptm] row=P1-2 type=\"modified residue\" original_index=5 mapped mapped_index=3 residue=S"));
+        assert!(log.contains("[ptm] row=P1-2 type=\"modified residue\" original_index=2 code=VSP_DELETION_EVENT"));
+        assert!(!log.contains("P2"));
+    }
+}
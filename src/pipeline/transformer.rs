@@ -1,9 +1,53 @@
 use crate::error::{EtlError, Result};
-use crate::metrics::MetricsCollector;
+use crate::metrics::{FeatureCategory, MetricsCollector};
+use crate::pipeline::checksum::crc64_hex;
+use crate::pipeline::dedup::AccessionDedup;
+use crate::pipeline::enrich::{Enrichers, ExtraValue};
 use crate::pipeline::mapper::CoordinateMapper;
+use crate::pipeline::rejects::{RejectKind, RejectLog};
+use crate::pipeline::row_filter::RowFilter;
 use crate::pipeline::scratch::{IsoformScratch, ParsedEntry};
+use crate::pipeline::trace::EntryTracer;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
+use crate::log_warn;
+
+/// What happens to entries without a primary accession or with an empty sequence
+/// (`transform.invalid_entries`); their rows would break the non-null `id`/`sequence`
+/// invariants of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidEntryPolicy {
+    /// Drop the entry, count it in `invalid_entries` and record a reject
+    #[default]
+    Skip,
+    /// Abort the run on the first invalid entry
+    Fail,
+}
+
+/// Where a row's sequence came from (the `isoform_status` column).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IsoformStatus {
+    /// Entry without isoforms: the canonical sequence from the XML
+    #[default]
+    Canonical,
+    /// Isoform with its sidecar FASTA sequence
+    Sequenced,
+    /// Isoform missing from the sidecar, kept by `transform.keep_unsequenced_isoforms`:
+    /// null sequence, and null coordinate columns since nothing can be placed on it
+    Unsequenced,
+}
+
+impl IsoformStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IsoformStatus::Canonical => "canonical",
+            IsoformStatus::Sequenced => "sequenced",
+            IsoformStatus::Unsequenced => "unsequenced",
+        }
+    }
+}
 
 /// Row material emitted by the transformer and fed into the batcher.
 #[derive(Debug, Clone)]
@@ -11,13 +55,26 @@ pub struct TransformedRow {
     pub entry: Arc<ParsedEntry>,
     pub row_id: String,
     pub parent_id: String,
+    /// Empty for `IsoformStatus::Unsequenced` rows (written as null)
     pub sequence: String,
+    pub isoform_status: IsoformStatus,
     pub mapper: CoordinateMapper,
+    /// Values for enricher-registered extra columns, in schema order.
+    pub extra: Vec<ExtraValue>,
 }
 
 pub struct EntryTransformer<M: MetricsCollector> {
     metrics: M,
     sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    enrichers: Enrichers,
+    rejects: RejectLog,
+    strict_checksums: bool,
+    trace: EntryTracer,
+    dedup: AccessionDedup,
+    invalid_entries: InvalidEntryPolicy,
+    downgrade_length_mismatch: bool,
+    keep_unsequenced_isoforms: bool,
+    row_filter: Option<RowFilter>,
 }
 
 impl<M: MetricsCollector> EntryTransformer<M> {
@@ -25,28 +82,127 @@ impl<M: MetricsCollector> EntryTransformer<M> {
         Self {
             metrics,
             sidecar_fasta,
+            enrichers: Enrichers::default(),
+            rejects: RejectLog::disabled(),
+            strict_checksums: false,
+            trace: EntryTracer::disabled(),
+            dedup: AccessionDedup::disabled(),
+            invalid_entries: InvalidEntryPolicy::default(),
+            downgrade_length_mismatch: false,
+            keep_unsequenced_isoforms: false,
+            row_filter: None,
         }
     }
 
+    /// Runs `enrichers` over every row this transformer emits.
+    pub fn with_enrichers(mut self, enrichers: Enrichers) -> Self {
+        self.enrichers = enrichers;
+        self
+    }
+
+    /// Records rows this transformer drops into `rejects`.
+    pub fn with_reject_log(mut self, rejects: RejectLog) -> Self {
+        self.rejects = rejects;
+        self
+    }
+
+    /// Fails on the first sequence checksum mismatch instead of only counting it.
+    pub fn with_strict_checksums(mut self, strict: bool) -> Self {
+        self.strict_checksums = strict;
+        self
+    }
+
+    /// Logs the rows (and VSP edits) built for the traced accession to `trace`.
+    pub fn with_tracer(mut self, trace: EntryTracer) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Drops entries whose accession `dedup` has already seen (or a newer version of).
+    pub fn with_dedup(mut self, dedup: AccessionDedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Skips or fails on entries without an accession or sequence, per `policy`.
+    pub fn with_invalid_entries(mut self, policy: InvalidEntryPolicy) -> Self {
+        self.invalid_entries = policy;
+        self
+    }
+
+    /// Gives the mapped PTM sites of isoforms whose sidecar length disagrees with their
+    /// VSP edits the lowest confidence score instead of only counting them.
+    pub fn with_length_mismatch_downgrade(mut self, downgrade: bool) -> Self {
+        self.downgrade_length_mismatch = downgrade;
+        self
+    }
+
+    /// Emits isoforms missing from the sidecar as `IsoformStatus::Unsequenced` rows
+    /// instead of dropping them.
+    pub fn with_unsequenced_isoforms(mut self, keep: bool) -> Self {
+        self.keep_unsequenced_isoforms = keep;
+        self
+    }
+
+    /// Drops rows `filter` does not match (`filters.row_expr`) before they are enriched.
+    pub fn with_row_filter(mut self, filter: Option<RowFilter>) -> Self {
+        self.row_filter = filter;
+        self
+    }
+
     /// Expands a parsed entry into one or more row-level records.
     pub fn transform(&self, entry: ParsedEntry) -> Result<Vec<TransformedRow>> {
+        self.transform_shared(Arc::new(entry))
+    }
+
+    /// Same as `transform`, but lets the caller keep a handle on the entry so it can
+    /// reclaim (and recycle) it once the rows have been consumed.
+    pub fn transform_shared(&self, shared_entry: Arc<ParsedEntry>) -> Result<Vec<TransformedRow>> {
+        if !self.check_valid(&shared_entry)? {
+            return Ok(Vec::new());
+        }
+        if let Some(reason) = self.dedup.duplicate_reason(&shared_entry.accession, shared_entry.version) {
+            self.metrics.add_duplicate_entries(1);
+            self.trace.log(&shared_entry.accession, "row", || {
+                format!("entry dropped: code=DUPLICATE_ACCESSION ({})", reason)
+            });
+            self.rejects.record(
+                RejectKind::Row,
+                "DUPLICATE_ACCESSION",
+                &shared_entry.parent_id,
+                &shared_entry.accession,
+                || Some(format!("version={} {}", shared_entry.version.unwrap_or(0), reason)),
+            );
+            return Ok(Vec::new());
+        }
+
         // Track per-entry metrics before expansion.
         self.metrics
-            .add_features(entry.features.generic.len() as u64);
-        self.metrics.add_isoforms(entry.isoforms.len() as u64);
-
-        let shared_entry = Arc::new(entry);
+            .add_features(shared_entry.features.generic.len() as u64);
+        self.metrics.add_isoforms(shared_entry.isoforms.len() as u64);
+        self.metrics
+            .add_unknown_feature_types(shared_entry.unknown_feature_types as u64);
+        self.count_categories(&shared_entry);
+        self.verify_checksum(&shared_entry)?;
 
         if shared_entry.isoforms.is_empty() {
             let mapper = CoordinateMapper::from_entry(&shared_entry);
+            self.trace.log(&shared_entry.accession, "row", || {
+                format!("canonical row {} (no isoforms, identity mapping)", shared_entry.accession)
+            });
             let row = TransformedRow {
                 row_id: shared_entry.accession.clone(),
                 parent_id: shared_entry.accession.clone(),
                 sequence: shared_entry.sequence.clone(),
+                isoform_status: IsoformStatus::Canonical,
                 mapper,
                 entry: Arc::clone(&shared_entry),
+                extra: Vec::new(),
             };
-            return Ok(vec![row]);
+            let mut rows = vec![row];
+            self.filter_rows(&mut rows);
+            self.enrich(&mut rows)?;
+            return Ok(rows);
         }
 
         let sidecar = self
@@ -58,28 +214,212 @@ impl<M: MetricsCollector> EntryTransformer<M> {
         for iso in &shared_entry.isoforms {
             let isoform_id = canonical_isoform_id(iso);
             let Some(isoform_sequence) = sidecar.get(&isoform_id) else {
-                eprintln!(
-                    "[WARN] code=ISOFORM_SEQ_MISSING parent_id={} id={} isoform_id={}",
-                    shared_entry.parent_id, shared_entry.accession, isoform_id
+                log_warn!(
+                    "transform",
+                    "code=ISOFORM_SEQ_MISSING parent_id={} id={} isoform_id={}{}",
+                    shared_entry.parent_id,
+                    shared_entry.accession,
+                    isoform_id,
+                    if self.keep_unsequenced_isoforms { "; kept without sequence" } else { "" }
+                );
+                self.metrics.add_isoform_seq_missing(1);
+                if self.keep_unsequenced_isoforms {
+                    self.trace.log(&shared_entry.accession, "row", || {
+                        format!("isoform_id={} kept unsequenced: code=ISOFORM_SEQ_MISSING", isoform_id)
+                    });
+                    rows.push(TransformedRow {
+                        row_id: isoform_id,
+                        parent_id: shared_entry.parent_id.clone(),
+                        sequence: String::new(),
+                        isoform_status: IsoformStatus::Unsequenced,
+                        mapper: CoordinateMapper::from_entry_for_vsp_ids(&shared_entry, &iso.vsp_ids),
+                        entry: Arc::clone(&shared_entry),
+                        extra: Vec::new(),
+                    });
+                    continue;
+                }
+                self.trace.log(&shared_entry.accession, "row", || {
+                    format!("isoform_id={} dropped: code=ISOFORM_SEQ_MISSING", isoform_id)
+                });
+                self.metrics.add_rows_rejected(1);
+                self.rejects.record(
+                    RejectKind::Row,
+                    "ISOFORM_SEQ_MISSING",
+                    &shared_entry.parent_id,
+                    &isoform_id,
+                    || Some("isoform sequence not found in FASTA sidecar".to_string()),
                 );
                 continue;
             };
 
-            let mapper = CoordinateMapper::from_entry_for_vsp_ids(&shared_entry, &iso.vsp_ids);
+            let mut mapper = CoordinateMapper::from_entry_for_vsp_ids(&shared_entry, &iso.vsp_ids);
+            let expected_len = mapper.expected_len(shared_entry.sequence.len());
+            if let Some(conflict) = mapper.conflict() {
+                // The row is kept; only its PTM sites can't be placed
+                log_warn!(
+                    "transform",
+                    "code=VSP_CONFLICT parent_id={} id={} {}; PTM sites of this isoform are not mapped",
+                    shared_entry.parent_id, isoform_id, conflict
+                );
+                self.metrics.add_vsp_conflicts(1);
+            } else if expected_len != isoform_sequence.len() as i64 {
+                // Usually a mis-parsed VSP: sites would map, but shifted
+                log_warn!(
+                    "transform",
+                    "code=ISOFORM_LENGTH_MISMATCH parent_id={} id={} expected_len={} sidecar_len={} edits=[{}]{}",
+                    shared_entry.parent_id,
+                    isoform_id,
+                    expected_len,
+                    isoform_sequence.len(),
+                    mapper.describe_edits(),
+                    if self.downgrade_length_mismatch { "; mapped PTM sites get low confidence" } else { "" }
+                );
+                self.metrics.add_isoform_length_mismatch(1);
+                if self.downgrade_length_mismatch {
+                    mapper.mark_low_confidence();
+                }
+            }
+            self.trace.log(&shared_entry.accession, "row", || {
+                format!(
+                    "isoform_id={} sequence_len={} vsp_ids={:?} edits=[{}] total_delta={}",
+                    isoform_id,
+                    isoform_sequence.len(),
+                    iso.vsp_ids,
+                    mapper.describe_edits(),
+                    mapper.total_delta()
+                )
+            });
             rows.push(TransformedRow {
                 row_id: isoform_id,
                 parent_id: shared_entry.parent_id.clone(),
                 sequence: isoform_sequence.clone(),
+                isoform_status: IsoformStatus::Sequenced,
                 mapper,
                 entry: Arc::clone(&shared_entry),
+                extra: Vec::new(),
             });
         }
 
+        self.filter_rows(&mut rows);
+        self.enrich(&mut rows)?;
         Ok(rows)
     }
+
+    /// False (after counting and recording it) when the entry has no primary accession or
+    /// an empty sequence; an error instead under `InvalidEntryPolicy::Fail`.
+    fn check_valid(&self, entry: &ParsedEntry) -> Result<bool> {
+        let (code, reason) = if entry.accession.is_empty() {
+            ("MISSING_ACCESSION", "no primary accession")
+        } else if entry.sequence.is_empty() {
+            ("EMPTY_SEQUENCE", "empty sequence")
+        } else {
+            return Ok(true);
+        };
+
+        if self.invalid_entries == InvalidEntryPolicy::Fail {
+            return Err(EtlError::InvalidEntry {
+                accession: entry.accession.clone(),
+                reason: reason.to_string(),
+            });
+        }
+        log_warn!(
+            "transform",
+            "code={} parent_id={} id={}",
+            code, entry.parent_id, entry.accession
+        );
+        self.trace.log(&entry.accession, "row", || format!("entry dropped: code={}", code));
+        self.metrics.add_invalid_entries(1);
+        self.rejects.record(
+            RejectKind::Row,
+            code,
+            &entry.parent_id,
+            &entry.accession,
+            || Some(reason.to_string()),
+        );
+        Ok(false)
+    }
+
+    /// Counts the entry's parsed features and comments per category (once per entry,
+    /// not per isoform row).
+    fn count_categories(&self, entry: &ParsedEntry) {
+        let (features, comments) = (&entry.features, &entry.comments);
+        let counts = [
+            (FeatureCategory::Generic, features.generic.len()),
+            (FeatureCategory::ActiveSite, features.active_sites.len()),
+            (FeatureCategory::BindingSite, features.binding_sites.len()),
+            (FeatureCategory::MetalCoordination, features.metal_coordinations.len()),
+            (FeatureCategory::MutagenesisSite, features.mutagenesis_sites.len()),
+            (FeatureCategory::Domain, features.domains.len()),
+            (FeatureCategory::NaturalVariant, features.natural_variants.len()),
+            (FeatureCategory::SubcellularLocation, comments.locations.len()),
+            (FeatureCategory::Subunit, comments.subunits.len()),
+            (FeatureCategory::Interaction, comments.interactions.len()),
+            (FeatureCategory::OtherComment, comments.others.len()),
+        ];
+        for (category, count) in counts {
+            if count > 0 {
+                self.metrics.add_feature_category(category, count as u64);
+            }
+        }
+    }
+
+    /// Compares the canonical sequence's CRC64 with the `checksum` attribute, if any.
+    fn verify_checksum(&self, entry: &ParsedEntry) -> Result<()> {
+        let Some(expected) = entry.checksum.as_deref() else {
+            return Ok(());
+        };
+        let actual = crc64_hex(entry.sequence.as_bytes());
+        if actual.eq_ignore_ascii_case(expected) {
+            return Ok(());
+        }
+
+        log_warn!(
+            "transform",
+            "code=CHECKSUM_MISMATCH parent_id={} id={} expected={} actual={}",
+            entry.parent_id, entry.accession, expected, actual
+        );
+        self.metrics.add_checksum_mismatches(1);
+        if self.strict_checksums {
+            return Err(EtlError::ChecksumMismatch {
+                accession: entry.accession.clone(),
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    fn filter_rows(&self, rows: &mut Vec<TransformedRow>) {
+        let Some(filter) = &self.row_filter else {
+            return;
+        };
+        let before = rows.len();
+        rows.retain(|row| {
+            let keep = filter.matches(row);
+            if !keep {
+                self.trace.log(&row.entry.accession, "row", || format!("row {} dropped by filters.row_expr", row.row_id));
+            }
+            keep
+        });
+        let dropped = (before - rows.len()) as u64;
+        if dropped > 0 {
+            self.metrics.add_rows_filtered(dropped);
+        }
+    }
+
+    fn enrich(&self, rows: &mut Vec<TransformedRow>) -> Result<()> {
+        let before = rows.len();
+        self.enrichers.apply(rows, &self.rejects)?;
+        let dropped = (before - rows.len()) as u64;
+        if dropped > 0 {
+            self.metrics.add_rows_rejected(dropped);
+        }
+        Ok(())
+    }
 }
 
-fn canonical_isoform_id(iso: &IsoformScratch) -> String {
+/// Returns the sidecar lookup key (e.g. `P04637-2`) for an isoform.
+pub fn canonical_isoform_id(iso: &IsoformScratch) -> String {
     match iso.isoform_sequence.as_deref() {
         Some(r) if !r.starts_with("VSP_") && r.contains('-') => {
             r.split_whitespace().next().unwrap_or(r).to_string()
@@ -92,3 +432,26 @@ fn canonical_isoform_id(iso: &IsoformScratch) -> String {
             .to_string(),
     }
 }
+
+/// Whether a comment scoped to `molecule` (e.g. "Isoform 2") belongs on the row `row_id`.
+///
+/// Unscoped comments, entries without isoforms and molecules that name no known
+/// isoform apply to every row.
+pub fn molecule_applies_to_row(entry: &ParsedEntry, molecule: Option<&str>, row_id: &str) -> bool {
+    let Some(molecule) = molecule else {
+        return true;
+    };
+    let name = molecule.trim();
+    let name = name.strip_prefix("Isoform ").unwrap_or(name).trim();
+    entry
+        .isoforms
+        .iter()
+        .find(|iso| iso.names.iter().any(|n| n.trim() == name))
+        .is_none_or(|iso| canonical_isoform_id(iso) == row_id)
+}
+
+/// Whether a feature whose coordinates refer to the isoform `sequence` (`<location sequence>`)
+/// belongs on the row `row_id`. Features in canonical coordinates go on every row.
+pub fn sequence_applies_to_row(sequence: Option<&str>, row_id: &str) -> bool {
+    sequence.is_none_or(|sequence| sequence == row_id)
+}
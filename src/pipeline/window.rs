@@ -0,0 +1,88 @@
+//! Residue windows around a sequence position (PTM site context, training tables).
+
+use serde::{Deserialize, Serialize};
+
+/// What a window holds where it runs past the N- or C-terminus.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowPadding {
+    /// Fill with `-`, keeping every window `2k + 1` long with the site in the middle
+    #[default]
+    Gap,
+    /// Fill with `X` (unknown residue), for tokenizers without a gap symbol
+    Unknown,
+    /// Cut the window at the terminus, so it is shorter near either end
+    Truncate,
+}
+
+impl WindowPadding {
+    fn fill(self) -> Option<char> {
+        match self {
+            WindowPadding::Gap => Some('-'),
+            WindowPadding::Unknown => Some('X'),
+            WindowPadding::Truncate => None,
+        }
+    }
+}
+
+/// Window length and terminal policy of a site context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SiteContext {
+    /// Residues either side of the site
+    pub flank: usize,
+    pub padding: WindowPadding,
+}
+
+/// The `k` residues either side of the 1-based `pos`, plus `pos` itself, with the
+/// termini padded by `-`. None if `pos` is outside the sequence.
+pub fn sequence_window(sequence: &str, pos: usize, k: usize) -> Option<String> {
+    sequence_window_with(sequence, pos, k, WindowPadding::Gap)
+}
+
+/// Like `sequence_window`, with the given terminal `padding`.
+pub fn sequence_window_with(sequence: &str, pos: usize, k: usize, padding: WindowPadding) -> Option<String> {
+    let bytes = sequence.as_bytes();
+    if pos == 0 || pos > bytes.len() {
+        return None;
+    }
+    let centre = pos - 1;
+    let start = centre.saturating_sub(k);
+    let end = (centre + k + 1).min(bytes.len());
+
+    let mut window = String::with_capacity(2 * k + 1);
+    let fill = padding.fill();
+    if let Some(fill) = fill {
+        window.extend(std::iter::repeat_n(fill, k - (centre - start)));
+    }
+    window.extend(bytes[start..end].iter().map(|&aa| aa as char));
+    if let Some(fill) = fill {
+        window.extend(std::iter::repeat_n(fill, centre + k + 1 - end));
+    }
+    Some(window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pads_or_truncates_at_the_termini() {
+        assert_eq!(sequence_window("MSKT", 2, 2).as_deref(), Some("-MSKT"));
+        assert_eq!(sequence_window("MSKT", 4, 2).as_deref(), Some("SKT--"));
+        assert_eq!(sequence_window("MSKT", 3, 0).as_deref(), Some("K"));
+        assert_eq!(
+            sequence_window_with("MSKT", 1, 2, WindowPadding::Unknown).as_deref(),
+            Some("XXMSK")
+        );
+        assert_eq!(
+            sequence_window_with("MSKT", 4, 2, WindowPadding::Truncate).as_deref(),
+            Some("SKT")
+        );
+    }
+
+    #[test]
+    fn rejects_positions_outside_the_sequence() {
+        assert_eq!(sequence_window("MSKT", 0, 2), None);
+        assert_eq!(sequence_window("MSKT", 5, 2), None);
+    }
+}
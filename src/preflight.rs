@@ -0,0 +1,216 @@
+//! Entry-count preflight (`performance.preflight`).
+//!
+//! Before the run every input is scanned for `<entry` start tags with memchr's SIMD
+//! substring search, without parsing XML, giving an estimate of each file's entries and
+//! decompressed bytes. The totals turn the progress line into a percentage with an ETA,
+//! and swarm mode uses the per-file sizes to start the largest files first, so a big
+//! file isn't left to run alone at the end.
+//!
+//! With `performance.preflight_sample_mb` only the first N decompressed MB of a file are
+//! scanned. For `.gz` inputs the compression ratio of that prefix extrapolates the
+//! decompressed size from the file size, and the entry count is scaled by the same
+//! factor, which assumes entries of similar size throughout the file.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use memchr::memmem;
+use rayon::prelude::*;
+use serde::Serialize;
+
+const ENTRY_TAG: &[u8] = b"<entry";
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Estimated work of one input file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileEstimate {
+    pub path: PathBuf,
+    pub entries: u64,
+    /// Decompressed XML bytes (what `bytes_read` counts during the run)
+    pub bytes: u64,
+    /// Whole file scanned rather than extrapolated from a sample
+    pub exact: bool,
+}
+
+/// Per-file estimates of a run's inputs, in input order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Preflight {
+    pub total_entries: u64,
+    pub total_bytes: u64,
+    pub files: Vec<FileEstimate>,
+}
+
+impl Preflight {
+    fn new(files: Vec<FileEstimate>) -> Self {
+        Self {
+            total_entries: files.iter().map(|f| f.entries).sum(),
+            total_bytes: files.iter().map(|f| f.bytes).sum(),
+            files,
+        }
+    }
+
+    /// Whether every file was counted in full.
+    pub fn is_exact(&self) -> bool {
+        self.files.iter().all(|f| f.exact)
+    }
+
+    /// Estimated decompressed bytes of `path` (None when it was not scanned).
+    pub fn bytes_of(&self, path: &Path) -> Option<u64> {
+        self.files.iter().find(|f| f.path == path).map(|f| f.bytes)
+    }
+}
+
+/// Estimates every file in parallel; `sample_bytes` limits the scan of each file.
+pub fn scan(files: &[PathBuf], sample_bytes: Option<u64>) -> Result<Preflight> {
+    let estimates = files
+        .par_iter()
+        .map(|path| estimate_file(path, sample_bytes))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Preflight::new(estimates))
+}
+
+/// Counts (or samples) the entries of one `.xml` / `.xml.gz` file.
+pub fn estimate_file(path: &Path, sample_bytes: Option<u64>) -> Result<FileEstimate> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let file_size = file.metadata()?.len();
+    let mut consumed = 0;
+    let source = CountingReader {
+        inner: file,
+        count: &mut consumed,
+    };
+    let tally = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz")) {
+        count_entries(MultiGzDecoder::new(source), sample_bytes)
+    } else {
+        count_entries(source, sample_bytes)
+    }
+    .with_context(|| format!("Failed to scan {}", path.display()))?;
+
+    Ok(extrapolate(path, tally, consumed, file_size))
+}
+
+/// Scales a sampled tally up to the whole file; `consumed` of `file_size` on-disk bytes
+/// produced the tally's decompressed bytes.
+fn extrapolate(path: &Path, tally: Tally, consumed: u64, file_size: u64) -> FileEstimate {
+    let (entries, bytes) = if tally.complete || tally.bytes == 0 || consumed == 0 {
+        (tally.entries, tally.bytes)
+    } else {
+        let bytes = tally.bytes as f64 * file_size as f64 / consumed as f64;
+        let entries = tally.entries as f64 * bytes / tally.bytes as f64;
+        (entries.round() as u64, bytes.round() as u64)
+    };
+    FileEstimate {
+        path: path.to_path_buf(),
+        entries,
+        bytes,
+        exact: tally.complete,
+    }
+}
+
+/// `<entry` tags in the scanned prefix of a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Tally {
+    entries: u64,
+    bytes: u64,
+    /// Reached the end of the input
+    complete: bool,
+}
+
+/// Counts `<entry` start tags (not `<entryName>`-style prefixes) in up to `limit` bytes
+/// of `reader`. The last bytes of each chunk are carried over so tags split across
+/// chunk boundaries are counted once.
+fn count_entries<R: Read>(mut reader: R, limit: Option<u64>) -> io::Result<Tally> {
+    let finder = memmem::Finder::new(ENTRY_TAG);
+    let mut buf = vec![0u8; ENTRY_TAG.len() + CHUNK_SIZE];
+    let mut carried = 0;
+    let mut tally = Tally::default();
+    loop {
+        let want = limit.map_or(CHUNK_SIZE, |limit| CHUNK_SIZE.min((limit - tally.bytes) as usize));
+        if want == 0 {
+            return Ok(tally);
+        }
+        let n = match reader.read(&mut buf[carried..carried + want]) {
+            Ok(0) => {
+                tally.complete = true;
+                return Ok(tally);
+            }
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        tally.bytes += n as u64;
+
+        // A tag ending the window is left for the next chunk, which knows its next byte
+        let filled = carried + n;
+        let window = &buf[..filled];
+        tally.entries += finder
+            .find_iter(window)
+            .filter(|&at| window.get(at + ENTRY_TAG.len()).is_some_and(|&b| b == b'>' || b.is_ascii_whitespace()))
+            .count() as u64;
+        carried = filled.min(ENTRY_TAG.len());
+        buf.copy_within(filled - carried..filled, 0);
+    }
+}
+
+/// Counts the bytes read from the file underneath a decoder.
+struct CountingReader<'a, R> {
+    inner: R,
+    count: &'a mut u64,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        *self.count += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Yields at most `step` bytes per read, to split tags across chunks.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn counts_entry_tags_across_chunk_boundaries() {
+        let xml = b"<uniprot><entry dataset=\"Swiss-Prot\"><entryName/></entry>\n<entry>\
+                    </entry><entry\nversion=\"2\"></entry></uniprot>";
+        for step in [1, 3, 7, xml.len()] {
+            let tally = count_entries(Trickle { data: xml, step }, None).unwrap();
+            assert_eq!(tally.entries, 3, "step {}", step);
+            assert_eq!(tally.bytes, xml.len() as u64);
+            assert!(tally.complete);
+        }
+    }
+
+    #[test]
+    fn extrapolates_a_sampled_prefix() {
+        let xml = "<entry></entry>".repeat(100);
+        let tally = count_entries(xml.as_bytes(), Some(150)).unwrap();
+        assert_eq!(tally, Tally { entries: 10, bytes: 150, complete: false });
+
+        // 150 decompressed bytes from 50 compressed ones of a 500-byte file
+        let estimate = extrapolate(Path::new("a.xml.gz"), tally, 50, 500);
+        assert_eq!((estimate.entries, estimate.bytes, estimate.exact), (100, 1500, false));
+
+        let whole = count_entries(xml.as_bytes(), Some(10_000)).unwrap();
+        let estimate = extrapolate(Path::new("a.xml"), whole, 1500, 1500);
+        assert_eq!((estimate.entries, estimate.bytes, estimate.exact), (100, 1500, true));
+    }
+}
@@ -0,0 +1,64 @@
+//! In-process CPU profiling for `--profile-cpu` (requires the `profiling` cargo feature).
+//!
+//! Samples the whole process while the pipeline runs and writes a flamegraph SVG and a
+//! pprof protobuf (`go tool pprof`, speedscope, ...) into the run's `profiles/` directory.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Sampling frequency in Hz (not a multiple of common timer frequencies).
+pub const SAMPLE_FREQUENCY: i32 = 99;
+/// Flamegraph written by `CpuProfiler::finish`.
+pub const FLAMEGRAPH_FILE: &str = "cpu_flamegraph.svg";
+/// pprof protobuf written by `CpuProfiler::finish`.
+pub const PPROF_FILE: &str = "cpu_profile.pb";
+
+/// A running CPU profile.
+pub struct CpuProfiler {
+    #[cfg(feature = "profiling")]
+    guard: pprof::ProfilerGuard<'static>,
+}
+
+impl CpuProfiler {
+    /// Starts sampling; fails in builds without the `profiling` feature.
+    #[cfg(feature = "profiling")]
+    pub fn start() -> Result<Self> {
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(SAMPLE_FREQUENCY)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()?;
+        Ok(Self { guard })
+    }
+
+    #[cfg(not(feature = "profiling"))]
+    pub fn start() -> Result<Self> {
+        Err(anyhow::anyhow!("--profile-cpu requires a build with `--features profiling`"))
+    }
+
+    /// Stops sampling and writes the flamegraph and protobuf into `dir`; returns their paths.
+    #[cfg(feature = "profiling")]
+    pub fn finish(self, dir: &Path) -> Result<Vec<PathBuf>> {
+        use anyhow::Context;
+        use pprof::protos::Message;
+        use std::fs::{self, File};
+
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let report = self.guard.report().build()?;
+
+        let flamegraph = dir.join(FLAMEGRAPH_FILE);
+        let file = File::create(&flamegraph).with_context(|| format!("Failed to create {}", flamegraph.display()))?;
+        report.flamegraph(file)?;
+
+        let pprof = dir.join(PPROF_FILE);
+        let mut encoded = Vec::new();
+        report.pprof()?.encode(&mut encoded)?;
+        fs::write(&pprof, encoded).with_context(|| format!("Failed to write {}", pprof.display()))?;
+
+        Ok(vec![flamegraph, pprof])
+    }
+
+    #[cfg(not(feature = "profiling"))]
+    pub fn finish(self, _dir: &Path) -> Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,250 @@
+//! Reference-proteome download workflow (`uniprot_etl fetch-proteome`).
+//!
+//! The proteome's entries are streamed from the UniProtKB REST API as gzipped XML,
+//! together with the isoform FASTA for the same proteome (used as the sidecar). Both
+//! land in a per-proteome cache directory next to a `proteome.json` recording their
+//! SHA-256 and the UniProt release; cached files are re-verified before reuse.
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::sidecar::sha256_file;
+use crate::{log_info, log_warn};
+
+/// A downloaded artifact and its checksum.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DownloadedFile {
+    pub url: String,
+    pub path: PathBuf,
+    /// SHA-256 of the local file (hex)
+    pub sha256: String,
+}
+
+/// Provenance of a `fetch-proteome` input, recorded in report.yaml.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProteomeInfo {
+    pub proteome_id: String,
+    /// UniProt release the files were served from (`X-UniProt-Release`)
+    pub release: Option<String>,
+    pub release_date: Option<String>,
+    pub xml: DownloadedFile,
+    pub isoform_fasta: Option<DownloadedFile>,
+    /// True if verified cached files were reused instead of downloading
+    #[serde(skip_deserializing)]
+    pub cache_hit: bool,
+}
+
+/// True for proteome identifiers like `UP000005640`.
+pub fn is_valid_proteome_id(id: &str) -> bool {
+    id.len() > 2 && id.starts_with("UP") && id[2..].bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Downloads (or reuses from `cache_dir`) the XML and isoform FASTA of a proteome.
+///
+/// `refresh` ignores the cache. Cached files whose checksum no longer matches
+/// `proteome.json` are downloaded again.
+pub fn fetch_proteome(
+    base_url: &str,
+    proteome_id: &str,
+    cache_dir: &Path,
+    with_isoforms: bool,
+    refresh: bool,
+) -> Result<ProteomeInfo> {
+    if !is_valid_proteome_id(proteome_id) {
+        return Err(anyhow!("Invalid proteome id: {:?}", proteome_id));
+    }
+    let dir = cache_dir.join("proteomes").join(proteome_id);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let meta_path = dir.join("proteome.json");
+
+    if !refresh {
+        if let Some(mut cached) = load_verified(&meta_path)? {
+            if cached.isoform_fasta.is_some() || !with_isoforms {
+                cached.cache_hit = true;
+                return Ok(cached);
+            }
+        }
+    }
+
+    let base_url = base_url.trim_end_matches('/');
+    let query = format!("{}/stream?query=proteome:{}", base_url, proteome_id);
+
+    let xml_url = format!("{}&format=xml&compressed=true", query);
+    let xml_path = dir.join(format!("{}.xml.gz", proteome_id));
+    let release = download(&xml_url, &xml_path, false)?;
+    verify_gzip(&xml_path)?;
+
+    let isoform_fasta = if with_isoforms {
+        let url = format!("{}&format=fasta&includeIsoform=true&compressed=true", query);
+        let path = dir.join(format!("{}_isoforms.fasta", proteome_id));
+        download(&url, &path, true)?;
+        Some(DownloadedFile {
+            sha256: sha256_file(&path)?,
+            url,
+            path,
+        })
+    } else {
+        None
+    };
+
+    let info = ProteomeInfo {
+        proteome_id: proteome_id.to_string(),
+        release: release.version,
+        release_date: release.date,
+        xml: DownloadedFile {
+            sha256: sha256_file(&xml_path)?,
+            url: xml_url,
+            path: xml_path,
+        },
+        isoform_fasta,
+        cache_hit: false,
+    };
+    fs::write(&meta_path, serde_json::to_string_pretty(&info)?)
+        .with_context(|| format!("Failed to write {}", meta_path.display()))?;
+    Ok(info)
+}
+
+/// Cached proteome metadata, if present and every recorded file still matches its checksum.
+fn load_verified(meta_path: &Path) -> Result<Option<ProteomeInfo>> {
+    let Ok(text) = fs::read_to_string(meta_path) else {
+        return Ok(None);
+    };
+    let Ok(info) = serde_json::from_str::<ProteomeInfo>(&text) else {
+        log_warn!("proteome", "Ignoring unreadable {}", meta_path.display());
+        return Ok(None);
+    };
+
+    for file in std::iter::once(&info.xml).chain(&info.isoform_fasta) {
+        if !file.path.exists() || sha256_file(&file.path)? != file.sha256 {
+            log_warn!(
+                "proteome",
+                "Cached {} is missing or fails its checksum; downloading again",
+                file.path.display()
+            );
+            return Ok(None);
+        }
+    }
+    Ok(Some(info))
+}
+
+/// Release advertised by the UniProt REST API response headers.
+#[derive(Default)]
+struct ReleaseHeaders {
+    version: Option<String>,
+    date: Option<String>,
+}
+
+/// Downloads `url` to `dest` via a `.part` file, optionally gunzipping on the fly.
+fn download(url: &str, dest: &Path, gunzip: bool) -> Result<ReleaseHeaders> {
+    log_info!("proteome", "Downloading {}", url);
+    let response = ureq::get(url)
+        .call()
+        .with_context(|| format!("Failed to download {}", url))?;
+    let release = ReleaseHeaders {
+        version: response.header("X-UniProt-Release").map(str::to_string),
+        date: response.header("X-UniProt-Release-Date").map(str::to_string),
+    };
+
+    let body = response.into_reader();
+    let mut reader: Box<dyn Read> = if gunzip {
+        Box::new(GzDecoder::new(body))
+    } else {
+        Box::new(body)
+    };
+
+    let mut part = dest.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let mut out = File::create(&part).with_context(|| format!("Failed to create {}", part.display()))?;
+    io::copy(&mut reader, &mut out)
+        .with_context(|| format!("Failed to write {}", part.display()))?;
+    drop(out);
+
+    fs::rename(&part, dest)
+        .with_context(|| format!("Failed to move {} into place", part.display()))?;
+    Ok(release)
+}
+
+/// Decompresses `path` end to end so a truncated or corrupt download fails here
+/// (gzip CRC32/length trailer) rather than halfway through the run.
+fn verify_gzip(path: &Path) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    io::copy(&mut GzDecoder::new(io::BufReader::new(file)), &mut io::sink())
+        .with_context(|| format!("Gzip integrity check failed for {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use tiny_http::{Header, Response, Server};
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_is_valid_proteome_id() {
+        assert!(is_valid_proteome_id("UP000005640"));
+        assert!(!is_valid_proteome_id("UP"));
+        assert!(!is_valid_proteome_id("P04637"));
+        assert!(!is_valid_proteome_id("UP0000&x=1"));
+    }
+
+    #[test]
+    fn test_fetch_proteome_downloads_then_reuses_cache() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", server.server_addr());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let body = if request.url().contains("format=xml") {
+                    gzip(b"<uniprot><entry><accession>P1</accession></entry></uniprot>")
+                } else {
+                    gzip(b">sp|P1-2|X\nMK\n")
+                };
+                let response = Response::from_data(body)
+                    .with_header(Header::from_bytes("X-UniProt-Release", "2024_06").unwrap());
+                request.respond(response).unwrap();
+            }
+        });
+
+        let cache = std::env::temp_dir().join("uniprot_etl_test_proteome_cache");
+        let _ = fs::remove_dir_all(&cache);
+
+        let info = fetch_proteome(&base_url, "UP000000001", &cache, true, false).unwrap();
+        assert!(!info.cache_hit);
+        assert_eq!(info.release.as_deref(), Some("2024_06"));
+        assert!(info.xml.url.contains("query=proteome:UP000000001"));
+        let fasta = info.isoform_fasta.as_ref().unwrap();
+        assert_eq!(fs::read_to_string(&fasta.path).unwrap(), ">sp|P1-2|X\nMK\n");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        let cached = fetch_proteome(&base_url, "UP000000001", &cache, true, false).unwrap();
+        assert!(cached.cache_hit);
+        assert_eq!(cached.xml, info.xml);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // A corrupted cache entry is detected and replaced
+        fs::write(&fasta.path, ">tampered\n").unwrap();
+        let again = fetch_proteome(&base_url, "UP000000001", &cache, true, false).unwrap();
+        assert!(!again.cache_hit);
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+        fs::remove_dir_all(&cache).unwrap();
+    }
+}
@@ -0,0 +1,394 @@
+//! PTM mapping regression fixtures (`ptm_fixtures` binary, `tests/ptm_regressions.rs`).
+//!
+//! Turns `[PTM_FAIL]` records (stderr log lines or a run's `rejected.parquet`) into
+//! minimized JSON fixtures: the canonical and row sequence fragments around the site,
+//! the row's VSP edits and the site itself, all rebased onto the fragments. Replaying a
+//! fixture runs the pipeline's own `resolve_site`, so once a fixture is checked in with
+//! the corrected `expected` outcome, the production mapping bug it captured stays fixed.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::Array;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::builders::ptm::{resolve_site, SiteFrame, SiteOutcome};
+use crate::pipeline::mapper::CoordinateMapper;
+use crate::pipeline::scratch::{FeatureScratch, ParsedEntry};
+use crate::pipeline::transformer::canonical_isoform_id;
+use crate::stats::utf8;
+
+/// Where the regression harness looks for fixtures, relative to the crate root.
+pub const FIXTURE_DIR: &str = "tests/fixtures/ptm_regressions";
+
+/// Outcome of a site that lands on the row.
+pub const MAPPED: &str = "MAPPED";
+
+/// Residues kept on either side of the site and the edits before it.
+const FLANK: usize = 10;
+
+/// One failed PTM site, as logged by the pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PtmFailure {
+    pub code: String,
+    pub parent_id: String,
+    pub id: String,
+    pub original_index: i32,
+}
+
+/// Parses a `[PTM_FAIL] code=... parent_id=... id=... original_index=...` log line.
+pub fn parse_fail_line(line: &str) -> Option<PtmFailure> {
+    let rest = &line[line.find("[PTM_FAIL]")? + "[PTM_FAIL]".len()..];
+    let field = |name: &str| {
+        rest.split_whitespace()
+            .find_map(|token| token.strip_prefix(name)?.strip_prefix('='))
+    };
+    Some(PtmFailure {
+        code: field("code")?.to_string(),
+        parent_id: field("parent_id")?.to_string(),
+        id: field("id")?.to_string(),
+        original_index: field("original_index")?.parse().ok()?,
+    })
+}
+
+/// The `[PTM_FAIL]` records of a captured stderr log.
+pub fn read_fail_log(path: &Path) -> Result<Vec<PtmFailure>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut failures = Vec::new();
+    for line in BufReader::new(file).lines() {
+        failures.extend(parse_fail_line(&line?));
+    }
+    Ok(failures)
+}
+
+/// The PTM rejections of a run's `rejected.parquet` (`detail` = "ptm original_index=N").
+pub fn read_rejected(path: &Path) -> Result<Vec<PtmFailure>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut failures = Vec::new();
+    for batch in ParquetRecordBatchReaderBuilder::try_new(file)?.build()? {
+        let batch = batch?;
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .ok_or_else(|| anyhow!("{} has no {} column", path.display(), name))
+                .and_then(|array| utf8(array.as_ref()))
+        };
+        let (parent_ids, ids, codes, details) =
+            (column("parent_id")?, column("id")?, column("code")?, column("detail")?);
+        for row in 0..batch.num_rows() {
+            let index = details
+                .is_valid(row)
+                .then(|| details.value(row))
+                .and_then(|detail| detail.strip_prefix("ptm original_index="))
+                .and_then(|index| index.parse().ok());
+            if let Some(original_index) = index {
+                failures.push(PtmFailure {
+                    code: codes.value(row).to_string(),
+                    parent_id: parent_ids.value(row).to_string(),
+                    id: ids.value(row).to_string(),
+                    original_index,
+                });
+            }
+        }
+    }
+    Ok(failures)
+}
+
+/// Coordinate frame of a fixture (see `SiteFrame`).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FixtureFrame {
+    Canonical,
+    Isoform,
+    RowSequence,
+}
+
+/// A minimized, replayable PTM mapping case.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PtmFixture {
+    /// Outcome seen in production
+    pub observed: String,
+    /// Outcome the pipeline must produce: a `[PTM_FAIL]` code or `MAPPED`. Starts out
+    /// as `observed`; set it to the correct outcome when fixing the bug.
+    pub expected: String,
+    pub parent_id: String,
+    pub id: String,
+    /// 1-based site in the source entry
+    pub original_index: i32,
+    pub frame: FixtureFrame,
+    /// Residues cut from the start of both fragments
+    pub offset: usize,
+    /// 1-based site in the fragments
+    pub site: i32,
+    pub canonical: String,
+    /// Row sequence fragment; empty in the canonical frame, where the row is `canonical`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub row_sequence: String,
+    /// VSP edits of the row, in fragment coordinates
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edits: Vec<FixtureEdit>,
+}
+
+/// A splice-variant feature of a fixture.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FixtureEdit {
+    pub feature_type: String,
+    pub begin: i32,
+    pub end: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl PtmFixture {
+    /// Runs the site through the pipeline's mapping and returns the outcome code.
+    pub fn replay(&self) -> &'static str {
+        outcome_code(self.resolve())
+    }
+
+    fn resolve(&self) -> SiteOutcome {
+        let features = self
+            .edits
+            .iter()
+            .enumerate()
+            .map(|(i, edit)| FeatureScratch {
+                id: Some(format!("VSP_{}", i)),
+                feature_type: edit.feature_type.clone(),
+                start: Some(edit.begin),
+                end: Some(edit.end),
+                variation: edit.variation.clone(),
+                description: edit.description.clone(),
+                ..Default::default()
+            });
+        let mut entry = ParsedEntry::default();
+        entry.features.generic.extend(features);
+        let vsp_ids: Vec<String> = (0..self.edits.len()).map(|i| format!("VSP_{}", i)).collect();
+        let mapper = CoordinateMapper::from_entry_for_vsp_ids(&entry, &vsp_ids);
+
+        let (frame, row) = match self.frame {
+            FixtureFrame::Canonical => (SiteFrame::Canonical, &self.canonical),
+            FixtureFrame::Isoform => (SiteFrame::Isoform(&mapper), &self.row_sequence),
+            FixtureFrame::RowSequence => (SiteFrame::RowSequence, &self.row_sequence),
+        };
+        resolve_site(self.canonical.as_bytes(), row.as_bytes(), self.site, frame)
+    }
+
+    /// Fixture file name, e.g. `residue_mismatch_P04637-2_120.json`.
+    pub fn file_name(&self) -> String {
+        format!("{}_{}_{}.json", self.observed.to_ascii_lowercase(), self.id, self.original_index)
+    }
+}
+
+/// `MAPPED` or the failure code of `outcome`.
+pub fn outcome_code(outcome: SiteOutcome) -> &'static str {
+    match outcome {
+        SiteOutcome::Mapped { .. } => MAPPED,
+        SiteOutcome::Failed { code, .. } => code,
+    }
+}
+
+/// Builds the minimized fixture for `failure` from its source entry and the row's
+/// sequence (the sidecar FASTA sequence for isoform rows).
+///
+/// Returns None when the entry no longer reproduces the failure. A minimized fixture
+/// that replays differently is replaced by the unminimized one.
+pub fn build_fixture(entry: &ParsedEntry, row_sequence: &str, failure: &PtmFailure) -> Option<PtmFixture> {
+    let full = full_fixture(entry, row_sequence, failure);
+    if full.replay() != failure.code {
+        return None;
+    }
+    let minimized = minimize(&full);
+    Some(if minimized.replay() == failure.code { minimized } else { full })
+}
+
+fn full_fixture(entry: &ParsedEntry, row_sequence: &str, failure: &PtmFailure) -> PtmFixture {
+    let site = failure.original_index;
+    let isoform_located = entry.features.generic.iter().any(|feat| {
+        feat.start == Some(site) && feat.end == Some(site) && feat.sequence.as_deref() == Some(failure.id.as_str())
+    });
+    let frame = if isoform_located {
+        FixtureFrame::RowSequence
+    } else if failure.id == failure.parent_id {
+        FixtureFrame::Canonical
+    } else {
+        FixtureFrame::Isoform
+    };
+
+    let vsp_ids: HashSet<&str> = entry
+        .isoforms
+        .iter()
+        .find(|iso| canonical_isoform_id(iso) == failure.id)
+        .map(|iso| iso.vsp_ids.iter().map(String::as_str).collect())
+        .unwrap_or_default();
+    let edits = match frame {
+        FixtureFrame::Isoform => entry
+            .features
+            .generic
+            .iter()
+            .filter(|feat| feat.id.as_deref().is_some_and(|id| vsp_ids.contains(id)))
+            .filter_map(|feat| {
+                Some(FixtureEdit {
+                    feature_type: feat.feature_type.clone(),
+                    begin: feat.start?,
+                    end: feat.end?,
+                    variation: feat.variation.clone(),
+                    description: feat.description.clone(),
+                })
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    PtmFixture {
+        observed: failure.code.clone(),
+        expected: failure.code.clone(),
+        parent_id: failure.parent_id.clone(),
+        id: failure.id.clone(),
+        original_index: site,
+        frame,
+        offset: 0,
+        site,
+        canonical: match frame {
+            FixtureFrame::RowSequence => String::new(),
+            _ => entry.sequence.clone(),
+        },
+        row_sequence: match frame {
+            FixtureFrame::Canonical => String::new(),
+            _ => row_sequence.to_string(),
+        },
+        edits,
+    }
+}
+
+/// Cuts both sequences to the site and the edits before it, plus `FLANK` residues.
+///
+/// Edits after the site cannot move it and are dropped; the prefix before the first
+/// kept edit is identical in both sequences, so cutting it shifts every coordinate alike.
+fn minimize(full: &PtmFixture) -> PtmFixture {
+    let site = full.site as usize;
+    let edits: Vec<&FixtureEdit> = full.edits.iter().filter(|edit| edit.begin <= full.site).collect();
+    let reference = match full.frame {
+        FixtureFrame::RowSequence => &full.row_sequence,
+        _ => &full.canonical,
+    };
+
+    let first = edits.iter().map(|edit| edit.begin as usize).min().unwrap_or(site);
+    let offset = first.min(site).min(reference.len() + 1).saturating_sub(FLANK + 1);
+    let last = edits.iter().map(|edit| edit.end as usize).max().unwrap_or(site).max(site);
+    let cut = |sequence: &str, end: usize| {
+        let start = offset.min(sequence.len());
+        sequence[start..end.clamp(start, sequence.len())].to_string()
+    };
+
+    // Only the row position decides an outcome past the mapping, so keep the row to
+    // there (or whole, when the site falls off its end)
+    let mapped = match full.resolve() {
+        SiteOutcome::Mapped { index, .. } => Some(index),
+        SiteOutcome::Failed { mapped, .. } => mapped,
+    };
+    let row_end = match mapped {
+        Some(mapped) if (mapped as usize) <= full.row_sequence.len() => mapped as usize + FLANK,
+        Some(_) => usize::MAX,
+        None => last + FLANK,
+    };
+
+    PtmFixture {
+        offset,
+        site: (site - offset) as i32,
+        canonical: cut(&full.canonical, last + FLANK),
+        row_sequence: cut(&full.row_sequence, row_end),
+        edits: edits
+            .into_iter()
+            .map(|edit| FixtureEdit {
+                begin: edit.begin - offset as i32,
+                end: edit.end - offset as i32,
+                ..edit.clone()
+            })
+            .collect(),
+        ..full.clone()
+    }
+}
+
+/// Writes `fixture` into `dir` unless a fixture of the same name exists (its
+/// `expected` may have been curated). Returns whether it was written.
+pub fn write_fixture(dir: &Path, fixture: &PtmFixture) -> Result<bool> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(fixture.file_name());
+    if path.exists() {
+        return Ok(false);
+    }
+    fs::write(&path, serde_json::to_string_pretty(fixture)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::scratch::IsoformScratch;
+
+    #[test]
+    fn parses_ptm_fail_log_lines() {
+        let line = "[PTM_FAIL] code=RESIDUE_MISMATCH parent_id=P04637 id=P04637-2 original_index=120 mapped_index=96 original_aa=S isoform_aa=T shift=-24 vsp_count=1";
+        assert_eq!(
+            parse_fail_line(line),
+            Some(PtmFailure {
+                code: "RESIDUE_MISMATCH".to_string(),
+                parent_id: "P04637".to_string(),
+                id: "P04637-2".to_string(),
+                original_index: 120,
+            })
+        );
+        assert_eq!(parse_fail_line("[WARN] code=ISOFORM_SEQ_MISSING parent_id=P1 id=P1-2"), None);
+    }
+
+    #[test]
+    fn minimized_fixture_replays_like_the_entry() {
+        // 200 residues; VSP at 101-110 -> "PQ" (delta -8); the sidecar isoform
+        // disagrees at the mapped position of canonical 150
+        let canonical: String = "ACDEFGHIKLMNPQRSTVWY".repeat(10);
+        let mut isoform = format!("{}PQ{}", &canonical[..100], &canonical[110..]);
+        isoform.replace_range(141..142, "W");
+        let mut entry = ParsedEntry {
+            accession: "P1MIN".to_string(),
+            parent_id: "P1MIN".to_string(),
+            sequence: canonical,
+            ..Default::default()
+        };
+        entry.features.generic.push(FeatureScratch {
+            id: Some("VSP_1".to_string()),
+            feature_type: "splice variant".to_string(),
+            start: Some(101),
+            end: Some(110),
+            variation: Some("PQ".to_string()),
+            ..Default::default()
+        });
+        entry.isoforms.push(IsoformScratch {
+            isoform_id: "P1MIN-2".to_string(),
+            vsp_ids: vec!["VSP_1".to_string()],
+            ..Default::default()
+        });
+
+        let failure = PtmFailure {
+            code: "RESIDUE_MISMATCH".to_string(),
+            parent_id: "P1MIN".to_string(),
+            id: "P1MIN-2".to_string(),
+            original_index: 150,
+        };
+        let fixture = build_fixture(&entry, &isoform, &failure).unwrap();
+        assert_eq!(fixture.replay(), "RESIDUE_MISMATCH");
+        assert_eq!(fixture.offset, 90);
+        assert_eq!(fixture.site, 60);
+        assert_eq!(fixture.edits[0].begin, 11);
+        assert!(fixture.canonical.len() < 80 && fixture.row_sequence.len() < 80);
+
+        // A failure the entry does not reproduce yields no fixture
+        let stale = PtmFailure { original_index: 20, ..failure };
+        assert!(build_fixture(&entry, &isoform, &stale).is_none());
+    }
+}
@@ -0,0 +1,175 @@
+//! End-of-run data quality score and gates.
+//!
+//! Gates are configured under `quality:` in the config; any breach marks the run
+//! as failed in report.yaml and makes the process exit non-zero.
+
+use serde::Serialize;
+
+use crate::config::QualityConfig;
+use crate::metrics::Metrics;
+
+/// Counters the quality gates are computed from.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QualityInputs {
+    pub ptm_attempted: u64,
+    pub ptm_failed: u64,
+    pub isoforms: u64,
+    pub isoform_seq_missing: u64,
+    pub rows_rejected: u64,
+}
+
+impl QualityInputs {
+    pub fn from_metrics(metrics: &Metrics) -> Self {
+        Self {
+            ptm_attempted: metrics.ptm_attempted(),
+            ptm_failed: metrics.ptm_failed(),
+            isoforms: metrics.isoforms(),
+            isoform_seq_missing: metrics.isoform_seq_missing(),
+            rows_rejected: metrics.rows_rejected(),
+        }
+    }
+
+    fn ptm_failure_ratio(&self) -> f64 {
+        ratio(self.ptm_failed, self.ptm_attempted)
+    }
+
+    fn isoform_seq_missing_ratio(&self) -> f64 {
+        ratio(self.isoform_seq_missing, self.isoforms)
+    }
+}
+
+/// Outcome of a single gate.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct QualityGate {
+    pub name: String,
+    pub value: f64,
+    pub threshold: f64,
+    pub passed: bool,
+}
+
+/// Quality section of report.yaml.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct QualityReport {
+    /// 1.0 = no PTM failures and no missing isoform sequences (mean of the success rates)
+    pub score: f64,
+    pub passed: bool,
+    pub gates: Vec<QualityGate>,
+}
+
+impl Default for QualityReport {
+    fn default() -> Self {
+        Self {
+            score: 1.0,
+            passed: true,
+            gates: Vec::new(),
+        }
+    }
+}
+
+impl QualityReport {
+    pub fn evaluate(inputs: &QualityInputs, config: &QualityConfig) -> Self {
+        let mut gates = Vec::new();
+        if let Some(max) = config.max_ptm_failure_ratio {
+            gates.push(gate("ptm_failure_ratio", inputs.ptm_failure_ratio(), max));
+        }
+        if let Some(max) = config.max_isoform_seq_missing_ratio {
+            gates.push(gate("isoform_seq_missing_ratio", inputs.isoform_seq_missing_ratio(), max));
+        }
+        if let Some(max) = config.max_rows_rejected {
+            gates.push(gate("rows_rejected", inputs.rows_rejected as f64, max as f64));
+        }
+
+        let rates = [
+            (inputs.ptm_attempted > 0).then(|| 1.0 - inputs.ptm_failure_ratio()),
+            (inputs.isoforms > 0).then(|| 1.0 - inputs.isoform_seq_missing_ratio()),
+        ];
+        let rates: Vec<f64> = rates.into_iter().flatten().collect();
+        let score = if rates.is_empty() {
+            1.0
+        } else {
+            rates.iter().sum::<f64>() / rates.len() as f64
+        };
+
+        Self {
+            score,
+            passed: gates.iter().all(|g| g.passed),
+            gates,
+        }
+    }
+
+    /// Human-readable summary of breached gates, or `None` if all passed.
+    pub fn failure_message(&self) -> Option<String> {
+        let failed: Vec<String> = self
+            .gates
+            .iter()
+            .filter(|g| !g.passed)
+            .map(|g| format!("{}={:.4} > {}", g.name, g.value, g.threshold))
+            .collect();
+        if failed.is_empty() {
+            None
+        } else {
+            Some(format!("Quality gates failed: {}", failed.join(", ")))
+        }
+    }
+}
+
+fn gate(name: &str, value: f64, threshold: f64) -> QualityGate {
+    QualityGate {
+        name: name.to_string(),
+        value,
+        threshold,
+        passed: value <= threshold,
+    }
+}
+
+fn ratio(numerator: u64, denominator: u64) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs() -> QualityInputs {
+        QualityInputs {
+            ptm_attempted: 1000,
+            ptm_failed: 30,
+            isoforms: 200,
+            isoform_seq_missing: 0,
+            rows_rejected: 0,
+        }
+    }
+
+    #[test]
+    fn test_breached_gate_fails_run() {
+        let config = QualityConfig {
+            max_ptm_failure_ratio: Some(0.02),
+            max_isoform_seq_missing_ratio: Some(0.005),
+            max_rows_rejected: Some(0),
+            check_invariants: false,
+        };
+        let report = QualityReport::evaluate(&inputs(), &config);
+
+        assert!(!report.passed);
+        assert_eq!(report.gates.len(), 3);
+        assert!(!report.gates[0].passed);
+        assert!(report.gates[1].passed && report.gates[2].passed);
+        assert!((report.score - 0.985).abs() < 1e-9);
+        assert_eq!(
+            report.failure_message().unwrap(),
+            "Quality gates failed: ptm_failure_ratio=0.0300 > 0.02"
+        );
+    }
+
+    #[test]
+    fn test_no_gates_configured_passes() {
+        let report = QualityReport::evaluate(&inputs(), &QualityConfig::default());
+        assert!(report.passed);
+        assert!(report.gates.is_empty());
+        assert!(report.failure_message().is_none());
+    }
+}
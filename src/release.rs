@@ -0,0 +1,188 @@
+//! UniProt release identification for the `release` column and report.yaml.
+//!
+//! UniProt XML carries no release in its entries, so the run's release is resolved
+//! once, in order of precedence: `--release`, the release header of a
+//! `fetch-proteome` download, a `release` attribute on the `<uniprot>` root, the
+//! `reldate.txt` shipped next to the FTP dumps, and `storage.sidecar_download.release`.
+
+use anyhow::Result;
+use quick_xml::events::Event;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::config::Settings;
+use crate::metrics::Metrics;
+use crate::paths::list_xml_inputs;
+use crate::pipeline::reader::create_xml_reader;
+
+/// Release stamped into the output, and where it came from.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ReleaseInfo {
+    /// e.g. "2024_06"
+    pub version: String,
+    /// One of: cli, proteome_download, xml_root, reldate.txt, config
+    pub source: &'static str,
+}
+
+impl ReleaseInfo {
+    fn new(version: impl Into<String>, source: &'static str) -> Self {
+        Self {
+            version: version.into(),
+            source,
+        }
+    }
+}
+
+/// Resolves the run's release; `None` when no source knows it.
+///
+/// `input` may be a file or a swarm directory (its first XML file is probed).
+pub fn resolve_release(
+    cli: Option<&str>,
+    downloaded: Option<&str>,
+    input: &Path,
+    settings: &Settings,
+) -> Result<Option<ReleaseInfo>> {
+    if let Some(version) = cli {
+        return Ok(Some(ReleaseInfo::new(version, "cli")));
+    }
+    if let Some(version) = downloaded {
+        return Ok(Some(ReleaseInfo::new(version, "proteome_download")));
+    }
+
+    let (probe, dir) = if input.is_dir() {
+        (list_xml_inputs(input)?.into_iter().next(), Some(input))
+    } else {
+        (Some(input.to_path_buf()), input.parent())
+    };
+    if let Some(path) = probe.filter(|p| p.is_file()) {
+        if let Some(version) = root_release(&path, settings)? {
+            return Ok(Some(ReleaseInfo::new(version, "xml_root")));
+        }
+    }
+    if let Some(version) = dir.and_then(|d| fs::read_to_string(d.join("reldate.txt")).ok()) {
+        if let Some(version) = parse_reldate(&version) {
+            return Ok(Some(ReleaseInfo::new(version, "reldate.txt")));
+        }
+    }
+
+    Ok(settings
+        .storage
+        .sidecar_download
+        .release
+        .as_deref()
+        .map(|version| ReleaseInfo::new(version, "config")))
+}
+
+/// `release` attribute of the document's `<uniprot>` root element, if any.
+fn root_release(path: &Path, settings: &Settings) -> Result<Option<String>> {
+    let mut reader = create_xml_reader(path, settings, &Metrics::new())?;
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) => {
+                if e.local_name().as_ref() != b"uniprot" {
+                    return Ok(None);
+                }
+                for attr in e.attributes().flatten() {
+                    if attr.key.local_name().as_ref() == b"release" {
+                        return Ok(Some(attr.unescape_value()?.into_owned()));
+                    }
+                }
+                return Ok(None);
+            }
+            Event::Eof => return Ok(None),
+            _ => buf.clear(),
+        }
+    }
+}
+
+/// Extracts the release from a UniProt `reldate.txt`, e.g.
+/// "UniProt Knowledgebase Release 2024_06 consists of:" -> "2024_06".
+pub fn parse_reldate(text: &str) -> Option<String> {
+    text.split_whitespace()
+        .skip_while(|word| *word != "Release")
+        .nth(1)
+        .filter(|v| v.len() == 7 && v.as_bytes()[4] == b'_')
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_release_precedence() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_release");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("sprot.xml");
+        fs::write(&input, r#"<?xml version="1.0"?><uniprot xmlns="http://uniprot.org/uniprot"><entry/></uniprot>"#).unwrap();
+        let mut settings = Settings::default();
+
+        assert_eq!(resolve_release(None, None, &input, &settings).unwrap(), None);
+
+        settings.storage.sidecar_download.release = Some("2023_01".to_string());
+        assert_eq!(
+            resolve_release(None, None, &input, &settings).unwrap(),
+            Some(ReleaseInfo::new("2023_01", "config"))
+        );
+
+        fs::write(
+            dir.join("reldate.txt"),
+            "UniProt Knowledgebase Release 2024_06 consists of:\nUniProtKB/Swiss-Prot Release 2024_06 of 24-Jul-2024\n",
+        )
+        .unwrap();
+        assert_eq!(
+            resolve_release(None, None, &dir, &settings).unwrap(),
+            Some(ReleaseInfo::new("2024_06", "reldate.txt"))
+        );
+
+        fs::write(&input, r#"<uniprot release="2024_05"><entry/></uniprot>"#).unwrap();
+        assert_eq!(
+            resolve_release(None, None, &input, &settings).unwrap(),
+            Some(ReleaseInfo::new("2024_05", "xml_root"))
+        );
+        assert_eq!(
+            resolve_release(None, Some("2024_04"), &input, &settings).unwrap(),
+            Some(ReleaseInfo::new("2024_04", "proteome_download"))
+        );
+        assert_eq!(
+            resolve_release(Some("2024_03"), Some("2024_04"), &input, &settings).unwrap(),
+            Some(ReleaseInfo::new("2024_03", "cli"))
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_release_column_is_stamped() {
+        use crate::pipeline::parser::{parse_entries_with_hooks, ParseHooks};
+        use arrow::array::StringArray;
+        use arrow::compute::cast;
+        use arrow::datatypes::DataType;
+        use crossbeam_channel::unbounded;
+        use quick_xml::Reader;
+        use std::io::Cursor;
+
+        let xml = r#"<uniprot><entry><accession>P1</accession><sequence length="2">MK</sequence></entry></uniprot>"#;
+        let hooks = ParseHooks {
+            release: Some("2024_06".to_string()),
+            ..ParseHooks::default()
+        };
+        let (tx, rx) = unbounded();
+        parse_entries_with_hooks(Reader::from_reader(Cursor::new(xml.as_bytes())), tx, &Metrics::new(), 16, None, &hooks)
+            .unwrap();
+
+        let batch = rx.recv().unwrap();
+        let release = cast(batch.column_by_name("release").unwrap(), &DataType::Utf8).unwrap();
+        let release = release.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(release.value(0), "2024_06");
+    }
+
+    #[test]
+    fn test_parse_reldate() {
+        assert_eq!(parse_reldate("UniProt Knowledgebase Release 2024_06 consists of:"), Some("2024_06".to_string()));
+        assert_eq!(parse_reldate("no release here"), None);
+    }
+}
@@ -6,13 +6,21 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use sysinfo::System;
 
 use crate::metrics::Metrics;
-use crate::runs::RunContext;
+use crate::pipeline::pacing::ChannelTuning;
+use crate::pipeline::parser::EntrySampling;
+use crate::preflight::Preflight;
+use crate::proteome::ProteomeInfo;
+use crate::quality::QualityReport;
+use crate::release::ReleaseInfo;
+use crate::runs::{RunContext, RunIndexRecord};
 use crate::sampler::ResourceSampler;
+use crate::sidecar::{SidecarCheck, SidecarInfo};
 
 /// Status of an ETL run.
 #[derive(Serialize, Clone, Debug)]
@@ -20,6 +28,8 @@ use crate::sampler::ResourceSampler;
 pub enum RunStatus {
     Success,
     Error { message: String },
+    /// Stopped early by SIGINT/SIGTERM; outputs hold the rows read until then
+    Cancelled,
 }
 
 /// Complete report for a single ETL run.
@@ -35,6 +45,29 @@ pub struct RunReport {
     pub performance: PerformanceMetrics,
     pub resources: ResourceMetrics,
     pub bottleneck: BottleneckInfo,
+    /// Auto-downloaded sidecar FASTA provenance (absent when a sidecar path was configured)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sidecar: Option<SidecarInfo>,
+    /// Sidecar/XML release-consistency spot check (absent when disabled or no sidecar)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sidecar_check: Option<SidecarCheck>,
+    /// Downloaded reference proteome and its UniProt release (`fetch-proteome` runs)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proteome: Option<ProteomeInfo>,
+    /// UniProt release stamped into the `release` column, and its source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release: Option<ReleaseInfo>,
+    /// Entry subset of a `--sample-every` / `--max-entries` smoke run; absent for full runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<EntrySampling>,
+    /// Batch channel pacing and suggested `channel_capacity` (`performance.adaptive_channel`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<ChannelTuning>,
+    /// Entry and byte estimates of the inputs (`performance.preflight`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preflight: Option<Preflight>,
+    /// Data quality score and gate outcomes
+    pub quality: QualityReport,
 }
 
 /// Environment information about the system.
@@ -64,6 +97,26 @@ pub struct PerformanceMetrics {
     pub ptm_failed_vsp_unresolvable: u64,
     pub ptm_failed_isoform_oob: u64,
     pub ptm_failed_residue_mismatch: u64,
+    pub isoform_seq_missing: u64,
+    pub rows_rejected: u64,
+    pub checksum_mismatches: u64,
+    pub duplicate_entries: u64,
+    /// Entries without an accession or sequence dropped by `transform.invalid_entries: skip`
+    pub invalid_entries: u64,
+    /// Isoforms with overlapping VSP edits the mapper cannot order; their PTM sites fail as VSP_UNRESOLVABLE
+    pub vsp_conflicts: u64,
+    /// Isoforms whose sidecar sequence length disagrees with canonical length + VSP delta
+    pub isoform_length_mismatch: u64,
+    /// Sidecar FASTA records loaded into the isoform sequence map
+    pub fasta_records_loaded: u64,
+    /// Sidecar FASTA records skipped as duplicates (`storage.fasta_duplicates`) or without an accession/sequence
+    pub fasta_records_skipped: u64,
+    /// Rows dropped by `filters.row_expr`
+    pub rows_filtered: u64,
+    /// Features whose type has no route (`transform.feature_routes`); kept in `features` only
+    pub unknown_feature_types: u64,
+    /// Extracted features/comments per category, keyed by output column
+    pub feature_counts: BTreeMap<&'static str, u64>,
     pub bytes_read: u64,
     pub bytes_written: u64,
     pub bytes_per_sec: f64,
@@ -75,6 +128,27 @@ pub struct ResourceMetrics {
     pub peak_rss_mb: f64,
     pub peak_cpu_percent: f32,
     pub avg_channel_fullness_percent: f32,
+    /// Largest in-memory size of one batch's Arrow builders
+    pub peak_builder_mb: f64,
+    /// Average builder memory per row; × batch_size ≈ memory per batch
+    pub builder_bytes_per_row: f64,
+    /// 1Hz sampler readings thinned to at most `SERIES_POINTS`; charted in report.html
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub series: Vec<ResourcePoint>,
+}
+
+/// Maximum number of sampler readings kept in `resources.series`.
+pub const SERIES_POINTS: usize = 240;
+
+/// One sampler reading in `resources.series`.
+#[derive(Serialize, Clone, Debug)]
+pub struct ResourcePoint {
+    pub elapsed_secs: f64,
+    pub cpu_percent: f32,
+    pub rss_mb: f64,
+    pub channel_fullness_percent: f32,
+    /// Entries processed so far; steps at file boundaries in swarm mode
+    pub entries: u64,
 }
 
 /// Bottleneck diagnosis information.
@@ -161,6 +235,18 @@ impl RunReport {
                 ptm_failed_vsp_unresolvable: metrics.ptm_failed_vsp_unresolvable(),
                 ptm_failed_isoform_oob: metrics.ptm_failed_isoform_oob(),
                 ptm_failed_residue_mismatch: metrics.ptm_failed_residue_mismatch(),
+                isoform_seq_missing: metrics.isoform_seq_missing(),
+                rows_rejected: metrics.rows_rejected(),
+                checksum_mismatches: metrics.checksum_mismatches(),
+                duplicate_entries: metrics.duplicate_entries(),
+                invalid_entries: metrics.invalid_entries(),
+                vsp_conflicts: metrics.vsp_conflicts(),
+                isoform_length_mismatch: metrics.isoform_length_mismatch(),
+                fasta_records_loaded: metrics.fasta_records_loaded(),
+                fasta_records_skipped: metrics.fasta_records_skipped(),
+                rows_filtered: metrics.rows_filtered(),
+                unknown_feature_types: metrics.unknown_feature_types(),
+                feature_counts: metrics.feature_category_counts(),
                 bytes_read,
                 bytes_written: metrics.bytes_written(),
                 bytes_per_sec,
@@ -169,12 +255,52 @@ impl RunReport {
                 peak_rss_mb: high_water_marks.peak_rss_bytes as f64 / (1024.0 * 1024.0),
                 peak_cpu_percent: high_water_marks.peak_cpu_percent,
                 avg_channel_fullness_percent: high_water_marks.avg_channel_fullness * 100.0,
+                peak_builder_mb: metrics.peak_builder_bytes() as f64 / (1024.0 * 1024.0),
+                builder_bytes_per_row: metrics.builder_bytes_per_row(),
+                series: sampler
+                    .series(SERIES_POINTS)
+                    .into_iter()
+                    .map(|sample| ResourcePoint {
+                        elapsed_secs: sample.elapsed.as_secs_f64(),
+                        cpu_percent: sample.cpu_percent,
+                        rss_mb: sample.rss_bytes as f64 / (1024.0 * 1024.0),
+                        channel_fullness_percent: sample.channel_fullness * 100.0,
+                        entries: sample.entries,
+                    })
+                    .collect(),
             },
             bottleneck: BottleneckInfo {
                 diagnosis: bottleneck_diagnosis.diagnosis,
                 confidence: bottleneck_diagnosis.confidence,
                 recommendations: bottleneck_diagnosis.recommendations,
             },
+            sidecar: None,
+            sidecar_check: None,
+            proteome: None,
+            release: None,
+            sampling: None,
+            channel: None,
+            preflight: None,
+            quality: QualityReport::default(),
+        }
+    }
+
+    /// This run's line for `runs/index.yaml`.
+    pub fn index_record(&self, input: Option<PathBuf>, outputs: Vec<PathBuf>) -> RunIndexRecord {
+        let (status, error) = match &self.status {
+            RunStatus::Success => ("Success", None),
+            RunStatus::Error { message } => ("Error", Some(message.clone())),
+            RunStatus::Cancelled => ("Cancelled", None),
+        };
+        RunIndexRecord {
+            run_id: self.run_id.clone(),
+            status: status.to_string(),
+            error,
+            started_at: self.timestamp,
+            duration_secs: self.duration_secs,
+            input,
+            entries: self.performance.entries_parsed,
+            outputs,
         }
     }
 
@@ -187,6 +313,12 @@ impl RunReport {
 
         Ok(())
     }
+
+    /// Save the report as a self-contained HTML page (see `report_html`).
+    pub fn save_html(&self, path: &Path) -> Result<()> {
+        fs::write(path, crate::report_html::render(self))
+            .with_context(|| format!("Failed to write HTML report to {}", path.display()))
+    }
 }
 
 #[cfg(test)]
@@ -213,5 +345,8 @@ mod tests {
         let yaml = serde_yaml::to_string(&error).unwrap();
         assert!(yaml.contains("Error"));
         assert!(yaml.contains("Test error"));
+
+        let yaml = serde_yaml::to_string(&RunStatus::Cancelled).unwrap();
+        assert_eq!(yaml.trim(), "status: Cancelled");
     }
 }
@@ -0,0 +1,357 @@
+//! Self-contained HTML rendering of a run report (`report.html` next to `report.yaml`).
+//!
+//! The page template (`resources/report.html`) is embedded in the binary and the charts
+//! are inline SVG built here, so the file opens offline with no scripts or external assets.
+//! It carries the same data as report.yaml, laid out for people who review run quality.
+
+use std::fmt::Write;
+
+use crate::report::{ResourcePoint, RunReport, RunStatus};
+
+const TEMPLATE: &str = include_str!("../resources/report.html");
+
+const CHART_WIDTH: f64 = 520.0;
+const CHART_HEIGHT: f64 = 200.0;
+const PLOT_LEFT: f64 = 60.0;
+const PLOT_RIGHT: f64 = 10.0;
+const PLOT_TOP: f64 = 10.0;
+const PLOT_BOTTOM: f64 = 24.0;
+
+const BAR_LABEL_WIDTH: f64 = 190.0;
+const BAR_ROW_HEIGHT: f64 = 22.0;
+
+/// Render `report` as a complete HTML page.
+pub fn render(report: &RunReport) -> String {
+    let (status, status_class, message) = match &report.status {
+        RunStatus::Success => ("Success", "ok", None),
+        RunStatus::Error { message } => ("Error", "error", Some(message.as_str())),
+        RunStatus::Cancelled => ("Cancelled", "cancelled", None),
+    };
+
+    fill(TEMPLATE, |placeholder| match placeholder {
+        "RUN_ID" => escape(&report.run_id),
+        "STATUS" => status.to_string(),
+        "STATUS_CLASS" => status_class.to_string(),
+        "TIMESTAMP" => report.timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        "DURATION" => format!("{:.1} s", report.duration_secs),
+        "MESSAGE" => message
+            .map(|message| format!("<p class=\"message\">{}</p>", escape(message)))
+            .unwrap_or_default(),
+        "CARDS" => cards(report),
+        "THROUGHPUT_CHARTS" => throughput_charts(&report.resources.series),
+        "RESOURCE_CHARTS" => resource_charts(&report.resources.series),
+        "PTM_CHART" => ptm_chart(report),
+        "FEATURES" => features(report),
+        "BOTTLENECK" => bottleneck(report),
+        "ENVIRONMENT" => environment(report),
+        "VERSION" => env!("CARGO_PKG_VERSION").to_string(),
+        _ => String::new(),
+    })
+}
+
+/// Replace every `{{NAME}}` in `template` in one pass, so substituted values are never rescanned.
+fn fill(template: &str, value: impl Fn(&str) -> String) -> String {
+    let mut page = String::with_capacity(template.len() * 2);
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        page.push_str(&rest[..start]);
+        page.push_str(&value(&rest[start + 2..start + 2 + len]));
+        rest = &rest[start + 2 + len + 2..];
+    }
+    page.push_str(rest);
+    page
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `1234567` -> `1,234,567`.
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+fn percent(part: u64, whole: u64) -> String {
+    if whole == 0 {
+        "n/a".to_string()
+    } else {
+        format!("{:.1}%", part as f64 * 100.0 / whole as f64)
+    }
+}
+
+fn cards(report: &RunReport) -> String {
+    let perf = &report.performance;
+    let resources = &report.resources;
+    let cards = [
+        ("Entries", thousands(perf.entries_parsed)),
+        ("Entries / sec", format!("{:.0}", perf.entries_per_sec)),
+        ("Read", format!("{:.1} MB", perf.bytes_read as f64 / (1024.0 * 1024.0))),
+        ("Written", format!("{:.1} MB", perf.bytes_written as f64 / (1024.0 * 1024.0))),
+        ("PTM sites mapped", percent(perf.ptm_mapped, perf.ptm_attempted)),
+        ("Rows rejected", thousands(perf.rows_rejected)),
+        ("Quality score", format!("{:.3}", report.quality.score)),
+        ("Peak RSS", format!("{:.0} MB", resources.peak_rss_mb)),
+        ("Peak CPU", format!("{:.0}%", resources.peak_cpu_percent)),
+    ];
+    let mut html = String::new();
+    for (label, value) in cards {
+        let _ = writeln!(
+            html,
+            "<div class=\"card\"><div class=\"label\">{label}</div><div class=\"value\">{value}</div></div>"
+        );
+    }
+    html
+}
+
+fn throughput_charts(series: &[ResourcePoint]) -> String {
+    let rate: Vec<(f64, f64)> = series
+        .windows(2)
+        .map(|pair| {
+            let dt = pair[1].elapsed_secs - pair[0].elapsed_secs;
+            let delta = pair[1].entries.saturating_sub(pair[0].entries) as f64;
+            (pair[1].elapsed_secs, if dt > 0.0 { delta / dt } else { 0.0 })
+        })
+        .collect();
+    let progress: Vec<(f64, f64)> = series.iter().map(|p| (p.elapsed_secs, p.entries as f64)).collect();
+    line_chart("Entries per second", &rate) + &line_chart("Entries processed", &progress)
+}
+
+fn resource_charts(series: &[ResourcePoint]) -> String {
+    let points = |value: fn(&ResourcePoint) -> f64| -> Vec<(f64, f64)> {
+        series.iter().map(|p| (p.elapsed_secs, value(p))).collect()
+    };
+    line_chart("CPU (%)", &points(|p| p.cpu_percent as f64))
+        + &line_chart("RSS (MB)", &points(|p| p.rss_mb))
+        + &line_chart("Batch channel fullness (%)", &points(|p| p.channel_fullness_percent as f64))
+}
+
+/// Line chart over elapsed seconds, y axis from zero; a placeholder below two points.
+fn line_chart(title: &str, points: &[(f64, f64)]) -> String {
+    let mut html = format!("<figure><figcaption>{}</figcaption>", escape(title));
+    if points.len() < 2 {
+        html.push_str("<p class=\"empty\">Not enough samples (the sampler reads once per second).</p></figure>\n");
+        return html;
+    }
+    let x_min = points[0].0;
+    let x_max = points[points.len() - 1].0.max(x_min + f64::EPSILON);
+    let y_max = points.iter().map(|&(_, y)| y).fold(0.0, f64::max);
+    let y_max = if y_max > 0.0 { y_max } else { 1.0 };
+    let plot_width = CHART_WIDTH - PLOT_LEFT - PLOT_RIGHT;
+    let plot_height = CHART_HEIGHT - PLOT_TOP - PLOT_BOTTOM;
+    let bottom = PLOT_TOP + plot_height;
+
+    let polyline = points
+        .iter()
+        .map(|&(x, y)| {
+            let px = PLOT_LEFT + (x - x_min) / (x_max - x_min) * plot_width;
+            let py = bottom - y / y_max * plot_height;
+            format!("{px:.1},{py:.1}")
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let _ = writeln!(
+        html,
+        "<svg viewBox=\"0 0 {CHART_WIDTH} {CHART_HEIGHT}\" role=\"img\" aria-label=\"{title}\">\
+         <line x1=\"{PLOT_LEFT}\" y1=\"{PLOT_TOP}\" x2=\"{PLOT_LEFT}\" y2=\"{bottom}\" stroke=\"#bcccdc\"/>\
+         <line x1=\"{PLOT_LEFT}\" y1=\"{bottom}\" x2=\"{right}\" y2=\"{bottom}\" stroke=\"#bcccdc\"/>\
+         <line x1=\"{PLOT_LEFT}\" y1=\"{mid}\" x2=\"{right}\" y2=\"{mid}\" stroke=\"#f0f4f8\"/>\
+         <text x=\"{label_x}\" y=\"{top_label}\" text-anchor=\"end\">{y_top}</text>\
+         <text x=\"{label_x}\" y=\"{mid_label}\" text-anchor=\"end\">{y_mid}</text>\
+         <text x=\"{label_x}\" y=\"{bottom}\" text-anchor=\"end\">0</text>\
+         <text x=\"{PLOT_LEFT}\" y=\"{CHART_HEIGHT}\">{x_min:.0} s</text>\
+         <text x=\"{right}\" y=\"{CHART_HEIGHT}\" text-anchor=\"end\">{x_max:.0} s</text>\
+         <polyline fill=\"none\" stroke=\"#1c7ed6\" stroke-width=\"1.5\" points=\"{polyline}\"/>\
+         </svg></figure>",
+        title = escape(title),
+        right = CHART_WIDTH - PLOT_RIGHT,
+        mid = PLOT_TOP + plot_height / 2.0,
+        label_x = PLOT_LEFT - 4.0,
+        top_label = PLOT_TOP + 8.0,
+        mid_label = PLOT_TOP + plot_height / 2.0 + 4.0,
+        y_top = axis_value(y_max),
+        y_mid = axis_value(y_max / 2.0),
+    );
+    html
+}
+
+fn axis_value(value: f64) -> String {
+    if value >= 1_000_000.0 {
+        format!("{:.1}M", value / 1_000_000.0)
+    } else if value >= 10_000.0 {
+        format!("{:.0}k", value / 1_000.0)
+    } else if value >= 10.0 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.2}")
+    }
+}
+
+fn ptm_chart(report: &RunReport) -> String {
+    let perf = &report.performance;
+    let mut html = format!(
+        "<p>{} of {} PTM sites mapped ({}); {} failed.</p>\n",
+        thousands(perf.ptm_mapped),
+        thousands(perf.ptm_attempted),
+        percent(perf.ptm_mapped, perf.ptm_attempted),
+        thousands(perf.ptm_failed),
+    );
+    let reasons = [
+        ("Canonical out of bounds", perf.ptm_failed_canonical_oob),
+        ("VSP deletion", perf.ptm_failed_vsp_deletion),
+        ("Mapper out of bounds", perf.ptm_failed_mapper_oob),
+        ("VSP unresolvable", perf.ptm_failed_vsp_unresolvable),
+        ("Isoform out of bounds", perf.ptm_failed_isoform_oob),
+        ("Residue mismatch", perf.ptm_failed_residue_mismatch),
+    ];
+    let max = reasons.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    if max == 0 {
+        html.push_str("<p class=\"empty\">No PTM mapping failures.</p>\n");
+        return html;
+    }
+    html.push_str(&bar_chart(&reasons, max));
+    html
+}
+
+/// Horizontal bars scaled to `max`, one row per label.
+fn bar_chart(rows: &[(&str, u64)], max: u64) -> String {
+    let height = rows.len() as f64 * BAR_ROW_HEIGHT + 4.0;
+    let bar_space = CHART_WIDTH - BAR_LABEL_WIDTH - 80.0;
+    let mut svg = format!("<svg viewBox=\"0 0 {CHART_WIDTH} {height}\" role=\"img\" aria-label=\"PTM failure reasons\">");
+    for (i, &(label, count)) in rows.iter().enumerate() {
+        let y = i as f64 * BAR_ROW_HEIGHT + 2.0;
+        let width = count as f64 / max as f64 * bar_space;
+        let _ = write!(
+            svg,
+            "<text x=\"{label_x}\" y=\"{text_y}\" text-anchor=\"end\">{label}</text>\
+             <rect x=\"{BAR_LABEL_WIDTH}\" y=\"{y}\" width=\"{width:.1}\" height=\"{bar_height}\" fill=\"#e8590c\"/>\
+             <text x=\"{value_x:.1}\" y=\"{text_y}\">{count}</text>",
+            label_x = BAR_LABEL_WIDTH - 6.0,
+            text_y = y + BAR_ROW_HEIGHT / 2.0 + 3.0,
+            label = escape(label),
+            bar_height = BAR_ROW_HEIGHT - 6.0,
+            value_x = BAR_LABEL_WIDTH + width + 4.0,
+            count = thousands(count),
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn features(report: &RunReport) -> String {
+    let counts = &report.performance.feature_counts;
+    if counts.values().all(|&count| count == 0) {
+        return "<p class=\"empty\">No features extracted.</p>\n".to_string();
+    }
+    let mut html = String::from("<table>\n");
+    for (column, &count) in counts.iter().filter(|(_, &count)| count > 0) {
+        let _ = writeln!(html, "<tr><td>{}</td><td class=\"num\">{}</td></tr>", escape(column), thousands(count));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn bottleneck(report: &RunReport) -> String {
+    let bottleneck = &report.bottleneck;
+    let mut html = format!(
+        "<p>{} (confidence {:.0}%)</p>\n",
+        escape(&bottleneck.diagnosis),
+        bottleneck.confidence * 100.0
+    );
+    if !bottleneck.recommendations.is_empty() {
+        html.push_str("<ul>\n");
+        for recommendation in &bottleneck.recommendations {
+            let _ = writeln!(html, "<li>{}</li>", escape(recommendation));
+        }
+        html.push_str("</ul>\n");
+    }
+    html
+}
+
+fn environment(report: &RunReport) -> String {
+    let env = &report.environment;
+    let rows = [
+        ("OS", format!("{} {}", env.os, env.os_version)),
+        ("CPU", env.cpu_model.clone()),
+        ("Cores", env.cpu_cores.to_string()),
+        ("Memory", format!("{:.1} GB", env.total_memory_gb)),
+    ];
+    let mut html = String::from("<table>\n");
+    for (label, value) in rows {
+        let _ = writeln!(html, "<tr><th>{label}</th><td>{}</td></tr>", escape(&value));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::runs::RunContext;
+    use crate::sampler::{ChannelStats, ResourceSampler};
+    use std::sync::Arc;
+
+    #[test]
+    fn renders_charts_and_escapes_report_text() {
+        let runs_dir = std::env::temp_dir().join("uniprot_etl_test_report_html");
+        let _ = std::fs::remove_dir_all(&runs_dir);
+        let run = RunContext::new(&runs_dir).unwrap();
+        let metrics = Metrics::new();
+        let mut sampler = ResourceSampler::start(Arc::new(ChannelStats::new(4)), metrics.clone());
+        sampler.stop();
+
+        let status = RunStatus::Error {
+            message: "bad <entry> & {{CARDS}}".to_string(),
+        };
+        let mut report = RunReport::generate(&run, &metrics, &sampler, status);
+        report.performance.ptm_attempted = 10;
+        report.performance.ptm_mapped = 7;
+        report.performance.ptm_failed = 3;
+        report.performance.ptm_failed_residue_mismatch = 3;
+        report.resources.series = (0..3)
+            .map(|i| ResourcePoint {
+                elapsed_secs: i as f64,
+                cpu_percent: 50.0,
+                rss_mb: 100.0 + i as f64,
+                channel_fullness_percent: 25.0,
+                entries: i * 1000,
+            })
+            .collect();
+
+        let html = render(&report);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("bad &lt;entry&gt; &amp; {{CARDS}}"));
+        assert_eq!(html.matches("<polyline").count(), 5);
+        assert!(html.contains("7 of 10 PTM sites mapped (70.0%)"));
+        assert!(html.contains(">Residue mismatch</text>"));
+        assert_eq!(thousands(1_234_567), "1,234,567");
+
+        report.resources.series.clear();
+        assert!(render(&report).contains("Not enough samples"));
+
+        let _ = std::fs::remove_dir_all(&runs_dir);
+    }
+}
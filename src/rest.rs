@@ -0,0 +1,240 @@
+//! Minimal UniProtKB REST client (https://rest.uniprot.org).
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::RestFetchConfig;
+use crate::log_warn;
+
+/// Base URL of the UniProtKB REST API.
+pub const DEFAULT_REST_URL: &str = "https://rest.uniprot.org/uniprotkb";
+
+/// True for strings that look like a UniProt accession or entry name
+/// (letters, digits, `_`, `-` for isoforms, `.` for versions).
+pub fn is_valid_accession(accession: &str) -> bool {
+    !accession.is_empty()
+        && accession.len() <= 32
+        && accession
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'))
+}
+
+/// Fetches the UniProt XML document for one accession.
+pub fn fetch_entry_xml(base_url: &str, accession: &str) -> Result<Vec<u8>> {
+    if !is_valid_accession(accession) {
+        return Err(anyhow!("Invalid accession: {:?}", accession));
+    }
+    let url = format!("{}/{}.xml", base_url.trim_end_matches('/'), accession);
+    let response = ureq::get(&url)
+        .call()
+        .with_context(|| format!("Failed to fetch {}", url))?;
+
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut body)
+        .with_context(|| format!("Failed to read response from {}", url))?;
+    Ok(body)
+}
+
+/// Totals of an accession fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FetchSummary {
+    /// Distinct accessions requested
+    pub accessions: usize,
+    /// HTTP requests issued, including retries
+    pub requests: usize,
+    pub bytes: u64,
+}
+
+/// Reads an accession list: whitespace- or comma-separated, `#` starts a comment.
+pub fn read_accession_file(path: &Path) -> Result<Vec<String>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read accession list {}", path.display()))?;
+    Ok(text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Fetches `accessions` through the `/accessions` endpoint in batches of
+/// `config.batch_size` and writes the XML responses to `dest`.
+///
+/// `dest` ends up holding one `<uniprot>` document per batch back to back; the
+/// parser only looks for `<entry>` elements, so it reads like a single file.
+pub fn fetch_accessions_to(
+    config: &RestFetchConfig,
+    accessions: &[String],
+    dest: &Path,
+) -> Result<FetchSummary> {
+    let mut seen = HashSet::new();
+    let unique: Vec<&str> = accessions
+        .iter()
+        .map(|a| a.trim())
+        .filter(|a| seen.insert(*a))
+        .collect();
+    if unique.is_empty() {
+        return Err(anyhow!("No accessions given"));
+    }
+    if let Some(bad) = unique.iter().find(|a| !is_valid_accession(a)) {
+        return Err(anyhow!("Invalid accession: {:?}", bad));
+    }
+
+    let mut out = BufWriter::new(
+        File::create(dest).with_context(|| format!("Failed to create {}", dest.display()))?,
+    );
+    let min_interval = Duration::from_millis(config.min_interval_ms);
+    let mut last_request: Option<Instant> = None;
+    let mut summary = FetchSummary {
+        accessions: unique.len(),
+        requests: 0,
+        bytes: 0,
+    };
+
+    for batch in unique.chunks(config.batch_size.max(1)) {
+        let url = format!(
+            "{}/accessions?accessions={}&format=xml",
+            config.base_url.trim_end_matches('/'),
+            batch.join(",")
+        );
+
+        let mut attempt = 0;
+        let response = loop {
+            if let Some(wait) = last_request.and_then(|t| min_interval.checked_sub(t.elapsed())) {
+                thread::sleep(wait);
+            }
+            last_request = Some(Instant::now());
+            summary.requests += 1;
+
+            match ureq::get(&url).call() {
+                Ok(response) => break response,
+                Err(ureq::Error::Status(status, response))
+                    if (status == 429 || status >= 500) && attempt < config.max_retries =>
+                {
+                    attempt += 1;
+                    let backoff = response
+                        .header("Retry-After")
+                        .and_then(|v| v.trim().parse().ok())
+                        .map(Duration::from_secs)
+                        .unwrap_or(min_interval * 2u32.pow(attempt));
+                    log_warn!(
+                        "rest",
+                        "UniProt REST returned {}; retry {}/{} in {:?}",
+                        status, attempt, config.max_retries, backoff
+                    );
+                    thread::sleep(backoff);
+                }
+                Err(e) => return Err(e).with_context(|| format!("Failed to fetch {}", url)),
+            }
+        };
+
+        summary.bytes += io::copy(&mut response.into_reader(), &mut out)
+            .with_context(|| format!("Failed to read response from {}", url))?;
+        writeln!(out)?;
+    }
+
+    out.flush()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use crate::pipeline::reader::create_xml_reader;
+    use crossbeam_channel::unbounded;
+    use std::sync::{Arc, Mutex};
+    use tiny_http::{Header, Response, Server};
+
+    #[test]
+    fn test_is_valid_accession() {
+        assert!(is_valid_accession("P04637"));
+        assert!(is_valid_accession("P04637-2"));
+        assert!(is_valid_accession("P53_HUMAN"));
+        assert!(!is_valid_accession(""));
+        assert!(!is_valid_accession("P04637/../x"));
+        assert!(!is_valid_accession("P04637?format=fasta"));
+    }
+
+    #[test]
+    fn test_read_accession_file() {
+        let path = std::env::temp_dir().join("uniprot_etl_test_accessions.txt");
+        fs::write(&path, "# targets\nP04637, Q9Y6K9\n\nP38398 # BRCA1\n").unwrap();
+        assert_eq!(
+            read_accession_file(&path).unwrap(),
+            vec!["P04637", "Q9Y6K9", "P38398"]
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_accessions_batches_and_retries() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/uniprotkb", server.server_addr());
+        let urls = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&urls);
+        let handle = thread::spawn(move || {
+            for (i, request) in server.incoming_requests().take(3).enumerate() {
+                seen.lock().unwrap().push(request.url().to_string());
+                let response = if i == 0 {
+                    // First call is throttled
+                    Response::from_string("slow down")
+                        .with_status_code(429)
+                        .with_header(Header::from_bytes("Retry-After", "0").unwrap())
+                } else {
+                    let query = request.url().split("accessions=").nth(1).unwrap();
+                    let entries: String = query
+                        .split('&')
+                        .next()
+                        .unwrap()
+                        .split(',')
+                        .map(|a| format!("<entry><accession>{}</accession><sequence length=\"2\">MK</sequence></entry>", a))
+                        .collect();
+                    Response::from_string(format!(
+                        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<uniprot>{}</uniprot>",
+                        entries
+                    ))
+                };
+                request.respond(response).unwrap();
+            }
+        });
+
+        let config = RestFetchConfig {
+            base_url,
+            batch_size: 2,
+            min_interval_ms: 0,
+            max_retries: 1,
+        };
+        let accessions: Vec<String> = ["P1", "P2", "P1", "P3"].iter().map(|s| s.to_string()).collect();
+        let dest = std::env::temp_dir().join("uniprot_etl_test_rest_fetch.xml");
+        let summary = fetch_accessions_to(&config, &accessions, &dest).unwrap();
+        handle.join().unwrap();
+
+        assert_eq!(summary.accessions, 3);
+        assert_eq!(summary.requests, 3);
+        let urls = urls.lock().unwrap();
+        assert!(urls[1].ends_with("/uniprotkb/accessions?accessions=P1,P2&format=xml"));
+        assert!(urls[2].contains("accessions=P3&"));
+
+        // The concatenated documents parse like one file
+        let settings = Settings::default();
+        let metrics = Metrics::new();
+        let reader = create_xml_reader(&dest, &settings, &metrics).unwrap();
+        let (tx, rx) = unbounded();
+        parse_entries(reader, tx, &metrics, 100, None).unwrap();
+        let rows: usize = rx.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 3);
+
+        fs::remove_file(&dest).unwrap();
+    }
+}
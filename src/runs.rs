@@ -4,8 +4,14 @@
 
 use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use crate::log_warn;
+
+/// Registry of all runs in `runs_dir`, one line per finished run.
+pub const RUN_INDEX_FILE: &str = "index.yaml";
 
 /// Context for a single ETL run, including directory paths and timing.
 pub struct RunContext {
@@ -72,6 +78,36 @@ impl RunContext {
         self.run_dir.join("report.yaml")
     }
 
+    /// Path to the report.html rendering of report.yaml.
+    pub fn report_html_path(&self) -> PathBuf {
+        self.run_dir.join("report.html")
+    }
+
+    /// Path to the XML fetched from the UniProt REST API (`--accessions` runs).
+    pub fn fetched_xml_path(&self) -> PathBuf {
+        self.run_dir.join("accessions.xml")
+    }
+
+    /// Path to the manifest.jsonl (one line per processed input, watch mode).
+    pub fn manifest_path(&self) -> PathBuf {
+        self.run_dir.join("manifest.jsonl")
+    }
+
+    /// Path to the `--trace-accession` log for `accession`.
+    pub fn trace_path(&self, accession: &str) -> PathBuf {
+        self.run_dir.join(format!("trace_{}.log", accession))
+    }
+
+    /// Path to the rejected.parquet side output within this run directory.
+    pub fn rejected_path(&self) -> PathBuf {
+        self.run_dir.join("rejected.parquet")
+    }
+
+    /// Directory for profiler output (`--profile-cpu`, scripts/profile_*.sh).
+    pub fn profiles_dir(&self) -> PathBuf {
+        self.run_dir.join("profiles")
+    }
+
     /// Path to the etl.log file within this run directory.
     pub fn log_path(&self) -> PathBuf {
         self.run_dir.join("etl.log")
@@ -81,6 +117,15 @@ impl RunContext {
     pub fn config_snapshot_path(&self) -> PathBuf {
         self.run_dir.join("config_snapshot.yaml")
     }
+
+    /// Path to the run_snapshot.json.zst file (settings plus everything they came from).
+    pub fn run_snapshot_path(&self) -> PathBuf {
+        self.run_dir.join("run_snapshot.json.zst")
+    }
+
+    pub fn lineage_path(&self) -> PathBuf {
+        self.run_dir.join("lineage.json")
+    }
 }
 
 fn normalize_run_id(raw: &str) -> Result<String> {
@@ -120,13 +165,10 @@ fn is_reusable_precreated_run_dir(run_dir: &Path) -> Result<bool> {
     let entries = fs::read_dir(run_dir)
         .with_context(|| format!("Failed to read run directory: {}", run_dir.display()))?;
 
-    let mut saw_entries = false;
     for entry in entries {
         let entry = entry.with_context(|| {
             format!("Failed to read directory entry in {}", run_dir.display())
         })?;
-        saw_entries = true;
-
         let Some(name) = entry.file_name().to_str().map(|s| s.to_string()) else {
             return Ok(false);
         };
@@ -146,11 +188,71 @@ fn is_reusable_precreated_run_dir(run_dir: &Path) -> Result<bool> {
     Ok(true)
 }
 
-/// Clean up old run directories, keeping only the most recent `keep_count`.
+/// One run's line in `runs/index.yaml`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RunIndexRecord {
+    pub run_id: String,
+    /// Success, Error or Cancelled (as in report.yaml)
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub duration_secs: f64,
+    pub input: Option<PathBuf>,
+    pub entries: u64,
+    pub outputs: Vec<PathBuf>,
+}
+
+/// Appends `record` to `<runs_dir>/index.yaml`.
+///
+/// The file is a YAML sequence with one flow mapping per line, so every run is a
+/// single appended line written in one call: concurrent runs never interleave and a
+/// crash cannot leave a partial record behind earlier ones.
+pub fn append_run_index(runs_dir: &Path, record: &RunIndexRecord) -> Result<()> {
+    let path = runs_dir.join(RUN_INDEX_FILE);
+    // JSON is valid YAML flow syntax and always fits on one line
+    let line = format!("- {}\n", serde_json::to_string(record)?);
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open run index {}", path.display()))?;
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Failed to append to run index {}", path.display()))?;
+    Ok(())
+}
+
+/// Reads every record of `<runs_dir>/index.yaml` (empty if there is none yet).
+pub fn read_run_index(runs_dir: &Path) -> Result<Vec<RunIndexRecord>> {
+    let path = runs_dir.join(RUN_INDEX_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let yaml = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let records: Option<Vec<RunIndexRecord>> =
+        serde_yaml::from_str(&yaml).with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(records.unwrap_or_default())
+}
+
+/// Marker file that pins a run directory against cleanup.
+pub const KEEP_MARKER: &str = ".keep";
+
+/// Which run directories `cleanup_old_runs` keeps. A run is kept if any rule matches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Keep the most recent N runs
+    pub keep_runs: usize,
+    /// Keep runs modified within this many days
+    pub max_age_days: Option<u64>,
+    /// Keep runs whose report.yaml is not `status: Success`/`Cancelled` (or is missing)
+    pub keep_failed: bool,
+}
+
+/// Clean up old run directories according to `policy`.
 ///
-/// Runs are sorted by directory name (which includes timestamp) and older
-/// runs beyond `keep_count` are removed.
-pub fn cleanup_old_runs(runs_dir: &Path, keep_count: usize) -> Result<()> {
+/// Runs are sorted by directory name (which includes timestamp). Directories holding a
+/// `.keep` marker are never removed.
+pub fn cleanup_old_runs(runs_dir: &Path, policy: &RetentionPolicy) -> Result<()> {
     if !runs_dir.exists() {
         return Ok(());
     }
@@ -172,24 +274,61 @@ pub fn cleanup_old_runs(runs_dir: &Path, keep_count: usize) -> Result<()> {
     // Sort by name (timestamp order since format is run_YYYYMMDD_HHMMSS)
     run_dirs.sort();
 
-    // Remove oldest runs if we have more than keep_count
-    if run_dirs.len() > keep_count {
-        let to_remove = run_dirs.len() - keep_count;
-        for dir in run_dirs.into_iter().take(to_remove) {
-            if let Err(e) = fs::remove_dir_all(&dir) {
-                // Log but don't fail on cleanup errors
-                eprintln!(
-                    "[WARN] Failed to remove old run directory {}: {}",
-                    dir.display(),
-                    e
-                );
-            }
+    // Only runs older than the most recent keep_runs are candidates
+    let candidates = run_dirs.len().saturating_sub(policy.keep_runs);
+    for dir in run_dirs.into_iter().take(candidates) {
+        if is_retained(&dir, policy) {
+            continue;
+        }
+        if let Err(e) = fs::remove_dir_all(&dir) {
+            // Log but don't fail on cleanup errors
+            log_warn!(
+                "runs",
+                "Failed to remove old run directory {}: {}",
+                dir.display(),
+                e
+            );
         }
     }
 
     Ok(())
 }
 
+/// Whether a run outside the most recent `keep_runs` is still kept by `policy`.
+fn is_retained(run_dir: &Path, policy: &RetentionPolicy) -> bool {
+    if run_dir.join(KEEP_MARKER).exists() {
+        return true;
+    }
+    if policy.keep_failed && !run_succeeded(run_dir) {
+        return true;
+    }
+    if let Some(days) = policy.max_age_days {
+        let age = fs::metadata(run_dir)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+        // Unknown age: keep rather than delete
+        if age.is_none_or(|age| age.as_secs() < days * 24 * 60 * 60) {
+            return true;
+        }
+    }
+    false
+}
+
+/// True if the run's report.yaml records `Success` or `Cancelled`.
+fn run_succeeded(run_dir: &Path) -> bool {
+    let Ok(yaml) = fs::read_to_string(run_dir.join("report.yaml")) else {
+        return false;
+    };
+    let Ok(report) = serde_yaml::from_str::<serde_yaml::Value>(&yaml) else {
+        return false;
+    };
+    matches!(
+        report.get("status").and_then(|status| status.as_str()),
+        Some("Success" | "Cancelled")
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -227,7 +366,11 @@ mod tests {
         }
 
         // Keep only 2 runs
-        cleanup_old_runs(&temp_dir, 2).unwrap();
+        let policy = RetentionPolicy {
+            keep_runs: 2,
+            ..RetentionPolicy::default()
+        };
+        cleanup_old_runs(&temp_dir, &policy).unwrap();
 
         let remaining: Vec<_> = fs::read_dir(&temp_dir)
             .unwrap()
@@ -239,4 +382,82 @@ mod tests {
         // Cleanup
         let _ = fs::remove_dir_all(&temp_dir);
     }
+
+    #[test]
+    fn test_cleanup_keeps_pinned_failed_and_recent_runs() {
+        let temp_dir = std::env::temp_dir().join("uniprot_etl_test_retention");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let statuses = ["Success", "Error", "Success", "Success"];
+        for (i, status) in statuses.iter().enumerate() {
+            let run_dir = temp_dir.join(format!("run_2025010{}_120000", i + 1));
+            fs::create_dir_all(&run_dir).unwrap();
+            fs::write(run_dir.join("report.yaml"), format!("run_id: x\nstatus: {}\n", status)).unwrap();
+        }
+        File::create(temp_dir.join("run_20250103_120000").join(KEEP_MARKER)).unwrap();
+
+        let policy = RetentionPolicy {
+            keep_runs: 1,
+            max_age_days: None,
+            keep_failed: true,
+        };
+        cleanup_old_runs(&temp_dir, &policy).unwrap();
+        let mut remaining: Vec<String> = fs::read_dir(&temp_dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        remaining.sort();
+        // run 1 succeeded and is neither pinned nor recent
+        assert_eq!(remaining, ["run_20250102_120000", "run_20250103_120000", "run_20250104_120000"]);
+
+        // Every directory was just created, so all are younger than a day
+        let policy = RetentionPolicy {
+            keep_runs: 0,
+            max_age_days: Some(1),
+            keep_failed: false,
+        };
+        cleanup_old_runs(&temp_dir, &policy).unwrap();
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 3);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_run_index_appends_one_line_per_run() {
+        let temp_dir = std::env::temp_dir().join("uniprot_etl_test_run_index");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        assert!(read_run_index(&temp_dir).unwrap().is_empty());
+
+        let record = RunIndexRecord {
+            run_id: "run_20250101_120000".to_string(),
+            status: "Error".to_string(),
+            error: Some("input: not found".to_string()),
+            started_at: Utc::now(),
+            duration_secs: 1.5,
+            input: Some(PathBuf::from("data/raw/sprot.xml")),
+            entries: 0,
+            outputs: vec![PathBuf::from("data/parquet/uniprot.parquet")],
+        };
+        append_run_index(&temp_dir, &record).unwrap();
+        append_run_index(&temp_dir, &record).unwrap();
+
+        let yaml = fs::read_to_string(temp_dir.join(RUN_INDEX_FILE)).unwrap();
+        assert_eq!(yaml.lines().count(), 2);
+        assert_eq!(read_run_index(&temp_dir).unwrap(), vec![record.clone(), record]);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_normalize_run_id_rejects_platform_path_syntax() {
+        assert_eq!(normalize_run_id(" nightly-01 ").unwrap(), "run_nightly-01");
+        // Device names are harmless once prefixed
+        assert_eq!(normalize_run_id("CON").unwrap(), "run_CON");
+        for raw in [r"C:\runs\x", "a:b", "../x", "a/b", "x."] {
+            assert!(normalize_run_id(raw).is_err(), "{raw} should be rejected");
+        }
+    }
 }
@@ -3,16 +3,17 @@
 //! Samples CPU usage, RSS memory, and channel fullness at 1Hz intervals
 //! to identify performance bottlenecks without impacting the hot path.
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
 use sysinfo::{Pid, ProcessRefreshKind, RefreshKind, System};
+use crate::log_warn;
+use crate::metrics::Metrics;
 
 /// Statistics about channel usage for backpressure tracking.
 pub struct ChannelStats {
-    #[allow(dead_code)] // Used in tests
     capacity: usize,
     samples: Mutex<Vec<f32>>,
 }
@@ -27,7 +28,6 @@ impl ChannelStats {
     }
 
     /// Record the current channel length as a fullness sample.
-    #[allow(dead_code)] // Used in tests
     pub fn record_fullness(&self, current_len: usize) {
         let fullness = if self.capacity > 0 {
             current_len as f32 / self.capacity as f32
@@ -50,11 +50,44 @@ impl ChannelStats {
             0.0
         }
     }
+
+    /// Fullness at or below which the given share `q` (0.0 - 1.0) of samples fall.
+    pub fn fullness_quantile(&self, q: f32) -> f32 {
+        let Ok(samples) = self.samples.lock() else {
+            return 0.0;
+        };
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let mut sorted = samples.clone();
+        sorted.sort_by(f32::total_cmp);
+        let rank = ((sorted.len() - 1) as f32 * q.clamp(0.0, 1.0)).round() as usize;
+        sorted[rank]
+    }
+
+    /// Share of samples with fullness at most `fullness`.
+    pub fn share_at_most(&self, fullness: f32) -> f32 {
+        self.share(|f| f <= fullness)
+    }
+
+    /// Share of samples with fullness at least `fullness`.
+    pub fn share_at_least(&self, fullness: f32) -> f32 {
+        self.share(|f| f >= fullness)
+    }
+
+    fn share(&self, matches: impl Fn(f32) -> bool) -> f32 {
+        let Ok(samples) = self.samples.lock() else {
+            return 0.0;
+        };
+        if samples.is_empty() {
+            return 0.0;
+        }
+        samples.iter().filter(|&&f| matches(f)).count() as f32 / samples.len() as f32
+    }
 }
 
 /// A single resource sample taken at a point in time.
 #[derive(Clone, Debug)]
-#[allow(dead_code)]
 pub struct ResourceSample {
     /// Time since sampler started
     pub elapsed: Duration,
@@ -64,6 +97,8 @@ pub struct ResourceSample {
     pub rss_bytes: u64,
     /// Channel fullness at this sample (0.0 - 1.0)
     pub channel_fullness: f32,
+    /// Entries counted by `Metrics` so far (swarm workers merge theirs when a file ends)
+    pub entries: u64,
 }
 
 /// High-water marks from resource sampling.
@@ -94,22 +129,91 @@ pub struct ResourceSampler {
     stop_flag: Arc<AtomicBool>,
     handle: Option<JoinHandle<()>>,
     channel_stats: Arc<ChannelStats>,
+    latest_rss: Arc<AtomicU64>,
+}
+
+/// Gates the start of new swarm workers on the sampler's latest RSS reading.
+///
+/// When process RSS is above the cap, `acquire` blocks until it drops back below,
+/// unless no other worker is running (a lone worker must always make progress).
+pub struct MemoryGovernor {
+    latest_rss: Arc<AtomicU64>,
+    limit_bytes: u64,
+    active: AtomicUsize,
+    pauses: AtomicU64,
 }
 
+/// RAII guard for one running worker; releases its slot on drop.
+pub struct WorkerPermit<'a> {
+    governor: &'a MemoryGovernor,
+}
+
+impl MemoryGovernor {
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Create a governor reading from `latest_rss` with the given cap in bytes.
+    pub fn new(latest_rss: Arc<AtomicU64>, limit_bytes: u64) -> Self {
+        Self {
+            latest_rss,
+            limit_bytes,
+            active: AtomicUsize::new(0),
+            pauses: AtomicU64::new(0),
+        }
+    }
+
+    /// Wait until there is memory headroom, then register a running worker.
+    pub fn acquire(&self) -> WorkerPermit<'_> {
+        let mut paused = false;
+        while self.latest_rss.load(Ordering::Relaxed) > self.limit_bytes
+            && self.active.load(Ordering::Acquire) > 0
+        {
+            if !paused {
+                paused = true;
+                self.pauses.fetch_add(1, Ordering::Relaxed);
+                log_warn!(
+                    "sampler",
+                    "RSS {:.2} GB exceeds max_rss_gb {:.2} GB; pausing new worker",
+                    self.latest_rss.load(Ordering::Relaxed) as f64 / GIB,
+                    self.limit_bytes as f64 / GIB
+                );
+            }
+            thread::sleep(Self::POLL_INTERVAL);
+        }
+        self.active.fetch_add(1, Ordering::AcqRel);
+        WorkerPermit { governor: self }
+    }
+
+    /// Number of times a worker start was delayed by the cap.
+    pub fn pauses(&self) -> u64 {
+        self.pauses.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for WorkerPermit<'_> {
+    fn drop(&mut self) {
+        self.governor.active.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
 impl ResourceSampler {
     /// Start the resource sampler in a background thread.
     ///
-    /// Samples CPU, RSS, and channel fullness every 1 second.
-    pub fn start(channel_stats: Arc<ChannelStats>) -> Self {
+    /// Samples CPU, RSS, channel fullness, and the `metrics` entry count every 1 second.
+    pub fn start(channel_stats: Arc<ChannelStats>, metrics: Metrics) -> Self {
         let samples = Arc::new(Mutex::new(Vec::with_capacity(1024)));
         let stop_flag = Arc::new(AtomicBool::new(false));
 
+        let latest_rss = Arc::new(AtomicU64::new(0));
+
         let samples_clone = Arc::clone(&samples);
         let stop_clone = Arc::clone(&stop_flag);
         let channel_stats_clone = Arc::clone(&channel_stats);
+        let latest_rss_clone = Arc::clone(&latest_rss);
 
         let handle = thread::spawn(move || {
-            Self::sampling_loop(samples_clone, stop_clone, channel_stats_clone);
+            Self::sampling_loop(samples_clone, stop_clone, channel_stats_clone, latest_rss_clone, metrics);
         });
 
         Self {
@@ -117,13 +221,21 @@ impl ResourceSampler {
             stop_flag,
             handle: Some(handle),
             channel_stats,
+            latest_rss,
         }
     }
 
+    /// Shared gauge holding the most recent RSS sample in bytes.
+    pub fn rss_gauge(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.latest_rss)
+    }
+
     fn sampling_loop(
         samples: Arc<Mutex<Vec<ResourceSample>>>,
         stop_flag: Arc<AtomicBool>,
         channel_stats: Arc<ChannelStats>,
+        latest_rss: Arc<AtomicU64>,
+        metrics: Metrics,
     ) {
         let pid = Pid::from_u32(std::process::id());
         let refresh_kind =
@@ -154,11 +266,13 @@ impl ResourceSampler {
             );
 
             if let Some(process) = sys.process(pid) {
+                latest_rss.store(process.memory(), Ordering::Relaxed);
                 let sample = ResourceSample {
                     elapsed: start.elapsed(),
                     cpu_percent: process.cpu_usage(),
                     rss_bytes: process.memory(),
                     channel_fullness: channel_stats.average_fullness(),
+                    entries: metrics.entries(),
                 };
 
                 if let Ok(mut samples_guard) = samples.lock() {
@@ -201,6 +315,19 @@ impl ResourceSampler {
         }
     }
 
+    /// Collected samples thinned to at most `max_points`, evenly spaced from first to last.
+    pub fn series(&self, max_points: usize) -> Vec<ResourceSample> {
+        let Ok(samples) = self.samples.lock() else {
+            return Vec::new();
+        };
+        if samples.len() <= max_points {
+            return samples.to_vec();
+        }
+        let last = samples.len() - 1;
+        let steps = max_points.saturating_sub(1).max(1);
+        (0..max_points).map(|i| samples[i * last / steps].clone()).collect()
+    }
+
     /// Diagnose performance bottlenecks based on collected samples.
     ///
     /// Heuristics:
@@ -284,12 +411,14 @@ mod tests {
 
         let avg = stats.average_fullness();
         assert!((avg - 0.5).abs() < 0.01); // (0.5 + 0.7 + 0.3) / 3 = 0.5
+        assert!((stats.fullness_quantile(1.0) - 0.7).abs() < 0.01);
+        assert!((stats.share_at_least(0.5) - 2.0 / 3.0).abs() < 0.01);
     }
 
     #[test]
     fn test_sampler_start_stop() {
         let channel_stats = Arc::new(ChannelStats::new(8));
-        let mut sampler = ResourceSampler::start(channel_stats);
+        let mut sampler = ResourceSampler::start(channel_stats, Metrics::new());
 
         // Let it run briefly
         thread::sleep(Duration::from_millis(100));
@@ -312,6 +441,7 @@ mod tests {
             stop_flag: Arc::new(AtomicBool::new(true)),
             handle: None,
             channel_stats: stats,
+            latest_rss: Arc::new(AtomicU64::new(0)),
         };
         let diagnosis = sampler.diagnose_bottleneck();
         assert!(diagnosis.diagnosis.contains("Writer"));
@@ -326,8 +456,34 @@ mod tests {
             stop_flag: Arc::new(AtomicBool::new(true)),
             handle: None,
             channel_stats: stats2,
+            latest_rss: Arc::new(AtomicU64::new(0)),
         };
         let diagnosis2 = sampler2.diagnose_bottleneck();
         assert!(diagnosis2.diagnosis.contains("Parser"));
     }
+
+    #[test]
+    fn test_memory_governor_pauses_until_rss_drops() {
+        let gauge = Arc::new(AtomicU64::new(2_000));
+        let governor = Arc::new(MemoryGovernor::new(Arc::clone(&gauge), 1_000));
+
+        // A lone worker proceeds even when over the cap.
+        let first = governor.acquire();
+        assert_eq!(governor.pauses(), 0);
+
+        let waiter = {
+            let governor = Arc::clone(&governor);
+            thread::spawn(move || {
+                let _permit = governor.acquire();
+            })
+        };
+
+        thread::sleep(Duration::from_millis(100));
+        assert!(!waiter.is_finished());
+        gauge.store(500, Ordering::Relaxed);
+        waiter.join().unwrap();
+
+        assert_eq!(governor.pauses(), 1);
+        drop(first);
+    }
 }
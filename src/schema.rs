@@ -1,11 +1,15 @@
+use anyhow::{anyhow, Result};
+use arrow::array::{ArrayRef, RecordBatch, StringArray, StructArray};
+use arrow::compute::cast;
 use arrow::datatypes::{DataType, Field, Fields, Schema};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 /// Creates the Arrow schema for UniProt entries.
 ///
 /// Top-level columns:
 /// - id: Utf8 (primary accession)
-/// - sequence: Utf8 (amino acid string)
+/// - sequence: Utf8 (amino acid string; null for isoforms kept without a sidecar sequence)
 /// - organism_id: Int32 (NCBI TaxID)
 ///
 /// Nested columns:
@@ -15,7 +19,7 @@ use std::sync::Arc;
 pub fn create_uniprot_schema() -> Schema {
     Schema::new(vec![
         Field::new("id", DataType::Utf8, false),
-        Field::new("sequence", DataType::Utf8, false),
+        Field::new("sequence", DataType::Utf8, true),
         Field::new("organism_id", DataType::Int32, true),
         Field::new("isoforms", isoforms_list_type(), true),
         Field::new("features", features_list_type(), true),
@@ -24,9 +28,13 @@ pub fn create_uniprot_schema() -> Schema {
         Field::new("entry_name", DataType::Utf8, true),
         Field::new("gene_name", DataType::Utf8, true),
         Field::new("protein_name", DataType::Utf8, true),
-        Field::new("organism_name", DataType::Utf8, true),
+        Field::new("organism_name", dict_utf8(), true),
         Field::new("existence", DataType::Int8, true),
         Field::new("structures", structures_list_type(), true),
+        // PDB chain ranges mapped onto the row's sequence
+        Field::new("structure_coverage", structure_coverage_list_type(), true),
+        // Mean AlphaFold pLDDT of the canonical model (`transform.alphafold_dir`)
+        Field::new("alphafold_mean_plddt", DataType::Float32, true),
         // Super-Substrate columns
         Field::new("parent_id", DataType::Utf8, false),
         Field::new("ptm_sites", ptm_sites_list_type(), true),
@@ -44,6 +52,29 @@ pub fn create_uniprot_schema() -> Schema {
         // Category B: Text-Based Comment Features
         Field::new("subunits", subunits_list_type(), true),
         Field::new("interactions", interactions_list_type(), true),
+        // Entry quality flags from <sequence fragment=... precursor=...>
+        Field::new("is_fragment", DataType::Boolean, false),
+        Field::new("is_precursor", DataType::Boolean, false),
+        // <entry dataset="Swiss-Prot|TrEMBL">: curated vs automatic
+        Field::new("dataset", dict_utf8(), true),
+        // <geneLocation type="mitochondrion|plastid|...">: organelle/plasmid-encoded genes
+        Field::new("gene_location", dict_utf8(), true),
+        // EC numbers from <recommendedName><ecNumber> and <dbReference type="EC">
+        Field::new("ec_numbers", ec_numbers_list_type(), true),
+        // Reactome/KEGG cross-references for pathway enrichment
+        Field::new("pathways", named_xrefs_list_type(), true),
+        // InterPro/Pfam/SMART cross-references (domain families)
+        Field::new("domain_xrefs", named_xrefs_list_type(), true),
+        // Flat counts, for SQL engines where list lengths of nested columns are awkward
+        Field::new("isoform_count", DataType::Int32, false),
+        Field::new("sequence_length", DataType::Int32, true),
+        Field::new("ptm_site_count", DataType::Int32, false),
+        // Long tail of comment types without a dedicated column
+        Field::new("other_comments", other_comments_list_type(), true),
+        // UniProt release of the run (see `release`), null when unknown
+        Field::new("release", dict_utf8(), true),
+        // canonical | sequenced | unsequenced: where the row's sequence came from
+        Field::new("isoform_status", dict_utf8(), false),
     ])
 }
 
@@ -52,12 +83,186 @@ pub fn schema_ref() -> Arc<Schema> {
     Arc::new(create_uniprot_schema())
 }
 
-/// Isoform struct: isoform_id, isoform_sequence, isoform_note
+/// Built-in schema followed by extra columns registered through `pipeline::enrich`.
+pub fn schema_with_extra_columns(extra: &[Field]) -> Arc<Schema> {
+    if extra.is_empty() {
+        return schema_ref();
+    }
+    let mut fields: Vec<Field> = create_uniprot_schema()
+        .fields()
+        .iter()
+        .map(|f| f.as_ref().clone())
+        .collect();
+    fields.extend(extra.iter().cloned());
+    Arc::new(Schema::new(fields))
+}
+
+/// Named subsets of the built-in columns (`schema.profile`).
+///
+/// Columns outside the profile are neither built nor written, so smaller profiles also
+/// save the builder work. Enricher columns are always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaProfile {
+    /// Accessions, sequence, organism and gene: 5 flat columns
+    Minimal,
+    /// Entry metadata plus the PTM, site, domain, variant, isoform and location columns
+    Standard,
+    /// Every column
+    #[default]
+    Full,
+}
+
+const MINIMAL_COLUMNS: &[&str] = &["id", "sequence", "organism_id", "gene_name", "parent_id"];
+
+const STANDARD_COLUMNS: &[&str] = &[
+    "id",
+    "sequence",
+    "organism_id",
+    "isoforms",
+    "location",
+    "entry_name",
+    "gene_name",
+    "protein_name",
+    "organism_name",
+    "existence",
+    "parent_id",
+    "ptm_sites",
+    "active_sites",
+    "binding_sites",
+    "domains",
+    "natural_variants",
+    "is_fragment",
+    "is_precursor",
+    "dataset",
+    "ec_numbers",
+    "isoform_count",
+    "sequence_length",
+    "ptm_site_count",
+    "release",
+    "isoform_status",
+];
+
+impl SchemaProfile {
+    pub fn name(self) -> &'static str {
+        match self {
+            SchemaProfile::Minimal => "minimal",
+            SchemaProfile::Standard => "standard",
+            SchemaProfile::Full => "full",
+        }
+    }
+
+    /// Whether the built-in column `name` is part of the profile.
+    pub fn includes(self, name: &str) -> bool {
+        match self {
+            SchemaProfile::Minimal => MINIMAL_COLUMNS.contains(&name),
+            SchemaProfile::Standard => STANDARD_COLUMNS.contains(&name),
+            SchemaProfile::Full => true,
+        }
+    }
+
+    /// `schema` without the built-in columns outside the profile (other columns are kept).
+    pub fn project(self, schema: &Schema) -> Arc<Schema> {
+        project_builtin(schema, |name| self.includes(name))
+    }
+
+    /// The built-in schema of the profile.
+    pub fn schema(self) -> Arc<Schema> {
+        self.project(&create_uniprot_schema())
+    }
+}
+
+/// `schema` without the built-in columns `keep` rejects (other columns are kept).
+pub fn project_builtin(schema: &Schema, keep: impl Fn(&str) -> bool) -> Arc<Schema> {
+    let builtin = create_uniprot_schema();
+    let fields: Vec<Field> = schema
+        .fields()
+        .iter()
+        .filter(|f| builtin.field_with_name(f.name()).is_err() || keep(f.name()))
+        .map(|f| f.as_ref().clone())
+        .collect();
+    Arc::new(Schema::new(fields))
+}
+
+/// Schema of the run directory's `rejected.parquet` (see `pipeline::rejects`).
+pub fn rejected_schema() -> Arc<Schema> {
+    Arc::new(Schema::new(vec![
+        Field::new("parent_id", DataType::Utf8, false),
+        Field::new("id", DataType::Utf8, false),
+        Field::new("kind", dict_utf8(), false),
+        Field::new("code", dict_utf8(), false),
+        Field::new("detail", DataType::Utf8, true),
+    ]))
+}
+
+/// Dictionary-encoded Utf8 for low-cardinality strings
+/// (organism_name, feature_type, evidence_code, structure db).
+pub fn dict_utf8() -> DataType {
+    DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+}
+
+/// Record batches and struct arrays: both have columns addressable by name.
+pub trait NamedColumns {
+    fn named_column(&self, name: &str) -> Option<&ArrayRef>;
+}
+
+impl NamedColumns for RecordBatch {
+    fn named_column(&self, name: &str) -> Option<&ArrayRef> {
+        self.column_by_name(name)
+    }
+}
+
+impl NamedColumns for StructArray {
+    fn named_column(&self, name: &str) -> Option<&ArrayRef> {
+        self.column_by_name(name)
+    }
+}
+
+/// String column `name` as plain Utf8, whether it was written plain or as `dict_utf8`
+/// (files from before a column was dictionary-encoded read back as plain Utf8).
+pub fn utf8_column(columns: &impl NamedColumns, name: &str) -> Result<StringArray> {
+    let column = columns
+        .named_column(name)
+        .ok_or_else(|| anyhow!("{} column not found", name))?;
+    let column = cast(column, &DataType::Utf8)?;
+    Ok(column
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .ok_or_else(|| anyhow!("{} is not a string column", name))?
+        .clone())
+}
+
+/// EC numbers: List<Utf8>, e.g. ["2.7.11.1"]; partial numbers keep their dashes ("3.4.-.-")
+fn ec_numbers_list_type() -> DataType {
+    DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)))
+}
+
+/// Named cross-reference struct of `pathways` and `domain_xrefs`: db ("Reactome", "KEGG";
+/// "InterPro", "Pfam", "SMART"), id, name (pathway/entry name; null for KEGG)
+fn named_xref_struct_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("db", dict_utf8(), false),
+        Field::new("id", DataType::Utf8, false),
+        Field::new("name", DataType::Utf8, true),
+    ])
+}
+
+fn named_xrefs_list_type() -> DataType {
+    DataType::List(Arc::new(Field::new(
+        "item",
+        DataType::Struct(named_xref_struct_fields()),
+        true,
+    )))
+}
+
+/// Isoform struct: isoform_id, isoform_sequence, isoform_note, sequence_type, event_type
 fn isoform_struct_fields() -> Fields {
     Fields::from(vec![
         Field::new("isoform_id", DataType::Utf8, false),
         Field::new("isoform_sequence", DataType::Utf8, true),
         Field::new("isoform_note", DataType::Utf8, true),
+        Field::new("sequence_type", dict_utf8(), true),
+        Field::new("event_type", dict_utf8(), true),
     ])
 }
 
@@ -69,14 +274,19 @@ fn isoforms_list_type() -> DataType {
     )))
 }
 
-/// Feature struct: feature_type, description, start, end, evidence_code
+/// Feature struct: feature_type, description, start, end, evidence_code, position_status
+///
+/// `position_status` is null for exact coordinates, otherwise the XML `status`
+/// ("less than", "greater than", "uncertain", "unknown"); ranges whose ends differ
+/// read "<begin>..<end>", e.g. "less than..certain".
 fn feature_struct_fields() -> Fields {
     Fields::from(vec![
-        Field::new("feature_type", DataType::Utf8, false),
+        Field::new("feature_type", dict_utf8(), false),
         Field::new("description", DataType::Utf8, true),
         Field::new("start", DataType::Int32, true),
         Field::new("end", DataType::Int32, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
+        Field::new("position_status", dict_utf8(), true),
     ])
 }
 
@@ -88,11 +298,14 @@ fn features_list_type() -> DataType {
     )))
 }
 
-/// Location struct: location, evidence_code
+/// Location struct: location, evidence_code, topology, orientation, molecule
 fn location_struct_fields() -> Fields {
     Fields::from(vec![
         Field::new("location", DataType::Utf8, false),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
+        Field::new("topology", dict_utf8(), true),
+        Field::new("orientation", dict_utf8(), true),
+        Field::new("molecule", DataType::Utf8, true),
     ])
 }
 
@@ -107,7 +320,7 @@ fn location_list_type() -> DataType {
 /// Structure struct: db, id
 fn structure_struct_fields() -> Fields {
     Fields::from(vec![
-        Field::new("db", DataType::Utf8, false),
+        Field::new("db", dict_utf8(), false),
         Field::new("id", DataType::Utf8, false),
     ])
 }
@@ -120,7 +333,28 @@ fn structures_list_type() -> DataType {
     )))
 }
 
-/// PTM sites: List<Struct<site_index, site_aa, modifications>>
+/// Structure coverage struct: pdb_id, method, resolution, chains, start, end
+fn structure_coverage_struct_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("pdb_id", DataType::Utf8, false),
+        Field::new("method", dict_utf8(), true),
+        Field::new("resolution", DataType::Float32, true),
+        Field::new("chains", DataType::Utf8, false),
+        Field::new("start", DataType::Int32, false),
+        Field::new("end", DataType::Int32, false),
+    ])
+}
+
+fn structure_coverage_list_type() -> DataType {
+    DataType::List(Arc::new(Field::new(
+        "item",
+        DataType::Struct(structure_coverage_struct_fields()),
+        true,
+    )))
+}
+
+/// PTM sites: List<Struct<site_index, site_aa, modifications, site_context, plddt,
+/// mapping_shift, mapping_failure_code>>
 fn ptm_sites_list_type() -> DataType {
     DataType::List(Arc::new(Field::new(
         "item",
@@ -134,6 +368,10 @@ fn ptm_site_struct_fields() -> Fields {
         Field::new("site_index", DataType::Int32, false),
         Field::new("site_aa", DataType::Utf8, false),
         Field::new("modifications", ptm_modifications_list_type(), true),
+        Field::new("site_context", DataType::Utf8, true),
+        Field::new("plddt", DataType::Float32, true),
+        Field::new("mapping_shift", DataType::Int32, true),
+        Field::new("mapping_failure_code", dict_utf8(), true),
     ])
 }
 
@@ -149,13 +387,24 @@ fn ptm_modification_struct_fields() -> Fields {
     Fields::from(vec![
         Field::new("mod_type", DataType::Int32, false),
         Field::new("confidence_score", DataType::Float32, false),
+        Field::new("partner_protein", DataType::Utf8, true),
+        Field::new("partner_residue", DataType::Utf8, true),
+        Field::new("linkage_chemistry", dict_utf8(), true),
+        Field::new("mod_class", dict_utf8(), true),
+        Field::new("mod_subclass", dict_utf8(), true),
+        Field::new("ptm_accession", DataType::Utf8, true),
+        Field::new("target_aa", DataType::Utf8, true),
+        Field::new("monoisotopic_mass_delta", DataType::Float64, true),
+        Field::new("average_mass_delta", DataType::Float64, true),
+        Field::new("evidence_code", dict_utf8(), true),
     ])
 }
 // ============================================================================
 // Schema Helpers for 8 New Enriched Features
 // ============================================================================
 
-/// Active Site struct: id, description, start, end, confidence_score
+/// Active Site struct: id, description, start, end, confidence_score, position_status,
+/// mapping_shift, mapping_failure_code
 fn active_sites_list_type() -> DataType {
     DataType::List(Arc::new(Field::new(
         "item",
@@ -164,7 +413,8 @@ fn active_sites_list_type() -> DataType {
     )))
 }
 
-/// Binding Site struct: id, description, start, end, confidence_score
+/// Binding Site struct: id, description, start, end, confidence_score, position_status,
+/// mapping_shift, mapping_failure_code
 fn binding_sites_list_type() -> DataType {
     DataType::List(Arc::new(Field::new(
         "item",
@@ -173,7 +423,8 @@ fn binding_sites_list_type() -> DataType {
     )))
 }
 
-/// Metal Coordination Site struct: id, description, metal, start, end, confidence_score
+/// Metal Coordination Site struct: id, description, metal, start, end, confidence_score, position_status,
+/// mapping_shift, mapping_failure_code
 fn metal_coordinations_list_type() -> DataType {
     DataType::List(Arc::new(Field::new(
         "item",
@@ -189,12 +440,16 @@ fn metal_coordination_struct_fields() -> Fields {
         Field::new("metal", DataType::Utf8, true),
         Field::new("start", DataType::Int32, true),
         Field::new("end", DataType::Int32, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("position_status", dict_utf8(), true),
+        Field::new("mapping_shift", DataType::Int32, true),
+        Field::new("mapping_failure_code", dict_utf8(), true),
     ])
 }
 
-/// Mutagenesis Site struct: id, description, start, end, confidence_score
+/// Mutagenesis Site struct: id, description, start, end, confidence_score, position_status,
+/// mapping_shift, mapping_failure_code
 fn mutagenesis_sites_list_type() -> DataType {
     DataType::List(Arc::new(Field::new(
         "item",
@@ -203,7 +458,12 @@ fn mutagenesis_sites_list_type() -> DataType {
     )))
 }
 
-/// Domain struct: id, description, domain_name, start, end, confidence_score
+/// Domain struct: id, description, domain_name, family_accessions, start, end, confidence_score,
+/// position_status,
+/// mapping_shift, mapping_failure_code
+///
+/// `family_accessions` lists the `domain_xrefs` ids whose name matches the domain
+/// (`transform.reconcile_domain_xrefs`); null when reconciliation is off.
 fn domains_list_type() -> DataType {
     DataType::List(Arc::new(Field::new(
         "item",
@@ -217,14 +477,24 @@ fn domain_struct_fields() -> Fields {
         Field::new("id", DataType::Utf8, true),
         Field::new("description", DataType::Utf8, true),
         Field::new("domain_name", DataType::Utf8, true),
+        Field::new(
+            "family_accessions",
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            true,
+        ),
         Field::new("start", DataType::Int32, true),
         Field::new("end", DataType::Int32, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("position_status", dict_utf8(), true),
+        Field::new("mapping_shift", DataType::Int32, true),
+        Field::new("mapping_failure_code", dict_utf8(), true),
     ])
 }
 
-/// Natural Variant struct: id, description, original, variation, start, end, confidence_score
+/// Natural Variant struct: id, description, original, variation, dbsnp_id, disease_association,
+/// start, end, confidence_score, position_status,
+/// mapping_shift, mapping_failure_code
 fn natural_variants_list_type() -> DataType {
     DataType::List(Arc::new(Field::new(
         "item",
@@ -239,14 +509,19 @@ fn natural_variant_struct_fields() -> Fields {
         Field::new("description", DataType::Utf8, true),
         Field::new("original", DataType::Utf8, true),
         Field::new("variation", DataType::Utf8, true),
+        Field::new("dbsnp_id", DataType::Utf8, true),
+        Field::new("disease_association", DataType::Utf8, true),
         Field::new("start", DataType::Int32, true),
         Field::new("end", DataType::Int32, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("position_status", dict_utf8(), true),
+        Field::new("mapping_shift", DataType::Int32, true),
+        Field::new("mapping_failure_code", dict_utf8(), true),
     ])
 }
 
-/// Subunit comment struct: text, confidence_score
+/// Subunit comment struct: text, evidence_code, confidence_score, molecule
 fn subunits_list_type() -> DataType {
     DataType::List(Arc::new(Field::new(
         "item",
@@ -258,8 +533,9 @@ fn subunits_list_type() -> DataType {
 fn subunit_struct_fields() -> Fields {
     Fields::from(vec![
         Field::new("text", DataType::Utf8, false),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("molecule", DataType::Utf8, true),
     ])
 }
 
@@ -276,19 +552,48 @@ fn interaction_struct_fields() -> Fields {
     Fields::from(vec![
         Field::new("interactant_id_1", DataType::Utf8, true),
         Field::new("interactant_id_2", DataType::Utf8, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("intact_id_1", DataType::Utf8, true),
+        Field::new("intact_id_2", DataType::Utf8, true),
+        Field::new("organisms_differ", DataType::Boolean, true),
+        Field::new("experiments", DataType::Int32, true),
+    ])
+}
+
+/// Other comment struct: comment_type, text, evidence_code, molecule
+fn other_comments_list_type() -> DataType {
+    DataType::List(Arc::new(Field::new(
+        "item",
+        DataType::Struct(other_comment_struct_fields()),
+        true,
+    )))
+}
+
+fn other_comment_struct_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("comment_type", dict_utf8(), false),
+        Field::new("text", DataType::Utf8, false),
+        Field::new("evidence_code", dict_utf8(), true),
+        Field::new("molecule", DataType::Utf8, true),
     ])
 }
 
 /// Helper for coordinate-based features with standard fields
+///
+/// `mapping_shift` (mapped minus canonical start) and `mapping_failure_code` are only
+/// filled with `transform.debug_mapping`, which also keeps features that fail to map,
+/// in canonical coordinates and with the failure code set.
 fn coordinate_feature_struct_fields(_feature_name: &str) -> Fields {
     Fields::from(vec![
         Field::new("id", DataType::Utf8, true),
         Field::new("description", DataType::Utf8, true),
         Field::new("start", DataType::Int32, true),
         Field::new("end", DataType::Int32, true),
-        Field::new("evidence_code", DataType::Utf8, true),
+        Field::new("evidence_code", dict_utf8(), true),
         Field::new("confidence_score", DataType::Float32, true),
+        Field::new("position_status", dict_utf8(), true),
+        Field::new("mapping_shift", DataType::Int32, true),
+        Field::new("mapping_failure_code", dict_utf8(), true),
     ])
 }
\ No newline at end of file
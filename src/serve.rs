@@ -0,0 +1,339 @@
+//! On-demand extraction service (`uniprot_etl serve`).
+//!
+//! A small blocking HTTP server that runs a handful of entries through the same
+//! parser/transformer as a batch run and returns the rows as JSON or an Arrow IPC
+//! stream:
+//!
+//! - `POST /extract` with raw UniProt XML as the body
+//! - `GET /entry/<accession>` to fetch the entry from the UniProt REST API first
+//! - `GET /health`
+//!
+//! Add `?format=arrow` for `application/vnd.apache.arrow.stream` (default: JSON).
+//! Bad input is answered with 400, a failed or unusable UniProt REST fetch with 502.
+
+use std::collections::HashMap;
+use std::io::{BufReader, Cursor, Read};
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{
+    Array, BooleanArray, Float32Array, Float64Array, Int32Array, Int64Array, Int8Array, ListArray,
+    StringArray, StructArray,
+};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, SchemaRef};
+use arrow::ipc::writer::{IpcWriteOptions, StreamWriter};
+use arrow::record_batch::RecordBatch;
+use crossbeam_channel::unbounded;
+use quick_xml::Reader;
+use serde_json::{Map, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::metrics::Metrics;
+use crate::pipeline::parser::{parse_entries_with_hooks, ParseHooks};
+use crate::rest::{fetch_entry_xml, is_valid_accession};
+use crate::schema::schema_ref;
+use crate::{log_info, log_warn};
+
+/// Largest XML body accepted by `POST /extract`.
+pub const MAX_BODY_BYTES: u64 = 64 * 1024 * 1024;
+
+const ARROW_STREAM_MIME: &str = "application/vnd.apache.arrow.stream";
+
+/// Server settings.
+#[derive(Clone)]
+pub struct ServeOptions {
+    /// Base URL of the UniProtKB REST API used by `/entry/<accession>`
+    pub rest_url: String,
+    /// Number of request-handling threads
+    pub workers: usize,
+    /// Optional isoform sidecar (same role as in a batch run)
+    pub sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    /// Parse hooks of a batch run with the same settings (release, transform options, ...)
+    pub hooks: ParseHooks,
+}
+
+/// Response encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Arrow,
+}
+
+/// Runs `xml` (one or more `<entry>` elements inside `<uniprot>`) through the pipeline.
+pub fn extract_batches(
+    xml: &[u8],
+    sidecar_fasta: Option<Arc<HashMap<String, String>>>,
+    hooks: &ParseHooks,
+) -> Result<Vec<RecordBatch>> {
+    let mut reader = Reader::from_reader(BufReader::new(Cursor::new(xml)));
+    reader.config_mut().trim_text(true);
+
+    let (tx, rx) = unbounded();
+    parse_entries_with_hooks(reader, tx, &Metrics::new(), 10_000, sidecar_fasta, hooks)?;
+    Ok(rx.iter().collect())
+}
+
+/// Encodes batches as an Arrow IPC stream (schema only when there are no rows).
+pub fn encode_arrow(batches: &[RecordBatch]) -> Result<Vec<u8>> {
+    let schema: SchemaRef = batches.first().map_or_else(schema_ref, |b| b.schema());
+    let mut out = Vec::new();
+    // Every dictionary column shares dict_id 0 in the schema; let the writer number them
+    let options = IpcWriteOptions::default().with_preserve_dict_id(false);
+    let mut writer = StreamWriter::try_new_with_options(&mut out, &schema, options)?;
+    for batch in batches {
+        writer.write(batch)?;
+    }
+    writer.finish()?;
+    drop(writer);
+    Ok(out)
+}
+
+/// Encodes batches as a JSON array with one object per row.
+pub fn encode_json(batches: &[RecordBatch]) -> Result<Value> {
+    let mut rows = Vec::new();
+    for batch in batches {
+        let schema = batch.schema();
+        for row in 0..batch.num_rows() {
+            let mut object = Map::new();
+            for (field, column) in schema.fields().iter().zip(batch.columns()) {
+                object.insert(field.name().clone(), json_value(column.as_ref(), row)?);
+            }
+            rows.push(Value::Object(object));
+        }
+    }
+    Ok(Value::Array(rows))
+}
+
+fn json_value(array: &dyn Array, index: usize) -> Result<Value> {
+    if array.is_null(index) {
+        return Ok(Value::Null);
+    }
+
+    macro_rules! primitive {
+        ($ty:ty) => {
+            Value::from(array.as_any().downcast_ref::<$ty>().unwrap().value(index))
+        };
+    }
+
+    Ok(match array.data_type() {
+        DataType::Utf8 => primitive!(StringArray),
+        DataType::Boolean => primitive!(BooleanArray),
+        DataType::Int8 => primitive!(Int8Array),
+        DataType::Int32 => primitive!(Int32Array),
+        DataType::Int64 => primitive!(Int64Array),
+        DataType::Float32 => primitive!(Float32Array),
+        DataType::Float64 => primitive!(Float64Array),
+        DataType::Dictionary(_, _) => {
+            let strings = cast(&array.slice(index, 1), &DataType::Utf8)?;
+            json_value(strings.as_ref(), 0)?
+        }
+        DataType::List(_) => {
+            let values = array.as_any().downcast_ref::<ListArray>().unwrap().value(index);
+            Value::Array(
+                (0..values.len())
+                    .map(|i| json_value(values.as_ref(), i))
+                    .collect::<Result<_>>()?,
+            )
+        }
+        DataType::Struct(fields) => {
+            let structs = array.as_any().downcast_ref::<StructArray>().unwrap();
+            let mut object = Map::new();
+            for (field, column) in fields.iter().zip(structs.columns()) {
+                object.insert(field.name().clone(), json_value(column.as_ref(), index)?);
+            }
+            Value::Object(object)
+        }
+        other => return Err(anyhow!("Unsupported column type for JSON output: {}", other)),
+    })
+}
+
+/// Binds `addr` and serves requests until the process is killed.
+pub fn serve(addr: &str, options: ServeOptions) -> Result<()> {
+    let server = Arc::new(Server::http(addr).map_err(|e| anyhow!("Failed to bind {}: {}", addr, e))?);
+    log_info!("serve", "Serving on http://{} ({} workers)", addr, options.workers);
+
+    let options = Arc::new(options);
+    let workers: Vec<_> = (0..options.workers.max(1))
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let options = Arc::clone(&options);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle(request, &options);
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker
+            .join()
+            .map_err(|_| anyhow!("Server worker thread panicked"))?;
+    }
+    Ok(())
+}
+
+fn handle(mut request: Request, options: &ServeOptions) {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let format = if query.split('&').any(|p| p == "format=arrow") {
+        OutputFormat::Arrow
+    } else {
+        OutputFormat::Json
+    };
+
+    let response = route(&method, path, format, || read_body(&mut request), options)
+        .unwrap_or_else(|(status, e)| {
+            log_warn!("serve", "{} {} failed ({}): {:#}", method, path, status, e);
+            text_response(status, &format!("{:#}", e))
+        });
+    // The client may already have gone away; nothing useful to do about it
+    let _ = request.respond(response);
+}
+
+/// Status code and cause of a failed request.
+type Failure = (u16, anyhow::Error);
+
+fn bad_request(e: anyhow::Error) -> Failure {
+    (400, e)
+}
+
+fn bad_gateway(e: anyhow::Error) -> Failure {
+    (502, e)
+}
+
+fn route(
+    method: &Method,
+    path: &str,
+    format: OutputFormat,
+    body: impl FnOnce() -> Result<Vec<u8>>,
+    options: &ServeOptions,
+) -> std::result::Result<Response<Cursor<Vec<u8>>>, Failure> {
+    let extract = |xml: &[u8]| extract_batches(xml, options.sidecar_fasta.clone(), &options.hooks);
+    match (method, path) {
+        (Method::Get, "/health") => Ok(text_response(200, "ok")),
+        (Method::Post, "/extract") => {
+            let batches = body().and_then(|xml| extract(&xml)).map_err(bad_request)?;
+            rows_response(&batches, format).map_err(bad_request)
+        }
+        (Method::Get, p) if p.starts_with("/entry/") => {
+            let accession = &p["/entry/".len()..];
+            if !is_valid_accession(accession) {
+                return Err(bad_request(anyhow!("Invalid accession: {:?}", accession)));
+            }
+            // The accession is fine from here on: anything that fails is UniProt's answer
+            let batches = fetch_entry_xml(&options.rest_url, accession)
+                .and_then(|xml| extract(&xml))
+                .map_err(bad_gateway)?;
+            rows_response(&batches, format).map_err(bad_gateway)
+        }
+        _ => Ok(text_response(404, "not found")),
+    }
+}
+
+fn read_body(request: &mut Request) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+        .context("Failed to read request body")?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(anyhow!("Request body exceeds {} bytes", MAX_BODY_BYTES));
+    }
+    Ok(body)
+}
+
+fn rows_response(batches: &[RecordBatch], format: OutputFormat) -> Result<Response<Cursor<Vec<u8>>>> {
+    let (body, mime) = match format {
+        OutputFormat::Json => (serde_json::to_vec(&encode_json(batches)?)?, "application/json"),
+        OutputFormat::Arrow => (encode_arrow(batches)?, ARROW_STREAM_MIME),
+    };
+    Ok(Response::from_data(body).with_header(content_type(mime)))
+}
+
+fn text_response(status: u16, body: &str) -> Response<Cursor<Vec<u8>>> {
+    Response::from_data(body.as_bytes().to_vec())
+        .with_status_code(status)
+        .with_header(content_type("text/plain; charset=utf-8"))
+}
+
+fn content_type(mime: &str) -> Header {
+    Header::from_bytes("Content-Type", mime).expect("static header is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::ipc::reader::StreamReader;
+
+    const XML: &str = r#"<uniprot>
+        <entry dataset="Swiss-Prot"><accession>P1</accession>
+            <feature type="domain" description="Kinase"><location><begin position="1"/><end position="3"/></location></feature>
+            <sequence length="4">MTAK</sequence>
+        </entry>
+    </uniprot>"#;
+
+    fn options(rest_url: &str) -> ServeOptions {
+        ServeOptions {
+            rest_url: rest_url.to_string(),
+            workers: 1,
+            sidecar_fasta: None,
+            hooks: ParseHooks {
+                release: Some("2024_06".to_string()),
+                ..ParseHooks::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_extract_to_json_and_arrow() {
+        let batches = extract_batches(XML.as_bytes(), None, &ParseHooks::default()).unwrap();
+
+        let json = encode_json(&batches).unwrap();
+        let row = &json.as_array().unwrap()[0];
+        assert_eq!(row["id"], "P1");
+        assert_eq!(row["sequence"], "MTAK");
+        assert_eq!(row["dataset"], "Swiss-Prot");
+        assert_eq!(row["features"][0]["feature_type"], "domain");
+        assert_eq!(row["features"][0]["end"], 3);
+
+        let ipc = encode_arrow(&batches).unwrap();
+        let decoded: Vec<_> = StreamReader::try_new(Cursor::new(ipc), None)
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(decoded, batches);
+
+        // No entries still yields a readable (schema-only) stream
+        let empty = encode_arrow(&[]).unwrap();
+        let reader = StreamReader::try_new(Cursor::new(empty), None).unwrap();
+        assert_eq!(reader.schema(), schema_ref());
+    }
+
+    #[test]
+    fn test_status_codes_and_hooks() {
+        let options = options("http://127.0.0.1:9");
+        let status = |method: Method, path: &str, body: &str| {
+            let body = body.as_bytes().to_vec();
+            match route(&method, path, OutputFormat::Json, || Ok(body), &options) {
+                Ok(response) => response.status_code().0,
+                Err((status, _)) => status,
+            }
+        };
+
+        assert_eq!(status(Method::Post, "/extract", XML), 200);
+        assert_eq!(status(Method::Post, "/extract", "<uniprot><entry>"), 400);
+        assert_eq!(status(Method::Get, "/entry/not%20an%20accession", ""), 400);
+        // Unreachable REST API: not the client's fault
+        assert_eq!(status(Method::Get, "/entry/P04637", ""), 502);
+        assert_eq!(status(Method::Get, "/nowhere", ""), 404);
+
+        // Served rows carry the configured release
+        let batches = extract_batches(XML.as_bytes(), None, &options.hooks).unwrap();
+        assert_eq!(encode_json(&batches).unwrap()[0]["release"], "2024_06");
+    }
+}
@@ -0,0 +1,50 @@
+//! Clean shutdown on SIGINT/SIGTERM.
+//!
+//! The first signal only sets a flag: parsers stop at the next entry boundary, the
+//! batches already built are flushed, writers close their output normally and the
+//! run report is written with a `Cancelled` status. A second signal exits at once.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use crate::log_warn;
+
+/// Exit code of a forced exit on the second signal (128 + SIGINT).
+const FORCED_EXIT_CODE: i32 = 130;
+
+/// Shared cancellation flag; clones observe the same state.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Installs the process-wide SIGINT/SIGTERM handler and returns the token it cancels.
+///
+/// Can be called once per process.
+pub fn install_signal_handler() -> Result<CancelToken> {
+    let token = CancelToken::new();
+    let handler_token = token.clone();
+    ctrlc::set_handler(move || {
+        if handler_token.is_cancelled() {
+            log_warn!("shutdown", "Second signal received, exiting without cleanup");
+            std::process::exit(FORCED_EXIT_CODE);
+        }
+        log_warn!("shutdown", "Signal received, finishing current batches (signal again to force exit)");
+        handler_token.cancel();
+    })
+    .context("Failed to install SIGINT/SIGTERM handler")?;
+    Ok(token)
+}
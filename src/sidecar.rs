@@ -0,0 +1,420 @@
+//! Sidecar FASTA auto-download and cache.
+//!
+//! When no `fasta_sidecar_path` is configured but the input contains isoforms,
+//! the matching UniProt varsplic FASTA can be fetched into a local cache and
+//! used for the run. The checksum of the file actually used is recorded in the report.
+//!
+//! Loaded sidecars are spot-checked against the XML: a FASTA from a different
+//! release shows up as isoform length disagreements here instead of as an
+//! unexplained RESIDUE_MISMATCH spike later in the run.
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use quick_xml::events::Event;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::config::{Settings, SidecarCheckConfig, SidecarDownloadConfig};
+use crate::metrics::Metrics;
+use crate::pipeline::handlers::metadata;
+use crate::pipeline::mapper::CoordinateMapper;
+use crate::pipeline::reader::create_xml_reader;
+use crate::pipeline::scratch::EntryScratch;
+use crate::pipeline::transformer::canonical_isoform_id;
+
+/// Provenance of an auto-resolved sidecar FASTA, recorded in report.yaml.
+#[derive(Serialize, Clone, Debug)]
+pub struct SidecarInfo {
+    /// Local path of the (decompressed) FASTA used for the run
+    pub path: PathBuf,
+    /// URL the FASTA was (or would have been) downloaded from
+    pub source_url: String,
+    /// SHA-256 of the local FASTA file (hex)
+    pub sha256: String,
+    /// True if the file was already present in the cache
+    pub cache_hit: bool,
+}
+
+/// Outcome of the sidecar/XML release-consistency spot check.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SidecarCheck {
+    /// Entries with isoforms that were inspected
+    pub entries_checked: usize,
+    /// Isoforms whose sidecar record was compared
+    pub isoforms_checked: usize,
+    /// Isoforms whose sidecar length disagrees with canonical length + VSP delta
+    pub length_mismatches: usize,
+    /// Isoforms referenced by the XML but absent from the sidecar
+    pub missing_records: usize,
+    /// A few offending isoform ids for the log
+    pub examples: Vec<String>,
+}
+
+impl SidecarCheck {
+    /// Fraction of checked isoforms that were mismatched or missing.
+    pub fn mismatch_ratio(&self) -> f64 {
+        let total = self.isoforms_checked + self.missing_records;
+        if total == 0 {
+            return 0.0;
+        }
+        (self.length_mismatches + self.missing_records) as f64 / total as f64
+    }
+
+    /// True when the ratio exceeds the configured tolerance.
+    pub fn is_suspect(&self, cfg: &SidecarCheckConfig) -> bool {
+        self.mismatch_ratio() > cfg.max_mismatch_ratio
+    }
+}
+
+const MAX_CHECK_EXAMPLES: usize = 5;
+
+/// Spot-checks the sidecar against the first `cfg.sample_entries` isoform-bearing entries.
+///
+/// For every isoform, the expected length is the canonical length plus the net
+/// delta of its VSP edits; a sidecar from the same release must agree.
+pub fn check_sidecar_consistency(
+    input: &Path,
+    settings: &Settings,
+    sidecar: &HashMap<String, String>,
+    cfg: &SidecarCheckConfig,
+) -> Result<SidecarCheck> {
+    let mut check = SidecarCheck::default();
+    if cfg.sample_entries == 0 {
+        return Ok(check);
+    }
+
+    for path in probe_files(input)? {
+        check_file(&path, settings, sidecar, cfg.sample_entries, &mut check)?;
+        if check.entries_checked >= cfg.sample_entries {
+            break;
+        }
+    }
+    Ok(check)
+}
+
+fn check_file(
+    path: &Path,
+    settings: &Settings,
+    sidecar: &HashMap<String, String>,
+    sample_entries: usize,
+    check: &mut SidecarCheck,
+) -> Result<()> {
+    let metrics = Metrics::new();
+    let mut reader = create_xml_reader(path, settings, &metrics)?;
+    let mut scratch = EntryScratch::new();
+    let mut buf = Vec::with_capacity(4096);
+
+    while check.entries_checked < sample_entries {
+        buf.clear();
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"entry" => {
+                scratch.reset();
+                metadata::consume_entry(&mut reader, &mut scratch, &mut buf)?;
+                let entry = scratch.take_entry();
+                if entry.isoforms.is_empty() {
+                    continue;
+                }
+                check.entries_checked += 1;
+
+                for iso in &entry.isoforms {
+                    let isoform_id = canonical_isoform_id(iso);
+                    let Some(seq) = sidecar.get(&isoform_id) else {
+                        check.missing_records += 1;
+                        if check.examples.len() < MAX_CHECK_EXAMPLES {
+                            check.examples.push(format!("{} (missing)", isoform_id));
+                        }
+                        continue;
+                    };
+
+                    check.isoforms_checked += 1;
+                    let mapper = CoordinateMapper::from_entry_for_vsp_ids(&entry, &iso.vsp_ids);
+                    let expected = mapper.expected_len(entry.sequence.len());
+                    if seq.len() as i64 != expected {
+                        check.length_mismatches += 1;
+                        if check.examples.len() < MAX_CHECK_EXAMPLES {
+                            check.examples.push(format!(
+                                "{} (expected {} aa, sidecar {} aa)",
+                                isoform_id,
+                                expected,
+                                seq.len()
+                            ));
+                        }
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if any of the first `probe_entries` entries of the input declare isoforms.
+///
+/// For directory inputs, each `.xml`/`.xml.gz` file is probed in name order until one matches.
+pub fn input_has_isoforms(input: &Path, settings: &Settings, probe_entries: usize) -> Result<bool> {
+    for path in probe_files(input)? {
+        if file_has_isoforms(&path, settings, probe_entries)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn probe_files(input: &Path) -> Result<Vec<PathBuf>> {
+    if !input.is_dir() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(input)
+        .with_context(|| format!("Failed to read input directory: {}", input.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.ends_with(".xml") || n.ends_with(".xml.gz"))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn file_has_isoforms(path: &Path, settings: &Settings, probe_entries: usize) -> Result<bool> {
+    let metrics = Metrics::new();
+    let mut reader = create_xml_reader(path, settings, &metrics)?;
+    let mut buf = Vec::with_capacity(4096);
+    let mut entries_seen = 0usize;
+
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"isoform" => {
+                return Ok(true);
+            }
+            Event::End(e) if e.local_name().as_ref() == b"entry" => {
+                entries_seen += 1;
+                if entries_seen >= probe_entries {
+                    return Ok(false);
+                }
+            }
+            Event::Eof => return Ok(false),
+            _ => {}
+        }
+    }
+}
+
+/// Renders the download URL, substituting `{release}` from the configuration.
+pub fn render_url(cfg: &SidecarDownloadConfig) -> Result<String> {
+    if !cfg.url_template.contains("{release}") {
+        return Ok(cfg.url_template.clone());
+    }
+    let release = cfg.release.as_deref().ok_or_else(|| {
+        anyhow!("sidecar_download.url_template contains {{release}} but sidecar_download.release is not set")
+    })?;
+    Ok(cfg.url_template.replace("{release}", release))
+}
+
+/// Local cache path for the decompressed sidecar of the configured release.
+///
+/// Without a release the file is keyed as "current" and only ever overwritten,
+/// never reused: `resolve_sidecar` has no way to tell whether it is stale.
+pub fn cached_sidecar_path(cfg: &SidecarDownloadConfig) -> PathBuf {
+    let release = cfg.release.as_deref().unwrap_or("current");
+    cfg.cache_dir.join(format!("uniprot_varsplic_{}.fasta", release))
+}
+
+/// Resolves the sidecar FASTA from the cache, downloading it first if necessary.
+///
+/// The cache is only trusted when `cfg.release` is set; an unversioned sidecar
+/// is downloaded again on every run.
+pub fn resolve_sidecar(cfg: &SidecarDownloadConfig) -> Result<SidecarInfo> {
+    let url = render_url(cfg)?;
+    let path = cached_sidecar_path(cfg);
+    let cache_hit = cfg.release.is_some() && path.exists();
+
+    if !cache_hit {
+        fs::create_dir_all(&cfg.cache_dir).with_context(|| {
+            format!(
+                "Failed to create sidecar cache directory: {}",
+                cfg.cache_dir.display()
+            )
+        })?;
+        download_to(&url, &path)?;
+    }
+
+    let sha256 = sha256_file(&path)?;
+    Ok(SidecarInfo {
+        path,
+        source_url: url,
+        sha256,
+        cache_hit,
+    })
+}
+
+/// Downloads `url` to `dest`, decompressing gzip payloads on the fly.
+///
+/// Data is written to a `.part` file first and renamed on success so an
+/// interrupted download never leaves a truncated FASTA in the cache.
+fn download_to(url: &str, dest: &Path) -> Result<()> {
+    let response = ureq::get(url)
+        .call()
+        .with_context(|| format!("Failed to download sidecar FASTA from {}", url))?;
+    let body = response.into_reader();
+
+    let part = dest.with_extension("fasta.part");
+    let mut out = File::create(&part)
+        .with_context(|| format!("Failed to create {}", part.display()))?;
+
+    let mut reader: Box<dyn Read> = if url.ends_with(".gz") {
+        Box::new(GzDecoder::new(body))
+    } else {
+        Box::new(body)
+    };
+    io::copy(&mut reader, &mut out)
+        .with_context(|| format!("Failed to write sidecar FASTA to {}", part.display()))?;
+    drop(out);
+
+    fs::rename(&part, dest)
+        .with_context(|| format!("Failed to move {} into place", part.display()))?;
+    Ok(())
+}
+
+/// Computes the hex-encoded SHA-256 of a file.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_release_placeholder() {
+        let cfg = SidecarDownloadConfig {
+            url_template: "https://example.org/release-{release}/varsplic.fasta.gz".to_string(),
+            release: Some("2024_06".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_url(&cfg).unwrap(),
+            "https://example.org/release-2024_06/varsplic.fasta.gz"
+        );
+        assert!(cached_sidecar_path(&cfg).ends_with("uniprot_varsplic_2024_06.fasta"));
+
+        let missing = SidecarDownloadConfig {
+            release: None,
+            ..cfg
+        };
+        assert!(render_url(&missing).is_err());
+    }
+
+    #[test]
+    fn cache_hit_skips_download_and_hashes_file() {
+        let cache_dir = std::env::temp_dir().join("uniprot_etl_test_sidecar_cache");
+        let _ = fs::remove_dir_all(&cache_dir);
+        fs::create_dir_all(&cache_dir).unwrap();
+
+        let cfg = SidecarDownloadConfig {
+            enabled: true,
+            // Unreachable URL: a cache hit must never touch the network.
+            url_template: "http://127.0.0.1:9/varsplic.fasta".to_string(),
+            release: Some("2024_06".to_string()),
+            cache_dir: cache_dir.clone(),
+            ..Default::default()
+        };
+        fs::write(cached_sidecar_path(&cfg), ">sp|Q9TEST-2|X\nMTAK\n").unwrap();
+
+        let info = resolve_sidecar(&cfg).unwrap();
+        assert!(info.cache_hit);
+        assert_eq!(info.sha256.len(), 64);
+
+        // An unversioned cache file may be stale, so it is downloaded again (and fails here)
+        let unversioned = SidecarDownloadConfig { release: None, ..cfg };
+        fs::write(cached_sidecar_path(&unversioned), ">sp|Q9TEST-2|X\nMTAK\n").unwrap();
+        assert!(resolve_sidecar(&unversioned).is_err());
+
+        let _ = fs::remove_dir_all(&cache_dir);
+    }
+
+    #[test]
+    fn flags_sidecar_from_another_release() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_sidecar_check");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let xml = dir.join("entries.xml");
+        fs::write(
+            &xml,
+            r#"<uniprot><entry><accession>Q1</accession>
+            <comment type="alternative products">
+                <isoform><id>Q1-1</id><sequence type="displayed"/></isoform>
+                <isoform><id>Q1-2</id><sequence type="described" ref="VSP_1"/></isoform>
+            </comment>
+            <feature type="splice variant" id="VSP_1">
+                <location><begin position="2"/><end position="3"/></location>
+            </feature>
+            <sequence length="6">MTAKLV</sequence>
+            </entry></uniprot>"#,
+        )
+        .unwrap();
+
+        let settings = Settings::default();
+        let cfg = SidecarCheckConfig::default();
+
+        let mut same_release = HashMap::new();
+        same_release.insert("Q1-1".to_string(), "MTAKLV".to_string());
+        same_release.insert("Q1-2".to_string(), "MKLV".to_string());
+        let check = check_sidecar_consistency(&xml, &settings, &same_release, &cfg).unwrap();
+        assert_eq!(check.isoforms_checked, 2);
+        assert_eq!(check.length_mismatches, 0);
+        assert!(!check.is_suspect(&cfg));
+
+        let mut other_release = HashMap::new();
+        other_release.insert("Q1-1".to_string(), "MTAKLVQQ".to_string());
+        let check = check_sidecar_consistency(&xml, &settings, &other_release, &cfg).unwrap();
+        assert_eq!(check.length_mismatches, 1);
+        assert_eq!(check.missing_records, 1);
+        assert!(check.is_suspect(&cfg));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn probes_input_for_isoforms() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_sidecar_probe");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let with_iso = dir.join("with_iso.xml");
+        fs::write(
+            &with_iso,
+            r#"<uniprot><entry><accession>Q1</accession>
+            <comment type="alternative products"><isoform><id>Q1-1</id></isoform></comment>
+            </entry></uniprot>"#,
+        )
+        .unwrap();
+        let without_iso = dir.join("without_iso.xml");
+        fs::write(
+            &without_iso,
+            "<uniprot><entry><accession>Q2</accession></entry></uniprot>",
+        )
+        .unwrap();
+
+        let settings = Settings::default();
+        assert!(input_has_isoforms(&with_iso, &settings, 10).unwrap());
+        assert!(!input_has_isoforms(&without_iso, &settings, 10).unwrap());
+        assert!(input_has_isoforms(&dir, &settings, 10).unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
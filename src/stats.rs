@@ -0,0 +1,383 @@
+//! Dataset-level statistics over produced Parquet output (`uniprot_etl stats`).
+//!
+//! Replaces the ad-hoc inspect binaries with one report that can run in CI on
+//! sample data: row/parent counts, isoform ratio, feature-type and PTM-type
+//! histograms, rows per organism, per-column null rates and file sizes.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{Array, Int32Array, ListArray, StringArray, StructArray};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ProjectionMask;
+use serde::Serialize;
+
+use crate::pipeline::ptm_vocab::ModType;
+use crate::writer::dataset::output_files;
+
+/// Per-file summary.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileStats {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub rows: u64,
+    pub row_groups: usize,
+}
+
+/// Aggregate statistics across one or more Parquet files.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DatasetStats {
+    pub files: Vec<FileStats>,
+    pub total_bytes: u64,
+    pub rows: u64,
+    pub distinct_parents: u64,
+    /// Rows per distinct parent (1.0 = no isoform explosion)
+    pub isoform_ratio: f64,
+    pub feature_type_counts: BTreeMap<String, u64>,
+    pub ptm_type_histogram: BTreeMap<String, u64>,
+    /// Rows per `organism_id` (NCBI TaxID; "unknown" when null)
+    pub organism_counts: BTreeMap<String, u64>,
+    /// `organism_name` of each TaxID in `organism_counts`, when the column is present
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub organism_names: BTreeMap<String, String>,
+    /// Fraction of null values per top-level column
+    pub null_rates: BTreeMap<String, f64>,
+}
+
+impl DatasetStats {
+    /// Collects statistics for a Parquet file or a directory of `.parquet` files.
+    pub fn collect(path: &Path) -> Result<Self> {
+        Self::collect_columns(path, None)
+    }
+
+    /// Like `collect`, reading only the named top-level columns when `columns` is given
+    /// (null rates then cover those columns only).
+    pub fn collect_columns(path: &Path, columns: Option<&[&str]>) -> Result<Self> {
+        let files = parquet_files(path)?;
+        if files.is_empty() {
+            return Err(anyhow!("No Parquet files found at {}", path.display()));
+        }
+
+        let mut stats = DatasetStats::default();
+        let mut parents: HashSet<String> = HashSet::new();
+        let mut null_counts: BTreeMap<String, u64> = BTreeMap::new();
+
+        for file in files {
+            let size_bytes = fs::metadata(&file)?.len();
+            let builder = ParquetRecordBatchReaderBuilder::try_new(
+                File::open(&file).with_context(|| format!("Failed to open {}", file.display()))?,
+            )?;
+            let row_groups = builder.metadata().num_row_groups();
+            let builder = match columns {
+                Some(columns) => {
+                    let roots = builder
+                        .schema()
+                        .fields()
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, field)| columns.contains(&field.name().as_str()))
+                        .map(|(i, _)| i);
+                    let mask = ProjectionMask::roots(builder.parquet_schema(), roots);
+                    builder.with_projection(mask)
+                }
+                None => builder,
+            };
+            let mut rows = 0u64;
+
+            for batch in builder.build()? {
+                let batch = batch?;
+                rows += batch.num_rows() as u64;
+                stats.accumulate(&batch, &mut parents, &mut null_counts)?;
+            }
+
+            stats.total_bytes += size_bytes;
+            stats.rows += rows;
+            stats.files.push(FileStats {
+                path: file,
+                size_bytes,
+                rows,
+                row_groups,
+            });
+        }
+
+        stats.distinct_parents = parents.len() as u64;
+        stats.isoform_ratio = if stats.distinct_parents > 0 {
+            stats.rows as f64 / stats.distinct_parents as f64
+        } else {
+            0.0
+        };
+        stats.null_rates = null_counts
+            .into_iter()
+            .map(|(column, nulls)| {
+                let rate = if stats.rows > 0 {
+                    nulls as f64 / stats.rows as f64
+                } else {
+                    0.0
+                };
+                (column, rate)
+            })
+            .collect();
+
+        Ok(stats)
+    }
+
+    fn accumulate(
+        &mut self,
+        batch: &RecordBatch,
+        parents: &mut HashSet<String>,
+        null_counts: &mut BTreeMap<String, u64>,
+    ) -> Result<()> {
+        let schema = batch.schema();
+        for (field, column) in schema.fields().iter().zip(batch.columns()) {
+            *null_counts.entry(field.name().clone()).or_default() += column.null_count() as u64;
+        }
+
+        if let Some(parent_ids) = batch.column_by_name("parent_id") {
+            let parent_ids = utf8(parent_ids.as_ref())?;
+            for i in 0..parent_ids.len() {
+                if parent_ids.is_valid(i) && !parents.contains(parent_ids.value(i)) {
+                    parents.insert(parent_ids.value(i).to_string());
+                }
+            }
+        }
+
+        if let Some(organism_ids) = batch.column_by_name("organism_id") {
+            let organism_ids = organism_ids
+                .as_any()
+                .downcast_ref::<Int32Array>()
+                .ok_or_else(|| anyhow!("organism_id is not an Int32 column"))?;
+            let names = batch.column_by_name("organism_name").map(|c| utf8(c.as_ref())).transpose()?;
+            for i in 0..organism_ids.len() {
+                let taxon = if organism_ids.is_valid(i) {
+                    organism_ids.value(i).to_string()
+                } else {
+                    "unknown".to_string()
+                };
+                if let Some(names) = names.as_ref().filter(|names| names.is_valid(i)) {
+                    if !self.organism_names.contains_key(&taxon) {
+                        self.organism_names.insert(taxon.clone(), names.value(i).to_string());
+                    }
+                }
+                *self.organism_counts.entry(taxon).or_default() += 1;
+            }
+        }
+
+        if let Some(features) = list_values(batch, "features")? {
+            if let Some(types) = features.column_by_name("feature_type") {
+                let types = utf8(types.as_ref())?;
+                for i in 0..types.len() {
+                    if types.is_valid(i) {
+                        *self
+                            .feature_type_counts
+                            .entry(types.value(i).to_string())
+                            .or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        if let Some(sites) = list_values(batch, "ptm_sites")? {
+            let mods = sites
+                .column_by_name("modifications")
+                .and_then(|c| c.as_any().downcast_ref::<ListArray>());
+            if let Some(mods) = mods {
+                // Sites kept by transform.debug_mapping although they did not map
+                let unmapped = sites.column_by_name("mapping_failure_code");
+                let offsets = mods.value_offsets();
+                let mods_struct = mods
+                    .values()
+                    .as_any()
+                    .downcast_ref::<StructArray>()
+                    .ok_or_else(|| anyhow!("modifications is not a List<Struct>"))?;
+                if let Some(mod_types) = mods_struct
+                    .column_by_name("mod_type")
+                    .and_then(|c| c.as_any().downcast_ref::<Int32Array>())
+                {
+                    let mapped = (0..sites.len()).filter(|&site| !unmapped.is_some_and(|f| f.is_valid(site)));
+                    for i in mapped.flat_map(|site| offsets[site] as usize..offsets[site + 1] as usize) {
+                        if mod_types.is_valid(i) {
+                            *self
+                                .ptm_type_histogram
+                                .entry(mod_type_label(mod_types.value(i)).to_string())
+                                .or_default() += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Name of a `ptm_sites.modifications.mod_type` code: its subclass, else its class.
+pub(crate) fn mod_type_label(mod_type: i32) -> &'static str {
+    ModType::from_code(mod_type).label()
+}
+
+/// Returns the flattened struct values of a `List<Struct>` column, if present.
+fn list_values(batch: &RecordBatch, name: &str) -> Result<Option<StructArray>> {
+    let Some(column) = batch.column_by_name(name) else {
+        return Ok(None);
+    };
+    let list = column
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .ok_or_else(|| anyhow!("{} is not a ListArray", name))?;
+    let values = list
+        .values()
+        .as_any()
+        .downcast_ref::<StructArray>()
+        .ok_or_else(|| anyhow!("{} is not a List<Struct>", name))?;
+    Ok(Some(values.clone()))
+}
+
+/// Decodes a (possibly dictionary-encoded) string column into a StringArray.
+pub(crate) fn utf8(array: &dyn Array) -> Result<StringArray> {
+    let decoded = cast(array, &DataType::Utf8)?;
+    Ok(decoded
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .ok_or_else(|| anyhow!("expected a string column"))?
+        .clone())
+}
+
+/// `path` itself, or the `.parquet` files of a directory (side outputs excluded).
+///
+/// A `.parquet` entry that is itself a directory is a rolled dataset (swarm mode with
+/// `storage.max_output_file_gb`) and contributes its `part-*.parquet` files, in order.
+pub(crate) fn parquet_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "parquet"))
+        // `_`-prefixed files are side outputs such as the row-group index
+        .filter(|p| !p.file_name().is_some_and(|n| n.to_string_lossy().starts_with('_')))
+        .collect();
+    entries.sort();
+    let mut files = Vec::with_capacity(entries.len());
+    for entry in entries {
+        if entry.is_dir() {
+            files.extend(output_files(&entry)?);
+        } else {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::metrics::Metrics;
+    use crate::pipeline::batcher::Batcher;
+    use crate::pipeline::mapper::CoordinateMapper;
+    use crate::pipeline::scratch::{FeatureScratch, ParsedEntry};
+    use crate::pipeline::transformer::{IsoformStatus, TransformedRow};
+    use crate::writer::parquet::write_batches;
+    use crossbeam_channel::unbounded;
+    use std::sync::Arc;
+
+    fn row(id: &str, parent_id: &str) -> TransformedRow {
+        let mut entry = ParsedEntry {
+            accession: parent_id.to_string(),
+            parent_id: parent_id.to_string(),
+            sequence: "MSAK".to_string(),
+            ..Default::default()
+        };
+        entry.features.generic.push(FeatureScratch {
+            feature_type: "modified residue".to_string(),
+            description: Some("Phosphoserine".to_string()),
+            start: Some(2),
+            end: Some(2),
+            ..Default::default()
+        });
+        let mapper = CoordinateMapper::from_entry(&entry);
+        TransformedRow {
+            entry: Arc::new(entry),
+            row_id: id.to_string(),
+            parent_id: parent_id.to_string(),
+            sequence: "MSAK".to_string(),
+            isoform_status: IsoformStatus::Canonical,
+            mapper,
+            extra: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_collects_dataset_stats() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_stats");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("out.parquet");
+
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        let mut batcher = Batcher::with_batch_size(tx, metrics.clone(), 100);
+        for (id, parent) in [("A-1", "A"), ("A-2", "A"), ("B", "B")] {
+            batcher.add_row(row(id, parent)).unwrap();
+        }
+        batcher.finish().unwrap();
+        write_batches(rx, &output, &metrics, &Settings::default()).unwrap();
+
+        let stats = DatasetStats::collect(&dir).unwrap();
+        assert_eq!(stats.files.len(), 1);
+        assert_eq!(stats.rows, 3);
+        assert_eq!(stats.distinct_parents, 2);
+        assert!((stats.isoform_ratio - 1.5).abs() < 1e-9);
+        assert_eq!(stats.feature_type_counts.get("modified residue"), Some(&3));
+        assert_eq!(stats.ptm_type_histogram.get("phosphorylation"), Some(&3));
+        assert_eq!(stats.organism_counts.get("unknown"), Some(&3));
+        assert_eq!(stats.null_rates.get("organism_id"), Some(&1.0));
+        assert_eq!(stats.null_rates.get("id"), Some(&0.0));
+
+        let projected = DatasetStats::collect_columns(&dir, Some(&["parent_id", "organism_id"])).unwrap();
+        assert_eq!(projected.distinct_parents, 2);
+        assert!(projected.feature_type_counts.is_empty());
+        assert_eq!(projected.null_rates.len(), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn expands_rolled_swarm_outputs_into_their_parts() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_stats_swarm");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // One swarm input written whole, one rolled into a part per batch
+        let write = |name: &str, ids: &[&str], settings: &Settings| {
+            let metrics = Metrics::new();
+            let (tx, rx) = unbounded();
+            let mut batcher = Batcher::with_batch_size(tx, metrics.clone(), 1);
+            for id in ids {
+                batcher.add_row(row(id, id)).unwrap();
+            }
+            batcher.finish().unwrap();
+            write_batches(rx, &dir.join(name), &metrics, settings).unwrap();
+        };
+        write("a.parquet", &["A"], &Settings::default());
+        let mut rolling = Settings::default();
+        rolling.storage.max_output_file_gb = Some(1e-9);
+        write("b.parquet", &["B", "C", "D"], &rolling);
+        assert!(dir.join("b.parquet").is_dir());
+
+        let files = parquet_files(&dir).unwrap();
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[0], dir.join("a.parquet"));
+        assert!(files[1..].iter().all(|f| f.starts_with(dir.join("b.parquet"))));
+        let stats = DatasetStats::collect(&dir).unwrap();
+        assert_eq!(stats.rows, 4);
+        assert_eq!(stats.distinct_parents, 4);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
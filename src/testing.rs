@@ -0,0 +1,438 @@
+//! Test support for code built on the pipeline (`--features testing`).
+//!
+//! `EntryXml` / `FeatureXml` assemble UniProt XML entries, `sidecar` and `sidecar_fasta`
+//! build the isoform sequences the transformer looks up, and `parse_xml` runs the parse
+//! into a `MemorySink`, so a test reads as the entry it describes. `canonical_row` skips
+//! the parse for tests that start from a `TransformedRow`:
+//!
+//! ```ignore
+//! let xml = uniprot_xml(&[EntryXml::new("P1", "MSKTA")
+//!     .feature(FeatureXml::at("modified residue", 2).description("Phosphoserine"))]);
+//! let output = parse_xml(&xml, None, &ParseHooks::default())?;
+//! assert_eq!(output.ids(), ["P1"]);
+//! ```
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::Arc;
+
+use arrow::array::{Array, StringArray};
+use arrow::compute::concat_batches;
+use arrow::record_batch::RecordBatch;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use quick_xml::escape::escape;
+use quick_xml::Reader;
+
+use crate::error::Result;
+use crate::fasta::SidecarFasta;
+use crate::metrics::Metrics;
+use crate::pipeline::mapper::CoordinateMapper;
+use crate::pipeline::parser::{parse_entries_with_hooks, ParseHooks};
+use crate::pipeline::scratch::ParsedEntry;
+use crate::pipeline::transformer::{IsoformStatus, TransformedRow};
+use crate::schema::schema_ref;
+
+/// Collects the batches a pipeline sends.
+pub struct MemorySink {
+    sender: Sender<RecordBatch>,
+    receiver: Receiver<RecordBatch>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        let (sender, receiver) = unbounded();
+        Self { sender, receiver }
+    }
+
+    /// Sender to hand to `parse_entries*` or a `Batcher`.
+    pub fn sender(&self) -> Sender<RecordBatch> {
+        self.sender.clone()
+    }
+
+    /// Every batch sent so far (the senders handed out must be dropped first).
+    pub fn into_batches(self) -> Vec<RecordBatch> {
+        drop(self.sender);
+        self.receiver.iter().collect()
+    }
+}
+
+impl Default for MemorySink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Batches and counters of one `parse_xml` run.
+pub struct ParsedOutput {
+    pub batches: Vec<RecordBatch>,
+    pub metrics: Metrics,
+}
+
+impl ParsedOutput {
+    pub fn num_rows(&self) -> usize {
+        self.batches.iter().map(RecordBatch::num_rows).sum()
+    }
+
+    /// All rows as one batch.
+    pub fn batch(&self) -> RecordBatch {
+        let schema = self.batches.first().map_or_else(schema_ref, RecordBatch::schema);
+        concat_batches(&schema, &self.batches).expect("batches of one run share a schema")
+    }
+
+    /// `id` of every row, in output order.
+    pub fn ids(&self) -> Vec<String> {
+        self.batches
+            .iter()
+            .flat_map(|batch| {
+                let ids = batch.column_by_name("id").unwrap();
+                let ids = ids.as_any().downcast_ref::<StringArray>().unwrap();
+                (0..ids.len()).map(|i| ids.value(i).to_string()).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Parses `xml` through the full pipeline (transform and build included) into memory.
+pub fn parse_xml(xml: &str, sidecar: Option<SidecarFasta>, hooks: &ParseHooks) -> Result<ParsedOutput> {
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes().to_vec()));
+    reader.config_mut().trim_text(true);
+    let metrics = Metrics::new();
+    let sink = MemorySink::new();
+    parse_entries_with_hooks(reader, sink.sender(), &metrics, 1024, sidecar, hooks)?;
+    Ok(ParsedOutput {
+        batches: sink.into_batches(),
+        metrics,
+    })
+}
+
+/// The row the transformer emits for `entry` without isoforms: its accession as `id` and
+/// `parent_id`, its own sequence and an identity mapping. Override fields with struct
+/// update syntax (`TransformedRow { row_id, ..canonical_row(entry) }`).
+pub fn canonical_row(entry: ParsedEntry) -> TransformedRow {
+    let mapper = CoordinateMapper::from_entry(&entry);
+    TransformedRow {
+        row_id: entry.accession.clone(),
+        parent_id: entry.accession.clone(),
+        sequence: entry.sequence.clone(),
+        isoform_status: IsoformStatus::Canonical,
+        mapper,
+        entry: Arc::new(entry),
+        extra: Vec::new(),
+    }
+}
+
+/// Isoform accession -> sequence map, as loaded from a sidecar FASTA.
+pub fn sidecar<'a>(records: impl IntoIterator<Item = (&'a str, &'a str)>) -> SidecarFasta {
+    Arc::new(
+        records
+            .into_iter()
+            .map(|(id, sequence)| (id.to_string(), sequence.to_string()))
+            .collect::<HashMap<_, _>>(),
+    )
+}
+
+/// FASTA text of `records` in UniProt's `>sp|ACC|...` header format, for code that reads
+/// the sidecar from disk.
+pub fn sidecar_fasta<'a>(records: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    records
+        .into_iter()
+        .map(|(id, sequence)| format!(">sp|{}|TEST\n{}\n", id, sequence))
+        .collect()
+}
+
+/// A `<uniprot>` document of `entries`.
+pub fn uniprot_xml(entries: &[EntryXml]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<uniprot>\n");
+    for entry in entries {
+        xml.push_str(&entry.to_xml());
+    }
+    xml.push_str("</uniprot>\n");
+    xml
+}
+
+/// One `<entry>`.
+#[derive(Debug, Clone, Default)]
+pub struct EntryXml {
+    accession: String,
+    sequence: String,
+    name: Option<String>,
+    gene: Option<String>,
+    protein: Option<String>,
+    organism: Option<(i32, String)>,
+    isoforms: Vec<(String, Option<String>)>,
+    comments: Vec<(String, String)>,
+    features: Vec<FeatureXml>,
+    evidence: Vec<(String, String)>,
+}
+
+impl EntryXml {
+    pub fn new(accession: &str, sequence: &str) -> Self {
+        Self {
+            accession: accession.to_string(),
+            sequence: sequence.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Entry name (`<name>`, e.g. `P53_HUMAN`).
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Primary gene name.
+    pub fn gene(mut self, gene: &str) -> Self {
+        self.gene = Some(gene.to_string());
+        self
+    }
+
+    /// Recommended full protein name.
+    pub fn protein(mut self, protein: &str) -> Self {
+        self.protein = Some(protein.to_string());
+        self
+    }
+
+    pub fn organism(mut self, taxon_id: i32, scientific_name: &str) -> Self {
+        self.organism = Some((taxon_id, scientific_name.to_string()));
+        self
+    }
+
+    /// Displayed isoform `id` (the canonical sequence).
+    pub fn displayed_isoform(mut self, id: &str) -> Self {
+        self.isoforms.push((id.to_string(), None));
+        self
+    }
+
+    /// Isoform `id` described by the splice variants `vsp_ids` (space-separated).
+    pub fn described_isoform(mut self, id: &str, vsp_ids: &str) -> Self {
+        self.isoforms.push((id.to_string(), Some(vsp_ids.to_string())));
+        self
+    }
+
+    /// Free-text comment of `comment_type` (`function`, `subunit`, ...).
+    pub fn comment(mut self, comment_type: &str, text: &str) -> Self {
+        self.comments.push((comment_type.to_string(), text.to_string()));
+        self
+    }
+
+    pub fn feature(mut self, feature: FeatureXml) -> Self {
+        self.features.push(feature);
+        self
+    }
+
+    /// `<evidence key type>` that features refer to by `key`.
+    pub fn evidence(mut self, key: &str, eco: &str) -> Self {
+        self.evidence.push((key.to_string(), eco.to_string()));
+        self
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<entry dataset=\"Swiss-Prot\">\n");
+        xml.push_str(&format!("  <accession>{}</accession>\n", escape(&self.accession)));
+        if let Some(name) = &self.name {
+            xml.push_str(&format!("  <name>{}</name>\n", escape(name)));
+        }
+        if let Some(protein) = &self.protein {
+            xml.push_str(&format!(
+                "  <protein><recommendedName><fullName>{}</fullName></recommendedName></protein>\n",
+                escape(protein)
+            ));
+        }
+        if let Some(gene) = &self.gene {
+            xml.push_str(&format!("  <gene><name type=\"primary\">{}</name></gene>\n", escape(gene)));
+        }
+        if let Some((taxon_id, scientific_name)) = &self.organism {
+            xml.push_str(&format!(
+                "  <organism><name type=\"scientific\">{}</name><dbReference type=\"NCBI Taxonomy\" id=\"{}\"/></organism>\n",
+                escape(scientific_name),
+                taxon_id
+            ));
+        }
+        for (comment_type, text) in &self.comments {
+            xml.push_str(&format!(
+                "  <comment type=\"{}\"><text>{}</text></comment>\n",
+                escape(comment_type),
+                escape(text)
+            ));
+        }
+        if !self.isoforms.is_empty() {
+            xml.push_str("  <comment type=\"alternative products\">\n");
+            for (id, vsp_ids) in &self.isoforms {
+                let sequence = match vsp_ids {
+                    Some(vsp_ids) => format!("<sequence type=\"described\" ref=\"{}\"/>", escape(vsp_ids)),
+                    None => "<sequence type=\"displayed\"/>".to_string(),
+                };
+                xml.push_str(&format!("    <isoform><id>{}</id>{}</isoform>\n", escape(id), sequence));
+            }
+            xml.push_str("  </comment>\n");
+        }
+        for feature in &self.features {
+            xml.push_str(&feature.to_xml());
+        }
+        for (key, eco) in &self.evidence {
+            xml.push_str(&format!("  <evidence key=\"{}\" type=\"{}\"/>\n", escape(key), escape(eco)));
+        }
+        xml.push_str(&format!(
+            "  <sequence length=\"{}\">{}</sequence>\n</entry>\n",
+            self.sequence.len(),
+            escape(&self.sequence)
+        ));
+        xml
+    }
+}
+
+/// One `<feature>`.
+#[derive(Debug, Clone)]
+pub struct FeatureXml {
+    feature_type: String,
+    begin: i32,
+    end: i32,
+    description: Option<String>,
+    id: Option<String>,
+    evidence: Option<String>,
+    sequence: Option<String>,
+    original: Option<String>,
+    variation: Option<String>,
+}
+
+impl FeatureXml {
+    /// Single-position feature (`<position>`).
+    pub fn at(feature_type: &str, position: i32) -> Self {
+        Self::span(feature_type, position, position)
+    }
+
+    /// Range feature (`<begin>`/`<end>`).
+    pub fn span(feature_type: &str, begin: i32, end: i32) -> Self {
+        Self {
+            feature_type: feature_type.to_string(),
+            begin,
+            end,
+            description: None,
+            id: None,
+            evidence: None,
+            sequence: None,
+            original: None,
+            variation: None,
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Feature id (`VSP_…`, `VAR_…`, `PRO_…`).
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Space-separated evidence keys.
+    pub fn evidence(mut self, keys: &str) -> Self {
+        self.evidence = Some(keys.to_string());
+        self
+    }
+
+    /// Locates the feature on isoform `isoform_id` instead of the canonical sequence.
+    pub fn on_isoform(mut self, isoform_id: &str) -> Self {
+        self.sequence = Some(isoform_id.to_string());
+        self
+    }
+
+    /// `<original>`/`<variation>` of a splice variant, variant or mutagenesis site
+    /// (`variation` = `Missing` for deletions).
+    pub fn change(mut self, original: &str, variation: &str) -> Self {
+        self.original = Some(original.to_string());
+        self.variation = Some(variation.to_string());
+        self
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut attributes = format!("type=\"{}\"", escape(&self.feature_type));
+        for (name, value) in [("description", &self.description), ("id", &self.id), ("evidence", &self.evidence)] {
+            if let Some(value) = value {
+                attributes.push_str(&format!(" {}=\"{}\"", name, escape(value)));
+            }
+        }
+        let mut xml = format!("  <feature {}>", attributes);
+        if let Some(original) = &self.original {
+            xml.push_str(&format!("<original>{}</original>", escape(original)));
+        }
+        if let Some(variation) = &self.variation {
+            xml.push_str(&format!("<variation>{}</variation>", escape(variation)));
+        }
+        let location_attr = self
+            .sequence
+            .as_ref()
+            .map(|s| format!(" sequence=\"{}\"", escape(s)))
+            .unwrap_or_default();
+        let location = if self.begin == self.end {
+            format!("<position position=\"{}\"/>", self.begin)
+        } else {
+            format!("<begin position=\"{}\"/><end position=\"{}\"/>", self.begin, self.end)
+        };
+        xml.push_str(&format!("<location{}>{}</location></feature>\n", location_attr, location));
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, ListArray, StructArray};
+
+    #[test]
+    fn built_entries_run_through_the_pipeline() {
+        let xml = uniprot_xml(&[
+            EntryXml::new("Q9SEQ", "MSTKY")
+                .name("SEQ_HUMAN")
+                .gene("SEQ")
+                .organism(9606, "Homo sapiens")
+                .displayed_isoform("Q9SEQ-1")
+                .described_isoform("Q9SEQ-2", "VSP_000001")
+                .feature(FeatureXml::at("splice variant", 1).id("VSP_000001").change("M", "Missing"))
+                .feature(FeatureXml::at("modified residue", 3).description("Phosphothreonine").evidence("1"))
+                .evidence("1", "ECO:0000269"),
+            EntryXml::new("P1", "MAK").protein("Kinase & phosphatase"),
+        ]);
+        let output = parse_xml(
+            &xml,
+            Some(sidecar([("Q9SEQ-1", "MSTKY"), ("Q9SEQ-2", "STKY")])),
+            &ParseHooks::default(),
+        )
+        .unwrap();
+
+        // The displayed isoform's id names the canonical row
+        assert_eq!(output.ids(), ["Q9SEQ-1", "Q9SEQ-2", "P1"]);
+        assert_eq!(output.metrics.entries(), 3);
+
+        let batch = output.batch();
+        let proteins = batch.column_by_name("protein_name").unwrap();
+        let proteins = proteins.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(proteins.value(2), "Kinase & phosphatase");
+        let sites = batch.column_by_name("ptm_sites").unwrap();
+        let sites = sites.as_any().downcast_ref::<ListArray>().unwrap();
+        let site_index = |row: usize| {
+            let row = sites.value(row);
+            let row = row.as_any().downcast_ref::<StructArray>().unwrap();
+            let index = row.column_by_name("site_index").unwrap();
+            index.as_any().downcast_ref::<Int32Array>().unwrap().values().to_vec()
+        };
+        // Mapped through the deleted initiator methionine on the isoform row
+        assert_eq!(site_index(0), [3]);
+        assert_eq!(site_index(1), [2]);
+    }
+
+    #[test]
+    fn writes_sidecar_fasta_in_uniprot_header_format() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_testing_fasta");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("varsplic.fasta");
+        std::fs::write(&path, sidecar_fasta([("P1-2", "MKV"), ("P1-3", "MA")])).unwrap();
+
+        let map = crate::fasta::load_fasta_map(&path).unwrap();
+        assert_eq!(map["P1-2"], "MKV");
+        assert_eq!(map["P1-3"], "MA");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
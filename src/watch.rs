@@ -0,0 +1,231 @@
+//! Landing-directory watch mode (`--watch <dir>`).
+//!
+//! The landing directory itself is the queue: every `*.xml` / `*.xml.gz` still in it is
+//! pending, and finished files are moved to `processed/` (or `failed/`). Filesystem
+//! events from `notify` only wake the loop up; a periodic rescan covers missed events.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::paths::list_xml_inputs;
+use crate::shutdown::CancelToken;
+
+/// Subdirectory for successfully processed inputs.
+pub const PROCESSED_DIR: &str = "processed";
+/// Subdirectory for inputs that failed to process (kept out of the queue).
+pub const FAILED_DIR: &str = "failed";
+
+/// How long a dropped file's size must stay unchanged before it is picked up.
+pub const SETTLE_INTERVAL: Duration = Duration::from_secs(2);
+/// Fallback rescan period in case a filesystem event is missed.
+pub const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+/// Longest stretch the loop blocks without checking for cancellation.
+const CANCEL_POLL: Duration = Duration::from_millis(200);
+
+/// One line of the run's `manifest.jsonl`.
+#[derive(Serialize, Clone, Debug)]
+pub struct ManifestRecord {
+    pub processed_at: DateTime<Utc>,
+    pub input: PathBuf,
+    pub archived_to: PathBuf,
+    pub output: Option<PathBuf>,
+    pub status: &'static str,
+    pub error: Option<String>,
+}
+
+/// A watched landing directory.
+pub struct LandingDir {
+    dir: PathBuf,
+    events: Receiver<()>,
+    // Dropping the watcher stops event delivery
+    _watcher: RecommendedWatcher,
+}
+
+impl LandingDir {
+    /// Starts watching `dir` (non-recursively) and creates its archive subdirectories.
+    pub fn open(dir: &Path) -> Result<Self> {
+        for sub in [PROCESSED_DIR, FAILED_DIR] {
+            let path = dir.join(sub);
+            fs::create_dir_all(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+        }
+
+        let (tx, events) = unbounded();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if res.is_ok() {
+                let _ = tx.send(());
+            }
+        })?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", dir.display()))?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// XML inputs currently waiting in the landing directory whose size has stopped
+    /// changing over `settle` (so half-copied drops are left for the next round).
+    /// Empty when `cancel` fires while settling.
+    pub fn pending(&self, settle: Duration, cancel: &CancelToken) -> Result<Vec<PathBuf>> {
+        let before = sizes(&list_xml_inputs(&self.dir)?);
+        if before.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut left = settle;
+        while !left.is_zero() {
+            if cancel.is_cancelled() {
+                return Ok(Vec::new());
+            }
+            let slice = left.min(CANCEL_POLL);
+            thread::sleep(slice);
+            left -= slice;
+        }
+        let after = sizes(&list_xml_inputs(&self.dir)?);
+        Ok(before
+            .into_iter()
+            .filter(|entry| after.contains(entry))
+            .map(|(path, _)| path)
+            .collect())
+    }
+
+    /// Blocks until something changes in the directory, `rescan` elapses or `cancel`
+    /// fires (checked every [`CANCEL_POLL`]).
+    /// Bursts of events (e.g. one copy = create + many modifies) are coalesced.
+    pub fn wait(&self, rescan: Duration, cancel: &CancelToken) {
+        let mut left = rescan;
+        while !left.is_zero() && !cancel.is_cancelled() {
+            let slice = left.min(CANCEL_POLL);
+            match self.events.recv_timeout(slice) {
+                Ok(()) => {
+                    while self.events.try_recv().is_ok() {}
+                    return;
+                }
+                Err(RecvTimeoutError::Timeout) => left -= slice,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    /// Moves `input` into `processed/` or `failed/`, returning its new path.
+    pub fn archive(&self, input: &Path, succeeded: bool) -> Result<PathBuf> {
+        let sub = if succeeded { PROCESSED_DIR } else { FAILED_DIR };
+        let file_name = input
+            .file_name()
+            .with_context(|| format!("Input path has no filename: {}", input.display()))?;
+        let mut target = self.dir.join(sub).join(file_name);
+        if target.exists() {
+            // Same file name dropped again: keep both, newest gets a timestamp prefix
+            let stamped = format!(
+                "{}_{}",
+                Utc::now().format("%Y%m%d_%H%M%S"),
+                file_name.to_string_lossy()
+            );
+            target = self.dir.join(sub).join(stamped);
+        }
+        fs::rename(input, &target).with_context(|| {
+            format!("Failed to move {} to {}", input.display(), target.display())
+        })?;
+        Ok(target)
+    }
+}
+
+fn sizes(files: &[PathBuf]) -> Vec<(PathBuf, u64)> {
+    files
+        .iter()
+        .filter_map(|path| Some((path.clone(), fs::metadata(path).ok()?.len())))
+        .collect()
+}
+
+/// Appends one JSON line to the manifest at `path`.
+pub fn append_manifest(path: &Path, record: &ManifestRecord) -> Result<()> {
+    use std::io::Write;
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open manifest {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_and_archive() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_watch");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let landing = LandingDir::open(&dir).unwrap();
+
+        fs::write(dir.join("a.xml"), b"<uniprot/>").unwrap();
+        fs::write(dir.join("notes.txt"), b"").unwrap();
+        let pending = landing.pending(Duration::from_millis(10), &CancelToken::new()).unwrap();
+        assert_eq!(pending, vec![dir.join("a.xml")]);
+
+        let archived = landing.archive(&pending[0], true).unwrap();
+        assert_eq!(archived, dir.join(PROCESSED_DIR).join("a.xml"));
+        assert!(landing.pending(Duration::from_millis(10), &CancelToken::new()).unwrap().is_empty());
+
+        // A second drop with the same name does not clobber the archived copy
+        fs::write(dir.join("a.xml"), b"<uniprot/>").unwrap();
+        let again = landing.archive(&dir.join("a.xml"), true).unwrap();
+        assert_ne!(again, archived);
+        assert!(archived.exists() && again.exists());
+
+        let manifest = dir.join("manifest.jsonl");
+        let record = ManifestRecord {
+            processed_at: Utc::now(),
+            input: dir.join("a.xml"),
+            archived_to: again,
+            output: None,
+            status: "ok",
+            error: None,
+        };
+        append_manifest(&manifest, &record).unwrap();
+        append_manifest(&manifest, &record).unwrap();
+        assert_eq!(fs::read_to_string(&manifest).unwrap().lines().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wait_returns_promptly_once_cancelled() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_watch_cancel");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let landing = LandingDir::open(&dir).unwrap();
+        let cancel = CancelToken::new();
+        let canceller = cancel.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            canceller.cancel();
+        });
+
+        let started = std::time::Instant::now();
+        landing.wait(RESCAN_INTERVAL, &cancel);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        // Settling is skipped too
+        fs::write(dir.join("a.xml"), b"<uniprot/>").unwrap();
+        assert!(landing.pending(SETTLE_INTERVAL, &cancel).unwrap().is_empty());
+        handle.join().unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
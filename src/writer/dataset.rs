@@ -0,0 +1,354 @@
+//! Partitioned Parquet dataset directories (`storage.max_output_file_gb`,
+//! `storage.append_to_dataset`).
+//!
+//! A dataset is a directory of `part-NNNNN.parquet` files plus `_manifest.json`, which
+//! lists every part with its row count, size and the time it was added. A fresh write
+//! replaces the parts of an earlier run; an append keeps them and numbers new parts
+//! after the highest existing one. Parts are created with create-new semantics, so two
+//! runs appending to the same directory never overwrite each other's files. Once the
+//! run's parts are closed, the manifest is updated under `_manifest.json.lock`: it is
+//! re-read, this run's parts are added and the result is renamed into place, so
+//! concurrent appends each keep the other's parts.
+//!
+//! `storage.accession_shards` output uses the same directory layout with a fixed set
+//! of `shard-NNNNN-of-NNNNN.parquet` files instead (see `shard_path`).
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use arrow::datatypes::Schema;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use serde::{Deserialize, Serialize};
+
+/// Dataset-level part listing.
+pub const MANIFEST_FILE: &str = "_manifest.json";
+/// Held (created exclusively) while a writer updates `_manifest.json`.
+const MANIFEST_LOCK_FILE: &str = "_manifest.json.lock";
+/// How long a writer waits for the manifest lock before giving up.
+const MANIFEST_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Contents of `_manifest.json`.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct DatasetManifest {
+    pub total_rows: u64,
+    pub files: Vec<DatasetFile>,
+}
+
+/// One part of the manifest.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DatasetFile {
+    pub file: String,
+    pub rows: u64,
+    pub bytes: u64,
+    /// Unix seconds at which the part was written
+    pub added_at: u64,
+}
+
+/// A dataset directory being written by this run.
+pub struct Dataset {
+    dir: PathBuf,
+    next_index: usize,
+    /// Manifest of the parts present when the dataset was opened
+    manifest: DatasetManifest,
+    /// Parts this run added
+    added: Vec<DatasetFile>,
+    appending: bool,
+}
+
+impl Dataset {
+    /// Starts a fresh dataset at `dir`, removing the parts and manifest of an earlier run.
+    pub fn create(dir: &Path) -> Result<Self> {
+        ensure_dir(dir)?;
+        for stale in output_files(dir)? {
+            fs::remove_file(&stale).with_context(|| format!("Failed to remove {}", stale.display()))?;
+        }
+        remove_if_exists(&dir.join(MANIFEST_FILE))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            next_index: 0,
+            manifest: DatasetManifest::default(),
+            added: Vec::new(),
+            appending: false,
+        })
+    }
+
+    /// Opens the dataset at `dir` (created if missing) to add parts after the existing
+    /// ones. Existing parts must have `schema`.
+    pub fn append(dir: &Path, schema: &Schema) -> Result<Self> {
+        ensure_dir(dir)?;
+        let parts = output_files(dir)?;
+        if let Some(first) = parts.first() {
+            let existing = part_schema(first)?;
+            if existing.fields() != schema.fields() {
+                return Err(anyhow!(
+                    "Output schema differs from the parts in {}; write to a new dataset",
+                    dir.display()
+                ));
+            }
+        }
+        let manifest = match read_manifest(dir)? {
+            Some(manifest) => manifest,
+            // Dataset written without a manifest: list what is there
+            None => rebuild_manifest(&parts)?,
+        };
+        let next_index = parts.iter().filter_map(|p| part_index(p)).max().map_or(0, |i| i + 1);
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            next_index,
+            manifest,
+            added: Vec::new(),
+            appending: true,
+        })
+    }
+
+    /// Creates the next free part file. A name taken by a concurrent writer is skipped.
+    pub fn create_part(&mut self) -> Result<(PathBuf, File)> {
+        loop {
+            let path = part_path(&self.dir, self.next_index);
+            self.next_index += 1;
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok((path, file)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e).with_context(|| format!("Failed to create {}", path.display())),
+            }
+        }
+    }
+
+    /// Adds a closed part of `rows` rows to the manifest.
+    pub fn add_part(&mut self, path: &Path, rows: u64) -> Result<()> {
+        let bytes = fs::metadata(path)
+            .with_context(|| format!("Failed to stat {}", path.display()))?
+            .len();
+        self.added.push(DatasetFile {
+            file: file_name(path),
+            rows,
+            bytes,
+            added_at: now_secs(),
+        });
+        Ok(())
+    }
+
+    /// Writes `_manifest.json`, replacing the previous one in a single rename.
+    ///
+    /// An append re-reads the manifest under the lock first, so parts committed by a
+    /// concurrent writer since `append` are kept.
+    pub fn commit(self) -> Result<DatasetManifest> {
+        let _lock = ManifestLock::acquire(&self.dir)?;
+        let mut manifest = if self.appending {
+            // Still missing: nobody committed since `append` rebuilt the listing
+            read_manifest(&self.dir)?.unwrap_or(self.manifest)
+        } else {
+            self.manifest
+        };
+        for part in self.added {
+            manifest.total_rows += part.rows;
+            manifest.files.push(part);
+        }
+        write_manifest(&self.dir, &manifest)?;
+        Ok(manifest)
+    }
+}
+
+/// Re-reads the size of every part listed in `dir`'s manifest after the parts were
+/// rewritten in place (e.g. by `backfill`); no-op without a manifest.
+pub fn refresh_manifest_sizes(dir: &Path) -> Result<()> {
+    let _lock = ManifestLock::acquire(dir)?;
+    let Some(mut manifest) = read_manifest(dir)? else {
+        return Ok(());
+    };
+    for part in &mut manifest.files {
+        let path = dir.join(&part.file);
+        part.bytes = fs::metadata(&path)
+            .with_context(|| format!("Failed to stat {}", path.display()))?
+            .len();
+    }
+    write_manifest(dir, &manifest)
+}
+
+/// Exclusive hold on a dataset's manifest; released on drop.
+struct ManifestLock(PathBuf);
+
+impl ManifestLock {
+    fn acquire(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_LOCK_FILE);
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self(path)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    if started.elapsed() >= MANIFEST_LOCK_TIMEOUT {
+                        return Err(anyhow!(
+                            "Timed out waiting for {}; remove it if no other run is writing the dataset",
+                            path.display()
+                        ));
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(e) => return Err(e).with_context(|| format!("Failed to create {}", path.display())),
+            }
+        }
+    }
+}
+
+impl Drop for ManifestLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Replaces `_manifest.json` by renaming a temp file only this writer uses; call with
+/// the manifest lock held.
+fn write_manifest(dir: &Path, manifest: &DatasetManifest) -> Result<()> {
+    let path = dir.join(MANIFEST_FILE);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    let tmp = dir.join(format!("{}.{}-{}.tmp", MANIFEST_FILE, std::process::id(), nanos));
+    fs::write(&tmp, serde_json::to_string_pretty(manifest)?)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, &path).with_context(|| format!("Failed to move {} into place", tmp.display()))?;
+    Ok(())
+}
+
+/// File `index` of a dataset directory.
+pub fn part_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("part-{:05}.parquet", index))
+}
+
+/// Shard `index` of an `accession_shards` output directory, e.g. `shard-00003-of-00016.parquet`.
+pub fn shard_path(dir: &Path, index: usize, shards: usize) -> PathBuf {
+    dir.join(format!("shard-{:05}-of-{:05}.parquet", index, shards))
+}
+
+/// The Parquet files making up `output`: the `part-*.parquet` files (in order) if it
+/// is a dataset directory, otherwise `output` itself.
+pub fn output_files(output: &Path) -> Result<Vec<PathBuf>> {
+    if !output.is_dir() {
+        return Ok(vec![output.to_path_buf()]);
+    }
+    let mut parts: Vec<PathBuf> = fs::read_dir(output)
+        .with_context(|| format!("Failed to list {}", output.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| part_index(path).is_some())
+        .collect();
+    parts.sort_by_key(|path| part_index(path));
+    Ok(parts)
+}
+
+/// `_manifest.json` of the dataset at `dir`, if it has one.
+pub fn read_manifest(dir: &Path) -> Result<Option<DatasetManifest>> {
+    let path = dir.join(MANIFEST_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let manifest = serde_json::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+    Ok(Some(manifest))
+}
+
+fn part_index(path: &Path) -> Option<usize> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("part-")?
+        .strip_suffix(".parquet")?
+        .parse()
+        .ok()
+}
+
+fn ensure_dir(dir: &Path) -> Result<()> {
+    if dir.is_file() {
+        return Err(anyhow!("{} is a file; a dataset directory is written there", dir.display()));
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn part_schema(path: &Path) -> Result<Schema> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+    Ok(builder.schema().as_ref().clone())
+}
+
+/// Manifest entries read from the part footers, for a dataset without a manifest.
+fn rebuild_manifest(parts: &[PathBuf]) -> Result<DatasetManifest> {
+    let mut manifest = DatasetManifest::default();
+    for path in parts {
+        let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let metadata = file.metadata()?;
+        let rows = SerializedFileReader::new(file)?.metadata().file_metadata().num_rows() as u64;
+        let added_at = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        manifest.total_rows += rows;
+        manifest.files.push(DatasetFile {
+            file: file_name(path),
+            rows,
+            bytes: metadata.len(),
+            added_at,
+        });
+    }
+    Ok(manifest)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_appends_keep_each_others_parts() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_dataset_append");
+        let _ = fs::remove_dir_all(&dir);
+        let schema = Schema::empty();
+
+        // Both writers open the (empty) dataset before either commits
+        let mut first = Dataset::append(&dir, &schema).unwrap();
+        let mut second = Dataset::append(&dir, &schema).unwrap();
+        let (a, _) = first.create_part().unwrap();
+        let (b, _) = second.create_part().unwrap();
+        assert_ne!(a, b);
+        first.add_part(&a, 3).unwrap();
+        second.add_part(&b, 4).unwrap();
+        first.commit().unwrap();
+        second.commit().unwrap();
+
+        let manifest = read_manifest(&dir).unwrap().unwrap();
+        assert_eq!(manifest.total_rows, 7);
+        let files: Vec<&str> = manifest.files.iter().map(|f| f.file.as_str()).collect();
+        assert_eq!(files, ["part-00000.parquet", "part-00001.parquet"]);
+        // Lock and temp files are gone
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, [MANIFEST_FILE, "part-00000.parquet", "part-00001.parquet"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,422 @@
+//! Delta Lake output sink (`storage.output_format: delta`).
+//!
+//! `output_path` is a Delta table directory. Each run writes one Parquet data file
+//! into it and commits an `add` action as the next `_delta_log/<version>.json`, so
+//! successive releases append to the same table and older releases stay readable
+//! through time travel (`VERSION AS OF n`). The commit is written in full to a temp
+//! file and hard-linked into place, which fails if the version exists: a run that
+//! fails before committing leaves only an unreferenced data file behind, two writers
+//! racing for a version cannot both win it, and no reader sees a partial commit. A
+//! writer that loses the race re-checks the table schema before retrying on top of
+//! the winner's version. The UniProt release of the batch is recorded in the commit's
+//! `commitInfo` so versions can be mapped back to releases.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{Array, AsArray};
+use arrow::datatypes::{DataType, Field, Fields, Schema};
+use arrow::record_batch::RecordBatch;
+use crossbeam_channel::Receiver;
+use parquet::arrow::ArrowWriter;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::config::Settings;
+use crate::metrics::MetricsCollector;
+use crate::writer::parquet::writer_properties_builder;
+use crate::log_info;
+
+/// Transaction log directory inside the table.
+pub const DELTA_LOG_DIR: &str = "_delta_log";
+
+/// Commit attempts before giving up on a contended table.
+const MAX_COMMIT_ATTEMPTS: usize = 10;
+
+/// Consumes RecordBatches from the channel and appends them to the Delta table at `table`.
+pub fn write_batches<M: MetricsCollector>(
+    rx: Receiver<RecordBatch>,
+    table: &Path,
+    metrics: &M,
+    settings: &Settings,
+) -> Result<()> {
+    let log_dir = table.join(DELTA_LOG_DIR);
+    fs::create_dir_all(&log_dir)
+        .with_context(|| format!("Failed to create Delta table {}", table.display()))?;
+
+    // The table schema comes from the first batch so enricher columns are included
+    let first = rx.recv().ok();
+    let schema = first
+        .as_ref()
+        .map(|batch| batch.schema())
+        .unwrap_or_else(|| settings.schema.profile.schema());
+    let release = first.as_ref().and_then(batch_release);
+    let schema_string = delta_schema(&schema)?.to_string();
+    check_schema(table, latest_version(table)?, &schema_string)?;
+
+    let file_name = data_file_name(table);
+    let data_path = table.join(&file_name);
+    let file = File::create(&data_path)
+        .with_context(|| format!("Failed to create {}", data_path.display()))?;
+    let props = writer_properties_builder(settings)?.build();
+    let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(props))?;
+    let mut num_records = 0u64;
+    for batch in first.into_iter().chain(rx) {
+        let batch_bytes = batch.get_array_memory_size() as u64;
+        writer.write(&batch)?;
+        num_records += batch.num_rows() as u64;
+        metrics.add_bytes_written(batch_bytes);
+    }
+    writer.close()?;
+
+    let add = json!({
+        "add": {
+            "path": file_name,
+            "partitionValues": {},
+            "size": fs::metadata(&data_path)?.len(),
+            "modificationTime": now_millis(),
+            "dataChange": true,
+            "stats": json!({ "numRecords": num_records }).to_string(),
+        }
+    });
+    let version = commit(table, &schema_string, &add, release.as_deref())?;
+
+    log_info!(
+        "writer",
+        "Wrote Delta: {} (version {}, {} rows)",
+        table.display(),
+        version,
+        num_records
+    );
+    Ok(())
+}
+
+/// Latest committed version of the table, `None` when it has no commits yet.
+pub fn latest_version(table: &Path) -> Result<Option<u64>> {
+    let log_dir = table.join(DELTA_LOG_DIR);
+    let entries = match fs::read_dir(&log_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to list {}", log_dir.display())),
+    };
+    let mut latest = None;
+    for entry in entries {
+        let name = entry?.file_name();
+        let version = name
+            .to_str()
+            .and_then(|n| n.strip_suffix(".json"))
+            .and_then(|n| n.parse::<u64>().ok());
+        latest = latest.max(version);
+    }
+    Ok(latest)
+}
+
+/// Writes `add` as the next version, with protocol and metadata on version 0.
+/// Returns the committed version.
+fn commit(table: &Path, schema_string: &str, add: &Value, release: Option<&str>) -> Result<u64> {
+    for _ in 0..MAX_COMMIT_ATTEMPTS {
+        // Re-checked on every attempt: a writer that took the version may have changed the schema
+        let latest = latest_version(table)?;
+        check_schema(table, latest, schema_string)?;
+        let version = latest.map_or(0, |v| v + 1);
+        let mut actions = vec![json!({
+            "commitInfo": {
+                "timestamp": now_millis(),
+                "operation": "WRITE",
+                "operationParameters": { "mode": "Append" },
+                "engineInfo": concat!("uniprot_etl/", env!("CARGO_PKG_VERSION")),
+                "uniprotRelease": release,
+            }
+        })];
+        if version == 0 {
+            actions.push(json!({
+                "protocol": { "minReaderVersion": 1, "minWriterVersion": 2 }
+            }));
+            actions.push(json!({
+                "metaData": {
+                    "id": table_id(table),
+                    "format": { "provider": "parquet", "options": {} },
+                    "schemaString": schema_string,
+                    "partitionColumns": [],
+                    "configuration": {},
+                    "createdTime": now_millis(),
+                }
+            }));
+        }
+        actions.push(add.clone());
+
+        let path = commit_path(table, version);
+        let tmp = commit_tmp_path(table, version);
+        let linked = write_commit_file(&tmp, &actions).and_then(|()| {
+            // Put-if-absent: the link fails when another writer took this version
+            fs::hard_link(&tmp, &path).map_err(anyhow::Error::from)
+        });
+        let _ = fs::remove_file(&tmp);
+        match linked {
+            Ok(()) => return Ok(version),
+            // Another writer took this version; retry on top of it
+            Err(e) if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::AlreadyExists) => {
+                continue
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to commit {}", path.display())),
+        }
+    }
+    Err(anyhow!(
+        "Gave up committing to Delta table {} after {} attempts",
+        table.display(),
+        MAX_COMMIT_ATTEMPTS
+    ))
+}
+
+/// Fails unless the table at `version` (if it has any commits) has schema `schema_string`.
+fn check_schema(table: &Path, version: Option<u64>, schema_string: &str) -> Result<()> {
+    let Some(version) = version else {
+        return Ok(());
+    };
+    if current_schema_string(table, version)?.as_deref() != Some(schema_string) {
+        return Err(anyhow!(
+            "Output schema differs from Delta table {}; write to a new table",
+            table.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Schema string of the last `metaData` action at or before `version`.
+fn current_schema_string(table: &Path, version: u64) -> Result<Option<String>> {
+    let mut schema = None;
+    for v in 0..=version {
+        let path = commit_path(table, v);
+        let file = File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+        for line in BufReader::new(file).lines() {
+            let action: Value = serde_json::from_str(&line?)?;
+            if let Some(s) = action.pointer("/metaData/schemaString").and_then(Value::as_str) {
+                schema = Some(s.to_string());
+            }
+        }
+    }
+    Ok(schema)
+}
+
+fn commit_path(table: &Path, version: u64) -> PathBuf {
+    table.join(DELTA_LOG_DIR).join(format!("{:020}.json", version))
+}
+
+/// Private staging file for this writer's attempt at `version`; hidden from Delta
+/// readers (and `latest_version`), which only list `<version>.json`.
+fn commit_tmp_path(table: &Path, version: u64) -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    table
+        .join(DELTA_LOG_DIR)
+        .join(format!(".{:020}.json.{}-{}.tmp", version, std::process::id(), nanos))
+}
+
+fn write_commit_file(path: &Path, actions: &[Value]) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    for action in actions {
+        writeln!(file, "{}", action)?;
+    }
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Converts an Arrow schema to Delta's JSON schema; dictionary columns map to
+/// their value type.
+fn delta_schema(schema: &Schema) -> Result<Value> {
+    struct_type(schema.fields())
+}
+
+fn struct_type(fields: &Fields) -> Result<Value> {
+    let fields = fields
+        .iter()
+        .map(|field| delta_field(field))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({ "type": "struct", "fields": fields }))
+}
+
+fn delta_field(field: &Field) -> Result<Value> {
+    Ok(json!({
+        "name": field.name(),
+        "type": delta_type(field.data_type())?,
+        "nullable": field.is_nullable(),
+        "metadata": {},
+    }))
+}
+
+fn delta_type(data_type: &DataType) -> Result<Value> {
+    let primitive = match data_type {
+        DataType::Utf8 | DataType::LargeUtf8 => "string",
+        DataType::Boolean => "boolean",
+        DataType::Int8 => "byte",
+        DataType::Int16 => "short",
+        DataType::Int32 => "integer",
+        DataType::Int64 => "long",
+        DataType::Float32 => "float",
+        DataType::Float64 => "double",
+        DataType::Dictionary(_, value) => return delta_type(value),
+        DataType::List(item) | DataType::LargeList(item) => {
+            return Ok(json!({
+                "type": "array",
+                "elementType": delta_type(item.data_type())?,
+                "containsNull": item.is_nullable(),
+            }));
+        }
+        DataType::Struct(fields) => return struct_type(fields),
+        other => return Err(anyhow!("Delta output does not support column type {}", other)),
+    };
+    Ok(Value::from(primitive))
+}
+
+/// The `release` value of the first row, if the batch has one.
+fn batch_release(batch: &RecordBatch) -> Option<String> {
+    let column = batch.column_by_name("release")?;
+    if column.is_empty() || column.is_null(0) {
+        return None;
+    }
+    let dict = column.as_any_dictionary_opt()?;
+    let key = dict.normalized_keys()[0];
+    Some(dict.values().as_string::<i32>().value(key).to_string())
+}
+
+/// Unique data file name (Delta only requires uniqueness within the table).
+fn data_file_name(table: &Path) -> String {
+    format!("part-{}.zstd.parquet", &hex_digest(table)[..20])
+}
+
+/// UUID-formatted table id.
+fn table_id(table: &Path) -> String {
+    let hex = hex_digest(table);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// SHA-256 of the table path, the current time and the process id.
+fn hex_digest(table: &Path) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(table.to_string_lossy().as_bytes());
+    hasher.update(nanos.to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use arrow::array::{DictionaryArray, Int32Array, StringArray};
+    use arrow::datatypes::Int32Type;
+    use crossbeam_channel::unbounded;
+    use std::sync::Arc;
+
+    fn release_batch(accessions: &[&str], release: &str) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("organism_id", DataType::Int32, true),
+            Field::new(
+                "release",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ),
+        ]));
+        let releases: DictionaryArray<Int32Type> =
+            accessions.iter().map(|_| Some(release)).collect();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(accessions.to_vec())),
+                Arc::new(Int32Array::from(vec![9606; accessions.len()])),
+                Arc::new(releases),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn append(table: &Path, batch: RecordBatch) {
+        let (tx, rx) = unbounded();
+        tx.send(batch).unwrap();
+        drop(tx);
+        write_batches(rx, table, &Metrics::new(), &Settings::default()).unwrap();
+    }
+
+    fn actions(table: &Path, version: u64) -> Vec<Value> {
+        fs::read_to_string(commit_path(table, version))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_releases_append_as_versions() {
+        let dir = std::env::temp_dir().join(format!("uniprot_etl_delta_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let table = dir.join("uniprot.delta");
+
+        append(&table, release_batch(&["P1", "P2"], "2024_05"));
+        append(&table, release_batch(&["P1", "P2", "P3"], "2024_06"));
+        assert_eq!(latest_version(&table).unwrap(), Some(1));
+        // Staging files are linked into place and removed
+        let mut log: Vec<String> = fs::read_dir(table.join(DELTA_LOG_DIR))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        log.sort();
+        assert_eq!(log, [format!("{:020}.json", 0), format!("{:020}.json", 1)]);
+
+        let v0 = actions(&table, 0);
+        assert_eq!(v0[0]["commitInfo"]["uniprotRelease"], "2024_05");
+        assert_eq!(v0[1]["protocol"]["minReaderVersion"], 1);
+        let schema: Value =
+            serde_json::from_str(v0[2]["metaData"]["schemaString"].as_str().unwrap()).unwrap();
+        assert_eq!(schema["fields"][1]["type"], "integer");
+        assert_eq!(schema["fields"][2]["type"], "string");
+
+        // Later versions only append data
+        let v1 = actions(&table, 1);
+        assert_eq!(v1.len(), 2);
+        assert_eq!(v1[0]["commitInfo"]["uniprotRelease"], "2024_06");
+        assert_eq!(v1[1]["add"]["stats"], r#"{"numRecords":3}"#);
+        let data = table.join(v1[1]["add"]["path"].as_str().unwrap());
+        assert_eq!(v1[1]["add"]["size"], fs::metadata(data).unwrap().len());
+
+        // A different schema is refused rather than silently mixed in
+        let (tx, rx) = unbounded();
+        tx.send(release_batch(&["P1"], "2024_07").project(&[0]).unwrap()).unwrap();
+        drop(tx);
+        assert!(write_batches(rx, &table, &Metrics::new(), &Settings::default()).is_err());
+        assert_eq!(latest_version(&table).unwrap(), Some(1));
+
+        // Also at commit time, e.g. when a concurrent writer changed it after the data was written
+        let projected = release_batch(&["P1"], "2024_07").project(&[0]).unwrap();
+        let other_schema = delta_schema(&projected.schema()).unwrap().to_string();
+        assert!(commit(&table, &other_schema, &v1[1], None).is_err());
+        assert_eq!(latest_version(&table).unwrap(), Some(1));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+//! DuckDB output sink (`storage.output_format: duckdb`, cargo feature `duckdb`).
+//!
+//! Batches are appended straight into a DuckDB database file, one table per entity
+//! (see `writer::normalize`). DuckDB's appender cannot take lists of structs, which
+//! is why the nested columns are split out rather than stored as one wide table.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ::duckdb::Connection;
+use anyhow::{anyhow, Context, Result};
+use arrow::datatypes::{DataType, Schema};
+use arrow::record_batch::RecordBatch;
+use crossbeam_channel::Receiver;
+
+use crate::metrics::MetricsCollector;
+use crate::schema::SchemaProfile;
+use crate::writer::normalize::{normalize, quote_ident};
+use crate::log_info;
+
+/// DuckDB's vector size; the appender takes at most this many rows per chunk.
+const APPEND_CHUNK_ROWS: usize = 2048;
+
+/// Consumes RecordBatches from the channel and appends them to a new DuckDB database.
+///
+/// Without any batch, the tables of `profile`'s schema are created empty.
+pub fn write_batches<M: MetricsCollector>(
+    rx: Receiver<RecordBatch>,
+    output: &Path,
+    metrics: &M,
+    profile: SchemaProfile,
+) -> Result<()> {
+    // Match the Parquet writer, which truncates an existing output
+    for path in [output.to_path_buf(), output.with_extension("duckdb.wal")] {
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("Failed to remove {}", path.display()));
+            }
+            _ => {}
+        }
+    }
+    let conn = Connection::open(output)
+        .with_context(|| format!("Failed to open DuckDB database {}", output.display()))?;
+
+    let mut created = HashSet::new();
+    for batch in rx {
+        let batch_bytes = batch.get_array_memory_size() as u64;
+        append_batch(&conn, &mut created, &batch)?;
+        metrics.add_bytes_written(batch_bytes);
+    }
+    if created.is_empty() {
+        // No entries: still create the (empty) tables so downstream queries work
+        append_batch(&conn, &mut created, &RecordBatch::new_empty(profile.schema()))?;
+    }
+    conn.close().map_err(|(_, e)| e)?;
+
+    log_info!(
+        "writer",
+        "Wrote DuckDB: {} ({} tables)",
+        output.display(),
+        created.len()
+    );
+    Ok(())
+}
+
+/// Normalizes `batch` and appends every table, creating tables on first use.
+fn append_batch(conn: &Connection, created: &mut HashSet<String>, batch: &RecordBatch) -> Result<()> {
+    for (table, rows) in normalize(batch)? {
+        if created.insert(table.clone()) {
+            conn.execute_batch(&create_table_sql(&table, rows.schema().as_ref())?)?;
+        }
+        let mut appender = conn.appender(&table)?;
+        for offset in (0..rows.num_rows()).step_by(APPEND_CHUNK_ROWS) {
+            let len = APPEND_CHUNK_ROWS.min(rows.num_rows() - offset);
+            appender.append_record_batch(rows.slice(offset, len))?;
+        }
+        appender.flush()?;
+    }
+    Ok(())
+}
+
+fn create_table_sql(table: &str, schema: &Schema) -> Result<String> {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|f| Ok(format!("{} {}", quote_ident(f.name()), sql_type(f.data_type())?)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!("CREATE TABLE {} ({})", quote_ident(table), columns.join(", ")))
+}
+
+/// DuckDB column type for a (dictionary-free) Arrow type.
+fn sql_type(data_type: &DataType) -> Result<String> {
+    Ok(match data_type {
+        DataType::Boolean => "BOOLEAN".to_string(),
+        DataType::Int8 => "TINYINT".to_string(),
+        DataType::Int16 => "SMALLINT".to_string(),
+        DataType::Int32 => "INTEGER".to_string(),
+        DataType::Int64 => "BIGINT".to_string(),
+        DataType::UInt32 => "UINTEGER".to_string(),
+        DataType::UInt64 => "UBIGINT".to_string(),
+        DataType::Float32 => "REAL".to_string(),
+        DataType::Float64 => "DOUBLE".to_string(),
+        DataType::Utf8 => "VARCHAR".to_string(),
+        DataType::List(item) => format!("{}[]", sql_type(item.data_type())?),
+        DataType::Struct(fields) => {
+            let members = fields
+                .iter()
+                .map(|f| Ok(format!("{} {}", quote_ident(f.name()), sql_type(f.data_type())?)))
+                .collect::<Result<Vec<_>>>()?;
+            format!("STRUCT({})", members.join(", "))
+        }
+        other => return Err(anyhow!("column type {} is not supported by the DuckDB sink", other)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+
+    const XML: &str = r#"<uniprot>
+        <entry>
+            <accession>P1</accession>
+            <organism><name type="scientific">Homo sapiens</name><dbReference type="NCBI Taxonomy" id="9606"/></organism>
+            <feature type="domain" description="Kinase"><location><begin position="1"/><end position="4"/></location></feature>
+            <feature type="modified residue" description="Phosphoserine"><location><position position="3"/></location></feature>
+            <sequence length="6">MKSAKW</sequence>
+        </entry>
+        <entry><accession>P2</accession><sequence length="2">MK</sequence></entry>
+    </uniprot>"#;
+
+    #[test]
+    fn test_writes_one_table_per_entity() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_duckdb");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("out.duckdb");
+
+        let mut reader = Reader::from_reader(XML.as_bytes());
+        reader.config_mut().trim_text(true);
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        parse_entries(reader, tx, &metrics, 16, None).unwrap();
+        write_batches(rx, &output, &metrics, SchemaProfile::Full).unwrap();
+
+        let conn = Connection::open(&output).unwrap();
+        let query = |sql: &str| -> String { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        assert_eq!(query("SELECT string_agg(id, ',' ORDER BY id) FROM proteins"), "P1,P2");
+        assert_eq!(query("SELECT organism_name FROM proteins WHERE id = 'P1'"), "Homo sapiens");
+        assert_eq!(
+            query("SELECT string_agg(feature_type || '@' || start, ',' ORDER BY ordinal) FROM features"),
+            "domain@1,modified residue@3"
+        );
+        assert_eq!(query("SELECT protein_id || ':' || site_aa FROM ptm_sites"), "P1:S");
+        let modifications: i64 = conn
+            .query_row("SELECT count(*) FROM ptm_sites_modifications WHERE parent_ordinal = 0", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(modifications, 1);
+
+        drop(conn);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
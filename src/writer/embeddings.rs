@@ -0,0 +1,304 @@
+//! Sharded sequence export for embedding jobs (`storage.embedding_export`).
+//!
+//! Reads written Parquet output back and writes every row's sequence into fixed-size
+//! FASTA or CSV shards that ESM-style embedding scripts read directly, plus:
+//! - `manifest.json`: shard files with row counts, first/last id and SHA-256
+//! - `join_keys.parquet`: (shard, shard_row) -> id, parent_id, sequence CRC64, to
+//!   attach the embeddings back to the Parquet rows
+//!
+//! Shards follow output file and row order, so the same output gives the same shards.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{Array, ArrayRef, Int32Builder, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+
+use crate::config::{EmbeddingExportConfig, ShardFormat};
+use crate::pipeline::checksum::crc64_hex;
+use crate::sidecar::sha256_file;
+use crate::stats::utf8;
+
+/// Shard listing written next to the shards.
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// Row -> shard position mapping written next to the shards.
+pub const JOIN_KEYS_FILE: &str = "join_keys.parquet";
+
+/// Contents of `manifest.json`.
+#[derive(Serialize, Debug)]
+pub struct ShardManifest {
+    pub format: ShardFormat,
+    pub rows_per_shard: usize,
+    pub total_rows: u64,
+    pub shards: Vec<ShardRecord>,
+}
+
+/// One shard of the manifest.
+#[derive(Serialize, Debug)]
+pub struct ShardRecord {
+    pub index: usize,
+    pub file: String,
+    pub rows: usize,
+    pub first_id: String,
+    pub last_id: String,
+    pub sha256: String,
+}
+
+/// Schema: shard, shard_row, id, parent_id, sequence_crc64.
+pub fn join_keys_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("shard", DataType::Int32, false),
+        Field::new("shard_row", DataType::Int32, false),
+        Field::new("id", DataType::Utf8, false),
+        Field::new("parent_id", DataType::Utf8, false),
+        Field::new("sequence_crc64", DataType::Utf8, false),
+    ]))
+}
+
+/// Writes the sequences of `outputs` as shards into `dir`, with the manifest and join
+/// key file; returns the manifest.
+pub fn write_embedding_shards(
+    outputs: &[PathBuf],
+    dir: &Path,
+    config: &EmbeddingExportConfig,
+) -> Result<ShardManifest> {
+    if config.rows_per_shard == 0 {
+        return Err(anyhow!("embedding_export.rows_per_shard must be at least 1"));
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let props = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let join_path = dir.join(JOIN_KEYS_FILE);
+    let file = File::create(&join_path).with_context(|| format!("Failed to create {}", join_path.display()))?;
+    let mut join_writer = ArrowWriter::try_new(file, join_keys_schema(), Some(props))?;
+
+    let mut shards = ShardWriter::new(dir, config);
+    for output in outputs {
+        let file = File::open(output).with_context(|| format!("Failed to open {}", output.display()))?;
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+        let roots = ["id", "parent_id", "sequence"]
+            .iter()
+            .map(|name| builder.schema().index_of(name))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let projection = ProjectionMask::roots(builder.parquet_schema(), roots);
+        for batch in builder.with_projection(projection).build()? {
+            let keys = shards.write_batch(&batch?)?;
+            join_writer.write(&keys)?;
+        }
+    }
+    join_writer.close()?;
+
+    let manifest = shards.finish()?;
+    let manifest_path = dir.join(MANIFEST_FILE);
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+    Ok(manifest)
+}
+
+/// The shard currently being filled.
+struct OpenShard {
+    path: PathBuf,
+    out: BufWriter<File>,
+    rows: usize,
+    first_id: String,
+    last_id: String,
+}
+
+/// Splits rows over consecutive shard files.
+struct ShardWriter<'a> {
+    dir: &'a Path,
+    config: &'a EmbeddingExportConfig,
+    current: Option<OpenShard>,
+    manifest: ShardManifest,
+}
+
+impl<'a> ShardWriter<'a> {
+    fn new(dir: &'a Path, config: &'a EmbeddingExportConfig) -> Self {
+        Self {
+            dir,
+            config,
+            current: None,
+            manifest: ShardManifest {
+                format: config.format,
+                rows_per_shard: config.rows_per_shard,
+                total_rows: 0,
+                shards: Vec::new(),
+            },
+        }
+    }
+
+    /// Writes the rows of `batch` and returns their join keys.
+    fn write_batch(&mut self, batch: &RecordBatch) -> Result<RecordBatch> {
+        let ids = utf8(column(batch, "id")?.as_ref())?;
+        let parent_ids = utf8(column(batch, "parent_id")?.as_ref())?;
+        let sequences = utf8(column(batch, "sequence")?.as_ref())?;
+
+        let mut shard = Int32Builder::new();
+        let mut shard_row = Int32Builder::new();
+        let mut id = StringBuilder::new();
+        let mut parent_id = StringBuilder::new();
+        let mut checksum = StringBuilder::new();
+        for row in 0..batch.num_rows() {
+            // Isoforms kept without a sidecar sequence have nothing to embed
+            if sequences.is_null(row) {
+                continue;
+            }
+            let (row_id, sequence) = (ids.value(row), sequences.value(row));
+            let (index, position) = self.write_row(row_id, sequence)?;
+            shard.append_value(index as i32);
+            shard_row.append_value(position as i32);
+            id.append_value(row_id);
+            parent_id.append_value(parent_ids.value(row));
+            checksum.append_value(crc64_hex(sequence.as_bytes()));
+        }
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(shard.finish()),
+            Arc::new(shard_row.finish()),
+            Arc::new(id.finish()),
+            Arc::new(parent_id.finish()),
+            Arc::new(checksum.finish()),
+        ];
+        Ok(RecordBatch::try_new(join_keys_schema(), columns)?)
+    }
+
+    /// Appends one record, returning its (shard index, 0-based row within the shard).
+    fn write_row(&mut self, id: &str, sequence: &str) -> Result<(usize, usize)> {
+        if self.current.is_none() {
+            self.open_shard(id)?;
+        }
+        let index = self.manifest.shards.len();
+        let shard = self.current.as_mut().expect("shard opened above");
+        match self.config.format {
+            ShardFormat::Fasta => writeln!(shard.out, ">{}\n{}", id, sequence)?,
+            ShardFormat::Csv => writeln!(shard.out, "{},{}", id, sequence)?,
+        }
+        let position = shard.rows;
+        shard.rows += 1;
+        shard.last_id = id.to_string();
+        self.manifest.total_rows += 1;
+        if shard.rows == self.config.rows_per_shard {
+            self.close_shard()?;
+        }
+        Ok((index, position))
+    }
+
+    fn open_shard(&mut self, first_id: &str) -> Result<()> {
+        let index = self.manifest.shards.len();
+        let path = self
+            .dir
+            .join(format!("shard_{:05}.{}", index, self.config.format.extension()));
+        let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        if self.config.format == ShardFormat::Csv {
+            writeln!(out, "id,sequence")?;
+        }
+        self.current = Some(OpenShard {
+            path,
+            out,
+            rows: 0,
+            first_id: first_id.to_string(),
+            last_id: first_id.to_string(),
+        });
+        Ok(())
+    }
+
+    fn close_shard(&mut self) -> Result<()> {
+        let Some(mut shard) = self.current.take() else {
+            return Ok(());
+        };
+        shard.out.flush()?;
+        drop(shard.out);
+        let file = shard
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.manifest.shards.push(ShardRecord {
+            index: self.manifest.shards.len(),
+            file,
+            rows: shard.rows,
+            first_id: shard.first_id,
+            last_id: shard.last_id,
+            sha256: sha256_file(&shard.path)?,
+        });
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<ShardManifest> {
+        self.close_shard()?;
+        Ok(self.manifest)
+    }
+}
+
+fn column(batch: &RecordBatch, name: &str) -> Result<ArrayRef> {
+    batch
+        .column_by_name(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("output is missing column {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use crate::writer::parquet::write_batches;
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+
+    #[test]
+    fn splits_sequences_into_fixed_size_shards() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_embedding_shards");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let entries: String = ["P1", "P2", "P3"]
+            .iter()
+            .map(|a| format!("<entry><accession>{}</accession><sequence length=\"2\">MK</sequence></entry>", a))
+            .collect();
+        let xml = format!("<uniprot>{}</uniprot>", entries);
+        let output = dir.join("out.parquet");
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        parse_entries(Reader::from_reader(xml.as_bytes()), tx, &metrics, 1, None).unwrap();
+        write_batches(rx, &output, &metrics, &Settings::default()).unwrap();
+
+        let config = EmbeddingExportConfig {
+            rows_per_shard: 2,
+            ..EmbeddingExportConfig::default()
+        };
+        let shard_dir = dir.join("shards");
+        let manifest = write_embedding_shards(&[output], &shard_dir, &config).unwrap();
+        assert_eq!(manifest.total_rows, 3);
+        assert_eq!(manifest.shards.len(), 2);
+        assert_eq!(manifest.shards[1].rows, 1);
+        assert_eq!(manifest.shards[1].first_id, "P3");
+        assert_eq!(
+            fs::read_to_string(shard_dir.join("shard_00000.fasta")).unwrap(),
+            ">P1\nMK\n>P2\nMK\n"
+        );
+        assert!(shard_dir.join(MANIFEST_FILE).exists());
+
+        let file = File::open(shard_dir.join(JOIN_KEYS_FILE)).unwrap();
+        let rows: usize = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum();
+        assert_eq!(rows, 3);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,177 @@
+//! Accession -> gene mapping table (`storage.id_mapping_path`).
+//!
+//! Reads written Parquet output back and writes the `id`, `parent_id`, `gene_name`,
+//! `entry_name` and `organism_id` of every row, so consumers that only need to map
+//! accessions to gene symbols do not scan the wide file for them. There is one row per
+//! output row, so isoform accessions map too; `parent_id` groups them by entry. A
+//! `.tsv` path is written as tab-separated text with a header row, any other path as
+//! Parquet.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{Array, ArrayRef, Int32Array};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+
+use crate::stats::utf8;
+
+/// Output columns the table is read from, in table order.
+pub const ID_MAPPING_COLUMNS: [&str; 5] = ["id", "parent_id", "gene_name", "entry_name", "organism_id"];
+
+/// Schema: id, parent_id, gene_name, entry_name, organism_id.
+pub fn id_mapping_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("parent_id", DataType::Utf8, false),
+        Field::new("gene_name", DataType::Utf8, true),
+        Field::new("entry_name", DataType::Utf8, true),
+        Field::new("organism_id", DataType::Int32, true),
+    ]))
+}
+
+enum Sink {
+    Tsv(BufWriter<File>),
+    Parquet(Box<ArrowWriter<File>>),
+}
+
+/// Writes the mapping row of every row of `outputs` into `path`; returns the number of
+/// rows written.
+pub fn write_id_mapping(outputs: &[PathBuf], path: &Path) -> Result<u64> {
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut sink = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("tsv")) {
+        let mut out = BufWriter::new(file);
+        writeln!(out, "{}", ID_MAPPING_COLUMNS.join("\t"))?;
+        Sink::Tsv(out)
+    } else {
+        let props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        Sink::Parquet(Box::new(ArrowWriter::try_new(file, id_mapping_schema(), Some(props))?))
+    };
+
+    let mut total = 0u64;
+    for output in outputs {
+        let file = File::open(output).with_context(|| format!("Failed to open {}", output.display()))?;
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+        let roots = ID_MAPPING_COLUMNS
+            .iter()
+            .map(|name| builder.schema().index_of(name))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let projection = ProjectionMask::roots(builder.parquet_schema(), roots);
+        for batch in builder.with_projection(projection).build()? {
+            let batch = mapping_batch(&batch?)?;
+            total += batch.num_rows() as u64;
+            match &mut sink {
+                Sink::Tsv(out) => write_tsv(out, &batch)?,
+                Sink::Parquet(writer) => writer.write(&batch)?,
+            }
+        }
+    }
+
+    match sink {
+        Sink::Tsv(mut out) => out.flush()?,
+        Sink::Parquet(writer) => {
+            writer.close()?;
+        }
+    }
+    Ok(total)
+}
+
+/// The mapping columns of an output batch, in table order.
+fn mapping_batch(batch: &RecordBatch) -> Result<RecordBatch> {
+    let columns = ID_MAPPING_COLUMNS
+        .iter()
+        .map(|&name| {
+            let column = batch
+                .column_by_name(name)
+                .ok_or_else(|| anyhow!("output is missing column {}", name))?;
+            Ok(match column.data_type() {
+                DataType::Int32 => Arc::clone(column),
+                _ => Arc::new(utf8(column.as_ref())?) as ArrayRef,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RecordBatch::try_new(id_mapping_schema(), columns)?)
+}
+
+fn write_tsv(out: &mut impl Write, batch: &RecordBatch) -> Result<()> {
+    let strings = (0..4).map(|i| utf8(batch.column(i).as_ref())).collect::<Result<Vec<_>>>()?;
+    let organism_ids = batch
+        .column(4)
+        .as_any()
+        .downcast_ref::<Int32Array>()
+        .ok_or_else(|| anyhow!("organism_id is not Int32"))?;
+    for row in 0..batch.num_rows() {
+        for column in &strings {
+            // Null names are empty fields
+            let value = if column.is_null(row) { "" } else { column.value(row) };
+            write!(out, "{}\t", value)?;
+        }
+        if organism_ids.is_null(row) {
+            writeln!(out)?;
+        } else {
+            writeln!(out, "{}", organism_ids.value(row))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use crate::writer::parquet::write_batches;
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+    use std::fs;
+
+    #[test]
+    fn writes_tsv_and_parquet_mapping_tables() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_id_mapping");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let xml = r#"<uniprot>
+            <entry><accession>P1</accession><name>A_HUMAN</name>
+                <gene><name type="primary">GENEA</name></gene>
+                <organism><dbReference type="NCBI Taxonomy" id="9606"/></organism>
+                <sequence length="2">MK</sequence></entry>
+            <entry><accession>P2</accession><sequence length="2">MK</sequence></entry>
+        </uniprot>"#;
+        let output = dir.join("out.parquet");
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        let mut reader = Reader::from_reader(xml.as_bytes());
+        reader.config_mut().trim_text(true);
+        parse_entries(reader, tx, &metrics, 16, None).unwrap();
+        write_batches(rx, &output, &metrics, &Settings::default()).unwrap();
+
+        let tsv = dir.join("id_mapping.tsv");
+        assert_eq!(write_id_mapping(std::slice::from_ref(&output), &tsv).unwrap(), 2);
+        assert_eq!(
+            fs::read_to_string(&tsv).unwrap(),
+            "id\tparent_id\tgene_name\tentry_name\torganism_id\nP1\tP1\tGENEA\tA_HUMAN\t9606\nP2\tP2\t\t\t\n"
+        );
+
+        let parquet = dir.join("id_mapping.parquet");
+        assert_eq!(write_id_mapping(&[output], &parquet).unwrap(), 2);
+        let batches: Vec<RecordBatch> = ParquetRecordBatchReaderBuilder::try_new(File::open(&parquet).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap())
+            .collect();
+        assert_eq!(batches[0].schema(), id_mapping_schema());
+        assert_eq!(utf8(batches[0].column(2).as_ref()).unwrap().value(0), "GENEA");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
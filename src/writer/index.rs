@@ -0,0 +1,268 @@
+//! Row-group index over written Parquet output (`storage.write_index`).
+//!
+//! Maps every `parent_id`/`id` to the output file and row-group ordinal holding it, so
+//! a single protein can be read back by decoding one row group instead of scanning
+//! the whole dataset. The index is itself a small Parquet file next to the output;
+//! its name starts with `_` so dataset readers (Spark, pyarrow, `stats`) skip it.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{
+    ArrayRef, BooleanArray, Int32Array, Int32Builder, Int64Array, Int64Builder, StringArray, StringBuilder,
+};
+use arrow::compute::kernels::boolean::or;
+use arrow::compute::kernels::cmp::eq;
+use arrow::compute::{cast, filter_record_batch};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+
+use crate::pipeline::builders::DictStringBuilder;
+use crate::schema::dict_utf8;
+
+/// File name of the index written next to a swarm output directory's files.
+pub const SWARM_INDEX_FILE: &str = "_index.parquet";
+
+/// Index location for a single output file: `<dir>/_<stem>.index.parquet`.
+pub fn index_path_for(output: &Path) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    output.with_file_name(format!("_{}.index.parquet", stem))
+}
+
+/// Schema: parent_id, id, file (relative to the index), row_group, row (within the row group).
+pub fn index_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("parent_id", DataType::Utf8, false),
+        Field::new("id", DataType::Utf8, false),
+        Field::new("file", dict_utf8(), false),
+        Field::new("row_group", DataType::Int32, false),
+        Field::new("row", DataType::Int64, false),
+    ]))
+}
+
+/// One indexed row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexHit {
+    pub parent_id: String,
+    pub id: String,
+    /// Output file (resolved against the index's directory)
+    pub file: PathBuf,
+    pub row_group: usize,
+    pub row: i64,
+}
+
+/// Indexes `outputs` into `index_path`; returns the number of rows indexed.
+///
+/// Outputs are recorded relative to the index's directory when they live under it.
+pub fn write_index(outputs: &[PathBuf], index_path: &Path) -> Result<u64> {
+    let base = index_path.parent().unwrap_or_else(|| Path::new(""));
+    let props = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let file = File::create(index_path)
+        .with_context(|| format!("Failed to create index {}", index_path.display()))?;
+    let mut writer = ArrowWriter::try_new(file, index_schema(), Some(props))?;
+
+    let mut total = 0u64;
+    for output in outputs {
+        let name = output.strip_prefix(base).unwrap_or(output).to_string_lossy().into_owned();
+        let open = || -> Result<ParquetRecordBatchReaderBuilder<File>> {
+            let file = File::open(output).with_context(|| format!("Failed to open {}", output.display()))?;
+            Ok(ParquetRecordBatchReaderBuilder::try_new(file)?)
+        };
+        let builder = open()?;
+        let row_groups = builder.metadata().num_row_groups();
+        let roots = [builder.schema().index_of("parent_id")?, builder.schema().index_of("id")?];
+        let projection = ProjectionMask::roots(builder.parquet_schema(), roots);
+
+        for row_group in 0..row_groups {
+            let reader = open()?
+                .with_projection(projection.clone())
+                .with_row_groups(vec![row_group])
+                .build()?;
+            let mut row = 0i64;
+            for batch in reader {
+                let batch = batch?;
+                let parent_ids = utf8_column(&batch, "parent_id")?;
+                let ids = utf8_column(&batch, "id")?;
+
+                let mut parent_id_col = StringBuilder::new();
+                let mut id_col = StringBuilder::new();
+                let mut file_col = DictStringBuilder::new();
+                let mut row_group_col = Int32Builder::new();
+                let mut row_col = Int64Builder::new();
+                for i in 0..batch.num_rows() {
+                    parent_id_col.append_value(parent_ids.value(i));
+                    id_col.append_value(ids.value(i));
+                    file_col.append_value(&name);
+                    row_group_col.append_value(row_group as i32);
+                    row_col.append_value(row);
+                    row += 1;
+                }
+                let columns: Vec<ArrayRef> = vec![
+                    Arc::new(parent_id_col.finish()),
+                    Arc::new(id_col.finish()),
+                    Arc::new(file_col.finish()),
+                    Arc::new(row_group_col.finish()),
+                    Arc::new(row_col.finish()),
+                ];
+                writer.write(&RecordBatch::try_new(index_schema(), columns)?)?;
+                total += batch.num_rows() as u64;
+            }
+        }
+    }
+
+    writer.close()?;
+    Ok(total)
+}
+
+/// Index entries whose `parent_id` or `id` equals `accession`.
+pub fn lookup(index_path: &Path, accession: &str) -> Result<Vec<IndexHit>> {
+    let base = index_path.parent().unwrap_or_else(|| Path::new(""));
+    let file = File::open(index_path)
+        .with_context(|| format!("Failed to open index {}", index_path.display()))?;
+    let mut hits = Vec::new();
+    for batch in ParquetRecordBatchReaderBuilder::try_new(file)?.build()? {
+        let batch = filter_accession(&batch?, accession)?;
+        let parent_ids = utf8_column(&batch, "parent_id")?;
+        let ids = utf8_column(&batch, "id")?;
+        let files = utf8_column(&batch, "file")?;
+        let row_groups = batch
+            .column_by_name("row_group")
+            .and_then(|c| c.as_any().downcast_ref::<Int32Array>())
+            .ok_or_else(|| anyhow!("index is missing row_group"))?;
+        let rows = batch
+            .column_by_name("row")
+            .and_then(|c| c.as_any().downcast_ref::<Int64Array>())
+            .ok_or_else(|| anyhow!("index is missing row"))?;
+        for i in 0..batch.num_rows() {
+            hits.push(IndexHit {
+                parent_id: parent_ids.value(i).to_string(),
+                id: ids.value(i).to_string(),
+                file: base.join(files.value(i)),
+                row_group: row_groups.value(i) as usize,
+                row: rows.value(i),
+            });
+        }
+    }
+    Ok(hits)
+}
+
+/// Reads the rows of `accession` (a parent or isoform id) by decoding only the row
+/// groups the index points at.
+pub fn read_indexed(index_path: &Path, accession: &str) -> Result<Vec<RecordBatch>> {
+    let mut row_groups: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+    for hit in lookup(index_path, accession)? {
+        let groups = row_groups.entry(hit.file).or_default();
+        if !groups.contains(&hit.row_group) {
+            groups.push(hit.row_group);
+        }
+    }
+
+    let mut batches = Vec::new();
+    for (path, groups) in row_groups {
+        let file = File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file)?
+            .with_row_groups(groups)
+            .build()?;
+        for batch in reader {
+            let batch = filter_accession(&batch?, accession)?;
+            if batch.num_rows() > 0 {
+                batches.push(batch);
+            }
+        }
+    }
+    Ok(batches)
+}
+
+/// Rows of `batch` whose `parent_id` or `id` equals `accession`.
+fn filter_accession(batch: &RecordBatch, accession: &str) -> Result<RecordBatch> {
+    let needle = StringArray::new_scalar(accession);
+    let parent_match = eq(&utf8_column(batch, "parent_id")?, &needle)?;
+    let id_match = eq(&utf8_column(batch, "id")?, &needle)?;
+    let mask: BooleanArray = or(&parent_match, &id_match)?;
+    Ok(filter_record_batch(batch, &mask)?)
+}
+
+/// A string column as plain Utf8 (dictionary columns are decoded).
+fn utf8_column(batch: &RecordBatch, name: &str) -> Result<StringArray> {
+    let column = batch
+        .column_by_name(name)
+        .ok_or_else(|| anyhow!("missing column {}", name))?;
+    let column = cast(column, &DataType::Utf8)?;
+    Ok(column
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .ok_or_else(|| anyhow!("column {} is not a string column", name))?
+        .clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use crate::writer::parquet::write_batches;
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+    use std::fs;
+
+    fn write_output(path: &Path, accessions: &[&str], settings: &Settings) {
+        let entries: String = accessions
+            .iter()
+            .map(|a| format!("<entry><accession>{}</accession><sequence length=\"2\">MK</sequence></entry>", a))
+            .collect();
+        let xml = format!("<uniprot>{}</uniprot>", entries);
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        parse_entries(Reader::from_reader(xml.as_bytes()), tx, &metrics, 1, None).unwrap();
+        write_batches(rx, path, &metrics, settings).unwrap();
+    }
+
+    #[test]
+    fn test_index_points_at_row_groups() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_index");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut settings = Settings::default();
+        settings.performance.max_row_group_size = 2;
+
+        let outputs = vec![dir.join("a.parquet"), dir.join("b.parquet")];
+        write_output(&outputs[0], &["P1", "P2", "P3"], &settings);
+        write_output(&outputs[1], &["Q1", "Q2"], &settings);
+
+        let index_path = dir.join(SWARM_INDEX_FILE);
+        assert_eq!(write_index(&outputs, &index_path).unwrap(), 5);
+
+        assert_eq!(
+            lookup(&index_path, "P3").unwrap(),
+            vec![IndexHit {
+                parent_id: "P3".to_string(),
+                id: "P3".to_string(),
+                file: dir.join("a.parquet"),
+                row_group: 1,
+                row: 0,
+            }]
+        );
+        assert!(lookup(&index_path, "X9").unwrap().is_empty());
+
+        let batches = read_indexed(&index_path, "Q2").unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), 1);
+        assert_eq!(utf8_column(&batches[0], "id").unwrap().value(0), "Q2");
+
+        assert_eq!(index_path_for(&outputs[0]), dir.join("_a.index.parquet"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1 +1,116 @@
+pub mod dataset;
+pub mod delta;
+#[cfg(feature = "duckdb")]
+pub mod duckdb;
+pub mod embeddings;
+pub mod id_mapping;
+pub mod index;
+pub mod normalize;
 pub mod parquet;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod ptm_flat;
+pub mod rejected;
+pub mod sort;
+pub mod variant_peptides;
+
+use anyhow::{anyhow, Result};
+use arrow::record_batch::RecordBatch;
+use crossbeam_channel::Receiver;
+use std::path::Path;
+
+use crate::config::{OutputFormat, Settings};
+use crate::metrics::MetricsCollector;
+
+/// Fails if `storage.output_format` is not available in this build, is combined with
+/// an option that only applies to Parquet output, or a derived output needs a column
+/// that `schema.profile` leaves out.
+pub fn validate_output(settings: &Settings) -> Result<()> {
+    let storage = &settings.storage;
+    let profile = settings.schema.profile;
+    let derived: [(bool, &str, &[&str]); 4] = [
+        (storage.ptm_flat.path.is_some(), "ptm_flat", &["sequence", "ptm_sites"]),
+        (storage.variant_peptides.path.is_some(), "variant_peptides", &["sequence", "natural_variants"]),
+        (storage.embedding_export.dir.is_some(), "embedding_export", &["sequence"]),
+        (storage.id_mapping_path.is_some(), "id_mapping_path", &id_mapping::ID_MAPPING_COLUMNS),
+    ];
+    for (enabled, option, columns) in derived {
+        if let Some(missing) = columns.iter().find(|column| enabled && !profile.includes(column)) {
+            return Err(anyhow!(
+                "{} reads the {} column, which schema.profile: {} does not produce",
+                option,
+                missing,
+                profile.name()
+            ));
+        }
+    }
+    match storage.output_format {
+        OutputFormat::Parquet
+            if (storage.max_output_file_gb.is_some() || storage.append_to_dataset) && storage.sort_by_parent_id =>
+        {
+            return Err(anyhow!(
+                "sort_by_parent_id rewrites a single file; it cannot be combined with max_output_file_gb or append_to_dataset"
+            ));
+        }
+        OutputFormat::Parquet
+            if storage.accession_shards > 0 && (storage.max_output_file_gb.is_some() || storage.append_to_dataset) =>
+        {
+            return Err(anyhow!(
+                "accession_shards writes a fixed shard layout; it cannot be combined with max_output_file_gb or append_to_dataset"
+            ));
+        }
+        OutputFormat::Parquet => return Ok(()),
+        OutputFormat::Duckdb if cfg!(not(feature = "duckdb")) => {
+            return Err(anyhow!(
+                "output_format: duckdb requires a build with `--features duckdb` (or duckdb-bundled)"
+            ));
+        }
+        OutputFormat::Postgres if cfg!(not(feature = "postgres")) => {
+            return Err(anyhow!("output_format: postgres requires a build with `--features postgres`"));
+        }
+        OutputFormat::Postgres if storage.postgres.connection_url().is_none() => {
+            return Err(anyhow!(
+                "output_format: postgres needs storage.postgres.url or the DATABASE_URL environment variable"
+            ));
+        }
+        _ => {}
+    }
+    if storage.sort_by_parent_id
+        || storage.max_output_file_gb.is_some()
+        || storage.append_to_dataset
+        || storage.accession_shards > 0
+        || storage.write_index
+        || storage.ptm_flat.path.is_some()
+        || storage.variant_peptides.path.is_some()
+        || storage.embedding_export.dir.is_some()
+        || storage.id_mapping_path.is_some()
+    {
+        return Err(anyhow!(
+            "sort_by_parent_id, max_output_file_gb, append_to_dataset, accession_shards, write_index, ptm_flat, variant_peptides, embedding_export and id_mapping_path only apply to output_format: parquet"
+        ));
+    }
+    Ok(())
+}
+
+/// Consumes RecordBatches from the channel into `output`, in the configured
+/// `storage.output_format` (the `postgres` format ignores `output`; for `delta` it is
+/// the table directory).
+pub fn write_output<M: MetricsCollector>(
+    rx: Receiver<RecordBatch>,
+    output: &Path,
+    metrics: &M,
+    settings: &Settings,
+) -> Result<()> {
+    match settings.storage.output_format {
+        OutputFormat::Parquet => parquet::write_batches(rx, output, metrics, settings),
+        OutputFormat::Delta => delta::write_batches(rx, output, metrics, settings),
+        #[cfg(feature = "duckdb")]
+        OutputFormat::Duckdb => duckdb::write_batches(rx, output, metrics, settings.schema.profile),
+        #[cfg(not(feature = "duckdb"))]
+        OutputFormat::Duckdb => validate_output(settings),
+        #[cfg(feature = "postgres")]
+        OutputFormat::Postgres => postgres::write_batches(rx, &settings.storage.postgres, metrics, settings.schema.profile),
+        #[cfg(not(feature = "postgres"))]
+        OutputFormat::Postgres => validate_output(settings),
+    }
+}
@@ -0,0 +1,184 @@
+//! Normalization of nested batches into flat, relational tables for the database
+//! sinks (`writer::duckdb`, `writer::postgres`).
+//!
+//! `proteins` holds the scalar columns of every row, and each `List<Struct>` column
+//! (features, isoforms, ptm_sites, ...) becomes a table of the same name keyed by
+//! `protein_id` (the row's `id`) and `ordinal` (position in the list). Lists nested
+//! inside those structs become `<table>_<field>` tables that also carry
+//! `parent_ordinal`. Dictionary columns are decoded to plain Utf8.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use arrow::array::{Array, ArrayRef, Int32Builder, ListArray, StructArray, UInt32Builder};
+use arrow::compute::{cast, take};
+use arrow::datatypes::{DataType, Field, Fields, Schema};
+use arrow::record_batch::RecordBatch;
+
+/// Table holding the top-level columns, one row per output row.
+pub const PROTEINS_TABLE: &str = "proteins";
+
+/// Splits `batch` into `(table, rows)` pairs, `proteins` first.
+pub fn normalize(batch: &RecordBatch) -> Result<Vec<(String, RecordBatch)>> {
+    let ids = batch
+        .column_by_name("id")
+        .ok_or_else(|| anyhow!("batch has no id column"))?;
+
+    let mut tables = vec![];
+    let mut fields = vec![];
+    let mut columns = vec![];
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        match struct_list(column) {
+            Some(list) => flatten_list(field.name(), ids, None, list, &mut tables)?,
+            None => {
+                let column = plain(column)?;
+                fields.push(Field::new(field.name(), column.data_type().clone(), field.is_nullable()));
+                columns.push(column);
+            }
+        }
+    }
+    let proteins = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?;
+    tables.insert(0, (PROTEINS_TABLE.to_string(), proteins));
+    Ok(tables)
+}
+
+/// Emits the structs of `list` as rows of `table`, keyed by the owning protein and,
+/// for nested lists, the owning struct's ordinal.
+fn flatten_list(
+    table: &str,
+    protein_ids: &ArrayRef,
+    parent_ordinals: Option<&ArrayRef>,
+    list: &ListArray,
+    out: &mut Vec<(String, RecordBatch)>,
+) -> Result<()> {
+    let offsets = list.value_offsets();
+    let mut owners = UInt32Builder::new();
+    let mut ordinals = Int32Builder::new();
+    for row in 0..list.len() {
+        // Null lists have equal offsets and contribute no rows
+        for ordinal in 0..(offsets[row + 1] - offsets[row]) {
+            owners.append_value(row as u32);
+            ordinals.append_value(ordinal);
+        }
+    }
+    let owners = owners.finish();
+    let first = offsets[0] as usize;
+    let values = list.values().slice(first, offsets[list.len()] as usize - first);
+    let structs = values
+        .as_any()
+        .downcast_ref::<StructArray>()
+        .ok_or_else(|| anyhow!("{} is not a list of structs", table))?;
+
+    let protein_id = take(protein_ids.as_ref(), &owners, None)?;
+    let ordinal: ArrayRef = Arc::new(ordinals.finish());
+    let mut fields = vec![Field::new("protein_id", protein_id.data_type().clone(), false)];
+    let mut columns = vec![protein_id.clone()];
+    if let Some(parent) = parent_ordinals {
+        fields.push(Field::new("parent_ordinal", DataType::Int32, false));
+        columns.push(take(parent.as_ref(), &owners, None)?);
+    }
+    fields.push(Field::new("ordinal", DataType::Int32, false));
+    columns.push(ordinal.clone());
+
+    let at = out.len();
+    for (field, column) in structs.fields().iter().zip(structs.columns()) {
+        match struct_list(column) {
+            Some(nested) => {
+                let nested_table = format!("{}_{}", table, field.name());
+                flatten_list(&nested_table, &protein_id, Some(&ordinal), nested, out)?;
+            }
+            None => {
+                let column = plain(column)?;
+                fields.push(Field::new(field.name(), column.data_type().clone(), true));
+                columns.push(column);
+            }
+        }
+    }
+    let rows = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?;
+    out.insert(at, (table.to_string(), rows));
+    Ok(())
+}
+
+/// `column` as a `ListArray` if it is a list of structs.
+fn struct_list(column: &ArrayRef) -> Option<&ListArray> {
+    let list = column.as_any().downcast_ref::<ListArray>()?;
+    matches!(list.value_type(), DataType::Struct(_)).then_some(list)
+}
+
+/// `column` with dictionary encoding removed.
+fn plain(column: &ArrayRef) -> Result<ArrayRef> {
+    let target = plain_type(column.data_type());
+    if &target == column.data_type() {
+        return Ok(column.clone());
+    }
+    Ok(cast(column, &target)?)
+}
+
+fn plain_type(data_type: &DataType) -> DataType {
+    match data_type {
+        DataType::Dictionary(_, value) => plain_type(value),
+        DataType::List(item) => DataType::List(Arc::new(
+            item.as_ref().clone().with_data_type(plain_type(item.data_type())),
+        )),
+        DataType::Struct(fields) => DataType::Struct(Fields::from(
+            fields
+                .iter()
+                .map(|f| f.as_ref().clone().with_data_type(plain_type(f.data_type())))
+                .collect::<Vec<_>>(),
+        )),
+        other => other.clone(),
+    }
+}
+
+/// Double-quoted SQL identifier.
+pub fn quote_ident(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use arrow::array::{Int32Array, StringArray};
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+
+    #[test]
+    fn test_normalize_splits_nested_lists_into_tables() {
+        let xml = r#"<uniprot>
+            <entry>
+                <accession>P1</accession>
+                <feature type="domain" description="Kinase"><location><begin position="1"/><end position="4"/></location></feature>
+                <feature type="modified residue" description="Phosphoserine"><location><position position="3"/></location></feature>
+                <sequence length="6">MKSAKW</sequence>
+            </entry>
+            <entry><accession>P2</accession><sequence length="2">MK</sequence></entry>
+        </uniprot>"#;
+        let mut reader = Reader::from_reader(xml.as_bytes());
+        reader.config_mut().trim_text(true);
+        let (tx, rx) = unbounded();
+        parse_entries(reader, tx, &Metrics::new(), 16, None).unwrap();
+        let tables = normalize(&rx.recv().unwrap()).unwrap();
+
+        let table = |name: &str| &tables.iter().find(|(t, _)| t == name).unwrap().1;
+        assert_eq!(tables[0].0, PROTEINS_TABLE);
+        assert_eq!(table(PROTEINS_TABLE).num_rows(), 2);
+        assert!(table(PROTEINS_TABLE).column_by_name("features").is_none());
+
+        let features = table("features");
+        assert_eq!(features.num_rows(), 2);
+        assert_eq!(features.column_by_name("feature_type").unwrap().data_type(), &DataType::Utf8);
+        let ordinals = features.column_by_name("ordinal").unwrap();
+        let ordinals = ordinals.as_any().downcast_ref::<Int32Array>().unwrap();
+        assert_eq!(ordinals.values(), &[0, 1]);
+
+        let modifications = table("ptm_sites_modifications");
+        let protein_ids = modifications.column_by_name("protein_id").unwrap();
+        let protein_ids = protein_ids.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(protein_ids.value(0), "P1");
+        assert!(modifications.column_by_name("parent_ordinal").is_some());
+
+        assert_eq!(quote_ident("end"), "\"end\"");
+    }
+}
@@ -1,15 +1,21 @@
+use arrow::datatypes::SchemaRef;
 use arrow::record_batch::RecordBatch;
 use crossbeam_channel::Receiver;
 use parquet::arrow::ArrowWriter;
 use parquet::basic::{Compression, Encoding, ZstdLevel};
-use parquet::file::properties::{WriterProperties, WriterVersion};
+use parquet::file::properties::{
+    EnabledStatistics, WriterProperties, WriterPropertiesBuilder, WriterVersion,
+};
+use parquet::schema::types::ColumnPath;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crate::config::Settings;
 use crate::metrics::MetricsCollector;
-use crate::schema::schema_ref;
-use anyhow::{anyhow, Result};
+use crate::writer::dataset::Dataset;
+use anyhow::{anyhow, Context, Result};
+use crate::log_info;
 
 /// Consumes RecordBatches from the channel and writes them to a Parquet file.
 pub fn write_batches<M: MetricsCollector>(
@@ -18,41 +24,220 @@ pub fn write_batches<M: MetricsCollector>(
     metrics: &M,
     settings: &Settings,
 ) -> Result<()> {
-    let file = File::create(output)?;
+    write_batches_with_schema(rx, output, metrics, settings, settings.schema.profile.schema())
+}
+
+/// Like `write_batches`, for batches carrying enricher columns (see `Enrichers::schema`).
+///
+/// With `storage.max_output_file_gb` or `storage.append_to_dataset` set, `output` is a
+/// dataset directory of `part-NNNNN.parquet` files instead (see `writer::dataset`).
+pub fn write_batches_with_schema<M: MetricsCollector>(
+    rx: Receiver<RecordBatch>,
+    output: &Path,
+    metrics: &M,
+    settings: &Settings,
+    schema: SchemaRef,
+) -> Result<()> {
     let props = writer_properties(settings)?;
-    let mut writer = ArrowWriter::try_new(file, schema_ref(), Some(props))?;
+    let budget = match settings.storage.max_output_file_gb {
+        Some(gb) if gb > 0.0 => (gb * 1024.0 * 1024.0 * 1024.0) as usize,
+        Some(gb) => return Err(anyhow!("Invalid max_output_file_gb: {} (must be positive)", gb)),
+        None => usize::MAX,
+    };
+    let mut dataset = if settings.storage.append_to_dataset {
+        Some(Dataset::append(output, &schema)?)
+    } else if budget != usize::MAX {
+        Some(Dataset::create(output)?)
+    } else {
+        None
+    };
 
+    let open = |dataset: &mut Option<Dataset>| -> Result<(PathBuf, ArrowWriter<File>)> {
+        let (path, file) = match dataset {
+            Some(dataset) => dataset.create_part()?,
+            None => {
+                let file = File::create(output).with_context(|| format!("Failed to create {}", output.display()))?;
+                (output.to_path_buf(), file)
+            }
+        };
+        let writer = ArrowWriter::try_new(file, Arc::clone(&schema), Some(props.clone()))?;
+        Ok((path, writer))
+    };
+
+    let mut part_rows = 0;
+    let (mut path, mut writer) = open(&mut dataset)?;
     for batch in rx {
+        // Roll before the batch that would start past the budget, so no part is empty
+        if part_rows > 0 && writer.bytes_written() + writer.in_progress_size() >= budget {
+            close_part(writer, &path, dataset.as_mut())?;
+            part_rows = 0;
+            (path, writer) = open(&mut dataset)?;
+        }
         let batch_bytes = batch.get_array_memory_size() as u64;
+        part_rows += batch.num_rows();
         writer.write(&batch)?;
         metrics.add_bytes_written(batch_bytes);
     }
+    close_part(writer, &path, dataset.as_mut())?;
+    if let Some(dataset) = dataset {
+        let manifest = dataset.commit()?;
+        log_info!(
+            "writer",
+            "Wrote Parquet dataset: {} ({} files, {} rows)",
+            output.display(),
+            manifest.files.len(),
+            manifest.total_rows
+        );
+    }
 
+    Ok(())
+}
+
+fn close_part(writer: ArrowWriter<File>, path: &Path, dataset: Option<&mut Dataset>) -> Result<()> {
     let file_metadata = writer.close()?;
+    if let Some(dataset) = dataset {
+        dataset.add_part(path, file_metadata.num_rows as u64)?;
+    }
     let row_groups = file_metadata.row_groups;
     let total_bytes: i64 = row_groups.iter().map(|rg| rg.total_byte_size).sum();
-    eprintln!(
+    log_info!(
+        "writer",
         "Wrote Parquet: {} (size: {:.2} MB)",
-        output.display(),
+        path.display(),
         total_bytes as f64 / (1024.0 * 1024.0)
     );
-
     Ok(())
 }
 
 /// Creates optimized WriterProperties for UniProt data from Settings.
 fn writer_properties(settings: &Settings) -> Result<WriterProperties> {
+    Ok(writer_properties_builder(settings)?.build())
+}
+
+/// Writer property builder shared by the main writer and the sorted rewrite.
+pub(crate) fn writer_properties_builder(settings: &Settings) -> Result<WriterPropertiesBuilder> {
     let zstd_level = ZstdLevel::try_new(settings.performance.zstd_level as i32)
         .map_err(|e| anyhow!("Invalid zstd_level: {}", e))?;
 
-    Ok(WriterProperties::builder()
+    let perf = &settings.performance;
+    if !(perf.bloom_filter_fpp > 0.0 && perf.bloom_filter_fpp < 1.0) {
+        return Err(anyhow!(
+            "Invalid bloom_filter_fpp: {} (must be in (0, 1))",
+            perf.bloom_filter_fpp
+        ));
+    }
+
+    let statistics = if perf.column_statistics {
+        EnabledStatistics::Page
+    } else {
+        EnabledStatistics::None
+    };
+
+    let mut builder = WriterProperties::builder()
         .set_writer_version(WriterVersion::PARQUET_2_0)
         .set_compression(Compression::ZSTD(zstd_level))
         // Use dictionary encoding for string columns (good for repeated values)
         .set_column_encoding("id".into(), Encoding::PLAIN)
         .set_column_encoding("sequence".into(), Encoding::PLAIN)
         .set_dictionary_enabled(true)
+        // Min/max statistics let engines prune row groups on range predicates
+        .set_statistics_enabled(statistics)
         // Row group size: balance between compression and random access
-        .set_max_row_group_size(settings.performance.max_row_group_size)
-        .build())
+        .set_max_row_group_size(perf.max_row_group_size);
+
+    // Bloom filters let point lookups (e.g. WHERE id = 'P04637') skip row groups
+    for column in &perf.bloom_filter_columns {
+        let path = ColumnPath::from(column.as_str());
+        builder = builder
+            .set_column_bloom_filter_enabled(path.clone(), true)
+            .set_column_bloom_filter_fpp(path, perf.bloom_filter_fpp);
+    }
+
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use crate::writer::dataset::{output_files, part_path, read_manifest};
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+    use std::fs;
+
+    /// Parses `count` minimal entries into batches of 10 and writes them to `output`.
+    fn write_entries(count: usize, output: &Path, settings: &Settings) {
+        let entries: String = (0..count)
+            .map(|i| format!("<entry><accession>P{:05}</accession><sequence length=\"3\">MKT</sequence></entry>", i))
+            .collect();
+        let xml = format!("<uniprot>{}</uniprot>", entries);
+        let (tx, rx) = unbounded();
+        parse_entries(Reader::from_reader(xml.as_bytes()), tx, &Metrics::new(), 10, None).unwrap();
+        write_batches(rx, output, &Metrics::new(), settings).unwrap();
+    }
+
+    #[test]
+    fn rolls_to_a_new_part_past_the_size_budget() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_rolling_output");
+        let _ = fs::remove_dir_all(&dir);
+        let mut settings = Settings::default();
+        // A few bytes: every batch after the first starts a new part
+        settings.storage.max_output_file_gb = Some(1e-9);
+        write_entries(30, &dir, &settings);
+
+        let parts = output_files(&dir).unwrap();
+        assert_eq!(parts, (0..3).map(|i| part_path(&dir, i)).collect::<Vec<_>>());
+        assert_eq!(read_manifest(&dir).unwrap().unwrap().total_rows, 30);
+
+        // A fresh (non-append) write replaces the earlier parts
+        write_entries(10, &dir, &settings);
+        assert_eq!(output_files(&dir).unwrap(), vec![part_path(&dir, 0)]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn appends_parts_after_the_existing_ones() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_append_dataset");
+        let _ = fs::remove_dir_all(&dir);
+        let mut settings = Settings::default();
+        settings.storage.append_to_dataset = true;
+        write_entries(10, &dir, &settings);
+        // A part from a run that wrote no manifest entry for it is kept and skipped
+        fs::copy(part_path(&dir, 0), part_path(&dir, 1)).unwrap();
+        write_entries(20, &dir, &settings);
+
+        assert_eq!(
+            output_files(&dir).unwrap(),
+            (0..3).map(|i| part_path(&dir, i)).collect::<Vec<_>>()
+        );
+        let manifest = read_manifest(&dir).unwrap().unwrap();
+        let files: Vec<&str> = manifest.files.iter().map(|f| f.file.as_str()).collect();
+        assert_eq!(files, ["part-00000.parquet", "part-00002.parquet"]);
+        assert_eq!(manifest.total_rows, 30);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bloom_filters_follow_settings() {
+        let mut settings = Settings::default();
+        let props = writer_properties(&settings).unwrap();
+        for column in ["id", "parent_id", "gene_name"] {
+            assert!(props.bloom_filter_properties(&ColumnPath::from(column)).is_some());
+        }
+        assert!(props.bloom_filter_properties(&ColumnPath::from("sequence")).is_none());
+        assert_eq!(
+            props.statistics_enabled(&ColumnPath::from("id")),
+            EnabledStatistics::Page
+        );
+
+        settings.performance.bloom_filter_columns.clear();
+        settings.performance.column_statistics = false;
+        let props = writer_properties(&settings).unwrap();
+        assert!(props.bloom_filter_properties(&ColumnPath::from("id")).is_none());
+        assert_eq!(
+            props.statistics_enabled(&ColumnPath::from("id")),
+            EnabledStatistics::None
+        );
+    }
 }
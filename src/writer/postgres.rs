@@ -0,0 +1,201 @@
+//! PostgreSQL output sink (`storage.output_format: postgres`, cargo feature `postgres`).
+//!
+//! Batches are normalized into one table per entity (see `writer::normalize`) and
+//! streamed with `COPY ... FROM STDIN (FORMAT binary)` into `storage.postgres.schema`.
+//! Each input file is loaded in a single transaction, so a failed run leaves no
+//! partial rows behind. Tables are created on first use (`CREATE TABLE IF NOT
+//! EXISTS`) under an advisory lock, so parallel swarm workers can share them.
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::datatypes::{
+    DataType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, Schema, UInt32Type,
+};
+use arrow::record_batch::RecordBatch;
+use crossbeam_channel::Receiver;
+use postgres::binary_copy::BinaryCopyInWriter;
+use postgres::types::{ToSql, Type};
+use postgres::{Client, GenericClient, NoTls};
+
+use crate::config::PostgresConfig;
+use crate::metrics::MetricsCollector;
+use crate::schema::SchemaProfile;
+use crate::writer::normalize::{normalize, quote_ident};
+use crate::log_info;
+
+/// Key of the advisory lock serializing table creation across connections.
+const DDL_LOCK_KEY: i64 = 0x0075_6e69_7072_6f74; // "uniprot"
+
+/// Consumes RecordBatches from the channel and COPYs them into Postgres.
+pub fn write_batches<M: MetricsCollector>(
+    rx: Receiver<RecordBatch>,
+    config: &PostgresConfig,
+    metrics: &M,
+    profile: SchemaProfile,
+) -> Result<()> {
+    let url = config
+        .connection_url()
+        .ok_or_else(|| anyhow!("storage.postgres.url is not set (nor DATABASE_URL)"))?;
+    let mut client = Client::connect(&url, NoTls).context("Failed to connect to Postgres")?;
+
+    // Tables are created from the first batch so enricher columns are included
+    let first = rx.recv().ok();
+    let template = first.clone().unwrap_or_else(|| RecordBatch::new_empty(profile.schema()));
+    create_tables(&mut client, &config.schema, &template.slice(0, 0))?;
+
+    let mut tx = client.transaction()?;
+    let mut rows_copied = 0u64;
+    for batch in first.into_iter().chain(rx) {
+        let batch_bytes = batch.get_array_memory_size() as u64;
+        for (table, rows) in normalize(&batch)? {
+            rows_copied += copy_rows(&mut tx, &config.schema, &table, &rows)?;
+        }
+        metrics.add_bytes_written(batch_bytes);
+    }
+    tx.commit()?;
+
+    log_info!(
+        "writer",
+        "Wrote Postgres: {} rows into schema {}",
+        rows_copied, config.schema
+    );
+    Ok(())
+}
+
+/// Creates the schema and the tables of `template` that do not exist yet.
+fn create_tables(client: &mut Client, schema: &str, template: &RecordBatch) -> Result<()> {
+    let mut tx = client.transaction()?;
+    tx.execute("SELECT pg_advisory_xact_lock($1)", &[&DDL_LOCK_KEY])?;
+    tx.batch_execute(&format!("CREATE SCHEMA IF NOT EXISTS {}", quote_ident(schema)))?;
+    for (table, rows) in normalize(template)? {
+        tx.batch_execute(&create_table_sql(schema, &table, rows.schema().as_ref())?)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Streams `rows` into `schema.table` with a binary COPY; returns the rows written.
+fn copy_rows(
+    client: &mut impl GenericClient,
+    schema: &str,
+    table: &str,
+    rows: &RecordBatch,
+) -> Result<u64> {
+    if rows.num_rows() == 0 {
+        return Ok(0);
+    }
+    let fields = rows.schema().fields().clone();
+    let types = fields
+        .iter()
+        .map(|f| pg_type(f.data_type()).map(|(t, _)| t))
+        .collect::<Result<Vec<_>>>()?;
+    let columns: Vec<String> = fields.iter().map(|f| quote_ident(f.name())).collect();
+    let sink = client.copy_in(&format!(
+        "COPY {}.{} ({}) FROM STDIN (FORMAT binary)",
+        quote_ident(schema),
+        quote_ident(table),
+        columns.join(", ")
+    ))?;
+
+    let mut writer = BinaryCopyInWriter::new(sink, &types);
+    for row in 0..rows.num_rows() {
+        let values = rows
+            .columns()
+            .iter()
+            .map(|column| cell(column, row))
+            .collect::<Result<Vec<_>>>()?;
+        let params: Vec<&(dyn ToSql + Sync)> = values.iter().map(|v| v.as_ref()).collect();
+        writer.write(&params)?;
+    }
+    Ok(writer.finish()?)
+}
+
+fn create_table_sql(schema: &str, table: &str, columns: &Schema) -> Result<String> {
+    let columns = columns
+        .fields()
+        .iter()
+        .map(|f| Ok(format!("{} {}", quote_ident(f.name()), pg_type(f.data_type())?.1)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!(
+        "CREATE TABLE IF NOT EXISTS {}.{} ({})",
+        quote_ident(schema),
+        quote_ident(table),
+        columns.join(", ")
+    ))
+}
+
+/// Postgres wire type and column type for a (dictionary-free) Arrow type.
+fn pg_type(data_type: &DataType) -> Result<(Type, &'static str)> {
+    Ok(match data_type {
+        DataType::Boolean => (Type::BOOL, "BOOLEAN"),
+        DataType::Int8 | DataType::Int16 => (Type::INT2, "SMALLINT"),
+        DataType::Int32 => (Type::INT4, "INTEGER"),
+        DataType::Int64 | DataType::UInt32 => (Type::INT8, "BIGINT"),
+        DataType::Float32 => (Type::FLOAT4, "REAL"),
+        DataType::Float64 => (Type::FLOAT8, "DOUBLE PRECISION"),
+        DataType::Utf8 => (Type::TEXT, "TEXT"),
+        DataType::List(item) if item.data_type() == &DataType::Utf8 => (Type::TEXT_ARRAY, "TEXT[]"),
+        DataType::List(item) if item.data_type() == &DataType::Int32 => (Type::INT4_ARRAY, "INTEGER[]"),
+        other => return Err(anyhow!("column type {} is not supported by the Postgres sink", other)),
+    })
+}
+
+/// Value of `column` at `row` as a COPY parameter (`None` for nulls).
+fn cell(column: &ArrayRef, row: usize) -> Result<Box<dyn ToSql + Sync + '_>> {
+    let valid = column.is_valid(row);
+    Ok(match column.data_type() {
+        DataType::Boolean => Box::new(valid.then(|| column.as_boolean().value(row))),
+        DataType::Int8 => Box::new(valid.then(|| column.as_primitive::<Int8Type>().value(row) as i16)),
+        DataType::Int16 => Box::new(valid.then(|| column.as_primitive::<Int16Type>().value(row))),
+        DataType::Int32 => Box::new(valid.then(|| column.as_primitive::<Int32Type>().value(row))),
+        DataType::Int64 => Box::new(valid.then(|| column.as_primitive::<Int64Type>().value(row))),
+        DataType::UInt32 => Box::new(valid.then(|| column.as_primitive::<UInt32Type>().value(row) as i64)),
+        DataType::Float32 => Box::new(valid.then(|| column.as_primitive::<Float32Type>().value(row))),
+        DataType::Float64 => Box::new(valid.then(|| column.as_primitive::<Float64Type>().value(row))),
+        DataType::Utf8 => Box::new(valid.then(|| column.as_string::<i32>().value(row))),
+        DataType::List(item) if item.data_type() == &DataType::Utf8 => Box::new(valid.then(|| {
+            let values = column.as_list::<i32>().value(row);
+            values.as_string::<i32>().iter().map(|v| v.map(str::to_string)).collect::<Vec<_>>()
+        })),
+        DataType::List(item) if item.data_type() == &DataType::Int32 => Box::new(valid.then(|| {
+            let values = column.as_list::<i32>().value(row);
+            values.as_primitive::<Int32Type>().iter().collect::<Vec<_>>()
+        })),
+        other => return Err(anyhow!("column type {} is not supported by the Postgres sink", other)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int8Array, StringArray};
+    use arrow::datatypes::Field;
+    use std::sync::Arc;
+
+    use crate::schema::schema_ref;
+
+    #[test]
+    fn test_tables_and_cells_map_to_postgres_types() {
+        let tables = normalize(&RecordBatch::new_empty(schema_ref())).unwrap();
+        let (_, features) = tables.iter().find(|(t, _)| t == "features").unwrap();
+        assert_eq!(
+            create_table_sql("uniprot", "features", features.schema().as_ref()).unwrap(),
+            "CREATE TABLE IF NOT EXISTS \"uniprot\".\"features\" (\"protein_id\" TEXT, \"ordinal\" INTEGER, \
+             \"feature_type\" TEXT, \"description\" TEXT, \"start\" INTEGER, \"end\" INTEGER, \
+             \"evidence_code\" TEXT, \"position_status\" TEXT)"
+        );
+        // Every built-in table is representable
+        for (table, rows) in &tables {
+            create_table_sql("public", table, rows.schema().as_ref()).unwrap();
+        }
+
+        let existence: ArrayRef = Arc::new(Int8Array::from(vec![Some(1), None]));
+        assert_eq!(format!("{:?}", cell(&existence, 0).unwrap()), "Some(1)");
+        assert_eq!(format!("{:?}", cell(&existence, 1).unwrap()), "None");
+        let names: ArrayRef = Arc::new(StringArray::from(vec!["TP53"]));
+        assert_eq!(format!("{:?}", cell(&names, 0).unwrap()), "Some(\"TP53\")");
+
+        let unsupported = Schema::new(vec![Field::new("x", DataType::Float16, true)]);
+        assert!(create_table_sql("public", "t", &unsupported).is_err());
+    }
+}
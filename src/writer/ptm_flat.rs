@@ -0,0 +1,382 @@
+//! Flattened PTM training table (`storage.ptm_flat`).
+//!
+//! Reads written Parquet output back and explodes `ptm_sites` into one row per
+//! (id, site_index, mod_type), with the residues around the site, so model training
+//! can consume it without re-nesting. Optionally adds unmodified target residues of
+//! the same rows as negative examples (`storage.ptm_flat.negatives`).
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{
+    Array, ArrayRef, BooleanBuilder, Float32Array, Float32Builder, Int32Array, Int32Builder, ListArray, StringArray,
+    StringBuilder, StructArray,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+
+use crate::config::{NegativeSamplingConfig, PtmFlatConfig};
+use crate::pipeline::builders::DictStringBuilder;
+use crate::pipeline::checksum::crc64;
+use crate::pipeline::window::sequence_window_with;
+use crate::schema::dict_utf8;
+use crate::stats::utf8;
+
+/// Schema: id, parent_id, site_index, site_aa, mod_type, mod_class, mod_subclass,
+/// ptm_accession, confidence_score, evidence_code, window, label.
+///
+/// `label` is true for annotated modifications and false for sampled negative sites,
+/// whose modification columns are null.
+pub fn ptm_flat_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("parent_id", DataType::Utf8, false),
+        Field::new("site_index", DataType::Int32, false),
+        Field::new("site_aa", DataType::Utf8, false),
+        Field::new("mod_type", DataType::Int32, true),
+        Field::new("mod_class", dict_utf8(), true),
+        Field::new("mod_subclass", dict_utf8(), true),
+        Field::new("ptm_accession", DataType::Utf8, true),
+        Field::new("confidence_score", DataType::Float32, true),
+        Field::new("evidence_code", dict_utf8(), true),
+        Field::new("window", DataType::Utf8, false),
+        Field::new("label", DataType::Boolean, false),
+    ]))
+}
+
+/// Explodes the `ptm_sites` of `outputs` into `path`, with the configured window around
+/// each site and, if enabled, sampled negative sites; returns the number of rows written.
+pub fn write_ptm_flat(outputs: &[PathBuf], path: &Path, config: &PtmFlatConfig) -> Result<u64> {
+    let props = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = ArrowWriter::try_new(file, ptm_flat_schema(), Some(props))?;
+
+    let mut total = 0u64;
+    for output in outputs {
+        let file = File::open(output).with_context(|| format!("Failed to open {}", output.display()))?;
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+        let roots = ["id", "parent_id", "sequence", "ptm_sites"]
+            .iter()
+            .map(|name| builder.schema().index_of(name))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let projection = ProjectionMask::roots(builder.parquet_schema(), roots);
+        for batch in builder.with_projection(projection).build()? {
+            let flat = flatten_batch(&batch?, config)?;
+            total += flat.num_rows() as u64;
+            writer.write(&flat)?;
+        }
+    }
+
+    writer.close()?;
+    Ok(total)
+}
+
+/// Column builders of one flattened batch.
+#[derive(Default)]
+struct FlatRows {
+    id: StringBuilder,
+    parent_id: StringBuilder,
+    site_index: Int32Builder,
+    site_aa: StringBuilder,
+    mod_type: Int32Builder,
+    mod_class: DictStringBuilder,
+    mod_subclass: DictStringBuilder,
+    accession: StringBuilder,
+    confidence: Float32Builder,
+    evidence: DictStringBuilder,
+    window: StringBuilder,
+    label: BooleanBuilder,
+}
+
+impl FlatRows {
+    fn append_site(&mut self, id: &str, parent_id: &str, site_index: i32, site_aa: &str, window: &str) {
+        self.id.append_value(id);
+        self.parent_id.append_value(parent_id);
+        self.site_index.append_value(site_index);
+        self.site_aa.append_value(site_aa);
+        self.window.append_value(window);
+    }
+
+    /// A site with no modification columns and `label` false.
+    fn append_negative(&mut self, id: &str, parent_id: &str, site_index: i32, site_aa: &str, window: &str) {
+        self.append_site(id, parent_id, site_index, site_aa, window);
+        self.mod_type.append_null();
+        self.mod_class.append_null();
+        self.mod_subclass.append_null();
+        self.accession.append_null();
+        self.confidence.append_null();
+        self.evidence.append_null();
+        self.label.append_value(false);
+    }
+
+    fn finish(mut self) -> Result<RecordBatch> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.id.finish()),
+            Arc::new(self.parent_id.finish()),
+            Arc::new(self.site_index.finish()),
+            Arc::new(self.site_aa.finish()),
+            Arc::new(self.mod_type.finish()),
+            Arc::new(self.mod_class.finish()),
+            Arc::new(self.mod_subclass.finish()),
+            Arc::new(self.accession.finish()),
+            Arc::new(self.confidence.finish()),
+            Arc::new(self.evidence.finish()),
+            Arc::new(self.window.finish()),
+            Arc::new(self.label.finish()),
+        ];
+        Ok(RecordBatch::try_new(ptm_flat_schema(), columns)?)
+    }
+}
+
+fn flatten_batch(batch: &RecordBatch, config: &PtmFlatConfig) -> Result<RecordBatch> {
+    let context = config.site_context();
+    let ids = utf8(column(batch, "id")?.as_ref())?;
+    let parent_ids = utf8(column(batch, "parent_id")?.as_ref())?;
+    let sequences = utf8(column(batch, "sequence")?.as_ref())?;
+    let sites = list(column(batch, "ptm_sites")?.as_ref(), "ptm_sites")?;
+    let window = |sequence: &str, index: i32| {
+        sequence_window_with(sequence, index as usize, context.flank, context.padding).unwrap_or_default()
+    };
+
+    let mut out = FlatRows::default();
+    for row in 0..batch.num_rows() {
+        if sites.is_null(row) {
+            continue;
+        }
+        let (id, parent_id, sequence) = (ids.value(row), parent_ids.value(row), sequences.value(row));
+        let row_sites = sites.value(row);
+        let row_sites = struct_array(row_sites.as_ref(), "ptm_sites")?;
+        let indices = int32(row_sites, "site_index")?;
+        let residues = utf8(field(row_sites, "site_aa")?.as_ref())?;
+        let modifications = list(field(row_sites, "modifications")?.as_ref(), "modifications")?;
+        // Sites kept by transform.debug_mapping although they did not map onto the row
+        let unmapped = |site: usize| row_sites.column_by_name("mapping_failure_code").is_some_and(|f| f.is_valid(site));
+
+        for site in 0..row_sites.len() {
+            if modifications.is_null(site) || unmapped(site) {
+                continue;
+            }
+            let index = indices.value(site);
+            let site_window = window(sequence, index);
+            let mods = modifications.value(site);
+            let mods = struct_array(mods.as_ref(), "modifications")?;
+            let types = int32(mods, "mod_type")?;
+            let classes = optional_utf8(mods, "mod_class")?;
+            let subclasses = optional_utf8(mods, "mod_subclass")?;
+            let accessions = optional_utf8(mods, "ptm_accession")?;
+            let evidence_codes = optional_utf8(mods, "evidence_code")?;
+            let scores = field(mods, "confidence_score")?;
+            let scores = scores
+                .as_any()
+                .downcast_ref::<Float32Array>()
+                .ok_or_else(|| anyhow!("confidence_score is not Float32"))?;
+
+            for m in 0..mods.len() {
+                out.append_site(id, parent_id, index, residues.value(site), &site_window);
+                out.mod_type.append_value(types.value(m));
+                out.mod_class.append_option(value_at(&classes, m));
+                out.mod_subclass.append_option(value_at(&subclasses, m));
+                out.accession.append_option(value_at(&accessions, m));
+                out.confidence.append_option(scores.is_valid(m).then(|| scores.value(m)));
+                out.evidence.append_option(value_at(&evidence_codes, m));
+                out.label.append_value(true);
+            }
+        }
+
+        let modified: Vec<i32> = (0..row_sites.len())
+            .filter(|&site| !unmapped(site))
+            .map(|site| indices.value(site))
+            .collect();
+        for index in sample_negatives(&config.negatives, id, sequence, &modified) {
+            let aa = &sequence[index as usize - 1..index as usize];
+            out.append_negative(id, parent_id, index, aa, &window(sequence, index));
+        }
+    }
+
+    out.finish()
+}
+
+/// 1-based positions of unmodified target residues drawn as negatives for one row:
+/// `ratio` per modified site (rounded), at most every candidate.
+///
+/// The draw is seeded from `seed` and the row id, so it does not depend on batch or
+/// file order and reruns produce the same table.
+fn sample_negatives(config: &NegativeSamplingConfig, id: &str, sequence: &str, modified: &[i32]) -> Vec<i32> {
+    let wanted = (config.ratio * modified.len() as f64).round() as usize;
+    if wanted == 0 {
+        return Vec::new();
+    }
+    let targets = config.residues.as_bytes();
+    let mut candidates: Vec<i32> = sequence
+        .bytes()
+        .enumerate()
+        .map(|(i, aa)| (i as i32 + 1, aa))
+        .filter(|(pos, aa)| targets.contains(aa) && !modified.contains(pos))
+        .map(|(pos, _)| pos)
+        .collect();
+
+    // Partial Fisher-Yates: the first `wanted` slots end up a uniform sample
+    let wanted = wanted.min(candidates.len());
+    let mut state = config.seed ^ crc64(id.as_bytes());
+    for i in 0..wanted {
+        let j = i + (splitmix64(&mut state) % (candidates.len() - i) as u64) as usize;
+        candidates.swap(i, j);
+    }
+    candidates.truncate(wanted);
+    candidates.sort_unstable();
+    candidates
+}
+
+/// SplitMix64 step: small, seedable and good enough for picking sample positions.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn column(batch: &RecordBatch, name: &str) -> Result<ArrayRef> {
+    batch
+        .column_by_name(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("output is missing column {}", name))
+}
+
+fn field(array: &StructArray, name: &str) -> Result<ArrayRef> {
+    array
+        .column_by_name(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("struct is missing field {}", name))
+}
+
+fn list(array: &dyn Array, name: &str) -> Result<ListArray> {
+    array
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .cloned()
+        .ok_or_else(|| anyhow!("{} is not a List", name))
+}
+
+fn struct_array<'a>(array: &'a dyn Array, name: &str) -> Result<&'a StructArray> {
+    array
+        .as_any()
+        .downcast_ref::<StructArray>()
+        .ok_or_else(|| anyhow!("{} is not a List<Struct>", name))
+}
+
+fn int32(array: &StructArray, name: &str) -> Result<Int32Array> {
+    field(array, name)?
+        .as_any()
+        .downcast_ref::<Int32Array>()
+        .cloned()
+        .ok_or_else(|| anyhow!("{} is not Int32", name))
+}
+
+/// A string field decoded to Utf8, or None for output written before it existed.
+fn optional_utf8(array: &StructArray, name: &str) -> Result<Option<StringArray>> {
+    array.column_by_name(name).map(|c| utf8(c.as_ref())).transpose()
+}
+
+fn value_at(array: &Option<StringArray>, i: usize) -> Option<&str> {
+    array.as_ref().filter(|a| a.is_valid(i)).map(|a| a.value(i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use crate::writer::parquet::write_batches;
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+    use std::fs;
+
+    #[test]
+    fn flattens_one_row_per_modification() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_ptm_flat");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let xml = r#"<uniprot><entry>
+            <accession>P1</accession>
+            <feature type="modified residue" description="Phosphoserine" evidence="E1">
+                <location><position position="2"/></location>
+            </feature>
+            <feature type="glycosylation site" description="N-linked (GlcNAc...) asparagine">
+                <location><position position="4"/></location>
+            </feature>
+            <evidence key="E1" type="ECO:0000269"/>
+            <sequence length="5">MSKNT</sequence>
+        </entry></uniprot>"#;
+        let output = dir.join("out.parquet");
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        parse_entries(Reader::from_reader(xml.as_bytes()), tx, &metrics, 1, None).unwrap();
+        write_batches(rx, &output, &metrics, &Settings::default()).unwrap();
+
+        let flat_path = dir.join("ptm_sites_flat.parquet");
+        let mut config = PtmFlatConfig {
+            window: 2,
+            ..PtmFlatConfig::default()
+        };
+        assert_eq!(write_ptm_flat(std::slice::from_ref(&output), &flat_path, &config).unwrap(), 2);
+
+        let file = File::open(&flat_path).unwrap();
+        let batch = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let text = |name: &str| utf8(batch.column_by_name(name).unwrap().as_ref()).unwrap();
+        assert_eq!(text("id").value(0), "P1");
+        assert_eq!(text("site_aa").value(0), "S");
+        assert_eq!(text("window").value(0), "-MSKN");
+        assert_eq!(text("window").value(1), "SKNT-");
+        assert_eq!(text("mod_class").value(0), "modified_residue");
+        assert_eq!(text("evidence_code").value(0), "ECO:0000269");
+        assert!(text("evidence_code").is_null(1));
+
+        // One negative per modified site, drawn from the unmodified T at 5
+        config.negatives.ratio = 1.0;
+        assert_eq!(write_ptm_flat(&[output], &flat_path, &config).unwrap(), 3);
+        let file = File::open(&flat_path).unwrap();
+        let batch = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let site_index = batch.column_by_name("site_index").unwrap();
+        let site_index = site_index.as_any().downcast_ref::<Int32Array>().unwrap();
+        assert_eq!(site_index.value(2), 5);
+        assert!(batch.column_by_name("mod_type").unwrap().is_null(2));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn samples_negatives_reproducibly() {
+        let config = NegativeSamplingConfig {
+            ratio: 2.0,
+            residues: "STY".to_string(),
+            seed: 7,
+        };
+        let sequence = "MSSTTYYSTYSTY";
+        let first = sample_negatives(&config, "P1", sequence, &[2]);
+        assert_eq!(first.len(), 2);
+        assert!(first.iter().all(|&p| p != 2 && b"STY".contains(&sequence.as_bytes()[p as usize - 1])));
+        assert_eq!(sample_negatives(&config, "P1", sequence, &[2]), first);
+        assert!(sample_negatives(&config, "P1", sequence, &[]).is_empty());
+    }
+}
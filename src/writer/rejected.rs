@@ -0,0 +1,124 @@
+use arrow::array::{ArrayRef, StringBuilder};
+use arrow::record_batch::RecordBatch;
+use crossbeam_channel::Receiver;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::pipeline::builders::DictStringBuilder;
+use crate::pipeline::rejects::Rejection;
+use crate::schema::rejected_schema;
+use anyhow::Result;
+
+const REJECTED_BATCH_ROWS: usize = 8_192;
+
+/// Drains rejections until every `RejectLog` handle is dropped, writing them to `output`.
+///
+/// Returns the number of rejections written.
+pub fn write_rejections(rx: Receiver<Rejection>, output: &Path) -> Result<u64> {
+    let props = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(output)?, rejected_schema(), Some(props))?;
+
+    let mut pending = Vec::with_capacity(REJECTED_BATCH_ROWS);
+    let mut total = 0u64;
+    for rejection in rx {
+        pending.push(rejection);
+        if pending.len() >= REJECTED_BATCH_ROWS {
+            writer.write(&rejections_batch(&pending)?)?;
+            total += pending.len() as u64;
+            pending.clear();
+        }
+    }
+    if !pending.is_empty() {
+        writer.write(&rejections_batch(&pending)?)?;
+        total += pending.len() as u64;
+    }
+
+    writer.close()?;
+    Ok(total)
+}
+
+fn rejections_batch(rejections: &[Rejection]) -> Result<RecordBatch> {
+    let mut parent_id = StringBuilder::new();
+    let mut id = StringBuilder::new();
+    let mut kind = DictStringBuilder::new();
+    let mut code = DictStringBuilder::new();
+    let mut detail = StringBuilder::new();
+
+    for r in rejections {
+        parent_id.append_value(&r.parent_id);
+        id.append_value(&r.id);
+        kind.append_value(r.kind.as_str());
+        code.append_value(r.code);
+        detail.append_option(r.detail.as_deref());
+    }
+
+    let arrays: Vec<ArrayRef> = vec![
+        Arc::new(parent_id.finish()),
+        Arc::new(id.finish()),
+        Arc::new(kind.finish()),
+        Arc::new(code.finish()),
+        Arc::new(detail.finish()),
+    ];
+    Ok(RecordBatch::try_new(rejected_schema(), arrays)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::rejects::{RejectKind, RejectLog};
+    use arrow::array::{Array, StringArray};
+    use arrow::compute::cast;
+    use arrow::datatypes::DataType;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn test_write_rejections_round_trip() {
+        let path = std::env::temp_dir()
+            .join(format!("uniprot_etl_test_rejected_{}.parquet", std::process::id()));
+        let (log, rx) = RejectLog::channel();
+        log.record(RejectKind::Row, "ISOFORM_SEQ_MISSING", "P1", "P1-2", || None);
+        log.record(RejectKind::Feature, "RESIDUE_MISMATCH", "P1", "P1-3", || {
+            Some("ptm original_index=5".to_string())
+        });
+        RejectLog::disabled().record(RejectKind::Row, "IGNORED", "X", "X", || None);
+        drop(log);
+
+        assert_eq!(write_rejections(rx, &path).unwrap(), 2);
+
+        let file = File::open(&path).unwrap();
+        let batch = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let ids = batch
+            .column_by_name("id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let codes = cast(batch.column_by_name("code").unwrap(), &DataType::Utf8).unwrap();
+        let codes = codes.as_any().downcast_ref::<StringArray>().unwrap();
+        let detail = batch
+            .column_by_name("detail")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+
+        assert_eq!(ids.value(0), "P1-2");
+        assert_eq!(codes.value(1), "RESIDUE_MISMATCH");
+        assert!(detail.is_null(0));
+        assert_eq!(detail.value(1), "ptm original_index=5");
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,352 @@
+//! External sort of a written Parquet file by `(parent_id, id)`.
+//!
+//! The file is read back in chunks of `max_row_group_size` rows, each chunk is
+//! sorted in memory and spilled to `temp_dir` as a run, and the runs are k-way
+//! merged into the final file. Row groups are only closed on a parent_id
+//! boundary, so all isoform rows of a protein land in the same row group.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{Array, ArrayRef, StringArray};
+use arrow::compute::{concat_batches, interleave, lexsort_to_indices, take_record_batch, SortColumn};
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::{RecordBatch, RecordBatchReader};
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use parquet::arrow::ArrowWriter;
+
+use crate::config::Settings;
+use crate::writer::parquet::writer_properties_builder;
+use crate::log_info;
+
+/// Sorts `path` in place by `(parent_id, id)`, spilling sorted runs to `storage.temp_dir`.
+pub fn sort_parquet_by_parent_id(path: &Path, settings: &Settings) -> Result<()> {
+    let run_rows = settings.performance.max_row_group_size.max(1);
+    let temp_dir = &settings.storage.temp_dir;
+    fs::create_dir_all(temp_dir)
+        .with_context(|| format!("Failed to create temp_dir: {}", temp_dir.display()))?;
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+
+    let runs = spill_sorted_runs(path, settings, temp_dir, &stem, run_rows)?;
+    let sorted_path = path.with_extension("parquet.sorting");
+    let merge_result = merge_runs(&runs, &sorted_path, settings, run_rows);
+
+    for run in &runs {
+        let _ = fs::remove_file(run);
+    }
+    let row_groups = merge_result?;
+
+    fs::rename(&sorted_path, path)
+        .with_context(|| format!("Failed to replace {} with sorted output", path.display()))?;
+    log_info!(
+        "writer",
+        "Sorted {} by parent_id ({} runs, {} row groups)",
+        path.display(),
+        runs.len(),
+        row_groups
+    );
+    Ok(())
+}
+
+/// Reads `path` in `run_rows` chunks and writes each chunk sorted to its own run file.
+fn spill_sorted_runs(
+    path: &Path,
+    settings: &Settings,
+    temp_dir: &Path,
+    stem: &str,
+    run_rows: usize,
+) -> Result<Vec<PathBuf>> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?
+        .with_batch_size(run_rows)
+        .build()?;
+    let schema = reader.schema();
+
+    let mut runs = Vec::new();
+    let mut pending: Vec<RecordBatch> = Vec::new();
+    let mut pending_rows = 0;
+
+    for batch in reader {
+        let batch = batch?;
+        pending_rows += batch.num_rows();
+        pending.push(batch);
+        if pending_rows >= run_rows {
+            runs.push(write_run(&schema, &pending, settings, temp_dir, stem, runs.len())?);
+            pending.clear();
+            pending_rows = 0;
+        }
+    }
+    if pending_rows > 0 {
+        runs.push(write_run(&schema, &pending, settings, temp_dir, stem, runs.len())?);
+    }
+
+    Ok(runs)
+}
+
+fn write_run(
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    settings: &Settings,
+    temp_dir: &Path,
+    stem: &str,
+    index: usize,
+) -> Result<PathBuf> {
+    let batch = concat_batches(schema, batches)?;
+    let sort_columns = vec![
+        SortColumn {
+            values: column(&batch, "parent_id")?,
+            options: None,
+        },
+        SortColumn {
+            values: column(&batch, "id")?,
+            options: None,
+        },
+    ];
+    let indices = lexsort_to_indices(&sort_columns, None)?;
+    let sorted = take_record_batch(&batch, &indices)?;
+
+    let run_path = temp_dir.join(format!("{}.sort_run_{:05}.parquet", stem, index));
+    let props = writer_properties_builder(settings)?.build();
+    let mut writer = ArrowWriter::try_new(File::create(&run_path)?, schema.clone(), Some(props))?;
+    writer.write(&sorted)?;
+    writer.close()?;
+    Ok(run_path)
+}
+
+/// Read cursor over one sorted run.
+struct RunCursor {
+    reader: ParquetRecordBatchReader,
+    batch: RecordBatch,
+    parent_ids: StringArray,
+    ids: StringArray,
+    row: usize,
+    /// Index of `batch` in the current output chunk's source list
+    source: usize,
+}
+
+impl RunCursor {
+    fn open(path: &Path) -> Result<Option<Self>> {
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()?;
+        match reader.next() {
+            Some(batch) => {
+                let batch = batch?;
+                Ok(Some(Self {
+                    parent_ids: string_column(&batch, "parent_id")?,
+                    ids: string_column(&batch, "id")?,
+                    batch,
+                    reader,
+                    row: 0,
+                    source: 0,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn key(&self) -> (String, String) {
+        (
+            self.parent_ids.value(self.row).to_string(),
+            self.ids.value(self.row).to_string(),
+        )
+    }
+
+    /// Moves to the next row, loading the next batch when needed.
+    /// Returns `false` once the run is exhausted.
+    fn advance(&mut self, sources: &mut Vec<RecordBatch>) -> Result<bool> {
+        self.row += 1;
+        while self.row >= self.batch.num_rows() {
+            let Some(batch) = self.reader.next() else {
+                return Ok(false);
+            };
+            let batch = batch?;
+            self.parent_ids = string_column(&batch, "parent_id")?;
+            self.ids = string_column(&batch, "id")?;
+            self.batch = batch;
+            self.row = 0;
+            self.source = sources.len();
+            sources.push(self.batch.clone());
+        }
+        Ok(true)
+    }
+}
+
+/// K-way merges sorted runs into `output`, returning the number of row groups written.
+fn merge_runs(
+    runs: &[PathBuf],
+    output: &Path,
+    settings: &Settings,
+    row_group_rows: usize,
+) -> Result<usize> {
+    let mut cursors = Vec::with_capacity(runs.len());
+    for run in runs {
+        if let Some(cursor) = RunCursor::open(run)? {
+            cursors.push(cursor);
+        }
+    }
+    let schema = match cursors.first() {
+        Some(cursor) => cursor.batch.schema(),
+        None => settings.schema.profile.schema(),
+    };
+
+    // Row groups are closed manually on parent_id boundaries, so disable the size-based split
+    let props = writer_properties_builder(settings)?
+        .set_max_row_group_size(usize::MAX)
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(output)?, schema.clone(), Some(props))?;
+
+    let mut sources: Vec<RecordBatch> = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for (idx, cursor) in cursors.iter_mut().enumerate() {
+        cursor.source = sources.len();
+        sources.push(cursor.batch.clone());
+        heap.push(Reverse((cursor.key(), idx)));
+    }
+
+    let mut chunk: Vec<(usize, usize)> = Vec::with_capacity(row_group_rows);
+    let mut last_parent: Option<String> = None;
+    let mut row_groups = 0;
+
+    while let Some(Reverse(((parent_id, _), idx))) = heap.pop() {
+        let starts_new_parent = last_parent.as_deref() != Some(parent_id.as_str());
+        if chunk.len() >= row_group_rows && starts_new_parent {
+            write_chunk(&mut writer, &schema, &sources, &chunk)?;
+            writer.flush()?;
+            row_groups += 1;
+            chunk.clear();
+            // Only the batches cursors are currently positioned in are still referenced
+            sources.clear();
+            for cursor in cursors.iter_mut() {
+                cursor.source = sources.len();
+                sources.push(cursor.batch.clone());
+            }
+        }
+
+        let cursor = &mut cursors[idx];
+        chunk.push((cursor.source, cursor.row));
+        last_parent = Some(parent_id);
+        if cursor.advance(&mut sources)? {
+            heap.push(Reverse((cursor.key(), idx)));
+        }
+    }
+
+    if !chunk.is_empty() {
+        write_chunk(&mut writer, &schema, &sources, &chunk)?;
+        row_groups += 1;
+    }
+    writer.close()?;
+    Ok(row_groups)
+}
+
+fn write_chunk(
+    writer: &mut ArrowWriter<File>,
+    schema: &SchemaRef,
+    sources: &[RecordBatch],
+    chunk: &[(usize, usize)],
+) -> Result<()> {
+    let columns = (0..schema.fields().len())
+        .map(|col| {
+            let arrays: Vec<&dyn Array> = sources.iter().map(|b| b.column(col).as_ref()).collect();
+            interleave(&arrays, chunk)
+        })
+        .collect::<std::result::Result<Vec<ArrayRef>, _>>()?;
+    writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+    Ok(())
+}
+
+fn column(batch: &RecordBatch, name: &str) -> Result<ArrayRef> {
+    batch
+        .column_by_name(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("{} column not found", name))
+}
+
+fn string_column(batch: &RecordBatch, name: &str) -> Result<StringArray> {
+    column(batch, name)?
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .cloned()
+        .ok_or_else(|| anyhow!("{} is not a StringArray", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::pipeline::batcher::Batcher;
+    use crate::pipeline::mapper::CoordinateMapper;
+    use crate::pipeline::scratch::ParsedEntry;
+    use crate::pipeline::transformer::{IsoformStatus, TransformedRow};
+    use crate::writer::parquet::write_batches;
+    use crossbeam_channel::unbounded;
+    use std::sync::Arc;
+
+    fn row(id: &str, parent_id: &str) -> TransformedRow {
+        let entry = ParsedEntry {
+            accession: parent_id.to_string(),
+            parent_id: parent_id.to_string(),
+            sequence: "MTAK".to_string(),
+            ..Default::default()
+        };
+        let mapper = CoordinateMapper::from_entry(&entry);
+        TransformedRow {
+            entry: Arc::new(entry),
+            row_id: id.to_string(),
+            parent_id: parent_id.to_string(),
+            sequence: "MTAK".to_string(),
+            isoform_status: IsoformStatus::Canonical,
+            mapper,
+            extra: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_sorts_and_aligns_row_groups_on_parent_id() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_sort");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("out.parquet");
+
+        let mut settings = Settings::default();
+        settings.storage.temp_dir = dir.join("tmp");
+        settings.performance.max_row_group_size = 2;
+
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        let mut batcher = Batcher::with_batch_size(tx, metrics.clone(), 100);
+        for (id, parent) in [("B-1", "B"), ("A-1", "A"), ("C-1", "C"), ("A-2", "A"), ("B-2", "B")] {
+            batcher.add_row(row(id, parent)).unwrap();
+        }
+        batcher.finish().unwrap();
+        write_batches(rx, &output, &metrics, &settings).unwrap();
+
+        sort_parquet_by_parent_id(&output, &settings).unwrap();
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&output).unwrap()).unwrap();
+        let row_group_sizes: Vec<i64> = builder
+            .metadata()
+            .row_groups()
+            .iter()
+            .map(|rg| rg.num_rows())
+            .collect();
+        let ids: Vec<String> = builder
+            .build()
+            .unwrap()
+            .flat_map(|b| {
+                let ids = string_column(&b.unwrap(), "id").unwrap();
+                (0..ids.len()).map(|i| ids.value(i).to_string()).collect::<Vec<_>>()
+            })
+            .collect();
+
+        assert_eq!(ids, vec!["A-1", "A-2", "B-1", "B-2", "C-1"]);
+        assert_eq!(row_group_sizes, vec![2, 2, 1]);
+        assert_eq!(fs::read_dir(&settings.storage.temp_dir).unwrap().count(), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,386 @@
+//! Variant peptide table (`storage.variant_peptides`).
+//!
+//! Reads written Parquet output back and, for every natural variant, applies the
+//! original → variation change at its mapped position to produce the reference and
+//! variant peptides around it, so immunopeptidomics searches can add variant peptides
+//! to their database without re-deriving them from the nested columns. Each variant is
+//! classified by its notation; stop-gains keep the truncated peptide, while frameshifts
+//! and descriptive variations get no variant peptide.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{
+    Array, ArrayRef, Int32Array, Int32Builder, ListArray, StringArray, StringBuilder, StructArray,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+
+use crate::config::VariantPeptidesConfig;
+use crate::pipeline::builders::DictStringBuilder;
+use crate::schema::dict_utf8;
+use crate::stats::utf8;
+
+/// What a natural variant does to the protein, from its `<original>`/`<variation>`
+/// notation and description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Consequence {
+    /// Same number of residues replaced (`R -> H`)
+    Substitution,
+    /// Residues replaced by a run of a different length
+    Indel,
+    /// Variation "Missing"
+    Deletion,
+    /// Variation with a stop (`*`), or a description naming a nonsense/stop change
+    StopGain,
+    /// Description or variation naming a frameshift
+    Frameshift,
+    /// Variation is a note rather than residues
+    Unknown,
+}
+
+impl Consequence {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Consequence::Substitution => "substitution",
+            Consequence::Indel => "indel",
+            Consequence::Deletion => "deletion",
+            Consequence::StopGain => "stop_gain",
+            Consequence::Frameshift => "frameshift",
+            Consequence::Unknown => "unknown",
+        }
+    }
+}
+
+/// Schema: id, parent_id, variant_id, start, end, original, variation, consequence,
+/// dbsnp_id, disease_association, reference_peptide, variant_peptide.
+///
+/// `reference_peptide` is null when the variant lies outside the row's sequence;
+/// `variant_peptide` is also null when the `original` residues do not match the
+/// sequence or the consequence cannot be applied (frameshift, unknown).
+pub fn variant_peptides_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("parent_id", DataType::Utf8, false),
+        Field::new("variant_id", DataType::Utf8, true),
+        Field::new("start", DataType::Int32, false),
+        Field::new("end", DataType::Int32, false),
+        Field::new("original", DataType::Utf8, true),
+        Field::new("variation", DataType::Utf8, true),
+        Field::new("consequence", dict_utf8(), false),
+        Field::new("dbsnp_id", DataType::Utf8, true),
+        Field::new("disease_association", DataType::Utf8, true),
+        Field::new("reference_peptide", DataType::Utf8, true),
+        Field::new("variant_peptide", DataType::Utf8, true),
+    ]))
+}
+
+/// Writes one row per natural variant of `outputs` into `path`, with peptides of
+/// `config.flank` residues either side of the change; returns the number of rows written.
+pub fn write_variant_peptides(outputs: &[PathBuf], path: &Path, config: &VariantPeptidesConfig) -> Result<u64> {
+    let props = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = ArrowWriter::try_new(file, variant_peptides_schema(), Some(props))?;
+
+    let mut total = 0u64;
+    for output in outputs {
+        let file = File::open(output).with_context(|| format!("Failed to open {}", output.display()))?;
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+        let roots = ["id", "parent_id", "sequence", "natural_variants"]
+            .iter()
+            .map(|name| builder.schema().index_of(name))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let projection = ProjectionMask::roots(builder.parquet_schema(), roots);
+        for batch in builder.with_projection(projection).build()? {
+            let peptides = variant_batch(&batch?, config.flank)?;
+            total += peptides.num_rows() as u64;
+            writer.write(&peptides)?;
+        }
+    }
+
+    writer.close()?;
+    Ok(total)
+}
+
+/// Classifies a variant from its notation.
+pub fn classify(original: Option<&str>, variation: Option<&str>, description: Option<&str>) -> Consequence {
+    let variation = variation.unwrap_or("").trim();
+    let description = description.unwrap_or("").to_ascii_lowercase();
+    if description.contains("frameshift") || variation.to_ascii_lowercase().contains("fs") {
+        return Consequence::Frameshift;
+    }
+    if variation.contains('*') || description.contains("nonsense") || description.contains("stop codon") {
+        return Consequence::StopGain;
+    }
+    if variation.eq_ignore_ascii_case("missing") || (variation.is_empty() && description.contains("missing")) {
+        return Consequence::Deletion;
+    }
+    let original = original.unwrap_or("").trim();
+    if !is_residues(variation) || !is_residues(original) {
+        return Consequence::Unknown;
+    }
+    if original.len() == variation.len() {
+        Consequence::Substitution
+    } else {
+        Consequence::Indel
+    }
+}
+
+/// Reference and variant peptides of a change at 1-based `start..=end` of `sequence`,
+/// with `flank` residues either side (fewer at the termini).
+///
+/// The reference peptide is None when the range is outside the sequence; the variant
+/// peptide also when `original` does not match the sequence there or the consequence
+/// has no defined product. A stop-gain peptide ends at the stop.
+pub fn peptides(
+    sequence: &str,
+    start: i32,
+    end: i32,
+    original: Option<&str>,
+    variation: Option<&str>,
+    consequence: Consequence,
+    flank: usize,
+) -> (Option<String>, Option<String>) {
+    let len = sequence.len();
+    if start < 1 || end < start || end as usize > len {
+        return (None, None);
+    }
+    let (from, to) = (start as usize - 1, end as usize);
+    let prefix = &sequence[from.saturating_sub(flank)..from];
+    let suffix = &sequence[to..(to + flank).min(len)];
+    let reference = format!("{}{}{}", prefix, &sequence[from..to], suffix);
+
+    if original.is_some_and(|o| !o.trim().is_empty() && o.trim() != &sequence[from..to]) {
+        return (Some(reference), None);
+    }
+    let variation = variation.unwrap_or("").trim();
+    let variant = match consequence {
+        Consequence::Substitution | Consequence::Indel => Some(format!("{}{}{}", prefix, variation, suffix)),
+        Consequence::Deletion => Some(format!("{}{}", prefix, suffix)),
+        Consequence::StopGain => {
+            let before_stop = variation.split('*').next().unwrap_or("");
+            is_residues(before_stop).then(|| format!("{}{}", prefix, before_stop))
+        }
+        Consequence::Frameshift | Consequence::Unknown => None,
+    };
+    (Some(reference), variant)
+}
+
+/// Residue letters only (an empty run counts, as the replacement of a stop at the site).
+fn is_residues(text: &str) -> bool {
+    text.bytes().all(|b| b.is_ascii_alphabetic() && b.is_ascii_uppercase())
+}
+
+/// Column builders of one peptide batch.
+#[derive(Default)]
+struct PeptideRows {
+    id: StringBuilder,
+    parent_id: StringBuilder,
+    variant_id: StringBuilder,
+    start: Int32Builder,
+    end: Int32Builder,
+    original: StringBuilder,
+    variation: StringBuilder,
+    consequence: DictStringBuilder,
+    dbsnp_id: StringBuilder,
+    disease_association: StringBuilder,
+    reference_peptide: StringBuilder,
+    variant_peptide: StringBuilder,
+}
+
+impl PeptideRows {
+    fn finish(mut self) -> Result<RecordBatch> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.id.finish()),
+            Arc::new(self.parent_id.finish()),
+            Arc::new(self.variant_id.finish()),
+            Arc::new(self.start.finish()),
+            Arc::new(self.end.finish()),
+            Arc::new(self.original.finish()),
+            Arc::new(self.variation.finish()),
+            Arc::new(self.consequence.finish()),
+            Arc::new(self.dbsnp_id.finish()),
+            Arc::new(self.disease_association.finish()),
+            Arc::new(self.reference_peptide.finish()),
+            Arc::new(self.variant_peptide.finish()),
+        ];
+        Ok(RecordBatch::try_new(variant_peptides_schema(), columns)?)
+    }
+}
+
+fn variant_batch(batch: &RecordBatch, flank: usize) -> Result<RecordBatch> {
+    let ids = utf8(column(batch, "id")?.as_ref())?;
+    let parent_ids = utf8(column(batch, "parent_id")?.as_ref())?;
+    let sequences = utf8(column(batch, "sequence")?.as_ref())?;
+    let variants = column(batch, "natural_variants")?;
+    let variants = variants
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .ok_or_else(|| anyhow!("natural_variants is not a List"))?;
+
+    let mut out = PeptideRows::default();
+    for row in 0..batch.num_rows() {
+        if variants.is_null(row) {
+            continue;
+        }
+        let (id, parent_id, sequence) = (ids.value(row), parent_ids.value(row), sequences.value(row));
+        let row_variants = variants.value(row);
+        let row_variants = row_variants
+            .as_any()
+            .downcast_ref::<StructArray>()
+            .ok_or_else(|| anyhow!("natural_variants is not a List<Struct>"))?;
+        let text = |name: &str| -> Result<Option<StringArray>> {
+            row_variants.column_by_name(name).map(|c| utf8(c.as_ref())).transpose()
+        };
+        let (variant_ids, descriptions) = (text("id")?, text("description")?);
+        let (originals, variations) = (text("original")?, text("variation")?);
+        // Both are null for output written before they existed
+        let (dbsnp_ids, diseases) = (text("dbsnp_id")?, text("disease_association")?);
+        let starts = int32(row_variants, "start")?;
+        let ends = int32(row_variants, "end")?;
+
+        for v in 0..row_variants.len() {
+            let at = |array: &Option<StringArray>| -> Option<String> {
+                array.as_ref().filter(|a| a.is_valid(v)).map(|a| a.value(v).to_string())
+            };
+            let (start, end) = (starts.value(v), ends.value(v));
+            let (original, variation) = (at(&originals), at(&variations));
+            let consequence = classify(original.as_deref(), variation.as_deref(), at(&descriptions).as_deref());
+            let (reference, variant) = peptides(
+                sequence,
+                start,
+                end,
+                original.as_deref(),
+                variation.as_deref(),
+                consequence,
+                flank,
+            );
+
+            out.id.append_value(id);
+            out.parent_id.append_value(parent_id);
+            out.variant_id.append_option(at(&variant_ids));
+            out.start.append_value(start);
+            out.end.append_value(end);
+            out.original.append_option(original);
+            out.variation.append_option(variation);
+            out.consequence.append_value(consequence.as_str());
+            out.dbsnp_id.append_option(at(&dbsnp_ids));
+            out.disease_association.append_option(at(&diseases));
+            out.reference_peptide.append_option(reference);
+            out.variant_peptide.append_option(variant);
+        }
+    }
+
+    out.finish()
+}
+
+fn column(batch: &RecordBatch, name: &str) -> Result<ArrayRef> {
+    batch
+        .column_by_name(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("output is missing column {}", name))
+}
+
+fn int32(array: &StructArray, name: &str) -> Result<Int32Array> {
+    array
+        .column_by_name(name)
+        .ok_or_else(|| anyhow!("struct is missing field {}", name))?
+        .as_any()
+        .downcast_ref::<Int32Array>()
+        .cloned()
+        .ok_or_else(|| anyhow!("{} is not Int32", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::metrics::Metrics;
+    use crate::pipeline::parser::parse_entries;
+    use crate::writer::parquet::write_batches;
+    use crossbeam_channel::unbounded;
+    use quick_xml::Reader;
+    use std::fs;
+
+    #[test]
+    fn classifies_and_applies_variant_notations() {
+        let seq = "MTAKWYRSLE";
+        let apply = |start, end, original: &str, variation: &str, description: &str| {
+            let consequence = classify(Some(original), Some(variation), Some(description));
+            let (_, variant) = peptides(seq, start, end, Some(original), Some(variation), consequence, 2);
+            (consequence, variant)
+        };
+
+        assert_eq!(apply(5, 5, "W", "C", "in LFS"), (Consequence::Substitution, Some("AKCYR".to_string())));
+        assert_eq!(apply(5, 6, "WY", "G", ""), (Consequence::Indel, Some("AKGRS".to_string())));
+        assert_eq!(apply(5, 5, "W", "Missing", ""), (Consequence::Deletion, Some("AKYR".to_string())));
+        assert_eq!(apply(5, 5, "W", "*", ""), (Consequence::StopGain, Some("AK".to_string())));
+        assert_eq!(apply(5, 5, "W", "C", "frameshift"), (Consequence::Frameshift, None));
+        assert_eq!(apply(5, 5, "W", "in a patient", ""), (Consequence::Unknown, None));
+        // Original does not match the sequence: reference only
+        let (reference, variant) = peptides(seq, 5, 5, Some("Q"), Some("C"), Consequence::Substitution, 2);
+        assert_eq!(reference.as_deref(), Some("AKWYR"));
+        assert!(variant.is_none());
+        assert_eq!(peptides(seq, 11, 11, None, Some("C"), Consequence::Substitution, 2), (None, None));
+    }
+
+    #[test]
+    fn writes_one_row_per_natural_variant() {
+        let dir = std::env::temp_dir().join("uniprot_etl_test_variant_peptides");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let xml = r#"<uniprot><entry>
+            <accession>P1</accession>
+            <feature type="sequence variant" id="VAR_000001" description="in LFS; dbSNP:rs28934578">
+                <original>T</original>
+                <variation>M</variation>
+                <location><position position="2"/></location>
+            </feature>
+            <feature type="sequence variant" id="VAR_000002">
+                <original>K</original>
+                <variation>Missing</variation>
+                <location><position position="4"/></location>
+            </feature>
+            <sequence length="6">MTAKWY</sequence>
+        </entry></uniprot>"#;
+        let output = dir.join("out.parquet");
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        parse_entries(Reader::from_reader(xml.as_bytes()), tx, &metrics, 1, None).unwrap();
+        write_batches(rx, &output, &metrics, &Settings::default()).unwrap();
+
+        let path = dir.join("variant_peptides.parquet");
+        let config = VariantPeptidesConfig {
+            flank: 2,
+            ..VariantPeptidesConfig::default()
+        };
+        assert_eq!(write_variant_peptides(&[output], &path, &config).unwrap(), 2);
+
+        let file = File::open(&path).unwrap();
+        let batch = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let text = |name: &str| utf8(batch.column_by_name(name).unwrap().as_ref()).unwrap();
+        assert_eq!(text("variant_id").value(0), "VAR_000001");
+        assert_eq!(text("consequence").value(0), "substitution");
+        assert_eq!(text("reference_peptide").value(0), "MTAK");
+        assert_eq!(text("variant_peptide").value(0), "MMAK");
+        assert_eq!(text("dbsnp_id").value(0), "rs28934578");
+        assert_eq!(text("disease_association").value(0), "LFS");
+        assert_eq!(text("consequence").value(1), "deletion");
+        assert_eq!(text("variant_peptide").value(1), "TAWY");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
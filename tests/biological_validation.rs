@@ -1,12 +1,12 @@
 use uniprot_etl::pipeline::mapper::{CoordinateMapper, MapFailure};
-use uniprot_etl::pipeline::scratch::{EntryScratch, FeatureScratch};
+use uniprot_etl::pipeline::scratch::{FeatureScratch, ParsedEntry};
 
 #[test]
 fn tp53_s15_canonical_control_identity_mapping() {
     // TP53 (P04637) has a known phospho-site at Ser15.
     // Canonical control: canonical mapping must be identity.
-    let scratch = EntryScratch::new();
-    let mapper = CoordinateMapper::from_entry(&scratch);
+    let entry = ParsedEntry::default();
+    let mapper = CoordinateMapper::from_entry(&entry);
 
     // XML coordinates are 1-based. Ser15 => index 14 (0-based).
     let mapped = mapper.map_point_1based(15).expect("identity map");
@@ -17,10 +17,12 @@ fn tp53_s15_canonical_control_identity_mapping() {
 fn tp53_s15_deletion_event_blocks_ptm() {
     // Synthetic VSP: delete positions 10..=20 ("Missing"). A PTM at 15 must be rejected via deletion event.
     // We don't need full XML parsing here to validate mapper semantics.
-    let mut scratch = EntryScratch::new();
-    scratch.sequence = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string();
+    let mut entry = ParsedEntry {
+        sequence: "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string(),
+        ..Default::default()
+    };
 
-    scratch.features.push(FeatureScratch {
+    entry.features.generic.push(FeatureScratch {
         id: Some("VSP_TEST".to_string()),
         feature_type: "variant sequence".to_string(),
         start: Some(10),
@@ -29,7 +31,7 @@ fn tp53_s15_deletion_event_blocks_ptm() {
         ..Default::default()
     });
 
-    let mapper = CoordinateMapper::from_entry_for_vsp_ids(&scratch, &["VSP_TEST".to_string()]);
+    let mapper = CoordinateMapper::from_entry_for_vsp_ids(&entry, &["VSP_TEST".to_string()]);
 
     let err = mapper.map_point_1based(15).unwrap_err();
     assert!(matches!(err, MapFailure::VspDeletionEvent));
@@ -3,6 +3,8 @@ use std::io::Cursor;
 use std::sync::Arc;
 
 use arrow::array::{Array, Int8Array, ListArray, StringArray, StructArray};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
 use crossbeam_channel::unbounded;
 use quick_xml::Reader;
 
@@ -104,11 +106,8 @@ fn parses_nomenclature_and_structures_from_sample() -> Result<()> {
         .downcast_ref::<StringArray>()
         .unwrap();
     assert!(protein_name.is_valid(0));
-    let organism_name = batch
-        .column(idx("organism_name"))
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .unwrap();
+    let organism_name = cast(batch.column(idx("organism_name")), &DataType::Utf8)?;
+    let organism_name = organism_name.as_any().downcast_ref::<StringArray>().unwrap();
     assert_eq!(organism_name.value(0), "Homo sapiens");
 
     // Existence mapping
@@ -128,11 +127,8 @@ fn parses_nomenclature_and_structures_from_sample() -> Result<()> {
         .unwrap();
     let struct_vals = structures.value(0);
     let struct_arr = struct_vals.as_any().downcast_ref::<StructArray>().unwrap();
-    let dbs = struct_arr
-        .column(0)
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .unwrap();
+    let dbs = cast(struct_arr.column(0), &DataType::Utf8)?;
+    let dbs = dbs.as_any().downcast_ref::<StringArray>().unwrap();
     let ids_col = struct_arr
         .column(1)
         .as_any()
@@ -1,14 +1,35 @@
 use std::io::Cursor;
 use std::sync::Arc;
 
-use arrow::array::{Array, Int32Array, ListArray, StringArray, StructArray};
+use arrow::array::{
+    Array, ArrayRef, BooleanArray, Float32Array, Float64Array, Int32Array, ListArray, StringArray, StructArray,
+};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
 use crossbeam_channel::unbounded;
 use quick_xml::Reader;
 use std::collections::HashMap;
 
 use uniprot_etl::error::Result;
 use uniprot_etl::metrics::Metrics;
-use uniprot_etl::pipeline::parser::parse_entries;
+use uniprot_etl::pipeline::dedup::AccessionDedup;
+use uniprot_etl::pipeline::feature_routing::FeatureRouting;
+use uniprot_etl::pipeline::parser::{
+    parse_entries, parse_entries_sharded, parse_entries_with_hooks, shard_of, EntrySampling, ParseHooks,
+};
+use uniprot_etl::pipeline::plddt::PlddtSource;
+use uniprot_etl::pipeline::row_filter::RowFilter;
+use uniprot_etl::pipeline::scratch::FeatureContext;
+use uniprot_etl::pipeline::transformer::InvalidEntryPolicy;
+use uniprot_etl::pipeline::window::{SiteContext, WindowPadding};
+use uniprot_etl::schema::SchemaProfile;
+use uniprot_etl::shutdown::CancelToken;
+
+/// Decodes a dictionary-encoded Utf8 column into a plain StringArray.
+fn dict_strings(array: &ArrayRef) -> Result<StringArray> {
+    let decoded = cast(array, &DataType::Utf8)?;
+    Ok(decoded.as_any().downcast_ref::<StringArray>().unwrap().clone())
+}
 
 #[test]
 fn parses_single_entry_into_record_batch() -> Result<()> {
@@ -62,6 +83,11 @@ fn parses_single_entry_into_record_batch() -> Result<()> {
     let batch = &batches[0];
     assert_eq!(batch.num_rows(), 1);
 
+    let categories = metrics.feature_category_counts();
+    assert_eq!(categories["domains"], 1);
+    assert_eq!(categories["location"], 1);
+    assert_eq!(categories["active_sites"], 0);
+
     let ids = batch
         .column(0)
         .as_any()
@@ -98,6 +124,12 @@ fn parses_single_entry_into_record_batch() -> Result<()> {
     assert!(organisms.is_valid(0));
     assert_eq!(organisms.value(0), 9606);
 
+    // Low-cardinality strings are dictionary-encoded
+    assert!(matches!(
+        schema.field_with_name("organism_name").unwrap().data_type(),
+        DataType::Dictionary(_, _)
+    ));
+
     let isoforms = batch
         .column(3)
         .as_any()
@@ -139,11 +171,7 @@ fn parses_single_entry_into_record_batch() -> Result<()> {
         .as_any()
         .downcast_ref::<StructArray>()
         .unwrap();
-    let feature_types = feature_struct
-        .column(0)
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .unwrap();
+    let feature_types = dict_strings(feature_struct.column(0))?;
     assert_eq!(feature_types.value(0), "domain");
     let feature_desc = feature_struct
         .column(1)
@@ -163,11 +191,7 @@ fn parses_single_entry_into_record_batch() -> Result<()> {
         .downcast_ref::<Int32Array>()
         .unwrap();
     assert_eq!(feature_ends.value(0), 3);
-    let feature_evidence = feature_struct
-        .column(4)
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .unwrap();
+    let feature_evidence = dict_strings(feature_struct.column(4))?;
     assert_eq!(feature_evidence.value(0), "ECO:0000255");
 
     let locations = batch
@@ -187,11 +211,7 @@ fn parses_single_entry_into_record_batch() -> Result<()> {
         .downcast_ref::<StringArray>()
         .unwrap();
     assert_eq!(location_names.value(0), "Membrane");
-    let location_evidence = location_struct
-        .column(1)
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .unwrap();
+    let location_evidence = dict_strings(location_struct.column(1))?;
     assert_eq!(location_evidence.value(0), "ECO:0000255");
 
     assert_eq!(metrics.entries(), 1);
@@ -262,11 +282,7 @@ fn captures_subunit_comment_text_into_subunits_column() -> Result<()> {
         .unwrap();
     assert_eq!(texts.value(0), "Homodimer.");
 
-    let evidence_codes = subunit_struct
-        .column(1)
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .unwrap();
+    let evidence_codes = dict_strings(subunit_struct.column(1))?;
     assert_eq!(evidence_codes.value(0), "ECO:0000269");
 
     Ok(())
@@ -345,11 +361,7 @@ fn parses_multiple_entries_and_handles_missing_evidence() -> Result<()> {
         .as_any()
         .downcast_ref::<StructArray>()
         .unwrap();
-    let evidence_col_0 = feature_struct_0
-        .column(4)
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .unwrap();
+    let evidence_col_0 = dict_strings(feature_struct_0.column(4))?;
     assert_eq!(evidence_col_0.value(0), "ECO:0000255");
 
     // Entry 1: no evidence attribute should yield null evidence_code
@@ -359,15 +371,1685 @@ fn parses_multiple_entries_and_handles_missing_evidence() -> Result<()> {
         .as_any()
         .downcast_ref::<StructArray>()
         .unwrap();
-    let evidence_col_1 = feature_struct_1
+    let evidence_col_1 = dict_strings(feature_struct_1.column(4))?;
+    assert!(evidence_col_1.is_null(0));
+
+    assert_eq!(metrics.entries(), 2);
+    assert_eq!(metrics.batches(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn unescapes_entities_in_attributes_and_text() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry>
+        <accession>Q9ESC</accession>
+        <sequence length="4">MTAK</sequence>
+        <feature type="domain" description="Kinase &amp; ATP-binding">
+            <location>
+                <begin position="1"/>
+                <end position="4"/>
+            </location>
+        </feature>
+        <comment type="subcellular location">
+            <subcellularLocation>
+                <location>Membrane &lt;outer&gt;</location>
+            </subcellularLocation>
+        </comment>
+    </entry>
+</uniprot>
+"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+
+    let metrics = Metrics::new();
+    let (tx, rx) = unbounded();
+
+    parse_entries(reader, tx, &metrics, 16, None)?;
+
+    let batches: Vec<_> = rx.iter().collect();
+    let batch = &batches[0];
+
+    let features = batch
         .column(4)
         .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap();
+    let feature_values = features.value(0);
+    let feature_struct = feature_values
+        .as_any()
+        .downcast_ref::<StructArray>()
+        .unwrap();
+    let feature_desc = feature_struct
+        .column(1)
+        .as_any()
         .downcast_ref::<StringArray>()
         .unwrap();
-    assert!(evidence_col_1.is_null(0));
+    assert_eq!(feature_desc.value(0), "Kinase & ATP-binding");
 
-    assert_eq!(metrics.entries(), 2);
-    assert_eq!(metrics.batches(), 1);
+    let locations = batch
+        .column(5)
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap();
+    let location_values = locations.value(0);
+    let location_struct = location_values
+        .as_any()
+        .downcast_ref::<StructArray>()
+        .unwrap();
+    let location_names = location_struct
+        .column(0)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!(location_names.value(0), "Membrane <outer>");
+
+    Ok(())
+}
+
+#[test]
+fn captures_fragment_and_precursor_flags() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry>
+        <accession>Q9FRG</accession>
+        <sequence length="4" fragment="single" precursor="true">MTAK</sequence>
+    </entry>
+    <entry>
+        <accession>Q9FULL</accession>
+        <sequence length="4">MTAK</sequence>
+    </entry>
+</uniprot>
+"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+
+    let metrics = Metrics::new();
+    let (tx, rx) = unbounded();
+
+    parse_entries(reader, tx, &metrics, 16, None)?;
+
+    let batches: Vec<_> = rx.iter().collect();
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), 2);
+
+    let fragments = batch
+        .column_by_name("is_fragment")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<BooleanArray>()
+        .unwrap();
+    assert!(fragments.value(0));
+    assert!(!fragments.value(1));
+
+    let precursors = batch
+        .column_by_name("is_precursor")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<BooleanArray>()
+        .unwrap();
+    assert!(precursors.value(0));
+    assert!(!precursors.value(1));
+
+    Ok(())
+}
+
+#[test]
+fn captures_entry_dataset_attribute() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry dataset="Swiss-Prot" created="1986-07-21">
+        <accession>P1</accession>
+        <sequence length="2">MA</sequence>
+    </entry>
+    <entry dataset="TrEMBL">
+        <accession>A0A1</accession>
+        <sequence length="2">MA</sequence>
+    </entry>
+    <entry>
+        <accession>X1</accession>
+        <sequence length="2">MA</sequence>
+    </entry>
+</uniprot>
+"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+
+    let metrics = Metrics::new();
+    let (tx, rx) = unbounded();
+
+    parse_entries(reader, tx, &metrics, 16, None)?;
+
+    let batches: Vec<_> = rx.iter().collect();
+    let datasets = dict_strings(batches[0].column_by_name("dataset").unwrap())?;
+    assert_eq!(datasets.value(0), "Swiss-Prot");
+    assert_eq!(datasets.value(1), "TrEMBL");
+    assert!(datasets.is_null(2));
+
+    Ok(())
+}
+
+#[test]
+fn captures_organelle_gene_locations() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry>
+        <accession>P00395</accession>
+        <geneLocation type="mitochondrion"/>
+        <sequence length="2">MF</sequence>
+    </entry>
+    <entry>
+        <accession>P0C1A1</accession>
+        <geneLocation type="plasmid">
+            <name status="known">pXO1</name>
+        </geneLocation>
+        <geneLocation type="plasmid">
+            <name status="known">pXO2</name>
+        </geneLocation>
+        <geneLocation type="chloroplast"/>
+        <sequence length="2">MA</sequence>
+    </entry>
+    <entry>
+        <accession>P04637</accession>
+        <sequence length="2">ME</sequence>
+    </entry>
+</uniprot>
+"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+
+    let metrics = Metrics::new();
+    let (tx, rx) = unbounded();
+
+    parse_entries(reader, tx, &metrics, 16, None)?;
+
+    let batches: Vec<_> = rx.iter().collect();
+    let locations = dict_strings(batches[0].column_by_name("gene_location").unwrap())?;
+    assert_eq!(locations.value(0), "mitochondrion");
+    assert_eq!(locations.value(1), "plasmid; chloroplast");
+    assert!(locations.is_null(2));
+
+    Ok(())
+}
+
+#[test]
+fn extracts_ec_numbers_from_names_and_cross_references() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry>
+        <accession>P31749</accession>
+        <protein>
+            <recommendedName>
+                <fullName>RAC-alpha serine/threonine-protein kinase</fullName>
+                <ecNumber evidence="1">2.7.11.1</ecNumber>
+            </recommendedName>
+        </protein>
+        <dbReference type="EC" id="2.7.11.1"/>
+        <dbReference type="EC" id="3.4.-.-"/>
+        <dbReference type="PDB" id="1UNQ"/>
+        <sequence length="2">MS</sequence>
+    </entry>
+    <entry>
+        <accession>P04637</accession>
+        <sequence length="2">ME</sequence>
+    </entry>
+</uniprot>
+"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+
+    let metrics = Metrics::new();
+    let (tx, rx) = unbounded();
+
+    parse_entries(reader, tx, &metrics, 16, None)?;
+
+    let batches: Vec<_> = rx.iter().collect();
+    let ec_numbers = batches[0]
+        .column_by_name("ec_numbers")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap();
+    let row = ec_numbers.value(0);
+    let row = row.as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(row.iter().flatten().collect::<Vec<_>>(), ["2.7.11.1", "3.4.-.-"]);
+    assert_eq!(ec_numbers.value(1).len(), 0);
+
+    let structures = batches[0]
+        .column_by_name("structures")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap();
+    assert_eq!(structures.value(0).len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn captures_reactome_and_kegg_pathways() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry>
+        <accession>P04637</accession>
+        <dbReference type="KEGG" id="hsa:7157"/>
+        <dbReference type="Reactome" id="R-HSA-69541">
+            <property type="pathway name" value="Stabilization of p53"/>
+        </dbReference>
+        <dbReference type="GO" id="GO:0005634">
+            <property type="term" value="C:nucleus"/>
+        </dbReference>
+        <sequence length="2">ME</sequence>
+    </entry>
+</uniprot>
+"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+
+    let metrics = Metrics::new();
+    let (tx, rx) = unbounded();
+
+    parse_entries(reader, tx, &metrics, 16, None)?;
+
+    let batches: Vec<_> = rx.iter().collect();
+    let pathways = batches[0]
+        .column_by_name("pathways")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap()
+        .value(0);
+    let pathways = pathways.as_any().downcast_ref::<StructArray>().unwrap();
+    assert_eq!(pathways.len(), 2);
+    let dbs = dict_strings(pathways.column_by_name("db").unwrap())?;
+    let ids = pathways.column_by_name("id").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+    let names = pathways.column_by_name("name").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!((dbs.value(0), ids.value(0)), ("KEGG", "hsa:7157"));
+    assert!(names.is_null(0));
+    assert_eq!((dbs.value(1), ids.value(1)), ("Reactome", "R-HSA-69541"));
+    assert_eq!(names.value(1), "Stabilization of p53");
+
+    Ok(())
+}
+
+#[test]
+fn reconciles_domain_features_with_family_cross_references() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry>
+        <accession>Q9PDZ</accession>
+        <dbReference type="InterPro" id="IPR001478">
+            <property type="entry name" value="PDZ"/>
+        </dbReference>
+        <dbReference type="Pfam" id="PF00595">
+            <property type="entry name" value="PDZ"/>
+            <property type="match status" value="2"/>
+        </dbReference>
+        <dbReference type="SMART" id="SM00220">
+            <property type="entry name" value="S_TKc"/>
+        </dbReference>
+        <feature type="domain" description="PDZ 1">
+            <location><begin position="2"/><end position="4"/></location>
+        </feature>
+        <feature type="domain" description="Protein kinase">
+            <location><begin position="5"/><end position="7"/></location>
+        </feature>
+        <sequence length="8">MKPDZPDZ</sequence>
+    </entry>
+</uniprot>
+"#;
+
+    let parse = |reconcile: bool| -> Result<Vec<arrow::record_batch::RecordBatch>> {
+        let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+        reader.config_mut().trim_text(true);
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        let hooks = ParseHooks {
+            reconcile_domain_xrefs: reconcile,
+            ..ParseHooks::default()
+        };
+        parse_entries_with_hooks(reader, tx, &metrics, 16, None, &hooks)?;
+        Ok(rx.iter().collect())
+    };
+
+    let batches = parse(true)?;
+    let xrefs = batches[0]
+        .column_by_name("domain_xrefs")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap()
+        .value(0);
+    assert_eq!(xrefs.len(), 3);
+
+    let family_accessions = |batch: &arrow::record_batch::RecordBatch| {
+        let domains = batch
+            .column_by_name("domains")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap()
+            .value(0);
+        let domains = domains.as_any().downcast_ref::<StructArray>().unwrap();
+        domains
+            .column_by_name("family_accessions")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap()
+            .clone()
+    };
+    let families = family_accessions(&batches[0]);
+    let first = families.value(0);
+    let first = first.as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(first.iter().flatten().collect::<Vec<_>>(), ["IPR001478", "PF00595"]);
+    assert_eq!(families.value(1).len(), 0);
 
+    let families = family_accessions(&parse(false)?[0]);
+    assert!(families.is_null(0) && families.is_null(1));
+
+    Ok(())
+}
+
+#[test]
+fn deduplicates_repeated_accessions() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry version="3"><accession>P1</accession><sequence length="2">MA</sequence></entry>
+    <entry version="1"><accession>P2</accession><sequence length="2">MK</sequence></entry>
+    <entry version="5"><accession>P1</accession><sequence length="3">MAA</sequence></entry>
+    <entry version="5"><accession>P1</accession><sequence length="3">MAA</sequence></entry>
+</uniprot>
+"#;
+
+    let parse = |dedup: AccessionDedup| -> Result<(Vec<String>, Vec<String>, u64)> {
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        let hooks = ParseHooks {
+            dedup,
+            ..ParseHooks::default()
+        };
+        parse_entries_with_hooks(Reader::from_reader(xml.as_bytes()), tx, &metrics, 16, None, &hooks)?;
+        let (mut ids, mut sequences) = (Vec::new(), Vec::new());
+        for batch in rx.iter() {
+            let id = batch.column_by_name("id").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+            let seq = batch.column_by_name("sequence").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+            ids.extend(id.iter().flatten().map(str::to_string));
+            sequences.extend(seq.iter().flatten().map(str::to_string));
+        }
+        Ok((ids, sequences, metrics.duplicate_entries()))
+    };
+
+    let (ids, _, duplicates) = parse(AccessionDedup::disabled())?;
+    assert_eq!(ids, ["P1", "P2", "P1", "P1"]);
+    assert_eq!(duplicates, 0);
+
+    let (ids, sequences, duplicates) = parse(AccessionDedup::first_wins())?;
+    assert_eq!(ids, ["P1", "P2"]);
+    assert_eq!(sequences, ["MA", "MK"]);
+    assert_eq!(duplicates, 2);
+
+    let newest = HashMap::from([("P1".to_string(), 5), ("P2".to_string(), 1)]);
+    let (ids, sequences, duplicates) = parse(AccessionDedup::newest_version(newest))?;
+    assert_eq!(ids, ["P2", "P1"]);
+    assert_eq!(sequences, ["MK", "MAA"]);
+    assert_eq!(duplicates, 2);
+
+    Ok(())
+}
+
+#[test]
+fn skips_or_fails_on_entries_without_accession_or_sequence() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry><accession>P1</accession><sequence length="2">MA</sequence></entry>
+    <entry><name>NOACC_HUMAN</name><sequence length="2">MK</sequence></entry>
+    <entry><accession>P3</accession><sequence length="0"></sequence></entry>
+    <entry><accession>P4</accession></entry>
+</uniprot>
+"#;
+
+    let metrics = Metrics::new();
+    let (tx, rx) = unbounded();
+    parse_entries(Reader::from_reader(xml.as_bytes()), tx, &metrics, 16, None)?;
+    let mut ids = Vec::new();
+    for batch in rx.iter() {
+        let id = batch.column_by_name("id").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        ids.extend(id.iter().flatten().map(str::to_string));
+    }
+    assert_eq!(ids, ["P1"]);
+    assert_eq!(metrics.invalid_entries(), 3);
+
+    let hooks = ParseHooks {
+        invalid_entries: InvalidEntryPolicy::Fail,
+        ..ParseHooks::default()
+    };
+    let (tx, _rx) = unbounded();
+    let err = parse_entries_with_hooks(Reader::from_reader(xml.as_bytes()), tx, &Metrics::new(), 16, None, &hooks)
+        .unwrap_err();
+    assert!(err.to_string().contains("Invalid entry '': no primary accession"), "{}", err);
+
+    Ok(())
+}
+
+#[test]
+fn captures_unhandled_comment_types_into_other_comments() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry>
+        <accession>Q9OTH</accession>
+        <sequence length="4">MTAK</sequence>
+        <comment type="caution">
+            <text evidence="E1">Could be the product of a pseudogene.</text>
+        </comment>
+        <comment type="polymorphism">
+            <text>Common variant.</text>
+        </comment>
+        <comment type="subunit">
+            <text>Homodimer.</text>
+        </comment>
+        <evidence key="E1" type="ECO:0000305"/>
+    </entry>
+</uniprot>
+"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+
+    let metrics = Metrics::new();
+    let (tx, rx) = unbounded();
+
+    parse_entries(reader, tx, &metrics, 16, None)?;
+
+    let batches: Vec<_> = rx.iter().collect();
+    let others = batches[0]
+        .column_by_name("other_comments")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap();
+    // subunit keeps its own column
+    assert_eq!(others.value_length(0), 2);
+
+    let values = others.value(0);
+    let other_struct = values.as_any().downcast_ref::<StructArray>().unwrap();
+    let types = dict_strings(other_struct.column(0))?;
+    let texts = other_struct
+        .column(1)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    let evidence = dict_strings(other_struct.column(2))?;
+
+    assert_eq!(types.value(0), "caution");
+    assert_eq!(texts.value(0), "Could be the product of a pseudogene.");
+    assert_eq!(evidence.value(0), "ECO:0000305");
+    assert_eq!(types.value(1), "polymorphism");
+    assert!(evidence.is_null(1));
+
+    Ok(())
+}
+
+#[test]
+fn captures_position_status_of_uncertain_coordinates() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry>
+        <accession>P1</accession>
+        <sequence length="8">MTAKWYRS</sequence>
+        <feature type="domain" description="Kinase">
+            <location><begin status="less than" position="1"/><end position="6"/></location>
+        </feature>
+        <feature type="chain" description="Mature">
+            <location><begin status="unknown"/><end status="unknown"/></location>
+        </feature>
+        <feature type="active site">
+            <location><position status="uncertain" position="3"/></location>
+        </feature>
+        <feature type="binding site">
+            <location><position position="4"/></location>
+        </feature>
+    </entry>
+</uniprot>
+"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+
+    let metrics = Metrics::new();
+    let (tx, rx) = unbounded();
+
+    parse_entries(reader, tx, &metrics, 16, None)?;
+
+    let batches: Vec<_> = rx.iter().collect();
+    let struct_column = |name: &str| -> StructArray {
+        let list = batches[0]
+            .column_by_name(name)
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap()
+            .value(0);
+        list.as_any().downcast_ref::<StructArray>().unwrap().clone()
+    };
+
+    let features = struct_column("features");
+    let statuses = dict_strings(features.column_by_name("position_status").unwrap())?;
+    assert_eq!(statuses.value(0), "less than..certain");
+    assert_eq!(statuses.value(1), "unknown");
+    assert!(features.column_by_name("start").unwrap().is_null(1));
+    assert_eq!(statuses.value(2), "uncertain");
+    assert!(statuses.is_null(3));
+
+    let domains = struct_column("domains");
+    let statuses = dict_strings(domains.column_by_name("position_status").unwrap())?;
+    assert_eq!(statuses.value(0), "less than..certain");
+
+    let active_sites = struct_column("active_sites");
+    let statuses = dict_strings(active_sites.column_by_name("position_status").unwrap())?;
+    assert_eq!(statuses.value(0), "uncertain");
+
+    let binding_sites = struct_column("binding_sites");
+    assert!(binding_sites.column_by_name("position_status").unwrap().is_null(0));
+
+    Ok(())
+}
+
+#[test]
+fn captures_dbsnp_ids_and_disease_associations_of_natural_variants() -> Result<()> {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uniprot>
+    <entry>
+        <accession>P1</accession>
+        <sequence length="8">MTAKWYRS</sequence>
+        <feature type="sequence variant" id="VAR_000001" description="in LFS; germline mutation; in a sporadic cancer; somatic mutation">
+            <original>T</original>
+            <variation>M</variation>
+            <location><position position="2"/></location>
+            <dbReference type="dbSNP" id="rs28934578"/>
+        </feature>
+        <feature type="sequence variant" id="VAR_000002" description="dbSNP:rs1042522">
+            <original>K</original>
+            <variation>R</variation>
+            <location><position position="4"/></location>
+        </feature>
+    </entry>
+</uniprot>
+"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+
+    let metrics = Metrics::new();
+    let (tx, rx) = unbounded();
+
+    parse_entries(reader, tx, &metrics, 16, None)?;
+
+    let batches: Vec<_> = rx.iter().collect();
+    let variants = batches[0]
+        .column_by_name("natural_variants")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap()
+        .value(0);
+    let variants = variants.as_any().downcast_ref::<StructArray>().unwrap();
+    let column = |name: &str| -> StringArray {
+        variants
+            .column_by_name(name)
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap()
+            .clone()
+    };
+
+    let dbsnp = column("dbsnp_id");
+    assert_eq!(dbsnp.value(0), "rs28934578");
+    assert_eq!(dbsnp.value(1), "rs1042522");
+
+    let diseases = column("disease_association");
+    assert_eq!(diseases.value(0), "LFS; a sporadic cancer");
+    assert!(diseases.is_null(1));
+
+    Ok(())
+}
+
+#[test]
+fn samples_a_deterministic_subset_of_entries() -> Result<()> {
+    let entries: String = (0..10)
+        .map(|i| format!("<entry><accession>P{}</accession><sequence length=\"2\">MA</sequence></entry>", i))
+        .collect();
+    let xml = format!("<uniprot>{}</uniprot>", entries);
+
+    let run = |every, max_entries| -> Result<Vec<String>> {
+        let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+        reader.config_mut().trim_text(true);
+        let hooks = ParseHooks {
+            sampling: EntrySampling { every, max_entries },
+            ..ParseHooks::default()
+        };
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        parse_entries_with_hooks(reader, tx, &metrics, 16, None, &hooks)?;
+
+        let mut ids = Vec::new();
+        for batch in rx.iter() {
+            let col = batch.column_by_name("id").unwrap();
+            let col = col.as_any().downcast_ref::<StringArray>().unwrap();
+            ids.extend(col.iter().map(|v| v.unwrap().to_string()));
+        }
+        Ok(ids)
+    };
+
+    assert_eq!(run(Some(3), None)?, ["P0", "P3", "P6", "P9"]);
+    assert_eq!(run(None, Some(2))?, ["P0", "P1"]);
+    assert_eq!(run(Some(4), Some(2))?, ["P0", "P4"]);
+    assert_eq!(run(None, None)?.len(), 10);
+
+    Ok(())
+}
+
+#[test]
+fn cancelled_parse_stops_before_the_next_entry() -> Result<()> {
+    let xml = "<uniprot><entry><accession>P1</accession><sequence length=\"2\">MA</sequence></entry></uniprot>";
+    let hooks = ParseHooks {
+        cancel: CancelToken::new(),
+        ..ParseHooks::default()
+    };
+    hooks.cancel.cancel();
+
+    let (tx, rx) = unbounded();
+    parse_entries_with_hooks(Reader::from_reader(xml.as_bytes()), tx, &Metrics::new(), 16, None, &hooks)?;
+    assert_eq!(rx.iter().count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn captures_intact_interaction_metadata() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>P04637</accession>
+        <comment type="interaction">
+            <interactant intactId="EBI-366083"/>
+            <interactant intactId="EBI-1042898">
+                <id>Q9H3D4</id>
+                <label>TP63</label>
+            </interactant>
+            <organismsDiffer>true</organismsDiffer>
+            <experiments>7</experiments>
+        </comment>
+        <sequence length="2">MA</sequence>
+    </entry></uniprot>"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+    let (tx, rx) = unbounded();
+    parse_entries(reader, tx, &Metrics::new(), 16, None)?;
+    let batch = rx.recv().unwrap();
+
+    let interactions = batch
+        .column_by_name("interactions")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap()
+        .value(0);
+    let interactions = interactions.as_any().downcast_ref::<StructArray>().unwrap();
+    assert_eq!(interactions.len(), 1);
+    let text = |name: &str| {
+        let column = interactions.column_by_name(name).unwrap();
+        column.as_any().downcast_ref::<StringArray>().unwrap().value(0).to_string()
+    };
+    assert_eq!(text("interactant_id_1"), "P04637");
+    assert_eq!(text("interactant_id_2"), "Q9H3D4");
+    assert_eq!(text("intact_id_1"), "EBI-366083");
+    assert_eq!(text("intact_id_2"), "EBI-1042898");
+    let organisms_differ = interactions.column_by_name("organisms_differ").unwrap();
+    assert!(organisms_differ.as_any().downcast_ref::<BooleanArray>().unwrap().value(0));
+    let experiments = interactions.column_by_name("experiments").unwrap();
+    assert_eq!(experiments.as_any().downcast_ref::<Int32Array>().unwrap().value(0), 7);
+
+    Ok(())
+}
+
+#[test]
+fn captures_subcellular_topology_orientation_and_molecule() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>P00533</accession>
+        <comment type="subcellular location">
+            <molecule>Isoform 1</molecule>
+            <subcellularLocation>
+                <location>Cell membrane</location>
+                <topology>Single-pass type I membrane protein</topology>
+            </subcellularLocation>
+            <subcellularLocation>
+                <location>Endosome membrane</location>
+                <orientation>Cytoplasmic side</orientation>
+            </subcellularLocation>
+        </comment>
+        <sequence length="2">MA</sequence>
+    </entry></uniprot>"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+    let (tx, rx) = unbounded();
+    parse_entries(reader, tx, &Metrics::new(), 16, None)?;
+    let batch = rx.recv().unwrap();
+
+    let locations = batch
+        .column_by_name("location")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap()
+        .value(0);
+    let locations = locations.as_any().downcast_ref::<StructArray>().unwrap();
+    assert_eq!(locations.len(), 2);
+    let topology = dict_strings(locations.column_by_name("topology").unwrap())?;
+    assert_eq!(topology.value(0), "Single-pass type I membrane protein");
+    assert!(topology.is_null(1));
+    let orientation = dict_strings(locations.column_by_name("orientation").unwrap())?;
+    assert!(orientation.is_null(0));
+    assert_eq!(orientation.value(1), "Cytoplasmic side");
+    let molecule = locations.column_by_name("molecule").unwrap();
+    let molecule = molecule.as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(molecule.value(0), "Isoform 1");
+    assert_eq!(molecule.value(1), "Isoform 1");
+
+    Ok(())
+}
+
+#[test]
+fn attaches_molecule_scoped_comments_only_to_their_isoform_row() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>Q9ISO</accession>
+        <comment type="alternative products">
+            <isoform><id>Q9ISO-1</id><name>1</name><sequence type="displayed"/></isoform>
+            <isoform><id>Q9ISO-2</id><name>2</name><name>Beta</name><sequence type="described" ref="VSP_000001"/></isoform>
+        </comment>
+        <comment type="subcellular location">
+            <subcellularLocation><location>Cytoplasm</location></subcellularLocation>
+        </comment>
+        <comment type="subcellular location">
+            <molecule>Isoform 2</molecule>
+            <subcellularLocation><location>Nucleus</location></subcellularLocation>
+        </comment>
+        <comment type="function">
+            <molecule>Isoform Beta</molecule>
+            <text>Acts as a repressor.</text>
+        </comment>
+        <comment type="subunit">
+            <molecule>Isoform 3</molecule>
+            <text>Homodimer.</text>
+        </comment>
+        <sequence length="4">MTAK</sequence>
+    </entry></uniprot>"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+    let mut sidecar = HashMap::new();
+    sidecar.insert("Q9ISO-1".to_string(), "MTAK".to_string());
+    sidecar.insert("Q9ISO-2".to_string(), "MAK".to_string());
+    let (tx, rx) = unbounded();
+    parse_entries(reader, tx, &Metrics::new(), 16, Some(Arc::new(sidecar)))?;
+    let batch = rx.recv().unwrap();
+    assert_eq!(batch.num_rows(), 2);
+
+    let list = |name: &str, row: usize| {
+        let list = batch.column_by_name(name).unwrap();
+        let list = list.as_any().downcast_ref::<ListArray>().unwrap().value(row);
+        list.as_any().downcast_ref::<StructArray>().unwrap().clone()
+    };
+    let strings = |array: &StructArray, field: &str| -> Vec<String> {
+        let column = array.column_by_name(field).unwrap();
+        let column = column.as_any().downcast_ref::<StringArray>().unwrap();
+        column.iter().map(|v| v.unwrap().to_string()).collect()
+    };
+
+    assert_eq!(strings(&list("location", 0), "location"), ["Cytoplasm"]);
+    assert_eq!(strings(&list("location", 1), "location"), ["Cytoplasm", "Nucleus"]);
+    assert_eq!(list("other_comments", 0).len(), 0);
+    assert_eq!(strings(&list("other_comments", 1), "molecule"), ["Isoform Beta"]);
+    // A molecule naming no known isoform stays on every row
+    assert_eq!(strings(&list("subunits", 0), "text"), ["Homodimer."]);
+    assert_eq!(strings(&list("subunits", 1), "text"), ["Homodimer."]);
+
+    Ok(())
+}
+
+#[test]
+fn fills_isoform_sequence_length_and_ptm_site_counts() -> Result<()> {
+    let xml = r#"<uniprot>
+        <entry>
+            <accession>Q9CNT</accession>
+            <comment type="alternative products">
+                <isoform><id>Q9CNT-1</id><name>1</name><sequence type="displayed"/></isoform>
+                <isoform><id>Q9CNT-2</id><name>2</name><sequence type="described"/></isoform>
+            </comment>
+            <feature type="modified residue" description="Phosphoserine">
+                <location><position position="2"/></location>
+            </feature>
+            <feature type="modified residue" description="N6-acetyllysine">
+                <location><position position="4"/></location>
+            </feature>
+            <sequence length="4">MSAK</sequence>
+        </entry>
+        <entry><accession>P1CNT</accession><sequence length="5">MKTAY</sequence></entry>
+    </uniprot>"#;
+    let sidecar = Arc::new(HashMap::from([
+        ("Q9CNT-1".to_string(), "MSAK".to_string()),
+        ("Q9CNT-2".to_string(), "MS".to_string()),
+    ]));
+
+    let counts = |hooks: &ParseHooks| -> Result<Vec<(i32, i32, i32)>> {
+        let (tx, rx) = unbounded();
+        let reader = Reader::from_reader(xml.as_bytes());
+        parse_entries_with_hooks(reader, tx, &Metrics::new(), 16, Some(Arc::clone(&sidecar)), hooks)?;
+        let batch = rx.recv().unwrap();
+        let column = |name: &str| {
+            let array = batch.column_by_name(name).unwrap();
+            array.as_any().downcast_ref::<Int32Array>().unwrap().clone()
+        };
+        let (isoforms, lengths, sites) = (column("isoform_count"), column("sequence_length"), column("ptm_site_count"));
+        Ok((0..batch.num_rows()).map(|i| (isoforms.value(i), lengths.value(i), sites.value(i))).collect())
+    };
+
+    // The second isoform is too short for the K4 site
+    let expected = [(2, 4, 2), (2, 2, 1), (0, 5, 0)];
+    assert_eq!(counts(&ParseHooks::default())?, expected);
+
+    // Counting sites doesn't need the ptm_sites column itself
+    let columns: Vec<String> = ["isoform_count", "sequence_length", "ptm_site_count"].map(String::from).to_vec();
+    let hooks = ParseHooks {
+        columns: Some(columns.into()),
+        ..ParseHooks::default()
+    };
+    assert_eq!(counts(&hooks)?, expected);
+
+    Ok(())
+}
+
+#[test]
+fn attaches_isoform_located_features_only_to_their_row() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>Q9SEQ</accession>
+        <comment type="alternative products">
+            <isoform><id>Q9SEQ-1</id><name>1</name><sequence type="displayed"/></isoform>
+            <isoform><id>Q9SEQ-2</id><name>2</name><sequence type="described" ref="VSP_000001"/></isoform>
+        </comment>
+        <feature type="splice variant" id="VSP_000001">
+            <original>M</original><variation>Missing</variation>
+            <location><position position="1"/></location>
+        </feature>
+        <feature type="modified residue" description="Phosphothreonine">
+            <location><position position="3"/></location>
+        </feature>
+        <feature type="modified residue" description="Phosphoserine">
+            <location sequence="Q9SEQ-2"><position position="1"/></location>
+        </feature>
+        <feature type="active site" description="Nucleophile">
+            <location sequence="Q9SEQ-2"><position position="4"/></location>
+        </feature>
+        <sequence length="5">MSTKY</sequence>
+    </entry></uniprot>"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+    let mut sidecar = HashMap::new();
+    sidecar.insert("Q9SEQ-1".to_string(), "MSTKY".to_string());
+    sidecar.insert("Q9SEQ-2".to_string(), "STKY".to_string());
+    let (tx, rx) = unbounded();
+    parse_entries(reader, tx, &Metrics::new(), 16, Some(Arc::new(sidecar)))?;
+    let batch = rx.recv().unwrap();
+    assert_eq!(batch.num_rows(), 2);
+
+    let list = |name: &str, row: usize| {
+        let list = batch.column_by_name(name).unwrap();
+        let list = list.as_any().downcast_ref::<ListArray>().unwrap().value(row);
+        list.as_any().downcast_ref::<StructArray>().unwrap().clone()
+    };
+    let ints = |array: &StructArray, field: &str| -> Vec<i32> {
+        let column = array.column_by_name(field).unwrap();
+        let column = column.as_any().downcast_ref::<Int32Array>().unwrap();
+        column.iter().map(|v| v.unwrap()).collect()
+    };
+
+    // Canonical-coordinate site is mapped through the VSP deletion; the isoform-located
+    // site keeps its own coordinate and only appears on the isoform row
+    assert_eq!(ints(&list("ptm_sites", 0), "site_index"), [3]);
+    assert_eq!(ints(&list("ptm_sites", 1), "site_index"), [1, 2]);
+    assert_eq!(list("active_sites", 0).len(), 0);
+    assert_eq!(ints(&list("active_sites", 1), "start"), [4]);
+    assert_eq!(list("features", 0).len(), 2);
+    assert_eq!(list("features", 1).len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn counts_isoform_length_mismatches_and_optionally_downgrades_their_sites() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>Q9LEN</accession>
+        <comment type="alternative products">
+            <isoform><id>Q9LEN-1</id><name>1</name><sequence type="displayed"/></isoform>
+            <isoform><id>Q9LEN-2</id><name>2</name><sequence type="described" ref="VSP_000001"/></isoform>
+        </comment>
+        <feature type="splice variant" id="VSP_000001">
+            <original>M</original><variation>Missing</variation>
+            <location><position position="1"/></location>
+        </feature>
+        <feature type="modified residue" description="Phosphothreonine" evidence="E1">
+            <location><position position="3"/></location>
+        </feature>
+        <evidence key="E1" type="ECO:0000269"/>
+        <sequence length="5">MSTKY</sequence>
+    </entry></uniprot>"#;
+    // One residue deleted, yet the sidecar isoform is as long as the canonical sequence
+    let sidecar = Arc::new(HashMap::from([
+        ("Q9LEN-1".to_string(), "MSTKY".to_string()),
+        ("Q9LEN-2".to_string(), "STKYA".to_string()),
+    ]));
+
+    let confidences = |hooks: &ParseHooks| -> Result<(u64, Vec<f32>)> {
+        let (tx, rx) = unbounded();
+        let metrics = Metrics::new();
+        let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+        reader.config_mut().trim_text(true);
+        parse_entries_with_hooks(reader, tx, &metrics, 16, Some(Arc::clone(&sidecar)), hooks)?;
+        let batch = rx.recv().unwrap();
+        let sites = batch.column_by_name("ptm_sites").unwrap().as_any().downcast_ref::<ListArray>().unwrap().clone();
+        let scores = (0..batch.num_rows())
+            .map(|row| {
+                let sites = sites.value(row);
+                let sites = sites.as_any().downcast_ref::<StructArray>().unwrap();
+                let mods = sites.column_by_name("modifications").unwrap();
+                let mods = mods.as_any().downcast_ref::<ListArray>().unwrap().value(0);
+                let mods = mods.as_any().downcast_ref::<StructArray>().unwrap();
+                let score = mods.column_by_name("confidence_score").unwrap();
+                score.as_any().downcast_ref::<Float32Array>().unwrap().value(0)
+            })
+            .collect();
+        Ok((metrics.isoform_length_mismatch(), scores))
+    };
+
+    assert_eq!(confidences(&ParseHooks::default())?, (1, vec![1.0, 1.0]));
+
+    let hooks = ParseHooks {
+        low_confidence_on_length_mismatch: true,
+        ..ParseHooks::default()
+    };
+    // Only the site mapped onto the mismatched isoform is downgraded
+    assert_eq!(confidences(&hooks)?, (1, vec![1.0, 0.1]));
+
+    Ok(())
+}
+
+#[test]
+fn keeps_isoforms_missing_from_the_sidecar_when_asked() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>Q9UNS</accession>
+        <comment type="alternative products">
+            <isoform><id>Q9UNS-1</id><name>1</name><sequence type="displayed"/></isoform>
+            <isoform><id>Q9UNS-2</id><name>2</name><sequence type="described" ref="VSP_000001"/></isoform>
+        </comment>
+        <feature type="splice variant" id="VSP_000001">
+            <original>M</original><variation>Missing</variation>
+            <location><position position="1"/></location>
+        </feature>
+        <feature type="active site" description="Nucleophile">
+            <location><position position="2"/></location>
+        </feature>
+        <feature type="modified residue" description="Phosphothreonine">
+            <location><position position="3"/></location>
+        </feature>
+        <sequence length="5">MSTKY</sequence>
+    </entry></uniprot>"#;
+    let sidecar = Arc::new(HashMap::from([("Q9UNS-1".to_string(), "MSTKY".to_string())]));
+
+    let run = |hooks: &ParseHooks| -> Result<(u64, Vec<arrow::record_batch::RecordBatch>)> {
+        let (tx, rx) = unbounded();
+        let metrics = Metrics::new();
+        let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+        reader.config_mut().trim_text(true);
+        parse_entries_with_hooks(reader, tx, &metrics, 16, Some(Arc::clone(&sidecar)), hooks)?;
+        Ok((metrics.isoform_seq_missing(), rx.try_iter().collect()))
+    };
+
+    // Dropped by default
+    let (missing, batches) = run(&ParseHooks::default())?;
+    assert_eq!(missing, 1);
+    assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 1);
+
+    let hooks = ParseHooks {
+        keep_unsequenced_isoforms: true,
+        check_invariants: true,
+        ..ParseHooks::default()
+    };
+    let (missing, batches) = run(&hooks)?;
+    assert_eq!(missing, 1);
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    let column = |name: &str| batch.column_by_name(name).unwrap();
+
+    let ids = column("id").as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!((ids.value(0), ids.value(1)), ("Q9UNS-1", "Q9UNS-2"));
+    let status = dict_strings(column("isoform_status"))?;
+    assert_eq!((status.value(0), status.value(1)), ("sequenced", "unsequenced"));
+    let sequences = column("sequence").as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(sequences.value(0), "MSTKY");
+    assert!(sequences.is_null(1));
+
+    // Nothing is placed on the unsequenced row
+    for name in ["ptm_sites", "active_sites", "features"] {
+        let list = column(name).as_any().downcast_ref::<ListArray>().unwrap();
+        assert!(list.is_valid(0) && !list.value(0).is_empty(), "{name}");
+        assert!(list.is_null(1), "{name}");
+    }
+    let counts = column("ptm_site_count").as_any().downcast_ref::<Int32Array>().unwrap();
+    assert_eq!((counts.value(0), counts.value(1)), (1, 0));
+    let lengths = column("sequence_length").as_any().downcast_ref::<Int32Array>().unwrap();
+    assert_eq!(lengths.value(0), 5);
+    assert!(lengths.is_null(1));
+
+    Ok(())
+}
+
+#[test]
+fn exposes_mapping_shift_and_failures_with_debug_mapping() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>Q9DBG</accession>
+        <comment type="alternative products">
+            <isoform><id>Q9DBG-1</id><name>1</name><sequence type="displayed"/></isoform>
+            <isoform><id>Q9DBG-2</id><name>2</name><sequence type="described" ref="VSP_000001"/></isoform>
+        </comment>
+        <feature type="splice variant" id="VSP_000001">
+            <original>MS</original><variation>Missing</variation>
+            <location><begin position="1"/><end position="2"/></location>
+        </feature>
+        <feature type="modified residue" description="Phosphoserine">
+            <location><position position="2"/></location>
+        </feature>
+        <feature type="modified residue" description="Phosphothreonine">
+            <location><position position="3"/></location>
+        </feature>
+        <feature type="active site" description="Nucleophile">
+            <location><position position="1"/></location>
+        </feature>
+        <feature type="active site" description="Proton acceptor">
+            <location><position position="4"/></location>
+        </feature>
+        <sequence length="5">MSTKY</sequence>
+    </entry></uniprot>"#;
+    let sidecar = Arc::new(HashMap::from([
+        ("Q9DBG-1".to_string(), "MSTKY".to_string()),
+        ("Q9DBG-2".to_string(), "TKY".to_string()),
+    ]));
+
+    let parse = |debug_mapping: bool| -> Result<arrow::record_batch::RecordBatch> {
+        let (tx, rx) = unbounded();
+        let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+        reader.config_mut().trim_text(true);
+        let hooks = ParseHooks {
+            debug_mapping,
+            ..ParseHooks::default()
+        };
+        parse_entries_with_hooks(reader, tx, &Metrics::new(), 16, Some(Arc::clone(&sidecar)), &hooks)?;
+        Ok(rx.recv().unwrap())
+    };
+    let items = |batch: &arrow::record_batch::RecordBatch, column: &str, row: usize| {
+        let list = batch.column_by_name(column).unwrap().as_any().downcast_ref::<ListArray>().unwrap().value(row);
+        list.as_any().downcast_ref::<StructArray>().unwrap().clone()
+    };
+    let ints = |items: &StructArray, name: &str| {
+        let array = items.column_by_name(name).unwrap();
+        let array = array.as_any().downcast_ref::<Int32Array>().unwrap();
+        (0..array.len()).map(|i| array.is_valid(i).then(|| array.value(i))).collect::<Vec<_>>()
+    };
+    let codes = |items: &StructArray| -> Result<Vec<Option<String>>> {
+        let codes = dict_strings(items.column_by_name("mapping_failure_code").unwrap())?;
+        Ok((0..codes.len()).map(|i| codes.is_valid(i).then(|| codes.value(i).to_string())).collect())
+    };
+
+    // Off: the isoform row only has what maps, and no debug fields
+    let batch = parse(false)?;
+    let sites = items(&batch, "ptm_sites", 1);
+    assert_eq!(ints(&sites, "site_index"), [Some(1)]);
+    assert_eq!(ints(&sites, "mapping_shift"), [None]);
+    assert_eq!(ints(&items(&batch, "active_sites", 1), "start"), [Some(2)]);
+
+    let batch = parse(true)?;
+    let counts = batch.column_by_name("ptm_site_count").unwrap();
+    assert_eq!(counts.as_any().downcast_ref::<Int32Array>().unwrap().values().to_vec(), [2, 1]);
+    let canonical = items(&batch, "ptm_sites", 0);
+    assert_eq!(ints(&canonical, "mapping_shift"), [Some(0), Some(0)]);
+    assert_eq!(codes(&canonical)?, [None, None]);
+
+    // The deleted S2 is kept in canonical coordinates after the mapped T3 -> 1
+    let sites = items(&batch, "ptm_sites", 1);
+    assert_eq!(ints(&sites, "site_index"), [Some(1), Some(2)]);
+    assert_eq!(ints(&sites, "mapping_shift"), [Some(-2), None]);
+    assert_eq!(codes(&sites)?, [None, Some("VSP_DELETION_EVENT".to_string())]);
+
+    let active_sites = items(&batch, "active_sites", 1);
+    assert_eq!(ints(&active_sites, "start"), [Some(1), Some(2)]);
+    assert_eq!(ints(&active_sites, "mapping_shift"), [None, Some(-2)]);
+    assert_eq!(codes(&active_sites)?, [Some("VSP_DELETION_EVENT".to_string()), None]);
+
+    Ok(())
+}
+
+#[test]
+fn maps_pdb_chain_ranges_into_structure_coverage() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>Q9SEQ</accession>
+        <comment type="alternative products">
+            <isoform><id>Q9SEQ-1</id><name>1</name><sequence type="displayed"/></isoform>
+            <isoform><id>Q9SEQ-2</id><name>2</name><sequence type="described" ref="VSP_000001"/></isoform>
+        </comment>
+        <dbReference type="PDB" id="1ABC">
+            <property type="method" value="X-ray"/>
+            <property type="resolution" value="2.50 A"/>
+            <property type="chains" value="A/B=2-4, C=1-5, D=-"/>
+        </dbReference>
+        <dbReference type="GO" id="GO:0005634">
+            <property type="term" value="C:nucleus"/>
+        </dbReference>
+        <feature type="splice variant" id="VSP_000001">
+            <original>M</original><variation>Missing</variation>
+            <location><position position="1"/></location>
+        </feature>
+        <sequence length="5">MSTKY</sequence>
+    </entry></uniprot>"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+    let mut sidecar = HashMap::new();
+    sidecar.insert("Q9SEQ-1".to_string(), "MSTKY".to_string());
+    sidecar.insert("Q9SEQ-2".to_string(), "STKY".to_string());
+    let (tx, rx) = unbounded();
+    parse_entries(reader, tx, &Metrics::new(), 16, Some(Arc::new(sidecar)))?;
+    let batch = rx.recv().unwrap();
+    assert_eq!(batch.num_rows(), 2);
+
+    let coverage = |row: usize| {
+        let list = batch.column_by_name("structure_coverage").unwrap();
+        let list = list.as_any().downcast_ref::<ListArray>().unwrap().value(row);
+        list.as_any().downcast_ref::<StructArray>().unwrap().clone()
+    };
+    let ints = |array: &StructArray, field: &str| -> Vec<i32> {
+        let column = array.column_by_name(field).unwrap();
+        let column = column.as_any().downcast_ref::<Int32Array>().unwrap();
+        column.iter().map(|v| v.unwrap()).collect()
+    };
+
+    let canonical = coverage(0);
+    assert_eq!(ints(&canonical, "start"), [2, 1]);
+    assert_eq!(ints(&canonical, "end"), [4, 5]);
+    let chains = canonical.column_by_name("chains").unwrap();
+    assert_eq!(chains.as_any().downcast_ref::<StringArray>().unwrap().value(0), "A/B");
+    assert_eq!(dict_strings(canonical.column_by_name("method").unwrap())?.value(0), "X-ray");
+
+    // The isoform lacks residue 1, so only the A/B range maps (shifted by one)
+    let isoform = coverage(1);
+    assert_eq!(ints(&isoform, "start"), [1]);
+    assert_eq!(ints(&isoform, "end"), [3]);
+
+    Ok(())
+}
+
+#[test]
+fn captures_isoform_sequence_and_event_types() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>Q9EVT</accession>
+        <comment type="alternative products">
+            <event type="alternative splicing"/>
+            <event type="alternative initiation"/>
+            <isoform><id>Q9EVT-1</id><name>1</name><sequence type="displayed"/></isoform>
+            <isoform>
+                <id>Q9EVT-2</id><name>2</name>
+                <sequence type="not described"/>
+                <note>Produced by alternative initiation at Met-40 of isoform 1.</note>
+            </isoform>
+        </comment>
+        <sequence length="4">MTAK</sequence>
+    </entry></uniprot>"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+    let mut sidecar = HashMap::new();
+    sidecar.insert("Q9EVT-1".to_string(), "MTAK".to_string());
+    let (tx, rx) = unbounded();
+    parse_entries(reader, tx, &Metrics::new(), 16, Some(Arc::new(sidecar)))?;
+    let batch = rx.recv().unwrap();
+
+    let isoforms = batch
+        .column_by_name("isoforms")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap()
+        .value(0);
+    let isoforms = isoforms.as_any().downcast_ref::<StructArray>().unwrap();
+    let sequence_type = dict_strings(isoforms.column_by_name("sequence_type").unwrap())?;
+    assert_eq!(sequence_type.value(0), "displayed");
+    assert_eq!(sequence_type.value(1), "not described");
+    let event_type = dict_strings(isoforms.column_by_name("event_type").unwrap())?;
+    assert_eq!(event_type.value(0), "alternative splicing; alternative initiation");
+    assert_eq!(event_type.value(1), "alternative initiation");
+
+    Ok(())
+}
+
+#[test]
+fn identifies_ptm_sites_against_the_vocabulary() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>P1PTM</accession>
+        <feature type="modified residue" description="Phosphoserine; by PKA">
+            <location><position position="2"/></location>
+        </feature>
+        <feature type="modified residue" description="Unlisted modification">
+            <location><position position="3"/></location>
+        </feature>
+        <sequence length="4">MSKT</sequence>
+    </entry></uniprot>"#;
+
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+    let (tx, rx) = unbounded();
+    parse_entries(reader, tx, &Metrics::new(), 16, None)?;
+    let batch = rx.recv().unwrap();
+
+    let sites = batch
+        .column_by_name("ptm_sites")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap()
+        .value(0);
+    let sites = sites.as_any().downcast_ref::<StructArray>().unwrap();
+    let mods = sites.column_by_name("modifications").unwrap();
+    let mods = mods.as_any().downcast_ref::<ListArray>().unwrap().values().clone();
+    let mods = mods.as_any().downcast_ref::<StructArray>().unwrap();
+    assert_eq!(mods.len(), 2);
+
+    let mod_type = mods.column_by_name("mod_type").unwrap();
+    let mod_type = mod_type.as_any().downcast_ref::<Int32Array>().unwrap();
+    assert_eq!(mod_type.value(0), 10116);
+    assert_eq!(mod_type.value(1), 10009);
+    let subclass = dict_strings(mods.column_by_name("mod_subclass").unwrap())?;
+    assert_eq!(subclass.value(0), "phosphorylation");
+    assert!(subclass.is_null(1));
+    let class = dict_strings(mods.column_by_name("mod_class").unwrap())?;
+    assert_eq!(class.value(1), "modified_residue");
+    let target = mods.column_by_name("target_aa").unwrap();
+    let target = target.as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(target.value(0), "S");
+    assert!(target.is_null(1));
+    let mass = mods.column_by_name("monoisotopic_mass_delta").unwrap();
+    let mass = mass.as_any().downcast_ref::<Float64Array>().unwrap();
+    assert!((mass.value(0) - 79.966331).abs() < 1e-6);
+    assert!(mass.is_null(1));
+    let average = mods.column_by_name("average_mass_delta").unwrap();
+    let average = average.as_any().downcast_ref::<Float64Array>().unwrap();
+    assert!((average.value(0) - 79.98).abs() < 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn emits_ptm_site_context_when_enabled() -> Result<()> {
+    let xml = r#"<uniprot><entry>
+        <accession>P1CTX</accession>
+        <feature type="modified residue" description="Phosphoserine">
+            <location><position position="2"/></location>
+        </feature>
+        <sequence length="5">MSKTA</sequence>
+    </entry></uniprot>"#;
+
+    let site_context = |hooks: &ParseHooks| -> Result<StringArray> {
+        let (tx, rx) = unbounded();
+        parse_entries_with_hooks(Reader::from_reader(xml.as_bytes()), tx, &Metrics::new(), 16, None, hooks)?;
+        let batch = rx.recv().unwrap();
+        let sites = batch
+            .column_by_name("ptm_sites")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap()
+            .value(0);
+        let sites = sites.as_any().downcast_ref::<StructArray>().unwrap();
+        let context = sites.column_by_name("site_context").unwrap();
+        Ok(context.as_any().downcast_ref::<StringArray>().unwrap().clone())
+    };
+
+    assert!(site_context(&ParseHooks::default())?.is_null(0));
+
+    let hooks = ParseHooks {
+        site_context: Some(SiteContext {
+            flank: 2,
+            padding: WindowPadding::Unknown,
+        }),
+        ..ParseHooks::default()
+    };
+    assert_eq!(site_context(&hooks)?.value(0), "XMSKT");
+
+    Ok(())
+}
+
+#[test]
+fn attaches_alphafold_plddt_to_canonical_rows() -> Result<()> {
+    let dir = std::env::temp_dir().join("uniprot_etl_test_alphafold");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(
+        dir.join("AF-P1PLD-F1-confidence_v4.json"),
+        r#"[{"residueNumber":[1,2,3,4,5],"confidenceScore":[90.0,80.0,70.0,60.0,50.0]}]"#,
+    )?;
+    // Model of a different length than the sequence: ignored
+    std::fs::write(dir.join("P2PLD.json"), r#"{"confidenceScore":[90.0,80.0]}"#)?;
+
+    let xml = r#"<uniprot>
+        <entry>
+            <accession>P1PLD</accession>
+            <feature type="modified residue" description="Phosphoserine">
+                <location><position position="2"/></location>
+            </feature>
+            <sequence length="5">MSKTA</sequence>
+        </entry>
+        <entry>
+            <accession>P2PLD</accession>
+            <feature type="modified residue" description="Phosphoserine">
+                <location><position position="2"/></location>
+            </feature>
+            <sequence length="5">MSKTA</sequence>
+        </entry>
+    </uniprot>"#;
+    let hooks = ParseHooks {
+        plddt: PlddtSource::open(&dir)?,
+        ..ParseHooks::default()
+    };
+    let (tx, rx) = unbounded();
+    parse_entries_with_hooks(Reader::from_reader(xml.as_bytes()), tx, &Metrics::new(), 16, None, &hooks)?;
+    let batch = rx.recv().unwrap();
+
+    let mean = batch
+        .column_by_name("alphafold_mean_plddt")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Float32Array>()
+        .unwrap();
+    assert_eq!(mean.value(0), 70.0);
+    assert!(mean.is_null(1));
+
+    let sites = batch
+        .column_by_name("ptm_sites")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap()
+        .clone();
+    let site_plddt = |row: usize| {
+        let sites = sites.value(row);
+        let sites = sites.as_any().downcast_ref::<StructArray>().unwrap();
+        sites
+            .column_by_name("plddt")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Float32Array>()
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(site_plddt(0).value(0), 80.0);
+    assert!(site_plddt(1).is_null(0));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn a_failed_build_thread_stops_the_parser() {
+    // The first entry fails its checksum; the other build threads would happily build
+    // the rest if the parser kept reading
+    let entries: String = (0..50_000)
+        .map(|i| {
+            let checksum = if i == 0 { " checksum=\"0000000000000000\"" } else { "" };
+            format!(
+                "<entry><accession>P{:05}</accession><sequence length=\"4\"{}>MSKT</sequence></entry>",
+                i, checksum
+            )
+        })
+        .collect();
+    let xml = format!("<uniprot>{}</uniprot>", entries);
+    let hooks = ParseHooks {
+        build_threads: 3,
+        strict_checksums: true,
+        ..ParseHooks::default()
+    };
+    let (tx, rx) = unbounded();
+    let err = parse_entries_with_hooks(Reader::from_reader(xml.as_bytes()), tx, &Metrics::new(), 64, None, &hooks)
+        .unwrap_err();
+    assert!(err.to_string().contains("checksum mismatch"), "{err}");
+    let rows: usize = rx.iter().map(|batch| batch.num_rows()).sum();
+    assert!(rows < 25_000, "parser kept going after the build error: {rows} rows");
+}
+
+#[test]
+fn separate_build_threads_produce_the_same_rows() -> Result<()> {
+    let entries: String = (0..40)
+        .map(|i| {
+            format!(
+                "<entry><accession>P{:05}</accession><sequence length=\"4\">MSKT</sequence></entry>",
+                i
+            )
+        })
+        .collect();
+    let xml = format!("<uniprot>{}</uniprot>", entries);
+
+    let ids = |build_threads: usize| -> Result<Vec<String>> {
+        let hooks = ParseHooks {
+            build_threads,
+            ..ParseHooks::default()
+        };
+        let (tx, rx) = unbounded();
+        parse_entries_with_hooks(Reader::from_reader(xml.as_bytes()), tx, &Metrics::new(), 8, None, &hooks)?;
+        let mut ids = Vec::new();
+        for batch in rx.iter() {
+            let column = batch.column_by_name("id").unwrap();
+            let column = column.as_any().downcast_ref::<StringArray>().unwrap();
+            ids.extend(column.iter().map(|id| id.unwrap().to_string()));
+        }
+        Ok(ids)
+    };
+
+    let inline = ids(0)?;
+    assert_eq!(inline.len(), 40);
+    assert_eq!(ids(1)?, inline);
+
+    let mut parallel = ids(3)?;
+    parallel.sort();
+    assert_eq!(parallel, inline);
+
+    Ok(())
+}
+
+#[test]
+fn sharded_parse_routes_each_accession_to_its_hash_shard() -> Result<()> {
+    let entries: String = (0..40)
+        .map(|i| {
+            format!(
+                "<entry><accession>P{:05}</accession><sequence length=\"4\">MSKT</sequence></entry>",
+                i
+            )
+        })
+        .collect();
+    let xml = format!("<uniprot>{}</uniprot>", entries);
+
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..3).map(|_| unbounded()).unzip();
+    parse_entries_sharded(
+        Reader::from_reader(xml.as_bytes()),
+        senders,
+        &Metrics::new(),
+        8,
+        None,
+        &ParseHooks::default(),
+    )?;
+
+    let mut total = 0;
+    for (shard, rx) in receivers.iter().enumerate() {
+        let mut ids = Vec::new();
+        for batch in rx.iter() {
+            let column = batch.column_by_name("parent_id").unwrap();
+            let column = column.as_any().downcast_ref::<StringArray>().unwrap();
+            ids.extend(column.iter().map(|id| id.unwrap().to_string()));
+        }
+        assert!(!ids.is_empty(), "shard {} is empty", shard);
+        assert!(ids.iter().all(|id| shard_of(id, 3) == shard));
+        // Document order is kept within a shard
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        total += ids.len();
+    }
+    assert_eq!(total, 40);
+    // FNV-1a: the layout must not change between builds
+    assert_eq!(shard_of("P04637", 16), 1);
+    assert_eq!(shard_of("P04637", 1), 0);
+
+    Ok(())
+}
+
+#[test]
+fn truncated_entry_is_an_error_with_its_element_path() {
+    let xml = r#"<uniprot><entry><accession>P1TRC</accession><feature type="chain"><location>"#;
+    let (tx, _rx) = unbounded();
+    let err = parse_entries(Reader::from_reader(xml.as_bytes()), tx, &Metrics::new(), 16, None).unwrap_err();
+    assert!(
+        err.to_string().starts_with("Unexpected end of XML inside entry/feature/location at byte"),
+        "{}",
+        err
+    );
+    assert!(err.to_string().contains("(entry P1TRC, in entry/feature/location, at byte 76)"), "{}", err);
+}
+
+#[test]
+fn routes_feature_types_through_the_configured_table() -> Result<()> {
+    let xml = r#"<uniprot>
+        <entry>
+            <accession>P1ROUTE</accession>
+            <feature type="ligand site" description="ATP">
+                <location><position position="2"/></location>
+            </feature>
+            <feature type="brand new type">
+                <location><position position="3"/></location>
+            </feature>
+            <sequence length="5">MSKTA</sequence>
+        </entry>
+    </uniprot>"#;
+    let counts = |hooks: &ParseHooks| -> Result<(usize, usize, u64)> {
+        let metrics = Metrics::new();
+        let (tx, rx) = unbounded();
+        parse_entries_with_hooks(Reader::from_reader(xml.as_bytes()), tx, &metrics, 16, None, hooks)?;
+        let batch = rx.recv().unwrap();
+        let list_len = |column: &str| {
+            let list = batch.column_by_name(column).unwrap().as_any().downcast_ref::<ListArray>().unwrap();
+            list.value(0).len()
+        };
+        Ok((list_len("binding_sites"), list_len("features"), metrics.unknown_feature_types()))
+    };
+
+    assert_eq!(counts(&ParseHooks::default())?, (0, 2, 2));
+
+    let routes = HashMap::from([("ligand site".to_string(), FeatureContext::BindingSite)]);
+    let hooks = ParseHooks {
+        feature_routing: FeatureRouting::with_overrides(&routes),
+        ..ParseHooks::default()
+    };
+    assert_eq!(counts(&hooks)?, (1, 2, 1));
+    Ok(())
+}
+
+#[test]
+fn schema_profiles_build_only_their_columns() -> Result<()> {
+    let xml = r#"<uniprot>
+        <entry dataset="Swiss-Prot">
+            <accession>P1PROF</accession>
+            <gene><name type="primary">GENE1</name></gene>
+            <organism><dbReference type="NCBI Taxonomy" id="9606"/></organism>
+            <feature type="modified residue" description="Phosphoserine">
+                <location><position position="2"/></location>
+            </feature>
+            <sequence length="5">MSKTA</sequence>
+        </entry>
+    </uniprot>"#;
+    let batch = |profile: SchemaProfile| -> Result<arrow::record_batch::RecordBatch> {
+        let hooks = ParseHooks {
+            schema_profile: profile,
+            ..ParseHooks::default()
+        };
+        let (tx, rx) = unbounded();
+        parse_entries_with_hooks(Reader::from_reader(xml.as_bytes()), tx, &Metrics::new(), 16, None, &hooks)?;
+        Ok(rx.recv().unwrap())
+    };
+
+    let minimal = batch(SchemaProfile::Minimal)?;
+    let names: Vec<_> = minimal.schema().fields().iter().map(|f| f.name().clone()).collect();
+    assert_eq!(names, ["id", "sequence", "organism_id", "gene_name", "parent_id"]);
+    assert_eq!(minimal.schema(), SchemaProfile::Minimal.schema());
+    let genes = minimal.column_by_name("gene_name").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(genes.value(0), "GENE1");
+
+    let standard = batch(SchemaProfile::Standard)?;
+    assert!(standard.column_by_name("ptm_sites").is_some());
+    assert!(standard.column_by_name("interactions").is_none());
+    assert_eq!(standard.num_rows(), 1);
+
+    assert_eq!(batch(SchemaProfile::Full)?.num_columns(), SchemaProfile::Full.schema().fields().len());
+    Ok(())
+}
+
+#[test]
+fn row_filter_keeps_only_matching_rows() -> Result<()> {
+    let xml = r#"<uniprot>
+        <entry dataset="Swiss-Prot"><accession>P1</accession>
+            <organism><dbReference type="NCBI Taxonomy" id="9606"/></organism>
+            <protein><proteinExistence type="evidence at protein level"/></protein>
+            <sequence length="3">MKV</sequence></entry>
+        <entry dataset="TrEMBL"><accession>P2</accession>
+            <organism><dbReference type="NCBI Taxonomy" id="9606"/></organism>
+            <protein><proteinExistence type="predicted"/></protein>
+            <sequence length="3">MKV</sequence></entry>
+        <entry dataset="Swiss-Prot"><accession>P3</accession>
+            <organism><dbReference type="NCBI Taxonomy" id="10090"/></organism>
+            <protein><proteinExistence type="evidence at protein level"/></protein>
+            <sequence length="3">MKV</sequence></entry>
+    </uniprot>"#;
+    let (tx, rx) = unbounded();
+    let metrics = Metrics::new();
+    let mut reader = Reader::from_reader(Cursor::new(xml.as_bytes()));
+    reader.config_mut().trim_text(true);
+    let hooks = ParseHooks {
+        row_filter: Some(RowFilter::parse("organism_id == 9606 && existence <= 2")?),
+        ..ParseHooks::default()
+    };
+    parse_entries_with_hooks(reader, tx, &metrics, 16, None, &hooks)?;
+
+    let batches: Vec<_> = rx.try_iter().collect();
+    let ids: Vec<String> = batches
+        .iter()
+        .flat_map(|batch| {
+            let ids = batch.column_by_name("id").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+            ids.iter().map(|id| id.unwrap().to_string()).collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(ids, ["P1"]);
+    assert_eq!(metrics.rows_filtered(), 2);
+    assert_eq!(metrics.rows_rejected(), 0);
     Ok(())
 }
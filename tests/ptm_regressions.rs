@@ -0,0 +1,36 @@
+//! Replays the PTM mapping fixtures in tests/fixtures/ptm_regressions (written by the
+//! `ptm_fixtures` binary from production `[PTM_FAIL]` records) and checks that every
+//! one still produces its `expected` outcome.
+
+use std::fs;
+use std::path::Path;
+
+use uniprot_etl::ptm_fixtures::{PtmFixture, FIXTURE_DIR};
+
+#[test]
+fn ptm_regression_fixtures_replay_as_expected() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURE_DIR);
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixtures in {}", dir.display());
+
+    let mut regressions = Vec::new();
+    for path in &paths {
+        let fixture: PtmFixture = serde_json::from_str(&fs::read_to_string(path).unwrap())
+            .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        let actual = fixture.replay();
+        if actual != fixture.expected {
+            regressions.push(format!(
+                "{}: expected {}, got {}",
+                path.file_name().unwrap().to_string_lossy(),
+                fixture.expected,
+                actual
+            ));
+        }
+    }
+    assert!(regressions.is_empty(), "PTM mapping regressions:\n{}", regressions.join("\n"));
+}
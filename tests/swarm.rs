@@ -0,0 +1,105 @@
+//! End-to-end coverage of swarm mode: a directory input runs the binary once per file.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+use uniprot_etl::error::Result;
+
+fn entry(accession: &str, sequence: &str) -> String {
+    format!(
+        r#"<entry>
+            <accession>{accession}</accession>
+            <organism><dbReference type="NCBI Taxonomy" id="9606"/></organism>
+            <sequence length="{}">{sequence}</sequence>
+        </entry>"#,
+        sequence.len()
+    )
+}
+
+fn uniprot(entries: &[String]) -> String {
+    format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<uniprot>\n{}\n</uniprot>\n", entries.join("\n"))
+}
+
+/// Fresh scratch directory with `input/a.xml` (2 entries) and `input/b.xml.gz` (1 entry).
+fn swarm_dir(name: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("input"))?;
+    fs::write(
+        dir.join("input/a.xml"),
+        uniprot(&[entry("P1SWA", "MSKT"), entry("P2SWA", "MKLV")]),
+    )?;
+    let mut gz = GzEncoder::new(File::create(dir.join("input/b.xml.gz"))?, Compression::default());
+    gz.write_all(uniprot(&[entry("P1SWB", "MAAG")]).as_bytes())?;
+    gz.finish()?;
+    fs::write(
+        dir.join("config.yaml"),
+        "version: \"1\"\nstorage: {}\nperformance: {}\nlogging: {}\nruns:\n  runs_dir: runs\n",
+    )?;
+    Ok(dir)
+}
+
+fn run_swarm(dir: &Path) -> Result<Output> {
+    Ok(Command::new(env!("CARGO_BIN_EXE_uniprot_etl"))
+        .current_dir(dir)
+        .args(["-c", "config.yaml", "-i", "input", "-o", "out", "--run-id", "swarm"])
+        .output()?)
+}
+
+fn parquet_rows(path: &Path) -> Result<usize> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()?;
+    let mut rows = 0;
+    for batch in reader {
+        rows += batch?.num_rows();
+    }
+    Ok(rows)
+}
+
+fn report(dir: &Path) -> Result<serde_yaml::Value> {
+    let text = fs::read_to_string(dir.join("runs/run_swarm/report.yaml"))?;
+    Ok(serde_yaml::from_str(&text).expect("report.yaml parses"))
+}
+
+#[test]
+fn swarm_writes_one_output_per_file_and_merges_metrics() -> Result<()> {
+    let dir = swarm_dir("uniprot_etl_test_swarm_ok")?;
+    let output = run_swarm(&dir)?;
+    assert!(output.status.success(), "swarm failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    assert_eq!(parquet_rows(&dir.join("out/a.parquet"))?, 2);
+    assert_eq!(parquet_rows(&dir.join("out/b.parquet"))?, 1);
+
+    let report = report(&dir)?;
+    assert_eq!(report["status"].as_str(), Some("Success"));
+    assert_eq!(report["performance"]["entries_parsed"].as_u64(), Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn swarm_reports_corrupt_files_without_losing_the_others() -> Result<()> {
+    let dir = swarm_dir("uniprot_etl_test_swarm_corrupt")?;
+    fs::write(dir.join("input/c.xml"), "<uniprot>\n<entry>\n<accession>P1SWC</accession>\n<sequence")?;
+
+    let output = run_swarm(&dir)?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 file(s) failed out of 3"), "unexpected stderr: {stderr}");
+
+    assert_eq!(parquet_rows(&dir.join("out/a.parquet"))?, 2);
+    assert_eq!(parquet_rows(&dir.join("out/b.parquet"))?, 1);
+
+    let report = report(&dir)?;
+    assert_eq!(report["status"].as_str(), Some("Error"));
+    let message = report["message"].as_str().unwrap_or_default();
+    assert!(message.contains("1 file(s) failed out of 3"), "unexpected message: {message}");
+    assert!(report["performance"]["entries_parsed"].as_u64() >= Some(3));
+
+    Ok(())
+}